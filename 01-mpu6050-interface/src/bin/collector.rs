@@ -1,79 +1,284 @@
 //! MPU6050 Data Collector
 //!
 //! Collects sensor data in polling or FIFO mode and writes to HDF5 file.
+//! Lines typed while recording (or sent to `--event-port`) become events.
+//! `--storage` sets chunking, compression and the timestamp encoding.
+//! `--trigger-level` keeps only the samples around threshold crossings,
+//! one file per capture. `--config collector.toml --profile impact` takes
+//! the settings from a file; options given on the command line override it.
 //!
 //! Usage:
 //!   collector --output data.h5 --mode fifo --rate 1000 --duration 60
+//!   collector --trigger-in c3 --mark-out c4
+//!   collector --output site.h5 --rotate hourly --rotate-size 500
+//!   collector --event-port 5555
+//!   collector --storage deflate=6,shuffle,chunk=4096,ticks
+//!   collector --mode fifo --output hits.h5 --trigger-level 1.5 --pre-trigger 0.5 --post-trigger 2
+//!   collector --config collector.toml --profile impact --duration 600
 
 use clap::Parser;
-use ft232_sensor_interface::{Hdf5Writer, Mpu6050, StreamControl, TimeKeeper, TimestampedSample};
-use std::path::PathBuf;
+use ft232_sensor_interface::gpio::parse_pin;
+use ft232_sensor_interface::session::segment_path;
+use ft232_sensor_interface::{
+    Band, Capture, CaptureEvent, CollectionMode, CollectorConfig, Edge, Event, Gpio, Hdf5Writer, Mpu6050, Outage, Profile,
+    RetryPolicy, RotationInterval, SensorData, StorageOptions, StreamControl, StreamEvent, TimeKeeper, TimestampedSample,
+    TriggerConfig, TriggerProfile, TriggerSource,
+};
+use std::io::BufRead;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often the trigger line is sampled while recording
+const TRIGGER_POLL_INTERVAL: Duration = Duration::from_millis(2);
+
+/// Rate the FIFO actually delivers, used for batch timestamps
+const FIFO_SAMPLE_RATE: f64 = 850.0;
 
 #[derive(Parser, Debug)]
 #[command(name = "collector")]
 #[command(about = "Collect MPU6050 sensor data to HDF5 file", long_about = None)]
 struct Args {
-    /// Output HDF5 file path
-    #[arg(short, long, default_value = "sensor_data.h5")]
-    output: PathBuf,
+    /// Settings file with named profiles; command-line options override it
+    #[arg(short, long)]
+    config: Option<PathBuf>,
 
-    /// Collection mode: "polling" or "fifo"
-    #[arg(short, long, default_value = "polling")]
-    mode: String,
+    /// Profile of the settings file to use, e.g. "impact" for [profile.impact]
+    #[arg(short, long)]
+    profile: Option<String>,
 
-    /// Target sample rate in Hz (polling: 1-100, fifo: 4-1000)
-    #[arg(short, long, default_value = "100")]
-    rate: u32,
+    /// FT232H channel index (default: 0)
+    #[arg(long)]
+    device: Option<u32>,
+
+    /// Output HDF5 file path (default: sensor_data.h5)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Collection mode: "polling" (default) or "fifo"
+    #[arg(short, long, value_parser = mode_arg)]
+    mode: Option<CollectionMode>,
+
+    /// Target sample rate in Hz (polling: 1-100, fifo: 4-1000; default: 100)
+    #[arg(short, long)]
+    rate: Option<u32>,
 
     /// Duration in seconds (optional, runs until Ctrl+C if omitted)
     #[arg(short, long)]
     duration: Option<u64>,
+
+    /// Retries of a failed read before the USB channel is reopened (default: 3)
+    #[arg(long)]
+    retries: Option<u32>,
+
+    /// Keep trying to reconnect a lost sensor for this many seconds (0 = stop on first disconnect; default: 60)
+    #[arg(long)]
+    reconnect_timeout: Option<u64>,
+
+    /// ACBUS pin (e.g. c3) gating the recording: start when it goes active, stop when it drops (one-shot)
+    #[arg(long, value_parser = pin_arg)]
+    trigger_in: Option<u8>,
+
+    /// The trigger line is active low
+    #[arg(long)]
+    trigger_active_low: bool,
+
+    /// ACBUS pin toggled when recording starts (held low until then)
+    #[arg(long, value_parser = pin_arg)]
+    mark_out: Option<u8>,
+
+    /// Start a new file "hourly", "daily" or after a time such as 900s, 30m, 6h
+    #[arg(long, value_parser = interval_arg)]
+    rotate: Option<RotationInterval>,
+
+    /// Start a new file once the current one reaches this many MB
+    #[arg(long)]
+    rotate_size: Option<u64>,
+
+    /// Localhost TCP port for event lines from other programs (stdin is always read)
+    #[arg(long)]
+    event_port: Option<u16>,
+
+    /// Sample column layout, e.g. "deflate=6,shuffle,chunk=4096,ticks" (default: deflate=4,chunk=1024)
+    #[arg(long, value_parser = storage_arg)]
+    storage: Option<StorageOptions>,
+
+    /// Only keep samples around crossings of this level in g, one file per capture (FIFO mode)
+    #[arg(long, allow_hyphen_values = true)]
+    trigger_level: Option<f64>,
+
+    /// Signal compared with the trigger level: "x", "y", "z" or "magnitude" (default)
+    #[arg(long, value_parser = source_arg)]
+    trigger_source: Option<TriggerSource>,
+
+    /// Crossing that fires: "rising" (default), "falling" or "both"
+    #[arg(long, value_parser = edge_arg)]
+    trigger_edge: Option<Edge>,
+
+    /// How far in g the signal must move back before the trigger re-arms (default: 10% of the level)
+    #[arg(long)]
+    trigger_hysteresis: Option<f64>,
+
+    /// Filter each axis before triggering, in Hz: "5-200", "5-" (high-pass) or "-200" (low-pass)
+    #[arg(long, value_parser = band_arg)]
+    trigger_band: Option<Band>,
+
+    /// Seconds of history written before each trigger (default: 1)
+    #[arg(long)]
+    pre_trigger: Option<f64>,
+
+    /// Seconds written after each trigger (default: 4)
+    #[arg(long)]
+    post_trigger: Option<f64>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
+fn mode_arg(text: &str) -> Result<CollectionMode, String> {
+    CollectionMode::from_name(text).ok_or_else(|| format!("'{}' is not polling or fifo", text))
+}
 
-    // Validate arguments
-    if args.mode != "polling" && args.mode != "fifo" {
-        eprintln!("Error: mode must be 'polling' or 'fifo'");
-        std::process::exit(1);
+fn interval_arg(text: &str) -> Result<RotationInterval, String> {
+    RotationInterval::parse(text).ok_or_else(|| format!("'{}' is not hourly, daily or a time like 900s, 30m, 6h", text))
+}
+
+fn storage_arg(text: &str) -> Result<StorageOptions, String> {
+    StorageOptions::parse(text).map_err(|e| e.to_string())
+}
+
+fn pin_arg(text: &str) -> Result<u8, String> {
+    parse_pin(text).ok_or_else(|| format!("'{}' is not an ACBUS pin (0-7, c0-c7 or acbus0-acbus7)", text))
+}
+
+fn source_arg(text: &str) -> Result<TriggerSource, String> {
+    TriggerSource::from_name(text).ok_or_else(|| format!("'{}' is not x, y, z or magnitude", text))
+}
+
+fn edge_arg(text: &str) -> Result<Edge, String> {
+    Edge::from_name(text).ok_or_else(|| format!("'{}' is not rising, falling or both", text))
+}
+
+fn band_arg(text: &str) -> Result<Band, String> {
+    Band::parse(text).ok_or_else(|| format!("'{}' is not a band like 5-200, 5- or -200 (Hz)", text))
+}
+
+impl Args {
+    /// The options given on the command line, as the top settings layer
+    fn overrides(&self) -> Profile {
+        let trigger = TriggerProfile {
+            level_g: self.trigger_level,
+            source: self.trigger_source,
+            edge: self.trigger_edge,
+            hysteresis_g: self.trigger_hysteresis,
+            band: self.trigger_band,
+            pre_secs: self.pre_trigger,
+            post_secs: self.post_trigger,
+        };
+        Profile {
+            device: self.device,
+            output: self.output.clone(),
+            mode: self.mode,
+            rate: self.rate,
+            duration: self.duration,
+            retries: self.retries,
+            reconnect_timeout: self.reconnect_timeout,
+            trigger_in: self.trigger_in,
+            trigger_active_low: self.trigger_active_low.then_some(true),
+            mark_out: self.mark_out,
+            rotate: self.rotate,
+            rotate_size: self.rotate_size,
+            event_port: self.event_port,
+            storage: self.storage,
+            trigger: (trigger != TriggerProfile::default()).then_some(trigger),
+        }
     }
+}
 
-    if args.mode == "polling" && args.rate > 100 {
-        eprintln!("Warning: Polling mode limited to ~100 Hz, reducing from {} Hz", args.rate);
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let config = match CollectorConfig::resolve(args.config.as_deref(), args.profile.as_deref(), &args.overrides()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // Written into every output file, so the run can be repeated with --config
+    let config_toml = config.to_toml()?;
+
+    if config.mode == CollectionMode::Polling && config.rate > 100 {
+        eprintln!("Warning: Polling mode limited to ~100 Hz, reducing from {} Hz", config.rate);
     }
 
-    if args.mode == "fifo" && (args.rate < 4 || args.rate > 1000) {
-        eprintln!("Error: FIFO mode rate must be 4-1000 Hz");
+    let trigger = config.trigger;
+    if let Some(Err(e)) = trigger.map(|trigger| trigger.validate(FIFO_SAMPLE_RATE)) {
+        eprintln!("Error: {}", e);
         std::process::exit(1);
     }
 
     println!("MPU6050 Data Collector");
     println!("======================");
-    println!("Mode: {}", args.mode);
-    println!("Target rate: {} Hz", args.rate);
-    println!("Output file: {}", args.output.display());
-    if let Some(duration) = args.duration {
+    if let Some(path) = &args.config {
+        match &args.profile {
+            Some(profile) => println!("Config: {} (profile {})", path.display(), profile),
+            None => println!("Config: {}", path.display()),
+        }
+    }
+    println!("Mode: {}", config.mode.as_str());
+    println!("Target rate: {} Hz", config.rate);
+    println!("Output file: {}", config.output.display());
+    if let Some(duration) = config.duration {
         println!("Duration: {} seconds", duration);
     } else {
         println!("Duration: continuous (Ctrl+C to stop)");
     }
+    if let Some(pin) = config.trigger_in {
+        println!("Trigger: ACBUS{} (active {})", pin, if config.trigger_active_low { "low" } else { "high" });
+    }
+    if let Some(pin) = config.mark_out {
+        println!("Marker: ACBUS{}", pin);
+    }
+    if let Some(port) = config.event_port {
+        println!("Events: stdin and 127.0.0.1:{}", port);
+    }
+    if let Some(trigger) = &trigger {
+        println!("Captures: {} ({}, ...)", trigger, segment_path(&config.output, 1).display());
+    }
     println!();
 
     // Initialize sensor
     println!("Initializing sensor...");
-    let mut sensor = Mpu6050::new(0)?;
+    let mut sensor = Mpu6050::new(config.device)?;
     println!("Sensor initialized!\n");
 
+    if let Some(trigger) = &trigger {
+        return run_triggered(&config, trigger, &config_toml, args.profile.as_deref(), &mut sensor);
+    }
+
     // Create HDF5 writer
     println!("Creating HDF5 file...");
-    let mut writer = Hdf5Writer::create(
-        &args.output,
-        &args.mode,
-        args.rate as f64,
-    )?;
+    let rotation = config.rotation();
+    let mode = config.mode.as_str();
+    let mut writer = if rotation.is_enabled() {
+        Hdf5Writer::create_session(&config.output, mode, config.rate as f64, rotation, &config.storage)?
+    } else {
+        Hdf5Writer::create_with(&config.output, mode, config.rate as f64, &config.storage)?
+    };
+    if let Some(manifest) = writer.manifest_path() {
+        println!("Rotating files, segments listed in {}", manifest.display());
+    }
+    if let Some(serial) = sensor.serial_number() {
+        writer.write_device_serial(&serial)?;
+    }
+    writer.write_metadata_str("collector_config", &config_toml)?;
+    if let Some(profile) = &args.profile {
+        writer.write_metadata_str("collector_profile", profile)?;
+    }
+    // Anything not yet flushed when we crash can be restored by `recover`
+    writer.enable_journal()?;
+    // Lets the GUI (Follow File) or another reader watch the file while we write
+    writer.start_swmr()?;
     println!("HDF5 file created!\n");
 
     // Setup Ctrl+C handler
@@ -84,32 +289,45 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         r.store(false, Ordering::SeqCst);
     })?;
 
-    // Start time tracking
-    let collection_start = std::time::Instant::now();
-    let end_time = args.duration.map(|d| collection_start + std::time::Duration::from_secs(d));
+    let mut control = RunControl::new(&config, &sensor.gpio(), running)?;
 
     println!("Starting data collection...");
-    println!("Press Ctrl+C to stop\n");
+    println!("Press Ctrl+C to stop; type a line and Enter to record an event (\"kind: label\")\n");
 
     // Run collection based on mode
-    let result = if args.mode == "fifo" {
-        collect_fifo(&mut sensor, &mut writer, running.clone(), end_time)
+    let policy = config.retry_policy();
+    let result = if config.mode == CollectionMode::Fifo {
+        collect_fifo(&mut sensor, &mut writer, &policy, &mut control)
     } else {
-        collect_polling(&mut sensor, &mut writer, args.rate, running.clone(), end_time)
+        collect_polling(&mut sensor, &mut writer, config.rate, &policy, &mut control)
     };
+    control.finish();
 
     // Handle result
     match result {
         Ok(()) => {
-            let elapsed = collection_start.elapsed().as_secs_f64();
+            let elapsed = control.elapsed_secs();
             let samples = writer.sample_count();
-            let actual_rate = samples as f64 / elapsed;
 
             println!("\nCollection complete!");
             println!("Total samples: {}", samples);
             println!("Elapsed time: {:.2} seconds", elapsed);
-            println!("Actual sample rate: {:.1} Hz", actual_rate);
-            println!("File: {}", args.output.display());
+            // Zero when --trigger-in never fired
+            if elapsed > 0.0 {
+                println!("Actual sample rate: {:.1} Hz", samples as f64 / elapsed);
+            }
+            if writer.discontinuity_count() > 0 {
+                println!("Gaps recorded: {}", writer.discontinuity_count());
+            }
+            if writer.event_count() > 0 {
+                println!("Events recorded: {}", writer.event_count());
+            }
+            let manifest = writer.manifest_path();
+            writer.close()?;
+            match manifest {
+                Some(manifest) => println!("Session: {}", manifest.display()),
+                None => println!("File: {}", config.output.display()),
+            }
         }
         Err(e) => {
             eprintln!("\nError during collection: {}", e);
@@ -117,6 +335,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             if let Err(flush_err) = writer.flush() {
                 eprintln!("Failed to flush: {}", flush_err);
             }
+            eprintln!("Run `recover --input {}` to repair the file", writer.path().display());
             return Err(e);
         }
     }
@@ -129,24 +348,33 @@ fn collect_polling(
     sensor: &mut Mpu6050,
     writer: &mut Hdf5Writer,
     rate: u32,
-    running: Arc<AtomicBool>,
-    end_time: Option<std::time::Instant>,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    if !control.start()? {
+        return Ok(());
+    }
+
     let timer = TimeKeeper::new();
     let mut sample_buffer = Vec::with_capacity(100);
     let mut last_flush = std::time::Instant::now();
 
-    sensor.stream(rate, |data| {
+    sensor.stream_resilient(rate, policy, |event| {
         // Check if we should stop
-        if !running.load(Ordering::SeqCst) {
+        if !control.keep_going() {
+            return StreamControl::Break;
+        }
+        if let Err(e) = control.record_events(writer, &timer) {
+            eprintln!("Write error: {}", e);
             return StreamControl::Break;
         }
 
-        if let Some(end) = end_time {
-            if std::time::Instant::now() >= end {
-                return StreamControl::Break;
+        let data = match event {
+            StreamEvent::Data(data) => data,
+            StreamEvent::Reconnected(outage) => {
+                return record_outage(writer, &timer, outage);
             }
-        }
+        };
 
         // Create timestamped sample
         let sample = TimestampedSample {
@@ -191,50 +419,43 @@ fn collect_polling(
 fn collect_fifo(
     sensor: &mut Mpu6050,
     writer: &mut Hdf5Writer,
-    running: Arc<AtomicBool>,
-    end_time: Option<std::time::Instant>,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Wait for the trigger first, so the FIFO does not fill up meanwhile
+    if !control.start()? {
+        return Ok(());
+    }
+
     // Enable FIFO mode
     sensor.enable_fifo(1000)?;
     println!("FIFO mode enabled");
 
     let timer = TimeKeeper::new();
     let mut last_flush = std::time::Instant::now();
-    let sample_rate = 850.0; // Actual FIFO rate
 
-    sensor.stream_fifo(20, |batch| {
+    let stats = sensor.stream_fifo_resilient(policy, |event| {
         // Check if we should stop
-        if !running.load(Ordering::SeqCst) {
+        if !control.keep_going() {
+            return StreamControl::Break;
+        }
+        if let Err(e) = control.record_events(writer, &timer) {
+            eprintln!("Write error: {}", e);
             return StreamControl::Break;
         }
 
-        if let Some(end) = end_time {
-            if std::time::Instant::now() >= end {
-                return StreamControl::Break;
+        let batch = match event {
+            StreamEvent::Data(batch) => batch,
+            StreamEvent::Reconnected(outage) => {
+                return record_outage(writer, &timer, outage);
             }
-        }
+        };
 
         if batch.is_empty() {
             return StreamControl::Continue;
         }
 
-        // Get current timestamp (end of batch)
-        let batch_end_time = timer.elapsed_secs();
-        let batch_size = batch.len();
-
-        // Interpolate timestamps for samples in batch
-        // Assume evenly spaced samples
-        let dt = 1.0 / sample_rate;
-        let timestamped_samples: Vec<TimestampedSample> = batch.iter()
-            .enumerate()
-            .map(|(i, data)| {
-                let timestamp = batch_end_time - (batch_size - 1 - i) as f64 * dt;
-                TimestampedSample {
-                    timestamp,
-                    data: *data,
-                }
-            })
-            .collect();
+        let timestamped_samples = timestamp_batch(batch, timer.elapsed_secs());
 
         // Write batch
         if let Err(e) = writer.append_batch(&timestamped_samples) {
@@ -253,6 +474,13 @@ fn collect_fifo(
         StreamControl::Continue
     })?;
 
+    println!("FIFO reads: {} (mean fill {:.0}%, max {:.0}%), interval {:.1} ms, {} overflow(s), loop busy {:.1}%",
+        stats.polls, stats.mean_fill * 100.0, stats.max_fill * 100.0,
+        stats.interval.as_secs_f64() * 1000.0, stats.overflows, stats.cpu_fraction() * 100.0);
+    if stats.overflows > 0 {
+        eprintln!("Warning: FIFO overflowed {} time(s) — some samples were lost", stats.overflows);
+    }
+
     // Disable FIFO
     sensor.disable_fifo()?;
 
@@ -261,3 +489,393 @@ fn collect_fifo(
 
     Ok(())
 }
+
+/// Interpolate timestamps for a batch read at `end_time`, assuming evenly
+/// spaced samples
+fn timestamp_batch(batch: &[SensorData], end_time: f64) -> Vec<TimestampedSample> {
+    let dt = 1.0 / FIFO_SAMPLE_RATE;
+    batch.iter()
+        .enumerate()
+        .map(|(i, data)| TimestampedSample {
+            timestamp: end_time - (batch.len() - 1 - i) as f64 * dt,
+            data: *data,
+        })
+        .collect()
+}
+
+/// Record only the captures around trigger crossings, one file each
+fn run_triggered(
+    config: &CollectorConfig,
+    trigger: &TriggerConfig,
+    config_toml: &str,
+    profile: Option<&str>,
+    sensor: &mut Mpu6050,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut captures = Captures {
+        capture: Capture::new(trigger, FIFO_SAMPLE_RATE)?,
+        output: config.output.clone(),
+        rate: config.rate as f64,
+        storage: config.storage,
+        start_time: String::new(),
+        serial: sensor.serial_number(),
+        config_toml: config_toml.to_string(),
+        profile: profile.map(str::to_string),
+        writer: None,
+        samples: 0,
+    };
+
+    // Setup Ctrl+C handler
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        println!("\nReceived Ctrl+C, stopping collection...");
+        r.store(false, Ordering::SeqCst);
+    })?;
+
+    let mut control = RunControl::new(config, &sensor.gpio(), running)?;
+
+    println!("Waiting for the trigger level...");
+    println!("Press Ctrl+C to stop; lines typed go to the next capture as events (\"kind: label\")\n");
+
+    let result = collect_triggered(sensor, &mut captures, &config.retry_policy(), &mut control);
+    control.finish();
+    // A capture cut short by the end of the run is kept
+    let closed = captures.close();
+    result?;
+    closed?;
+
+    println!("\nCollection complete!");
+    println!("Captures: {} ({} samples)", captures.capture.count(), captures.samples);
+    println!("Elapsed time: {:.2} seconds", control.elapsed_secs());
+    Ok(())
+}
+
+/// Collect in FIFO mode, passing every batch through the trigger
+fn collect_triggered(
+    sensor: &mut Mpu6050,
+    captures: &mut Captures,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !control.start()? {
+        return Ok(());
+    }
+
+    sensor.enable_fifo(1000)?;
+    println!("FIFO mode enabled, trigger armed");
+
+    let timer = TimeKeeper::new();
+    captures.start_time = chrono::Local::now().to_rfc3339();
+
+    let stats = sensor.stream_fifo_resilient(policy, |event| {
+        if !control.keep_going() {
+            return StreamControl::Break;
+        }
+        // Lines typed between captures wait for the next one
+        if let Some(writer) = &mut captures.writer {
+            if let Err(e) = control.record_events(writer, &timer) {
+                eprintln!("Write error: {}", e);
+                return StreamControl::Break;
+            }
+        }
+
+        let batch = match event {
+            StreamEvent::Data(batch) => batch,
+            StreamEvent::Reconnected(outage) => {
+                // History from before the gap must not end up in a capture
+                captures.capture.discontinuity();
+                return match &mut captures.writer {
+                    Some(writer) => record_outage(writer, &timer, outage),
+                    None => {
+                        eprintln!("Warning: sensor reconnected after {:.2} s ({} attempt(s)): {}",
+                            outage.duration().as_secs_f64(), outage.reconnects, outage.cause);
+                        StreamControl::Continue
+                    }
+                };
+            }
+        };
+
+        if batch.is_empty() {
+            return StreamControl::Continue;
+        }
+
+        if let Err(e) = captures.push(&timestamp_batch(batch, timer.elapsed_secs())) {
+            eprintln!("Write error: {}", e);
+            return StreamControl::Break;
+        }
+
+        StreamControl::Continue
+    })?;
+
+    println!("FIFO reads: {} (mean fill {:.0}%, max {:.0}%), {} overflow(s)",
+        stats.polls, stats.mean_fill * 100.0, stats.max_fill * 100.0, stats.overflows);
+    if stats.overflows > 0 {
+        eprintln!("Warning: FIFO overflowed {} time(s) — captures may miss samples", stats.overflows);
+    }
+
+    sensor.disable_fifo()?;
+
+    Ok(())
+}
+
+/// Files of a triggered run, `<output>_0001.h5` onwards, one per capture
+///
+/// All share the `start_time` of the run, so their timestamps are on one
+/// time base like the segments of a rotating session.
+struct Captures {
+    capture: Capture,
+    output: PathBuf,
+    rate: f64,
+    storage: StorageOptions,
+    /// RFC 3339 time of t = 0
+    start_time: String,
+    serial: Option<String>,
+    /// Resolved collector settings as TOML, and the profile they came from
+    config_toml: String,
+    profile: Option<String>,
+    /// File of the running capture
+    writer: Option<Hdf5Writer>,
+    /// Samples in closed captures
+    samples: usize,
+}
+
+impl Captures {
+    /// Run a batch through the trigger, opening, filling and closing files
+    fn push(&mut self, batch: &[TimestampedSample]) -> ft232_sensor_interface::Result<()> {
+        for event in self.capture.push(batch) {
+            match event {
+                CaptureEvent::Start { time, value, history } => {
+                    let path = segment_path(&self.output, self.capture.count());
+                    eprintln!("  Trigger at {:.3}s ({:.3} g), capturing to {}", time, value, path.display());
+                    let mut writer = self.create(&path, time, value)?;
+                    writer.append_batch(&history)?;
+                    self.writer = Some(writer);
+                }
+                CaptureEvent::Samples(samples) => {
+                    if let Some(writer) = &mut self.writer {
+                        writer.append_batch(&samples)?;
+                    }
+                }
+                CaptureEvent::End => self.close()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn create(&self, path: &Path, time: f64, value: f64) -> ft232_sensor_interface::Result<Hdf5Writer> {
+        let mut writer = Hdf5Writer::create_with(path, "fifo", self.rate, &self.storage)?;
+        writer.set_start_time(&self.start_time)?;
+        if let Some(serial) = &self.serial {
+            writer.write_device_serial(serial)?;
+        }
+        writer.write_metadata_str("collector_config", &self.config_toml)?;
+        if let Some(profile) = &self.profile {
+            writer.write_metadata_str("collector_profile", profile)?;
+        }
+        writer.write_metadata_str("trigger", &self.capture.config().to_string())?;
+        writer.write_metadata_f64("trigger_time", time)?;
+        writer.write_metadata_f64("trigger_value_g", value)?;
+        writer.enable_journal()?;
+        writer.start_swmr()?;
+        writer.add_event(&Event::new(time, "trigger", &format!("{:.3} g", value)))?;
+        Ok(writer)
+    }
+
+    /// Finish the running capture, if any
+    fn close(&mut self) -> ft232_sensor_interface::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let samples = writer.sample_count();
+            let path = writer.path().to_path_buf();
+            writer.close()?;
+            self.samples += samples;
+            eprintln!("  Capture complete: {} samples in {}", samples, path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Write the gap left by a reconnect into the file, and a `reconnect`
+/// event where the samples resume
+fn record_outage(writer: &mut Hdf5Writer, timer: &TimeKeeper, outage: &Outage) -> StreamControl {
+    let end = timer.elapsed_secs();
+    let start = (end - outage.duration().as_secs_f64()).max(0.0);
+    eprintln!("Warning: sensor reconnected after {:.2} s ({} attempt(s)): {}",
+        outage.duration().as_secs_f64(), outage.reconnects, outage.cause);
+
+    let written = writer.write_discontinuity(start, end, &outage.cause)
+        .and_then(|_| writer.add_event(&Event::new(end, "reconnect", &outage.cause)));
+    if let Err(e) = written {
+        eprintln!("Write error: {}", e);
+        return StreamControl::Break;
+    }
+    StreamControl::Continue
+}
+
+/// Collect event lines from stdin and, with `--event-port`, from every
+/// client of a localhost TCP listener, each with the time it arrived
+fn spawn_event_sources(port: Option<u16>) -> std::io::Result<Receiver<(Instant, String)>> {
+    let (tx, rx) = mpsc::channel();
+    if let Some(port) = port {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                std::thread::spawn(move || forward_lines(std::io::BufReader::new(stream), tx));
+            }
+        });
+    }
+    std::thread::spawn(move || forward_lines(std::io::stdin().lock(), tx));
+    Ok(rx)
+}
+
+fn forward_lines(input: impl BufRead, tx: Sender<(Instant, String)>) {
+    for line in input.lines().map_while(Result::ok) {
+        if tx.send((Instant::now(), line)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Decides when recording runs: Ctrl+C, `--duration` and the trigger/marker pins
+struct RunControl {
+    running: Arc<AtomicBool>,
+    duration: Option<Duration>,
+    started: Option<Instant>,
+    end_time: Option<Instant>,
+    trigger: Option<Trigger>,
+    mark: Option<(Gpio, u8)>,
+    events: Receiver<(Instant, String)>,
+}
+
+struct Trigger {
+    gpio: Gpio,
+    pin: u8,
+    active_high: bool,
+    last_poll: Instant,
+}
+
+impl Trigger {
+    fn is_active(&self) -> ft232_sensor_interface::Result<bool> {
+        Ok(self.gpio.read(self.pin)? == self.active_high)
+    }
+
+    /// Transition into the active level
+    fn active_edge(&self) -> Edge {
+        if self.active_high { Edge::Rising } else { Edge::Falling }
+    }
+}
+
+impl RunControl {
+    fn new(config: &CollectorConfig, gpio: &Gpio, running: Arc<AtomicBool>) -> Result<Self, Box<dyn std::error::Error>> {
+        let trigger = match config.trigger_in {
+            Some(pin) => {
+                gpio.set_input(pin)?;
+                Some(Trigger { gpio: gpio.clone(), pin, active_high: !config.trigger_active_low, last_poll: Instant::now() })
+            }
+            None => None,
+        };
+        let mark = match config.mark_out {
+            Some(pin) => {
+                gpio.set_output(pin, false)?;
+                Some((gpio.clone(), pin))
+            }
+            None => None,
+        };
+
+        Ok(RunControl {
+            running,
+            duration: config.duration.map(Duration::from_secs),
+            started: None,
+            end_time: None,
+            trigger,
+            mark,
+            events: spawn_event_sources(config.event_port)?,
+        })
+    }
+
+    /// Wait for the trigger line, then toggle the marker and start the clock
+    ///
+    /// Returns false if Ctrl+C arrived while waiting.
+    fn start(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        if let Some(trigger) = &self.trigger {
+            if !trigger.is_active()? {
+                println!("Waiting for trigger on ACBUS{}...", trigger.pin);
+                while !trigger.is_active()? {
+                    if !self.running.load(Ordering::SeqCst) {
+                        return Ok(false);
+                    }
+                    // Short waits so Ctrl+C is noticed; the level check above
+                    // catches an edge that fell between two of them
+                    trigger.gpio.wait_for_edge(trigger.pin, trigger.active_edge(), Some(Duration::from_millis(100)))?;
+                }
+            }
+            println!("Triggered, recording");
+        }
+
+        if let Some((gpio, pin)) = &self.mark {
+            gpio.toggle(*pin)?;
+        }
+
+        let now = Instant::now();
+        self.started = Some(now);
+        self.end_time = self.duration.map(|d| now + d);
+        Ok(true)
+    }
+
+    /// False once Ctrl+C, the duration or a released trigger ends the run
+    ///
+    /// The trigger is one-shot: once released it is not re-armed, the run
+    /// ends and a new one has to be started for the next active period.
+    fn keep_going(&mut self) -> bool {
+        if !self.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        if self.end_time.is_some_and(|end| Instant::now() >= end) {
+            return false;
+        }
+
+        if let Some(trigger) = &mut self.trigger {
+            if trigger.last_poll.elapsed() >= TRIGGER_POLL_INTERVAL {
+                trigger.last_poll = Instant::now();
+                match trigger.is_active() {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        println!("\nTrigger released, stopping collection...");
+                        return false;
+                    }
+                    // A lost adapter is handled by the reconnect logic
+                    Err(e) => eprintln!("Trigger read error: {}", e),
+                }
+            }
+        }
+        true
+    }
+
+    /// Store the event lines received since the last call
+    ///
+    /// Lines are timed when they arrive; anything sent before the trigger
+    /// fired is put at t = 0.
+    fn record_events(&mut self, writer: &mut Hdf5Writer, timer: &TimeKeeper) -> ft232_sensor_interface::Result<()> {
+        while let Ok((received, line)) = self.events.try_recv() {
+            let time = (timer.elapsed_secs() - received.elapsed().as_secs_f64()).max(0.0);
+            if let Some(event) = Event::parse(&line, time) {
+                eprintln!("  Event at {:.2}s [{}] {}", event.time, event.kind, event.label);
+                writer.add_event(&event)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the marker back low
+    fn finish(&self) {
+        if let Some((gpio, pin)) = &self.mark {
+            let _ = gpio.write(*pin, false);
+        }
+    }
+
+    /// Seconds since recording started (0 if it never did)
+    fn elapsed_secs(&self) -> f64 {
+        self.started.map_or(0.0, |t| t.elapsed().as_secs_f64())
+    }
+}
@@ -0,0 +1,86 @@
+//! Repair an HDF5 recording after a crash or power loss
+//!
+//! Usage:
+//!   recover --input data.h5
+//!   recover --input data.h5 --check
+
+use clap::Parser;
+use ft232_sensor_interface::journal::{journal_path, JournalContents};
+use ft232_sensor_interface::{recover, Completion, Hdf5Reader};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(name = "recover")]
+#[command(about = "Repair an HDF5 file left behind by an interrupted collector", long_about = None)]
+struct Args {
+    /// HDF5 file written by the collector
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Only report the state of the file and its journal
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if args.check {
+        check(&args.input);
+        return Ok(());
+    }
+
+    let report = recover(&args.input)?;
+    if report.already_complete {
+        println!("{} was closed cleanly ({} samples), nothing to recover",
+            args.input.display(), report.sample_count);
+        return Ok(());
+    }
+    if report.rebuilt {
+        println!("File was unreadable: rebuilt from the journal (original kept as .damaged)");
+    }
+    if report.journal_found {
+        println!("Replayed {} samples and {} gaps from the journal",
+            report.journal_samples, report.journal_discontinuities);
+    }
+    if report.torn_bytes > 0 {
+        println!("Dropped {} bytes of an incomplete journal record", report.torn_bytes);
+    }
+    if report.lost_samples > 0 {
+        println!("Lost {} samples that were written before the journal", report.lost_samples);
+    }
+    println!("Recovered file holds {} samples", report.sample_count);
+
+    Ok(())
+}
+
+fn check(path: &Path) {
+    match Hdf5Reader::open(path) {
+        Ok(reader) => {
+            let samples = reader.get_total_samples().unwrap_or(0);
+            let state = match reader.completion() {
+                Completion::Complete => "closed cleanly",
+                Completion::Recovered => "recovered",
+                Completion::Unknown => "not closed (still recording, interrupted or older format)",
+            };
+            println!("{}: {} samples, {}", path.display(), samples, state);
+        }
+        Err(e) => println!("{}: cannot be opened ({})", path.display(), e),
+    }
+
+    let journal = journal_path(path);
+    if !journal.exists() {
+        println!("No journal");
+        return;
+    }
+    match JournalContents::read(&journal) {
+        Ok(contents) => println!(
+            "Journal: {} samples and {} gaps after sample {}{}",
+            contents.samples.len(),
+            contents.discontinuities.len(),
+            contents.header.sample_base,
+            if contents.torn_bytes > 0 { ", torn record at the end" } else { "" }
+        ),
+        Err(e) => println!("Journal: unreadable ({})", e),
+    }
+}
@@ -7,7 +7,10 @@ use eframe::egui;
 use egui_plot::{Line, Plot, PlotPoints};
 use ft232_sensor_interface::{Hdf5Writer, TimestampedSample};
 use std::sync::mpsc::TryRecvError;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+
+/// How often a followed file is checked for new samples
+const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Main application struct
 pub struct SensorGuiApp {
@@ -59,8 +62,63 @@ impl SensorGuiApp {
         }
     }
 
+    /// Append samples a collector has written to the followed file since the last poll
+    fn poll_followed_file(&mut self) {
+        let Some(follower) = &mut self.state.live.follower else {
+            return;
+        };
+        if follower.last_poll.elapsed() < FOLLOW_POLL_INTERVAL {
+            return;
+        }
+
+        match data::poll_follower(follower) {
+            Ok(samples) => {
+                for sample in samples {
+                    self.state.live.buffer.push(sample);
+                }
+            }
+            Err(e) => {
+                self.stop_following();
+                self.state.ui.error = Some(e);
+            }
+        }
+    }
+
+    /// Follow a file that is being recorded by another process
+    fn follow_file(&mut self, path: &std::path::Path) {
+        if self.state.mode == AppMode::Live {
+            self.disconnect_sensor();
+        }
+        self.stop_following();
+        self.state.ui.error = None;
+
+        // Replay as much history as the live buffer holds
+        match data::follow_file(path, 10000) {
+            Ok(follower) => {
+                self.state.live.sample_rate = follower.reader.metadata().sample_rate_hz;
+                self.state.live.follower = Some(follower);
+                self.state.live.buffer.clear();
+                self.state.mode = AppMode::Follow;
+                self.state.ui.status = format!("Following {}", path.display());
+                self.state.ui.active_tab = Tab::Live;
+            }
+            Err(e) => {
+                self.state.ui.error = Some(format!("Failed to follow file: {}", e));
+            }
+        }
+    }
+
+    /// Stop following a file
+    fn stop_following(&mut self) {
+        if self.state.live.follower.take().is_some() {
+            self.state.mode = AppMode::Idle;
+            self.state.ui.status = "Stopped following".to_string();
+        }
+    }
+
     /// Connect to sensor
     fn connect_sensor(&mut self) {
+        self.stop_following();
         self.state.ui.error = None;
         self.state.ui.status = "Connecting...".to_string();
 
@@ -129,8 +187,10 @@ impl SensorGuiApp {
             return;
         }
 
-        if let Some(mut writer) = self.state.live.hdf5_writer.take() {
-            let _ = writer.flush();
+        if let Some(writer) = self.state.live.hdf5_writer.take() {
+            if let Err(e) = writer.close() {
+                self.state.ui.error = Some(format!("Failed to close recording: {}", e));
+            }
         }
 
         self.state.live.is_recording = false;
@@ -160,6 +220,11 @@ impl SensorGuiApp {
                         self.disconnect_sensor();
                     }
                     ui.label("🟢 Connected");
+                } else if self.state.mode == AppMode::Follow {
+                    if ui.button("⏹ Stop Following").clicked() {
+                        self.stop_following();
+                    }
+                    ui.label("🟠 Following file");
                 } else {
                     if ui.button("▶ Connect Sensor").clicked() {
                         self.connect_sensor();
@@ -177,6 +242,18 @@ impl SensorGuiApp {
                         self.load_file(&path);
                     }
                 }
+                if ui.button("📡 Follow File")
+                    .on_hover_text("Watch a file while the collector is still recording it")
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new()
+                        .add_filter("HDF5 Files", &["h5", "hdf5"])
+                        .add_filter("Session Manifests", &["manifest"])
+                        .pick_file()
+                    {
+                        self.follow_file(&path);
+                    }
+                }
 
                 ui.separator();
 
@@ -227,6 +304,7 @@ impl SensorGuiApp {
                 match self.state.mode {
                     AppMode::Idle => ui.label("⚪ Idle"),
                     AppMode::Live => ui.label("🟢 Live Streaming"),
+                    AppMode::Follow => ui.label("🟠 Following File"),
                     AppMode::Playback => ui.label("📁 File Playback"),
                 };
                 ui.separator();
@@ -234,7 +312,11 @@ impl SensorGuiApp {
                 // Metadata
                 ui.heading("Info");
                 match self.state.mode {
-                    AppMode::Live => {
+                    AppMode::Live | AppMode::Follow => {
+                        if let Some(follower) = &self.state.live.follower {
+                            let name = follower.path.file_name().unwrap_or_default();
+                            ui.label(format!("File: {}", name.to_string_lossy()));
+                        }
                         ui.label(format!("Rate: ~{:.0} Hz", self.state.live.sample_rate));
                         ui.label(format!("Buffer: {} samples", self.state.live.buffer.len()));
                         if let Some(sample) = self.state.live.buffer.latest() {
@@ -245,7 +327,7 @@ impl SensorGuiApp {
                         if let Some(data) = &self.state.file_data {
                             ui.label(format!("Mode: {}", data.metadata.acquisition_mode));
                             ui.label(format!("Rate: {:.0} Hz", data.metadata.sample_rate_hz));
-                            ui.label(format!("Samples: {}", data.samples.len()));
+                            ui.label(format!("Samples: {}", data.sample_count));
                             ui.label(format!(
                                 "Duration: {:.1}s",
                                 data.time_range.1 - data.time_range.0
@@ -275,7 +357,7 @@ impl SensorGuiApp {
                 ui.separator();
 
                 // Live controls
-                if self.state.mode == AppMode::Live {
+                if self.state.mode.is_streaming() {
                     ui.heading("Live View");
                     ui.horizontal(|ui| {
                         ui.label("Window:");
@@ -314,7 +396,7 @@ impl SensorGuiApp {
                     });
 
                     // Live FFT settings
-                    if self.state.mode == AppMode::Live {
+                    if self.state.mode.is_streaming() {
                         ui.separator();
                         ui.label("Live FFT");
                         ui.horizontal(|ui| {
@@ -406,11 +488,12 @@ impl SensorGuiApp {
 
     /// Render live view with scrolling plots and current values
     fn render_live_view(&mut self, ui: &mut egui::Ui) {
-        if self.state.mode != AppMode::Live {
+        if !self.state.mode.is_streaming() {
             ui.centered_and_justified(|ui| {
                 ui.vertical_centered(|ui| {
                     ui.heading("No Sensor Connected");
-                    ui.label("Click 'Connect Sensor' to start live streaming.");
+                    ui.label("Click 'Connect Sensor' to start live streaming,");
+                    ui.label("or 'Follow File' to watch a running collector.");
                 });
             });
             return;
@@ -522,7 +605,7 @@ impl SensorGuiApp {
     fn render_time_series(&self, ui: &mut egui::Ui) {
         let display = match self.state.mode {
             AppMode::Playback => self.state.display_data.as_ref(),
-            AppMode::Live => {
+            AppMode::Live | AppMode::Follow => {
                 // Show live buffer as time series when paused
                 return self.render_live_buffer_as_timeseries(ui);
             }
@@ -745,7 +828,7 @@ impl SensorGuiApp {
             ));
 
             // Show live update status
-            if self.state.mode == AppMode::Live {
+            if self.state.mode.is_streaming() {
                 ui.separator();
                 if let Some(last_update) = self.state.ui.fft_last_update {
                     let elapsed = last_update.elapsed().as_secs_f64();
@@ -837,6 +920,7 @@ impl SensorGuiApp {
         if self.state.mode == AppMode::Live {
             self.disconnect_sensor();
         }
+        self.stop_following();
 
         self.state.ui.status = format!("Loading {}...", path.display());
         self.state.ui.error = None;
@@ -861,8 +945,10 @@ impl SensorGuiApp {
     /// Update display data after time range change
     fn update_display_data(&mut self) {
         if let Some(file_data) = &self.state.file_data {
-            self.state.display_data =
-                Some(data::downsample(&file_data.samples, self.state.ui.time_range));
+            match data::overview(file_data, self.state.ui.time_range) {
+                Ok(display) => self.state.display_data = Some(display),
+                Err(e) => self.state.ui.error = Some(e),
+            }
             self.state.fft_results = None;
         }
     }
@@ -870,22 +956,17 @@ impl SensorGuiApp {
     /// Recompute FFT
     fn recompute_fft(&mut self) {
         let samples: Vec<TimestampedSample> = match self.state.mode {
-            AppMode::Playback => {
-                if let Some(file_data) = &self.state.file_data {
-                    file_data
-                        .samples
-                        .iter()
-                        .filter(|s| {
-                            s.timestamp >= self.state.ui.time_range.0
-                                && s.timestamp <= self.state.ui.time_range.1
-                        })
-                        .cloned()
-                        .collect()
-                } else {
-                    return;
-                }
-            }
-            AppMode::Live => {
+            AppMode::Playback => match &self.state.file_data {
+                Some(file_data) => match data::read_window(file_data, self.state.ui.time_range) {
+                    Ok(samples) => samples,
+                    Err(e) => {
+                        self.state.ui.error = Some(e);
+                        return;
+                    }
+                },
+                None => return,
+            },
+            AppMode::Live | AppMode::Follow => {
                 // Use the FFT time window for live mode
                 self.state.live.buffer
                     .get_window(self.state.ui.fft_time_window)
@@ -903,7 +984,7 @@ impl SensorGuiApp {
                 .as_ref()
                 .map(|d| d.metadata.sample_rate_hz)
                 .unwrap_or(850.0),
-            AppMode::Live => self.state.live.sample_rate,
+            AppMode::Live | AppMode::Follow => self.state.live.sample_rate,
             AppMode::Idle => 850.0,
         };
 
@@ -914,7 +995,7 @@ impl SensorGuiApp {
 
     /// Check if FFT needs updating (for live mode)
     fn maybe_update_live_fft(&mut self) {
-        if self.state.mode != AppMode::Live {
+        if !self.state.mode.is_streaming() {
             return;
         }
         if self.state.ui.active_tab != Tab::FftAnalysis {
@@ -936,12 +1017,13 @@ impl eframe::App for SensorGuiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         // Poll for sensor data
         self.poll_sensor_data();
+        self.poll_followed_file();
 
         // Check if live FFT needs updating
         self.maybe_update_live_fft();
 
         // Request repaint for live updates
-        if self.state.mode == AppMode::Live && !self.state.live.paused {
+        if self.state.mode.is_streaming() && !self.state.live.paused {
             ctx.request_repaint();
         }
 
@@ -1,149 +1,102 @@
 //! Data loading, downsampling, and FFT computation
 
-use crate::state::{DisplayData, FftResults, LoadedData};
-use ft232_sensor_interface::{Hdf5Reader, TimestampedSample};
+use crate::state::{DisplayData, FftResults, FileFollower, LoadedData};
+use ft232_sensor_interface::overview::CHANNELS;
+use ft232_sensor_interface::{schema, Hdf5Reader, TimestampedSample};
 use num_complex::Complex;
 use rustfft::FftPlanner;
 use std::f64::consts::PI;
 use std::path::Path;
+use std::time::Instant;
 
 /// Maximum points to display (for performance)
 const MAX_DISPLAY_POINTS: usize = 4000;
 
-/// Load data from HDF5 file
+/// Open an HDF5 file for playback
 pub fn load_file(path: &Path) -> Result<LoadedData, String> {
     let reader = Hdf5Reader::open(path)
         .map_err(|e| format!("Failed to open file: {}", e))?;
 
-    let total = reader.get_total_samples()
+    let sample_count = reader.get_total_samples()
         .map_err(|e| format!("Failed to get sample count: {}", e))?;
 
-    if total == 0 {
-        return Err("File contains no samples".to_string());
-    }
-
-    let samples = reader.read_range(0, total)
-        .map_err(|e| format!("Failed to read samples: {}", e))?;
-
-    let time_range = (
-        samples.first().unwrap().timestamp,
-        samples.last().unwrap().timestamp,
-    );
+    let time_range = reader.time_span()
+        .map_err(|e| format!("Failed to read timestamps: {}", e))?
+        .ok_or_else(|| "File contains no samples".to_string())?;
 
     Ok(LoadedData {
         metadata: reader.metadata().clone(),
-        samples,
+        reader,
+        sample_count,
         time_range,
     })
 }
 
-/// Downsample data for display using MinMax algorithm
-pub fn downsample(
-    samples: &[TimestampedSample],
-    time_range: (f64, f64),
-) -> DisplayData {
-    // Filter to time range
-    let filtered: Vec<_> = samples
-        .iter()
-        .filter(|s| s.timestamp >= time_range.0 && s.timestamp <= time_range.1)
-        .collect();
+/// Read the samples of a loaded file inside `time_range`
+pub fn read_window(data: &LoadedData, time_range: (f64, f64)) -> Result<Vec<TimestampedSample>, String> {
+    data.reader.read_time_range(time_range.0, time_range.1)
+        .map_err(|e| format!("Failed to read samples: {}", e))
+}
 
-    if filtered.is_empty() {
-        return DisplayData {
-            timestamps: vec![],
-            accel_x: vec![],
-            accel_y: vec![],
-            accel_z: vec![],
-            gyro_x: vec![],
-            gyro_y: vec![],
-            gyro_z: vec![],
-        };
-    }
+/// Start following a file that is still being written
+///
+/// Only the last `backlog` samples already in the file are replayed.
+pub fn follow_file(path: &Path, backlog: usize) -> Result<FileFollower, String> {
+    let mut reader = Hdf5Reader::open_swmr(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
 
-    let n = filtered.len();
-    if n <= MAX_DISPLAY_POINTS {
-        // No downsampling needed
-        return extract_display_data(&filtered);
-    }
+    let total = reader.refresh()
+        .map_err(|e| format!("Failed to get sample count: {}", e))?;
 
-    // MinMax downsampling: preserve peaks
-    downsample_minmax(&filtered, MAX_DISPLAY_POINTS)
+    Ok(FileFollower {
+        reader,
+        path: path.to_path_buf(),
+        position: total.saturating_sub(backlog),
+        last_poll: Instant::now(),
+    })
 }
 
-/// Extract display data from samples
-fn extract_display_data(samples: &[&TimestampedSample]) -> DisplayData {
-    DisplayData {
-        timestamps: samples.iter().map(|s| s.timestamp).collect(),
-        accel_x: samples.iter().map(|s| s.data.accel_x_g()).collect(),
-        accel_y: samples.iter().map(|s| s.data.accel_y_g()).collect(),
-        accel_z: samples.iter().map(|s| s.data.accel_z_g()).collect(),
-        gyro_x: samples.iter().map(|s| s.data.gyro_x_dps()).collect(),
-        gyro_y: samples.iter().map(|s| s.data.gyro_y_dps()).collect(),
-        gyro_z: samples.iter().map(|s| s.data.gyro_z_dps()).collect(),
-    }
+/// Read the samples appended since the last poll
+pub fn poll_follower(follower: &mut FileFollower) -> Result<Vec<TimestampedSample>, String> {
+    let samples = follower.reader.follow(follower.position)
+        .map_err(|e| format!("Failed to read samples: {}", e))?;
+    follower.position += samples.len();
+    follower.last_poll = Instant::now();
+    Ok(samples)
 }
 
-/// MinMax downsampling - keeps min and max from each bucket
-fn downsample_minmax(samples: &[&TimestampedSample], target_points: usize) -> DisplayData {
-    let bucket_size = samples.len() / (target_points / 2);
-    if bucket_size < 2 {
-        return extract_display_data(samples);
-    }
+/// Min/max envelope of `time_range` for plotting
+///
+/// Comes from the file's overview pyramid, so only about
+/// `MAX_DISPLAY_POINTS` values are read however long the window is. Each
+/// bin contributes its minimum at its first and its maximum at its last
+/// timestamp; bins of a single sample become a single point.
+pub fn overview(data: &LoadedData, time_range: (f64, f64)) -> Result<DisplayData, String> {
+    let bins = data.reader.read_overview(time_range.0, time_range.1, MAX_DISPLAY_POINTS / 2)
+        .map_err(|e| format!("Failed to read overview: {}", e))?;
+    let scale: Vec<f32> = CHANNELS.iter()
+        .map(|name| schema::channel(name).map_or(1.0, |info| info.scale_factor as f32))
+        .collect();
 
-    let mut timestamps = Vec::with_capacity(target_points);
-    let mut accel_x = Vec::with_capacity(target_points);
-    let mut accel_y = Vec::with_capacity(target_points);
-    let mut accel_z = Vec::with_capacity(target_points);
-    let mut gyro_x = Vec::with_capacity(target_points);
-    let mut gyro_y = Vec::with_capacity(target_points);
-    let mut gyro_z = Vec::with_capacity(target_points);
-
-    for chunk in samples.chunks(bucket_size) {
-        if chunk.is_empty() {
-            continue;
+    let mut timestamps = Vec::with_capacity(bins.len() * 2);
+    let mut values: [Vec<f32>; CHANNELS.len()] = Default::default();
+    let mut push = |t: f64, raw: &[f64; CHANNELS.len()]| {
+        timestamps.push(t);
+        for (i, column) in values.iter_mut().enumerate() {
+            column.push(raw[i] as f32 * scale[i]);
         }
-
-        // Find min and max by total acceleration magnitude
-        let (min_idx, max_idx) = chunk
-            .iter()
-            .enumerate()
-            .fold((0, 0), |(min_i, max_i), (i, s)| {
-                let mag = s.data.accel_x_g().powi(2)
-                    + s.data.accel_y_g().powi(2)
-                    + s.data.accel_z_g().powi(2);
-                let min_mag = chunk[min_i].data.accel_x_g().powi(2)
-                    + chunk[min_i].data.accel_y_g().powi(2)
-                    + chunk[min_i].data.accel_z_g().powi(2);
-                let max_mag = chunk[max_i].data.accel_x_g().powi(2)
-                    + chunk[max_i].data.accel_y_g().powi(2)
-                    + chunk[max_i].data.accel_z_g().powi(2);
-
-                (
-                    if mag < min_mag { i } else { min_i },
-                    if mag > max_mag { i } else { max_i },
-                )
-            });
-
-        // Add in time order
-        let (first_idx, second_idx) = if min_idx < max_idx {
-            (min_idx, max_idx)
+    };
+    for bin in &bins {
+        if bin.count == 1 {
+            push(bin.start_time, &bin.mean);
         } else {
-            (max_idx, min_idx)
-        };
-
-        for &idx in &[first_idx, second_idx] {
-            let s = chunk[idx];
-            timestamps.push(s.timestamp);
-            accel_x.push(s.data.accel_x_g());
-            accel_y.push(s.data.accel_y_g());
-            accel_z.push(s.data.accel_z_g());
-            gyro_x.push(s.data.gyro_x_dps());
-            gyro_y.push(s.data.gyro_y_dps());
-            gyro_z.push(s.data.gyro_z_dps());
+            push(bin.start_time, &bin.min);
+            push(bin.end_time, &bin.max);
         }
     }
 
-    DisplayData {
+    let [accel_x, accel_y, accel_z, gyro_x, gyro_y, gyro_z] = values;
+    Ok(DisplayData {
         timestamps,
         accel_x,
         accel_y,
@@ -151,7 +104,7 @@ fn downsample_minmax(samples: &[&TimestampedSample], target_points: usize) -> Di
         gyro_x,
         gyro_y,
         gyro_z,
-    }
+    })
 }
 
 /// Compute FFT for all axes
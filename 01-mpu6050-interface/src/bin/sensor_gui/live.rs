@@ -1,7 +1,7 @@
 //! Live sensor streaming thread management
 
 use crate::state::SensorHandle;
-use ft232_sensor_interface::{Mpu6050, SensorData, StreamControl};
+use ft232_sensor_interface::{Mpu6050, PollScheduler, SensorData, StreamControl};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{self, Sender};
 use std::sync::Arc;
@@ -61,7 +61,9 @@ fn run_sensor_thread(tx: Sender<SensorData>, stop_signal: Arc<AtomicBool>) {
 
 /// Run in FIFO mode (~850 Hz)
 fn run_fifo_mode(mut sensor: Mpu6050, tx: Sender<SensorData>, stop_signal: Arc<AtomicBool>) {
-    let result = sensor.stream_fifo(20, |batch| {
+    // A quarter-full FIFO per read keeps plot latency around 25 ms
+    let scheduler = PollScheduler::new(85, 1000.0).target_fill(0.25);
+    let result = sensor.stream_fifo_scheduled(scheduler, |batch| {
         if stop_signal.load(Ordering::Relaxed) {
             return StreamControl::Break;
         }
@@ -1,6 +1,6 @@
 //! Application state management
 
-use ft232_sensor_interface::{Hdf5Writer, Metadata, SensorData, TimestampedSample};
+use ft232_sensor_interface::{Hdf5Reader, Hdf5Writer, Metadata, SensorData, TimestampedSample};
 use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -51,14 +51,25 @@ pub enum AppMode {
     #[default]
     Idle,     // No connection, no file
     Live,     // Connected to sensor
+    Follow,   // Tailing a file another process is writing
     Playback, // Viewing saved file
 }
 
+impl AppMode {
+    /// Samples keep arriving (from the sensor or a followed file)
+    pub fn is_streaming(self) -> bool {
+        matches!(self, AppMode::Live | AppMode::Follow)
+    }
+}
+
 /// Live streaming state
 pub struct LiveState {
     /// Sensor thread handle
     pub sensor_handle: Option<SensorHandle>,
 
+    /// File being followed instead of a sensor
+    pub follower: Option<FileFollower>,
+
     /// Circular buffer for live data
     pub buffer: CircularBuffer,
 
@@ -83,6 +94,7 @@ impl Default for LiveState {
     fn default() -> Self {
         Self {
             sensor_handle: None,
+            follower: None,
             buffer: CircularBuffer::new(10000), // ~10 seconds at 1000 Hz
             is_recording: false,
             recording_start: None,
@@ -125,6 +137,15 @@ impl SensorHandle {
     }
 }
 
+/// HDF5 file opened in SWMR mode while a collector writes it
+pub struct FileFollower {
+    pub reader: Hdf5Reader,
+    pub path: PathBuf,
+    /// Index of the next sample to read
+    pub position: usize,
+    pub last_poll: Instant,
+}
+
 /// Circular buffer for live sensor data
 pub struct CircularBuffer {
     data: VecDeque<TimestampedSample>,
@@ -184,10 +205,11 @@ impl CircularBuffer {
     }
 }
 
-/// Data loaded from HDF5 file
+/// HDF5 file opened for playback; samples are read per visible window
 pub struct LoadedData {
     pub metadata: Metadata,
-    pub samples: Vec<TimestampedSample>,
+    pub reader: Hdf5Reader,
+    pub sample_count: usize,
     pub time_range: (f64, f64),
 }
 
@@ -0,0 +1,252 @@
+//! Event and annotation track of a recording
+//!
+//! Marks such as "motor start", "hammer hit 3" or a FIFO overflow, kept
+//! with the samples and on the same clock:
+//!
+//! ```text
+//! /events
+//!     time       f64   s since start_time, like sensor_data/timestamps
+//!     kind       str   short tag, e.g. "note", "hit", "overflow"
+//!     label      str   free text
+//!     payload    str   JSON text, empty if none (not validated)
+//! ```
+//!
+//! The text columns are fixed-length (256 bytes, 1024 for `payload`) so
+//! events can still be added after [`Hdf5Writer::start_swmr`]. Longer text
+//! is cut and NUL characters are dropped.
+//!
+//! Events are added with [`Hdf5Writer::add_event`](crate::Hdf5Writer::add_event)
+//! and read with [`Hdf5Reader::events`](crate::Hdf5Reader::events). They are
+//! not journaled: an event reaches the file at the next flush, and a file
+//! rebuilt by [`crate::journal::recover`] from the journal alone has none.
+
+use crate::hdf5_format::{fixed_text, read_text, Hdf5Writer, PAYLOAD_LEN, TEXT_LEN};
+use crate::{Mpu6050Error, Result};
+use hdf5::types::FixedUnicode;
+use hdf5::{Dataset, Group};
+
+/// Kind given to lines typed without a `kind:` prefix
+pub const NOTE: &str = "note";
+
+/// Something that happened at a point of a recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    /// Seconds since collection start, same clock as the sample timestamps
+    pub time: f64,
+    pub kind: String,
+    pub label: String,
+    /// JSON text
+    pub payload: Option<String>,
+}
+
+impl Event {
+    pub fn new(time: f64, kind: &str, label: &str) -> Self {
+        Event { time, kind: kind.to_string(), label: label.to_string(), payload: None }
+    }
+
+    pub fn with_payload(mut self, payload: &str) -> Self {
+        self.payload = Some(payload.to_string());
+        self
+    }
+
+    /// Parse a line typed during recording
+    ///
+    /// `motor start` is a [`NOTE`]; `hit: hammer hit 3` has kind `hit`. A
+    /// trailing JSON object becomes the payload: `hit: hammer {"n": 3}`.
+    /// Blank lines and lines starting with `#` give `None`.
+    pub fn parse(line: &str, time: f64) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (kind, rest) = match line.split_once(':') {
+            Some((kind, rest)) if !kind.is_empty() && !kind.contains(char::is_whitespace) && !kind.contains('{') => {
+                (kind, rest.trim())
+            }
+            _ => (NOTE, line),
+        };
+        let (label, payload) = match rest.find('{') {
+            Some(at) if rest.ends_with('}') => (rest[..at].trim(), Some(&rest[at..])),
+            _ => (rest, None),
+        };
+        let event = Event::new(time, kind, label);
+        Some(match payload {
+            Some(payload) => event.with_payload(payload),
+            None => event,
+        })
+    }
+}
+
+/// Stretch of a recording between two event boundaries
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventSpan<'a> {
+    pub start: f64,
+    pub end: f64,
+    /// Event that opened the span, `None` for the stretch before the first
+    pub event: Option<&'a Event>,
+}
+
+/// Cut `start..end` at every event inside it
+///
+/// `events` must be in time order, as [`Hdf5Reader::events`](crate::Hdf5Reader::events)
+/// returns them. Empty spans (events at the same time, or at `start`) are
+/// left out.
+pub fn spans(events: &[Event], start: f64, end: f64) -> Vec<EventSpan<'_>> {
+    let mut spans = Vec::new();
+    let mut current = EventSpan { start, end, event: None };
+    for event in events.iter().filter(|e| e.time >= start && e.time < end) {
+        current.end = event.time;
+        if current.end > current.start {
+            spans.push(current);
+        }
+        current = EventSpan { start: event.time, end, event: Some(event) };
+    }
+    if current.end > current.start {
+        spans.push(current);
+    }
+    spans
+}
+
+/// Datasets of the `events` group
+pub(crate) struct EventTrack {
+    time: Dataset,
+    kind: Dataset,
+    label: Dataset,
+    payload: Dataset,
+    count: usize,
+}
+
+impl EventTrack {
+    pub(crate) fn create(group: &Group) -> Result<Self> {
+        Ok(EventTrack {
+            time: Hdf5Writer::create_dataset::<f64>(group, "time", 16)?,
+            kind: Hdf5Writer::create_dataset::<FixedUnicode<TEXT_LEN>>(group, "kind", 16)?,
+            label: Hdf5Writer::create_dataset::<FixedUnicode<TEXT_LEN>>(group, "label", 16)?,
+            payload: Hdf5Writer::create_dataset::<FixedUnicode<PAYLOAD_LEN>>(group, "payload", 16)?,
+            count: 0,
+        })
+    }
+
+    pub(crate) fn open(group: &Group) -> Result<Self> {
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open event {}", name), e));
+        let (time, kind, label, payload) = (open("time")?, open("kind")?, open("label")?, open("payload")?);
+        let count = [&time, &kind, &label, &payload].iter().map(|d| d.size()).min().unwrap_or(0);
+        Ok(EventTrack { time, kind, label, payload, count })
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.count
+    }
+
+    pub(crate) fn append(&mut self, event: &Event) -> Result<()> {
+        let new_size = self.count + 1;
+        let kind = fixed_text::<TEXT_LEN>(&event.kind)?;
+        let label = fixed_text::<TEXT_LEN>(&event.label)?;
+        let payload = fixed_text::<PAYLOAD_LEN>(event.payload.as_deref().unwrap_or(""))?;
+        append(&self.time, new_size, &[event.time])?;
+        append(&self.kind, new_size, &[kind])?;
+        append(&self.label, new_size, &[label])?;
+        append(&self.payload, new_size, &[payload])?;
+        self.count = new_size;
+        Ok(())
+    }
+}
+
+fn append<T: hdf5::H5Type>(dataset: &Dataset, new_size: usize, data: &[T]) -> Result<()> {
+    dataset.resize((new_size,))
+        .and_then(|_| dataset.write_slice(data, new_size - data.len()..))
+        .map_err(|e| Mpu6050Error::storage("Failed to append event", e))
+}
+
+/// All events of one file, in the order they were added
+pub(crate) fn read(group: &Group) -> Result<Vec<Event>> {
+    let text = |name: &str| -> Result<Vec<String>> {
+        group.dataset(name)
+            .and_then(|d| read_text(&d))
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to read event {}", name), e))
+    };
+    let time = group.dataset("time")
+        .and_then(|d| d.read_raw::<f64>())
+        .map_err(|e| Mpu6050Error::storage("Failed to read event time", e))?;
+
+    Ok(time.into_iter()
+        .zip(text("kind")?)
+        .zip(text("label")?)
+        .zip(text("payload")?)
+        .map(|(((time, kind), label), payload)| Event {
+            time,
+            kind,
+            label,
+            payload: Some(payload).filter(|p| !p.is_empty()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_kind_label_and_payload() {
+        assert_eq!(Event::parse("motor start", 1.5), Some(Event::new(1.5, NOTE, "motor start")));
+        assert_eq!(Event::parse("hit: hammer hit 3", 2.0), Some(Event::new(2.0, "hit", "hammer hit 3")));
+        assert_eq!(
+            Event::parse("hit: hammer {\"n\": 3}", 2.0),
+            Some(Event::new(2.0, "hit", "hammer").with_payload("{\"n\": 3}"))
+        );
+        assert_eq!(Event::parse("ratio 1:4 gearbox", 0.0).unwrap().kind, NOTE);
+        assert_eq!(Event::parse("   ", 0.0), None);
+        assert_eq!(Event::parse("# comment", 0.0), None);
+    }
+
+    #[test]
+    fn spans_cut_at_events_in_range() {
+        let events = vec![
+            Event::new(-1.0, NOTE, "before"),
+            Event::new(0.0, NOTE, "at start"),
+            Event::new(4.0, "hit", "one"),
+            Event::new(4.0, "hit", "same time"),
+            Event::new(7.0, "hit", "two"),
+            Event::new(10.0, NOTE, "at end"),
+        ];
+        let spans = spans(&events, 0.0, 10.0);
+        let bounds: Vec<(f64, f64, Option<&str>)> = spans.iter()
+            .map(|s| (s.start, s.end, s.event.map(|e| e.label.as_str())))
+            .collect();
+        assert_eq!(bounds, vec![
+            (0.0, 4.0, Some("at start")),
+            (4.0, 7.0, Some("same time")),
+            (7.0, 10.0, Some("two")),
+        ]);
+    }
+
+    #[test]
+    fn spans_without_events_cover_range() {
+        let spans = spans(&[], 1.0, 2.0);
+        assert_eq!(spans, vec![EventSpan { start: 1.0, end: 2.0, event: None }]);
+    }
+
+    #[test]
+    fn events_append_under_swmr() {
+        let path = std::env::temp_dir().join(format!("mpu-events-swmr-{}.h5", std::process::id()));
+        let mut writer = Hdf5Writer::create(&path, "fifo", 1000.0).unwrap();
+        writer.start_swmr().unwrap();
+        writer.add_event(&Event::new(0.5, NOTE, "motor start")).unwrap();
+        writer.add_event(&Event::new(1.0, "hit", "hammer\0 3").with_payload("{\"n\": 3}")).unwrap();
+        writer.add_event(&Event::new(1.5, NOTE, &"x".repeat(300))).unwrap();
+        writer.write_discontinuity(1.0, 1.25, "USB\0 reset").unwrap();
+        writer.close().unwrap();
+
+        let reader = crate::Hdf5Reader::open(&path).unwrap();
+        let events = reader.events(..).unwrap();
+        let gaps = reader.discontinuities().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0], Event::new(0.5, NOTE, "motor start"));
+        assert_eq!(events[1], Event::new(1.0, "hit", "hammer 3").with_payload("{\"n\": 3}"));
+        assert_eq!(events[2].label.len(), TEXT_LEN);
+        assert_eq!(gaps[0].cause, "USB reset");
+    }
+}
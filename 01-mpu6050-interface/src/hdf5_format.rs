@@ -2,11 +2,57 @@
 //!
 //! Provides writer and reader interfaces for storing MPU6050 sensor data
 //! in HDF5 format.
+//!
+//! Files are created with the latest HDF5 file format so that a writer can
+//! switch to SWMR (single-writer/multiple-reader) mode with
+//! [`Hdf5Writer::start_swmr`]. A reader opened with [`Hdf5Reader::open_swmr`]
+//! can then tail the file with [`Hdf5Reader::follow`] while it is still
+//! being written, e.g. the GUI watching a running `collector`. Opened on a
+//! session manifest, it moves on to new segments as the writer rotates.
+//!
+//! Long recordings can be split into several files with
+//! [`Hdf5Writer::create_session`]; [`Hdf5Reader::open`] reads them back
+//! as one through the session manifest (see [`crate::session`]).
+//!
+//! The layout is described in [`crate::schema`]; files of the older
+//! version 1 schema are read the same way.
+//!
+//! Large recordings need not be loaded whole: [`Hdf5Reader::read_time_range`]
+//! locates a time window by binary search over the `timestamps` column, and
+//! [`Hdf5Reader::iter_chunks`] walks a file in blocks of bounded size.
+//! For plotting, [`Hdf5Reader::read_overview`] answers from the min/max/mean
+//! pyramid the writer keeps next to the samples (see [`crate::overview`]).
+//!
+//! Annotations and incidents are stored with [`Hdf5Writer::add_event`] and
+//! read back with [`Hdf5Reader::events`] (see [`crate::events`]).
+//!
+//! Chunk size, compression and the timestamp encoding of the sample
+//! columns are chosen with [`Hdf5Writer::create_with`] (see
+//! [`crate::storage`]); readers pick them up from the file.
 
+use crate::events::{self, Event, EventTrack};
+use crate::journal::{journal_path, Journal, JournalHeader};
+use crate::overview::{self, Level, OverviewBin, OverviewWriter, Rebinner};
+use crate::schema::{self, ChannelInfo, CRATE_VERSION, SCHEMA_VERSION};
+use crate::session::{is_manifest, Manifest, Rotation, Session};
+use crate::storage::{self, StorageOptions, TimestampFormat};
 use crate::{Mpu6050Error, Result, SensorData};
-use hdf5::{Dataset, File, Group};
-use std::path::Path;
-use std::time::Instant;
+use hdf5::types::{FixedUnicode, TypeDescriptor, VarLenUnicode};
+use hdf5::{Dataset, File, Group, Location};
+use std::fs::OpenOptions;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often a SWMR writer makes appended samples visible to readers
+const SWMR_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The file is synced to disk and the journal emptied at most this often
+/// while SWMR flushes run
+const JOURNAL_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rows read at a time when binning raw samples
+const READ_BLOCK: usize = 65_536;
 
 /// Sample with timestamp
 #[derive(Debug, Clone)]
@@ -21,7 +67,63 @@ pub struct Metadata {
     pub start_time: String,      // ISO 8601 timestamp
     pub sample_rate_hz: f64,     // Target sample rate
     pub acquisition_mode: String, // "polling" or "fifo"
-    pub version: String,         // Format version
+    pub version: String,         // Schema version ("1.0" or "2.0")
+    pub host: Option<String>,    // Recording machine (schema 2)
+    pub crate_version: Option<String>, // Writing library (schema 2)
+    pub device_serial: Option<String>, // FT232H serial number (schema 2)
+}
+
+/// How the writer of a file finished, from the `completion` metadata attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// Closed by [`Hdf5Writer::close`]
+    Complete,
+    /// Repaired by [`crate::journal::recover`] after an interrupted recording
+    Recovered,
+    /// No marker: still being written, interrupted and not recovered yet,
+    /// or written before the marker existed
+    Unknown,
+}
+
+impl Completion {
+    fn marker(self) -> &'static str {
+        match self {
+            Completion::Complete => "complete",
+            Completion::Recovered => "recovered",
+            Completion::Unknown => "unknown",
+        }
+    }
+
+    fn from_marker(marker: &str) -> Self {
+        match marker {
+            "complete" => Completion::Complete,
+            "recovered" => Completion::Recovered,
+            _ => Completion::Unknown,
+        }
+    }
+}
+
+/// Gap in the recording, e.g. while the sensor was being reconnected
+#[derive(Debug, Clone)]
+pub struct Discontinuity {
+    /// Seconds since collection start, same clock as the sample timestamps
+    pub start_time: f64,
+    pub end_time: f64,
+    pub cause: String,
+}
+
+impl Discontinuity {
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+}
+
+/// Datasets of the `discontinuities` group, created on first use
+struct DiscontinuityHandles {
+    start_time: Dataset,
+    end_time: Dataset,
+    cause: Dataset,
+    count: usize,
 }
 
 /// Handles for HDF5 datasets
@@ -33,6 +135,118 @@ struct DatasetHandles {
     gyro_x: Dataset,
     gyro_y: Dataset,
     gyro_z: Dataset,
+    time: TimestampFormat,
+    /// Rows per chunk; the time search steps through `timestamps` in these units
+    chunk_size: usize,
+}
+
+impl DatasetHandles {
+    fn open(group: &Group) -> Result<Self> {
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open {} dataset", name), e));
+        let timestamps = open("timestamps")?;
+        Ok(DatasetHandles {
+            time: TimestampFormat::of(&timestamps),
+            chunk_size: timestamps.chunk().and_then(|chunk| chunk.first().copied()).unwrap_or(storage::DEFAULT_CHUNK_SIZE),
+            timestamps,
+            accel_x: open("accel_x")?,
+            accel_y: open("accel_y")?,
+            accel_z: open("accel_z")?,
+            gyro_x: open("gyro_x")?,
+            gyro_y: open("gyro_y")?,
+            gyro_z: open("gyro_z")?,
+        })
+    }
+
+    /// Rows present in every column
+    fn len(&self) -> usize {
+        self.all().iter().map(|d| d.size()).min().unwrap_or(0)
+    }
+
+    /// Timestamps of rows `start..end` in seconds
+    fn read_timestamps(&self, start: usize, end: usize) -> Result<Vec<f64>> {
+        match self.time {
+            TimestampFormat::Seconds => {
+                let seconds: Vec<f64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Mpu6050Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(seconds)
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Mpu6050Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(ticks.into_iter().map(|t| storage::from_ticks(t, rate)).collect())
+            }
+        }
+    }
+
+    /// Rows `start..end` as samples
+    fn read(&self, start: usize, end: usize) -> Result<Vec<TimestampedSample>> {
+        // Read each dataset slice
+        let timestamps = self.read_timestamps(start, end)?;
+
+        let accel_x: Vec<i16> = self.accel_x.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read accel_x", e))?
+            .to_vec();
+
+        let accel_y: Vec<i16> = self.accel_y.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read accel_y", e))?
+            .to_vec();
+
+        let accel_z: Vec<i16> = self.accel_z.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read accel_z", e))?
+            .to_vec();
+
+        let gyro_x: Vec<i16> = self.gyro_x.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read gyro_x", e))?
+            .to_vec();
+
+        let gyro_y: Vec<i16> = self.gyro_y.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read gyro_y", e))?
+            .to_vec();
+
+        let gyro_z: Vec<i16> = self.gyro_z.read_slice_1d(start..end)
+            .map_err(|e| Mpu6050Error::storage("Failed to read gyro_z", e))?
+            .to_vec();
+
+        // Combine into samples
+        let samples: Vec<TimestampedSample> = timestamps.into_iter()
+            .zip(accel_x.into_iter())
+            .zip(accel_y.into_iter())
+            .zip(accel_z.into_iter())
+            .zip(gyro_x.into_iter())
+            .zip(gyro_y.into_iter())
+            .zip(gyro_z.into_iter())
+            .map(|((((((ts, ax), ay), az), gx), gy), gz)| {
+                TimestampedSample {
+                    timestamp: ts,
+                    data: SensorData {
+                        accel_x: ax,
+                        accel_y: ay,
+                        accel_z: az,
+                        gyro_x: gx,
+                        gyro_y: gy,
+                        gyro_z: gz,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(samples)
+    }
+
+    fn all(&self) -> [&Dataset; 7] {
+        [
+            &self.timestamps,
+            &self.accel_x,
+            &self.accel_y,
+            &self.accel_z,
+            &self.gyro_x,
+            &self.gyro_y,
+            &self.gyro_z,
+        ]
+    }
 }
 
 /// HDF5 writer for sensor data collection
@@ -40,7 +254,28 @@ pub struct Hdf5Writer {
     file: File,
     datasets: DatasetHandles,
     start_time: Instant,
+    discontinuities: Option<DiscontinuityHandles>,
+    events: Option<EventTrack>,
     sample_count: usize,
+    swmr: bool,
+    last_flush: Instant,
+    last_checkpoint: Instant,
+    path: PathBuf,
+    metadata: Metadata,
+    journal: Option<Journal>,
+    session: Option<Session>,
+    /// `None` for files reopened for repair that predate the pyramid
+    overview: Option<OverviewWriter>,
+    /// Layout of the sample columns, kept for new segments
+    storage: StorageOptions,
+    /// `write_metadata_*` attributes, repeated in every segment
+    extra_metadata: Vec<(String, ExtraAttr)>,
+}
+
+#[derive(Clone)]
+enum ExtraAttr {
+    Str(String),
+    F64(f64),
 }
 
 impl Hdf5Writer {
@@ -51,53 +286,72 @@ impl Hdf5Writer {
     /// * `mode` - Acquisition mode ("polling" or "fifo")
     /// * `rate` - Target sample rate in Hz
     pub fn create<P: AsRef<Path>>(path: P, mode: &str, rate: f64) -> Result<Self> {
-        // Create HDF5 file
-        let file = File::create(path)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to create HDF5 file: {}", e)))?;
+        Self::create_with(path, mode, rate, &StorageOptions::default())
+    }
+
+    /// Like [`create`](Self::create), with the sample columns chunked,
+    /// compressed and timestamped per `storage`
+    pub fn create_with<P: AsRef<Path>>(path: P, mode: &str, rate: f64, storage: &StorageOptions) -> Result<Self> {
+        storage.validate()?;
+
+        // Create HDF5 file (latest format, required for SWMR)
+        let file = File::with_options()
+            .with_fapl(|p| p.libver_latest())
+            .create(path.as_ref())
+            .map_err(|e| Mpu6050Error::storage("Failed to create HDF5 file", e))?;
 
         // Create metadata group
         let metadata_group = file.create_group("metadata")
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to create metadata group: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to create metadata group", e))?;
 
         // Write metadata attributes
         let start_time = chrono::Local::now().to_rfc3339();
-        let start_time_vlu: hdf5::types::VarLenUnicode = start_time.parse().unwrap();
+        let start_time_vlu = attr_text("start_time", &start_time)?;
         metadata_group.new_attr::<hdf5::types::VarLenUnicode>()
             .create("start_time")
             .and_then(|attr| attr.write_scalar(&start_time_vlu))
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to write start_time: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to write start_time", e))?;
 
         metadata_group.new_attr::<f64>()
             .create("sample_rate_hz")
             .and_then(|attr| attr.write_scalar(&rate))
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to write sample_rate_hz: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to write sample_rate_hz", e))?;
 
-        let mode_vlu: hdf5::types::VarLenUnicode = mode.parse().unwrap();
+        let mode_vlu = attr_text("acquisition_mode", mode)?;
         metadata_group.new_attr::<hdf5::types::VarLenUnicode>()
             .create("acquisition_mode")
             .and_then(|attr| attr.write_scalar(&mode_vlu))
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to write acquisition_mode: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to write acquisition_mode", e))?;
 
-        let version_vlu: hdf5::types::VarLenUnicode = "1.0".parse().unwrap();
+        let version_vlu: hdf5::types::VarLenUnicode = SCHEMA_VERSION.parse().unwrap();
         metadata_group.new_attr::<hdf5::types::VarLenUnicode>()
             .create("version")
             .and_then(|attr| attr.write_scalar(&version_vlu))
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to write version: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to write version", e))?;
+
+        let host = schema::host_name();
+        if let Some(host) = &host {
+            let host_vlu = attr_text("host", host)?;
+            set_attr(&metadata_group, "host", &host_vlu)?;
+        }
+        let crate_vlu: hdf5::types::VarLenUnicode = CRATE_VERSION.parse().unwrap();
+        set_attr(&metadata_group, "crate_version", &crate_vlu)?;
 
         // Create sensor_data group
         let data_group = file.create_group("sensor_data")
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to create sensor_data group: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to create sensor_data group", e))?;
 
         // Create chunked, compressed datasets
-        let chunk_size = 1024;
-
-        let timestamps = Self::create_dataset::<f64>(&data_group, "timestamps", chunk_size)?;
-        let accel_x = Self::create_dataset::<i16>(&data_group, "accel_x", chunk_size)?;
-        let accel_y = Self::create_dataset::<i16>(&data_group, "accel_y", chunk_size)?;
-        let accel_z = Self::create_dataset::<i16>(&data_group, "accel_z", chunk_size)?;
-        let gyro_x = Self::create_dataset::<i16>(&data_group, "gyro_x", chunk_size)?;
-        let gyro_y = Self::create_dataset::<i16>(&data_group, "gyro_y", chunk_size)?;
-        let gyro_z = Self::create_dataset::<i16>(&data_group, "gyro_z", chunk_size)?;
+        let timestamps = match storage.timestamps {
+            TimestampFormat::Seconds => storage.create_dataset::<f64>(&data_group, "timestamps")?,
+            TimestampFormat::Ticks(_) => storage.create_dataset::<i64>(&data_group, "timestamps")?,
+        };
+        let accel_x = storage.create_dataset::<i16>(&data_group, "accel_x")?;
+        let accel_y = storage.create_dataset::<i16>(&data_group, "accel_y")?;
+        let accel_z = storage.create_dataset::<i16>(&data_group, "accel_z")?;
+        let gyro_x = storage.create_dataset::<i16>(&data_group, "gyro_x")?;
+        let gyro_y = storage.create_dataset::<i16>(&data_group, "gyro_y")?;
+        let gyro_z = storage.create_dataset::<i16>(&data_group, "gyro_z")?;
 
         let datasets = DatasetHandles {
             timestamps,
@@ -107,24 +361,341 @@ impl Hdf5Writer {
             gyro_x,
             gyro_y,
             gyro_z,
+            time: storage.timestamps,
+            chunk_size: storage.chunk_size,
         };
 
+        // Units and scaling of every column (schema 2); timestamps follow their encoding
+        for (name, info) in schema::channels() {
+            let dataset = data_group.dataset(name)
+                .map_err(|e| Mpu6050Error::storage(format!("Failed to open {} dataset", name), e))?;
+            let info = if name == "timestamps" { datasets.time.channel() } else { info };
+            info.write(&dataset)?;
+        }
+
+        let overview = OverviewWriter::create(&file)?;
+
         Ok(Self {
             file,
             datasets,
             start_time: Instant::now(),
+            discontinuities: None,
+            events: None,
             sample_count: 0,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.as_ref().to_path_buf(),
+            metadata: Metadata {
+                start_time,
+                sample_rate_hz: rate,
+                acquisition_mode: mode.to_string(),
+                version: SCHEMA_VERSION.to_string(),
+                host,
+                crate_version: Some(CRATE_VERSION.to_string()),
+                device_serial: None,
+            },
+            journal: None,
+            session: None,
+            overview: Some(overview),
+            storage: *storage,
+            extra_metadata: Vec::new(),
+        })
+    }
+
+    /// Start a recording that moves on to a new file whenever `rotation`
+    /// says so
+    ///
+    /// Segments are named after `output` (`data.h5` -> `data_0001.h5`, ...)
+    /// and listed in `data.manifest`. Sample and gap counts as well as
+    /// [`elapsed_secs`](Self::elapsed_secs) cover the whole session. Every
+    /// segment is laid out per `storage`.
+    pub fn create_session<P: AsRef<Path>>(
+        output: P, mode: &str, rate: f64, rotation: Rotation, storage: &StorageOptions,
+    ) -> Result<Self> {
+        let mut session = Session::new(output.as_ref(), rotation);
+        let mut writer = Self::create_with(session.begin_segment(), mode, rate, storage)?;
+        session.set_start_time(&writer.metadata.start_time);
+        session.save_manifest()?;
+        writer.session = Some(session);
+        Ok(writer)
+    }
+
+    /// Manifest of a session writer
+    pub fn manifest_path(&self) -> Option<PathBuf> {
+        self.session.as_ref().map(Session::manifest_path)
+    }
+
+    /// File currently being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Close the current segment and continue in the next one
+    fn rotate(&mut self) -> Result<()> {
+        let Some(mut session) = self.session.take() else {
+            return Ok(());
+        };
+        session.samples_before += self.sample_count;
+        session.gaps_before += self.segment_discontinuities();
+        session.events_before += self.segment_events();
+
+        let mut next = Self::create_with(
+            session.begin_segment(), &self.metadata.acquisition_mode, self.metadata.sample_rate_hz, &self.storage,
+        )?;
+        // Timestamps keep counting from the session start
+        next.set_start_time(&self.metadata.start_time)?;
+        next.start_time = self.start_time;
+        if let Some(serial) = &self.metadata.device_serial {
+            next.write_device_serial(serial)?;
+        }
+        for (name, value) in &self.extra_metadata {
+            match value {
+                ExtraAttr::Str(value) => next.write_metadata_str(name, value)?,
+                ExtraAttr::F64(value) => next.write_metadata_f64(name, *value)?,
+            }
+        }
+        if self.journal.is_some() {
+            next.enable_journal()?;
+        }
+        if self.swmr {
+            next.start_swmr()?;
+        }
+        next.session = Some(session);
+
+        std::mem::replace(self, next).close()?;
+        if let Some(session) = &self.session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Record the serial number of the FT232H; must come before
+    /// [`start_swmr`](Self::start_swmr)
+    pub fn write_device_serial(&mut self, serial: &str) -> Result<()> {
+        if self.swmr {
+            return Err(Mpu6050Error::InvalidParameter(
+                "device_serial must be written before start_swmr()".to_string(),
+            ));
+        }
+        let group = self.file.group("metadata")
+            .map_err(|e| Mpu6050Error::storage("Failed to open metadata group", e))?;
+        let value = attr_text("device_serial", serial)?;
+        set_attr(&group, "device_serial", &value)?;
+        self.metadata.device_serial = Some(serial.to_string());
+        Ok(())
+    }
+
+    /// Write an extra string attribute into the `metadata` group; must come
+    /// before [`start_swmr`](Self::start_swmr)
+    pub fn write_metadata_str(&mut self, name: &str, value: &str) -> Result<()> {
+        let group = self.metadata_group(name)?;
+        set_attr(&group, name, &attr_text(name, value)?)?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::Str(value.to_string())));
+        Ok(())
+    }
+
+    /// Write an extra numeric attribute into the `metadata` group; must come
+    /// before [`start_swmr`](Self::start_swmr)
+    pub fn write_metadata_f64(&mut self, name: &str, value: f64) -> Result<()> {
+        let group = self.metadata_group(name)?;
+        set_attr(&group, name, &value)?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::F64(value)));
+        Ok(())
+    }
+
+    fn metadata_group(&self, name: &str) -> Result<Group> {
+        if self.swmr {
+            return Err(Mpu6050Error::InvalidParameter(format!(
+                "{} must be written before start_swmr()", name
+            )));
+        }
+        self.file.group("metadata")
+            .map_err(|e| Mpu6050Error::storage("Failed to open metadata group", e))
+    }
+
+    /// Replace the `start_time` taken at creation (RFC 3339), e.g. for data
+    /// recorded earlier by another instrument; must come before
+    /// [`start_swmr`](Self::start_swmr)
+    pub fn set_start_time(&mut self, start_time: &str) -> Result<()> {
+        let group = self.metadata_group("start_time")?;
+        let value = attr_text("start_time", start_time)?;
+        set_attr(&group, "start_time", &value)?;
+        self.metadata.start_time = start_time.to_string();
+        Ok(())
+    }
+
+    /// Open an existing file to repair it (see [`crate::journal::recover`])
+    pub(crate) fn reopen(path: &Path) -> Result<Self> {
+        let file = swmr::open_for_repair(path)
+            .map_err(|e| Mpu6050Error::storage("Failed to open HDF5 file for repair", e))?;
+        let metadata = Hdf5Reader::read_metadata(&file)?;
+        let data_group = file.group("sensor_data")
+            .map_err(|e| Mpu6050Error::storage("Failed to open sensor_data group", e))?;
+        let datasets = DatasetHandles::open(&data_group)?;
+
+        let discontinuities = match file.group("discontinuities") {
+            Ok(group) => {
+                let open = |name: &str| group.dataset(name)
+                    .map_err(|e| Mpu6050Error::storage(format!("Failed to open discontinuity {}", name), e));
+                let (start_time, end_time, cause) = (open("start_time")?, open("end_time")?, open("cause")?);
+                let count = start_time.size().min(end_time.size()).min(cause.size());
+                Some(DiscontinuityHandles { start_time, end_time, cause, count })
+            }
+            Err(_) => None,
+        };
+        let events = match file.group("events") {
+            Ok(group) => Some(EventTrack::open(&group)?),
+            Err(_) => None,
+        };
+
+        let sample_count = datasets.len();
+        let overview = if file.group("overview").is_ok() {
+            Some(OverviewWriter::resume(&file, sample_count, |start, end| datasets.read(start, end))?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            file,
+            sample_count,
+            datasets,
+            start_time: Instant::now(),
+            discontinuities,
+            events,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.to_path_buf(),
+            metadata,
+            journal: None,
+            session: None,
+            overview,
+            // Only read for new segments, which a repaired file never gets
+            storage: StorageOptions::default(),
+            extra_metadata: Vec::new(),
         })
     }
 
+    /// Start a new file from a journal whose HDF5 file was unreadable, with
+    /// the default [`StorageOptions`]
+    pub(crate) fn rebuild(path: &Path, header: &JournalHeader) -> Result<Self> {
+        let mut writer = Self::create(path, &header.acquisition_mode, header.sample_rate_hz)?;
+        writer.set_start_time(&header.start_time)?;
+        Ok(writer)
+    }
+
+    /// Keep the first `samples` samples and `gaps` discontinuities
+    pub(crate) fn truncate(&mut self, samples: usize, gaps: usize) -> Result<()> {
+        for dataset in self.datasets.all() {
+            dataset.resize((samples,))
+                .map_err(|e| Mpu6050Error::storage("Failed to truncate dataset", e))?;
+        }
+        self.sample_count = samples;
+        if self.overview.is_some() {
+            let datasets = &self.datasets;
+            self.overview = Some(OverviewWriter::resume(&self.file, samples, |start, end| datasets.read(start, end))?);
+        }
+
+        if let Some(handles) = &mut self.discontinuities {
+            for dataset in [&handles.start_time, &handles.end_time, &handles.cause] {
+                dataset.resize((gaps,))
+                    .map_err(|e| Mpu6050Error::storage("Failed to truncate discontinuities", e))?;
+            }
+            handles.count = gaps;
+        }
+        Ok(())
+    }
+
+    /// Mirror appended samples and gaps into `<file>.journal` until the
+    /// next flush, so that [`crate::journal::recover`] can restore them
+    /// after a crash
+    pub fn enable_journal(&mut self) -> Result<()> {
+        if self.journal.is_some() {
+            return Ok(());
+        }
+        self.flush()?;
+        let header = JournalHeader {
+            sample_base: self.sample_count as u64,
+            gap_base: self.segment_discontinuities() as u64,
+            start_time: self.metadata.start_time.clone(),
+            acquisition_mode: self.metadata.acquisition_mode.clone(),
+            sample_rate_hz: self.metadata.sample_rate_hz,
+        };
+        self.journal = Some(Journal::create(journal_path(&self.path), header)?);
+        Ok(())
+    }
+
+    /// Flush, close, and mark the file complete
+    ///
+    /// Stores the final `sample_count` and `completion = "complete"` in
+    /// the metadata group and deletes the journal. A writer that is only
+    /// dropped leaves the file without a marker. For a session this closes
+    /// the last segment and writes the final manifest.
+    pub fn close(self) -> Result<()> {
+        self.finish(Completion::Complete)
+    }
+
+    pub(crate) fn finish(mut self, completion: Completion) -> Result<()> {
+        if let Some(overview) = &mut self.overview {
+            overview.finish()?;
+        }
+        self.flush()?;
+        let Hdf5Writer { file, sample_count, path, journal, session, .. } = self;
+        // Also invalidates the dataset handles left in `self`
+        file.close()
+            .map_err(|e| Mpu6050Error::storage("Failed to close HDF5 file", e))?;
+
+        // A SWMR writer cannot add attributes, so the marker is written on a fresh handle
+        let file = File::open_rw(&path)
+            .map_err(|e| Mpu6050Error::storage("Failed to reopen HDF5 file", e))?;
+        let group = file.group("metadata")
+            .map_err(|e| Mpu6050Error::storage("Failed to open metadata group", e))?;
+        set_attr(&group, "sample_count", &(sample_count as u64))?;
+        let marker: hdf5::types::VarLenUnicode = completion.marker().parse().unwrap();
+        set_attr(&group, "completion", &marker)?;
+        file.close()
+            .map_err(|e| Mpu6050Error::storage("Failed to close HDF5 file", e))?;
+
+        if let Some(journal) = journal {
+            journal.remove()?;
+        }
+        if let Some(session) = session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Switch the file to SWMR mode so readers can open it while it grows
+    ///
+    /// No groups, datasets or attributes can be added afterwards, so the
+    /// `discontinuities` and `events` groups are created up front. Appended samples become
+    /// visible to readers at least every 250 ms.
+    pub fn start_swmr(&mut self) -> Result<()> {
+        if self.swmr {
+            return Ok(());
+        }
+        self.create_discontinuities()?;
+        self.create_events()?;
+        swmr::start_write(&self.file)
+            .map_err(|e| Mpu6050Error::storage("Failed to start SWMR write", e))?;
+        self.swmr = true;
+        Ok(())
+    }
+
+    /// Whether [`start_swmr`](Self::start_swmr) has been called
+    pub fn is_swmr(&self) -> bool {
+        self.swmr
+    }
+
     /// Create a resizable, chunked, compressed dataset
-    fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
+    pub(crate) fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
         group.new_dataset::<T>()
             .shape((0..,))  // Resizable, starts at 0
             .chunk((chunk_size,))  // Chunk size for efficient I/O
             .deflate(4)  // DEFLATE compression level 4
             .create(name)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to create dataset {}: {}", name, e)))
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to create dataset {}", name), e))
     }
 
     /// Append a single sample
@@ -137,11 +708,13 @@ impl Hdf5Writer {
         if samples.is_empty() {
             return Ok(());
         }
+        if let Some(journal) = &mut self.journal {
+            journal.append_samples(samples)?;
+        }
 
         let new_size = self.sample_count + samples.len();
 
         // Prepare data arrays
-        let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
         let accel_x: Vec<i16> = samples.iter().map(|s| s.data.accel_x).collect();
         let accel_y: Vec<i16> = samples.iter().map(|s| s.data.accel_y).collect();
         let accel_z: Vec<i16> = samples.iter().map(|s| s.data.accel_z).collect();
@@ -150,7 +723,16 @@ impl Hdf5Writer {
         let gyro_z: Vec<i16> = samples.iter().map(|s| s.data.gyro_z).collect();
 
         // Resize and append to each dataset
-        self.append_to_dataset(&self.datasets.timestamps, new_size, &timestamps)?;
+        match self.datasets.time {
+            TimestampFormat::Seconds => {
+                let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
+                self.append_to_dataset(&self.datasets.timestamps, new_size, &timestamps)?;
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = samples.iter().map(|s| storage::to_ticks(s.timestamp, rate)).collect();
+                self.append_to_dataset(&self.datasets.timestamps, new_size, &ticks)?;
+            }
+        }
         self.append_to_dataset(&self.datasets.accel_x, new_size, &accel_x)?;
         self.append_to_dataset(&self.datasets.accel_y, new_size, &accel_y)?;
         self.append_to_dataset(&self.datasets.accel_z, new_size, &accel_z)?;
@@ -159,31 +741,153 @@ impl Hdf5Writer {
         self.append_to_dataset(&self.datasets.gyro_z, new_size, &gyro_z)?;
 
         self.sample_count = new_size;
+        if let Some(overview) = &mut self.overview {
+            overview.push(samples);
+        }
+        if self.swmr && self.last_flush.elapsed() >= SWMR_FLUSH_INTERVAL {
+            self.flush_file()?;
+            if self.last_checkpoint.elapsed() >= JOURNAL_CHECKPOINT_INTERVAL {
+                self.checkpoint()?;
+            }
+        }
+
+        let rotate = match &mut self.session {
+            Some(session) => {
+                session.observe(samples);
+                session.due(&self.path)
+            }
+            None => false,
+        };
+        if rotate {
+            self.rotate()?;
+        }
         Ok(())
     }
 
     /// Append data to a dataset
     fn append_to_dataset<T: hdf5::H5Type>(&self, dataset: &Dataset, new_size: usize, data: &[T]) -> Result<()> {
         dataset.resize((new_size,))
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to resize dataset: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to resize dataset", e))?;
 
         let start = new_size - data.len();
         dataset.write_slice(data, start..)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to write to dataset: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to write to dataset", e))?;
 
         Ok(())
     }
 
-    /// Flush data to disk
+    /// Record a gap in the data (times in seconds, same clock as the samples)
+    pub fn write_discontinuity(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        self.create_discontinuities()?;
+        if let Some(journal) = &mut self.journal {
+            journal.append_gap(start_time, end_time, cause)?;
+        }
+        let handles = self.discontinuities.as_ref().unwrap();
+        let new_size = handles.count + 1;
+        let cause = fixed_text::<TEXT_LEN>(cause)?;
+
+        self.append_to_dataset(&handles.start_time, new_size, &[start_time])?;
+        self.append_to_dataset(&handles.end_time, new_size, &[end_time])?;
+        self.append_to_dataset(&handles.cause, new_size, &[cause])?;
+
+        self.discontinuities.as_mut().unwrap().count = new_size;
+        Ok(())
+    }
+
+    /// Create the `discontinuities` group unless it already exists
+    fn create_discontinuities(&mut self) -> Result<()> {
+        if self.discontinuities.is_none() {
+            let group = self.file.create_group("discontinuities")
+                .map_err(|e| Mpu6050Error::storage("Failed to create discontinuities group", e))?;
+            self.discontinuities = Some(DiscontinuityHandles {
+                start_time: Self::create_dataset::<f64>(&group, "start_time", 16)?,
+                end_time: Self::create_dataset::<f64>(&group, "end_time", 16)?,
+                cause: Self::create_dataset::<FixedUnicode<TEXT_LEN>>(&group, "cause", 16)?,
+                count: 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Record an event in the `events` group (see [`crate::events`])
+    ///
+    /// `event.time` is on the sample clock. A session writes it to the
+    /// segment in progress.
+    pub fn add_event(&mut self, event: &Event) -> Result<()> {
+        self.create_events()?;
+        self.events.as_mut().unwrap().append(event)
+    }
+
+    /// Create the `events` group unless it already exists
+    fn create_events(&mut self) -> Result<()> {
+        if self.events.is_none() {
+            let group = self.file.create_group("events")
+                .map_err(|e| Mpu6050Error::storage("Failed to create events group", e))?;
+            self.events = Some(EventTrack::create(&group)?);
+        }
+        Ok(())
+    }
+
+    /// Events recorded, over all segments of a session
+    pub fn event_count(&self) -> usize {
+        self.segment_events() + self.session.as_ref().map_or(0, |s| s.events_before)
+    }
+
+    fn segment_events(&self) -> usize {
+        self.events.as_ref().map_or(0, EventTrack::count)
+    }
+
+    /// Discontinuities recorded, over all segments of a session
+    pub fn discontinuity_count(&self) -> usize {
+        self.segment_discontinuities() + self.session.as_ref().map_or(0, |s| s.gaps_before)
+    }
+
+    fn segment_discontinuities(&self) -> usize {
+        self.discontinuities.as_ref().map_or(0, |d| d.count)
+    }
+
+    /// Write buffered data to the file and, with a journal, sync the file
+    /// to disk and empty the journal
     pub fn flush(&mut self) -> Result<()> {
+        self.flush_file()?;
+        self.checkpoint()
+    }
+
+    /// Hand buffered data to HDF5, which makes it visible to SWMR readers
+    fn flush_file(&mut self) -> Result<()> {
+        if let Some(overview) = &mut self.overview {
+            overview.flush()?;
+        }
         self.file.flush()
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to flush HDF5 file: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to flush HDF5 file", e))?;
+        self.last_flush = Instant::now();
         Ok(())
     }
 
-    /// Get current sample count
+    /// Sync the file to disk, then empty the journal: it may only drop
+    /// what has reached the disk
+    fn checkpoint(&mut self) -> Result<()> {
+        self.last_checkpoint = Instant::now();
+        let gaps = self.segment_discontinuities();
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        // FlushFileBuffers on Windows needs a handle with write access
+        OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .and_then(|file| file.sync_all())
+            .map_err(|source| Mpu6050Error::Io {
+                context: format!("Failed to sync {}", self.path.display()),
+                source,
+            })?;
+        journal.restart(self.sample_count, gaps)?;
+        Ok(())
+    }
+
+    /// Get current sample count (all segments of a session)
     pub fn sample_count(&self) -> usize {
-        self.sample_count
+        self.sample_count + self.session.as_ref().map_or(0, |s| s.samples_before)
     }
 
     /// Get elapsed time since start
@@ -192,80 +896,250 @@ impl Hdf5Writer {
     }
 }
 
-/// HDF5 reader for accessing collected sensor data
-pub struct Hdf5Reader {
-    #[allow(dead_code)]
+/// One file behind a reader
+struct Segment {
     file: File,
     datasets: DatasetHandles,
+    /// `overview` group, absent in files written before it existed
+    overview: Option<Group>,
+}
+
+impl Segment {
+    fn open(file: File) -> Result<Self> {
+        let data_group = file.group("sensor_data")
+            .map_err(|e| Mpu6050Error::storage("Failed to open sensor_data group", e))?;
+        let datasets = DatasetHandles::open(&data_group)?;
+        let overview = file.group("overview").ok();
+        Ok(Segment { file, datasets, overview })
+    }
+
+    /// Feed rows `start..end` to `out`, using pyramid level `factor` for
+    /// the bins it has stored and raw samples around them
+    fn summarize(&self, start: usize, end: usize, factor: Option<usize>, out: &mut Rebinner) -> Result<()> {
+        let level = match (factor, &self.overview) {
+            (Some(factor), Some(group)) => Level::open(group, factor).ok(),
+            _ => None,
+        };
+        let Some(level) = level else {
+            return self.summarize_raw(start, end, out);
+        };
+        let first = start.div_ceil(level.factor);
+        let last = (end / level.factor).min(level.len());
+        if first >= last {
+            return self.summarize_raw(start, end, out);
+        }
+        self.summarize_raw(start, first * level.factor, out)?;
+        for bin in level.read(first, last)? {
+            out.push(bin);
+        }
+        self.summarize_raw(last * level.factor, end, out)
+    }
+
+    fn summarize_raw(&self, start: usize, end: usize, out: &mut Rebinner) -> Result<()> {
+        for from in (start..end).step_by(READ_BLOCK) {
+            let to = (from + READ_BLOCK).min(end);
+            for sample in self.datasets.read(from, to)? {
+                out.push(OverviewBin::from_sample(&sample));
+            }
+        }
+        Ok(())
+    }
+
+    fn completion(&self) -> Completion {
+        self.file.group("metadata")
+            .and_then(|group| group.attr("completion"))
+            .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+            .map(|marker| Completion::from_marker(marker.as_str()))
+            .unwrap_or(Completion::Unknown)
+    }
+
+    fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let group = match self.file.group("discontinuities") {
+            Ok(group) => group,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let read_f64 = |name: &str| -> Result<Vec<f64>> {
+            group.dataset(name)
+                .and_then(|d| d.read_raw::<f64>())
+                .map_err(|e| Mpu6050Error::storage(format!("Failed to read discontinuity {}", name), e))
+        };
+        let start_time = read_f64("start_time")?;
+        let end_time = read_f64("end_time")?;
+        let cause = group.dataset("cause")
+            .and_then(|d| read_text(&d))
+            .map_err(|e| Mpu6050Error::storage("Failed to read discontinuity cause", e))?;
+
+        Ok(start_time.into_iter()
+            .zip(end_time)
+            .zip(cause)
+            .map(|((start_time, end_time), cause)| Discontinuity {
+                start_time,
+                end_time,
+                cause,
+            })
+            .collect())
+    }
+
+    fn events(&self) -> Result<Vec<Event>> {
+        match self.file.group("events") {
+            Ok(group) => events::read(&group),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    fn timestamp(&self, index: usize) -> Result<f64> {
+        let value = self.datasets.read_timestamps(index, index + 1)?;
+        value.first().copied()
+            .ok_or_else(|| Mpu6050Error::InvalidParameter(format!("No timestamp at index {}", index)))
+    }
+
+    /// Index of the first sample at or after `t` (after `t` if `after` is
+    /// set), or the sample count if there is none
+    ///
+    /// Timestamps increase monotonically, so the search probes the first
+    /// timestamp of each chunk and then reads a single chunk.
+    fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let before = |ts: f64| if after { ts <= t } else { ts < t };
+        let len = self.datasets.len();
+        if len == 0 || before(self.timestamp(len - 1)?) {
+            return Ok(len);
+        }
+
+        let chunk_size = self.datasets.chunk_size;
+        let (mut low, mut high) = (0, len.div_ceil(chunk_size));
+        while low < high {
+            let mid = (low + high) / 2;
+            if before(self.timestamp(mid * chunk_size)?) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return Ok(0);
+        }
+
+        let start = (low - 1) * chunk_size;
+        let end = (low * chunk_size).min(len);
+        let chunk = self.datasets.read_timestamps(start, end)?;
+        Ok(start + chunk.partition_point(|&ts| before(ts)))
+    }
+}
+
+/// HDF5 reader for accessing collected sensor data
+///
+/// A reader opened on a session manifest spans all segments of the session;
+/// sample indices run on from one segment to the next.
+pub struct Hdf5Reader {
+    segments: Vec<Segment>,
     metadata: Metadata,
+    manifest: Option<Manifest>,
+    /// Manifest re-read on every refresh, for a session opened with `open_swmr`
+    live_manifest: Option<PathBuf>,
 }
 
 impl Hdf5Reader {
-    /// Open an existing HDF5 file for reading
+    /// Open an existing HDF5 file, or a session manifest, for reading
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            return Self::open_session(path);
+        }
         let file = File::open(path)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open HDF5 file: {}", e)))?;
-
-        // Read metadata
-        let metadata = Self::read_metadata(&file)?;
+            .map_err(|e| Mpu6050Error::storage("Failed to open HDF5 file", e))?;
+        Self::from_file(file)
+    }
 
-        // Open datasets
-        let data_group = file.group("sensor_data")
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open sensor_data group: {}", e)))?;
+    /// Open every segment listed in a manifest written by
+    /// [`Hdf5Writer::create_session`]
+    pub fn open_session<P: AsRef<Path>>(manifest_path: P) -> Result<Self> {
+        Self::from_manifest(manifest_path.as_ref(), |path| File::open(path))
+    }
 
-        let datasets = DatasetHandles {
-            timestamps: data_group.dataset("timestamps")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open timestamps dataset: {}", e)))?,
-            accel_x: data_group.dataset("accel_x")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open accel_x dataset: {}", e)))?,
-            accel_y: data_group.dataset("accel_y")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open accel_y dataset: {}", e)))?,
-            accel_z: data_group.dataset("accel_z")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open accel_z dataset: {}", e)))?,
-            gyro_x: data_group.dataset("gyro_x")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open gyro_x dataset: {}", e)))?,
-            gyro_y: data_group.dataset("gyro_y")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open gyro_y dataset: {}", e)))?,
-            gyro_z: data_group.dataset("gyro_z")
-                .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open gyro_z dataset: {}", e)))?,
-        };
+    /// Open a file that a SWMR writer may still be appending to
+    ///
+    /// The sample count is fixed at open time; call [`refresh`](Self::refresh)
+    /// or [`follow`](Self::follow) to pick up newer samples. `path` may also
+    /// be the manifest of a rotating session: each refresh then re-reads the
+    /// manifest and continues into segments started since.
+    pub fn open_swmr<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            let mut reader = Self::from_manifest(path, swmr::open_read)?;
+            reader.live_manifest = Some(path.to_path_buf());
+            return Ok(reader);
+        }
+        let file = swmr::open_read(path)
+            .map_err(|e| Mpu6050Error::storage("Failed to open HDF5 file for SWMR read", e))?;
+        Self::from_file(file)
+    }
 
+    fn from_file(file: File) -> Result<Self> {
+        let metadata = Self::read_metadata(&file)?;
         Ok(Self {
-            file,
-            datasets,
+            segments: vec![Segment::open(file)?],
             metadata,
+            manifest: None,
+            live_manifest: None,
         })
     }
 
+    fn from_manifest(manifest_path: &Path, open: fn(&Path) -> hdf5::Result<File>) -> Result<Self> {
+        let manifest = Manifest::load(manifest_path)?;
+        let segments = manifest.segment_paths(manifest_path).iter()
+            .map(|path| Self::open_segment(path, open))
+            .collect::<Result<Vec<_>>>()?;
+        let metadata = Self::read_metadata(&segments[0].file)?;
+        Ok(Self { segments, metadata, manifest: Some(manifest), live_manifest: None })
+    }
+
+    fn open_segment(path: &Path, open: fn(&Path) -> hdf5::Result<File>) -> Result<Segment> {
+        open(path)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open segment {}", path.display()), e))
+            .and_then(Segment::open)
+    }
+
     /// Read metadata from file
     fn read_metadata(file: &File) -> Result<Metadata> {
         let metadata_group = file.group("metadata")
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to open metadata group: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to open metadata group", e))?;
 
         let start_time = metadata_group.attr("start_time")
             .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
             .map(|s| s.to_string())
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read start_time: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to read start_time", e))?;
 
         let sample_rate_hz = metadata_group.attr("sample_rate_hz")
             .and_then(|attr| attr.read_scalar::<f64>())
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read sample_rate_hz: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to read sample_rate_hz", e))?;
 
         let acquisition_mode = metadata_group.attr("acquisition_mode")
             .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
             .map(|s| s.to_string())
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read acquisition_mode: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to read acquisition_mode", e))?;
 
         let version = metadata_group.attr("version")
             .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
             .map(|s| s.to_string())
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read version: {}", e)))?;
+            .map_err(|e| Mpu6050Error::storage("Failed to read version", e))?;
+
+        // Not present in version 1 files
+        let optional = |name: &str| -> Option<String> {
+            metadata_group.attr(name)
+                .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+                .map(|s| s.to_string())
+                .ok()
+        };
 
         Ok(Metadata {
             start_time,
             sample_rate_hz,
             acquisition_mode,
             version,
+            host: optional("host"),
+            crate_version: optional("crate_version"),
+            device_serial: optional("device_serial"),
         })
     }
 
@@ -274,10 +1148,90 @@ impl Hdf5Reader {
         &self.metadata
     }
 
+    /// Units and scaling of the `sensor_data` column `name`
+    ///
+    /// Read from the dataset attributes of a version 2 file; version 1
+    /// files get the fixed scaling of [`schema::channels`].
+    pub fn channel_info(&self, name: &str) -> Option<ChannelInfo> {
+        self.segments[0].file.group("sensor_data")
+            .and_then(|group| group.dataset(name))
+            .ok()
+            .and_then(|dataset| ChannelInfo::read(&dataset))
+            .or_else(|| schema::channel(name))
+    }
+
+    /// Session manifest, if the reader was opened on one
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Whether the recording was closed cleanly, recovered, or neither
+    ///
+    /// A session is only complete if every segment is.
+    pub fn completion(&self) -> Completion {
+        self.segments.iter().map(Segment::completion).fold(Completion::Complete, |all, c| match (all, c) {
+            (Completion::Unknown, _) | (_, Completion::Unknown) => Completion::Unknown,
+            (Completion::Recovered, _) | (_, Completion::Recovered) => Completion::Recovered,
+            _ => Completion::Complete,
+        })
+    }
+
+    /// Gaps recorded during collection (empty for files without any)
+    pub fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.discontinuities()?);
+        }
+        Ok(all)
+    }
+
+    /// Events with a time in `range`, sorted by time
+    ///
+    /// Files written before the event track existed have none.
+    pub fn events<R: RangeBounds<f64>>(&self, range: R) -> Result<Vec<Event>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.events()?.into_iter().filter(|event| range.contains(&event.time)));
+        }
+        all.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(all)
+    }
+
     /// Get total number of samples in file
+    ///
+    /// While a SWMR writer is active the columns can briefly differ in
+    /// length; the shortest one counts, so only complete rows are reported.
     pub fn get_total_samples(&self) -> Result<usize> {
-        let size = self.datasets.timestamps.size();
-        Ok(size)
+        Ok(self.segments.iter().map(|s| s.datasets.len()).sum())
+    }
+
+    /// Re-read the dataset extents written by a SWMR writer since the last
+    /// call and return the new total sample count
+    ///
+    /// For a session opened with [`open_swmr`](Self::open_swmr) the segments
+    /// the writer has rotated into are opened and appended first.
+    pub fn refresh(&mut self) -> Result<usize> {
+        if let Some(manifest_path) = &self.live_manifest {
+            let manifest = Manifest::load(manifest_path)?;
+            for path in manifest.segment_paths(manifest_path).iter().skip(self.segments.len()) {
+                self.segments.push(Self::open_segment(path, swmr::open_read)?);
+            }
+            self.manifest = Some(manifest);
+        }
+        for dataset in self.segments.iter().flat_map(|s| s.datasets.all()) {
+            swmr::refresh(dataset)
+                .map_err(|e| Mpu6050Error::storage("Failed to refresh dataset", e))?;
+        }
+        self.get_total_samples()
+    }
+
+    /// Refresh and return every sample from index `from` to the current end
+    ///
+    /// Keep a cursor and advance it by the number of samples returned to
+    /// tail a file that is still being written.
+    pub fn follow(&mut self, from: usize) -> Result<Vec<TimestampedSample>> {
+        let total = self.refresh()?;
+        self.read_range(from, total.saturating_sub(from))
     }
 
     /// Read a range of samples
@@ -290,57 +1244,19 @@ impl Hdf5Reader {
         let actual_count = count.min(total - start);
         let end = start + actual_count;
 
-        // Read each dataset slice
-        let timestamps: Vec<f64> = self.datasets.timestamps.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read timestamps: {}", e)))?
-            .to_vec();
-
-        let accel_x: Vec<i16> = self.datasets.accel_x.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read accel_x: {}", e)))?
-            .to_vec();
-
-        let accel_y: Vec<i16> = self.datasets.accel_y.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read accel_y: {}", e)))?
-            .to_vec();
-
-        let accel_z: Vec<i16> = self.datasets.accel_z.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read accel_z: {}", e)))?
-            .to_vec();
-
-        let gyro_x: Vec<i16> = self.datasets.gyro_x.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read gyro_x: {}", e)))?
-            .to_vec();
-
-        let gyro_y: Vec<i16> = self.datasets.gyro_y.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read gyro_y: {}", e)))?
-            .to_vec();
-
-        let gyro_z: Vec<i16> = self.datasets.gyro_z.read_slice_1d(start..end)
-            .map_err(|e| Mpu6050Error::CommunicationError(format!("Failed to read gyro_z: {}", e)))?
-            .to_vec();
-
-        // Combine into samples
-        let samples: Vec<TimestampedSample> = timestamps.into_iter()
-            .zip(accel_x.into_iter())
-            .zip(accel_y.into_iter())
-            .zip(accel_z.into_iter())
-            .zip(gyro_x.into_iter())
-            .zip(gyro_y.into_iter())
-            .zip(gyro_z.into_iter())
-            .map(|((((((ts, ax), ay), az), gx), gy), gz)| {
-                TimestampedSample {
-                    timestamp: ts,
-                    data: SensorData {
-                        accel_x: ax,
-                        accel_y: ay,
-                        accel_z: az,
-                        gyro_x: gx,
-                        gyro_y: gy,
-                        gyro_z: gz,
-                    },
-                }
-            })
-            .collect();
+        let mut samples = Vec::with_capacity(end - start);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                samples.extend(segment.datasets.read(from - offset, to - offset)?);
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
 
         Ok(samples)
     }
@@ -355,4 +1271,358 @@ impl Hdf5Reader {
         let start = total.saturating_sub(count);
         self.read_range(start, count)
     }
+
+    /// First and last timestamp, `None` if there are no samples
+    pub fn time_span(&self) -> Result<Option<(f64, f64)>> {
+        let mut filled = self.segments.iter().filter(|s| s.datasets.len() > 0);
+        let Some(first) = filled.next() else {
+            return Ok(None);
+        };
+        let last = filled.next_back().unwrap_or(first);
+        Ok(Some((first.timestamp(0)?, last.timestamp(last.datasets.len() - 1)?)))
+    }
+
+    /// Index of the first sample with a timestamp of `t` or later
+    ///
+    /// Equals [`get_total_samples`](Self::get_total_samples) if the
+    /// recording ends before `t`.
+    pub fn index_at_time(&self, t: f64) -> Result<usize> {
+        self.search(t, false)
+    }
+
+    pub(crate) fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let index = segment.search(t, after)?;
+            if index < len {
+                return Ok(offset + index);
+            }
+            offset += len;
+        }
+        Ok(offset)
+    }
+
+    /// Samples with `t0 <= timestamp <= t1`
+    ///
+    /// Only the slices inside the window are read, so this is cheap for a
+    /// short window of a long recording.
+    pub fn read_time_range(&self, t0: f64, t1: f64) -> Result<Vec<TimestampedSample>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        self.read_range(start, end.saturating_sub(start))
+    }
+
+    /// Min/max/mean envelope of the samples from `t0` to `t1` in at most
+    /// about `max_bins` bins
+    ///
+    /// Served from the coarsest pyramid level that still gives `max_bins`
+    /// bins, so the cost depends on `max_bins` rather than the window
+    /// length. Short windows come back one sample per bin. Rows not yet
+    /// covered by the pyramid, and files without one, are summarised from
+    /// the raw samples.
+    pub fn read_overview(&self, t0: f64, t1: f64, max_bins: usize) -> Result<Vec<OverviewBin>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        if end <= start {
+            return Ok(Vec::new());
+        }
+        let per_bin = (end - start).div_ceil(max_bins.max(1));
+        let factor = overview::FACTORS.iter().rev().copied().find(|&f| f <= per_bin);
+
+        let mut out = Rebinner::new(per_bin);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                segment.summarize(from - offset, to - offset, factor, &mut out)?;
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
+        Ok(out.finish())
+    }
+
+    /// Iterate over all samples in blocks of `size`
+    ///
+    /// At most one block is held in memory at a time. The sample count is
+    /// taken when the iterator is created; samples appended later by a
+    /// SWMR writer are not included.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ft232_sensor_interface::Hdf5Reader;
+    ///
+    /// let reader = Hdf5Reader::open("long_run.h5")?;
+    /// let mut peak = 0i16;
+    /// for chunk in reader.iter_chunks(100_000) {
+    ///     for sample in chunk? {
+    ///         peak = peak.max(sample.data.accel_z.saturating_abs());
+    ///     }
+    /// }
+    /// # Ok::<(), ft232_sensor_interface::Mpu6050Error>(())
+    /// ```
+    pub fn iter_chunks(&self, size: usize) -> SampleChunks<'_> {
+        SampleChunks {
+            reader: self,
+            position: 0,
+            end: self.get_total_samples().unwrap_or(0),
+            size: size.max(1),
+        }
+    }
+}
+
+/// Iterator returned by [`Hdf5Reader::iter_chunks`]
+pub struct SampleChunks<'a> {
+    reader: &'a Hdf5Reader,
+    position: usize,
+    end: usize,
+    size: usize,
+}
+
+impl Iterator for SampleChunks<'_> {
+    type Item = Result<Vec<TimestampedSample>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.end {
+            return None;
+        }
+        let count = self.size.min(self.end - self.position);
+        let chunk = self.reader.read_range(self.position, count);
+        // Stop after an error instead of retrying the same block forever
+        self.position = if chunk.is_ok() { self.position + count } else { self.end };
+        Some(chunk)
+    }
+}
+
+/// Write a scalar attribute, replacing an existing one of the same name
+pub(crate) fn set_attr<T: hdf5::H5Type>(location: &Location, name: &str, value: &T) -> Result<()> {
+    let attr = match location.attr(name) {
+        Ok(attr) => attr,
+        Err(_) => location.new_attr::<T>().create(name)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to create {}", name), e))?,
+    };
+    attr.write_scalar(value)
+        .map_err(|e| Mpu6050Error::storage(format!("Failed to write {}", name), e))
+}
+
+/// Convert text for a string attribute, refusing NUL characters
+pub(crate) fn attr_text(name: &str, value: &str) -> Result<VarLenUnicode> {
+    value.parse()
+        .map_err(|_| Mpu6050Error::InvalidParameter(format!("{} contains a NUL character", name)))
+}
+
+/// Byte capacity of the short fixed-length text columns (causes, event kinds and labels)
+pub(crate) const TEXT_LEN: usize = 256;
+/// Byte capacity of the event payload column
+pub(crate) const PAYLOAD_LEN: usize = 1024;
+
+/// Convert free text for a fixed-length string column
+///
+/// Variable-length strings cannot be appended once SWMR is on, so text that
+/// grows with the recording is stored fixed-length. NUL bytes are dropped
+/// and text longer than `N` bytes is cut at a character boundary.
+pub(crate) fn fixed_text<const N: usize>(value: &str) -> Result<FixedUnicode<N>> {
+    let mut text: String = value.chars().filter(|&c| c != '\0').collect();
+    if text.len() > N {
+        let mut end = N;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text.parse()
+        .map_err(|e| Mpu6050Error::InvalidParameter(format!("Text cannot be stored: {}", e)))
+}
+
+/// Read a text column written either fixed-length or, by older versions, variable-length
+pub(crate) fn read_text(dataset: &Dataset) -> hdf5::Result<Vec<String>> {
+    let descriptor = dataset.dtype()?.to_descriptor()?;
+    Ok(match descriptor {
+        TypeDescriptor::VarLenUnicode | TypeDescriptor::VarLenAscii => dataset
+            .read_raw::<VarLenUnicode>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        TypeDescriptor::FixedUnicode(n) | TypeDescriptor::FixedAscii(n) if n <= TEXT_LEN => dataset
+            .read_raw::<FixedUnicode<TEXT_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        _ => dataset
+            .read_raw::<FixedUnicode<PAYLOAD_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    })
+}
+
+/// SWMR calls that hdf5-rust does not wrap
+mod swmr {
+    use hdf5::{Dataset, File};
+    use hdf5_sys::{h5, h5d, h5f, h5p};
+    use std::ffi::{c_void, CString};
+    use std::path::Path;
+
+    fn c_path(path: &Path) -> hdf5::Result<CString> {
+        CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| hdf5::Error::from("file name contains a NUL byte"))
+    }
+
+    pub fn start_write(file: &File) -> hdf5::Result<()> {
+        if unsafe { h5f::H5Fstart_swmr_write(file.id()) } < 0 {
+            return Err("H5Fstart_swmr_write failed".into());
+        }
+        Ok(())
+    }
+
+    pub fn refresh(dataset: &Dataset) -> hdf5::Result<()> {
+        if unsafe { h5d::H5Drefresh(dataset.id()) } < 0 {
+            return Err("H5Drefresh failed".into());
+        }
+        Ok(())
+    }
+
+    pub fn open_read(path: &Path) -> hdf5::Result<File> {
+        let name = c_path(path)?;
+        let flags = h5f::H5F_ACC_RDONLY | h5f::H5F_ACC_SWMR_READ;
+        let id = unsafe { h5f::H5Fopen(name.as_ptr(), flags, h5p::H5P_DEFAULT) };
+        if id < 0 {
+            return Err(format!("H5Fopen failed for {}", path.display()).into());
+        }
+        hdf5::from_id(id)
+    }
+
+    /// Open read-write even if a crashed writer left the superblock marked
+    /// as in use, like `h5clear -s`
+    pub fn open_for_repair(path: &Path) -> hdf5::Result<File> {
+        let name = c_path(path)?;
+        let key = CString::new("clear_status_flags").unwrap();
+        let mut clear: h5::hbool_t = 1;
+        let id = unsafe {
+            if h5::H5open() < 0 {
+                return Err("H5open failed".into());
+            }
+            let fapl = h5p::H5Pcreate(*h5p::H5P_CLS_FILE_ACCESS);
+            if fapl < 0 {
+                return Err("H5Pcreate failed".into());
+            }
+            let id = if h5p::H5Pset(fapl, key.as_ptr(), &mut clear as *mut h5::hbool_t as *mut c_void) < 0 {
+                -1
+            } else {
+                h5f::H5Fopen(name.as_ptr(), h5f::H5F_ACC_RDWR, fapl)
+            };
+            h5p::H5Pclose(fapl);
+            id
+        };
+        if id < 0 {
+            return Err(format!("H5Fopen failed for {}", path.display()).into());
+        }
+        hdf5::from_id(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(i: usize) -> TimestampedSample {
+        let i16 = i as i16;
+        TimestampedSample {
+            timestamp: i as f64 / 100.0,
+            data: SensorData { accel_x: i16, accel_y: 0, accel_z: 16384, gyro_x: 0, gyro_y: 0, gyro_z: -i16 },
+        }
+    }
+
+    #[test]
+    fn time_lookup_spans_session_segments() {
+        let dir = std::env::temp_dir().join(format!("mpu-time-range-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let storage = StorageOptions { chunk_size: 16, ..StorageOptions::default() };
+        let mut writer = Hdf5Writer::create_session(dir.join("run.h5"), "fifo", 100.0, Rotation::default(), &storage).unwrap();
+        let samples: Vec<TimestampedSample> = (0..200).map(sample).collect();
+        writer.append_batch(&samples[..100]).unwrap();
+        writer.rotate().unwrap();
+        writer.append_batch(&samples[100..]).unwrap();
+        let manifest = writer.manifest_path().unwrap();
+        writer.close().unwrap();
+
+        let reader = Hdf5Reader::open(&manifest).unwrap();
+        let span = reader.time_span().unwrap();
+        let before = reader.index_at_time(-1.0).unwrap();
+        let across = reader.index_at_time(0.995).unwrap();
+        let after = reader.index_at_time(5.0).unwrap();
+        let window = reader.read_time_range(0.97, 1.02).unwrap();
+        let reversed = reader.read_time_range(1.5, 1.0).unwrap();
+        drop(reader);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(span, Some((0.0, 1.99)));
+        assert_eq!((before, across, after), (0, 100, 200));
+        let x: Vec<i16> = window.iter().map(|s| s.data.accel_x).collect();
+        assert_eq!(x, [97, 98, 99, 100, 101, 102]);
+        assert!(reversed.is_empty());
+    }
+
+    #[test]
+    fn swmr_flushes_keep_the_journal_until_a_checkpoint() {
+        use crate::journal::{journal_path, JournalContents};
+
+        let path = std::env::temp_dir().join(format!("mpu-flush-journal-{}.h5", std::process::id()));
+        let journaled = || JournalContents::read(journal_path(&path)).unwrap();
+        let mut writer = Hdf5Writer::create(&path, "fifo", 100.0).unwrap();
+        writer.enable_journal().unwrap();
+        writer.start_swmr().unwrap();
+        writer.append_batch(&(0..50).map(sample).collect::<Vec<_>>()).unwrap();
+        // Past the SWMR flush interval: the file is flushed, the journal kept
+        std::thread::sleep(SWMR_FLUSH_INTERVAL);
+        writer.append_batch(&(50..80).map(sample).collect::<Vec<_>>()).unwrap();
+        assert_eq!(journaled().samples.len(), 80);
+
+        writer.flush().unwrap();
+        let contents = journaled();
+        assert!(contents.samples.is_empty());
+        assert_eq!(contents.header.sample_base, 80);
+        writer.append_batch(&(80..100).map(sample).collect::<Vec<_>>()).unwrap();
+        writer.close().unwrap();
+        assert!(!journal_path(&path).exists());
+        let total = Hdf5Reader::open(&path).unwrap().get_total_samples().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(total, 100);
+    }
+
+    #[test]
+    fn following_a_session_continues_into_new_segments() {
+        let dir = std::env::temp_dir().join(format!("mpu-follow-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut writer = Hdf5Writer::create_session(dir.join("run.h5"), "fifo", 100.0, Rotation::default(), &StorageOptions::default()).unwrap();
+        writer.start_swmr().unwrap();
+        let samples: Vec<TimestampedSample> = (0..150).map(sample).collect();
+        writer.append_batch(&samples[..100]).unwrap();
+        writer.rotate().unwrap();
+        writer.append_batch(&samples[100..]).unwrap();
+        let manifest = writer.manifest_path().unwrap();
+        writer.close().unwrap();
+
+        // Start following while the manifest still lists the first segment only
+        let full = Manifest::load(&manifest).unwrap();
+        let mut first = full.clone();
+        first.segments.truncate(1);
+        first.save(&manifest).unwrap();
+        let mut reader = Hdf5Reader::open_swmr(&manifest).unwrap();
+        let before = reader.follow(0).unwrap();
+        full.save(&manifest).unwrap();
+        let after = reader.follow(before.len()).unwrap();
+        let listed = reader.manifest().map(|m| m.segments.len());
+        drop(reader);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before.len(), 100);
+        let x: Vec<i16> = after.iter().map(|s| s.data.accel_x).collect();
+        assert_eq!(x, (100..150).collect::<Vec<i16>>());
+        assert_eq!(listed, Some(2));
+    }
 }
@@ -0,0 +1,468 @@
+//! Write-ahead journal for crash-safe recording
+//!
+//! With [`Hdf5Writer::enable_journal`] every batch and gap is appended to a
+//! sidecar file, `<file>.journal`, before it goes into the HDF5 datasets.
+//! Each checkpoint (every [`Hdf5Writer::flush`] call, and every few seconds
+//! of SWMR writing) syncs the HDF5 file to disk and starts the journal over,
+//! so after a crash or power loss it holds exactly the data that may not
+//! have reached the file.
+//! [`recover`] truncates the file back to its last flush and replays the
+//! journal; if the file cannot be opened at all it is rebuilt from the
+//! journal alone. [`Hdf5Writer::close`] deletes the journal and marks the
+//! file complete (see [`Completion`]).
+//!
+//! Layout, little endian: the magic `MPUJRNL1`, a header (sample and gap
+//! counts at the last flush, start time, mode, rate), then records of
+//! `tag u8, length u32, payload, FNV-1a u32`. A torn record at the end is
+//! dropped when reading.
+
+use crate::hdf5_format::Completion;
+use crate::{Discontinuity, Hdf5Reader, Hdf5Writer, Mpu6050Error, Result, SensorData, TimestampedSample};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"MPUJRNL1";
+const TAG_SAMPLES: u8 = 1;
+const TAG_GAP: u8 = 2;
+/// Bytes per journaled sample: timestamp plus six i16 channels
+const SAMPLE_BYTES: usize = 8 + 6 * 2;
+
+/// Records are forced to disk at most this often; a process crash loses
+/// nothing, a power cut at most this much
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// State of the HDF5 file when the journal was (re)started
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalHeader {
+    /// Samples already flushed to the HDF5 file
+    pub sample_base: u64,
+    /// Discontinuities already flushed
+    pub gap_base: u64,
+    pub start_time: String,
+    pub acquisition_mode: String,
+    pub sample_rate_hz: f64,
+}
+
+/// `<file>.journal` next to an HDF5 file
+pub fn journal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+fn io_error(context: String) -> impl FnOnce(std::io::Error) -> Mpu6050Error {
+    move |source| Mpu6050Error::Io { context, source }
+}
+
+/// Open journal of a running [`Hdf5Writer`]
+pub(crate) struct Journal {
+    out: File,
+    path: PathBuf,
+    header: JournalHeader,
+    last_sync: Instant,
+}
+
+impl Journal {
+    pub(crate) fn create(path: PathBuf, header: JournalHeader) -> Result<Self> {
+        let out = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(io_error(format!("Cannot create journal {}", path.display())))?;
+
+        let mut journal = Journal { out, path, header, last_sync: Instant::now() };
+        journal.write_header()?;
+        Ok(journal)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend_from_slice(&self.header.sample_base.to_le_bytes());
+        buf.extend_from_slice(&self.header.gap_base.to_le_bytes());
+        put_str(&mut buf, &self.header.start_time);
+        put_str(&mut buf, &self.header.acquisition_mode);
+        buf.extend_from_slice(&self.header.sample_rate_hz.to_le_bytes());
+
+        self.out.write_all(&buf)
+            .and_then(|_| self.out.sync_data())
+            .map_err(io_error(format!("Failed to write journal {}", self.path.display())))?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn append(&mut self, tag: u8, payload: &[u8]) -> Result<()> {
+        // One write per record, so a crash can only tear the last one
+        let mut record = Vec::with_capacity(payload.len() + 9);
+        record.push(tag);
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        record.extend_from_slice(&fnv1a(payload).to_le_bytes());
+
+        self.out.write_all(&record)
+            .map_err(io_error(format!("Failed to append to journal {}", self.path.display())))?;
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.out.sync_data()
+                .map_err(io_error(format!("Failed to sync journal {}", self.path.display())))?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_samples(&mut self, samples: &[TimestampedSample]) -> Result<()> {
+        let mut payload = Vec::with_capacity(4 + samples.len() * SAMPLE_BYTES);
+        payload.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        for sample in samples {
+            let d = &sample.data;
+            payload.extend_from_slice(&sample.timestamp.to_le_bytes());
+            for value in [d.accel_x, d.accel_y, d.accel_z, d.gyro_x, d.gyro_y, d.gyro_z] {
+                payload.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        self.append(TAG_SAMPLES, &payload)
+    }
+
+    pub(crate) fn append_gap(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&start_time.to_le_bytes());
+        payload.extend_from_slice(&end_time.to_le_bytes());
+        put_str(&mut payload, cause);
+        self.append(TAG_GAP, &payload)
+    }
+
+    /// The HDF5 file has been synced: start over from its current counts
+    pub(crate) fn restart(&mut self, sample_base: usize, gap_base: usize) -> Result<()> {
+        self.header.sample_base = sample_base as u64;
+        self.header.gap_base = gap_base as u64;
+        self.out.set_len(0)
+            .and_then(|_| self.out.seek(SeekFrom::Start(0)))
+            .map_err(io_error(format!("Failed to reset journal {}", self.path.display())))?;
+        self.write_header()
+    }
+
+    /// Delete the journal after a clean close
+    pub(crate) fn remove(self) -> Result<()> {
+        let Journal { out, path, .. } = self;
+        drop(out);
+        std::fs::remove_file(&path).map_err(io_error(format!("Failed to delete journal {}", path.display())))
+    }
+}
+
+fn put_str(buf: &mut Vec<u8>, text: &str) {
+    let mut end = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let bytes = &text.as_bytes()[..end];
+    buf.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811C_9DC5u32, |hash, &b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Reads the little-endian fields of a journal
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+/// Everything a journal left behind by an interrupted recording holds
+#[derive(Debug, Clone)]
+pub struct JournalContents {
+    pub header: JournalHeader,
+    pub samples: Vec<TimestampedSample>,
+    pub discontinuities: Vec<Discontinuity>,
+    /// Bytes at the end that did not form a complete, valid record
+    pub torn_bytes: usize,
+}
+
+impl JournalContents {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(io_error(format!("Cannot read journal {}", path.display())))?;
+        Self::parse(&bytes).ok_or_else(|| {
+            Mpu6050Error::InvalidParameter(format!("{} is not a journal or its header is damaged", path.display()))
+        })
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor { buf: bytes, pos: 0 };
+        if cursor.take(MAGIC.len())? != MAGIC {
+            return None;
+        }
+        let header = JournalHeader {
+            sample_base: cursor.u64()?,
+            gap_base: cursor.u64()?,
+            start_time: cursor.string()?,
+            acquisition_mode: cursor.string()?,
+            sample_rate_hz: cursor.f64()?,
+        };
+
+        let mut contents = JournalContents { header, samples: Vec::new(), discontinuities: Vec::new(), torn_bytes: 0 };
+        while cursor.remaining() > 0 {
+            let start = cursor.pos;
+            if contents.parse_record(&mut cursor).is_none() {
+                contents.torn_bytes = bytes.len() - start;
+                break;
+            }
+        }
+        Some(contents)
+    }
+
+    fn parse_record(&mut self, cursor: &mut Cursor) -> Option<()> {
+        let tag = cursor.take(1)?[0];
+        let len = cursor.u32()? as usize;
+        let payload = cursor.take(len)?;
+        if cursor.u32()? != fnv1a(payload) {
+            return None;
+        }
+
+        let mut fields = Cursor { buf: payload, pos: 0 };
+        match tag {
+            TAG_SAMPLES => {
+                let count = fields.u32()? as usize;
+                if fields.remaining() != count * SAMPLE_BYTES {
+                    return None;
+                }
+                for _ in 0..count {
+                    let timestamp = fields.f64()?;
+                    let data = SensorData {
+                        accel_x: fields.i16()?,
+                        accel_y: fields.i16()?,
+                        accel_z: fields.i16()?,
+                        gyro_x: fields.i16()?,
+                        gyro_y: fields.i16()?,
+                        gyro_z: fields.i16()?,
+                    };
+                    self.samples.push(TimestampedSample { timestamp, data });
+                }
+            }
+            TAG_GAP => self.discontinuities.push(Discontinuity {
+                start_time: fields.f64()?,
+                end_time: fields.f64()?,
+                cause: fields.string()?,
+            }),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// What [`recover`] did to a file
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// The file was closed cleanly and left alone
+    pub already_complete: bool,
+    /// A journal was found and replayed
+    pub journal_found: bool,
+    /// The HDF5 file could not be opened; it was moved to `<file>.damaged`
+    /// and a new one written from the journal
+    pub rebuilt: bool,
+    /// Samples replayed from the journal
+    pub journal_samples: usize,
+    /// Discontinuities replayed from the journal
+    pub journal_discontinuities: usize,
+    /// Flushed samples that could not be recovered (all of them for a rebuild)
+    pub lost_samples: usize,
+    /// Incomplete record dropped from the end of the journal
+    pub torn_bytes: usize,
+    /// Samples in the repaired file
+    pub sample_count: usize,
+}
+
+/// Repair a file left behind by an interrupted recording
+///
+/// Files that were closed cleanly and have no journal are left untouched.
+/// Otherwise the datasets are cut back to the state of the last flush (or
+/// to the shortest column, without a journal), the journal is replayed and
+/// the file is marked [`Completion::Recovered`].
+pub fn recover<P: AsRef<Path>>(path: P) -> Result<RecoveryReport> {
+    let path = path.as_ref();
+    let journal_file = journal_path(path);
+    let journal = if journal_file.exists() { Some(JournalContents::read(&journal_file)?) } else { None };
+
+    let mut report = RecoveryReport::default();
+    if journal.is_none() {
+        if let Ok(reader) = Hdf5Reader::open(path) {
+            if reader.completion() == Completion::Complete {
+                report.already_complete = true;
+                report.sample_count = reader.get_total_samples()?;
+                return Ok(report);
+            }
+        }
+    }
+
+    let mut writer = match (Hdf5Writer::reopen(path), &journal) {
+        (Ok(writer), _) => writer,
+        (Err(e), None) => return Err(e),
+        (Err(_), Some(contents)) => {
+            let mut damaged = path.as_os_str().to_owned();
+            damaged.push(".damaged");
+            std::fs::rename(path, &damaged)
+                .map_err(io_error(format!("Cannot move damaged file {}", path.display())))?;
+            report.rebuilt = true;
+            Hdf5Writer::rebuild(path, &contents.header)?
+        }
+    };
+
+    match &journal {
+        Some(contents) => {
+            let header = &contents.header;
+            let samples = writer.sample_count().min(header.sample_base as usize);
+            let gaps = writer.discontinuity_count().min(header.gap_base as usize);
+            report.lost_samples += header.sample_base as usize - samples;
+            writer.truncate(samples, gaps)?;
+
+            writer.append_batch(&contents.samples)?;
+            for gap in &contents.discontinuities {
+                writer.write_discontinuity(gap.start_time, gap.end_time, &gap.cause)?;
+            }
+            report.journal_found = true;
+            report.journal_samples = contents.samples.len();
+            report.journal_discontinuities = contents.discontinuities.len();
+            report.torn_bytes = contents.torn_bytes;
+        }
+        None => writer.truncate(writer.sample_count(), writer.discontinuity_count())?,
+    }
+
+    report.sample_count = writer.sample_count();
+    writer.finish(Completion::Recovered)?;
+    if journal.is_some() {
+        std::fs::remove_file(&journal_file)
+            .map_err(io_error(format!("Failed to delete journal {}", journal_file.display())))?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> JournalHeader {
+        JournalHeader {
+            sample_base: 2048,
+            gap_base: 1,
+            start_time: "2026-10-18T09:00:00+02:00".to_string(),
+            acquisition_mode: "fifo".to_string(),
+            sample_rate_hz: 1000.0,
+        }
+    }
+
+    fn sample(i: i16) -> TimestampedSample {
+        TimestampedSample {
+            timestamp: i as f64 * 0.001,
+            data: SensorData { accel_x: i, accel_y: -i, accel_z: 16384, gyro_x: 1, gyro_y: 2, gyro_z: i * 3 },
+        }
+    }
+
+    fn write_journal(path: &Path) {
+        let mut journal = Journal::create(path.to_path_buf(), header()).unwrap();
+        journal.append_samples(&[sample(1), sample(2)]).unwrap();
+        journal.append_gap(0.5, 1.25, "USB reset").unwrap();
+        journal.append_samples(&[sample(3)]).unwrap();
+    }
+
+    #[test]
+    fn journal_round_trip() {
+        let path = std::env::temp_dir().join(format!("mpu-journal-{}.journal", std::process::id()));
+        write_journal(&path);
+        let contents = JournalContents::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents.header, header());
+        assert_eq!(contents.samples.len(), 3);
+        assert_eq!(contents.samples[2].data.gyro_z, 9);
+        assert_eq!(contents.samples[1].timestamp, 0.002);
+        assert_eq!(contents.discontinuities[0].cause, "USB reset");
+        assert_eq!(contents.torn_bytes, 0);
+    }
+
+    #[test]
+    fn torn_tail_is_dropped() {
+        let path = std::env::temp_dir().join(format!("mpu-journal-torn-{}.journal", std::process::id()));
+        write_journal(&path);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Cut into the last record, then corrupt the one before it
+        let cut = &bytes[..bytes.len() - 5];
+        let contents = JournalContents::parse(cut).unwrap();
+        assert_eq!(contents.samples.len(), 2);
+        assert_eq!(contents.discontinuities.len(), 1);
+        assert!(contents.torn_bytes > 0);
+
+        let mut flipped = cut.to_vec();
+        let gap_checksum = flipped.len() - contents.torn_bytes - 1;
+        flipped[gap_checksum] ^= 0xFF;
+        let contents = JournalContents::parse(&flipped).unwrap();
+        assert_eq!(contents.samples.len(), 2);
+        assert!(contents.discontinuities.is_empty());
+
+        assert!(JournalContents::parse(b"NOTAJRNL").is_none());
+    }
+
+    #[test]
+    fn rebuild_counts_flushed_samples_once() {
+        let path = std::env::temp_dir().join(format!("mpu-journal-rebuild-{}.h5", std::process::id()));
+        std::fs::write(&path, b"truncated by a power cut").unwrap();
+        write_journal(&journal_path(&path));
+
+        let report = recover(&path).unwrap();
+        let mut damaged = path.as_os_str().to_owned();
+        damaged.push(".damaged");
+        std::fs::remove_file(&damaged).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.rebuilt);
+        assert_eq!(report.lost_samples, 2048);
+        assert_eq!(report.sample_count, 3);
+        assert_eq!(report.journal_discontinuities, 1);
+        assert!(!journal_path(&path).exists());
+    }
+}
@@ -90,27 +90,51 @@
 //! // Collect 2048 samples for FFT analysis
 //! let samples = sensor.collect_samples_fifo(1000, 2048)?;
 //!
-//! // Or stream FIFO batches with callback (20ms intervals, ~17 samples/batch)
-//! sensor.stream_fifo(20, |batch| {
+//! // Or stream FIFO batches with callback; the read interval adapts to
+//! // keep the FIFO about half full (~40 samples/batch)
+//! let stats = sensor.stream_fifo_adaptive(|batch| {
 //!     println!("Received {} samples", batch.len());
 //!     // Process batch...
 //!     ft232_sensor_interface::StreamControl::Continue
 //! })?;
+//! println!("{} overflows", stats.overflows);
 //! # Ok::<(), ft232_sensor_interface::Mpu6050Error>(())
 //! ```
 //!
 //! **Note**: FIFO mode provides buffered high-speed sampling (~2.5x faster than
-//! direct polling). Samples are read in batches with 20-50ms latency. Use direct
+//! direct polling). Samples are read in batches with ~50ms latency. Use direct
 //! `stream()` for real-time applications requiring immediate response.
 
 pub mod error;
 mod ffi;
 pub mod mpu6050;
 pub mod hdf5_format;
+pub mod journal;
+pub mod events;
+pub mod export;
+pub mod import;
+pub mod overview;
+pub mod schema;
+pub mod session;
+pub mod storage;
+pub mod capture;
+pub mod config;
 pub mod common;
+pub mod recovery;
+pub mod gpio;
 
 // Re-export public API
 pub use error::{Mpu6050Error, Result};
 pub use mpu6050::{Mpu6050, SensorData, StreamControl};
-pub use hdf5_format::{Hdf5Reader, Hdf5Writer, Metadata, TimestampedSample};
-pub use common::{TimeKeeper, create_bar};
+pub use hdf5_format::{Completion, Discontinuity, Hdf5Reader, Hdf5Writer, Metadata, SampleChunks, TimestampedSample};
+pub use overview::OverviewBin;
+pub use events::{Event, EventSpan};
+pub use journal::{recover, RecoveryReport};
+pub use schema::{migrate, ChannelInfo, SCHEMA_VERSION};
+pub use session::{Manifest, Rotation, RotationInterval};
+pub use storage::{Compression, StorageOptions, TimestampFormat};
+pub use capture::{Band, Capture, CaptureEvent, TriggerConfig, TriggerSource};
+pub use config::{CollectionMode, CollectorConfig, ConfigFile, Profile, TriggerProfile};
+pub use common::{PollScheduler, PollStats, TimeKeeper, create_bar};
+pub use recovery::{Outage, RetryPolicy, StreamEvent};
+pub use gpio::{Edge, EdgeDetector, Gpio};
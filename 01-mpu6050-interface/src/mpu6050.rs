@@ -1,8 +1,13 @@
 //! MPU6050 sensor driver using FTDI MPSSE I2C interface
 
-use crate::error::{Mpu6050Error, Result};
+use crate::common::{PollScheduler, PollStats};
+use crate::error::{check_status, Mpu6050Error, Result, TransportOp};
+use crate::recovery::{self, Outage, RetryPolicy, StreamEvent};
 use crate::ffi::*;
+use crate::gpio::{Gpio, GpioState};
+use std::cell::RefCell;
 use std::ptr;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 // MPU6050 I2C addresses
@@ -131,9 +136,14 @@ impl SensorData {
 
 /// MPU6050 sensor interface
 pub struct Mpu6050 {
-    handle: FT_HANDLE,
+    handle: FT_HANDLE,   // Null after a failed reconnect
+    channel_index: u32,
     address: u8,
     fifo_enabled: bool,  // Track FIFO mode state
+    fifo_request_hz: u16, // Rate passed to enable_fifo(), restored on reconnect
+    fifo_rate_hz: f64,   // Configured FIFO sample rate (after divider rounding)
+    fifo_overflowed: bool, // INT_STATUS overflow bit seen by the last batch read
+    gpio: Rc<RefCell<GpioState>>, // ACBUS setup, shared with `Gpio` handles
 }
 
 impl Mpu6050 {
@@ -146,12 +156,31 @@ impl Mpu6050 {
     /// * `Ok(Mpu6050)` - Initialized sensor
     /// * `Err(Mpu6050Error)` - If initialization fails
     pub fn new(channel_index: u32) -> Result<Self> {
+        let handle = Self::open_channel(channel_index)?;
+
+        let mut sensor = Mpu6050 {
+            handle,
+            channel_index,
+            address: MPU6050_ADDRESS,
+            fifo_enabled: false,  // Start with FIFO disabled
+            fifo_request_hz: 0,
+            fifo_rate_hz: 0.0,
+            fifo_overflowed: false,
+            gpio: GpioState::new(handle),
+        };
+
+        // Initialize the sensor
+        sensor.init()?;
+
+        Ok(sensor)
+    }
+
+    /// Open and configure an I2C channel
+    fn open_channel(channel_index: u32) -> Result<FT_HANDLE> {
         // Check number of available channels
         let mut num_channels: DWORD = 0;
         let status = unsafe { I2C_GetNumChannels(&mut num_channels) };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Open, None)?;
 
         if num_channels == 0 {
             return Err(Mpu6050Error::NoChannelsFound);
@@ -164,9 +193,7 @@ impl Mpu6050 {
         // Open the channel
         let mut handle: FT_HANDLE = ptr::null_mut();
         let status = unsafe { I2C_OpenChannel(channel_index, &mut handle) };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Open, None)?;
 
         // Configure the channel
         let mut config = ChannelConfig {
@@ -178,21 +205,79 @@ impl Mpu6050 {
         };
 
         let status = unsafe { I2C_InitChannel(handle, &mut config) };
-        if status != FT_OK {
+        if let Err(e) = check_status(status, TransportOp::Configure, None) {
             unsafe { I2C_CloseChannel(handle) };
-            return Err(status.into());
+            return Err(e);
         }
 
-        let mut sensor = Mpu6050 {
-            handle,
-            address: MPU6050_ADDRESS,
-            fifo_enabled: false,  // Start with FIFO disabled
-        };
+        Ok(handle)
+    }
 
-        // Initialize the sensor
-        sensor.init()?;
+    /// Reopen the channel and bring the sensor back to its previous state
+    ///
+    /// Closes the current handle (which may already be dead), opens the
+    /// channel again, re-runs `init` and re-enables the FIFO at the rate
+    /// last passed to [`Mpu6050::enable_fifo`].
+    pub fn reconnect(&mut self) -> Result<()> {
+        if !self.handle.is_null() {
+            self.gpio.borrow_mut().detach();
+            unsafe { I2C_CloseChannel(self.handle) };
+            self.handle = ptr::null_mut();
+        }
 
-        Ok(sensor)
+        self.handle = Self::open_channel(self.channel_index)?;
+        self.gpio.borrow_mut().attach(self.handle)?;
+        self.init()?;
+
+        if self.fifo_enabled {
+            self.fifo_enabled = false;
+            self.enable_fifo(self.fifo_request_hz)?;
+        }
+
+        Ok(())
+    }
+
+    /// Spare ACBUS pins of this channel as digital I/O
+    pub fn gpio(&self) -> Gpio {
+        Gpio::new(self.gpio.clone())
+    }
+
+    /// Serial number of the FT232H, `None` if the driver does not report one
+    pub fn serial_number(&self) -> Option<String> {
+        let mut info: FT_DEVICE_LIST_INFO_NODE = unsafe { std::mem::zeroed() };
+        let status = unsafe { I2C_GetChannelInfo(self.channel_index, &mut info) };
+        if status != FT_OK {
+            return None;
+        }
+        let serial = &info.SerialNumber;
+        let len = serial.iter().position(|&b| b == 0).unwrap_or(serial.len());
+        let serial = String::from_utf8_lossy(&serial[..len]).trim().to_string();
+        (!serial.is_empty()).then_some(serial)
+    }
+
+    /// Run a sensor operation with retries and reconnects
+    ///
+    /// Transient USB errors are retried with backoff; if that fails, or the
+    /// adapter has disappeared, the channel is reopened via
+    /// [`Mpu6050::reconnect`] until it answers or `policy` gives up. The
+    /// returned [`Outage`] is set when a reconnect was needed.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ft232_sensor_interface::{Mpu6050, RetryPolicy};
+    ///
+    /// let mut sensor = Mpu6050::new(0)?;
+    /// let (data, outage) = sensor.with_recovery(&RetryPolicy::default(), |s| s.read_all())?;
+    /// if let Some(outage) = outage {
+    ///     println!("Reconnected after {:?}", outage.duration());
+    /// }
+    /// # Ok::<(), ft232_sensor_interface::Mpu6050Error>(())
+    /// ```
+    pub fn with_recovery<T, F>(&mut self, policy: &RetryPolicy, op: F) -> Result<(T, Option<Outage>)>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        recovery::recover(self, policy, op, |s| s.reconnect())
     }
 
     /// Initialize the MPU6050 sensor
@@ -242,9 +327,7 @@ impl Mpu6050 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Write, Some(reg))?;
 
         // Note: With FAST_TRANSFER_BYTES, transferred count is in bits, not bytes
         // Only check status per FTDI sample code pattern
@@ -271,9 +354,7 @@ impl Mpu6050 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         // Read the data
         let mut data = [0u8];
@@ -294,12 +375,11 @@ impl Mpu6050 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         if transferred != 1 {
             return Err(Mpu6050Error::TransferError {
+                register: Some(reg),
                 expected: 1,
                 actual: transferred,
             });
@@ -329,9 +409,7 @@ impl Mpu6050 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         // Read the data immediately (repeated START)
         let mut data = vec![0u8; count];
@@ -353,9 +431,7 @@ impl Mpu6050 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         // Note: When using FAST_TRANSFER_BYTES, the transferred count is in bits, not bytes
         // (e.g., 6 bytes = 48 bits). Based on FTDI sample code, we should only check status.
@@ -555,6 +631,52 @@ impl Mpu6050 {
         Ok(sample_count)
     }
 
+    /// Polling stream that survives USB errors and unplugging
+    ///
+    /// Like [`Mpu6050::stream`], with every read going through
+    /// [`Mpu6050::with_recovery`]. The callback sees
+    /// [`StreamEvent::Reconnected`] after a reconnect; the schedule then
+    /// restarts from the current time instead of trying to catch up.
+    pub fn stream_resilient<F>(&mut self, rate_hz: u32, policy: &RetryPolicy, mut callback: F) -> Result<u64>
+    where
+        F: FnMut(StreamEvent<'_, SensorData>) -> StreamControl,
+    {
+        if rate_hz == 0 || rate_hz > 1000 {
+            return Err(Mpu6050Error::InvalidParameter(format!(
+                "Sample rate must be between 1-1000 Hz, got {}",
+                rate_hz
+            )));
+        }
+
+        let interval = Duration::from_micros(1_000_000 / rate_hz as u64);
+        let mut sample_count = 0u64;
+        let mut next_sample_time = Instant::now();
+
+        loop {
+            let (data, outage) = self.with_recovery(policy, |s| s.read_all())?;
+
+            if let Some(outage) = &outage {
+                next_sample_time = Instant::now();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            }
+
+            sample_count += 1;
+            if callback(StreamEvent::Data(data)) == StreamControl::Break {
+                break;
+            }
+
+            next_sample_time += interval;
+            let now = Instant::now();
+            if next_sample_time > now {
+                std::thread::sleep(next_sample_time - now);
+            }
+        }
+
+        Ok(sample_count)
+    }
+
     /// Stream sensor data for a specified duration
     ///
     /// # Arguments
@@ -688,6 +810,8 @@ impl Mpu6050 {
         let gyro_rate = 1000u16;
         let divider = (gyro_rate / sample_rate_hz).saturating_sub(1);
         self.write_register(REG_SMPLRT_DIV, divider as u8)?;
+        self.fifo_request_hz = sample_rate_hz;
+        self.fifo_rate_hz = gyro_rate as f64 / (1 + divider) as f64;
 
         // Enable sensors to FIFO (but FIFO itself still disabled)
         self.write_register(REG_FIFO_EN, FIFO_EN_ALL_SENSORS)?;
@@ -832,7 +956,7 @@ impl Mpu6050 {
 
         // Check for overflow AFTER reading (overflow means some data was lost, but what we read is valid)
         // Reading INT_STATUS clears the overflow flag, no need to reset FIFO
-        let _ = self.read_register(REG_INT_STATUS)?;
+        self.fifo_overflowed = self.check_fifo_overflow()?;
 
         // Parse into SensorData structs
         Self::parse_fifo_data(&fifo_data)
@@ -921,6 +1045,102 @@ impl Mpu6050 {
         Ok(total_samples)
     }
 
+    /// Stream FIFO data, choosing the read interval automatically
+    ///
+    /// Like [`Mpu6050::stream_fifo`], but a [`PollScheduler`] aims each read at
+    /// half of the 85-frame FIFO, adapting to the measured fill rate and USB
+    /// latency instead of using a fixed interval.
+    ///
+    /// # Returns
+    /// * `Ok(PollStats)` - Read count, achieved fill, overflows and CPU time
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ft232_sensor_interface::{Mpu6050, StreamControl};
+    ///
+    /// let mut sensor = Mpu6050::new(0)?;
+    /// sensor.enable_fifo(1000)?;
+    ///
+    /// let mut count = 0;
+    /// let stats = sensor.stream_fifo_adaptive(|batch| {
+    ///     count += batch.len();
+    ///     if count >= 10000 { StreamControl::Break } else { StreamControl::Continue }
+    /// })?;
+    /// println!("{} reads, mean fill {:.0}%, {} overflows",
+    ///          stats.polls, stats.mean_fill * 100.0, stats.overflows);
+    /// # Ok::<(), ft232_sensor_interface::Mpu6050Error>(())
+    /// ```
+    pub fn stream_fifo_adaptive<F>(&mut self, callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        let scheduler = PollScheduler::new(FIFO_MAX_SAMPLES, self.fifo_rate_hz);
+        self.stream_fifo_scheduled(scheduler, callback)
+    }
+
+    /// Stream FIFO data with a caller-configured [`PollScheduler`]
+    pub fn stream_fifo_scheduled<F>(&mut self, mut scheduler: PollScheduler, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Mpu6050Error::FifoNotEnabled);
+        }
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let batch = self.read_fifo_batch()?;
+            scheduler.record(batch.len(), self.fifo_overflowed, read_start.elapsed());
+
+            if !batch.is_empty() && callback(&batch) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
+    /// Adaptive FIFO streaming that survives USB errors and unplugging
+    ///
+    /// Like [`Mpu6050::stream_fifo_adaptive`], but each read goes through
+    /// [`Mpu6050::with_recovery`]. After a reconnect the callback receives
+    /// [`StreamEvent::Reconnected`] before the next batch, so the gap can be
+    /// recorded.
+    pub fn stream_fifo_resilient<F>(&mut self, policy: &RetryPolicy, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(StreamEvent<'_, &[SensorData]>) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Mpu6050Error::FifoNotEnabled);
+        }
+
+        let mut scheduler = PollScheduler::new(FIFO_MAX_SAMPLES, self.fifo_rate_hz);
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let (batch, outage) = self.with_recovery(policy, |s| s.read_fifo_batch())?;
+
+            if let Some(outage) = &outage {
+                scheduler.resync();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            } else {
+                scheduler.record(batch.len(), self.fifo_overflowed, read_start.elapsed());
+            }
+
+            if !batch.is_empty() && callback(StreamEvent::Data(&batch)) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
     /// Collect samples using FIFO mode
     ///
     /// This is a convenience method that enables FIFO, collects the specified
@@ -957,11 +1177,7 @@ impl Mpu6050 {
 
         let mut samples = Vec::with_capacity(num_samples);
 
-        // Calculate appropriate batch interval
-        // At 1kHz, 50 samples = 50ms, read interval should be slightly longer
-        let batch_interval_ms = 50u64;
-
-        self.stream_fifo(batch_interval_ms, |batch| {
+        self.stream_fifo_adaptive(|batch| {
             samples.extend_from_slice(batch);
 
             if samples.len() >= num_samples {
@@ -980,9 +1196,15 @@ impl Mpu6050 {
 
 impl Drop for Mpu6050 {
     fn drop(&mut self) {
+        // Channel already closed by a reconnect that did not succeed
+        if self.handle.is_null() {
+            return;
+        }
+
         // Disable FIFO if it was enabled
         let _ = self.disable_fifo();
 
+        self.gpio.borrow_mut().detach();
         unsafe {
             I2C_CloseChannel(self.handle);
         }
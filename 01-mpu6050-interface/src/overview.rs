@@ -0,0 +1,374 @@
+//! Min/max/mean decimation pyramid of a recording
+//!
+//! While recording, [`Hdf5Writer`](crate::Hdf5Writer) folds every sample
+//! into one bin per level of [`FACTORS`] and appends completed bins to the
+//! `overview` group:
+//!
+//! ```text
+//! /overview
+//!     level_16, level_256, level_4096, level_65536
+//!         start_time, end_time   f64  timestamps of the first/last sample
+//!         count                  u32  samples in the bin
+//!         <channel>_min/_max     f64  raw counts, channels as in CHANNELS
+//!         <channel>_mean         f64
+//! ```
+//!
+//! Bin `k` of level `f` covers samples `k*f .. (k+1)*f` of its file; only
+//! the last bin of a closed file may hold fewer. Bins are written when the
+//! writer flushes, so a level can lag the samples by up to one bin plus a
+//! flush interval. [`Hdf5Reader::read_overview`](crate::Hdf5Reader::read_overview)
+//! fills such gaps, and files without an `overview` group, from the raw
+//! samples.
+
+use crate::hdf5_format::Hdf5Writer;
+use crate::{Mpu6050Error, Result, TimestampedSample};
+use hdf5::{Dataset, Group};
+
+/// Samples per bin of each level, finest first
+pub const FACTORS: [usize; 4] = [16, 256, 4096, 65536];
+
+/// Summarised `sensor_data` columns, the order of [`OverviewBin`] arrays
+pub const CHANNELS: [&str; 6] = ["accel_x", "accel_y", "accel_z", "gyro_x", "gyro_y", "gyro_z"];
+
+/// Summary of consecutive samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverviewBin {
+    /// Timestamp of the first sample
+    pub start_time: f64,
+    /// Timestamp of the last sample
+    pub end_time: f64,
+    /// Number of samples summarised
+    pub count: usize,
+    /// Smallest raw value per channel
+    pub min: [f64; CHANNELS.len()],
+    /// Largest raw value per channel
+    pub max: [f64; CHANNELS.len()],
+    /// Mean raw value per channel
+    pub mean: [f64; CHANNELS.len()],
+}
+
+impl OverviewBin {
+    /// Bin holding a single sample
+    pub fn from_sample(sample: &TimestampedSample) -> Self {
+        let d = &sample.data;
+        let values = [d.accel_x, d.accel_y, d.accel_z, d.gyro_x, d.gyro_y, d.gyro_z].map(f64::from);
+        OverviewBin {
+            start_time: sample.timestamp,
+            end_time: sample.timestamp,
+            count: 1,
+            min: values,
+            max: values,
+            mean: values,
+        }
+    }
+
+    /// Extend this bin by the (later) samples of `other`
+    pub fn merge(&mut self, other: &OverviewBin) {
+        let total = (self.count + other.count) as f64;
+        for i in 0..CHANNELS.len() {
+            self.min[i] = self.min[i].min(other.min[i]);
+            self.max[i] = self.max[i].max(other.max[i]);
+            self.mean[i] = (self.mean[i] * self.count as f64 + other.mean[i] * other.count as f64) / total;
+        }
+        self.end_time = other.end_time;
+        self.count += other.count;
+    }
+}
+
+/// Merges bins in time order into bins of at least `target` samples
+pub(crate) struct Rebinner {
+    target: usize,
+    current: Option<OverviewBin>,
+    bins: Vec<OverviewBin>,
+}
+
+impl Rebinner {
+    pub(crate) fn new(target: usize) -> Self {
+        Rebinner { target: target.max(1), current: None, bins: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, bin: OverviewBin) {
+        let current = match self.current.take() {
+            Some(mut current) => {
+                current.merge(&bin);
+                current
+            }
+            None => bin,
+        };
+        if current.count >= self.target {
+            self.bins.push(current);
+        } else {
+            self.current = Some(current);
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<OverviewBin> {
+        self.bins.extend(self.current.take());
+        self.bins
+    }
+}
+
+/// Datasets of one level
+pub(crate) struct Level {
+    pub(crate) factor: usize,
+    start_time: Dataset,
+    end_time: Dataset,
+    count: Dataset,
+    min: Vec<Dataset>,
+    max: Vec<Dataset>,
+    mean: Vec<Dataset>,
+}
+
+impl Level {
+    fn name(factor: usize) -> String {
+        format!("level_{}", factor)
+    }
+
+    fn create(overview: &Group, factor: usize) -> Result<Self> {
+        let group = overview.create_group(&Self::name(factor))
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to create overview level {}", factor), e))?;
+        let create = |name: &str| Hdf5Writer::create_dataset::<f64>(&group, name, 256);
+        let per_channel = |suffix: &str| -> Result<Vec<Dataset>> {
+            CHANNELS.iter().map(|c| create(&format!("{}_{}", c, suffix))).collect()
+        };
+        Ok(Level {
+            factor,
+            start_time: create("start_time")?,
+            end_time: create("end_time")?,
+            count: Hdf5Writer::create_dataset::<u32>(&group, "count", 256)?,
+            min: per_channel("min")?,
+            max: per_channel("max")?,
+            mean: per_channel("mean")?,
+        })
+    }
+
+    /// Open level `factor` of an `overview` group
+    pub(crate) fn open(overview: &Group, factor: usize) -> Result<Self> {
+        let group = overview.group(&Self::name(factor))
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open overview level {}", factor), e))?;
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open overview {}", name), e));
+        let per_channel = |suffix: &str| -> Result<Vec<Dataset>> {
+            CHANNELS.iter().map(|c| open(&format!("{}_{}", c, suffix))).collect()
+        };
+        Ok(Level {
+            factor,
+            start_time: open("start_time")?,
+            end_time: open("end_time")?,
+            count: open("count")?,
+            min: per_channel("min")?,
+            max: per_channel("max")?,
+            mean: per_channel("mean")?,
+        })
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Dataset> {
+        [&self.start_time, &self.end_time, &self.count].into_iter()
+            .chain(&self.min)
+            .chain(&self.max)
+            .chain(&self.mean)
+    }
+
+    /// Bins present in every dataset
+    pub(crate) fn len(&self) -> usize {
+        self.all().map(|d| d.size()).min().unwrap_or(0)
+    }
+
+    fn resize(&self, bins: usize) -> Result<()> {
+        for dataset in self.all() {
+            dataset.resize((bins,))
+                .map_err(|e| Mpu6050Error::storage("Failed to resize overview", e))?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, at: usize, bins: &[OverviewBin]) -> Result<()> {
+        let write = |dataset: &Dataset, values: Vec<f64>| {
+            dataset.write_slice(&values, at..)
+                .map_err(|e| Mpu6050Error::storage("Failed to write overview", e))
+        };
+        self.resize(at + bins.len())?;
+        write(&self.start_time, bins.iter().map(|b| b.start_time).collect())?;
+        write(&self.end_time, bins.iter().map(|b| b.end_time).collect())?;
+        let counts: Vec<u32> = bins.iter().map(|b| b.count as u32).collect();
+        self.count.write_slice(&counts, at..)
+            .map_err(|e| Mpu6050Error::storage("Failed to write overview", e))?;
+        for i in 0..CHANNELS.len() {
+            write(&self.min[i], bins.iter().map(|b| b.min[i]).collect())?;
+            write(&self.max[i], bins.iter().map(|b| b.max[i]).collect())?;
+            write(&self.mean[i], bins.iter().map(|b| b.mean[i]).collect())?;
+        }
+        Ok(())
+    }
+
+    /// Bins `from..to`
+    pub(crate) fn read(&self, from: usize, to: usize) -> Result<Vec<OverviewBin>> {
+        let read = |dataset: &Dataset| -> Result<Vec<f64>> {
+            Ok(dataset.read_slice_1d(from..to)
+                .map_err(|e| Mpu6050Error::storage("Failed to read overview", e))?
+                .to_vec())
+        };
+        let columns = |datasets: &[Dataset]| -> Result<Vec<Vec<f64>>> {
+            datasets.iter().map(read).collect()
+        };
+        let start_time = read(&self.start_time)?;
+        let end_time = read(&self.end_time)?;
+        let count: Vec<u32> = self.count.read_slice_1d(from..to)
+            .map_err(|e| Mpu6050Error::storage("Failed to read overview", e))?
+            .to_vec();
+        let (min, max, mean) = (columns(&self.min)?, columns(&self.max)?, columns(&self.mean)?);
+
+        Ok((0..to - from).map(|k| OverviewBin {
+            start_time: start_time[k],
+            end_time: end_time[k],
+            count: count[k] as usize,
+            min: std::array::from_fn(|i| min[i][k]),
+            max: std::array::from_fn(|i| max[i][k]),
+            mean: std::array::from_fn(|i| mean[i][k]),
+        }).collect())
+    }
+}
+
+/// Bins of one level being built from incoming samples
+#[derive(Default)]
+struct Accumulator {
+    pending: Option<OverviewBin>,
+    complete: Vec<OverviewBin>,
+}
+
+impl Accumulator {
+    fn push(&mut self, factor: usize, sample: &TimestampedSample) {
+        let bin = OverviewBin::from_sample(sample);
+        let pending = match self.pending.take() {
+            Some(mut pending) => {
+                pending.merge(&bin);
+                pending
+            }
+            None => bin,
+        };
+        if pending.count >= factor {
+            self.complete.push(pending);
+        } else {
+            self.pending = Some(pending);
+        }
+    }
+}
+
+/// Writer side of the pyramid, owned by [`Hdf5Writer`]
+pub(crate) struct OverviewWriter {
+    levels: Vec<(Level, Accumulator, usize)>,
+}
+
+impl OverviewWriter {
+    /// Create the `overview` group with empty levels under `parent`
+    pub(crate) fn create(parent: &Group) -> Result<Self> {
+        let group = parent.create_group("overview")
+            .map_err(|e| Mpu6050Error::storage("Failed to create overview group", e))?;
+        let levels = FACTORS.iter()
+            .map(|&factor| Ok((Level::create(&group, factor)?, Accumulator::default(), 0)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(OverviewWriter { levels })
+    }
+
+    /// Continue the pyramid of an existing file holding `samples` rows
+    ///
+    /// Levels are cut back to their complete bins; the samples after the
+    /// last one are fetched with `read(start, end)` to refill the open bin.
+    pub(crate) fn resume<F>(parent: &Group, samples: usize, read: F) -> Result<Self>
+    where
+        F: Fn(usize, usize) -> Result<Vec<TimestampedSample>>,
+    {
+        let group = parent.group("overview")
+            .map_err(|e| Mpu6050Error::storage("Failed to open overview group", e))?;
+        let mut levels = Vec::new();
+        for &factor in &FACTORS {
+            let level = Level::open(&group, factor)?;
+            let kept = level.len().min(samples / factor);
+            level.resize(kept)?;
+            let mut acc = Accumulator::default();
+            for sample in read(kept * factor, samples)? {
+                acc.push(factor, &sample);
+            }
+            levels.push((level, acc, kept));
+        }
+        Ok(OverviewWriter { levels })
+    }
+
+    pub(crate) fn push(&mut self, samples: &[TimestampedSample]) {
+        for (level, acc, _) in &mut self.levels {
+            for sample in samples {
+                acc.push(level.factor, sample);
+            }
+        }
+    }
+
+    /// Append the bins completed since the last call
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        for (level, acc, written) in &mut self.levels {
+            if !acc.complete.is_empty() {
+                level.append(*written, &acc.complete)?;
+                *written += acc.complete.len();
+                acc.complete.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Append everything, including the partly filled last bins
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        for (_, acc, _) in &mut self.levels {
+            acc.complete.extend(acc.pending.take());
+        }
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SensorData;
+
+    fn sample(i: usize) -> TimestampedSample {
+        let v = i as i16;
+        TimestampedSample {
+            timestamp: i as f64 * 0.001,
+            data: SensorData { accel_x: v, accel_y: -v, accel_z: 100, gyro_x: 0, gyro_y: v % 4, gyro_z: 1 },
+        }
+    }
+
+    #[test]
+    fn merge_keeps_extremes_and_weighted_mean() {
+        let mut bin = OverviewBin::from_sample(&sample(0));
+        for i in 1..4 {
+            bin.merge(&OverviewBin::from_sample(&sample(i)));
+        }
+        assert_eq!(bin.count, 4);
+        assert_eq!((bin.start_time, bin.end_time), (0.0, 0.003));
+        assert_eq!((bin.min[0], bin.max[0], bin.mean[0]), (0.0, 3.0, 1.5));
+        assert_eq!((bin.min[1], bin.max[1]), (-3.0, 0.0));
+        assert_eq!(bin.mean[2], 100.0);
+    }
+
+    #[test]
+    fn accumulator_completes_full_bins() {
+        let mut acc = Accumulator::default();
+        for i in 0..40 {
+            acc.push(16, &sample(i));
+        }
+        assert_eq!(acc.complete.len(), 2);
+        assert_eq!(acc.complete[1].start_time, 0.016);
+        assert_eq!(acc.complete[1].max[0], 31.0);
+        assert_eq!(acc.pending.as_ref().unwrap().count, 8);
+    }
+
+    #[test]
+    fn rebinner_reaches_target() {
+        let mut rebinner = Rebinner::new(10);
+        for i in 0..25 {
+            rebinner.push(OverviewBin::from_sample(&sample(i)));
+        }
+        let bins = rebinner.finish();
+        assert_eq!(bins.iter().map(|b| b.count).collect::<Vec<_>>(), vec![10, 10, 5]);
+        assert_eq!(bins[2].min[0], 20.0);
+    }
+}
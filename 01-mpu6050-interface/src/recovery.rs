@@ -0,0 +1,273 @@
+//! Retry and reconnect handling for long acquisitions
+//!
+//! A USB glitch shows up as an FTDI error from one register access. Instead of
+//! ending the run, [`recover`] retries transient errors with exponential
+//! backoff and, when the retries run out or the device has gone away,
+//! reopens the channel through the driver's `reconnect()` until it answers
+//! again. The time the device was unreachable is reported as an [`Outage`] so
+//! callers can record the gap.
+
+use crate::error::{Mpu6050Error, Result};
+use std::time::{Duration, Instant};
+
+/// How hard to try before giving up on the device
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries of a failed operation before the channel is reopened
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every further attempt
+    pub initial_backoff: Duration,
+    /// Upper bound for the retry/reconnect delay
+    pub max_backoff: Duration,
+    /// Give up reconnecting after this long (`None` = keep trying)
+    pub reconnect_timeout: Option<Duration>,
+}
+
+impl RetryPolicy {
+    /// Fail on the first error, as the plain streaming methods do
+    pub const NONE: RetryPolicy = RetryPolicy {
+        max_retries: 0,
+        initial_backoff: Duration::ZERO,
+        max_backoff: Duration::ZERO,
+        reconnect_timeout: Some(Duration::ZERO),
+    };
+
+    /// Delay before attempt number `attempt` (0-based)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.min(16);
+        (self.initial_backoff * factor).min(self.max_backoff)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            reconnect_timeout: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+/// Period during which the device could not be reached
+#[derive(Debug, Clone)]
+pub struct Outage {
+    /// When the first failing operation was seen
+    pub started: Instant,
+    /// When the device answered again after reconnecting
+    pub ended: Instant,
+    /// Reconnect attempts needed
+    pub reconnects: u32,
+    /// Error that started the outage
+    pub cause: String,
+}
+
+impl Outage {
+    pub fn duration(&self) -> Duration {
+        self.ended.duration_since(self.started)
+    }
+}
+
+/// Item passed to the callback of the resilient streaming methods
+#[derive(Debug)]
+pub enum StreamEvent<'a, T> {
+    /// New data
+    Data(T),
+    /// The device was reconnected; data between the two instants is missing
+    Reconnected(&'a Outage),
+}
+
+/// Run `op`, retrying and reconnecting according to `policy`
+///
+/// Returns the result of the first successful attempt, plus the outage if
+/// the channel had to be reopened to get it. Errors that are neither
+/// transient nor a disconnect are returned straight away unless an outage
+/// is already under way. Once reconnecting, every error is retried until
+/// `reconnect_timeout`: a replugged adapter can briefly answer with a wrong
+/// ID, fail a readback or fail init before it settles.
+pub(crate) fn recover<D, T, Op, Re>(
+    device: &mut D,
+    policy: &RetryPolicy,
+    mut op: Op,
+    mut reconnect: Re,
+) -> Result<(T, Option<Outage>)>
+where
+    Op: FnMut(&mut D) -> Result<T>,
+    Re: FnMut(&mut D) -> Result<()>,
+{
+    let mut retries = 0;
+    let mut first_failure: Option<Instant> = None;
+    let mut outage: Option<(Instant, String, u32)> = None;
+
+    loop {
+        let err = match op(device) {
+            Ok(value) => {
+                let outage = outage.map(|(started, cause, reconnects)| Outage {
+                    started,
+                    ended: Instant::now(),
+                    reconnects,
+                    cause,
+                });
+                return Ok((value, outage));
+            }
+            Err(e) if e.is_transient() || e.is_disconnected() || outage.is_some() => e,
+            Err(e) => return Err(e),
+        };
+        // The gap starts with the first failure, not after the in-place retries
+        let failed_at = *first_failure.get_or_insert_with(Instant::now);
+
+        if outage.is_none() && err.is_transient() && retries < policy.max_retries {
+            std::thread::sleep(policy.backoff(retries));
+            retries += 1;
+            continue;
+        }
+
+        let (started, cause, reconnects) =
+            outage.get_or_insert_with(|| (failed_at, err.to_string(), 0));
+
+        loop {
+            let elapsed = started.elapsed();
+            if policy.reconnect_timeout.is_some_and(|limit| elapsed >= limit) {
+                // Reconnecting disabled: report the original error unchanged
+                if *reconnects == 0 {
+                    return Err(err);
+                }
+                return Err(Mpu6050Error::ReconnectFailed {
+                    attempts: *reconnects,
+                    elapsed_secs: elapsed.as_secs_f64(),
+                    cause: cause.clone(),
+                });
+            }
+
+            std::thread::sleep(policy.backoff(*reconnects));
+            *reconnects += 1;
+
+            if reconnect(device).is_ok() {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{FtStatus, TransportOp};
+    use std::collections::VecDeque;
+
+    /// Device that answers from a script instead of the bus
+    #[derive(Default)]
+    struct Stub {
+        reads: VecDeque<Result<u8>>,
+        /// Reconnect outcomes; once used up the adapter stays unplugged
+        reconnects: VecDeque<Result<()>>,
+        read_at: Vec<Instant>,
+        reconnected_at: Vec<Instant>,
+    }
+
+    fn failure(status: FtStatus) -> Mpu6050Error {
+        Mpu6050Error::Transport { op: TransportOp::Read, register: Some(0x08), status }
+    }
+
+    fn policy(reconnect_timeout: Option<Duration>) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            reconnect_timeout,
+        }
+    }
+
+    fn run(stub: &mut Stub, policy: &RetryPolicy) -> Result<(u8, Option<Outage>)> {
+        recover(
+            stub,
+            policy,
+            |stub| {
+                stub.read_at.push(Instant::now());
+                stub.reads.pop_front().expect("read after the script ended")
+            },
+            |stub| {
+                stub.reconnected_at.push(Instant::now());
+                stub.reconnects.pop_front().unwrap_or_else(|| Err(failure(FtStatus::DeviceNotFound)))
+            },
+        )
+    }
+
+    #[test]
+    fn transient_errors_are_retried_in_place() {
+        let mut stub = Stub {
+            reads: VecDeque::from([
+                Err(failure(FtStatus::IoError)),
+                Err(Mpu6050Error::TransferError { register: Some(0x08), expected: 1, actual: 0 }),
+                Ok(7),
+            ]),
+            ..Stub::default()
+        };
+        let (value, outage) = run(&mut stub, &policy(Some(Duration::from_secs(1)))).unwrap();
+        assert_eq!(value, 7);
+        assert!(outage.is_none());
+        assert_eq!(stub.read_at.len(), 3);
+        assert!(stub.reconnected_at.is_empty());
+    }
+
+    #[test]
+    fn disconnect_reconnects_and_reports_the_outage() {
+        // A retried hiccup, then the adapter is unplugged: the first reconnect
+        // fails, the second opens a handle that is not ready yet
+        let mut stub = Stub {
+            reads: VecDeque::from([
+                Err(failure(FtStatus::IoError)),
+                Err(failure(FtStatus::DeviceNotFound)),
+                Err(failure(FtStatus::InvalidHandle)),
+                Ok(9),
+            ]),
+            reconnects: VecDeque::from([Err(failure(FtStatus::DeviceNotFound)), Ok(()), Ok(())]),
+            ..Stub::default()
+        };
+        let (value, outage) = run(&mut stub, &policy(Some(Duration::from_secs(1)))).unwrap();
+        let outage = outage.expect("the channel was reopened");
+        assert_eq!(value, 9);
+        assert_eq!(outage.reconnects, 3);
+        assert_eq!(outage.cause, failure(FtStatus::DeviceNotFound).to_string());
+
+        // The gap runs from the first failed read to the read that succeeded
+        assert_eq!(stub.read_at.len(), 4);
+        assert!(stub.read_at[0] <= outage.started && outage.started <= stub.read_at[1]);
+        assert!(stub.reconnected_at[2] <= outage.ended && stub.read_at[3] <= outage.ended);
+        assert!(outage.duration() >= stub.read_at[3].duration_since(stub.read_at[1]));
+    }
+
+    #[test]
+    fn reconnect_timeout_gives_up() {
+        let mut stub = Stub { reads: VecDeque::from([Err(failure(FtStatus::DeviceNotFound))]), ..Stub::default() };
+        match run(&mut stub, &policy(Some(Duration::from_millis(20)))) {
+            Err(Mpu6050Error::ReconnectFailed { attempts, elapsed_secs, cause }) => {
+                assert_eq!(attempts as usize, stub.reconnected_at.len());
+                assert!(attempts >= 1);
+                assert!(elapsed_secs >= 0.02);
+                assert_eq!(cause, failure(FtStatus::DeviceNotFound).to_string());
+            }
+            other => panic!("expected ReconnectFailed, got {:?}", other),
+        }
+        assert_eq!(stub.read_at.len(), 1);
+
+        // With reconnecting disabled the original error comes back unchanged
+        let mut stub = Stub { reads: VecDeque::from([Err(failure(FtStatus::DeviceNotFound))]), ..Stub::default() };
+        let err = run(&mut stub, &RetryPolicy::NONE).unwrap_err();
+        assert_eq!(err.status(), Some(FtStatus::DeviceNotFound));
+        assert!(stub.reconnected_at.is_empty());
+    }
+
+    #[test]
+    fn other_errors_are_returned_before_an_outage() {
+        let mut stub = Stub {
+            reads: VecDeque::from([Err(Mpu6050Error::InvalidParameter("odr".to_string())), Ok(1)]),
+            ..Stub::default()
+        };
+        let err = run(&mut stub, &policy(None)).unwrap_err();
+        assert!(matches!(err, Mpu6050Error::InvalidParameter(_)));
+        assert_eq!(stub.read_at.len(), 1);
+        assert!(stub.reconnected_at.is_empty());
+    }
+}
@@ -0,0 +1,217 @@
+//! Layout of the HDF5 recordings
+//!
+//! Schema version 2 (`metadata/version = "2.2"`):
+//!
+//! ```text
+//! /metadata                  attributes
+//!     version          str   "2.2"
+//!     start_time       str   RFC 3339 wall-clock time of t = 0
+//!     sample_rate_hz   f64   requested sample rate
+//!     acquisition_mode str   "polling", "fifo" or "import"
+//!     host             str   machine that made the recording (if known)
+//!     crate_version    str   writing library, e.g. "ft232_sensor_interface 0.1.0"
+//!     device_serial    str   serial number of the FT232H (if known)
+//!     sample_count     u64   set when the file is closed or recovered
+//!     completion       str   "complete" or "recovered"
+//!     source_*               imported files only, see crate::import
+//! /sensor_data               one row per sample, 1-D chunked datasets
+//!     timestamps       f64   s, since start_time (i64 ticks with
+//!                            StorageOptions::timestamps, see crate::storage)
+//!     accel_x/y/z      i16   16384 LSB/g, +/-2 g
+//!     gyro_x/y/z       i16   131 LSB/(deg/s), +/-250 deg/s
+//! /discontinuities           start_time, end_time (f64, s), cause (str, 256 bytes)
+//! /overview                  min/max/mean pyramid, see crate::overview
+//! /events                    time, kind, label, payload; see crate::events
+//! ```
+//!
+//! Each `sensor_data` column describes itself with the dataset attributes
+//! of [`ChannelInfo`] (`units`, `scale_factor`, `offset`, `full_scale`,
+//! `axis`), so the physical value is `raw * scale_factor + offset` without
+//! knowing the sensor. Axes are those printed on the MPU6050 package:
+//! right-handed, +Z out of the top face; gyro axes follow the right-hand rule.
+//!
+//! 2.1 added the optional `overview` group. Files without it (2.0, or
+//! migrated from 1.0) are plotted from the raw samples. 2.2 added the
+//! optional `events` group. Minor versions only add, so [`migrate`] leaves
+//! 2.x files alone. Text columns that grow during a recording are
+//! fixed-length so they can be appended under SWMR; older files stored
+//! them variable-length and read the same.
+//!
+//! Version 1.0 files have the same groups and columns, but neither the
+//! column attributes nor `host`, `crate_version` and `device_serial`.
+//! [`Hdf5Reader::channel_info`](crate::Hdf5Reader::channel_info) falls back
+//! to the fixed scaling above for them, and [`migrate`] upgrades a file in
+//! place.
+
+use crate::hdf5_format::set_attr;
+use crate::{Mpu6050Error, Result};
+use hdf5::{Dataset, File};
+use std::path::Path;
+
+/// Schema written by this version of the library
+pub const SCHEMA_VERSION: &str = "2.2";
+
+/// Library name and version stored as `crate_version`
+pub(crate) const CRATE_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// How to read one `sensor_data` column, stored as attributes on its dataset
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    /// Unit of the converted value ("s", "g", "deg/s")
+    pub units: String,
+    /// Units per raw count
+    pub scale_factor: f64,
+    /// Added after scaling
+    pub offset: f64,
+    /// Largest magnitude the sensor can report, in `units` (0 for timestamps)
+    pub full_scale: f64,
+    /// Measured axis and its sign, e.g. "+X"; empty for non-axis columns
+    pub axis: String,
+}
+
+impl ChannelInfo {
+    fn new(units: &str, scale_factor: f64, full_scale: f64, axis: &str) -> Self {
+        ChannelInfo {
+            units: units.to_string(),
+            scale_factor,
+            offset: 0.0,
+            full_scale,
+            axis: axis.to_string(),
+        }
+    }
+
+    /// Convert a raw value of this column to `units`
+    pub fn to_physical(&self, raw: f64) -> f64 {
+        raw * self.scale_factor + self.offset
+    }
+
+    pub(crate) fn write(&self, dataset: &Dataset) -> Result<()> {
+        let units: hdf5::types::VarLenUnicode = self.units.parse().unwrap();
+        let axis: hdf5::types::VarLenUnicode = self.axis.parse().unwrap();
+        set_attr(dataset, "units", &units)?;
+        set_attr(dataset, "scale_factor", &self.scale_factor)?;
+        set_attr(dataset, "offset", &self.offset)?;
+        set_attr(dataset, "full_scale", &self.full_scale)?;
+        set_attr(dataset, "axis", &axis)
+    }
+
+    /// Attributes of a version 2 column, `None` if they are missing
+    pub(crate) fn read(dataset: &Dataset) -> Option<Self> {
+        let read_str = |name: &str| -> Option<String> {
+            dataset.attr(name).ok()?
+                .read_scalar::<hdf5::types::VarLenUnicode>().ok()
+                .map(|s| s.to_string())
+        };
+        let read_f64 = |name: &str| -> Option<f64> {
+            dataset.attr(name).ok()?.read_scalar::<f64>().ok()
+        };
+        Some(ChannelInfo {
+            units: read_str("units")?,
+            scale_factor: read_f64("scale_factor")?,
+            offset: read_f64("offset").unwrap_or(0.0),
+            full_scale: read_f64("full_scale").unwrap_or(0.0),
+            axis: read_str("axis").unwrap_or_default(),
+        })
+    }
+}
+
+/// Descriptions of all `sensor_data` columns
+///
+/// The sensor is always configured for +/-2 g and +/-250 deg/s, so these
+/// hold for version 1 files as well.
+pub fn channels() -> Vec<(&'static str, ChannelInfo)> {
+    const ACCEL: f64 = 1.0 / 16384.0;
+    const GYRO: f64 = 1.0 / 131.0;
+    vec![
+        ("timestamps", ChannelInfo::new("s", 1.0, 0.0, "")),
+        ("accel_x", ChannelInfo::new("g", ACCEL, 2.0, "+X")),
+        ("accel_y", ChannelInfo::new("g", ACCEL, 2.0, "+Y")),
+        ("accel_z", ChannelInfo::new("g", ACCEL, 2.0, "+Z")),
+        ("gyro_x", ChannelInfo::new("deg/s", GYRO, 250.0, "+X")),
+        ("gyro_y", ChannelInfo::new("deg/s", GYRO, 250.0, "+Y")),
+        ("gyro_z", ChannelInfo::new("deg/s", GYRO, 250.0, "+Z")),
+    ]
+}
+
+/// Description of column `name`, if there is such a column
+pub fn channel(name: &str) -> Option<ChannelInfo> {
+    channels().into_iter().find(|(n, _)| *n == name).map(|(_, info)| info)
+}
+
+/// Major version of a `version` attribute ("1.0" -> 1)
+pub fn major_version(version: &str) -> u32 {
+    version.split('.').next().and_then(|v| v.trim().parse().ok()).unwrap_or(1)
+}
+
+/// Name of this machine, for the `host` attribute
+pub(crate) fn host_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Upgrade a file written with an older schema to [`SCHEMA_VERSION`]
+///
+/// Adds the column attributes, records the old version as `migrated_from`
+/// and the library doing the upgrade as `migrated_by`. Returns `false`
+/// without touching the file if it is already current. `host` and
+/// `device_serial` stay unset: nothing in a version 1 file tells them.
+pub fn migrate<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let file = File::open_rw(path.as_ref())
+        .map_err(|e| Mpu6050Error::storage("Failed to open HDF5 file for migration", e))?;
+    let metadata = file.group("metadata")
+        .map_err(|e| Mpu6050Error::storage("Failed to open metadata group", e))?;
+    let version = metadata.attr("version")
+        .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+        .map(|v| v.to_string())
+        .map_err(|e| Mpu6050Error::storage("Failed to read version", e))?;
+    if major_version(&version) >= major_version(SCHEMA_VERSION) {
+        return Ok(false);
+    }
+
+    let data_group = file.group("sensor_data")
+        .map_err(|e| Mpu6050Error::storage("Failed to open sensor_data group", e))?;
+    for (name, info) in channels() {
+        let dataset = data_group.dataset(name)
+            .map_err(|e| Mpu6050Error::storage(format!("Failed to open {} dataset", name), e))?;
+        info.write(&dataset)?;
+    }
+
+    let text = |value: &str| -> hdf5::types::VarLenUnicode { value.parse().unwrap() };
+    set_attr(&metadata, "migrated_from", &text(&version))?;
+    set_attr(&metadata, "migrated_by", &text(CRATE_VERSION))?;
+    set_attr(&metadata, "version", &text(SCHEMA_VERSION))?;
+    file.close()
+        .map_err(|e| Mpu6050Error::storage("Failed to close HDF5 file", e))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_scale_to_physical_units() {
+        let accel = channel("accel_z").unwrap();
+        assert_eq!(accel.units, "g");
+        assert_eq!(accel.to_physical(16384.0), 1.0);
+        assert_eq!(accel.axis, "+Z");
+
+        let gyro = channel("gyro_x").unwrap();
+        assert!((gyro.to_physical(-131.0) + 1.0).abs() < 1e-12);
+        assert_eq!(gyro.full_scale, 250.0);
+
+        assert_eq!(channel("timestamps").unwrap().to_physical(1.5), 1.5);
+        assert!(channel("temperature").is_none());
+    }
+
+    #[test]
+    fn version_parsing() {
+        assert_eq!(major_version("1.0"), 1);
+        assert_eq!(major_version(SCHEMA_VERSION), 2);
+        assert_eq!(major_version("garbage"), 1);
+    }
+}
@@ -0,0 +1,347 @@
+//! Recording sessions split over several HDF5 files
+//!
+//! [`Hdf5Writer::create_session`] writes `data_0001.h5`, `data_0002.h5`, ...
+//! instead of one `data.h5`, starting a new segment when the [`Rotation`]
+//! limits are reached. Every segment is a complete file of its own (same
+//! metadata, `start_time` of the session, timestamps continuing across
+//! segments). `data.manifest` lists the segments with their time ranges
+//! and sample counts; it is rewritten at each rotation and on close.
+//! [`Hdf5Reader::open`](crate::Hdf5Reader::open) accepts the manifest and
+//! presents all segments as one recording.
+//!
+//! [`Hdf5Writer::create_session`]: crate::Hdf5Writer::create_session
+
+use crate::{Mpu6050Error, Result, TimestampedSample};
+use chrono::Timelike;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the size of the current segment is checked
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When to start the next segment by time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationInterval {
+    /// At the top of every hour (local time)
+    Hourly,
+    /// At local midnight
+    Daily,
+    /// A fixed length after the segment was started
+    Every(Duration),
+}
+
+impl RotationInterval {
+    /// "hourly", "daily", or a length such as "900", "900s", "15m", "6h"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        match text.as_str() {
+            "hourly" => return Some(RotationInterval::Hourly),
+            "daily" => return Some(RotationInterval::Daily),
+            _ => {}
+        }
+        let (number, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+            Some((i, _)) => text.split_at(i),
+            None => (text.as_str(), "s"),
+        };
+        let scale = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            _ => return None,
+        };
+        match number.parse::<u64>() {
+            Ok(n) if n > 0 => Some(RotationInterval::Every(Duration::from_secs(n * scale))),
+            _ => None,
+        }
+    }
+
+    /// Time from now until a segment started now should end
+    fn remaining(&self) -> Duration {
+        let into_day = chrono::Local::now().num_seconds_from_midnight() as u64;
+        match self {
+            RotationInterval::Hourly => Duration::from_secs(3600 - into_day % 3600),
+            RotationInterval::Daily => Duration::from_secs(86_400u64.saturating_sub(into_day).max(1)),
+            RotationInterval::Every(length) => *length,
+        }
+    }
+}
+
+/// Same form as [`RotationInterval::parse`] takes, e.g. "daily" or "15m"
+impl fmt::Display for RotationInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationInterval::Hourly => write!(f, "hourly"),
+            RotationInterval::Daily => write!(f, "daily"),
+            RotationInterval::Every(length) => match length.as_secs() {
+                secs if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+                secs if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+                secs => write!(f, "{}s", secs),
+            },
+        }
+    }
+}
+
+/// Limits that end a segment; whichever is reached first counts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rotation {
+    pub interval: Option<RotationInterval>,
+    /// Segment file size in bytes
+    pub max_bytes: Option<u64>,
+}
+
+impl Rotation {
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// One file of a session, as listed in the manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentInfo {
+    /// File name, relative to the manifest
+    pub file: String,
+    /// Wall-clock time the segment was started (RFC 3339)
+    pub start_time: String,
+    /// First and last sample timestamp (seconds since session start),
+    /// NaN while the segment has no samples
+    pub first_timestamp: f64,
+    pub last_timestamp: f64,
+    pub samples: u64,
+}
+
+/// Segment list of a session, stored as `<name>.manifest`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    /// Start of the session, the `start_time` of every segment
+    pub start_time: String,
+    pub segments: Vec<SegmentInfo>,
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Mpu6050Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+        let malformed = |line: &str| Mpu6050Error::InvalidParameter(format!("Malformed line in {}: {}", path.display(), line));
+
+        let mut manifest = Manifest::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| malformed(line))?;
+            match key.trim() {
+                "start_time" => manifest.start_time = value.trim().to_string(),
+                "segment" => {
+                    let fields: Vec<&str> = value.split('|').map(str::trim).collect();
+                    let [file, start_time, first, last, samples] = fields[..] else {
+                        return Err(malformed(line));
+                    };
+                    manifest.segments.push(SegmentInfo {
+                        file: file.to_string(),
+                        start_time: start_time.to_string(),
+                        first_timestamp: first.parse().map_err(|_| malformed(line))?,
+                        last_timestamp: last.parse().map_err(|_| malformed(line))?,
+                        samples: samples.parse().map_err(|_| malformed(line))?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        if manifest.segments.is_empty() {
+            return Err(Mpu6050Error::InvalidParameter(format!("{} lists no segments", path.display())));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = format!("# MPU6050 recording session\nstart_time = {}\n", self.start_time);
+        text.push_str("# segment = file | started | first timestamp | last timestamp | samples\n");
+        for s in &self.segments {
+            text.push_str(&format!(
+                "segment = {} | {} | {:.6} | {:.6} | {}\n",
+                s.file, s.start_time, s.first_timestamp, s.last_timestamp, s.samples
+            ));
+        }
+        // Written next to the old one and renamed, so a reader never sees half a manifest
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| Mpu6050Error::Io { context: format!("Failed to write {}", path.display()), source: e })
+    }
+
+    /// Segment files, resolved against the directory of the manifest
+    pub fn segment_paths<P: AsRef<Path>>(&self, manifest_path: P) -> Vec<PathBuf> {
+        let dir = manifest_path.as_ref().parent().unwrap_or(Path::new(""));
+        self.segments.iter().map(|s| dir.join(&s.file)).collect()
+    }
+}
+
+/// `data.h5` -> `data.manifest`
+pub fn manifest_path<P: AsRef<Path>>(output: P) -> PathBuf {
+    output.as_ref().with_extension("manifest")
+}
+
+/// `data.h5`, 3 -> `data_0003.h5`
+pub fn segment_path<P: AsRef<Path>>(output: P, index: usize) -> PathBuf {
+    let output = output.as_ref();
+    let stem = output.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let ext = output.extension().map(|e| e.to_string_lossy()).unwrap_or("h5".into());
+    output.with_file_name(format!("{}_{:04}.{}", stem, index, ext))
+}
+
+/// Whether `path` names a session manifest rather than an HDF5 file
+pub fn is_manifest<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "manifest")
+}
+
+/// Rotation state of a writer created with `Hdf5Writer::create_session`
+pub(crate) struct Session {
+    output: PathBuf,
+    rotation: Rotation,
+    manifest: Manifest,
+    deadline: Option<Instant>,
+    next_size_check: Instant,
+    /// Samples, discontinuities and events in the segments before the current one
+    pub(crate) samples_before: usize,
+    pub(crate) gaps_before: usize,
+    pub(crate) events_before: usize,
+}
+
+impl Session {
+    pub(crate) fn new(output: &Path, rotation: Rotation) -> Self {
+        Session {
+            output: output.to_path_buf(),
+            rotation,
+            manifest: Manifest::default(),
+            deadline: None,
+            next_size_check: Instant::now(),
+            samples_before: 0,
+            gaps_before: 0,
+            events_before: 0,
+        }
+    }
+
+    pub(crate) fn set_start_time(&mut self, start_time: &str) {
+        self.manifest.start_time = start_time.to_string();
+    }
+
+    /// Add the next segment to the manifest and return its path
+    pub(crate) fn begin_segment(&mut self) -> PathBuf {
+        let path = segment_path(&self.output, self.manifest.segments.len() + 1);
+        self.manifest.segments.push(SegmentInfo {
+            file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            start_time: chrono::Local::now().to_rfc3339(),
+            first_timestamp: f64::NAN,
+            last_timestamp: f64::NAN,
+            samples: 0,
+        });
+        self.deadline = self.rotation.interval.map(|interval| Instant::now() + interval.remaining());
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        path
+    }
+
+    /// Account for samples appended to the current segment
+    pub(crate) fn observe(&mut self, samples: &[TimestampedSample]) {
+        let (Some(segment), Some(first), Some(last)) = (self.manifest.segments.last_mut(), samples.first(), samples.last()) else {
+            return;
+        };
+        if segment.samples == 0 {
+            segment.first_timestamp = first.timestamp;
+        }
+        segment.last_timestamp = last.timestamp;
+        segment.samples += samples.len() as u64;
+    }
+
+    /// Whether the segment at `path` has reached a rotation limit
+    pub(crate) fn due(&mut self, path: &Path) -> bool {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+        let Some(max_bytes) = self.rotation.max_bytes else {
+            return false;
+        };
+        if Instant::now() < self.next_size_check {
+            return false;
+        }
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        std::fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes)
+    }
+
+    pub(crate) fn save_manifest(&self) -> Result<()> {
+        self.manifest.save(manifest_path(&self.output))
+    }
+
+    pub(crate) fn manifest_path(&self) -> PathBuf {
+        manifest_path(&self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_parsing() {
+        assert_eq!(RotationInterval::parse("hourly"), Some(RotationInterval::Hourly));
+        assert_eq!(RotationInterval::parse("Daily"), Some(RotationInterval::Daily));
+        assert_eq!(RotationInterval::parse("900"), Some(RotationInterval::Every(Duration::from_secs(900))));
+        assert_eq!(RotationInterval::parse("15m"), Some(RotationInterval::Every(Duration::from_secs(900))));
+        assert_eq!(RotationInterval::parse("6h"), Some(RotationInterval::Every(Duration::from_secs(21_600))));
+        assert_eq!(RotationInterval::parse("0"), None);
+        assert_eq!(RotationInterval::parse("10d"), None);
+        assert_eq!(RotationInterval::parse("weekly"), None);
+        for text in ["hourly", "daily", "45s", "15m", "6h"] {
+            assert_eq!(RotationInterval::parse(text).unwrap().to_string(), text);
+        }
+
+        assert!(RotationInterval::Hourly.remaining() <= Duration::from_secs(3600));
+        assert!(RotationInterval::Daily.remaining() <= Duration::from_secs(86_400));
+    }
+
+    #[test]
+    fn segment_names() {
+        assert_eq!(segment_path("logs/data.h5", 3), PathBuf::from("logs/data_0003.h5"));
+        assert_eq!(segment_path("run", 12), PathBuf::from("run_0012.h5"));
+        assert_eq!(manifest_path("logs/data.h5"), PathBuf::from("logs/data.manifest"));
+        assert!(is_manifest("logs/data.manifest"));
+        assert!(!is_manifest("logs/data_0001.h5"));
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let manifest = Manifest {
+            start_time: "2026-10-18T09:00:00+02:00".to_string(),
+            segments: vec![
+                SegmentInfo {
+                    file: "data_0001.h5".to_string(),
+                    start_time: "2026-10-18T09:00:00+02:00".to_string(),
+                    first_timestamp: 0.0,
+                    last_timestamp: 3599.999,
+                    samples: 3_600_000,
+                },
+                SegmentInfo {
+                    file: "data_0002.h5".to_string(),
+                    start_time: "2026-10-18T10:00:00+02:00".to_string(),
+                    first_timestamp: f64::NAN,
+                    last_timestamp: f64::NAN,
+                    samples: 0,
+                },
+            ],
+        };
+        let path = std::env::temp_dir().join(format!("mpu-session-{}.manifest", std::process::id()));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.start_time, manifest.start_time);
+        assert_eq!(loaded.segments[0], manifest.segments[0]);
+        assert_eq!(loaded.segments[1].file, "data_0002.h5");
+        assert!(loaded.segments[1].first_timestamp.is_nan());
+        assert_eq!(loaded.segment_paths(&path)[1], std::env::temp_dir().join("data_0002.h5"));
+    }
+}
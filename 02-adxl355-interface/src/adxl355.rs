@@ -1,8 +1,14 @@
 //! ADXL355 sensor driver using FTDI MPSSE I2C interface
 
-use crate::error::{Adxl355Error, Result};
+use crate::common::{PollScheduler, PollStats};
+use crate::error::{check_status, Adxl355Error, Result, TransportOp};
+use crate::recovery::{self, Outage, RetryPolicy, StreamEvent};
 use crate::ffi::*;
+use crate::gpio::{Gpio, GpioState};
+use std::cell::RefCell;
+use std::path::Path;
 use std::ptr;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 // ADXL355 I2C addresses
@@ -11,10 +17,8 @@ const ADXL355_ADDRESS_HIGH: u8 = 0x53; // ASEL pin high
 
 // Device identification registers
 const REG_DEVID_AD: u8 = 0x00;    // Analog Devices ID (expect 0xAD)
-#[allow(dead_code)]
 const REG_DEVID_MST: u8 = 0x01;   // MEMS device ID (expect 0x1D)
-const REG_PARTID: u8 = 0x02;      // Part ID (expect 0xED for ADXL355)
-#[allow(dead_code)]
+const REG_PARTID: u8 = 0x02;      // Part ID (expect 0xED for ADXL355/ADXL357)
 const REG_REVID: u8 = 0x03;       // Silicon revision
 
 // Status and FIFO
@@ -51,13 +55,16 @@ const REG_FIFO_DATA: u8 = 0x11;   // FIFO read port
 
 // Configuration registers
 const REG_FILTER: u8 = 0x28;      // ODR and HPF settings
+const REG_SYNC: u8 = 0x2B;        // External sync / clock selection
 const REG_RANGE: u8 = 0x2C;       // Measurement range
 const REG_POWER_CTL: u8 = 0x2D;   // Power control
+const REG_SELF_TEST: u8 = 0x2E;   // Self-test (ST1, ST2)
 const REG_RESET: u8 = 0x2F;       // Software reset (write 0x52)
 
 // Expected device ID values
 const DEVID_AD_VALUE: u8 = 0xAD;  // Analog Devices
-const PARTID_VALUE: u8 = 0xED;    // ADXL355
+const DEVID_MST_VALUE: u8 = 0x1D; // Analog Devices MEMS
+const PARTID_VALUE: u8 = 0xED;    // ADXL355 and ADXL357
 
 // Status register bits
 #[allow(dead_code)]
@@ -67,27 +74,119 @@ const STATUS_FIFO_FULL: u8 = 0x02;    // FIFO full
 #[allow(dead_code)]
 const STATUS_FIFO_OVR: u8 = 0x04;     // FIFO overrun
 
+// Range register bits
+const RANGE_I2C_HS: u8 = 0x80;        // High-speed I2C mode (3.4 MHz)
+
 // Power control bits
 const POWER_CTL_STANDBY: u8 = 0x01;   // Standby mode
 
+// Self-test bits
+const SELF_TEST_ST1: u8 = 0x01;       // Enable self-test mode
+const SELF_TEST_ST2: u8 = 0x02;       // Apply electrostatic force
+
 // Reset command
 const RESET_CODE: u8 = 0x52;
 
 // FIFO constants
-#[allow(dead_code)]
 const FIFO_SAMPLE_SIZE: usize = 9;    // 3 bytes per axis × 3 axes (no temperature)
-#[allow(dead_code)]
 const FIFO_MAX_SAMPLES: usize = 96;   // Maximum FIFO depth
 
+/// Supported part in the ADXL35x family
+///
+/// The ADXL357 is register-compatible with the ADXL355 and reports the same
+/// PARTID (0xED), so the two cannot be told apart over the bus and `init`
+/// does not detect the ADXL357: it assumes ADXL355, and an ADXL357 has to
+/// be declared with [`Adxl355::set_variant`], or its +/-10/20/40g ranges
+/// are labelled and scaled as +/-2/4/8g. The collector therefore requires
+/// `--variant`. The ADXL354 has an analog output only and cannot be used
+/// with this driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceVariant {
+    /// +/-2/4/8g
+    Adxl355,
+    /// +/-10/20/40g
+    Adxl357,
+}
+
+impl DeviceVariant {
+    /// Family member reported for a PARTID value, if it is a supported part
+    ///
+    /// Never returns [`DeviceVariant::Adxl357`]: it shares PARTID 0xED with
+    /// the ADXL355.
+    pub fn detect(partid: u8) -> Option<Self> {
+        match partid {
+            PARTID_VALUE => Some(DeviceVariant::Adxl355),
+            _ => None,
+        }
+    }
+
+    /// Part name as printed on the package
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceVariant::Adxl355 => "ADXL355",
+            DeviceVariant::Adxl357 => "ADXL357",
+        }
+    }
+
+    /// Parse a part name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "adxl355" => Some(DeviceVariant::Adxl355),
+            "adxl357" => Some(DeviceVariant::Adxl357),
+            _ => None,
+        }
+    }
+
+    /// Ranges available on this part, smallest first
+    pub fn ranges(&self) -> [Range; 3] {
+        match self {
+            DeviceVariant::Adxl355 => [Range::G2, Range::G4, Range::G8],
+            DeviceVariant::Adxl357 => [Range::G10, Range::G20, Range::G40],
+        }
+    }
+
+    /// Range selected after reset (RANGE bits = 01)
+    pub fn default_range(&self) -> Range {
+        self.ranges()[0]
+    }
+
+    /// Datasheet self-test output change limits in g (min, max) for X, Y, Z
+    pub fn self_test_limits_g(&self) -> [(f32, f32); 3] {
+        match self {
+            DeviceVariant::Adxl355 => [(0.1, 0.6), (0.1, 0.6), (0.5, 3.0)],
+            DeviceVariant::Adxl357 => [(0.08, 0.5), (0.08, 0.5), (0.4, 2.5)],
+        }
+    }
+}
+
+/// Identification registers read during init
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub variant: DeviceVariant,
+    pub devid_ad: u8,
+    pub devid_mst: u8,
+    pub partid: u8,
+    pub revid: u8,
+}
+
 /// Measurement range
+///
+/// G2/G4/G8 apply to the ADXL355, G10/G20/G40 to the ADXL357. Both parts use
+/// the same RANGE register codes; only the scale factor differs.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Range {
-    /// +/-2g (default), 256000 LSB/g, 3.9 ug/LSB
-    G2 = 0x01,
-    /// +/-4g, 128000 LSB/g, 7.8 ug/LSB
-    G4 = 0x02,
-    /// +/-8g, 64000 LSB/g, 15.6 ug/LSB
-    G8 = 0x03,
+    /// +/-2g, 256000 LSB/g
+    G2,
+    /// +/-4g, 128000 LSB/g
+    G4,
+    /// +/-8g, 64000 LSB/g
+    G8,
+    /// +/-10g (ADXL357), 51200 LSB/g
+    G10,
+    /// +/-20g (ADXL357), 25600 LSB/g
+    G20,
+    /// +/-40g (ADXL357), 12800 LSB/g
+    G40,
 }
 
 impl Range {
@@ -97,6 +196,54 @@ impl Range {
             Range::G2 => 256_000.0,
             Range::G4 => 128_000.0,
             Range::G8 => 64_000.0,
+            Range::G10 => 51_200.0,
+            Range::G20 => 25_600.0,
+            Range::G40 => 12_800.0,
+        }
+    }
+
+    /// Full-scale input in g for this range
+    pub fn full_scale_g(&self) -> f32 {
+        // 20-bit two's complement: +/-2^19 LSB
+        524_288.0 / self.scale_factor()
+    }
+
+    /// RANGE register bits [1:0]
+    pub fn register_bits(&self) -> u8 {
+        match self {
+            Range::G2 | Range::G10 => 0x01,
+            Range::G4 | Range::G20 => 0x02,
+            Range::G8 | Range::G40 => 0x03,
+        }
+    }
+
+    /// Range on `variant` with the same register code as this one
+    pub fn for_variant(&self, variant: DeviceVariant) -> Range {
+        variant.ranges()[(self.register_bits() - 1) as usize]
+    }
+
+    /// Label used in HDF5 metadata ("2g", "10g", ...)
+    pub fn label(&self) -> &'static str {
+        match self {
+            Range::G2 => "2g",
+            Range::G4 => "4g",
+            Range::G8 => "8g",
+            Range::G10 => "10g",
+            Range::G20 => "20g",
+            Range::G40 => "40g",
+        }
+    }
+
+    /// Parse a label produced by [`Range::label`]
+    pub fn from_label(label: &str) -> Option<Range> {
+        match label {
+            "2g" => Some(Range::G2),
+            "4g" => Some(Range::G4),
+            "8g" => Some(Range::G8),
+            "10g" => Some(Range::G10),
+            "20g" => Some(Range::G20),
+            "40g" => Some(Range::G40),
+            _ => None,
         }
     }
 }
@@ -147,6 +294,134 @@ impl OutputDataRate {
     }
 }
 
+/// External synchronization mode (SYNC register)
+///
+/// In the external sync modes the DRDY pin is an input: every pulse on it
+/// starts a conversion. In the external clock modes the internal oscillator
+/// is replaced by a 1.024 MHz clock on INT2. Several sensors wired to the
+/// same sync line and clock sample coherently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Internal clock, free-running at the ODR (default)
+    Internal,
+    /// External sync on DRDY, no interpolation filter
+    ExternalSync,
+    /// External sync on DRDY with interpolation filter
+    ExternalSyncInterpolated,
+    /// External clock on INT2, internal sync
+    ExternalClock,
+    /// External clock on INT2 and external sync on DRDY
+    ExternalClockAndSync,
+    /// External clock on INT2 and external sync on DRDY with interpolation filter
+    ExternalClockAndSyncInterpolated,
+}
+
+impl SyncMode {
+    /// SYNC register value (EXT_CLK bit 2, EXT_SYNC bits [1:0])
+    fn register_value(&self) -> u8 {
+        match self {
+            SyncMode::Internal => 0x00,
+            SyncMode::ExternalSync => 0x01,
+            SyncMode::ExternalSyncInterpolated => 0x02,
+            SyncMode::ExternalClock => 0x04,
+            SyncMode::ExternalClockAndSync => 0x05,
+            SyncMode::ExternalClockAndSyncInterpolated => 0x06,
+        }
+    }
+
+    /// Decode a SYNC register value
+    fn from_register(value: u8) -> Option<Self> {
+        match value & 0x07 {
+            0x00 => Some(SyncMode::Internal),
+            0x01 => Some(SyncMode::ExternalSync),
+            0x02 => Some(SyncMode::ExternalSyncInterpolated),
+            0x04 => Some(SyncMode::ExternalClock),
+            0x05 => Some(SyncMode::ExternalClockAndSync),
+            0x06 => Some(SyncMode::ExternalClockAndSyncInterpolated),
+            _ => None,
+        }
+    }
+
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncMode::Internal => "internal",
+            SyncMode::ExternalSync => "ext-sync",
+            SyncMode::ExternalSyncInterpolated => "ext-sync-interp",
+            SyncMode::ExternalClock => "ext-clk",
+            SyncMode::ExternalClockAndSync => "ext-clk-sync",
+            SyncMode::ExternalClockAndSyncInterpolated => "ext-clk-sync-interp",
+        }
+    }
+
+    /// Parse a name produced by [`SyncMode::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "internal" => Some(SyncMode::Internal),
+            "ext-sync" => Some(SyncMode::ExternalSync),
+            "ext-sync-interp" => Some(SyncMode::ExternalSyncInterpolated),
+            "ext-clk" => Some(SyncMode::ExternalClock),
+            "ext-clk-sync" => Some(SyncMode::ExternalClockAndSync),
+            "ext-clk-sync-interp" => Some(SyncMode::ExternalClockAndSyncInterpolated),
+            _ => None,
+        }
+    }
+}
+
+/// How often FIFO reads refresh the temperature by default
+const DEFAULT_TEMPERATURE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Outputs above this fraction of full scale are treated as saturated
+const SELF_TEST_SATURATION: f32 = 0.95;
+
+/// Result of [`Adxl355::self_test`]
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    /// Range the test ran in
+    pub range: Range,
+    /// Samples averaged per phase
+    pub samples: usize,
+    /// Mean output with the self-test force off, in g
+    pub off_g: [f32; 3],
+    /// Mean output with the self-test force on, in g
+    pub on_g: [f32; 3],
+    /// Output change (on - off) in g
+    pub delta_g: [f32; 3],
+    /// Accepted (min, max) change per axis in g for the part variant
+    pub limits_g: [(f32, f32); 3],
+    /// Axes whose off or on output was at or near full scale. A saturated
+    /// axis cannot show the full self-test response and always fails.
+    pub axis_saturated: [bool; 3],
+    /// Per-axis pass/fail
+    pub axis_passed: [bool; 3],
+}
+
+impl SelfTestReport {
+    /// Judge the mean outputs of a test run at `range` against the limits
+    /// of `variant`
+    fn evaluate(range: Range, variant: DeviceVariant, samples: usize, off_g: [f32; 3], on_g: [f32; 3]) -> Self {
+        let saturation_g = range.full_scale_g() * SELF_TEST_SATURATION;
+        let limits_g = variant.self_test_limits_g();
+
+        let mut delta_g = [0.0f32; 3];
+        let mut axis_saturated = [false; 3];
+        let mut axis_passed = [false; 3];
+        for axis in 0..3 {
+            delta_g[axis] = on_g[axis] - off_g[axis];
+            let (min, max) = limits_g[axis];
+            axis_saturated[axis] = off_g[axis].abs() >= saturation_g || on_g[axis].abs() >= saturation_g;
+            axis_passed[axis] = !axis_saturated[axis] && delta_g[axis] >= min && delta_g[axis] <= max;
+        }
+
+        SelfTestReport { range, samples, off_g, on_g, delta_g, limits_g, axis_saturated, axis_passed }
+    }
+
+    /// True when every axis is within limits
+    pub fn passed(&self) -> bool {
+        self.axis_passed.iter().all(|&p| p)
+    }
+}
+
 /// Control flow for streaming operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StreamControl {
@@ -156,6 +431,161 @@ pub enum StreamControl {
     Break,
 }
 
+/// Temperature sensor calibration: raw = intercept + slope * (T - 25 C)
+///
+/// The datasheet nominal is 1885 LSB at 25 C and -9.05 LSB/C, with roughly
+/// 10% part-to-part spread on the slope. A two-point calibration against a
+/// reference thermometer brings this down to the sensor's own noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureCalibration {
+    /// Raw reading at 25 C (LSB)
+    pub intercept_lsb: f32,
+    /// Sensitivity (LSB/C)
+    pub slope_lsb_per_c: f32,
+}
+
+impl TemperatureCalibration {
+    /// Datasheet nominal values
+    pub const NOMINAL: TemperatureCalibration = TemperatureCalibration {
+        intercept_lsb: 1885.0,
+        slope_lsb_per_c: -9.05,
+    };
+
+    /// Build a calibration from two (raw, reference C) points
+    pub fn from_two_points(raw1: u16, temp1_c: f32, raw2: u16, temp2_c: f32) -> Result<Self> {
+        if (temp2_c - temp1_c).abs() < 1.0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Calibration points must be at least 1 C apart".to_string()
+            ));
+        }
+        let slope = (raw2 as f32 - raw1 as f32) / (temp2_c - temp1_c);
+        if slope == 0.0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Calibration points have identical raw readings".to_string()
+            ));
+        }
+        Ok(TemperatureCalibration {
+            intercept_lsb: raw1 as f32 + slope * (25.0 - temp1_c),
+            slope_lsb_per_c: slope,
+        })
+    }
+
+    /// Convert a raw 12-bit reading to Celsius
+    pub fn to_celsius(&self, raw: u16) -> f32 {
+        ((raw as f32 - self.intercept_lsb) / self.slope_lsb_per_c) + 25.0
+    }
+
+    /// Load from a `key = value` text file (`intercept_lsb`, `slope_lsb_per_c`)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+
+        let mut intercept = None;
+        let mut slope = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| Adxl355Error::InvalidParameter(format!("Malformed line in {}: {}", path.display(), line)))?;
+            let value: f32 = value.trim().parse()
+                .map_err(|_| Adxl355Error::InvalidParameter(format!("Invalid number in {}: {}", path.display(), line)))?;
+            match key.trim() {
+                "intercept_lsb" => intercept = Some(value),
+                "slope_lsb_per_c" => slope = Some(value),
+                _ => {}
+            }
+        }
+
+        match (intercept, slope) {
+            (Some(intercept_lsb), Some(slope_lsb_per_c)) if slope_lsb_per_c != 0.0 => {
+                Ok(TemperatureCalibration { intercept_lsb, slope_lsb_per_c })
+            }
+            _ => Err(Adxl355Error::InvalidParameter(format!(
+                "{} must define intercept_lsb and a non-zero slope_lsb_per_c", path.display()
+            ))),
+        }
+    }
+
+    /// Save as a `key = value` text file readable by [`TemperatureCalibration::load`]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = format!(
+            "# ADXL355 temperature calibration\nintercept_lsb = {}\nslope_lsb_per_c = {}\n",
+            self.intercept_lsb, self.slope_lsb_per_c
+        );
+        std::fs::write(path, text)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to write {}", path.display()), source: e })
+    }
+}
+
+impl Default for TemperatureCalibration {
+    fn default() -> Self {
+        Self::NOMINAL
+    }
+}
+
+/// I2C bus speed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum I2cSpeed {
+    /// 100 kHz
+    Standard,
+    /// 400 kHz
+    Fast,
+    /// 1 MHz (default, reliable on breadboards)
+    FastPlus,
+    /// 3.4 MHz, needs short wiring and strong pull-ups
+    HighSpeed,
+}
+
+impl I2cSpeed {
+    /// SCL clock rate in Hz
+    pub fn clock_hz(&self) -> u32 {
+        match self {
+            I2cSpeed::Standard => I2C_CLOCK_STANDARD_MODE,
+            I2cSpeed::Fast => I2C_CLOCK_FAST_MODE,
+            I2cSpeed::FastPlus => I2C_CLOCK_FAST_MODE_PLUS,
+            I2cSpeed::HighSpeed => I2C_CLOCK_HIGH_SPEED_MODE,
+        }
+    }
+
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            I2cSpeed::Standard => "standard",
+            I2cSpeed::Fast => "fast",
+            I2cSpeed::FastPlus => "fast-plus",
+            I2cSpeed::HighSpeed => "high-speed",
+        }
+    }
+
+    /// Parse a name produced by [`I2cSpeed::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(I2cSpeed::Standard),
+            "fast" => Some(I2cSpeed::Fast),
+            "fast-plus" => Some(I2cSpeed::FastPlus),
+            "high-speed" => Some(I2cSpeed::HighSpeed),
+            _ => None,
+        }
+    }
+}
+
+/// Result of [`Adxl355::check_bandwidth`]
+#[derive(Debug, Clone)]
+pub struct BandwidthReport {
+    /// Bus speed that was measured
+    pub speed: I2cSpeed,
+    /// Mean time for one FIFO batch (entry count read + 32-sample burst)
+    pub batch_time: Duration,
+    /// Samples per second the bus sustains, with a 20% safety margin
+    pub sustainable_hz: f64,
+    /// Fastest ODR preset at or below `sustainable_hz`
+    pub max_odr: Option<OutputDataRate>,
+}
+
 /// Sensor data structure containing accelerometer and temperature readings
 #[derive(Debug, Clone, Copy)]
 pub struct SensorData {
@@ -185,7 +615,12 @@ impl SensorData {
     /// Note: The temperature sensor is uncalibrated (~10% tolerance on slope).
     /// Useful for drift compensation, not absolute measurement.
     pub fn temperature_c(&self) -> f32 {
-        ((self.temperature as f32 - 1885.0) / -9.05) + 25.0
+        TemperatureCalibration::NOMINAL.to_celsius(self.temperature)
+    }
+
+    /// Convert raw temperature to Celsius using a per-device calibration
+    pub fn temperature_c_calibrated(&self, cal: &TemperatureCalibration) -> f32 {
+        cal.to_celsius(self.temperature)
     }
 }
 
@@ -205,30 +640,55 @@ fn parse_20bit(high: u8, mid: u8, low: u8) -> i32 {
 
 /// ADXL355 sensor interface
 pub struct Adxl355 {
-    handle: FT_HANDLE,
+    handle: FT_HANDLE, // Null after a failed reconnect
+    channel_index: u32,
     address: u8,
+    speed: I2cSpeed,
     range: Range,
+    info: Option<DeviceInfo>,
+    odr: OutputDataRate,
+    sync_mode: SyncMode,
     fifo_enabled: bool,
+    temp_calibration: TemperatureCalibration,
+    // FIFO holds no temperature, so it is polled periodically and carried forward
+    last_temperature: u16,
+    last_temperature_read: Option<Instant>,
+    temperature_interval: Duration,
+    // ACBUS setup, shared with `Gpio` handles
+    gpio: Rc<RefCell<GpioState>>,
 }
 
 impl Adxl355 {
     /// Create a new ADXL355 instance, auto-detecting the I2C address
     ///
     /// Tries 0x1D first, then 0x53. Use `with_address()` to skip detection.
-    pub fn new(channel_index: u32) -> Result<Self> {
+    /// Probing runs at `speed`, except that high-speed mode probes at
+    /// fast-mode-plus and switches to 3.4 MHz once the sensor is found.
+    pub fn new(channel_index: u32, speed: I2cSpeed) -> Result<Self> {
         // Open channel first, then probe both addresses
-        let handle = Self::open_channel(channel_index)?;
+        let handle = Self::open_channel(channel_index, Self::setup_speed(speed))?;
 
         for &address in &[ADXL355_ADDRESS_LOW, ADXL355_ADDRESS_HIGH] {
             let mut sensor = Adxl355 {
                 handle,
+                channel_index,
                 address,
+                speed,
                 range: Range::G2,
+                info: None,
+                odr: OutputDataRate::Odr1000,
+                sync_mode: SyncMode::Internal,
                 fifo_enabled: false,
+                temp_calibration: TemperatureCalibration::NOMINAL,
+                last_temperature: 0,
+                last_temperature_read: None,
+                temperature_interval: DEFAULT_TEMPERATURE_INTERVAL,
+                gpio: GpioState::new(handle),
             };
 
             match sensor.init() {
                 Ok(()) => {
+                    sensor.apply_bus_speed()?;
                     return Ok(sensor);
                 }
                 Err(_) => {
@@ -243,13 +703,13 @@ impl Adxl355 {
 
         // Neither address worked — close the channel and report
         unsafe { I2C_CloseChannel(handle) };
-        Err(Adxl355Error::CommunicationError(
+        Err(Adxl355Error::UnexpectedResponse(
             "ADXL355 not found at 0x1D or 0x53. Check wiring and VDDIO.".to_string()
         ))
     }
 
     /// Create a new ADXL355 instance with a specific I2C address
-    pub fn with_address(channel_index: u32, address: u8) -> Result<Self> {
+    pub fn with_address(channel_index: u32, address: u8, speed: I2cSpeed) -> Result<Self> {
         if address != ADXL355_ADDRESS_LOW && address != ADXL355_ADDRESS_HIGH {
             return Err(Adxl355Error::InvalidParameter(format!(
                 "Invalid I2C address: 0x{:02X}. Must be 0x1D or 0x53",
@@ -257,27 +717,101 @@ impl Adxl355 {
             )));
         }
 
-        let handle = Self::open_channel(channel_index)?;
+        let handle = Self::open_channel(channel_index, Self::setup_speed(speed))?;
 
         let mut sensor = Adxl355 {
             handle,
+            channel_index,
             address,
+            speed,
             range: Range::G2,
+            info: None,
+            odr: OutputDataRate::Odr1000,
+            sync_mode: SyncMode::Internal,
             fifo_enabled: false,
+            temp_calibration: TemperatureCalibration::NOMINAL,
+            last_temperature: 0,
+            last_temperature_read: None,
+            temperature_interval: DEFAULT_TEMPERATURE_INTERVAL,
+            gpio: GpioState::new(handle),
         };
 
         sensor.init()?;
+        sensor.apply_bus_speed()?;
 
         Ok(sensor)
     }
 
+    /// Reopen the channel and bring the sensor back to its previous state
+    ///
+    /// Closes the current handle (which may already be dead), opens the
+    /// channel again at the same address, re-runs `init` and the bus speed
+    /// setup, then restores range, ODR, sync mode and FIFO streaming.
+    pub fn reconnect(&mut self) -> Result<()> {
+        let (range, odr, sync_mode, fifo) = (self.range, self.odr, self.sync_mode, self.fifo_enabled);
+
+        if !self.handle.is_null() {
+            self.gpio.borrow_mut().detach();
+            unsafe { I2C_CloseChannel(self.handle) };
+            self.handle = ptr::null_mut();
+        }
+
+        self.handle = Self::open_channel(self.channel_index, Self::setup_speed(self.speed))?;
+        self.gpio.borrow_mut().attach(self.handle)?;
+        self.fifo_enabled = false;
+        self.init()?;
+        self.apply_bus_speed()?;
+
+        self.restore_configuration(range, odr, sync_mode, fifo)
+    }
+
+    /// Spare ACBUS pins of this channel as digital I/O
+    pub fn gpio(&self) -> Gpio {
+        Gpio::new(self.gpio.clone())
+    }
+
+    /// Run a sensor operation with retries and reconnects
+    ///
+    /// Transient USB errors are retried with backoff; if that fails, or the
+    /// adapter has disappeared, the channel is reopened via
+    /// [`Adxl355::reconnect`] until it answers or `policy` gives up. The
+    /// returned [`Outage`] is set when a reconnect was needed.
+    pub fn with_recovery<T, F>(&mut self, policy: &RetryPolicy, op: F) -> Result<(T, Option<Outage>)>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        recovery::recover(self, policy, op, |s| s.reconnect())
+    }
+
+    /// Re-apply range, ODR, sync mode and FIFO state after `init`
+    fn restore_configuration(&mut self, range: Range, odr: OutputDataRate, sync_mode: SyncMode, fifo: bool) -> Result<()> {
+        self.set_range(range)?;
+        self.set_odr(odr)?;
+        if sync_mode != SyncMode::Internal {
+            self.set_sync_mode(sync_mode)?;
+        }
+        if fifo {
+            self.enable_fifo(odr)?;
+        }
+        Ok(())
+    }
+
+    /// Speed used for reset and identification
+    ///
+    /// The sensor leaves reset with I2C_HS cleared, so it must be addressed
+    /// at fast-mode-plus or slower until the bit has been set.
+    fn setup_speed(speed: I2cSpeed) -> I2cSpeed {
+        match speed {
+            I2cSpeed::HighSpeed => I2cSpeed::FastPlus,
+            other => other,
+        }
+    }
+
     /// Open and configure an I2C channel
-    fn open_channel(channel_index: u32) -> Result<FT_HANDLE> {
+    fn open_channel(channel_index: u32, speed: I2cSpeed) -> Result<FT_HANDLE> {
         let mut num_channels: DWORD = 0;
         let status = unsafe { I2C_GetNumChannels(&mut num_channels) };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Open, None)?;
 
         if num_channels == 0 {
             return Err(Adxl355Error::NoChannelsFound);
@@ -289,12 +823,20 @@ impl Adxl355 {
 
         let mut handle: FT_HANDLE = ptr::null_mut();
         let status = unsafe { I2C_OpenChannel(channel_index, &mut handle) };
-        if status != FT_OK {
-            return Err(status.into());
+        check_status(status, TransportOp::Open, None)?;
+
+        if let Err(e) = Self::init_channel(handle, speed) {
+            unsafe { I2C_CloseChannel(handle) };
+            return Err(e);
         }
 
+        Ok(handle)
+    }
+
+    /// (Re)configure the MPSSE clock of an open channel
+    fn init_channel(handle: FT_HANDLE, speed: I2cSpeed) -> Result<()> {
         let mut config = ChannelConfig {
-            ClockRate: I2C_CLOCK_FAST_MODE_PLUS, // 1 MHz (3.4 MHz fails on breadboard)
+            ClockRate: speed.clock_hz(),
             LatencyTimer: 1,
             Options: 0,
             Pin: 0,
@@ -302,12 +844,36 @@ impl Adxl355 {
         };
 
         let status = unsafe { I2C_InitChannel(handle, &mut config) };
-        if status != FT_OK {
-            unsafe { I2C_CloseChannel(handle) };
-            return Err(status.into());
+        check_status(status, TransportOp::Configure, None)?;
+
+        Ok(())
+    }
+
+    /// Set I2C_HS to match the requested speed, then switch the bus clock
+    fn apply_bus_speed(&mut self) -> Result<()> {
+        let current = self.read_register(REG_RANGE)?;
+        let new_val = match self.speed {
+            I2cSpeed::HighSpeed => current | RANGE_I2C_HS,
+            _ => current & !RANGE_I2C_HS,
+        };
+        if new_val != current {
+            self.write_register(REG_RANGE, new_val)?;
         }
 
-        Ok(handle)
+        if self.speed == I2cSpeed::HighSpeed {
+            Self::init_channel(self.handle, I2cSpeed::HighSpeed)?;
+
+            // Confirm the sensor still answers at 3.4 MHz
+            let devid_ad = self.read_register(REG_DEVID_AD)?;
+            if devid_ad != DEVID_AD_VALUE {
+                return Err(Adxl355Error::UnexpectedResponse(format!(
+                    "No valid response at 3.4 MHz (DEVID_AD=0x{:02X}). Check wiring and pull-ups.",
+                    devid_ad
+                )));
+            }
+        }
+
+        Ok(())
     }
 
     /// Initialize the ADXL355 sensor
@@ -322,16 +888,28 @@ impl Adxl355 {
             return Err(Adxl355Error::InvalidDeviceId(devid_ad));
         }
 
-        let partid = self.read_register(REG_PARTID)?;
-        if partid != PARTID_VALUE {
-            return Err(Adxl355Error::InvalidPartId(partid));
+        let devid_mst = self.read_register(REG_DEVID_MST)?;
+        if devid_mst != DEVID_MST_VALUE {
+            return Err(Adxl355Error::InvalidMemsId(devid_mst));
         }
 
-        // Configure default range (+/-2g)
-        self.write_register(REG_RANGE, Range::G2 as u8)?;
+        let partid = self.read_register(REG_PARTID)?;
+        let detected = DeviceVariant::detect(partid)
+            .ok_or(Adxl355Error::InvalidPartId(partid))?;
+        let revid = self.read_register(REG_REVID)?;
+
+        // A variant declared before init (e.g. on reconnect) wins over detection
+        let variant = self.info.map(|info| info.variant).unwrap_or(detected);
+        self.info = Some(DeviceInfo { variant, devid_ad, devid_mst, partid, revid });
+
+        // Configure default range (+/-2g on ADXL355, +/-10g on ADXL357)
+        let default_range = variant.default_range();
+        self.write_register(REG_RANGE, default_range.register_bits())?;
+        self.range = default_range;
 
         // Configure default ODR (1000 Hz)
         self.write_register(REG_FILTER, OutputDataRate::Odr1000 as u8)?;
+        self.odr = OutputDataRate::Odr1000;
 
         // Enter measurement mode (clear standby bit)
         self.write_register(REG_POWER_CTL, 0x00)?;
@@ -366,9 +944,7 @@ impl Adxl355 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Write, Some(reg))?;
 
         Ok(())
     }
@@ -392,9 +968,7 @@ impl Adxl355 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         // Read the data
         let mut data = [0u8];
@@ -415,12 +989,11 @@ impl Adxl355 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         if transferred != 1 {
             return Err(Adxl355Error::TransferError {
+                register: Some(reg),
                 expected: 1,
                 actual: transferred,
             });
@@ -449,9 +1022,7 @@ impl Adxl355 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         let mut data = vec![0u8; count];
         transferred = 0;
@@ -472,9 +1043,7 @@ impl Adxl355 {
             )
         };
 
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Read, Some(reg))?;
 
         Ok(data)
     }
@@ -487,13 +1056,19 @@ impl Adxl355 {
     ///
     /// Automatically enters standby mode for configuration, then resumes.
     pub fn set_range(&mut self, range: Range) -> Result<()> {
+        if !self.variant().ranges().contains(&range) {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "Range {} is not available on the {}", range.label(), self.variant().name()
+            )));
+        }
+
         // Enter standby
         self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY)?;
         std::thread::sleep(Duration::from_millis(5));
 
-        // Read current RANGE register to preserve INT_POL bit
+        // Read current RANGE register to preserve I2C_HS and INT_POL bits
         let current = self.read_register(REG_RANGE)?;
-        let new_val = (current & 0xFC) | (range as u8);
+        let new_val = (current & 0xFC) | range.register_bits();
         self.write_register(REG_RANGE, new_val)?;
 
         self.range = range;
@@ -517,6 +1092,7 @@ impl Adxl355 {
         let current = self.read_register(REG_FILTER)?;
         let new_val = (current & 0xF0) | (odr as u8);
         self.write_register(REG_FILTER, new_val)?;
+        self.odr = odr;
 
         // Resume measurement
         self.write_register(REG_POWER_CTL, 0x00)?;
@@ -530,6 +1106,199 @@ impl Adxl355 {
         self.range
     }
 
+    /// Part variant (ADXL355 unless declared otherwise)
+    pub fn variant(&self) -> DeviceVariant {
+        self.info.map(|info| info.variant).unwrap_or(DeviceVariant::Adxl355)
+    }
+
+    /// Declare the part variant
+    ///
+    /// Needed for the ADXL357, which reports the same PARTID as the ADXL355.
+    /// The current range is re-interpreted for the new part (the register
+    /// code is unchanged), so no bus traffic is involved.
+    pub fn set_variant(&mut self, variant: DeviceVariant) {
+        let info = self.info.get_or_insert(DeviceInfo {
+            variant,
+            devid_ad: DEVID_AD_VALUE,
+            devid_mst: DEVID_MST_VALUE,
+            partid: PARTID_VALUE,
+            revid: 0,
+        });
+        info.variant = variant;
+        self.range = self.range.for_variant(variant);
+    }
+
+    /// Identification registers read during init (None before init)
+    pub fn device_info(&self) -> Option<DeviceInfo> {
+        self.info
+    }
+
+    /// Serial number of the FT232H the sensor is connected to
+    pub fn serial_number(&self) -> Option<String> {
+        let mut info: FT_DEVICE_LIST_INFO_NODE = unsafe { std::mem::zeroed() };
+        let status = unsafe { I2C_GetChannelInfo(self.channel_index, &mut info) };
+        if status != FT_OK {
+            return None;
+        }
+        let serial = &info.SerialNumber;
+        let len = serial.iter().position(|&b| b == 0).unwrap_or(serial.len());
+        let serial = String::from_utf8_lossy(&serial[..len]).trim().to_string();
+        (!serial.is_empty()).then_some(serial)
+    }
+
+    /// Set the synchronization / clock source
+    ///
+    /// Automatically enters standby mode for configuration, then resumes.
+    /// In the external modes the sensor produces no samples until sync
+    /// pulses (DRDY) and/or the external clock (INT2) are present.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) -> Result<()> {
+        // Enter standby
+        self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY)?;
+        std::thread::sleep(Duration::from_millis(5));
+
+        self.write_register(REG_SYNC, mode.register_value())?;
+
+        // Verify the write took effect
+        let readback = self.read_register(REG_SYNC)?;
+        if SyncMode::from_register(readback) != Some(mode) {
+            return Err(Adxl355Error::UnexpectedResponse(format!(
+                "SYNC readback mismatch: wrote 0x{:02X}, read 0x{:02X}",
+                mode.register_value(), readback
+            )));
+        }
+
+        self.sync_mode = mode;
+
+        // Resume measurement
+        self.write_register(REG_POWER_CTL, 0x00)?;
+        std::thread::sleep(Duration::from_millis(5));
+
+        Ok(())
+    }
+
+    /// Get the current synchronization mode
+    pub fn get_sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    /// Get the I2C bus speed
+    pub fn get_i2c_speed(&self) -> I2cSpeed {
+        self.speed
+    }
+
+    /// Measure how many samples per second the bus can move in FIFO mode
+    ///
+    /// Times 20 FIFO batches (FIFO_ENTRIES read plus a 32-sample burst from
+    /// FIFO_DATA) and keeps a 20% margin for scheduling jitter. Run before
+    /// starting acquisition: the burst reads drain the FIFO.
+    pub fn check_bandwidth(&mut self) -> Result<BandwidthReport> {
+        const ITERATIONS: u32 = 20;
+        const BATCH_SAMPLES: usize = FIFO_MAX_SAMPLES / 3;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            self.get_fifo_entries()?;
+            self.read_registers(REG_FIFO_DATA, BATCH_SAMPLES * FIFO_SAMPLE_SIZE)?;
+        }
+        let batch_time = start.elapsed() / ITERATIONS;
+
+        let sustainable_hz = BATCH_SAMPLES as f64 / batch_time.as_secs_f64() * 0.8;
+
+        let presets = [
+            OutputDataRate::Odr4000,
+            OutputDataRate::Odr2000,
+            OutputDataRate::Odr1000,
+            OutputDataRate::Odr500,
+            OutputDataRate::Odr250,
+            OutputDataRate::Odr125,
+            OutputDataRate::Odr62_5,
+            OutputDataRate::Odr31_25,
+            OutputDataRate::Odr15_625,
+            OutputDataRate::Odr7_813,
+            OutputDataRate::Odr3_906,
+        ];
+        let max_odr = presets.iter().copied().find(|odr| odr.as_hz() <= sustainable_hz);
+
+        Ok(BandwidthReport {
+            speed: self.speed,
+            batch_time,
+            sustainable_hz,
+            max_odr,
+        })
+    }
+
+    /// Get the current output data rate
+    pub fn get_odr(&self) -> OutputDataRate {
+        self.odr
+    }
+
+    /// Run the datasheet electrostatic self-test
+    ///
+    /// Enables self-test mode (ST1), averages `samples` readings with the
+    /// force off, applies the force (ST2), averages again, then restores
+    /// normal operation. The sensor must be stationary and FIFO mode off.
+    ///
+    /// The test runs at the part's widest range (+/-8 g, or +/-40 g on the
+    /// ADXL357), where 1 g of gravity plus the self-test force cannot
+    /// saturate; the range in use is restored afterwards.
+    pub fn self_test(&mut self, samples: usize) -> Result<SelfTestReport> {
+        if samples == 0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Self-test sample count must be at least 1".to_string()
+            ));
+        }
+        if self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "Disable FIFO mode before running the self-test".to_string()
+            ));
+        }
+
+        // Let the digital filter settle (~10 output periods) after each change
+        let period = Duration::from_secs_f64(1.0 / self.odr.as_hz());
+        let settle = (period * 10).max(Duration::from_millis(20));
+
+        let range = self.range;
+        let test_range = self.variant().ranges()[2];
+        self.set_range(test_range)?;
+
+        let off = self.write_register(REG_SELF_TEST, SELF_TEST_ST1).and_then(|_| {
+            std::thread::sleep(settle);
+            self.average_accel_g(samples, period)
+        });
+
+        let on = off.and_then(|off| {
+            self.write_register(REG_SELF_TEST, SELF_TEST_ST1 | SELF_TEST_ST2)?;
+            std::thread::sleep(settle);
+            self.average_accel_g(samples, period).map(|on| (off, on))
+        });
+
+        // Always leave self-test mode and go back to the range in use, even
+        // if a read failed
+        let reset = self.write_register(REG_SELF_TEST, 0x00);
+        std::thread::sleep(settle);
+        let restore = self.set_range(range);
+
+        let (off_g, on_g) = on?;
+        reset?;
+        restore?;
+        Ok(SelfTestReport::evaluate(test_range, self.variant(), samples, off_g, on_g))
+    }
+
+    /// Average `samples` polled readings, converted to g
+    fn average_accel_g(&mut self, samples: usize, period: Duration) -> Result<[f32; 3]> {
+        let mut sum = [0i64; 3];
+        for _ in 0..samples {
+            let (x, y, z) = self.read_accel()?;
+            sum[0] += x as i64;
+            sum[1] += y as i64;
+            sum[2] += z as i64;
+            std::thread::sleep(period);
+        }
+
+        let scale = self.range.scale_factor() * samples as f32;
+        Ok([sum[0] as f32 / scale, sum[1] as f32 / scale, sum[2] as f32 / scale])
+    }
+
     // ========================================================================
     // Data reading
     // ========================================================================
@@ -550,6 +1319,43 @@ impl Adxl355 {
         Ok((x, y, z))
     }
 
+    /// Set the per-device temperature calibration
+    pub fn set_temperature_calibration(&mut self, cal: TemperatureCalibration) {
+        self.temp_calibration = cal;
+    }
+
+    /// Get the per-device temperature calibration
+    pub fn temperature_calibration(&self) -> TemperatureCalibration {
+        self.temp_calibration
+    }
+
+    /// Read the temperature in Celsius using the device calibration
+    pub fn read_temperature_c(&mut self) -> Result<f32> {
+        let raw = self.read_temperature()?;
+        Ok(self.temp_calibration.to_celsius(raw))
+    }
+
+    /// Set how often FIFO batch reads refresh the temperature registers
+    ///
+    /// Temperature changes slowly, so the default 100 ms keeps the extra
+    /// bus traffic negligible. `Duration::ZERO` reads it on every batch.
+    pub fn set_fifo_temperature_interval(&mut self, interval: Duration) {
+        self.temperature_interval = interval;
+    }
+
+    /// Refresh the carried-forward temperature if the interval has elapsed
+    fn refresh_temperature(&mut self) -> Result<u16> {
+        let due = match self.last_temperature_read {
+            Some(t) => t.elapsed() >= self.temperature_interval,
+            None => true,
+        };
+        if due {
+            self.last_temperature = self.read_temperature()?;
+            self.last_temperature_read = Some(Instant::now());
+        }
+        Ok(self.last_temperature)
+    }
+
     /// Read all sensor data (temperature + XYZ accelerometer) in one burst
     ///
     /// Reads 11 bytes from TEMP2 (0x06) through ZDATA1 (0x10).
@@ -610,6 +1416,52 @@ impl Adxl355 {
         Ok(sample_count)
     }
 
+    /// Polling stream that survives USB errors and unplugging
+    ///
+    /// Like [`Adxl355::stream`], with every read going through
+    /// [`Adxl355::with_recovery`]. The callback sees
+    /// [`StreamEvent::Reconnected`] after a reconnect; the schedule then
+    /// restarts from the current time instead of trying to catch up.
+    pub fn stream_resilient<F>(&mut self, rate_hz: u32, policy: &RetryPolicy, mut callback: F) -> Result<u64>
+    where
+        F: FnMut(StreamEvent<'_, SensorData>) -> StreamControl,
+    {
+        if rate_hz == 0 || rate_hz > 4000 {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "Sample rate must be between 1-4000 Hz, got {}",
+                rate_hz
+            )));
+        }
+
+        let interval = Duration::from_micros(1_000_000 / rate_hz as u64);
+        let mut sample_count = 0u64;
+        let mut next_sample_time = Instant::now();
+
+        loop {
+            let (data, outage) = self.with_recovery(policy, |s| s.read_all())?;
+
+            if let Some(outage) = &outage {
+                next_sample_time = Instant::now();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            }
+
+            sample_count += 1;
+            if callback(StreamEvent::Data(data)) == StreamControl::Break {
+                break;
+            }
+
+            next_sample_time += interval;
+            let now = Instant::now();
+            if next_sample_time > now {
+                std::thread::sleep(next_sample_time - now);
+            }
+        }
+
+        Ok(sample_count)
+    }
+
     /// Stream sensor data for a specified duration
     pub fn stream_for<F>(&mut self, rate_hz: u32, duration: Duration, mut callback: F) -> Result<u64>
     where
@@ -660,12 +1512,14 @@ impl Adxl355 {
         let current_filter = self.read_register(REG_FILTER)?;
         let new_filter = (current_filter & 0xF0) | (odr as u8);
         self.write_register(REG_FILTER, new_filter)?;
+        self.odr = odr;
 
         // Enter measurement mode
         self.write_register(REG_POWER_CTL, 0x00)?;
         std::thread::sleep(Duration::from_millis(10));
 
         self.fifo_enabled = true;
+        self.last_temperature_read = None;
 
         // Drain any startup data
         let _ = self.read_fifo_batch();
@@ -701,6 +1555,7 @@ impl Adxl355 {
         let bytes_to_read = num_samples * 9;
 
         let fifo_data = self.read_registers(REG_FIFO_DATA, bytes_to_read)?;
+        let temperature = self.refresh_temperature()?;
 
         // Parse with X-axis marker alignment (bit 0 of low byte = X marker)
         let mut samples = Vec::with_capacity(num_samples);
@@ -731,7 +1586,7 @@ impl Adxl355 {
                 accel_x,
                 accel_y,
                 accel_z,
-                temperature: 0,
+                temperature,
             });
 
             i += 9;
@@ -783,6 +1638,87 @@ impl Adxl355 {
         Ok(total_samples)
     }
 
+    /// Stream FIFO data with the read interval chosen automatically
+    ///
+    /// A [`PollScheduler`] aims each read at half of the 32-sample FIFO,
+    /// tracking the measured fill rate and USB latency from the current ODR.
+    /// A read that finds all 32 samples counts as an overflow, since
+    /// FIFO_ENTRIES cannot tell a full FIFO from one that dropped samples.
+    pub fn stream_fifo_adaptive<F>(&mut self, callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        let scheduler = PollScheduler::new(FIFO_MAX_SAMPLES / 3, self.odr.as_hz());
+        self.stream_fifo_scheduled(scheduler, callback)
+    }
+
+    /// Stream FIFO data with a caller-configured [`PollScheduler`]
+    pub fn stream_fifo_scheduled<F>(&mut self, mut scheduler: PollScheduler, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "FIFO not enabled. Call enable_fifo() first.".to_string()
+            ));
+        }
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let batch = self.read_fifo_batch()?;
+            scheduler.record(batch.len(), false, read_start.elapsed());
+
+            if !batch.is_empty() && callback(&batch) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
+    /// Adaptive FIFO streaming that survives USB errors and unplugging
+    ///
+    /// Like [`Adxl355::stream_fifo_adaptive`], but each read goes through
+    /// [`Adxl355::with_recovery`]. After a reconnect the callback receives
+    /// [`StreamEvent::Reconnected`] before the next batch.
+    pub fn stream_fifo_resilient<F>(&mut self, policy: &RetryPolicy, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(StreamEvent<'_, &[SensorData]>) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "FIFO not enabled. Call enable_fifo() first.".to_string()
+            ));
+        }
+
+        let mut scheduler = PollScheduler::new(FIFO_MAX_SAMPLES / 3, self.odr.as_hz());
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let (result, outage) = self.with_recovery(policy, |s| s.read_fifo_batch())?;
+            let batch = result;
+
+            if let Some(outage) = &outage {
+                scheduler.resync();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            } else {
+                scheduler.record(batch.len(), false, read_start.elapsed());
+            }
+
+            if !batch.is_empty() && callback(StreamEvent::Data(&batch)) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
     /// Collect samples using FIFO mode
     pub fn collect_samples_fifo(&mut self, odr: OutputDataRate, num_samples: usize) -> Result<Vec<SensorData>> {
         if !self.fifo_enabled {
@@ -791,9 +1727,7 @@ impl Adxl355 {
 
         let mut samples = Vec::with_capacity(num_samples);
 
-        let batch_interval_ms = 50u64;
-
-        self.stream_fifo(batch_interval_ms, |batch| {
+        self.stream_fifo_adaptive(|batch| {
             samples.extend_from_slice(batch);
 
             if samples.len() >= num_samples {
@@ -811,11 +1745,63 @@ impl Adxl355 {
 
 impl Drop for Adxl355 {
     fn drop(&mut self) {
+        // Channel already closed by a reconnect that did not succeed
+        if self.handle.is_null() {
+            return;
+        }
+
         // Enter standby
         let _ = self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY);
 
+        self.gpio.borrow_mut().detach();
         unsafe {
             I2C_CloseChannel(self.handle);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_modes_round_trip() {
+        let modes = [
+            SyncMode::Internal,
+            SyncMode::ExternalSync,
+            SyncMode::ExternalSyncInterpolated,
+            SyncMode::ExternalClock,
+            SyncMode::ExternalClockAndSync,
+            SyncMode::ExternalClockAndSyncInterpolated,
+        ];
+        for mode in modes {
+            assert_eq!(SyncMode::from_register(mode.register_value()), Some(mode));
+            assert_eq!(SyncMode::from_name(mode.as_str()), Some(mode));
+        }
+        assert_eq!(SyncMode::ExternalClockAndSyncInterpolated.register_value(), 0x06);
+        // EXT_SYNC = 0b11 is reserved
+        assert_eq!(SyncMode::from_register(0x03), None);
+        assert_eq!(SyncMode::from_register(0x07), None);
+    }
+
+    #[test]
+    fn self_test_fails_saturated_axes_only() {
+        // Resting on Z with a 2 g self-test response
+        let (off, on) = ([0.0, 0.0, 1.0], [0.3, 0.3, 3.0]);
+        let report = SelfTestReport::evaluate(Range::G8, DeviceVariant::Adxl355, 50, off, on);
+        assert_eq!(report.axis_saturated, [false; 3]);
+        assert!(report.passed());
+        assert_eq!(report.delta_g[2], 2.0);
+
+        // The same part at +/-2 g: Z clips just above 2 g and cannot pass
+        let clipped = SelfTestReport::evaluate(Range::G2, DeviceVariant::Adxl355, 50, off, [0.3, 0.3, 2.04]);
+        assert_eq!(clipped.axis_saturated, [false, false, true]);
+        assert_eq!(clipped.axis_passed, [true, true, false]);
+        assert!(!clipped.passed());
+
+        // In scale, but X is below the ADXL357 limit
+        let weak = SelfTestReport::evaluate(Range::G40, DeviceVariant::Adxl357, 50, off, [0.05, 0.3, 2.0]);
+        assert_eq!(weak.axis_saturated, [false; 3]);
+        assert_eq!(weak.axis_passed, [false, true, true]);
+    }
+}
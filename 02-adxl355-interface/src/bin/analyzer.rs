@@ -1,9 +1,11 @@
 //! ADXL355 Data Analyzer
 //!
 //! Post-processing analysis tool for sensor data from HDF5 files.
+//! `--by-events` repeats the analyses for every stretch between events.
 
 use clap::Parser;
-use ft232_adxl355_interface::{Hdf5Reader, Range, TimestampedSample};
+use ft232_adxl355_interface::{Adxl355Error, Completion, Hdf5Reader, Range, TimestampedSample};
+use ft232_adxl355_interface::events;
 use num_complex::Complex;
 use rustfft::FftPlanner;
 use std::f64::consts::PI;
@@ -15,7 +17,7 @@ use std::path::PathBuf;
 #[command(name = "analyzer")]
 #[command(about = "Analyze ADXL355 sensor data from HDF5 file")]
 struct Args {
-    /// Input HDF5 file path
+    /// Input HDF5 file, or the .manifest of a rotated session
     #[arg(short, long)]
     input: PathBuf,
 
@@ -46,56 +48,70 @@ struct Args {
     /// Output file (default: stdout)
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    /// Analyse the stretches between recorded events one by one
+    #[arg(long)]
+    by_events: bool,
+
+    /// With --by-events: split only at events of this kind (e.g. "hit")
+    #[arg(long, requires = "by_events")]
+    event_kind: Option<String>,
+}
+
+/// Which analyses to run
+struct Selection {
+    statistics: bool,
+    fft: bool,
+    vibration: bool,
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 }
 
-fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+fn run() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
     let reader = Hdf5Reader::open(&args.input)?;
     let metadata = reader.metadata();
+    if reader.completion() == Completion::Recovered {
+        eprintln!("Note: recovered file, the recording was interrupted");
+    }
 
-    let range = match metadata.range.as_str() {
-        "4g" => Range::G4,
-        "8g" => Range::G8,
-        _ => Range::G2,
-    };
+    let range = Range::from_label(&metadata.range).unwrap_or(Range::G2);
 
-    let run_statistics = args.all || args.statistics;
-    let run_fft = args.all || args.fft;
-    let run_vibration = args.all || args.vibration;
+    let selection = Selection {
+        statistics: args.all || args.statistics,
+        fft: args.all || args.fft,
+        vibration: args.all || args.vibration,
+    };
 
-    if !run_statistics && !run_fft && !run_vibration {
-        eprintln!("Error: Must specify at least one analysis type (--statistics, --fft, --vibration, or --all)");
-        std::process::exit(1);
+    if !selection.statistics && !selection.fft && !selection.vibration {
+        return Err(Adxl355Error::Analysis("Must specify at least one analysis type (--statistics, --fft, --vibration, or --all)".to_string()).into());
     }
 
     println!("Loading data from {}...", args.input.display());
-    let total_samples = reader.get_total_samples()?;
-
-    if total_samples == 0 {
-        eprintln!("Error: No samples in file");
-        return Ok(());
+    if let Some(manifest) = reader.manifest() {
+        println!("Session: {} files", manifest.segments.len());
     }
-
-    let all_samples = reader.read_range(0, total_samples)?;
-    let file_start = all_samples.first().unwrap().timestamp;
-    let file_end = all_samples.last().unwrap().timestamp;
+    let Some((file_start, file_end)) = reader.time_span()? else {
+        return Err(Adxl355Error::Analysis("No samples in file".to_string()).into());
+    };
 
     let start_time = args.start.unwrap_or(file_start);
     let end_time = args.end.unwrap_or(file_end);
 
     if start_time >= end_time {
-        eprintln!("Error: Start time must be before end time");
-        std::process::exit(1);
+        return Err(Adxl355Error::Analysis("Start time must be before end time".to_string()).into());
     }
 
-    let samples: Vec<TimestampedSample> = all_samples.into_iter()
-        .filter(|s| s.timestamp >= start_time && s.timestamp <= end_time)
-        .collect();
+    let samples = reader.read_time_range(start_time, end_time)?;
 
     if samples.is_empty() {
-        eprintln!("Error: No samples in specified time range");
-        return Ok(());
+        return Err(Adxl355Error::Analysis("No samples in specified time range".to_string()).into());
     }
 
     println!("Loaded {} samples ({:.2}s to {:.2}s)",
@@ -113,42 +129,115 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     writeln!(output, "{}", "=".repeat(80))?;
     writeln!(output)?;
     writeln!(output, "File Information:")?;
-    writeln!(output, "  Sensor: {}", metadata.sensor_type)?;
+    match (&metadata.part, metadata.revision) {
+        (Some(part), Some(rev)) => writeln!(output, "  Sensor: {} (rev {})", part, rev)?,
+        (Some(part), None) => writeln!(output, "  Sensor: {}", part)?,
+        _ => writeln!(output, "  Sensor: {}", metadata.sensor_type)?,
+    }
     writeln!(output, "  Range: {}", metadata.range)?;
     writeln!(output, "  Acquisition mode: {}", metadata.acquisition_mode)?;
     writeln!(output, "  Sample rate: {:.1} Hz", metadata.sample_rate_hz)?;
     writeln!(output, "  Start time: {}", metadata.start_time)?;
+    writeln!(output, "  Schema version: {}", metadata.version)?;
+    if let Some(host) = &metadata.host {
+        writeln!(output, "  Recorded on: {}", host)?;
+    }
+    if let Some(serial) = &metadata.device_serial {
+        writeln!(output, "  FT232H serial: {}", serial)?;
+    }
     writeln!(output)?;
     writeln!(output, "Analysis Range:")?;
     writeln!(output, "  Start: {:.2}s", start_time)?;
     writeln!(output, "  End: {:.2}s", end_time)?;
     writeln!(output, "  Duration: {:.2}s", end_time - start_time)?;
     writeln!(output, "  Samples: {}", samples.len())?;
+    let gaps = reader.discontinuities()?;
+    let gaps: Vec<_> = gaps.iter()
+        .filter(|gap| gap.end_time >= start_time && gap.start_time <= end_time)
+        .collect();
+    if !gaps.is_empty() {
+        let total: f64 = gaps.iter().map(|gap| gap.duration()).sum();
+        writeln!(output, "  Gaps: {} ({:.2}s missing)", gaps.len(), total)?;
+        for gap in &gaps {
+            writeln!(output, "    {:.2}s - {:.2}s: {}", gap.start_time, gap.end_time, gap.cause)?;
+        }
+    }
+    let events = reader.events(start_time..=end_time)?;
+    if !events.is_empty() {
+        writeln!(output, "  Events: {}", events.len())?;
+        for event in &events {
+            match &event.payload {
+                Some(payload) => writeln!(output, "    {:.2}s [{}] {} {}", event.time, event.kind, event.label, payload)?,
+                None => writeln!(output, "    {:.2}s [{}] {}", event.time, event.kind, event.label)?,
+            }
+        }
+    }
 
-    if run_statistics {
+    let sample_rate = metadata.sample_rate_hz;
+    if args.by_events {
+        let boundaries: Vec<_> = events.into_iter()
+            .filter(|event| args.event_kind.iter().all(|kind| &event.kind == kind))
+            .collect();
+        let spans = events::spans(&boundaries, start_time, end_time);
+        for (number, span) in spans.iter().enumerate() {
+            let from = samples.partition_point(|s| s.timestamp < span.start);
+            // The sample at end_time belongs to the last span
+            let to = if number + 1 == spans.len() {
+                samples.len()
+            } else {
+                samples.partition_point(|s| s.timestamp < span.end)
+            };
+            writeln!(output, "\n{}", "#".repeat(80))?;
+            match span.event {
+                Some(event) => writeln!(output, "SEGMENT {}: [{}] {}", number + 1, event.kind, event.label)?,
+                None => writeln!(output, "SEGMENT {}: before first event", number + 1)?,
+            }
+            writeln!(output, "  {:.2}s - {:.2}s, {} samples", span.start, span.end, to - from)?;
+            writeln!(output, "{}", "#".repeat(80))?;
+            if from == to {
+                writeln!(output, "\n  No samples in segment")?;
+                continue;
+            }
+            run_analyses(&mut output, &selection, &samples[from..to], sample_rate, range)?;
+        }
+    } else {
+        run_analyses(&mut output, &selection, &samples, sample_rate, range)?;
+    }
+
+    writeln!(output, "\n{}", "=".repeat(80))?;
+    writeln!(output, "Analysis complete!")?;
+
+    Ok(())
+}
+
+fn run_analyses(
+    output: &mut dyn Write,
+    selection: &Selection,
+    samples: &[TimestampedSample],
+    sample_rate: f64,
+    range: Range,
+) -> io::Result<()> {
+    if selection.statistics {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "STATISTICAL ANALYSIS")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_statistics_analysis(&mut output, &samples, range)?;
+        run_statistics_analysis(output, samples, range)?;
     }
 
-    if run_fft {
+    if selection.fft {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "FREQUENCY ANALYSIS (FFT)")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_fft_analysis(&mut output, &samples, metadata.sample_rate_hz, range)?;
+        run_fft_analysis(output, samples, sample_rate, range)?;
     }
 
-    if run_vibration {
+    if selection.vibration {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "VIBRATION ANALYSIS")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_vibration_analysis(&mut output, &samples, metadata.sample_rate_hz, range)?;
+        run_vibration_analysis(output, samples, sample_rate, range)?;
     }
 
-    writeln!(output, "\n{}", "=".repeat(80))?;
-    writeln!(output, "Analysis complete!")?;
-
     Ok(())
 }
 
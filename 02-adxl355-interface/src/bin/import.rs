@@ -0,0 +1,112 @@
+//! Build recordings from CSV or WAV data of other instruments
+//!
+//! Usage:
+//!   import --input ref.csv --output ref.h5 --sensor "PCB 352C33" --time-column t
+//!   import --input ref.csv --output ref.h5 --sensor daq --rate 1000 --scale 0.10197
+//!   import --input hit.wav --output hit.h5 --sensor recorder --scale 2 --map accel_z=1
+
+use clap::Parser;
+use ft232_adxl355_interface::import::{import_csv, import_wav, ImportOptions, Timing, CHANNELS};
+use ft232_adxl355_interface::Range;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "import")]
+#[command(about = "Convert CSV or WAV data to ADXL355 recordings", long_about = None)]
+struct Args {
+    /// Input CSV or WAV file
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Output HDF5 file
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// csv or wav (default: from the input extension)
+    #[arg(long)]
+    format: Option<String>,
+
+    /// Instrument the data was recorded with, stored as source_sensor
+    #[arg(long)]
+    sensor: String,
+
+    /// Input units -> g; for WAV, g at full scale
+    #[arg(long, default_value_t = 1.0)]
+    scale: f64,
+
+    /// Range to store the data with: 2g, 4g, 8g, 10g, 20g or 40g
+    #[arg(long, default_value = "8g")]
+    range: String,
+
+    /// Channel mapping, e.g. accel_x=ax,accel_y=3,temperature=T
+    #[arg(long, value_delimiter = ',')]
+    map: Vec<String>,
+
+    /// CSV column holding the time in seconds (name or number)
+    #[arg(long, conflicts_with = "rate")]
+    time_column: Option<String>,
+
+    /// Sample rate in Hz of evenly spaced CSV rows
+    #[arg(long)]
+    rate: Option<f64>,
+
+    /// RFC 3339 time of the first sample (default: now)
+    #[arg(long)]
+    start_time: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let format = match &args.format {
+        Some(name) => name.to_ascii_lowercase(),
+        None => args.input.extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .ok_or("Cannot tell the format from the input name, use --format")?,
+    };
+    let range = Range::from_label(&args.range)
+        .ok_or_else(|| format!("Unknown range '{}' (2g, 4g, 8g, 10g, 20g, 40g)", args.range))?;
+    let mapping = args.map.iter()
+        .map(|entry| {
+            let (channel, source) = entry.split_once('=')
+                .ok_or_else(|| format!("Mapping '{}' is not channel=column ({})", entry, CHANNELS.join(", ")))?;
+            Ok((channel.trim().to_string(), source.trim().to_string()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let options = ImportOptions {
+        sensor: args.sensor.clone(),
+        scale: args.scale,
+        range,
+        mapping,
+        start_time: args.start_time.clone(),
+    };
+
+    let report = match format.as_str() {
+        "csv" | "txt" => {
+            let timing = match (&args.time_column, args.rate) {
+                (Some(column), _) => Timing::Column(column.clone()),
+                (None, Some(rate)) => Timing::Rate(rate),
+                (None, None) => return Err("CSV input needs --time-column or --rate".into()),
+            };
+            import_csv(&args.input, &args.output, &timing, &options)?
+        }
+        "wav" => {
+            if args.time_column.is_some() || args.rate.is_some() {
+                eprintln!("Warning: WAV files carry their own rate, --time-column/--rate ignored");
+            }
+            import_wav(&args.input, &args.output, &options)?
+        }
+        other => return Err(format!("Unknown format '{}' (csv, wav)", other).into()),
+    };
+
+    println!("Imported {} samples at {:.3} Hz to {}", report.samples, report.sample_rate_hz, args.output.display());
+    if report.gaps > 0 {
+        println!("  {} gap(s) in the time column stored as discontinuities", report.gaps);
+    }
+    if report.clipped > 0 {
+        eprintln!("Warning: {} value(s) beyond +/-{} clipped, consider a larger --range", report.clipped, range.label());
+    }
+    Ok(())
+}
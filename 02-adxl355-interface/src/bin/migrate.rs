@@ -0,0 +1,61 @@
+//! Upgrade recordings to the current HDF5 schema
+//!
+//! Usage:
+//!   migrate old.h5
+//!   migrate --check recordings/*.h5
+//!   migrate site.manifest        # every segment of a session
+
+use clap::Parser;
+use ft232_adxl355_interface::session::is_manifest;
+use ft232_adxl355_interface::{migrate, Hdf5Reader, Manifest, SCHEMA_VERSION};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "migrate")]
+#[command(about = "Upgrade ADXL355 HDF5 recordings to the current schema", long_about = None)]
+struct Args {
+    /// HDF5 files or session manifests (.manifest)
+    #[arg(required = true)]
+    inputs: Vec<PathBuf>,
+
+    /// Only print the schema version of each file
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let mut files = Vec::new();
+    for input in &args.inputs {
+        if is_manifest(input) {
+            files.extend(Manifest::load(input)?.segment_paths(input));
+        } else {
+            files.push(input.clone());
+        }
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        if args.check {
+            match Hdf5Reader::open(file) {
+                Ok(reader) => println!("{}: schema {}", file.display(), reader.metadata().version),
+                Err(e) => println!("{}: cannot open ({})", file.display(), e),
+            }
+            continue;
+        }
+        match migrate(file) {
+            Ok(true) => println!("{}: upgraded to schema {}", file.display(), SCHEMA_VERSION),
+            Ok(false) => println!("{}: already current", file.display()),
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} files could not be migrated", failed, files.len()).into());
+    }
+    Ok(())
+}
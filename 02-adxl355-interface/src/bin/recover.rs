@@ -0,0 +1,83 @@
+//! ADXL355 Recording Repair
+//!
+//! Restores an HDF5 file whose collector was killed or lost power, using
+//! the `<file>.journal` written next to it.
+
+use clap::Parser;
+use ft232_adxl355_interface::journal::{journal_path, JournalContents};
+use ft232_adxl355_interface::{recover, Completion, Hdf5Reader};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(name = "recover")]
+#[command(about = "Repair an interrupted ADXL355 recording from its journal", long_about = None)]
+struct Args {
+    /// Recording to repair
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Show the file and journal state without changing anything
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if args.check {
+        check(&args.input);
+        return Ok(());
+    }
+
+    let report = recover(&args.input)?;
+    if report.already_complete {
+        println!("{}: closed cleanly, {} samples, nothing to do", args.input.display(), report.sample_count);
+        return Ok(());
+    }
+    if report.rebuilt {
+        println!("HDF5 file unreadable, moved to .damaged and rewritten from the journal");
+        println!("Device info and calibration metadata were not journaled and are missing");
+    }
+    if report.journal_found {
+        println!("Journal: {} samples, {} gaps restored", report.journal_samples, report.journal_discontinuities);
+    }
+    if report.torn_bytes > 0 {
+        println!("Journal: {} bytes of a partly written record dropped", report.torn_bytes);
+    }
+    if report.lost_samples > 0 {
+        println!("Lost: {} samples", report.lost_samples);
+    }
+    println!("{}: {} samples", args.input.display(), report.sample_count);
+
+    Ok(())
+}
+
+fn check(path: &Path) {
+    match Hdf5Reader::open(path) {
+        Ok(reader) => {
+            let state = match reader.completion() {
+                Completion::Complete => "complete",
+                Completion::Recovered => "recovered",
+                Completion::Unknown => "no completion marker",
+            };
+            println!("{}: {} samples, {}", path.display(), reader.get_total_samples().unwrap_or(0), state);
+        }
+        Err(e) => println!("{}: unreadable: {}", path.display(), e),
+    }
+
+    let journal = journal_path(path);
+    if !journal.exists() {
+        println!("Journal: none");
+        return;
+    }
+    match JournalContents::read(&journal) {
+        Ok(contents) => {
+            println!("Journal: {} samples, {} gaps pending after sample {}",
+                contents.samples.len(), contents.discontinuities.len(), contents.header.sample_base);
+            if contents.torn_bytes > 0 {
+                println!("Journal: last record incomplete ({} bytes)", contents.torn_bytes);
+            }
+        }
+        Err(e) => println!("Journal: unreadable: {}", e),
+    }
+}
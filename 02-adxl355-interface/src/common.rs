@@ -1,6 +1,6 @@
 //! Common utilities shared across programs
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Tracks elapsed time since creation
 pub struct TimeKeeper {
@@ -27,6 +27,222 @@ impl Default for TimeKeeper {
     }
 }
 
+/// Counters from an adaptive FIFO polling run
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PollStats {
+    /// FIFO reads issued
+    pub polls: u64,
+    /// Reads that returned nothing
+    pub empty_polls: u64,
+    /// Samples returned over all reads
+    pub samples: u64,
+    /// Reads that found the FIFO overflowed (or completely full)
+    pub overflows: u64,
+    /// Mean FIFO fill at read time, as a fraction of capacity
+    pub mean_fill: f64,
+    /// Highest FIFO fill seen at read time, as a fraction of capacity
+    pub max_fill: f64,
+    /// Poll interval the scheduler settled on
+    pub interval: Duration,
+    /// Smoothed duration of one FIFO read (USB latency included)
+    pub read_latency: Duration,
+    /// Time the polling loop was awake: reads plus callback processing.
+    /// An upper bound on CPU use, since USB waits are counted too.
+    pub cpu_time: Duration,
+    /// Wall-clock time since the first poll
+    pub wall_time: Duration,
+}
+
+impl PollStats {
+    /// Fraction of wall time the loop was awake
+    pub fn cpu_fraction(&self) -> f64 {
+        let wall = self.wall_time.as_secs_f64();
+        if wall > 0.0 {
+            self.cpu_time.as_secs_f64() / wall
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Picks FIFO poll times so that each read finds the FIFO at a target fill
+///
+/// The fill rate is estimated from the samples each read returns and the
+/// time since the previous read, then the next read is scheduled
+/// `target * capacity / rate` after the previous one, less the measured read
+/// latency. An overflow halves the interval straight away.
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// # use ft232_adxl355_interface::PollScheduler;
+///
+/// // 32-sample FIFO filling at 1 kHz
+/// let mut scheduler = PollScheduler::new(32, 1000.0);
+/// for _ in 0..3 {
+///     scheduler.wait();
+///     let start = Instant::now();
+///     let batch_len = 16; // sensor.read_fifo_batch()?.len()
+///     scheduler.record(batch_len, false, start.elapsed());
+/// }
+/// assert!(scheduler.interval() < Duration::from_millis(32));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PollScheduler {
+    capacity: usize,
+    target_fill: f64,
+    min_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+    rate_hz: f64,
+    latency: Duration,
+    started: Option<Instant>,
+    last_poll: Option<Instant>,
+    slept: Duration,
+    fill_sum: f64,
+    stats: PollStats,
+}
+
+impl PollScheduler {
+    /// Default fraction of the FIFO to let fill between reads
+    pub const DEFAULT_TARGET_FILL: f64 = 0.5;
+
+    /// Weight of the newest observation in the rate and latency estimates
+    const SMOOTHING: f64 = 0.25;
+
+    /// `capacity` is the FIFO depth in samples, `nominal_rate_hz` the
+    /// expected fill rate used until real reads have been observed
+    pub fn new(capacity: usize, nominal_rate_hz: f64) -> Self {
+        let mut scheduler = PollScheduler {
+            capacity: capacity.max(1),
+            target_fill: Self::DEFAULT_TARGET_FILL,
+            min_interval: Duration::from_millis(1),
+            max_interval: Duration::from_secs(1),
+            interval: Duration::ZERO,
+            rate_hz: nominal_rate_hz.max(0.1),
+            latency: Duration::ZERO,
+            started: None,
+            last_poll: None,
+            slept: Duration::ZERO,
+            fill_sum: 0.0,
+            stats: PollStats::default(),
+        };
+        scheduler.update_interval();
+        scheduler
+    }
+
+    /// Target fill fraction (clamped to 0.1-0.9)
+    pub fn target_fill(mut self, fraction: f64) -> Self {
+        self.target_fill = fraction.clamp(0.1, 0.9);
+        self.update_interval();
+        self
+    }
+
+    /// Bounds for the poll interval (defaults 1 ms - 1 s)
+    pub fn interval_bounds(mut self, min: Duration, max: Duration) -> Self {
+        self.min_interval = min;
+        self.max_interval = max.max(min);
+        self.update_interval();
+        self
+    }
+
+    /// Current poll interval
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Estimated FIFO fill rate in samples per second
+    pub fn fill_rate_hz(&self) -> f64 {
+        self.rate_hz
+    }
+
+    /// Forget the previous read time so a gap (e.g. a reconnect) is not
+    /// taken for a slow fill
+    pub fn resync(&mut self) {
+        self.last_poll = None;
+    }
+
+    /// Sleep until the next read is due
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        self.started.get_or_insert(now);
+
+        if let Some(last) = self.last_poll {
+            let due = last + self.interval;
+            if due > now {
+                std::thread::sleep(due - now);
+                self.slept += due - now;
+            }
+        }
+    }
+
+    /// Feed back the outcome of one read
+    ///
+    /// `overflow` is the driver's own overflow flag; a read that returns a
+    /// full FIFO counts as an overflow too, since samples were probably lost.
+    pub fn record(&mut self, samples: usize, overflow: bool, read_time: Duration) {
+        let now = Instant::now();
+        let since_last = self.last_poll.map(|last| now.duration_since(last));
+        self.last_poll = Some(now);
+        self.started.get_or_insert(now - read_time);
+
+        self.observe(samples, overflow, read_time, since_last);
+
+        let started = self.started.unwrap_or(now);
+        self.stats.wall_time = now.duration_since(started);
+        self.stats.cpu_time = self.stats.wall_time.saturating_sub(self.slept);
+    }
+
+    fn observe(&mut self, samples: usize, overflow: bool, read_time: Duration, since_last: Option<Duration>) {
+        let fill = samples as f64 / self.capacity as f64;
+        let overflow = overflow || samples >= self.capacity;
+
+        self.stats.polls += 1;
+        self.stats.samples += samples as u64;
+        if samples == 0 {
+            self.stats.empty_polls += 1;
+        }
+        if overflow {
+            self.stats.overflows += 1;
+        }
+        self.fill_sum += fill.min(1.0);
+        self.stats.mean_fill = self.fill_sum / self.stats.polls as f64;
+        self.stats.max_fill = self.stats.max_fill.max(fill.min(1.0));
+
+        self.latency = if self.stats.polls == 1 {
+            read_time
+        } else {
+            self.latency.mul_f64(1.0 - Self::SMOOTHING) + read_time.mul_f64(Self::SMOOTHING)
+        };
+        self.stats.read_latency = self.latency;
+
+        // A full FIFO only gives a lower bound on the rate, so don't learn from it
+        if let Some(elapsed) = since_last {
+            let secs = elapsed.as_secs_f64();
+            if !overflow && samples > 0 && secs > 0.0 {
+                let observed = samples as f64 / secs;
+                self.rate_hz = self.rate_hz * (1.0 - Self::SMOOTHING) + observed * Self::SMOOTHING;
+            }
+        }
+
+        self.update_interval();
+        if overflow {
+            self.interval = (self.interval / 2).max(self.min_interval);
+            self.stats.interval = self.interval;
+        }
+    }
+
+    fn update_interval(&mut self) {
+        let fill_time = self.target_fill * self.capacity as f64 / self.rate_hz;
+        let interval = Duration::from_secs_f64(fill_time).saturating_sub(self.latency);
+        self.interval = interval.clamp(self.min_interval, self.max_interval);
+        self.stats.interval = self.interval;
+    }
+
+    pub fn stats(&self) -> &PollStats {
+        &self.stats
+    }
+}
+
 /// Create a horizontal bar graph for a value
 pub fn create_bar(value: f32, max_value: f32, width: usize) -> String {
     let normalized = (value / max_value).clamp(-1.0, 1.0);
@@ -0,0 +1,714 @@
+//! Collector settings from TOML files with named profiles
+//!
+//! A config file holds shared settings at the top level and named
+//! profiles under `[profile.<name>]`. Keys are the collector's long
+//! options with underscores, values are written as on the command line,
+//! and the level trigger sits in a `trigger` table:
+//!
+//! ```toml
+//! mode = "fifo"
+//! i2c_speed = "fast-plus"
+//! storage = "deflate=4,shuffle"
+//!
+//! [profile.impact]
+//! rate = 4000
+//! range = "8g"
+//! output = "hits.h5"
+//! trigger = { level_g = 0.5, band = "5-", pre_secs = 0.5, post_secs = 2 }
+//!
+//! [profile.longterm]
+//! rate = 125
+//! output = "site.h5"
+//! rotate = "daily"
+//! storage = "deflate=6,shuffle,chunk=16384,ticks"
+//! ```
+//!
+//! [`CollectorConfig::resolve`] stacks the built-in defaults, the top
+//! level, the chosen profile and the command line, each overriding the
+//! one before. [`CollectorConfig::to_toml`] writes the result back out as
+//! a config file; the collector keeps it in the `collector_config`
+//! metadata attribute, so `--config` on a copy of it repeats a recording.
+
+use crate::capture::{Band, TriggerConfig, TriggerSource};
+use crate::gpio::{parse_pin, Edge};
+use crate::recovery::RetryPolicy;
+use crate::session::{Rotation, RotationInterval};
+use crate::storage::StorageOptions;
+use crate::{Adxl355Error, DeviceVariant, I2cSpeed, Range, Result, SyncMode};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How the collector reads samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionMode {
+    /// One register read per sample, paced by the host
+    Polling,
+    /// Batches from the sensor FIFO at the ODR
+    Fifo,
+}
+
+impl CollectionMode {
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CollectionMode::Polling => "polling",
+            CollectionMode::Fifo => "fifo",
+        }
+    }
+
+    /// Parse a name produced by [`CollectionMode::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "polling" => Some(CollectionMode::Polling),
+            "fifo" => Some(CollectionMode::Fifo),
+            _ => None,
+        }
+    }
+}
+
+/// Settings of one layer: the top level of a config file, a profile or the
+/// command line. `None` leaves the value of the layer below.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    /// FT232H channel index
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<CollectionMode>,
+    /// Sample rate in Hz; FIFO mode rounds it to an ODR preset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<u32>,
+    /// Seconds to record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<u64>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub sync: Option<SyncMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_test: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp_cal: Option<PathBuf>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub variant: Option<DeviceVariant>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub i2c_speed: Option<I2cSpeed>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// Seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reconnect_timeout: Option<u64>,
+    #[serde(default, with = "pin", skip_serializing_if = "Option::is_none")]
+    pub trigger_in: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger_active_low: Option<bool>,
+    #[serde(default, with = "pin", skip_serializing_if = "Option::is_none")]
+    pub mark_out: Option<u8>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<RotationInterval>,
+    /// MB
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotate_size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_port: Option<u16>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub storage: Option<StorageOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trigger: Option<TriggerProfile>,
+}
+
+/// Level trigger settings of one layer, see [`TriggerConfig`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TriggerProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level_g: Option<f64>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub source: Option<TriggerSource>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub edge: Option<Edge>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hysteresis_g: Option<f64>,
+    #[serde(default, with = "text", skip_serializing_if = "Option::is_none")]
+    pub band: Option<Band>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_secs: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_secs: Option<f64>,
+}
+
+impl Profile {
+    /// These settings with every one that `over` sets replaced
+    pub fn merge(self, over: Profile) -> Profile {
+        Profile {
+            device: over.device.or(self.device),
+            output: over.output.or(self.output),
+            mode: over.mode.or(self.mode),
+            rate: over.rate.or(self.rate),
+            duration: over.duration.or(self.duration),
+            sync: over.sync.or(self.sync),
+            self_test: over.self_test.or(self.self_test),
+            temp_cal: over.temp_cal.or(self.temp_cal),
+            variant: over.variant.or(self.variant),
+            i2c_speed: over.i2c_speed.or(self.i2c_speed),
+            range: over.range.or(self.range),
+            retries: over.retries.or(self.retries),
+            reconnect_timeout: over.reconnect_timeout.or(self.reconnect_timeout),
+            trigger_in: over.trigger_in.or(self.trigger_in),
+            trigger_active_low: over.trigger_active_low.or(self.trigger_active_low),
+            mark_out: over.mark_out.or(self.mark_out),
+            rotate: over.rotate.or(self.rotate),
+            rotate_size: over.rotate_size.or(self.rotate_size),
+            event_port: over.event_port.or(self.event_port),
+            storage: over.storage.or(self.storage),
+            trigger: match (self.trigger, over.trigger) {
+                (Some(base), Some(over)) => Some(base.merge(over)),
+                (base, over) => over.or(base),
+            },
+        }
+    }
+}
+
+impl TriggerProfile {
+    /// These settings with every one that `over` sets replaced
+    pub fn merge(self, over: TriggerProfile) -> TriggerProfile {
+        TriggerProfile {
+            level_g: over.level_g.or(self.level_g),
+            source: over.source.or(self.source),
+            edge: over.edge.or(self.edge),
+            hysteresis_g: over.hysteresis_g.or(self.hysteresis_g),
+            band: over.band.or(self.band),
+            pre_secs: over.pre_secs.or(self.pre_secs),
+            post_secs: over.post_secs.or(self.post_secs),
+        }
+    }
+
+    /// The trigger these settings describe; the level is required and the
+    /// hysteresis defaults to a share of it
+    fn build(&self) -> Result<TriggerConfig> {
+        let level = self.level_g
+            .ok_or_else(|| Adxl355Error::Config("trigger settings given without trigger.level_g".to_string()))?;
+        let defaults = TriggerConfig::new(level);
+        Ok(TriggerConfig {
+            source: self.source.unwrap_or(defaults.source),
+            edge: self.edge.unwrap_or(defaults.edge),
+            hysteresis_g: self.hysteresis_g.unwrap_or(defaults.hysteresis_g),
+            band: self.band.or(defaults.band),
+            pre_secs: self.pre_secs.unwrap_or(defaults.pre_secs),
+            post_secs: self.post_secs.unwrap_or(defaults.post_secs),
+            ..defaults
+        })
+    }
+}
+
+/// A parsed config file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConfigFile {
+    /// Top-level settings, shared by every profile
+    pub base: Profile,
+    /// `[profile.<name>]` tables
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ConfigFile {
+    /// Read and parse a config file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+        Self::parse(&text).map_err(|e| match e {
+            Adxl355Error::Config(message) => Adxl355Error::Config(format!("{}: {}", path.display(), message)),
+            e => e,
+        })
+    }
+
+    /// Parse the text of a config file; unknown keys are errors
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut table: toml::Table = text.parse()
+            .map_err(|e: toml::de::Error| Adxl355Error::Config(e.to_string()))?;
+        let mut profiles = BTreeMap::new();
+        match table.remove("profile") {
+            Some(toml::Value::Table(entries)) => {
+                for (name, entry) in entries {
+                    let profile = entry.try_into()
+                        .map_err(|e: toml::de::Error| Adxl355Error::Config(format!("profile.{}: {}", name, e.message())))?;
+                    profiles.insert(name, profile);
+                }
+            }
+            Some(_) => return Err(Adxl355Error::Config("'profile' must be a table of [profile.<name>] tables".to_string())),
+            None => {}
+        }
+        let base = toml::Value::Table(table).try_into()
+            .map_err(|e: toml::de::Error| Adxl355Error::Config(e.message().to_string()))?;
+        Ok(ConfigFile { base, profiles })
+    }
+
+    /// Top-level settings with profile `name` on top, if one is given
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile> {
+        let Some(name) = name else {
+            return Ok(self.base.clone());
+        };
+        match self.profiles.get(name) {
+            Some(profile) => Ok(self.base.clone().merge(profile.clone())),
+            None => {
+                let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                Err(Adxl355Error::Config(format!(
+                    "no profile '{}' (defined: {})", name,
+                    if names.is_empty() { "none".to_string() } else { names.join(", ") }
+                )))
+            }
+        }
+    }
+}
+
+/// Every collector setting, after defaults, file and command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectorConfig {
+    /// FT232H channel index
+    pub device: u32,
+    pub output: PathBuf,
+    pub mode: CollectionMode,
+    /// Sample rate in Hz; FIFO mode rounds it to an ODR preset
+    pub rate: u32,
+    /// Seconds to record; until stopped if `None`
+    pub duration: Option<u64>,
+    pub sync: SyncMode,
+    pub self_test: bool,
+    pub temp_cal: Option<PathBuf>,
+    pub variant: DeviceVariant,
+    pub i2c_speed: I2cSpeed,
+    /// Measurement range; the part's power-on range if `None`
+    pub range: Option<Range>,
+    pub retries: u32,
+    /// Seconds to keep reconnecting a lost sensor
+    pub reconnect_timeout: u64,
+    pub trigger_in: Option<u8>,
+    pub trigger_active_low: bool,
+    pub mark_out: Option<u8>,
+    pub rotate: Option<RotationInterval>,
+    /// MB
+    pub rotate_size: Option<u64>,
+    pub event_port: Option<u16>,
+    pub storage: StorageOptions,
+    pub trigger: Option<TriggerConfig>,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> Self {
+        CollectorConfig {
+            device: 0,
+            output: PathBuf::from("sensor_data.h5"),
+            mode: CollectionMode::Polling,
+            rate: 100,
+            duration: None,
+            sync: SyncMode::Internal,
+            self_test: false,
+            temp_cal: None,
+            variant: DeviceVariant::Adxl355,
+            i2c_speed: I2cSpeed::FastPlus,
+            range: None,
+            retries: 3,
+            reconnect_timeout: 60,
+            trigger_in: None,
+            trigger_active_low: false,
+            mark_out: None,
+            rotate: None,
+            rotate_size: None,
+            event_port: None,
+            storage: StorageOptions::default(),
+            trigger: None,
+        }
+    }
+}
+
+impl CollectorConfig {
+    /// Defaults, then the top level of `config`, then `profile` from it,
+    /// then `overrides` (the command line)
+    pub fn resolve(config: Option<&Path>, profile: Option<&str>, overrides: &Profile) -> Result<Self> {
+        let file = match config {
+            Some(path) => ConfigFile::load(path)?,
+            None if profile.is_some() => {
+                return Err(Adxl355Error::Config("a profile needs a config file".to_string()));
+            }
+            None => ConfigFile::default(),
+        };
+        Self::from_profile(&file.profile(profile)?.merge(overrides.clone()))
+    }
+
+    /// Defaults with the settings of `profile` applied, checked for conflicts
+    pub fn from_profile(profile: &Profile) -> Result<Self> {
+        let defaults = CollectorConfig::default();
+        let config = CollectorConfig {
+            device: profile.device.unwrap_or(defaults.device),
+            output: profile.output.clone().unwrap_or(defaults.output),
+            mode: profile.mode.unwrap_or(defaults.mode),
+            rate: profile.rate.unwrap_or(defaults.rate),
+            duration: profile.duration.or(defaults.duration),
+            sync: profile.sync.unwrap_or(defaults.sync),
+            self_test: profile.self_test.unwrap_or(defaults.self_test),
+            temp_cal: profile.temp_cal.clone().or(defaults.temp_cal),
+            variant: profile.variant.ok_or_else(|| {
+                Adxl355Error::Config("variant must be given (adxl355 or adxl357): both parts report the same PARTID".to_string())
+            })?,
+            i2c_speed: profile.i2c_speed.unwrap_or(defaults.i2c_speed),
+            range: profile.range.or(defaults.range),
+            retries: profile.retries.unwrap_or(defaults.retries),
+            reconnect_timeout: profile.reconnect_timeout.unwrap_or(defaults.reconnect_timeout),
+            trigger_in: profile.trigger_in.or(defaults.trigger_in),
+            trigger_active_low: profile.trigger_active_low.unwrap_or(defaults.trigger_active_low),
+            mark_out: profile.mark_out.or(defaults.mark_out),
+            rotate: profile.rotate.or(defaults.rotate),
+            rotate_size: profile.rotate_size.or(defaults.rotate_size),
+            event_port: profile.event_port.or(defaults.event_port),
+            storage: profile.storage.unwrap_or(defaults.storage),
+            trigger: profile.trigger.as_ref().map(TriggerProfile::build).transpose()?,
+        };
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Settings that cannot be used together
+    fn check(&self) -> Result<()> {
+        let conflict = |message: &str| Err(Adxl355Error::Config(message.to_string()));
+        if self.rate == 0 {
+            return conflict("rate must be at least 1 Hz");
+        }
+        if let Some(range) = self.range {
+            if !self.variant.ranges().contains(&range) {
+                return Err(Adxl355Error::Config(format!(
+                    "range {} is not available on the {}", range.label(), self.variant.name()
+                )));
+            }
+        }
+        if self.trigger_in.is_some() && self.trigger_in == self.mark_out {
+            return conflict("trigger_in and mark_out must be different pins");
+        }
+        if self.trigger.is_some() && self.mode != CollectionMode::Fifo {
+            return conflict("a level trigger needs mode \"fifo\"");
+        }
+        if self.trigger.is_some() && self.rotation().is_enabled() {
+            return conflict("a level trigger already writes one file per capture, rotate/rotate_size cannot be added");
+        }
+        Ok(())
+    }
+
+    /// All settings as one layer, the inverse of [`CollectorConfig::from_profile`]
+    pub fn to_profile(&self) -> Profile {
+        Profile {
+            device: Some(self.device),
+            output: Some(self.output.clone()),
+            mode: Some(self.mode),
+            rate: Some(self.rate),
+            duration: self.duration,
+            sync: Some(self.sync),
+            self_test: Some(self.self_test),
+            temp_cal: self.temp_cal.clone(),
+            variant: Some(self.variant),
+            i2c_speed: Some(self.i2c_speed),
+            range: self.range,
+            retries: Some(self.retries),
+            reconnect_timeout: Some(self.reconnect_timeout),
+            trigger_in: self.trigger_in,
+            trigger_active_low: Some(self.trigger_active_low),
+            mark_out: self.mark_out,
+            rotate: self.rotate,
+            rotate_size: self.rotate_size,
+            event_port: self.event_port,
+            storage: Some(self.storage),
+            trigger: self.trigger.map(|trigger| TriggerProfile {
+                level_g: Some(trigger.level_g),
+                source: Some(trigger.source),
+                edge: Some(trigger.edge),
+                hysteresis_g: Some(trigger.hysteresis_g),
+                band: trigger.band,
+                pre_secs: Some(trigger.pre_secs),
+                post_secs: Some(trigger.post_secs),
+            }),
+        }
+    }
+
+    /// The settings as a config file, loadable with [`ConfigFile::parse`]
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(&self.to_profile())
+            .map_err(|e| Adxl355Error::Config(format!("cannot write settings as TOML: {}", e)))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.retries,
+            reconnect_timeout: Some(Duration::from_secs(self.reconnect_timeout)),
+            ..RetryPolicy::default()
+        }
+    }
+
+    pub fn rotation(&self) -> Rotation {
+        Rotation {
+            interval: self.rotate,
+            max_bytes: self.rotate_size.map(|mb| mb * 1_000_000),
+        }
+    }
+}
+
+/// Settings written in TOML the way the command line takes them
+trait Text: Sized {
+    fn to_text(&self) -> String;
+    fn from_text(text: &str) -> std::result::Result<Self, String>;
+}
+
+impl Text for CollectionMode {
+    fn to_text(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        CollectionMode::from_name(text).ok_or_else(|| format!("'{}' is not polling or fifo", text))
+    }
+}
+
+impl Text for SyncMode {
+    fn to_text(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        SyncMode::from_name(text)
+            .ok_or_else(|| format!("'{}' is not internal, ext-sync, ext-sync-interp, ext-clk, ext-clk-sync or ext-clk-sync-interp", text))
+    }
+}
+
+impl Text for DeviceVariant {
+    fn to_text(&self) -> String {
+        self.name().to_ascii_lowercase()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        DeviceVariant::from_name(text).ok_or_else(|| format!("'{}' is not adxl355 or adxl357", text))
+    }
+}
+
+impl Text for I2cSpeed {
+    fn to_text(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        I2cSpeed::from_name(text).ok_or_else(|| format!("'{}' is not standard, fast, fast-plus or high-speed", text))
+    }
+}
+
+impl Text for Range {
+    fn to_text(&self) -> String {
+        self.label().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        Range::from_label(text).ok_or_else(|| format!("'{}' is not 2g, 4g, 8g, 10g, 20g or 40g", text))
+    }
+}
+
+impl Text for RotationInterval {
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        RotationInterval::parse(text).ok_or_else(|| format!("'{}' is not hourly, daily or a time like 900s, 30m, 6h", text))
+    }
+}
+
+impl Text for StorageOptions {
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        StorageOptions::parse(text).map_err(|e| e.to_string())
+    }
+}
+
+impl Text for TriggerSource {
+    fn to_text(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        TriggerSource::from_name(text).ok_or_else(|| format!("'{}' is not x, y, z or magnitude", text))
+    }
+}
+
+impl Text for Edge {
+    fn to_text(&self) -> String {
+        self.as_str().to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        Edge::from_name(text).ok_or_else(|| format!("'{}' is not rising, falling or both", text))
+    }
+}
+
+impl Text for Band {
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+
+    fn from_text(text: &str) -> std::result::Result<Self, String> {
+        Band::parse(text).ok_or_else(|| format!("'{}' is not a band like 5-200, 5- or -200 (Hz)", text))
+    }
+}
+
+/// `#[serde(with)]` for optional [`Text`] values
+mod text {
+    use super::Text;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<T: Text, S: Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_str(&value.to_text()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T: Text, D: Deserializer<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        T::from_text(&text).map(Some).map_err(D::Error::custom)
+    }
+}
+
+/// `#[serde(with)]` for optional ACBUS pins, written "c3"
+mod pin {
+    use super::parse_pin;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pin: &Option<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+        match pin {
+            Some(pin) => serializer.serialize_str(&format!("c{}", pin)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        parse_pin(&text)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("'{}' is not an ACBUS pin (0-7, c0-c7 or acbus0-acbus7)", text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = r#"
+mode = "fifo"
+variant = "adxl355"
+rate = 1000
+storage = "deflate=4,shuffle"
+
+[profile.impact]
+rate = 4000
+range = "8g"
+output = "hits.h5"
+trigger = { level_g = 0.5, band = "5-", pre_secs = 0.5, post_secs = 2 }
+
+[profile.longterm]
+rate = 125
+output = "site.h5"
+rotate = "daily"
+storage = "deflate=6,shuffle,chunk=16384,ticks"
+"#;
+
+    #[test]
+    fn profile_and_command_line_override_the_file() {
+        let file = ConfigFile::parse(FILE).unwrap();
+        assert_eq!(file.profiles.keys().collect::<Vec<_>>(), ["impact", "longterm"]);
+
+        let config = CollectorConfig::from_profile(&file.profile(Some("impact")).unwrap()).unwrap();
+        assert_eq!(config.mode, CollectionMode::Fifo);
+        assert_eq!(config.rate, 4000);
+        assert_eq!(config.range, Some(Range::G8));
+        assert_eq!(config.output, PathBuf::from("hits.h5"));
+        assert_eq!(config.storage, StorageOptions::parse("deflate=4,shuffle").unwrap());
+        assert_eq!(config.retries, 3);
+        let trigger = config.trigger.unwrap();
+        assert_eq!((trigger.level_g, trigger.pre_secs, trigger.post_secs), (0.5, 0.5, 2.0));
+        assert_eq!(trigger.band, Band::parse("5-"));
+
+        // A new level on the command line scales the default hysteresis with it
+        let cli = Profile {
+            rate: Some(2000),
+            trigger: Some(TriggerProfile { level_g: Some(1.0), ..TriggerProfile::default() }),
+            ..Profile::default()
+        };
+        let config = CollectorConfig::from_profile(&file.profile(Some("impact")).unwrap().merge(cli)).unwrap();
+        assert_eq!(config.rate, 2000);
+        let trigger = config.trigger.unwrap();
+        assert_eq!((trigger.level_g, trigger.hysteresis_g, trigger.post_secs), (1.0, 0.1, 2.0));
+
+        let config = CollectorConfig::from_profile(&file.profile(None).unwrap()).unwrap();
+        assert_eq!((config.rate, config.trigger), (1000, None));
+        assert!(file.profile(Some("burst")).is_err());
+    }
+
+    #[test]
+    fn resolved_settings_round_trip() {
+        let file = ConfigFile::parse(FILE).unwrap();
+        for name in ["impact", "longterm"] {
+            let config = CollectorConfig::from_profile(&file.profile(Some(name)).unwrap()).unwrap();
+            let written = ConfigFile::parse(&config.to_toml().unwrap()).unwrap();
+            assert!(written.profiles.is_empty());
+            assert_eq!(CollectorConfig::from_profile(&written.base).unwrap(), config);
+        }
+        let config = CollectorConfig { trigger_in: Some(3), mark_out: Some(4), ..CollectorConfig::default() };
+        let written = config.to_toml().unwrap();
+        assert!(written.contains("trigger_in = \"c3\""));
+        assert_eq!(CollectorConfig::from_profile(&ConfigFile::parse(&written).unwrap().base).unwrap(), config);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_bad_values_and_conflicts() {
+        assert!(ConfigFile::parse("rat = 100").is_err());
+        assert!(ConfigFile::parse("mode = \"burst\"").is_err());
+        assert!(ConfigFile::parse("[profile.a]\nrotate = \"weekly\"").is_err());
+        assert!(ConfigFile::parse("[profile.a.trigger]\nlevel = 1.0").is_err());
+        assert!(ConfigFile::parse("profile = 3").is_err());
+
+        // The part cannot be detected, so it has to be named
+        assert!(CollectorConfig::from_profile(&Profile::default()).is_err());
+        let config = |text: &str| {
+            let mut base = ConfigFile::parse(text).unwrap().base;
+            base.variant.get_or_insert(DeviceVariant::Adxl355);
+            CollectorConfig::from_profile(&base)
+        };
+        assert!(config("trigger = { level_g = 1.0 }").is_err());
+        assert!(config("mode = \"fifo\"\ntrigger = { pre_secs = 1.0 }").is_err());
+        assert!(config("mode = \"fifo\"\nrotate = \"hourly\"\ntrigger = { level_g = 1.0 }").is_err());
+        assert!(config("range = \"10g\"").is_err());
+        assert!(config("variant = \"adxl357\"\nrange = \"10g\"").is_ok());
+        assert!(config("trigger_in = \"c3\"\nmark_out = \"acbus3\"").is_err());
+    }
+
+    #[test]
+    fn sensor_and_bus_settings_round_trip() {
+        let text = "variant = \"adxl357\"\nrange = \"40g\"\ni2c_speed = \"high-speed\"\nsync = \"ext-sync\"\nself_test = true\ntemp_cal = \"cal.toml\"";
+        let config = CollectorConfig::from_profile(&ConfigFile::parse(text).unwrap().base).unwrap();
+        assert_eq!((config.variant, config.range), (DeviceVariant::Adxl357, Some(Range::G40)));
+        assert_eq!((config.i2c_speed, config.sync), (I2cSpeed::HighSpeed, SyncMode::ExternalSync));
+        assert!(config.self_test);
+        assert_eq!(config.temp_cal, Some(PathBuf::from("cal.toml")));
+        let written = config.to_toml().unwrap();
+        assert_eq!(CollectorConfig::from_profile(&ConfigFile::parse(&written).unwrap().base).unwrap(), config);
+
+        // Without a setting the bus runs at 1 MHz
+        let default = CollectorConfig::from_profile(&Profile { variant: Some(DeviceVariant::Adxl355), ..Profile::default() }).unwrap();
+        assert_eq!(default.i2c_speed, I2cSpeed::FastPlus);
+        assert!(ConfigFile::parse("i2c_speed = \"3.4mhz\"").is_err());
+        assert!(ConfigFile::parse("sync = \"external\"").is_err());
+    }
+}
@@ -139,6 +139,12 @@ extern "C" {
         sizeTransfered: LPDWORD,
         options: DWORD,
     ) -> FT_STATUS;
+
+    /// Set direction and level of the eight ACBUS pins (1 = output / high)
+    pub fn FT_WriteGPIO(handle: FT_HANDLE, dir: UCHAR, value: UCHAR) -> FT_STATUS;
+
+    /// Read the levels of the eight ACBUS pins
+    pub fn FT_ReadGPIO(handle: FT_HANDLE, value: *mut UCHAR) -> FT_STATUS;
 }
 
 /// Helper function to convert FT_STATUS to a string description
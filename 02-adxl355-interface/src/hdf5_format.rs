@@ -1,9 +1,42 @@
 //! HDF5 file format for ADXL355 sensor data storage
+//!
+//! Writers use the latest HDF5 format and can switch to SWMR mode, after
+//! which [`Hdf5Reader::open_swmr`] / [`Hdf5Reader::follow`] read the file
+//! while it is still growing, or a rotating session through its manifest
+//! as new segments appear. [`Hdf5Writer::close`] stores a completion
+//! marker; files without one can be repaired with [`crate::journal::recover`].
+//! [`Hdf5Writer::create_session`] spreads a long recording over numbered
+//! files that [`Hdf5Reader::open`] joins again via the session manifest.
+//! The file layout and its versions are documented in [`crate::schema`].
+//! Plots of long recordings come from the min/max/mean bins of
+//! [`crate::overview`] through [`Hdf5Reader::read_overview`]. Events and
+//! annotations go to the track described in [`crate::events`]. How the
+//! sample columns are chunked and compressed, and how timestamps are
+//! encoded, is set with [`crate::storage::StorageOptions`].
 
-use crate::{Adxl355Error, Result, SensorData};
-use hdf5::{Dataset, File, Group};
-use std::path::Path;
-use std::time::Instant;
+use crate::events::{self, Event, EventTrack};
+use crate::journal::{journal_path, Journal, JournalHeader};
+use crate::overview::{self, Level, OverviewBin, OverviewWriter, Rebinner};
+use crate::schema::{self, ChannelInfo, CRATE_VERSION, SCHEMA_VERSION};
+use crate::session::{is_manifest, Manifest, Rotation, Session};
+use crate::storage::{self, StorageOptions, TimestampFormat};
+use crate::{Adxl355Error, DeviceInfo, Range, Result, SensorData, TemperatureCalibration};
+use hdf5::types::{FixedUnicode, TypeDescriptor, VarLenUnicode};
+use hdf5::{Dataset, File, Group, Location};
+use std::fs::OpenOptions;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Maximum delay before appended samples are visible to SWMR readers
+const SWMR_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The file is synced to disk and the journal emptied at most this often
+/// while SWMR flushes run
+const JOURNAL_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rows read at a time when binning raw samples
+const READ_BLOCK: usize = 65_536;
 
 /// Sample with timestamp
 #[derive(Debug, Clone)]
@@ -21,6 +54,68 @@ pub struct Metadata {
     pub sensor_type: String,
     pub range: String,
     pub version: String,
+    /// Exact part ("ADXL355", "ADXL357"), absent in older files
+    pub part: Option<String>,
+    /// Silicon revision (REVID), absent in older files
+    pub revision: Option<u8>,
+    /// Machine the recording was made on (schema 2)
+    pub host: Option<String>,
+    /// Library that wrote the file (schema 2)
+    pub crate_version: Option<String>,
+    /// FT232H serial number (schema 2, if the driver reported one)
+    pub device_serial: Option<String>,
+}
+
+/// `completion` metadata attribute: how the writer of a file finished
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// [`Hdf5Writer::close`] was called
+    Complete,
+    /// Written by [`crate::journal::recover`]
+    Recovered,
+    /// No marker: recording still running, interrupted, or an older file
+    Unknown,
+}
+
+impl Completion {
+    fn marker(self) -> &'static str {
+        match self {
+            Completion::Complete => "complete",
+            Completion::Recovered => "recovered",
+            Completion::Unknown => "unknown",
+        }
+    }
+
+    fn from_marker(marker: &str) -> Self {
+        match marker {
+            "complete" => Completion::Complete,
+            "recovered" => Completion::Recovered,
+            _ => Completion::Unknown,
+        }
+    }
+}
+
+/// Gap in the recording, e.g. while the sensor was being reconnected
+#[derive(Debug, Clone)]
+pub struct Discontinuity {
+    /// Seconds since collection start, same clock as the sample timestamps
+    pub start_time: f64,
+    pub end_time: f64,
+    pub cause: String,
+}
+
+impl Discontinuity {
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+}
+
+/// Datasets of the `discontinuities` group, created on first use
+struct DiscontinuityHandles {
+    start_time: Dataset,
+    end_time: Dataset,
+    cause: Dataset,
+    count: usize,
 }
 
 /// Handles for HDF5 datasets
@@ -30,6 +125,100 @@ struct DatasetHandles {
     accel_y: Dataset,
     accel_z: Dataset,
     temperature: Dataset,
+    time: TimestampFormat,
+    /// Rows per chunk; the time search steps through `timestamps` in these units
+    chunk_size: usize,
+}
+
+impl DatasetHandles {
+    fn open(group: &Group) -> Result<Self> {
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open {}", name), e));
+        let timestamps = open("timestamps")?;
+        Ok(DatasetHandles {
+            time: TimestampFormat::of(&timestamps),
+            chunk_size: timestamps.chunk().and_then(|chunk| chunk.first().copied()).unwrap_or(storage::DEFAULT_CHUNK_SIZE),
+            timestamps,
+            accel_x: open("accel_x")?,
+            accel_y: open("accel_y")?,
+            accel_z: open("accel_z")?,
+            temperature: open("temperature")?,
+        })
+    }
+
+    /// Rows written to every column
+    fn len(&self) -> usize {
+        self.all().iter().map(|d| d.size()).min().unwrap_or(0)
+    }
+
+    /// Timestamps of rows `start..end` in seconds
+    fn read_timestamps(&self, start: usize, end: usize) -> Result<Vec<f64>> {
+        match self.time {
+            TimestampFormat::Seconds => {
+                let seconds: Vec<f64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Adxl355Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(seconds)
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Adxl355Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(ticks.into_iter().map(|t| storage::from_ticks(t, rate)).collect())
+            }
+        }
+    }
+
+    /// Rows `start..end` as samples
+    fn read(&self, start: usize, end: usize) -> Result<Vec<TimestampedSample>> {
+        let timestamps = self.read_timestamps(start, end)?;
+        let accel_x: Vec<i32> = self.accel_x.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_x", e))?
+            .to_vec();
+        let accel_y: Vec<i32> = self.accel_y.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_y", e))?
+            .to_vec();
+        let accel_z: Vec<i32> = self.accel_z.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_z", e))?
+            .to_vec();
+        let temperature: Vec<u16> = self.temperature.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read temperature", e))?
+            .to_vec();
+
+        let samples = timestamps.into_iter()
+            .zip(accel_x)
+            .zip(accel_y)
+            .zip(accel_z)
+            .zip(temperature)
+            .map(|((((ts, ax), ay), az), temp)| {
+                TimestampedSample {
+                    timestamp: ts,
+                    data: SensorData {
+                        accel_x: ax,
+                        accel_y: ay,
+                        accel_z: az,
+                        temperature: temp,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(samples)
+    }
+
+    fn get(&self, name: &str) -> &Dataset {
+        match name {
+            "timestamps" => &self.timestamps,
+            "accel_x" => &self.accel_x,
+            "accel_y" => &self.accel_y,
+            "accel_z" => &self.accel_z,
+            _ => &self.temperature,
+        }
+    }
+
+    fn all(&self) -> [&Dataset; 5] {
+        [&self.timestamps, &self.accel_x, &self.accel_y, &self.accel_z, &self.temperature]
+    }
 }
 
 /// HDF5 writer for sensor data collection
@@ -37,51 +226,87 @@ pub struct Hdf5Writer {
     file: File,
     datasets: DatasetHandles,
     start_time: Instant,
+    discontinuities: Option<DiscontinuityHandles>,
+    events: Option<EventTrack>,
     sample_count: usize,
+    swmr: bool,
+    last_flush: Instant,
+    last_checkpoint: Instant,
+    path: PathBuf,
+    metadata: Metadata,
+    journal: Option<Journal>,
+    session: Option<Session>,
+    /// `write_metadata_*` calls, repeated in every new session segment
+    extra_metadata: Vec<(String, ExtraAttr)>,
+    temperature_calibration: Option<TemperatureCalibration>,
+    /// Absent when repairing a file written before the overview existed
+    overview: Option<OverviewWriter>,
+    /// Layout of the sample columns, kept for new segments
+    storage: StorageOptions,
+}
+
+#[derive(Clone)]
+enum ExtraAttr {
+    Str(String),
+    F64(f64),
 }
 
 impl Hdf5Writer {
     /// Create a new HDF5 file for data collection
     pub fn create<P: AsRef<Path>>(path: P, mode: &str, rate: f64, range: &str) -> Result<Self> {
-        let file = File::create(path)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create HDF5 file: {}", e)))?;
+        Self::create_with(path, mode, rate, range, &StorageOptions::default())
+    }
+
+    /// Like [`create`](Self::create), with the sample columns laid out per `storage`
+    pub fn create_with<P: AsRef<Path>>(path: P, mode: &str, rate: f64, range: &str, storage: &StorageOptions) -> Result<Self> {
+        storage.validate()?;
+        let file = File::with_options()
+            .with_fapl(|p| p.libver_latest())
+            .create(path.as_ref())
+            .map_err(|e| Adxl355Error::storage("Failed to create HDF5 file", e))?;
 
         // Create metadata group
         let metadata_group = file.create_group("metadata")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create metadata group: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to create metadata group", e))?;
 
         // Write metadata attributes
         let start_time = chrono::Local::now().to_rfc3339();
         let write_str_attr = |group: &Group, name: &str, value: &str| -> Result<()> {
-            let vlu: hdf5::types::VarLenUnicode = value.parse().unwrap();
+            let vlu = attr_text(name, value)?;
             group.new_attr::<hdf5::types::VarLenUnicode>()
                 .create(name)
                 .and_then(|attr| attr.write_scalar(&vlu))
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write {}: {}", name, e)))
+                .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))
         };
 
         write_str_attr(&metadata_group, "start_time", &start_time)?;
         write_str_attr(&metadata_group, "acquisition_mode", mode)?;
         write_str_attr(&metadata_group, "sensor_type", "adxl355")?;
         write_str_attr(&metadata_group, "range", range)?;
-        write_str_attr(&metadata_group, "version", "1.0")?;
+        write_str_attr(&metadata_group, "version", SCHEMA_VERSION)?;
+        write_str_attr(&metadata_group, "crate_version", CRATE_VERSION)?;
+        let host = schema::host_name();
+        if let Some(host) = &host {
+            write_str_attr(&metadata_group, "host", host)?;
+        }
 
         metadata_group.new_attr::<f64>()
             .create("sample_rate_hz")
             .and_then(|attr| attr.write_scalar(&rate))
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write sample_rate_hz: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to write sample_rate_hz", e))?;
 
         // Create sensor_data group
         let data_group = file.create_group("sensor_data")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create sensor_data group: {}", e)))?;
-
-        let chunk_size = 1024;
+            .map_err(|e| Adxl355Error::storage("Failed to create sensor_data group", e))?;
 
-        let timestamps = Self::create_dataset::<f64>(&data_group, "timestamps", chunk_size)?;
-        let accel_x = Self::create_dataset::<i32>(&data_group, "accel_x", chunk_size)?;
-        let accel_y = Self::create_dataset::<i32>(&data_group, "accel_y", chunk_size)?;
-        let accel_z = Self::create_dataset::<i32>(&data_group, "accel_z", chunk_size)?;
-        let temperature = Self::create_dataset::<u16>(&data_group, "temperature", chunk_size)?;
+        let timestamps = match storage.timestamps {
+            TimestampFormat::Seconds => storage.create_dataset::<f64>(&data_group, "timestamps")?,
+            TimestampFormat::Ticks(_) => storage.create_dataset::<i64>(&data_group, "timestamps")?,
+        };
+        let accel_x = storage.create_dataset::<i32>(&data_group, "accel_x")?;
+        let accel_y = storage.create_dataset::<i32>(&data_group, "accel_y")?;
+        let accel_z = storage.create_dataset::<i32>(&data_group, "accel_z")?;
+        let temperature = storage.create_dataset::<u16>(&data_group, "temperature")?;
 
         let datasets = DatasetHandles {
             timestamps,
@@ -89,23 +314,356 @@ impl Hdf5Writer {
             accel_y,
             accel_z,
             temperature,
+            time: storage.timestamps,
+            chunk_size: storage.chunk_size,
         };
+        let scale_range = Range::from_label(range).unwrap_or(Range::G2);
+        for (name, info) in schema::channels(scale_range, &TemperatureCalibration::NOMINAL) {
+            // The timestamps scaling follows their encoding
+            let info = if name == "timestamps" { datasets.time.channel() } else { info };
+            info.write(datasets.get(name))?;
+        }
+        let overview = OverviewWriter::create(&file)?;
 
         Ok(Self {
             file,
             datasets,
             start_time: Instant::now(),
+            discontinuities: None,
+            events: None,
             sample_count: 0,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.as_ref().to_path_buf(),
+            metadata: Metadata {
+                start_time,
+                sample_rate_hz: rate,
+                acquisition_mode: mode.to_string(),
+                sensor_type: "adxl355".to_string(),
+                range: range.to_string(),
+                version: SCHEMA_VERSION.to_string(),
+                part: None,
+                revision: None,
+                host,
+                crate_version: Some(CRATE_VERSION.to_string()),
+                device_serial: None,
+            },
+            journal: None,
+            session: None,
+            extra_metadata: Vec::new(),
+            temperature_calibration: None,
+            overview: Some(overview),
+            storage: *storage,
+        })
+    }
+
+    /// Create a recording split into segment files according to `rotation`
+    ///
+    /// `data.h5` becomes `data_0001.h5`, `data_0002.h5`, ... with the list
+    /// kept in `data.manifest`. Counts and elapsed time are session totals.
+    /// Every segment is laid out per `storage`.
+    pub fn create_session<P: AsRef<Path>>(
+        output: P, mode: &str, rate: f64, range: &str, rotation: Rotation, storage: &StorageOptions,
+    ) -> Result<Self> {
+        let mut session = Session::new(output.as_ref(), rotation);
+        let mut writer = Self::create_with(session.begin_segment(), mode, rate, range, storage)?;
+        session.set_start_time(&writer.metadata.start_time);
+        session.save_manifest()?;
+        writer.session = Some(session);
+        Ok(writer)
+    }
+
+    /// Manifest of a session writer
+    pub fn manifest_path(&self) -> Option<PathBuf> {
+        self.session.as_ref().map(Session::manifest_path)
+    }
+
+    /// File currently being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Finish the current segment and carry on in the next file
+    fn rotate(&mut self) -> Result<()> {
+        let Some(mut session) = self.session.take() else {
+            return Ok(());
+        };
+        session.samples_before += self.sample_count;
+        session.gaps_before += self.segment_discontinuities();
+        session.events_before += self.segment_events();
+
+        let metadata = &self.metadata;
+        let mut next = Self::create_with(
+            session.begin_segment(), &metadata.acquisition_mode, metadata.sample_rate_hz, &metadata.range, &self.storage,
+        )?;
+        next.set_start_time(&metadata.start_time)?;
+        next.start_time = self.start_time;
+        for (name, value) in &self.extra_metadata {
+            match value {
+                ExtraAttr::Str(value) => next.write_metadata_str(name, value)?,
+                ExtraAttr::F64(value) => next.write_metadata_f64(name, *value)?,
+            }
+        }
+        if let Some(cal) = &self.temperature_calibration {
+            next.write_temperature_calibration(cal)?;
+        }
+        if self.journal.is_some() {
+            next.enable_journal()?;
+        }
+        if self.swmr {
+            next.start_swmr()?;
+        }
+        next.session = Some(session);
+
+        std::mem::replace(self, next).close()?;
+        if let Some(session) = &self.session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Replace the `start_time` taken at creation (RFC 3339), e.g. for data
+    /// recorded earlier by another instrument
+    pub fn set_start_time(&mut self, start_time: &str) -> Result<()> {
+        self.check_not_swmr("start_time")?;
+        let group = self.file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        let value = attr_text("start_time", start_time)?;
+        set_attr(&group, "start_time", &value)?;
+        self.metadata.start_time = start_time.to_string();
+        Ok(())
+    }
+
+    /// Open a file left by an interrupted recording for [`crate::journal::recover`]
+    pub(crate) fn reopen(path: &Path) -> Result<Self> {
+        let file = swmr::open_for_repair(path)
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for repair", e))?;
+        let metadata = Hdf5Reader::read_metadata(&file)?;
+        let data_group = file.group("sensor_data")
+            .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+        let datasets = DatasetHandles::open(&data_group)?;
+
+        let discontinuities = match file.group("discontinuities") {
+            Ok(group) => {
+                let open = |name: &str| group.dataset(name)
+                    .map_err(|e| Adxl355Error::storage(format!("Failed to open discontinuity {}", name), e));
+                let (start_time, end_time, cause) = (open("start_time")?, open("end_time")?, open("cause")?);
+                let count = start_time.size().min(end_time.size()).min(cause.size());
+                Some(DiscontinuityHandles { start_time, end_time, cause, count })
+            }
+            Err(_) => None,
+        };
+        let events = match file.group("events") {
+            Ok(group) => Some(EventTrack::open(&group)?),
+            Err(_) => None,
+        };
+
+        let sample_count = datasets.len();
+        let overview = match file.group("overview") {
+            Ok(_) => Some(OverviewWriter::resume(&file, sample_count, |start, end| datasets.read(start, end))?),
+            Err(_) => None,
+        };
+
+        Ok(Self {
+            file,
+            sample_count,
+            datasets,
+            start_time: Instant::now(),
+            discontinuities,
+            events,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.to_path_buf(),
+            metadata,
+            journal: None,
+            session: None,
+            extra_metadata: Vec::new(),
+            temperature_calibration: None,
+            overview,
+            // Only read for new segments, which a repaired file never gets
+            storage: StorageOptions::default(),
         })
     }
 
-    fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
+    /// New file with the settings from a journal, replacing an unreadable
+    /// one, with the default [`StorageOptions`]
+    pub(crate) fn rebuild(path: &Path, header: &JournalHeader) -> Result<Self> {
+        let mut writer = Self::create(path, &header.acquisition_mode, header.sample_rate_hz, &header.range)?;
+        writer.set_start_time(&header.start_time)?;
+        Ok(writer)
+    }
+
+    /// Cut the columns to `samples` rows and the discontinuities to `gaps`
+    pub(crate) fn truncate(&mut self, samples: usize, gaps: usize) -> Result<()> {
+        for dataset in self.datasets.all() {
+            dataset.resize((samples,))
+                .map_err(|e| Adxl355Error::storage("Failed to truncate dataset", e))?;
+        }
+        self.sample_count = samples;
+        if self.overview.is_some() {
+            let datasets = &self.datasets;
+            self.overview = Some(OverviewWriter::resume(&self.file, samples, |start, end| datasets.read(start, end))?);
+        }
+
+        if let Some(handles) = &mut self.discontinuities {
+            for dataset in [&handles.start_time, &handles.end_time, &handles.cause] {
+                dataset.resize((gaps,))
+                    .map_err(|e| Adxl355Error::storage("Failed to truncate discontinuities", e))?;
+            }
+            handles.count = gaps;
+        }
+        Ok(())
+    }
+
+    /// Journal appended samples and gaps to `<file>.journal` until they are
+    /// flushed (see [`crate::journal`])
+    pub fn enable_journal(&mut self) -> Result<()> {
+        if self.journal.is_some() {
+            return Ok(());
+        }
+        self.flush()?;
+        let header = JournalHeader {
+            sample_base: self.sample_count as u64,
+            gap_base: self.segment_discontinuities() as u64,
+            start_time: self.metadata.start_time.clone(),
+            acquisition_mode: self.metadata.acquisition_mode.clone(),
+            range: self.metadata.range.clone(),
+            sample_rate_hz: self.metadata.sample_rate_hz,
+        };
+        self.journal = Some(Journal::create(journal_path(&self.path), header)?);
+        Ok(())
+    }
+
+    /// Flush and close the file, marking it `completion = "complete"`
+    ///
+    /// Also records the final `sample_count` and removes the journal.
+    /// Dropping the writer instead leaves the file unmarked. A session
+    /// writer closes its last segment and updates the manifest.
+    pub fn close(self) -> Result<()> {
+        self.finish(Completion::Complete)
+    }
+
+    pub(crate) fn finish(mut self, completion: Completion) -> Result<()> {
+        if let Some(overview) = &mut self.overview {
+            overview.finish()?;
+        }
+        self.flush()?;
+        let Hdf5Writer { file, sample_count, path, journal, session, .. } = self;
+        // Closing the file also invalidates the remaining dataset handles
+        file.close()
+            .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+
+        // Attributes cannot be added in SWMR mode, so reopen without it
+        let file = File::open_rw(&path)
+            .map_err(|e| Adxl355Error::storage("Failed to reopen HDF5 file", e))?;
+        let group = file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        set_attr(&group, "sample_count", &(sample_count as u64))?;
+        let marker: hdf5::types::VarLenUnicode = completion.marker().parse().unwrap();
+        set_attr(&group, "completion", &marker)?;
+        file.close()
+            .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+
+        if let Some(journal) = journal {
+            journal.remove()?;
+        }
+        if let Some(session) = session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Enter SWMR mode so the file can be read while samples are appended
+    ///
+    /// Metadata must be written before this: HDF5 does not allow new
+    /// attributes, groups or datasets afterwards.
+    pub fn start_swmr(&mut self) -> Result<()> {
+        if self.swmr {
+            return Ok(());
+        }
+        self.create_discontinuities()?;
+        self.create_events()?;
+        swmr::start_write(&self.file)
+            .map_err(|e| Adxl355Error::storage("Failed to start SWMR write", e))?;
+        self.swmr = true;
+        Ok(())
+    }
+
+    pub fn is_swmr(&self) -> bool {
+        self.swmr
+    }
+
+    fn check_not_swmr(&self, name: &str) -> Result<()> {
+        if self.swmr {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "cannot add metadata '{}' after start_swmr()", name
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
         group.new_dataset::<T>()
             .shape((0..,))
             .chunk((chunk_size,))
             .deflate(4)
             .create(name)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create dataset {}: {}", name, e)))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create dataset {}", name), e))
+    }
+
+    /// Write an extra string attribute into the `metadata` group
+    pub fn write_metadata_str(&mut self, name: &str, value: &str) -> Result<()> {
+        self.check_not_swmr(name)?;
+        let group = self.file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        let vlu = attr_text(name, value)?;
+        group.new_attr::<hdf5::types::VarLenUnicode>()
+            .create(name)
+            .and_then(|attr| attr.write_scalar(&vlu))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::Str(value.to_string())));
+        Ok(())
+    }
+
+    /// Write an extra numeric attribute into the `metadata` group
+    pub fn write_metadata_f64(&mut self, name: &str, value: f64) -> Result<()> {
+        self.check_not_swmr(name)?;
+        let group = self.file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        group.new_attr::<f64>()
+            .create(name)
+            .and_then(|attr| attr.write_scalar(&value))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::F64(value)));
+        Ok(())
+    }
+
+    /// Record the exact part and silicon revision
+    pub fn write_device_info(&mut self, info: &DeviceInfo) -> Result<()> {
+        self.write_metadata_str("part", info.variant.name())?;
+        self.write_metadata_f64("revision", info.revid as f64)
+    }
+
+    /// Record the serial number of the FT232H the sensor is attached to
+    pub fn write_device_serial(&mut self, serial: &str) -> Result<()> {
+        self.write_metadata_str("device_serial", serial)?;
+        self.metadata.device_serial = Some(serial.to_string());
+        Ok(())
+    }
+
+    /// Store the temperature calibration in the metadata and in the
+    /// scaling attributes of the `temperature` column
+    pub fn write_temperature_calibration(&mut self, cal: &TemperatureCalibration) -> Result<()> {
+        self.check_not_swmr("temp_cal_intercept_lsb")?;
+        let group = self.file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        set_attr(&group, "temp_cal_intercept_lsb", &(cal.intercept_lsb as f64))?;
+        set_attr(&group, "temp_cal_slope_lsb_per_c", &(cal.slope_lsb_per_c as f64))?;
+        schema::temperature_channel(cal).write(&self.datasets.temperature)?;
+        self.temperature_calibration = Some(*cal);
+        Ok(())
     }
 
     /// Append a single sample
@@ -118,44 +676,176 @@ impl Hdf5Writer {
         if samples.is_empty() {
             return Ok(());
         }
+        if let Some(journal) = &mut self.journal {
+            journal.append_samples(samples)?;
+        }
 
         let new_size = self.sample_count + samples.len();
 
-        let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
         let accel_x: Vec<i32> = samples.iter().map(|s| s.data.accel_x).collect();
         let accel_y: Vec<i32> = samples.iter().map(|s| s.data.accel_y).collect();
         let accel_z: Vec<i32> = samples.iter().map(|s| s.data.accel_z).collect();
         let temperature: Vec<u16> = samples.iter().map(|s| s.data.temperature).collect();
 
-        self.append_to_dataset(&self.datasets.timestamps, new_size, &timestamps)?;
+        match self.datasets.time {
+            TimestampFormat::Seconds => {
+                let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
+                self.append_to_dataset(&self.datasets.timestamps, new_size, &timestamps)?;
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = samples.iter().map(|s| storage::to_ticks(s.timestamp, rate)).collect();
+                self.append_to_dataset(&self.datasets.timestamps, new_size, &ticks)?;
+            }
+        }
         self.append_to_dataset(&self.datasets.accel_x, new_size, &accel_x)?;
         self.append_to_dataset(&self.datasets.accel_y, new_size, &accel_y)?;
         self.append_to_dataset(&self.datasets.accel_z, new_size, &accel_z)?;
         self.append_to_dataset(&self.datasets.temperature, new_size, &temperature)?;
 
         self.sample_count = new_size;
+        if let Some(overview) = &mut self.overview {
+            overview.push(samples);
+        }
+        if self.swmr && self.last_flush.elapsed() >= SWMR_FLUSH_INTERVAL {
+            self.flush_file()?;
+            if self.last_checkpoint.elapsed() >= JOURNAL_CHECKPOINT_INTERVAL {
+                self.checkpoint()?;
+            }
+        }
+
+        let rotate = match &mut self.session {
+            Some(session) => {
+                session.observe(samples);
+                session.due(&self.path)
+            }
+            None => false,
+        };
+        if rotate {
+            self.rotate()?;
+        }
         Ok(())
     }
 
     fn append_to_dataset<T: hdf5::H5Type>(&self, dataset: &Dataset, new_size: usize, data: &[T]) -> Result<()> {
         dataset.resize((new_size,))
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to resize dataset: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to resize dataset", e))?;
 
         let start = new_size - data.len();
         dataset.write_slice(data, start..)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write to dataset: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to write to dataset", e))?;
 
         Ok(())
     }
 
+    /// Record a gap in the data (times in seconds, same clock as the samples)
+    pub fn write_discontinuity(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        self.create_discontinuities()?;
+        if let Some(journal) = &mut self.journal {
+            journal.append_gap(start_time, end_time, cause)?;
+        }
+        let handles = self.discontinuities.as_ref().unwrap();
+        let new_size = handles.count + 1;
+        let cause = fixed_text::<TEXT_LEN>(cause)?;
+
+        self.append_to_dataset(&handles.start_time, new_size, &[start_time])?;
+        self.append_to_dataset(&handles.end_time, new_size, &[end_time])?;
+        self.append_to_dataset(&handles.cause, new_size, &[cause])?;
+
+        self.discontinuities.as_mut().unwrap().count = new_size;
+        Ok(())
+    }
+
+    fn create_discontinuities(&mut self) -> Result<()> {
+        if self.discontinuities.is_none() {
+            let group = self.file.create_group("discontinuities")
+                .map_err(|e| Adxl355Error::storage("Failed to create discontinuities group", e))?;
+            self.discontinuities = Some(DiscontinuityHandles {
+                start_time: Self::create_dataset::<f64>(&group, "start_time", 16)?,
+                end_time: Self::create_dataset::<f64>(&group, "end_time", 16)?,
+                cause: Self::create_dataset::<FixedUnicode<TEXT_LEN>>(&group, "cause", 16)?,
+                count: 0,
+            });
+        }
+        Ok(())
+    }
+
+    /// Store an event in the `events` group (see [`crate::events`])
+    ///
+    /// `event.time` uses the sample clock; during a session it is written to
+    /// the current segment.
+    pub fn add_event(&mut self, event: &Event) -> Result<()> {
+        self.create_events()?;
+        self.events.as_mut().unwrap().append(event)
+    }
+
+    fn create_events(&mut self) -> Result<()> {
+        if self.events.is_none() {
+            let group = self.file.create_group("events")
+                .map_err(|e| Adxl355Error::storage("Failed to create events group", e))?;
+            self.events = Some(EventTrack::create(&group)?);
+        }
+        Ok(())
+    }
+
+    /// Events added so far, including earlier segments of a session
+    pub fn event_count(&self) -> usize {
+        self.segment_events() + self.session.as_ref().map_or(0, |s| s.events_before)
+    }
+
+    fn segment_events(&self) -> usize {
+        self.events.as_ref().map_or(0, EventTrack::count)
+    }
+
+    /// Gaps recorded so far, including earlier segments of a session
+    pub fn discontinuity_count(&self) -> usize {
+        self.segment_discontinuities() + self.session.as_ref().map_or(0, |s| s.gaps_before)
+    }
+
+    fn segment_discontinuities(&self) -> usize {
+        self.discontinuities.as_ref().map_or(0, |d| d.count)
+    }
+
+    /// Write buffered data to the file and, with a journal, sync the file
+    /// to disk and empty the journal
     pub fn flush(&mut self) -> Result<()> {
+        self.flush_file()?;
+        self.checkpoint()
+    }
+
+    /// Hand buffered data to HDF5, which makes it visible to SWMR readers
+    fn flush_file(&mut self) -> Result<()> {
+        if let Some(overview) = &mut self.overview {
+            overview.flush()?;
+        }
         self.file.flush()
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to flush HDF5 file: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to flush HDF5 file", e))?;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// Sync the file to disk, then empty the journal: it may only drop
+    /// what has reached the disk
+    fn checkpoint(&mut self) -> Result<()> {
+        self.last_checkpoint = Instant::now();
+        let gaps = self.segment_discontinuities();
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        // FlushFileBuffers on Windows needs a handle with write access
+        OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .and_then(|file| file.sync_all())
+            .map_err(|source| Adxl355Error::Io {
+                context: format!("Failed to sync {}", self.path.display()),
+                source,
+            })?;
+        journal.restart(self.sample_count, gaps)?;
         Ok(())
     }
 
     pub fn sample_count(&self) -> usize {
-        self.sample_count
+        self.sample_count + self.session.as_ref().map_or(0, |s| s.samples_before)
     }
 
     pub fn elapsed_secs(&self) -> f64 {
@@ -163,49 +853,214 @@ impl Hdf5Writer {
     }
 }
 
-/// HDF5 reader for accessing collected sensor data
-pub struct Hdf5Reader {
-    #[allow(dead_code)]
+struct Segment {
     file: File,
     datasets: DatasetHandles,
+    overview: Option<Group>,
+}
+
+impl Segment {
+    fn open(file: File) -> Result<Self> {
+        let data_group = file.group("sensor_data")
+            .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+        let datasets = DatasetHandles::open(&data_group)?;
+        let overview = file.group("overview").ok();
+        Ok(Segment { file, datasets, overview })
+    }
+
+    /// Summarise rows `start..end` into `out` from level `factor` where it
+    /// has complete bins, and from the raw rows at either end
+    fn summarize(&self, start: usize, end: usize, factor: Option<usize>, out: &mut Rebinner) -> Result<()> {
+        let level = match (factor, &self.overview) {
+            (Some(factor), Some(group)) => Level::open(group, factor).ok(),
+            _ => None,
+        };
+        let Some(level) = level else {
+            return self.summarize_raw(start, end, out);
+        };
+        let first = start.div_ceil(level.factor);
+        let last = (end / level.factor).min(level.len());
+        if first >= last {
+            return self.summarize_raw(start, end, out);
+        }
+        self.summarize_raw(start, first * level.factor, out)?;
+        for bin in level.read(first, last)? {
+            out.push(bin);
+        }
+        self.summarize_raw(last * level.factor, end, out)
+    }
+
+    fn summarize_raw(&self, start: usize, end: usize, out: &mut Rebinner) -> Result<()> {
+        for from in (start..end).step_by(READ_BLOCK) {
+            let to = (from + READ_BLOCK).min(end);
+            for sample in self.datasets.read(from, to)? {
+                out.push(OverviewBin::from_sample(&sample));
+            }
+        }
+        Ok(())
+    }
+
+    fn completion(&self) -> Completion {
+        self.file.group("metadata")
+            .and_then(|group| group.attr("completion"))
+            .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+            .map(|marker| Completion::from_marker(marker.as_str()))
+            .unwrap_or(Completion::Unknown)
+    }
+
+    fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let group = match self.file.group("discontinuities") {
+            Ok(group) => group,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let read_f64 = |name: &str| -> Result<Vec<f64>> {
+            group.dataset(name)
+                .and_then(|d| d.read_raw::<f64>())
+                .map_err(|e| Adxl355Error::storage(format!("Failed to read discontinuity {}", name), e))
+        };
+        let start_time = read_f64("start_time")?;
+        let end_time = read_f64("end_time")?;
+        let cause = group.dataset("cause")
+            .and_then(|d| read_text(&d))
+            .map_err(|e| Adxl355Error::storage("Failed to read discontinuity cause", e))?;
+
+        Ok(start_time.into_iter()
+            .zip(end_time)
+            .zip(cause)
+            .map(|((start_time, end_time), cause)| Discontinuity {
+                start_time,
+                end_time,
+                cause,
+            })
+            .collect())
+    }
+
+    fn events(&self) -> Result<Vec<Event>> {
+        match self.file.group("events") {
+            Ok(group) => events::read(&group),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    fn timestamp(&self, index: usize) -> Result<f64> {
+        let value = self.datasets.read_timestamps(index, index + 1)?;
+        value.first().copied()
+            .ok_or_else(|| Adxl355Error::InvalidParameter(format!("No timestamp at index {}", index)))
+    }
+
+    /// First row at or after `t` (strictly after with `after`); the row
+    /// count if the file ends earlier
+    ///
+    /// Bisects over the first timestamp of each chunk, then reads the one
+    /// chunk that holds the boundary.
+    fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let before = |ts: f64| if after { ts <= t } else { ts < t };
+        let len = self.datasets.len();
+        if len == 0 || before(self.timestamp(len - 1)?) {
+            return Ok(len);
+        }
+
+        let chunk_size = self.datasets.chunk_size;
+        let (mut low, mut high) = (0, len.div_ceil(chunk_size));
+        while low < high {
+            let mid = (low + high) / 2;
+            if before(self.timestamp(mid * chunk_size)?) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return Ok(0);
+        }
+
+        let start = (low - 1) * chunk_size;
+        let end = (low * chunk_size).min(len);
+        let chunk = self.datasets.read_timestamps(start, end)?;
+        Ok(start + chunk.partition_point(|&ts| before(ts)))
+    }
+}
+
+/// HDF5 reader for accessing collected sensor data
+///
+/// Opened on a session manifest, the reader covers every segment in order
+/// and sample indices continue across file boundaries.
+pub struct Hdf5Reader {
+    segments: Vec<Segment>,
     metadata: Metadata,
+    manifest: Option<Manifest>,
+    /// Manifest re-read on every refresh, for a session opened with `open_swmr`
+    live_manifest: Option<PathBuf>,
 }
 
 impl Hdf5Reader {
+    /// Open a recording: an HDF5 file or a session manifest
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            return Self::open_session(path);
+        }
         let file = File::open(path)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open HDF5 file: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file", e))?;
+        Self::from_file(file)
+    }
 
-        let metadata = Self::read_metadata(&file)?;
+    /// Open all segments listed in a session manifest
+    pub fn open_session<P: AsRef<Path>>(manifest_path: P) -> Result<Self> {
+        Self::from_manifest(manifest_path.as_ref(), |path| File::open(path))
+    }
 
-        let data_group = file.group("sensor_data")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open sensor_data group: {}", e)))?;
+    /// Open a file that is still being written by a SWMR writer
+    ///
+    /// On the manifest of a rotating session, every refresh re-reads the
+    /// manifest and continues into the segments started since.
+    pub fn open_swmr<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            let mut reader = Self::from_manifest(path, swmr::open_read)?;
+            reader.live_manifest = Some(path.to_path_buf());
+            return Ok(reader);
+        }
+        let file = swmr::open_read(path)
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for SWMR read", e))?;
+        Self::from_file(file)
+    }
 
-        let datasets = DatasetHandles {
-            timestamps: data_group.dataset("timestamps")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open timestamps: {}", e)))?,
-            accel_x: data_group.dataset("accel_x")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_x: {}", e)))?,
-            accel_y: data_group.dataset("accel_y")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_y: {}", e)))?,
-            accel_z: data_group.dataset("accel_z")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_z: {}", e)))?,
-            temperature: data_group.dataset("temperature")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open temperature: {}", e)))?,
-        };
+    fn from_file(file: File) -> Result<Self> {
+        let metadata = Self::read_metadata(&file)?;
+        Ok(Self {
+            segments: vec![Segment::open(file)?],
+            metadata,
+            manifest: None,
+            live_manifest: None,
+        })
+    }
+
+    fn from_manifest(manifest_path: &Path, open: fn(&Path) -> hdf5::Result<File>) -> Result<Self> {
+        let manifest = Manifest::load(manifest_path)?;
+        let segments = manifest.segment_paths(manifest_path).iter()
+            .map(|path| Self::open_segment(path, open))
+            .collect::<Result<Vec<_>>>()?;
+        let metadata = Self::read_metadata(&segments[0].file)?;
+        Ok(Self { segments, metadata, manifest: Some(manifest), live_manifest: None })
+    }
 
-        Ok(Self { file, datasets, metadata })
+    fn open_segment(path: &Path, open: fn(&Path) -> hdf5::Result<File>) -> Result<Segment> {
+        open(path)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open segment {}", path.display()), e))
+            .and_then(Segment::open)
     }
 
     fn read_metadata(file: &File) -> Result<Metadata> {
         let group = file.group("metadata")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open metadata: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata", e))?;
 
         let read_str = |name: &str| -> Result<String> {
             group.attr(name)
                 .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
                 .map(|s| s.to_string())
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read {}: {}", name, e)))
+                .map_err(|e| Adxl355Error::storage(format!("Failed to read {}", name), e))
         };
 
         let start_time = read_str("start_time")?;
@@ -216,7 +1071,13 @@ impl Hdf5Reader {
 
         let sample_rate_hz = group.attr("sample_rate_hz")
             .and_then(|attr| attr.read_scalar::<f64>())
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read sample_rate_hz: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to read sample_rate_hz", e))?;
+
+        let part = read_str("part").ok();
+        let revision = group.attr("revision")
+            .and_then(|attr| attr.read_scalar::<f64>())
+            .ok()
+            .map(|v| v as u8);
 
         Ok(Metadata {
             start_time,
@@ -225,6 +1086,11 @@ impl Hdf5Reader {
             sensor_type,
             range,
             version,
+            part,
+            revision,
+            host: read_str("host").ok(),
+            crate_version: read_str("crate_version").ok(),
+            device_serial: read_str("device_serial").ok(),
         })
     }
 
@@ -232,8 +1098,94 @@ impl Hdf5Reader {
         &self.metadata
     }
 
+    /// Session manifest, if opened on one
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Completion marker left by the writer; a session counts as complete
+    /// only if all its segments are
+    pub fn completion(&self) -> Completion {
+        self.segments.iter().map(Segment::completion).fold(Completion::Complete, |all, c| match (all, c) {
+            (Completion::Unknown, _) | (_, Completion::Unknown) => Completion::Unknown,
+            (Completion::Recovered, _) | (_, Completion::Recovered) => Completion::Recovered,
+            _ => Completion::Complete,
+        })
+    }
+
+    /// Temperature calibration recorded with the file, or the datasheet
+    /// nominal for files written without one
+    pub fn temperature_calibration(&self) -> TemperatureCalibration {
+        match self.segments[0].file.group("metadata") {
+            Ok(group) => schema::stored_calibration(&group),
+            Err(_) => TemperatureCalibration::NOMINAL,
+        }
+    }
+
+    /// Units and scaling of `sensor_data` column `name`
+    ///
+    /// Schema 2 files store them as dataset attributes; for version 1 files
+    /// they are derived from `range` and the temperature calibration.
+    pub fn channel_info(&self, name: &str) -> Option<ChannelInfo> {
+        let stored = self.segments[0].file.group("sensor_data")
+            .and_then(|group| group.dataset(name))
+            .ok()
+            .and_then(|dataset| ChannelInfo::read(&dataset));
+        stored.or_else(|| {
+            let range = Range::from_label(&self.metadata.range).unwrap_or(Range::G2);
+            schema::channels(range, &self.temperature_calibration())
+                .into_iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, info)| info)
+        })
+    }
+
+    /// Gaps recorded during collection (empty for files without any)
+    pub fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.discontinuities()?);
+        }
+        Ok(all)
+    }
+
+    /// Events whose time lies in `range`, sorted by time (empty for files
+    /// without an event track)
+    pub fn events<R: RangeBounds<f64>>(&self, range: R) -> Result<Vec<Event>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.events()?.into_iter().filter(|event| range.contains(&event.time)));
+        }
+        all.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(all)
+    }
+
+    /// Number of complete rows (a SWMR writer may be mid-append)
     pub fn get_total_samples(&self) -> Result<usize> {
-        Ok(self.datasets.timestamps.size())
+        Ok(self.segments.iter().map(|s| s.datasets.len()).sum())
+    }
+
+    /// Pick up samples appended since the file was opened or last refreshed,
+    /// and for a followed session the segments it has rotated into
+    pub fn refresh(&mut self) -> Result<usize> {
+        if let Some(manifest_path) = &self.live_manifest {
+            let manifest = Manifest::load(manifest_path)?;
+            for path in manifest.segment_paths(manifest_path).iter().skip(self.segments.len()) {
+                self.segments.push(Self::open_segment(path, swmr::open_read)?);
+            }
+            self.manifest = Some(manifest);
+        }
+        for dataset in self.segments.iter().flat_map(|s| s.datasets.all()) {
+            swmr::refresh(dataset)
+                .map_err(|e| Adxl355Error::storage("Failed to refresh dataset", e))?;
+        }
+        self.get_total_samples()
+    }
+
+    /// Refresh, then read everything from sample `from` onwards
+    pub fn follow(&mut self, from: usize) -> Result<Vec<TimestampedSample>> {
+        let total = self.refresh()?;
+        self.read_range(from, total.saturating_sub(from))
     }
 
     pub fn read_range(&self, start: usize, count: usize) -> Result<Vec<TimestampedSample>> {
@@ -245,39 +1197,19 @@ impl Hdf5Reader {
         let actual_count = count.min(total - start);
         let end = start + actual_count;
 
-        let timestamps: Vec<f64> = self.datasets.timestamps.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read timestamps: {}", e)))?
-            .to_vec();
-        let accel_x: Vec<i32> = self.datasets.accel_x.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_x: {}", e)))?
-            .to_vec();
-        let accel_y: Vec<i32> = self.datasets.accel_y.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_y: {}", e)))?
-            .to_vec();
-        let accel_z: Vec<i32> = self.datasets.accel_z.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_z: {}", e)))?
-            .to_vec();
-        let temperature: Vec<u16> = self.datasets.temperature.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read temperature: {}", e)))?
-            .to_vec();
-
-        let samples = timestamps.into_iter()
-            .zip(accel_x)
-            .zip(accel_y)
-            .zip(accel_z)
-            .zip(temperature)
-            .map(|((((ts, ax), ay), az), temp)| {
-                TimestampedSample {
-                    timestamp: ts,
-                    data: SensorData {
-                        accel_x: ax,
-                        accel_y: ay,
-                        accel_z: az,
-                        temperature: temp,
-                    },
-                }
-            })
-            .collect();
+        let mut samples = Vec::with_capacity(actual_count);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                samples.extend(segment.datasets.read(from - offset, to - offset)?);
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
 
         Ok(samples)
     }
@@ -290,4 +1222,357 @@ impl Hdf5Reader {
         let start = total.saturating_sub(count);
         self.read_range(start, count)
     }
+
+    /// Timestamps of the first and last sample in the recording, `None` if empty
+    pub fn time_span(&self) -> Result<Option<(f64, f64)>> {
+        let mut filled = self.segments.iter().filter(|s| s.datasets.len() > 0);
+        let Some(first) = filled.next() else {
+            return Ok(None);
+        };
+        let last = filled.next_back().unwrap_or(first);
+        Ok(Some((first.timestamp(0)?, last.timestamp(last.datasets.len() - 1)?)))
+    }
+
+    /// Index of the first sample at or after `t` (the sample count if
+    /// there is none)
+    pub fn index_at_time(&self, t: f64) -> Result<usize> {
+        self.search(t, false)
+    }
+
+    pub(crate) fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let index = segment.search(t, after)?;
+            if index < len {
+                return Ok(offset + index);
+            }
+            offset += len;
+        }
+        Ok(offset)
+    }
+
+    /// Samples from `t0` to `t1` inclusive, read without touching the rest
+    /// of the file
+    pub fn read_time_range(&self, t0: f64, t1: f64) -> Result<Vec<TimestampedSample>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        self.read_range(start, end.saturating_sub(start))
+    }
+
+    /// At most about `max_bins` min/max/mean bins covering `t0..=t1`
+    ///
+    /// Uses the coarsest overview level that still yields `max_bins` bins,
+    /// so a plot of a whole week costs about as much as one of a minute.
+    /// Narrow windows return one bin per sample. Rows the overview does
+    /// not cover yet, and files without one, are summarised from the raw
+    /// data.
+    pub fn read_overview(&self, t0: f64, t1: f64, max_bins: usize) -> Result<Vec<OverviewBin>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        if end <= start {
+            return Ok(Vec::new());
+        }
+        let per_bin = (end - start).div_ceil(max_bins.max(1));
+        let factor = overview::FACTORS.iter().rev().copied().find(|&f| f <= per_bin);
+
+        let mut out = Rebinner::new(per_bin);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                segment.summarize(from - offset, to - offset, factor, &mut out)?;
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
+        Ok(out.finish())
+    }
+
+    /// Walk the recording in blocks of at most `size` samples
+    ///
+    /// Memory use is bounded by one block. Samples a SWMR writer appends
+    /// after the call are not visited.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ft232_adxl355_interface::Hdf5Reader;
+    ///
+    /// let reader = Hdf5Reader::open("long_run.h5")?;
+    /// let mut count = 0;
+    /// for chunk in reader.iter_chunks(100_000) {
+    ///     count += chunk?.len();
+    /// }
+    /// # Ok::<(), ft232_adxl355_interface::Adxl355Error>(())
+    /// ```
+    pub fn iter_chunks(&self, size: usize) -> SampleChunks<'_> {
+        SampleChunks {
+            reader: self,
+            position: 0,
+            end: self.get_total_samples().unwrap_or(0),
+            size: size.max(1),
+        }
+    }
+}
+
+/// Blocks of samples, see [`Hdf5Reader::iter_chunks`]
+pub struct SampleChunks<'a> {
+    reader: &'a Hdf5Reader,
+    position: usize,
+    end: usize,
+    size: usize,
+}
+
+impl Iterator for SampleChunks<'_> {
+    type Item = Result<Vec<TimestampedSample>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.end {
+            return None;
+        }
+        let count = self.size.min(self.end - self.position);
+        let chunk = self.reader.read_range(self.position, count);
+        // A failed block ends the iteration
+        self.position = if chunk.is_ok() { self.position + count } else { self.end };
+        Some(chunk)
+    }
+}
+
+/// Create or overwrite a scalar attribute
+pub(crate) fn set_attr<T: hdf5::H5Type>(location: &Location, name: &str, value: &T) -> Result<()> {
+    let attr = match location.attr(name) {
+        Ok(attr) => attr,
+        Err(_) => location.new_attr::<T>().create(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create {}", name), e))?,
+    };
+    attr.write_scalar(value)
+        .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))
+}
+
+/// Convert text for a string attribute, refusing NUL characters
+pub(crate) fn attr_text(name: &str, value: &str) -> Result<VarLenUnicode> {
+    value.parse()
+        .map_err(|_| Adxl355Error::InvalidParameter(format!("{} contains a NUL character", name)))
+}
+
+/// Byte capacity of the short fixed-length text columns (causes, event kinds and labels)
+pub(crate) const TEXT_LEN: usize = 256;
+/// Byte capacity of the event payload column
+pub(crate) const PAYLOAD_LEN: usize = 1024;
+
+/// Convert free text for a fixed-length string column
+///
+/// Variable-length strings cannot be appended once SWMR is on, so text that
+/// grows with the recording is stored fixed-length. NUL bytes are dropped
+/// and text longer than `N` bytes is cut at a character boundary.
+pub(crate) fn fixed_text<const N: usize>(value: &str) -> Result<FixedUnicode<N>> {
+    let mut text: String = value.chars().filter(|&c| c != '\0').collect();
+    if text.len() > N {
+        let mut end = N;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text.parse()
+        .map_err(|e| Adxl355Error::InvalidParameter(format!("Text cannot be stored: {}", e)))
+}
+
+/// Read a text column written either fixed-length or, by older versions, variable-length
+pub(crate) fn read_text(dataset: &Dataset) -> hdf5::Result<Vec<String>> {
+    let descriptor = dataset.dtype()?.to_descriptor()?;
+    Ok(match descriptor {
+        TypeDescriptor::VarLenUnicode | TypeDescriptor::VarLenAscii => dataset
+            .read_raw::<VarLenUnicode>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        TypeDescriptor::FixedUnicode(n) | TypeDescriptor::FixedAscii(n) if n <= TEXT_LEN => dataset
+            .read_raw::<FixedUnicode<TEXT_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        _ => dataset
+            .read_raw::<FixedUnicode<PAYLOAD_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    })
+}
+
+mod swmr {
+    use hdf5::{Dataset, File};
+    use hdf5_sys::{h5, h5d, h5f, h5p};
+    use std::ffi::{c_void, CString};
+    use std::path::Path;
+
+    fn c_path(path: &Path) -> hdf5::Result<CString> {
+        CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| hdf5::Error::from("file name contains a NUL byte"))
+    }
+
+    pub fn start_write(file: &File) -> hdf5::Result<()> {
+        match unsafe { h5f::H5Fstart_swmr_write(file.id()) } {
+            status if status < 0 => Err("H5Fstart_swmr_write failed".into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn refresh(dataset: &Dataset) -> hdf5::Result<()> {
+        match unsafe { h5d::H5Drefresh(dataset.id()) } {
+            status if status < 0 => Err("H5Drefresh failed".into()),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn open_read(path: &Path) -> hdf5::Result<File> {
+        let name = c_path(path)?;
+        let id = unsafe {
+            h5f::H5Fopen(name.as_ptr(), h5f::H5F_ACC_RDONLY | h5f::H5F_ACC_SWMR_READ, h5p::H5P_DEFAULT)
+        };
+        if id < 0 {
+            return Err(format!("H5Fopen failed for {}", path.display()).into());
+        }
+        hdf5::from_id(id)
+    }
+
+    /// Read-write open that clears the "file in use" superblock flags a
+    /// crashed writer leaves behind (same as `h5clear -s`)
+    pub fn open_for_repair(path: &Path) -> hdf5::Result<File> {
+        let name = c_path(path)?;
+        let key = CString::new("clear_status_flags").unwrap();
+        let mut clear: h5::hbool_t = 1;
+        let id = unsafe {
+            if h5::H5open() < 0 {
+                return Err("H5open failed".into());
+            }
+            let fapl = h5p::H5Pcreate(*h5p::H5P_CLS_FILE_ACCESS);
+            if fapl < 0 {
+                return Err("H5Pcreate failed".into());
+            }
+            let id = match h5p::H5Pset(fapl, key.as_ptr(), &mut clear as *mut h5::hbool_t as *mut c_void) {
+                status if status < 0 => -1,
+                _ => h5f::H5Fopen(name.as_ptr(), h5f::H5F_ACC_RDWR, fapl),
+            };
+            h5p::H5Pclose(fapl);
+            id
+        };
+        if id < 0 {
+            return Err(format!("H5Fopen failed for {}", path.display()).into());
+        }
+        hdf5::from_id(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_lookup_with_tick_timestamps_and_gap() {
+        let path = std::env::temp_dir().join(format!("adxl355-i2c-time-range-{}.h5", std::process::id()));
+        let storage = StorageOptions::parse("chunk=8,ticks=1000").unwrap();
+        let mut writer = Hdf5Writer::create_with(&path, "fifo", 1000.0, "2g", &storage).unwrap();
+        // 100 ms of data, a 0.5 s dropout, another 100 ms
+        let samples: Vec<TimestampedSample> = (0..200)
+            .map(|i: i32| TimestampedSample {
+                timestamp: (if i < 100 { i } else { i + 400 }) as f64 / 1000.0,
+                data: SensorData { accel_x: i, accel_y: 0, accel_z: 256_000, temperature: 1852 },
+            })
+            .collect();
+        writer.append_batch(&samples).unwrap();
+        writer.write_discontinuity(0.099, 0.5, "I2C bus stuck").unwrap();
+        writer.close().unwrap();
+
+        let reader = Hdf5Reader::open(&path).unwrap();
+        let span = reader.time_span().unwrap();
+        let in_gap = reader.index_at_time(0.3).unwrap();
+        let exact = reader.index_at_time(0.05).unwrap();
+        let window = reader.read_time_range(0.095, 0.5).unwrap();
+        let gap_only = reader.read_time_range(0.2, 0.4).unwrap();
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(span, Some((0.0, 0.599)));
+        assert_eq!((in_gap, exact), (100, 50));
+        let x: Vec<i32> = window.iter().map(|s| s.data.accel_x).collect();
+        assert_eq!(x, [95, 96, 97, 98, 99, 100]);
+        assert_eq!(window[5].timestamp, 0.5);
+        assert!(gap_only.is_empty());
+    }
+
+    #[test]
+    fn swmr_flushes_keep_the_journal_until_a_checkpoint() {
+        use crate::journal::{journal_path, JournalContents};
+
+        let path = std::env::temp_dir().join(format!("adxl355-i2c-flush-journal-{}.h5", std::process::id()));
+        let journaled = || JournalContents::read(journal_path(&path)).unwrap();
+        let batch = |range: std::ops::Range<i32>| -> Vec<TimestampedSample> {
+            range
+                .map(|i| TimestampedSample {
+                    timestamp: i as f64 / 1000.0,
+                    data: SensorData { accel_x: i, accel_y: 0, accel_z: 256_000, temperature: 1852 },
+                })
+                .collect()
+        };
+        let mut writer = Hdf5Writer::create(&path, "fifo", 1000.0, "2g").unwrap();
+        writer.enable_journal().unwrap();
+        writer.start_swmr().unwrap();
+        writer.append_batch(&batch(0..500)).unwrap();
+        // Past the SWMR flush interval: the file is flushed, the journal kept
+        std::thread::sleep(SWMR_FLUSH_INTERVAL);
+        writer.append_batch(&batch(500..800)).unwrap();
+        assert_eq!(journaled().samples.len(), 800);
+
+        writer.flush().unwrap();
+        let contents = journaled();
+        assert!(contents.samples.is_empty());
+        assert_eq!(contents.header.sample_base, 800);
+        writer.append_batch(&batch(800..1000)).unwrap();
+        writer.close().unwrap();
+        assert!(!journal_path(&path).exists());
+        let total = Hdf5Reader::open(&path).unwrap().get_total_samples().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(total, 1000);
+    }
+
+    #[test]
+    fn following_a_session_continues_into_new_segments() {
+        let dir = std::env::temp_dir().join(format!("adxl355-i2c-follow-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut writer = Hdf5Writer::create_session(dir.join("run.h5"), "fifo", 1000.0, "2g", Rotation::default(), &StorageOptions::default()).unwrap();
+        writer.start_swmr().unwrap();
+        let samples: Vec<TimestampedSample> = (0..150)
+            .map(|i: i32| TimestampedSample {
+                timestamp: i as f64 / 1000.0,
+                data: SensorData { accel_x: i, accel_y: 0, accel_z: 256_000, temperature: 1852 },
+            })
+            .collect();
+        writer.append_batch(&samples[..100]).unwrap();
+        writer.rotate().unwrap();
+        writer.append_batch(&samples[100..]).unwrap();
+        let manifest = writer.manifest_path().unwrap();
+        writer.close().unwrap();
+
+        // Start following while the manifest still lists the first segment only
+        let full = Manifest::load(&manifest).unwrap();
+        let mut first = full.clone();
+        first.segments.truncate(1);
+        first.save(&manifest).unwrap();
+        let mut reader = Hdf5Reader::open_swmr(&manifest).unwrap();
+        let before = reader.follow(0).unwrap();
+        full.save(&manifest).unwrap();
+        let after = reader.follow(before.len()).unwrap();
+        let listed = reader.manifest().map(|m| m.segments.len());
+        drop(reader);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before.len(), 100);
+        let x: Vec<i32> = after.iter().map(|s| s.data.accel_x).collect();
+        assert_eq!(x, (100..150).collect::<Vec<_>>());
+        assert_eq!(listed, Some(2));
+    }
 }
@@ -0,0 +1,474 @@
+//! Sidecar journal for recordings that may be cut short
+//!
+//! Once [`Hdf5Writer::enable_journal`] is called, batches and
+//! discontinuities are first appended to `<file>.journal` and then to the
+//! HDF5 file. The journal is reset whenever the file is synced to disk (on
+//! [`Hdf5Writer::flush`], and every few seconds of SWMR writing), so it
+//! only ever holds what the file may still be missing. [`recover`] trims the file to
+//! the last flush and appends the journal, or writes a fresh file from the
+//! journal when the old one cannot be opened. Metadata written after
+//! `create` (device info, calibration, self-test) is not journaled and is
+//! missing from a rebuilt file.
+//!
+//! On-disk format (little endian): magic `ADXLJRN1`, header (flushed
+//! sample and gap counts, start time, mode, range, rate), then records of
+//! `tag u8, length u32, payload, FNV-1a u32`. Reading stops at the first
+//! incomplete or corrupt record.
+
+use crate::hdf5_format::Completion;
+use crate::{Adxl355Error, Discontinuity, Hdf5Reader, Hdf5Writer, Result, SensorData, TimestampedSample};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"ADXLJRN1";
+const TAG_SAMPLES: u8 = 1;
+const TAG_GAP: u8 = 2;
+/// Timestamp, three 20-bit axes stored as i32, raw temperature
+const SAMPLE_BYTES: usize = 8 + 3 * 4 + 2;
+
+/// Maximum time between fsyncs of the journal
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Where the HDF5 file stood when the journal was started
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalHeader {
+    /// Samples in the file at the last flush
+    pub sample_base: u64,
+    /// Discontinuities in the file at the last flush
+    pub gap_base: u64,
+    pub start_time: String,
+    pub acquisition_mode: String,
+    pub range: String,
+    pub sample_rate_hz: f64,
+}
+
+/// Journal file belonging to an HDF5 file (`<file>.journal`)
+pub fn journal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+fn io_error(context: String) -> impl FnOnce(std::io::Error) -> Adxl355Error {
+    move |source| Adxl355Error::Io { context, source }
+}
+
+/// Journal being written alongside an [`Hdf5Writer`]
+pub(crate) struct Journal {
+    out: File,
+    path: PathBuf,
+    header: JournalHeader,
+    last_sync: Instant,
+}
+
+impl Journal {
+    pub(crate) fn create(path: PathBuf, header: JournalHeader) -> Result<Self> {
+        let out = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(io_error(format!("Cannot create journal {}", path.display())))?;
+
+        let mut journal = Journal { out, path, header, last_sync: Instant::now() };
+        journal.write_header()?;
+        Ok(journal)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend_from_slice(&self.header.sample_base.to_le_bytes());
+        buf.extend_from_slice(&self.header.gap_base.to_le_bytes());
+        put_str(&mut buf, &self.header.start_time);
+        put_str(&mut buf, &self.header.acquisition_mode);
+        put_str(&mut buf, &self.header.range);
+        buf.extend_from_slice(&self.header.sample_rate_hz.to_le_bytes());
+
+        self.out.write_all(&buf)
+            .and_then(|_| self.out.sync_data())
+            .map_err(io_error(format!("Failed to write journal {}", self.path.display())))?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn append(&mut self, tag: u8, payload: &[u8]) -> Result<()> {
+        // A single write_all per record: only the last record can be torn
+        let mut record = Vec::with_capacity(payload.len() + 9);
+        record.push(tag);
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        record.extend_from_slice(&fnv1a(payload).to_le_bytes());
+
+        self.out.write_all(&record)
+            .map_err(io_error(format!("Failed to append to journal {}", self.path.display())))?;
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.out.sync_data()
+                .map_err(io_error(format!("Failed to sync journal {}", self.path.display())))?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_samples(&mut self, samples: &[TimestampedSample]) -> Result<()> {
+        let mut payload = Vec::with_capacity(4 + samples.len() * SAMPLE_BYTES);
+        payload.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        for sample in samples {
+            let d = &sample.data;
+            payload.extend_from_slice(&sample.timestamp.to_le_bytes());
+            for axis in [d.accel_x, d.accel_y, d.accel_z] {
+                payload.extend_from_slice(&axis.to_le_bytes());
+            }
+            payload.extend_from_slice(&d.temperature.to_le_bytes());
+        }
+        self.append(TAG_SAMPLES, &payload)
+    }
+
+    pub(crate) fn append_gap(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&start_time.to_le_bytes());
+        payload.extend_from_slice(&end_time.to_le_bytes());
+        put_str(&mut payload, cause);
+        self.append(TAG_GAP, &payload)
+    }
+
+    /// Everything so far is in the HDF5 file; empty the journal
+    pub(crate) fn restart(&mut self, sample_base: usize, gap_base: usize) -> Result<()> {
+        self.header.sample_base = sample_base as u64;
+        self.header.gap_base = gap_base as u64;
+        self.out.set_len(0)
+            .and_then(|_| self.out.seek(SeekFrom::Start(0)))
+            .map_err(io_error(format!("Failed to reset journal {}", self.path.display())))?;
+        self.write_header()
+    }
+
+    pub(crate) fn remove(self) -> Result<()> {
+        let Journal { out, path, .. } = self;
+        drop(out);
+        std::fs::remove_file(&path).map_err(io_error(format!("Failed to delete journal {}", path.display())))
+    }
+}
+
+fn put_str(buf: &mut Vec<u8>, text: &str) {
+    let mut end = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let bytes = &text.as_bytes()[..end];
+    buf.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811C_9DC5u32, |hash, &b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Little-endian reader over a journal buffer
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+/// Parsed journal of an interrupted recording
+#[derive(Debug, Clone)]
+pub struct JournalContents {
+    pub header: JournalHeader,
+    pub samples: Vec<TimestampedSample>,
+    pub discontinuities: Vec<Discontinuity>,
+    /// Trailing bytes that were not a complete record with a valid checksum
+    pub torn_bytes: usize,
+}
+
+impl JournalContents {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(io_error(format!("Cannot read journal {}", path.display())))?;
+        Self::parse(&bytes).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("{}: not a journal, or header damaged", path.display()))
+        })
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor { buf: bytes, pos: 0 };
+        if cursor.take(MAGIC.len())? != MAGIC {
+            return None;
+        }
+        let header = JournalHeader {
+            sample_base: cursor.u64()?,
+            gap_base: cursor.u64()?,
+            start_time: cursor.string()?,
+            acquisition_mode: cursor.string()?,
+            range: cursor.string()?,
+            sample_rate_hz: cursor.f64()?,
+        };
+
+        let mut contents = JournalContents { header, samples: Vec::new(), discontinuities: Vec::new(), torn_bytes: 0 };
+        while cursor.remaining() > 0 {
+            let start = cursor.pos;
+            if contents.parse_record(&mut cursor).is_none() {
+                contents.torn_bytes = bytes.len() - start;
+                break;
+            }
+        }
+        Some(contents)
+    }
+
+    fn parse_record(&mut self, cursor: &mut Cursor) -> Option<()> {
+        let tag = cursor.take(1)?[0];
+        let len = cursor.u32()? as usize;
+        let payload = cursor.take(len)?;
+        if cursor.u32()? != fnv1a(payload) {
+            return None;
+        }
+
+        let mut fields = Cursor { buf: payload, pos: 0 };
+        match tag {
+            TAG_SAMPLES => {
+                let count = fields.u32()? as usize;
+                if fields.remaining() != count * SAMPLE_BYTES {
+                    return None;
+                }
+                for _ in 0..count {
+                    let timestamp = fields.f64()?;
+                    let data = SensorData {
+                        accel_x: fields.i32()?,
+                        accel_y: fields.i32()?,
+                        accel_z: fields.i32()?,
+                        temperature: fields.u16()?,
+                    };
+                    self.samples.push(TimestampedSample { timestamp, data });
+                }
+            }
+            TAG_GAP => self.discontinuities.push(Discontinuity {
+                start_time: fields.f64()?,
+                end_time: fields.f64()?,
+                cause: fields.string()?,
+            }),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Outcome of [`recover`]
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// Nothing to do: the file carries the completion marker and no journal exists
+    pub already_complete: bool,
+    /// A journal was replayed into the file
+    pub journal_found: bool,
+    /// The file could not be opened, was renamed to `<file>.damaged` and
+    /// replaced by one written from the journal
+    pub rebuilt: bool,
+    pub journal_samples: usize,
+    pub journal_discontinuities: usize,
+    /// Samples that had been flushed but are gone (all of them when rebuilt)
+    pub lost_samples: usize,
+    /// Size of the incomplete record dropped from the journal
+    pub torn_bytes: usize,
+    /// Samples in the file afterwards
+    pub sample_count: usize,
+}
+
+/// Bring a file from an interrupted recording back to a consistent state
+///
+/// A cleanly closed file without a journal is not touched. Otherwise the
+/// columns are trimmed to the last flush (to the shortest column when
+/// there is no journal), the journal is appended and the file is marked
+/// [`Completion::Recovered`].
+pub fn recover<P: AsRef<Path>>(path: P) -> Result<RecoveryReport> {
+    let path = path.as_ref();
+    let journal_file = journal_path(path);
+    let journal = if journal_file.exists() { Some(JournalContents::read(&journal_file)?) } else { None };
+
+    let mut report = RecoveryReport::default();
+    if journal.is_none() {
+        if let Ok(reader) = Hdf5Reader::open(path) {
+            if reader.completion() == Completion::Complete {
+                report.already_complete = true;
+                report.sample_count = reader.get_total_samples()?;
+                return Ok(report);
+            }
+        }
+    }
+
+    let mut writer = match (Hdf5Writer::reopen(path), &journal) {
+        (Ok(writer), _) => writer,
+        (Err(e), None) => return Err(e),
+        (Err(_), Some(contents)) => {
+            let mut damaged = path.as_os_str().to_owned();
+            damaged.push(".damaged");
+            std::fs::rename(path, &damaged)
+                .map_err(io_error(format!("Cannot move damaged file {}", path.display())))?;
+            report.rebuilt = true;
+            Hdf5Writer::rebuild(path, &contents.header)?
+        }
+    };
+
+    match &journal {
+        Some(contents) => {
+            let header = &contents.header;
+            let samples = writer.sample_count().min(header.sample_base as usize);
+            let gaps = writer.discontinuity_count().min(header.gap_base as usize);
+            report.lost_samples += header.sample_base as usize - samples;
+            writer.truncate(samples, gaps)?;
+
+            writer.append_batch(&contents.samples)?;
+            for gap in &contents.discontinuities {
+                writer.write_discontinuity(gap.start_time, gap.end_time, &gap.cause)?;
+            }
+            report.journal_found = true;
+            report.journal_samples = contents.samples.len();
+            report.journal_discontinuities = contents.discontinuities.len();
+            report.torn_bytes = contents.torn_bytes;
+        }
+        None => writer.truncate(writer.sample_count(), writer.discontinuity_count())?,
+    }
+
+    report.sample_count = writer.sample_count();
+    writer.finish(Completion::Recovered)?;
+    if journal.is_some() {
+        std::fs::remove_file(&journal_file)
+            .map_err(io_error(format!("Failed to delete journal {}", journal_file.display())))?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> JournalHeader {
+        JournalHeader {
+            sample_base: 1000,
+            gap_base: 2,
+            start_time: "2026-10-18T09:00:00+02:00".to_string(),
+            acquisition_mode: "fifo".to_string(),
+            range: "8g".to_string(),
+            sample_rate_hz: 500.0,
+        }
+    }
+
+    fn sample(i: i32) -> TimestampedSample {
+        TimestampedSample {
+            timestamp: i as f64 * 0.002,
+            data: SensorData { accel_x: i, accel_y: -i, accel_z: 64_000, temperature: 1852 },
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("adxl355-i2c-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn journal_round_trip() {
+        let path = temp_path("journal.journal");
+        let mut journal = Journal::create(path.clone(), header()).unwrap();
+        journal.append_samples(&[sample(524_287), sample(-524_288)]).unwrap();
+        journal.append_gap(0.5, 0.75, "I2C NACK").unwrap();
+        journal.append_samples(&[sample(3)]).unwrap();
+        let contents = JournalContents::read(&path).unwrap();
+        journal.remove().unwrap();
+
+        assert_eq!(contents.header, header());
+        assert_eq!(contents.samples.len(), 3);
+        assert_eq!(contents.samples[0].data.accel_x, 524_287);
+        assert_eq!(contents.samples[1].data.accel_y, 524_288);
+        assert_eq!(contents.discontinuities[0].cause, "I2C NACK");
+        assert_eq!(contents.torn_bytes, 0);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn restart_drops_flushed_records() {
+        let path = temp_path("restart.journal");
+        let mut journal = Journal::create(path.clone(), header()).unwrap();
+        journal.append_samples(&[sample(1), sample(2)]).unwrap();
+        journal.append_gap(0.5, 0.75, "I2C NACK").unwrap();
+        journal.restart(1002, 3).unwrap();
+        journal.append_samples(&[sample(3)]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        journal.remove().unwrap();
+
+        let contents = JournalContents::parse(&bytes).unwrap();
+        assert_eq!((contents.header.sample_base, contents.header.gap_base), (1002, 3));
+        assert_eq!(contents.samples.len(), 1);
+        assert_eq!(contents.samples[0].data.accel_x, 3);
+        assert!(contents.discontinuities.is_empty());
+
+        // Cutting into the only record leaves the header alone
+        let contents = JournalContents::parse(&bytes[..bytes.len() - 2]).unwrap();
+        assert!(contents.samples.is_empty());
+        assert!(contents.torn_bytes > 0);
+    }
+
+    #[test]
+    fn rebuild_reports_flushed_samples_once() {
+        let path = temp_path("rebuild.h5");
+        std::fs::write(&path, b"not an HDF5 file").unwrap();
+        let mut journal = Journal::create(journal_path(&path), header()).unwrap();
+        journal.append_samples(&[sample(1), sample(2), sample(3)]).unwrap();
+        journal.append_gap(0.004, 0.01, "I2C NACK").unwrap();
+        drop(journal);
+
+        let report = recover(&path).unwrap();
+        let mut damaged = path.as_os_str().to_owned();
+        damaged.push(".damaged");
+        std::fs::remove_file(&damaged).unwrap();
+        let reader = Hdf5Reader::open(&path).unwrap();
+        let gaps = reader.discontinuities().unwrap();
+        let completion = reader.completion();
+        drop(reader);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.rebuilt);
+        assert_eq!(report.lost_samples, 1000);
+        assert_eq!((report.journal_samples, report.sample_count), (3, 3));
+        assert_eq!(gaps.len(), 1);
+        assert_eq!(completion, Completion::Recovered);
+        assert!(!journal_path(&path).exists());
+    }
+}
@@ -3,7 +3,7 @@
 //! Uses FIFO mode for high-speed sampling (~700 Hz over I2C, 4kHz+ with SPI).
 //! Display updates at ~120 Hz, decoupled from read rate.
 
-use ft232_adxl355_interface::{Adxl355, Adxl355Error, OutputDataRate, create_bar};
+use ft232_adxl355_interface::{Adxl355, Adxl355Error, I2cSpeed, OutputDataRate, create_bar};
 use std::io::{self, Write};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -14,7 +14,7 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     println!("=====================");
     println!("Initializing FT232H I2C interface...");
 
-    let mut sensor = match Adxl355::new(0) {
+    let mut sensor = match Adxl355::new(0, I2cSpeed::FastPlus) {
         Ok(s) => {
             println!("Sensor initialized successfully!");
             s
@@ -0,0 +1,383 @@
+//! Min/max/mean summaries of a recording at several resolutions
+//!
+//! [`Hdf5Writer`](crate::Hdf5Writer) reduces the samples on the fly to
+//! bins of each size in [`FACTORS`] and stores them in the `overview`
+//! group next to `sensor_data`:
+//!
+//! ```text
+//! /overview
+//!     level_16, level_256, level_4096, level_65536
+//!         start_time, end_time   f64  timestamps of the first/last sample
+//!         count                  u32  samples in the bin
+//!         <channel>_min/_max     f64  raw LSB, channels as in CHANNELS
+//!         <channel>_mean         f64
+//! ```
+//!
+//! Level `f` holds one bin per `f` consecutive samples of the file, the
+//! last bin of a closed file possibly fewer. Completed bins go to disk at
+//! each flush; [`Hdf5Reader::read_overview`](crate::Hdf5Reader::read_overview)
+//! covers whatever the levels do not reach yet (and files written without
+//! them) from the raw samples.
+
+use crate::hdf5_format::Hdf5Writer;
+use crate::{Adxl355Error, Result, TimestampedSample};
+use hdf5::{Dataset, Group};
+
+/// Samples per bin of each level, finest first
+pub const FACTORS: [usize; 4] = [16, 256, 4096, 65536];
+
+/// Summarised `sensor_data` columns, the order of [`OverviewBin`] arrays
+pub const CHANNELS: [&str; 4] = ["accel_x", "accel_y", "accel_z", "temperature"];
+
+/// Min, max and mean of consecutive samples
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverviewBin {
+    /// Timestamp of the first sample
+    pub start_time: f64,
+    /// Timestamp of the last sample
+    pub end_time: f64,
+    /// Number of samples summarised
+    pub count: usize,
+    /// Smallest raw value per channel
+    pub min: [f64; CHANNELS.len()],
+    /// Largest raw value per channel
+    pub max: [f64; CHANNELS.len()],
+    /// Mean raw value per channel
+    pub mean: [f64; CHANNELS.len()],
+}
+
+impl OverviewBin {
+    /// Bin holding a single sample
+    pub fn from_sample(sample: &TimestampedSample) -> Self {
+        let d = &sample.data;
+        let values = [d.accel_x as f64, d.accel_y as f64, d.accel_z as f64, d.temperature as f64];
+        OverviewBin {
+            start_time: sample.timestamp,
+            end_time: sample.timestamp,
+            count: 1,
+            min: values,
+            max: values,
+            mean: values,
+        }
+    }
+
+    /// Extend this bin by the (later) samples of `other`
+    pub fn merge(&mut self, other: &OverviewBin) {
+        let total = (self.count + other.count) as f64;
+        for i in 0..CHANNELS.len() {
+            self.min[i] = self.min[i].min(other.min[i]);
+            self.max[i] = self.max[i].max(other.max[i]);
+            self.mean[i] = (self.mean[i] * self.count as f64 + other.mean[i] * other.count as f64) / total;
+        }
+        self.end_time = other.end_time;
+        self.count += other.count;
+    }
+}
+
+/// Merges bins in time order into bins of at least `target` samples
+pub(crate) struct Rebinner {
+    target: usize,
+    current: Option<OverviewBin>,
+    bins: Vec<OverviewBin>,
+}
+
+impl Rebinner {
+    pub(crate) fn new(target: usize) -> Self {
+        Rebinner { target: target.max(1), current: None, bins: Vec::new() }
+    }
+
+    pub(crate) fn push(&mut self, bin: OverviewBin) {
+        let current = match self.current.take() {
+            Some(mut current) => {
+                current.merge(&bin);
+                current
+            }
+            None => bin,
+        };
+        if current.count >= self.target {
+            self.bins.push(current);
+        } else {
+            self.current = Some(current);
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Vec<OverviewBin> {
+        self.bins.extend(self.current.take());
+        self.bins
+    }
+}
+
+/// Datasets of one level
+pub(crate) struct Level {
+    pub(crate) factor: usize,
+    start_time: Dataset,
+    end_time: Dataset,
+    count: Dataset,
+    min: Vec<Dataset>,
+    max: Vec<Dataset>,
+    mean: Vec<Dataset>,
+}
+
+impl Level {
+    fn name(factor: usize) -> String {
+        format!("level_{}", factor)
+    }
+
+    fn create(overview: &Group, factor: usize) -> Result<Self> {
+        let group = overview.create_group(&Self::name(factor))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create overview level {}", factor), e))?;
+        let create = |name: &str| Hdf5Writer::create_dataset::<f64>(&group, name, 256);
+        let per_channel = |suffix: &str| -> Result<Vec<Dataset>> {
+            CHANNELS.iter().map(|c| create(&format!("{}_{}", c, suffix))).collect()
+        };
+        Ok(Level {
+            factor,
+            start_time: create("start_time")?,
+            end_time: create("end_time")?,
+            count: Hdf5Writer::create_dataset::<u32>(&group, "count", 256)?,
+            min: per_channel("min")?,
+            max: per_channel("max")?,
+            mean: per_channel("mean")?,
+        })
+    }
+
+    /// Open level `factor` of an `overview` group
+    pub(crate) fn open(overview: &Group, factor: usize) -> Result<Self> {
+        let group = overview.group(&Self::name(factor))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open overview level {}", factor), e))?;
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open overview {}", name), e));
+        let per_channel = |suffix: &str| -> Result<Vec<Dataset>> {
+            CHANNELS.iter().map(|c| open(&format!("{}_{}", c, suffix))).collect()
+        };
+        Ok(Level {
+            factor,
+            start_time: open("start_time")?,
+            end_time: open("end_time")?,
+            count: open("count")?,
+            min: per_channel("min")?,
+            max: per_channel("max")?,
+            mean: per_channel("mean")?,
+        })
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Dataset> {
+        [&self.start_time, &self.end_time, &self.count].into_iter()
+            .chain(&self.min)
+            .chain(&self.max)
+            .chain(&self.mean)
+    }
+
+    /// Bins present in every dataset
+    pub(crate) fn len(&self) -> usize {
+        self.all().map(|d| d.size()).min().unwrap_or(0)
+    }
+
+    fn resize(&self, bins: usize) -> Result<()> {
+        for dataset in self.all() {
+            dataset.resize((bins,))
+                .map_err(|e| Adxl355Error::storage("Failed to resize overview", e))?;
+        }
+        Ok(())
+    }
+
+    fn append(&self, at: usize, bins: &[OverviewBin]) -> Result<()> {
+        let write = |dataset: &Dataset, values: Vec<f64>| {
+            dataset.write_slice(&values, at..)
+                .map_err(|e| Adxl355Error::storage("Failed to write overview", e))
+        };
+        self.resize(at + bins.len())?;
+        write(&self.start_time, bins.iter().map(|b| b.start_time).collect())?;
+        write(&self.end_time, bins.iter().map(|b| b.end_time).collect())?;
+        let counts: Vec<u32> = bins.iter().map(|b| b.count as u32).collect();
+        self.count.write_slice(&counts, at..)
+            .map_err(|e| Adxl355Error::storage("Failed to write overview", e))?;
+        for i in 0..CHANNELS.len() {
+            write(&self.min[i], bins.iter().map(|b| b.min[i]).collect())?;
+            write(&self.max[i], bins.iter().map(|b| b.max[i]).collect())?;
+            write(&self.mean[i], bins.iter().map(|b| b.mean[i]).collect())?;
+        }
+        Ok(())
+    }
+
+    /// Bins `from..to`
+    pub(crate) fn read(&self, from: usize, to: usize) -> Result<Vec<OverviewBin>> {
+        let read = |dataset: &Dataset| -> Result<Vec<f64>> {
+            Ok(dataset.read_slice_1d(from..to)
+                .map_err(|e| Adxl355Error::storage("Failed to read overview", e))?
+                .to_vec())
+        };
+        let columns = |datasets: &[Dataset]| -> Result<Vec<Vec<f64>>> {
+            datasets.iter().map(read).collect()
+        };
+        let start_time = read(&self.start_time)?;
+        let end_time = read(&self.end_time)?;
+        let count: Vec<u32> = self.count.read_slice_1d(from..to)
+            .map_err(|e| Adxl355Error::storage("Failed to read overview", e))?
+            .to_vec();
+        let (min, max, mean) = (columns(&self.min)?, columns(&self.max)?, columns(&self.mean)?);
+
+        Ok((0..to - from).map(|k| OverviewBin {
+            start_time: start_time[k],
+            end_time: end_time[k],
+            count: count[k] as usize,
+            min: std::array::from_fn(|i| min[i][k]),
+            max: std::array::from_fn(|i| max[i][k]),
+            mean: std::array::from_fn(|i| mean[i][k]),
+        }).collect())
+    }
+}
+
+/// Bins of one level being built from incoming samples
+#[derive(Default)]
+struct Accumulator {
+    pending: Option<OverviewBin>,
+    complete: Vec<OverviewBin>,
+}
+
+impl Accumulator {
+    fn push(&mut self, factor: usize, sample: &TimestampedSample) {
+        let bin = OverviewBin::from_sample(sample);
+        let pending = match self.pending.take() {
+            Some(mut pending) => {
+                pending.merge(&bin);
+                pending
+            }
+            None => bin,
+        };
+        if pending.count >= factor {
+            self.complete.push(pending);
+        } else {
+            self.pending = Some(pending);
+        }
+    }
+}
+
+/// Writer side of the pyramid, owned by [`Hdf5Writer`]
+pub(crate) struct OverviewWriter {
+    levels: Vec<(Level, Accumulator, usize)>,
+}
+
+impl OverviewWriter {
+    /// Create the `overview` group with empty levels under `parent`
+    pub(crate) fn create(parent: &Group) -> Result<Self> {
+        let group = parent.create_group("overview")
+            .map_err(|e| Adxl355Error::storage("Failed to create overview group", e))?;
+        let levels = FACTORS.iter()
+            .map(|&factor| Ok((Level::create(&group, factor)?, Accumulator::default(), 0)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(OverviewWriter { levels })
+    }
+
+    /// Continue the pyramid of an existing file holding `samples` rows
+    ///
+    /// Levels are cut back to their complete bins; the samples after the
+    /// last one are fetched with `read(start, end)` to refill the open bin.
+    pub(crate) fn resume<F>(parent: &Group, samples: usize, read: F) -> Result<Self>
+    where
+        F: Fn(usize, usize) -> Result<Vec<TimestampedSample>>,
+    {
+        let group = parent.group("overview")
+            .map_err(|e| Adxl355Error::storage("Failed to open overview group", e))?;
+        let mut levels = Vec::new();
+        for &factor in &FACTORS {
+            let level = Level::open(&group, factor)?;
+            let kept = level.len().min(samples / factor);
+            level.resize(kept)?;
+            let mut acc = Accumulator::default();
+            for sample in read(kept * factor, samples)? {
+                acc.push(factor, &sample);
+            }
+            levels.push((level, acc, kept));
+        }
+        Ok(OverviewWriter { levels })
+    }
+
+    pub(crate) fn push(&mut self, samples: &[TimestampedSample]) {
+        for (level, acc, _) in &mut self.levels {
+            for sample in samples {
+                acc.push(level.factor, sample);
+            }
+        }
+    }
+
+    /// Append the bins completed since the last call
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        for (level, acc, written) in &mut self.levels {
+            if !acc.complete.is_empty() {
+                level.append(*written, &acc.complete)?;
+                *written += acc.complete.len();
+                acc.complete.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Append everything, including the partly filled last bins
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        for (_, acc, _) in &mut self.levels {
+            acc.complete.extend(acc.pending.take());
+        }
+        self.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SensorData;
+
+    fn sample(i: usize) -> TimestampedSample {
+        let v = i as i32;
+        TimestampedSample {
+            timestamp: i as f64 / 500.0,
+            data: SensorData { accel_x: v * 1000, accel_y: -524_288, accel_z: 256_000, temperature: 1800 + i as u16 },
+        }
+    }
+
+    #[test]
+    fn merge_weights_mean_by_count() {
+        let mut bin = OverviewBin::from_sample(&sample(0));
+        bin.merge(&OverviewBin::from_sample(&sample(1)));
+        bin.merge(&OverviewBin::from_sample(&sample(2)));
+        bin.merge(&OverviewBin::from_sample(&sample(9)));
+
+        assert_eq!(bin.count, 4);
+        assert_eq!((bin.start_time, bin.end_time), (0.0, 0.018));
+        assert_eq!((bin.min[0], bin.max[0], bin.mean[0]), (0.0, 9000.0, 3000.0));
+        assert_eq!((bin.min[1], bin.max[1]), (-524_288.0, -524_288.0));
+        assert_eq!(bin.mean[3], 1803.0);
+    }
+
+    #[test]
+    fn resume_refills_open_bins() {
+        let path = std::env::temp_dir().join(format!("adxl355-i2c-overview-{}.h5", std::process::id()));
+        let file = hdf5::File::create(&path).unwrap();
+        let samples: Vec<TimestampedSample> = (0..72).map(sample).collect();
+
+        let mut writer = OverviewWriter::create(&file).unwrap();
+        writer.push(&samples[..40]);
+        writer.flush().unwrap();
+        drop(writer);
+
+        // Reopened after 40 rows: two complete bins of 16 stay, 8 rows are re-read
+        let mut writer = OverviewWriter::resume(&file, 40, |from, to| Ok(samples[from..to].to_vec())).unwrap();
+        writer.push(&samples[40..]);
+        writer.finish().unwrap();
+
+        let overview = file.group("overview").unwrap();
+        let level = Level::open(&overview, 16).unwrap();
+        let bins = level.read(0, level.len()).unwrap();
+        let coarse = Level::open(&overview, 256).unwrap();
+        let coarse_bins = coarse.read(0, coarse.len()).unwrap();
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(bins.iter().map(|b| b.count).collect::<Vec<_>>(), vec![16, 16, 16, 16, 8]);
+        assert_eq!(bins[2].start_time, 32.0 / 500.0);
+        assert_eq!((bins[2].min[0], bins[2].max[0]), (32_000.0, 47_000.0));
+        assert_eq!(coarse_bins.len(), 1);
+        assert_eq!(coarse_bins[0].count, 72);
+        assert!((coarse_bins[0].mean[3] - 1835.5).abs() < 1e-9);
+    }
+}
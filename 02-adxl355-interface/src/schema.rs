@@ -0,0 +1,270 @@
+//! HDF5 schema of ADXL355 recordings
+//!
+//! Version 2 (`metadata/version = "2.2"`):
+//!
+//! ```text
+//! /metadata                    attributes
+//!     version            str   "2.2"
+//!     start_time         str   RFC 3339 wall-clock time of t = 0
+//!     sample_rate_hz     f64   output data rate
+//!     acquisition_mode   str   "polling", "fifo" or "import"
+//!     sensor_type        str   "adxl355"
+//!     range              str   "2g", "4g", "8g" (ADXL357: "10g", "20g", "40g")
+//!     host               str   recording machine, if known
+//!     crate_version      str   e.g. "ft232_adxl355_interface 0.1.0"
+//!     device_serial      str   FT232H serial number, if known
+//!     part, revision, sync_mode, temp_cal_*, self_test*, ...  as written
+//!                              by the collector
+//!     sample_count, completion set on close / recover
+//!     source_sensor, source_file, source_scale  imports, see crate::import
+//! /sensor_data                 one row per sample
+//!     timestamps         f64   s since start_time (i64 ticks with
+//!                              StorageOptions::timestamps, see crate::storage)
+//!     accel_x/y/z        i32   20-bit raw, LSB/g depends on range
+//!     temperature        u16   raw TEMP2:TEMP1
+//! /discontinuities             start_time, end_time (f64 s), cause (str, 256 bytes)
+//! /overview                    binned min/max/mean, see crate::overview
+//! /events                      time, kind, label, payload; see crate::events
+//! ```
+//!
+//! Each `sensor_data` dataset carries the attributes of [`ChannelInfo`]:
+//! `units`, `scale_factor` and `offset` (value = raw * scale_factor +
+//! offset), `full_scale` and `axis`. Acceleration is in g along the axes
+//! marked on the ADXL355 package (right-handed, +Z out of the lid); the
+//! temperature column converts to degrees C with the calibration in use.
+//!
+//! The `overview` group is new in 2.1 and optional: readers summarise 2.0
+//! and migrated 1.0 files from `sensor_data` instead, and [`migrate`] does
+//! not touch any 2.x file. The `events` group (2.2) is optional as well.
+//! Text columns that grow during a recording are fixed-length so they can
+//! be appended under SWMR; older files stored them variable-length and
+//! read the same.
+//!
+//! Version 1.0 files lack the dataset attributes and `host`,
+//! `crate_version`, `device_serial`. The reader derives the column scaling
+//! from their `range` and `temp_cal_*` metadata instead; [`migrate`] writes
+//! it into the file.
+
+use crate::hdf5_format::set_attr;
+use crate::{Adxl355Error, Range, Result, TemperatureCalibration};
+use hdf5::{Dataset, File, Group};
+use std::path::Path;
+
+/// Schema version of newly written files
+pub const SCHEMA_VERSION: &str = "2.2";
+
+/// Written as `crate_version`
+pub(crate) const CRATE_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// Description of one `sensor_data` column (dataset attributes)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    /// "s", "g" or "degC"
+    pub units: String,
+    /// `units` per LSB
+    pub scale_factor: f64,
+    /// Added after scaling (temperature only)
+    pub offset: f64,
+    /// Measurement limit in `units`, 0 where there is none
+    pub full_scale: f64,
+    /// "+X", "+Y", "+Z", or empty
+    pub axis: String,
+}
+
+impl ChannelInfo {
+    /// Raw value in `units`
+    pub fn to_physical(&self, raw: f64) -> f64 {
+        raw * self.scale_factor + self.offset
+    }
+
+    pub(crate) fn write(&self, dataset: &Dataset) -> Result<()> {
+        let units: hdf5::types::VarLenUnicode = self.units.parse().unwrap();
+        let axis: hdf5::types::VarLenUnicode = self.axis.parse().unwrap();
+        set_attr(dataset, "units", &units)?;
+        set_attr(dataset, "scale_factor", &self.scale_factor)?;
+        set_attr(dataset, "offset", &self.offset)?;
+        set_attr(dataset, "full_scale", &self.full_scale)?;
+        set_attr(dataset, "axis", &axis)
+    }
+
+    /// Read the attributes back; `None` for a version 1 dataset
+    pub(crate) fn read(dataset: &Dataset) -> Option<Self> {
+        let text = |name: &str| -> Option<String> {
+            dataset.attr(name).ok()?
+                .read_scalar::<hdf5::types::VarLenUnicode>().ok()
+                .map(|s| s.to_string())
+        };
+        let number = |name: &str| -> Option<f64> {
+            dataset.attr(name).ok()?.read_scalar::<f64>().ok()
+        };
+        Some(ChannelInfo {
+            units: text("units")?,
+            scale_factor: number("scale_factor")?,
+            offset: number("offset").unwrap_or(0.0),
+            full_scale: number("full_scale").unwrap_or(0.0),
+            axis: text("axis").unwrap_or_default(),
+        })
+    }
+}
+
+/// Column descriptions for a recording at `range` with temperature
+/// calibration `cal`
+pub fn channels(range: Range, cal: &TemperatureCalibration) -> Vec<(&'static str, ChannelInfo)> {
+    let accel = |axis: &str| ChannelInfo {
+        units: "g".to_string(),
+        scale_factor: 1.0 / range.scale_factor() as f64,
+        offset: 0.0,
+        full_scale: range.full_scale_g() as f64,
+        axis: axis.to_string(),
+    };
+    vec![
+        ("timestamps", ChannelInfo {
+            units: "s".to_string(),
+            scale_factor: 1.0,
+            offset: 0.0,
+            full_scale: 0.0,
+            axis: String::new(),
+        }),
+        ("accel_x", accel("+X")),
+        ("accel_y", accel("+Y")),
+        ("accel_z", accel("+Z")),
+        ("temperature", temperature_channel(cal)),
+    ]
+}
+
+/// T = 25 C + (raw - intercept) / slope
+pub(crate) fn temperature_channel(cal: &TemperatureCalibration) -> ChannelInfo {
+    let slope = cal.slope_lsb_per_c as f64;
+    ChannelInfo {
+        units: "degC".to_string(),
+        scale_factor: 1.0 / slope,
+        offset: 25.0 - cal.intercept_lsb as f64 / slope,
+        full_scale: 0.0,
+        axis: String::new(),
+    }
+}
+
+/// Major number of a `version` attribute; unparsable counts as 1
+pub fn major_version(version: &str) -> u32 {
+    version.split('.').next().and_then(|v| v.trim().parse().ok()).unwrap_or(1)
+}
+
+/// Host name for the `host` attribute
+pub(crate) fn host_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Bring a version 1 file up to [`SCHEMA_VERSION`] in place
+///
+/// The column attributes are derived from the file's `range` and
+/// `temp_cal_*` metadata (nominal calibration if absent). The old version
+/// is kept as `migrated_from`. Returns `false` if there was nothing to do.
+pub fn migrate<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let file = File::open_rw(path.as_ref())
+        .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for migration", e))?;
+    let metadata = file.group("metadata")
+        .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+    let read_str = |name: &str| -> Result<String> {
+        metadata.attr(name)
+            .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+            .map(|s| s.to_string())
+            .map_err(|e| Adxl355Error::storage(format!("Failed to read {}", name), e))
+    };
+    let version = read_str("version")?;
+    if major_version(&version) >= major_version(SCHEMA_VERSION) {
+        return Ok(false);
+    }
+    let range = Range::from_label(&read_str("range")?).unwrap_or(Range::G2);
+    let cal = stored_calibration(&metadata);
+
+    let data_group = file.group("sensor_data")
+        .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+    for (name, info) in channels(range, &cal) {
+        let dataset = data_group.dataset(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open {}", name), e))?;
+        info.write(&dataset)?;
+    }
+
+    let text = |value: &str| -> hdf5::types::VarLenUnicode { value.parse().unwrap() };
+    set_attr(&metadata, "migrated_from", &text(&version))?;
+    set_attr(&metadata, "migrated_by", &text(CRATE_VERSION))?;
+    set_attr(&metadata, "version", &text(SCHEMA_VERSION))?;
+    file.close()
+        .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+    Ok(true)
+}
+
+/// `temp_cal_*` attributes of a metadata group, or the datasheet nominal
+pub(crate) fn stored_calibration(metadata: &Group) -> TemperatureCalibration {
+    let read = |name: &str| -> Option<f32> {
+        metadata.attr(name).ok()?.read_scalar::<f64>().ok().map(|v| v as f32)
+    };
+    match (read("temp_cal_intercept_lsb"), read("temp_cal_slope_lsb_per_c")) {
+        (Some(intercept_lsb), Some(slope_lsb_per_c)) if slope_lsb_per_c != 0.0 => {
+            TemperatureCalibration { intercept_lsb, slope_lsb_per_c }
+        }
+        _ => TemperatureCalibration::NOMINAL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Hdf5Writer;
+
+    #[test]
+    fn adxl357_range_scaling() {
+        let info = channels(Range::G40, &TemperatureCalibration::NOMINAL);
+        let names: Vec<&str> = info.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["timestamps", "accel_x", "accel_y", "accel_z", "temperature"]);
+
+        let accel_y = &info[2].1;
+        assert_eq!(accel_y.axis, "+Y");
+        assert!((accel_y.to_physical(12_800.0) - 1.0).abs() < 1e-12);
+        assert!((accel_y.full_scale - 40.96).abs() < 1e-5);
+        assert_eq!(info[0].1.units, "s");
+        assert!((info[4].1.to_physical(1885.0) - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn migrate_version_1_file() {
+        let path = std::env::temp_dir().join(format!("adxl355-i2c-schema-{}.h5", std::process::id()));
+        {
+            let text = |value: &str| -> hdf5::types::VarLenUnicode { value.parse().unwrap() };
+            let file = File::create(&path).unwrap();
+            let metadata = file.create_group("metadata").unwrap();
+            set_attr(&metadata, "version", &text("1.0")).unwrap();
+            set_attr(&metadata, "range", &text("4g")).unwrap();
+            // A zero slope is unusable; the nominal calibration is used instead
+            set_attr(&metadata, "temp_cal_intercept_lsb", &1900.0f64).unwrap();
+            set_attr(&metadata, "temp_cal_slope_lsb_per_c", &0.0f64).unwrap();
+            let data = file.create_group("sensor_data").unwrap();
+            for name in ["timestamps", "accel_x", "accel_y", "accel_z", "temperature"] {
+                Hdf5Writer::create_dataset::<f64>(&data, name, 16).unwrap();
+            }
+        }
+
+        assert!(migrate(&path).unwrap());
+        assert!(!migrate(&path).unwrap());
+
+        let file = File::open(&path).unwrap();
+        let metadata = file.group("metadata").unwrap();
+        let version = metadata.attr("version").unwrap().read_scalar::<hdf5::types::VarLenUnicode>().unwrap();
+        let from = metadata.attr("migrated_from").unwrap().read_scalar::<hdf5::types::VarLenUnicode>().unwrap();
+        let data = file.group("sensor_data").unwrap();
+        let accel_z = ChannelInfo::read(&data.dataset("accel_z").unwrap()).unwrap();
+        let temperature = ChannelInfo::read(&data.dataset("temperature").unwrap()).unwrap();
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(version.as_str(), SCHEMA_VERSION);
+        assert_eq!(from.as_str(), "1.0");
+        assert_eq!(accel_z, channels(Range::G4, &TemperatureCalibration::NOMINAL)[3].1);
+        assert_eq!(temperature, temperature_channel(&TemperatureCalibration::NOMINAL));
+    }
+}
@@ -0,0 +1,350 @@
+//! Long recordings as a series of HDF5 files
+//!
+//! A writer from [`Hdf5Writer::create_session`] closes the current file and
+//! opens the next (`data_0001.h5`, `data_0002.h5`, ...) whenever a
+//! [`Rotation`] limit is hit. Each segment carries the full metadata,
+//! including attributes added with `write_metadata_*`, and the session's
+//! `start_time`, so timestamps run on across files. The segment list with
+//! time ranges and sample counts goes to `data.manifest`, which
+//! [`Hdf5Reader::open`](crate::Hdf5Reader::open) reads as one recording.
+//!
+//! [`Hdf5Writer::create_session`]: crate::Hdf5Writer::create_session
+
+use crate::{Adxl355Error, Result, TimestampedSample};
+use chrono::Timelike;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the size of the current segment is checked
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When to start the next segment by time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationInterval {
+    /// At the top of every hour (local time)
+    Hourly,
+    /// At local midnight
+    Daily,
+    /// A fixed length after the segment was started
+    Every(Duration),
+}
+
+impl RotationInterval {
+    /// "hourly", "daily", or a length such as "900", "900s", "15m", "6h"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        match text.as_str() {
+            "hourly" => return Some(RotationInterval::Hourly),
+            "daily" => return Some(RotationInterval::Daily),
+            _ => {}
+        }
+        let (number, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+            Some((i, _)) => text.split_at(i),
+            None => (text.as_str(), "s"),
+        };
+        let scale = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            _ => return None,
+        };
+        match number.parse::<u64>() {
+            Ok(n) if n > 0 => Some(RotationInterval::Every(Duration::from_secs(n * scale))),
+            _ => None,
+        }
+    }
+
+    /// Time from now until a segment started now should end
+    fn remaining(&self) -> Duration {
+        let into_day = chrono::Local::now().num_seconds_from_midnight() as u64;
+        match self {
+            RotationInterval::Hourly => Duration::from_secs(3600 - into_day % 3600),
+            RotationInterval::Daily => Duration::from_secs(86_400u64.saturating_sub(into_day).max(1)),
+            RotationInterval::Every(length) => *length,
+        }
+    }
+}
+
+/// Same form as [`RotationInterval::parse`] takes, e.g. "daily" or "15m"
+impl fmt::Display for RotationInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationInterval::Hourly => write!(f, "hourly"),
+            RotationInterval::Daily => write!(f, "daily"),
+            RotationInterval::Every(length) => match length.as_secs() {
+                secs if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+                secs if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+                secs => write!(f, "{}s", secs),
+            },
+        }
+    }
+}
+
+/// Limits that end a segment; whichever is reached first counts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rotation {
+    pub interval: Option<RotationInterval>,
+    /// Segment file size in bytes
+    pub max_bytes: Option<u64>,
+}
+
+impl Rotation {
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// One file of a session, as listed in the manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentInfo {
+    /// File name, relative to the manifest
+    pub file: String,
+    /// Wall-clock time the segment was started (RFC 3339)
+    pub start_time: String,
+    /// First and last sample timestamp (seconds since session start),
+    /// NaN while the segment has no samples
+    pub first_timestamp: f64,
+    pub last_timestamp: f64,
+    pub samples: u64,
+}
+
+/// Segment list of a session, stored as `<name>.manifest`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    /// Start of the session, the `start_time` of every segment
+    pub start_time: String,
+    pub segments: Vec<SegmentInfo>,
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+        let malformed = |line: &str| Adxl355Error::InvalidParameter(format!("Malformed line in {}: {}", path.display(), line));
+
+        let mut manifest = Manifest::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| malformed(line))?;
+            match key.trim() {
+                "start_time" => manifest.start_time = value.trim().to_string(),
+                "segment" => {
+                    let fields: Vec<&str> = value.split('|').map(str::trim).collect();
+                    let [file, start_time, first, last, samples] = fields[..] else {
+                        return Err(malformed(line));
+                    };
+                    manifest.segments.push(SegmentInfo {
+                        file: file.to_string(),
+                        start_time: start_time.to_string(),
+                        first_timestamp: first.parse().map_err(|_| malformed(line))?,
+                        last_timestamp: last.parse().map_err(|_| malformed(line))?,
+                        samples: samples.parse().map_err(|_| malformed(line))?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        if manifest.segments.is_empty() {
+            return Err(Adxl355Error::InvalidParameter(format!("{} lists no segments", path.display())));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = format!("# ADXL355 recording session\nstart_time = {}\n", self.start_time);
+        text.push_str("# segment = file | started | first timestamp | last timestamp | samples\n");
+        for s in &self.segments {
+            text.push_str(&format!(
+                "segment = {} | {} | {:.6} | {:.6} | {}\n",
+                s.file, s.start_time, s.first_timestamp, s.last_timestamp, s.samples
+            ));
+        }
+        // Written next to the old one and renamed, so a reader never sees half a manifest
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to write {}", path.display()), source: e })
+    }
+
+    /// Segment files, resolved against the directory of the manifest
+    pub fn segment_paths<P: AsRef<Path>>(&self, manifest_path: P) -> Vec<PathBuf> {
+        let dir = manifest_path.as_ref().parent().unwrap_or(Path::new(""));
+        self.segments.iter().map(|s| dir.join(&s.file)).collect()
+    }
+}
+
+/// `data.h5` -> `data.manifest`
+pub fn manifest_path<P: AsRef<Path>>(output: P) -> PathBuf {
+    output.as_ref().with_extension("manifest")
+}
+
+/// `data.h5`, 3 -> `data_0003.h5`
+pub fn segment_path<P: AsRef<Path>>(output: P, index: usize) -> PathBuf {
+    let output = output.as_ref();
+    let stem = output.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let ext = output.extension().map(|e| e.to_string_lossy()).unwrap_or("h5".into());
+    output.with_file_name(format!("{}_{:04}.{}", stem, index, ext))
+}
+
+/// Whether `path` names a session manifest rather than an HDF5 file
+pub fn is_manifest<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "manifest")
+}
+
+/// Rotation state of a writer created with `Hdf5Writer::create_session`
+pub(crate) struct Session {
+    output: PathBuf,
+    rotation: Rotation,
+    manifest: Manifest,
+    deadline: Option<Instant>,
+    next_size_check: Instant,
+    /// Samples, discontinuities and events in the segments before the current one
+    pub(crate) samples_before: usize,
+    pub(crate) gaps_before: usize,
+    pub(crate) events_before: usize,
+}
+
+impl Session {
+    pub(crate) fn new(output: &Path, rotation: Rotation) -> Self {
+        Session {
+            output: output.to_path_buf(),
+            rotation,
+            manifest: Manifest::default(),
+            deadline: None,
+            next_size_check: Instant::now(),
+            samples_before: 0,
+            gaps_before: 0,
+            events_before: 0,
+        }
+    }
+
+    pub(crate) fn set_start_time(&mut self, start_time: &str) {
+        self.manifest.start_time = start_time.to_string();
+    }
+
+    /// Add the next segment to the manifest and return its path
+    pub(crate) fn begin_segment(&mut self) -> PathBuf {
+        let path = segment_path(&self.output, self.manifest.segments.len() + 1);
+        self.manifest.segments.push(SegmentInfo {
+            file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            start_time: chrono::Local::now().to_rfc3339(),
+            first_timestamp: f64::NAN,
+            last_timestamp: f64::NAN,
+            samples: 0,
+        });
+        self.deadline = self.rotation.interval.map(|interval| Instant::now() + interval.remaining());
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        path
+    }
+
+    /// Account for samples appended to the current segment
+    pub(crate) fn observe(&mut self, samples: &[TimestampedSample]) {
+        let (Some(segment), Some(first), Some(last)) = (self.manifest.segments.last_mut(), samples.first(), samples.last()) else {
+            return;
+        };
+        if segment.samples == 0 {
+            segment.first_timestamp = first.timestamp;
+        }
+        segment.last_timestamp = last.timestamp;
+        segment.samples += samples.len() as u64;
+    }
+
+    /// Whether the segment at `path` has reached a rotation limit
+    pub(crate) fn due(&mut self, path: &Path) -> bool {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+        let Some(max_bytes) = self.rotation.max_bytes else {
+            return false;
+        };
+        if Instant::now() < self.next_size_check {
+            return false;
+        }
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        std::fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes)
+    }
+
+    pub(crate) fn save_manifest(&self) -> Result<()> {
+        self.manifest.save(manifest_path(&self.output))
+    }
+
+    pub(crate) fn manifest_path(&self) -> PathBuf {
+        manifest_path(&self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: f64) -> TimestampedSample {
+        TimestampedSample {
+            timestamp,
+            data: crate::SensorData { accel_x: 0, accel_y: 0, accel_z: 256_000, temperature: 1852 },
+        }
+    }
+
+    #[test]
+    fn interval_parsing() {
+        assert_eq!(RotationInterval::parse(" DAILY "), Some(RotationInterval::Daily));
+        assert_eq!(RotationInterval::parse("90"), Some(RotationInterval::Every(Duration::from_secs(90))));
+        assert_eq!(RotationInterval::parse("2h"), Some(RotationInterval::Every(Duration::from_secs(7200))));
+        assert_eq!(RotationInterval::parse(""), None);
+        assert_eq!(RotationInterval::parse("-5m"), None);
+        assert_eq!(RotationInterval::parse("1.5h"), None);
+        assert_eq!(RotationInterval::Every(Duration::from_secs(90)).to_string(), "90s");
+        assert_eq!(RotationInterval::Every(Duration::from_secs(120)).to_string(), "2m");
+    }
+
+    #[test]
+    fn segment_names_keep_extension() {
+        assert_eq!(segment_path("vib/run.hdf5", 2), PathBuf::from("vib/run_0002.hdf5"));
+        assert_eq!(segment_path("vib/run", 10_000), PathBuf::from("vib/run_10000.h5"));
+        assert_eq!(manifest_path("vib/run.hdf5"), PathBuf::from("vib/run.manifest"));
+    }
+
+    #[test]
+    fn malformed_manifests_are_rejected() {
+        let path = std::env::temp_dir().join(format!("adxl355-i2c-session-{}.manifest", std::process::id()));
+        std::fs::write(&path, "start_time = 2026-10-18T09:00:00+02:00\nsegment = run_0001.h5 | x | 0.0\n").unwrap();
+        let short_segment = Manifest::load(&path);
+        std::fs::write(&path, "start_time = 2026-10-18T09:00:00+02:00\n").unwrap();
+        let no_segments = Manifest::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(short_segment, Err(Adxl355Error::InvalidParameter(_))));
+        assert!(matches!(no_segments, Err(Adxl355Error::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn observe_and_size_limit() {
+        let dir = std::env::temp_dir();
+        let output = dir.join(format!("adxl355-i2c-rotate-{}.h5", std::process::id()));
+        let rotation = Rotation { interval: None, max_bytes: Some(1000) };
+        let mut session = Session::new(&output, rotation);
+        let segment = session.begin_segment();
+        session.observe(&[]);
+        session.observe(&[sample(0.5), sample(0.502)]);
+        session.observe(&[sample(0.504)]);
+
+        let info = &session.manifest.segments[0];
+        assert_eq!((info.samples, info.first_timestamp, info.last_timestamp), (3, 0.5, 0.504));
+
+        std::fs::write(&segment, vec![0u8; 999]).unwrap();
+        session.next_size_check = Instant::now();
+        assert!(!session.due(&segment));
+        std::fs::write(&segment, vec![0u8; 1000]).unwrap();
+        // Checked at most once per SIZE_CHECK_INTERVAL
+        assert!(!session.due(&segment));
+        session.next_size_check = Instant::now();
+        assert!(session.due(&segment));
+        std::fs::remove_file(&segment).unwrap();
+    }
+}
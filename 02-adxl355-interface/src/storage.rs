@@ -0,0 +1,323 @@
+//! Chunking, compression and timestamp encoding of the sample columns
+//!
+//! [`StorageOptions`] is given to
+//! [`Hdf5Writer::create_with`](crate::Hdf5Writer::create_with) or
+//! [`Hdf5Writer::create_session`](crate::Hdf5Writer::create_session) and
+//! applies to every `sensor_data` column of every segment.
+//! The default is the layout of files written before the options existed:
+//! 1024-row chunks, deflate level 4, no shuffle, f64 timestamps.
+//!
+//! Readers need no settings. HDF5 records the filters of each dataset, and
+//! [`TimestampFormat::Ticks`] is recognised by the integer type of the
+//! `timestamps` column, whose `scale_factor` attribute is the tick length.
+//!
+//! LZF and Blosc are filter plugins compiled in by the `lzf` and `blosc`
+//! features. Other tools need the same plugin to open such files (h5py
+//! ships LZF; Blosc is in hdf5plugin).
+//!
+//! As text, options are a short spec such as
+//! `deflate=6,shuffle,chunk=4096,ticks` (see [`StorageOptions::parse`]);
+//! the collector's `--storage` flag and the `storage-bench` binary take it.
+
+use crate::schema::ChannelInfo;
+use crate::{Adxl355Error, Result};
+use hdf5::{Dataset, Group, H5Type};
+use std::fmt;
+
+/// Rows per chunk unless configured otherwise
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Ticks per second of [`TimestampFormat::Ticks`] when the spec names none
+pub const DEFAULT_TICK_RATE: u32 = 1_000_000;
+
+/// Level of `deflate` and `blosc-*` when the spec names none
+const DEFAULT_LEVEL: u8 = 4;
+
+/// Compression filter of the sample columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// zlib, level 0-9
+    Deflate(u8),
+    /// LZF: fast, moderate ratio (`lzf` feature)
+    Lzf,
+    /// Blosc with LZ4, level 0-9 (`blosc` feature)
+    BloscLz4(u8),
+    /// Blosc with Zstandard, level 0-9 (`blosc` feature)
+    BloscZstd(u8),
+}
+
+impl Compression {
+    /// Whether this build can write the filter
+    pub fn is_available(&self) -> bool {
+        match self {
+            Compression::Lzf => cfg!(feature = "lzf"),
+            Compression::BloscLz4(_) | Compression::BloscZstd(_) => cfg!(feature = "blosc"),
+            Compression::None | Compression::Deflate(_) => true,
+        }
+    }
+
+    fn level(&self) -> Option<u8> {
+        match self {
+            Compression::Deflate(level) | Compression::BloscLz4(level) | Compression::BloscZstd(level) => Some(*level),
+            Compression::None | Compression::Lzf => None,
+        }
+    }
+
+    /// Blosc shuffles inside the filter instead of using the HDF5 one
+    fn is_blosc(&self) -> bool {
+        matches!(self, Compression::BloscLz4(_) | Compression::BloscZstd(_))
+    }
+}
+
+/// How the `timestamps` column stores seconds since `start_time`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// f64 seconds
+    Seconds,
+    /// i64 count of ticks of 1/N s: exact at that resolution, and evenly
+    /// spaced values compress far better than f64
+    Ticks(u32),
+}
+
+impl TimestampFormat {
+    /// Format of an existing `timestamps` dataset
+    pub(crate) fn of(dataset: &Dataset) -> Self {
+        if !dataset.dtype().is_ok_and(|dtype| dtype.is::<i64>()) {
+            return TimestampFormat::Seconds;
+        }
+        let rate = ChannelInfo::read(dataset)
+            .filter(|info| info.scale_factor > 0.0)
+            .map_or(DEFAULT_TICK_RATE, |info| (1.0 / info.scale_factor).round() as u32);
+        TimestampFormat::Ticks(rate)
+    }
+
+    /// Column description stored on the dataset
+    pub(crate) fn channel(&self) -> ChannelInfo {
+        let scale_factor = match self {
+            TimestampFormat::Seconds => 1.0,
+            TimestampFormat::Ticks(rate) => 1.0 / *rate as f64,
+        };
+        ChannelInfo { units: "s".to_string(), scale_factor, offset: 0.0, full_scale: 0.0, axis: String::new() }
+    }
+}
+
+/// Ticks of 1/`rate` s nearest to `seconds`
+pub(crate) fn to_ticks(seconds: f64, rate: u32) -> i64 {
+    (seconds * rate as f64).round() as i64
+}
+
+/// Seconds of `ticks` ticks of 1/`rate` s
+pub(crate) fn from_ticks(ticks: i64, rate: u32) -> f64 {
+    ticks as f64 / rate as f64
+}
+
+/// Layout of the `sensor_data` columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StorageOptions {
+    /// Rows per chunk: larger chunks compress better, smaller ones keep
+    /// SWMR readers and time lookups cheap
+    pub chunk_size: usize,
+    pub compression: Compression,
+    /// Byte shuffle before compressing; helps slowly changing integers
+    pub shuffle: bool,
+    pub timestamps: TimestampFormat,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        StorageOptions {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            compression: Compression::Deflate(DEFAULT_LEVEL),
+            shuffle: false,
+            timestamps: TimestampFormat::Seconds,
+        }
+    }
+}
+
+impl StorageOptions {
+    /// Parse a comma-separated spec; items not given keep their default
+    ///
+    /// ```text
+    ///     none | deflate[=0-9] | lzf | blosc-lz4[=0-9] | blosc-zstd[=0-9]
+    ///     shuffle              byte shuffle before compressing
+    ///     chunk=<rows>         rows per chunk
+    ///     seconds | ticks[=N]  f64 timestamps, or i64 ticks of 1/N s (default 1 us)
+    /// ```
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut options = StorageOptions::default();
+        for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, value) = match item.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (item, None),
+            };
+            let invalid = || Adxl355Error::InvalidParameter(format!("Invalid storage option '{}'", item));
+            let number = |default: u32| -> Result<u32> {
+                value.map_or(Ok(default), |value| value.parse().map_err(|_| invalid()))
+            };
+            let level = || -> Result<u8> {
+                u8::try_from(number(DEFAULT_LEVEL as u32)?).map_err(|_| invalid())
+            };
+            match (key.to_ascii_lowercase().as_str(), value) {
+                ("none", None) => options.compression = Compression::None,
+                ("deflate" | "gzip", _) => options.compression = Compression::Deflate(level()?),
+                ("lzf", None) => options.compression = Compression::Lzf,
+                ("blosc-lz4" | "blosc", _) => options.compression = Compression::BloscLz4(level()?),
+                ("blosc-zstd", _) => options.compression = Compression::BloscZstd(level()?),
+                ("shuffle", None) => options.shuffle = true,
+                ("chunk", Some(_)) => options.chunk_size = number(0)? as usize,
+                ("seconds", None) => options.timestamps = TimestampFormat::Seconds,
+                ("ticks", _) => options.timestamps = TimestampFormat::Ticks(number(DEFAULT_TICK_RATE)?),
+                _ => return Err(invalid()),
+            }
+        }
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Check the values and that this build has the filter
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_size == 0 {
+            return Err(Adxl355Error::InvalidParameter("Chunk size must be at least 1 row".to_string()));
+        }
+        if let Some(level) = self.compression.level().filter(|&level| level > 9) {
+            return Err(Adxl355Error::InvalidParameter(format!("Compression level {} is out of range 0-9", level)));
+        }
+        if !self.compression.is_available() {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "{} compression needs a build with --features {}",
+                self.compression, if self.compression == Compression::Lzf { "lzf" } else { "blosc" }
+            )));
+        }
+        if self.timestamps == TimestampFormat::Ticks(0) {
+            return Err(Adxl355Error::InvalidParameter("Tick rate must be at least 1 per second".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Empty, growable 1-D column `name` in `group` with these settings
+    pub(crate) fn create_dataset<T: H5Type>(&self, group: &Group, name: &str) -> Result<Dataset> {
+        let mut builder = group.new_dataset::<T>()
+            .shape((0..,))
+            .chunk((self.chunk_size,));
+        if self.shuffle && !self.compression.is_blosc() {
+            builder = builder.shuffle();
+        }
+        builder = match self.compression {
+            Compression::None => builder,
+            Compression::Deflate(level) => builder.deflate(level),
+            #[cfg(feature = "lzf")]
+            Compression::Lzf => builder.lzf(),
+            #[cfg(feature = "blosc")]
+            Compression::BloscLz4(level) => builder.blosc_lz4(level, self.shuffle),
+            #[cfg(feature = "blosc")]
+            Compression::BloscZstd(level) => builder.blosc_zstd(level, self.shuffle),
+            #[allow(unreachable_patterns)]
+            _ => return Err(Adxl355Error::InvalidParameter(format!("{} compression is not built in", self.compression))),
+        };
+        builder.create(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create dataset {}", name), e))
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Deflate(level) => write!(f, "deflate={}", level),
+            Compression::Lzf => write!(f, "lzf"),
+            Compression::BloscLz4(level) => write!(f, "blosc-lz4={}", level),
+            Compression::BloscZstd(level) => write!(f, "blosc-zstd={}", level),
+        }
+    }
+}
+
+/// The spec [`StorageOptions::parse`] reads back
+impl fmt::Display for StorageOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.compression)?;
+        if self.shuffle {
+            write!(f, ",shuffle")?;
+        }
+        write!(f, ",chunk={}", self.chunk_size)?;
+        match self.timestamps {
+            TimestampFormat::Seconds => write!(f, ",seconds"),
+            TimestampFormat::Ticks(rate) => write!(f, ",ticks={}", rate),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_defaults_and_items() {
+        assert_eq!(StorageOptions::parse("").unwrap(), StorageOptions::default());
+        let options = StorageOptions::parse("deflate=6, shuffle, chunk=4096, ticks").unwrap();
+        assert_eq!(options.compression, Compression::Deflate(6));
+        assert!(options.shuffle);
+        assert_eq!(options.chunk_size, 4096);
+        assert_eq!(options.timestamps, TimestampFormat::Ticks(DEFAULT_TICK_RATE));
+        assert_eq!(StorageOptions::parse("none,ticks=4000").unwrap().timestamps, TimestampFormat::Ticks(4000));
+        assert_eq!(StorageOptions::parse("deflate").unwrap().compression, Compression::Deflate(DEFAULT_LEVEL));
+    }
+
+    #[test]
+    fn spec_errors() {
+        for spec in ["deflate=10", "chunk=0", "chunk", "ticks=0", "zip", "shuffle=1", "deflate=x"] {
+            assert!(StorageOptions::parse(spec).is_err(), "{}", spec);
+        }
+        assert_eq!(StorageOptions::parse("lzf").is_ok(), cfg!(feature = "lzf"));
+        assert_eq!(StorageOptions::parse("blosc-zstd=3").is_ok(), cfg!(feature = "blosc"));
+    }
+
+    #[test]
+    fn display_reads_back() {
+        for spec in ["none,chunk=64,seconds", "deflate=1,shuffle,chunk=8192,ticks=1000000"] {
+            let options = StorageOptions::parse(spec).unwrap();
+            assert_eq!(options.to_string(), spec);
+            assert_eq!(StorageOptions::parse(&options.to_string()).unwrap(), options);
+        }
+    }
+
+    #[test]
+    fn ticks_round_trip() {
+        assert_eq!(to_ticks(1.5e-6, 1_000_000), 2);
+        assert_eq!(to_ticks(-0.25, 1000), -250);
+        assert_eq!(from_ticks(4000, 4000), 1.0);
+        let t = 86_400.0 * 30.0 + 0.123_456;
+        assert!((from_ticks(to_ticks(t, DEFAULT_TICK_RATE), DEFAULT_TICK_RATE) - t).abs() < 1e-6);
+        assert_eq!(TimestampFormat::Ticks(1000).channel().scale_factor, 0.001);
+    }
+
+    #[test]
+    fn ticks_at_the_odr_keep_20_bit_columns() {
+        use crate::hdf5_format::{Hdf5Reader, Hdf5Writer, TimestampedSample};
+        use crate::SensorData;
+
+        let path = std::env::temp_dir().join(format!("adxl-storage-{}.h5", std::process::id()));
+        let storage = StorageOptions::parse("deflate=9,shuffle,chunk=64,ticks=4000").unwrap();
+        let samples: Vec<TimestampedSample> = (0..200i32)
+            .map(|i| TimestampedSample {
+                timestamp: i as f64 / 4000.0,
+                data: SensorData { accel_x: 524_287 - i, accel_y: -524_288 + i, accel_z: 256_000, temperature: 1885 + i as u16 },
+            })
+            .collect();
+        let mut writer = Hdf5Writer::create_with(&path, "fifo", 4000.0, "2g", &storage).unwrap();
+        writer.append_batch(&samples).unwrap();
+        writer.close().unwrap();
+
+        let file = hdf5::File::open(&path).unwrap();
+        let timestamps = file.dataset("sensor_data/timestamps").unwrap();
+        assert_eq!(TimestampFormat::of(&timestamps), TimestampFormat::Ticks(4000));
+        assert_eq!(file.dataset("sensor_data/temperature").unwrap().chunk(), Some(vec![64]));
+        drop((timestamps, file));
+        let read = Hdf5Reader::open(&path).unwrap().read_range(0, samples.len()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // One tick per ODR period: timestamps come back exactly
+        let row = |s: &TimestampedSample| (s.timestamp, s.data.accel_x, s.data.accel_y, s.data.accel_z, s.data.temperature);
+        assert_eq!(read.iter().map(row).collect::<Vec<_>>(), samples.iter().map(row).collect::<Vec<_>>());
+    }
+}
@@ -23,6 +23,8 @@ fn main() {
     // Link against the libraries
     // Note: libmpsse.dll depends on FTD2XX.dll, which will be loaded at runtime
     println!("cargo:rustc-link-lib=dylib=libmpsse");
+    // Raw MPSSE command path calls FT_Write/FT_Read directly
+    println!("cargo:rustc-link-lib=dylib=ftd2xx");
 
     // Rerun if the DLL paths change
     println!("cargo:rerun-if-changed=../00-ftdi-drivers/FTDI MPSSE/build/x64/DLL/libmpsse.dll");
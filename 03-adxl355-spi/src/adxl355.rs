@@ -1,15 +1,27 @@
 //! ADXL355 sensor driver using FTDI MPSSE SPI interface
 
-use crate::error::{Adxl355Error, Result};
+use crate::common::{PollScheduler, PollStats};
+use crate::error::{check_status, Adxl355Error, Result, TransportOp};
+use crate::recovery::{self, Outage, RetryPolicy, StreamEvent};
 use crate::ffi::*;
+use crate::gpio::{Gpio, GpioState};
+use crate::mpsse::{self, ChipSelect, CommandBuffer};
+use crate::spi::{BusLink, BusSelection, CsLine, SpiConfig, SpiMode};
+use crate::trace::{Replayer, Trace, TraceOp, Tracer};
+use std::cell::RefCell;
+use std::path::Path;
 use std::ptr;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 // Device identification registers
 const REG_DEVID_AD: u8 = 0x00;
+const REG_DEVID_MST: u8 = 0x01;
 const REG_PARTID: u8 = 0x02;
+const REG_REVID: u8 = 0x03;
 
 // Status and FIFO
+const REG_STATUS: u8 = 0x04;
 const REG_FIFO_ENTRIES: u8 = 0x05;
 
 // Temperature data (12-bit: TEMP2[3:0] = bits[11:8], TEMP1[7:0] = bits[7:0])
@@ -23,34 +35,190 @@ const REG_FIFO_DATA: u8 = 0x11;
 
 // Configuration registers
 const REG_FILTER: u8 = 0x28;
+const REG_SYNC: u8 = 0x2B;
 const REG_RANGE: u8 = 0x2C;
 const REG_POWER_CTL: u8 = 0x2D;
+const REG_SELF_TEST: u8 = 0x2E;
 const REG_RESET: u8 = 0x2F;
 
 // Expected device ID values
 const DEVID_AD_VALUE: u8 = 0xAD;
-const PARTID_VALUE: u8 = 0xED;
+const DEVID_MST_VALUE: u8 = 0x1D;
+const PARTID_VALUE: u8 = 0xED;    // ADXL355 and ADXL357
+
+// Status register bits
+const STATUS_FIFO_OVR: u8 = 0x04;
+
+// FIFO geometry: 96 entries of 3 bytes, one entry per axis
+const FIFO_DEPTH_ENTRIES: usize = 96;
+
+// FIFO entry flags (bits [1:0] of the low byte)
+const FIFO_X_MARKER: u8 = 0x01;
+const FIFO_EMPTY: u8 = 0x02;
 
 // Power control bits
 const POWER_CTL_STANDBY: u8 = 0x01;
 
+// Self-test bits
+const SELF_TEST_ST1: u8 = 0x01;
+const SELF_TEST_ST2: u8 = 0x02;
+
 // Reset command
 const RESET_CODE: u8 = 0x52;
 
+/// Supported part in the ADXL35x family
+///
+/// The ADXL357 is register-compatible with the ADXL355 and reports the same
+/// PARTID (0xED), so the two cannot be told apart over the bus and `init`
+/// does not detect the ADXL357: it assumes ADXL355, and an ADXL357 has to
+/// be declared with [`Adxl355::set_variant`], or its +/-10/20/40g ranges
+/// are labelled and scaled as +/-2/4/8g. The collector therefore requires
+/// `--variant`. The ADXL354 has an analog output only and cannot be used
+/// with this driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceVariant {
+    /// +/-2/4/8g
+    Adxl355,
+    /// +/-10/20/40g
+    Adxl357,
+}
+
+impl DeviceVariant {
+    /// Family member reported for a PARTID value, if it is a supported part
+    ///
+    /// Never returns [`DeviceVariant::Adxl357`]: it shares PARTID 0xED with
+    /// the ADXL355.
+    pub fn detect(partid: u8) -> Option<Self> {
+        match partid {
+            PARTID_VALUE => Some(DeviceVariant::Adxl355),
+            _ => None,
+        }
+    }
+
+    /// Part name as printed on the package
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeviceVariant::Adxl355 => "ADXL355",
+            DeviceVariant::Adxl357 => "ADXL357",
+        }
+    }
+
+    /// Parse a part name (case-insensitive)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "adxl355" => Some(DeviceVariant::Adxl355),
+            "adxl357" => Some(DeviceVariant::Adxl357),
+            _ => None,
+        }
+    }
+
+    /// Ranges available on this part, smallest first
+    pub fn ranges(&self) -> [Range; 3] {
+        match self {
+            DeviceVariant::Adxl355 => [Range::G2, Range::G4, Range::G8],
+            DeviceVariant::Adxl357 => [Range::G10, Range::G20, Range::G40],
+        }
+    }
+
+    /// Range selected after reset (RANGE bits = 01)
+    pub fn default_range(&self) -> Range {
+        self.ranges()[0]
+    }
+
+    /// Datasheet self-test output change limits in g (min, max) for X, Y, Z
+    pub fn self_test_limits_g(&self) -> [(f32, f32); 3] {
+        match self {
+            DeviceVariant::Adxl355 => [(0.1, 0.6), (0.1, 0.6), (0.5, 3.0)],
+            DeviceVariant::Adxl357 => [(0.08, 0.5), (0.08, 0.5), (0.4, 2.5)],
+        }
+    }
+}
+
+/// Identification registers read during init
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub variant: DeviceVariant,
+    pub devid_ad: u8,
+    pub devid_mst: u8,
+    pub partid: u8,
+    pub revid: u8,
+}
+
 /// Measurement range
+///
+/// G2/G4/G8 apply to the ADXL355, G10/G20/G40 to the ADXL357. Both parts use
+/// the same RANGE register codes; only the scale factor differs.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Range {
-    G2 = 0x01,
-    G4 = 0x02,
-    G8 = 0x03,
+    /// +/-2g, 256000 LSB/g
+    G2,
+    /// +/-4g, 128000 LSB/g
+    G4,
+    /// +/-8g, 64000 LSB/g
+    G8,
+    /// +/-10g (ADXL357), 51200 LSB/g
+    G10,
+    /// +/-20g (ADXL357), 25600 LSB/g
+    G20,
+    /// +/-40g (ADXL357), 12800 LSB/g
+    G40,
 }
 
 impl Range {
+    /// Scale factor in LSB/g for this range
     pub fn scale_factor(&self) -> f32 {
         match self {
             Range::G2 => 256_000.0,
             Range::G4 => 128_000.0,
             Range::G8 => 64_000.0,
+            Range::G10 => 51_200.0,
+            Range::G20 => 25_600.0,
+            Range::G40 => 12_800.0,
+        }
+    }
+
+    /// Full-scale input in g for this range
+    pub fn full_scale_g(&self) -> f32 {
+        // 20-bit two's complement: +/-2^19 LSB
+        524_288.0 / self.scale_factor()
+    }
+
+    /// RANGE register bits [1:0]
+    pub fn register_bits(&self) -> u8 {
+        match self {
+            Range::G2 | Range::G10 => 0x01,
+            Range::G4 | Range::G20 => 0x02,
+            Range::G8 | Range::G40 => 0x03,
+        }
+    }
+
+    /// Range on `variant` with the same register code as this one
+    pub fn for_variant(&self, variant: DeviceVariant) -> Range {
+        variant.ranges()[(self.register_bits() - 1) as usize]
+    }
+
+    /// Label used in HDF5 metadata ("2g", "10g", ...)
+    pub fn label(&self) -> &'static str {
+        match self {
+            Range::G2 => "2g",
+            Range::G4 => "4g",
+            Range::G8 => "8g",
+            Range::G10 => "10g",
+            Range::G20 => "20g",
+            Range::G40 => "40g",
+        }
+    }
+
+    /// Parse a label produced by [`Range::label`]
+    pub fn from_label(label: &str) -> Option<Range> {
+        match label {
+            "2g" => Some(Range::G2),
+            "4g" => Some(Range::G4),
+            "8g" => Some(Range::G8),
+            "10g" => Some(Range::G10),
+            "20g" => Some(Range::G20),
+            "40g" => Some(Range::G40),
+            _ => None,
         }
     }
 }
@@ -89,6 +257,125 @@ impl OutputDataRate {
     }
 }
 
+/// External synchronization mode (SYNC register, 0x2B)
+///
+/// With external sync the DRDY pin becomes an input and each pulse on it
+/// starts a conversion; with external clock the internal oscillator is
+/// replaced by a 1.024 MHz clock on INT2. Boards sharing the same sync
+/// pulse and clock sample coherently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    Internal,
+    ExternalSync,
+    ExternalSyncInterpolated,
+    ExternalClock,
+    ExternalClockAndSync,
+    ExternalClockAndSyncInterpolated,
+}
+
+impl SyncMode {
+    fn register_value(&self) -> u8 {
+        match self {
+            SyncMode::Internal => 0x00,
+            SyncMode::ExternalSync => 0x01,
+            SyncMode::ExternalSyncInterpolated => 0x02,
+            SyncMode::ExternalClock => 0x04,
+            SyncMode::ExternalClockAndSync => 0x05,
+            SyncMode::ExternalClockAndSyncInterpolated => 0x06,
+        }
+    }
+
+    fn from_register(value: u8) -> Option<Self> {
+        match value & 0x07 {
+            0x00 => Some(SyncMode::Internal),
+            0x01 => Some(SyncMode::ExternalSync),
+            0x02 => Some(SyncMode::ExternalSyncInterpolated),
+            0x04 => Some(SyncMode::ExternalClock),
+            0x05 => Some(SyncMode::ExternalClockAndSync),
+            0x06 => Some(SyncMode::ExternalClockAndSyncInterpolated),
+            _ => None,
+        }
+    }
+
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SyncMode::Internal => "internal",
+            SyncMode::ExternalSync => "ext-sync",
+            SyncMode::ExternalSyncInterpolated => "ext-sync-interp",
+            SyncMode::ExternalClock => "ext-clk",
+            SyncMode::ExternalClockAndSync => "ext-clk-sync",
+            SyncMode::ExternalClockAndSyncInterpolated => "ext-clk-sync-interp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "internal" => Some(SyncMode::Internal),
+            "ext-sync" => Some(SyncMode::ExternalSync),
+            "ext-sync-interp" => Some(SyncMode::ExternalSyncInterpolated),
+            "ext-clk" => Some(SyncMode::ExternalClock),
+            "ext-clk-sync" => Some(SyncMode::ExternalClockAndSync),
+            "ext-clk-sync-interp" => Some(SyncMode::ExternalClockAndSyncInterpolated),
+            _ => None,
+        }
+    }
+}
+
+/// How often FIFO reads refresh the temperature by default
+const DEFAULT_TEMPERATURE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Outputs above this fraction of full scale are treated as saturated
+const SELF_TEST_SATURATION: f32 = 0.95;
+
+/// Result of [`Adxl355::self_test`]
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    /// Range the test ran in
+    pub range: Range,
+    /// Samples averaged per phase
+    pub samples: usize,
+    /// Mean output with the self-test force off, in g
+    pub off_g: [f32; 3],
+    /// Mean output with the self-test force on, in g
+    pub on_g: [f32; 3],
+    /// Output change (on - off) in g
+    pub delta_g: [f32; 3],
+    /// Accepted (min, max) change per axis in g for the part variant
+    pub limits_g: [(f32, f32); 3],
+    /// Axes whose off or on output was at or near full scale. A saturated
+    /// axis cannot show the full self-test response and always fails.
+    pub axis_saturated: [bool; 3],
+    /// Per-axis pass/fail
+    pub axis_passed: [bool; 3],
+}
+
+impl SelfTestReport {
+    /// Judge the mean outputs of a test run at `range` against the limits
+    /// of `variant`
+    fn evaluate(range: Range, variant: DeviceVariant, samples: usize, off_g: [f32; 3], on_g: [f32; 3]) -> Self {
+        let saturation_g = range.full_scale_g() * SELF_TEST_SATURATION;
+        let limits_g = variant.self_test_limits_g();
+
+        let mut delta_g = [0.0f32; 3];
+        let mut axis_saturated = [false; 3];
+        let mut axis_passed = [false; 3];
+        for axis in 0..3 {
+            delta_g[axis] = on_g[axis] - off_g[axis];
+            let (min, max) = limits_g[axis];
+            axis_saturated[axis] = off_g[axis].abs() >= saturation_g || on_g[axis].abs() >= saturation_g;
+            axis_passed[axis] = !axis_saturated[axis] && delta_g[axis] >= min && delta_g[axis] <= max;
+        }
+
+        SelfTestReport { range, samples, off_g, on_g, delta_g, limits_g, axis_saturated, axis_passed }
+    }
+
+    /// True when every axis is within limits
+    pub fn passed(&self) -> bool {
+        self.axis_passed.iter().all(|&p| p)
+    }
+}
+
 /// Control flow for streaming operations
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StreamControl {
@@ -96,6 +383,102 @@ pub enum StreamControl {
     Break,
 }
 
+/// Temperature sensor calibration: raw = intercept + slope * (T - 25 C)
+///
+/// The datasheet nominal is 1885 LSB at 25 C and -9.05 LSB/C, with roughly
+/// 10% part-to-part spread on the slope. A two-point calibration against a
+/// reference thermometer brings this down to the sensor's own noise.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureCalibration {
+    /// Raw reading at 25 C (LSB)
+    pub intercept_lsb: f32,
+    /// Sensitivity (LSB/C)
+    pub slope_lsb_per_c: f32,
+}
+
+impl TemperatureCalibration {
+    /// Datasheet nominal values
+    pub const NOMINAL: TemperatureCalibration = TemperatureCalibration {
+        intercept_lsb: 1885.0,
+        slope_lsb_per_c: -9.05,
+    };
+
+    /// Build a calibration from two (raw, reference C) points
+    pub fn from_two_points(raw1: u16, temp1_c: f32, raw2: u16, temp2_c: f32) -> Result<Self> {
+        if (temp2_c - temp1_c).abs() < 1.0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Calibration points must be at least 1 C apart".to_string()
+            ));
+        }
+        let slope = (raw2 as f32 - raw1 as f32) / (temp2_c - temp1_c);
+        if slope == 0.0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Calibration points have identical raw readings".to_string()
+            ));
+        }
+        Ok(TemperatureCalibration {
+            intercept_lsb: raw1 as f32 + slope * (25.0 - temp1_c),
+            slope_lsb_per_c: slope,
+        })
+    }
+
+    /// Convert a raw 12-bit reading to Celsius
+    pub fn to_celsius(&self, raw: u16) -> f32 {
+        ((raw as f32 - self.intercept_lsb) / self.slope_lsb_per_c) + 25.0
+    }
+
+    /// Load from a `key = value` text file (`intercept_lsb`, `slope_lsb_per_c`)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+
+        let mut intercept = None;
+        let mut slope = None;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=')
+                .ok_or_else(|| Adxl355Error::InvalidParameter(format!("Malformed line in {}: {}", path.display(), line)))?;
+            let value: f32 = value.trim().parse()
+                .map_err(|_| Adxl355Error::InvalidParameter(format!("Invalid number in {}: {}", path.display(), line)))?;
+            match key.trim() {
+                "intercept_lsb" => intercept = Some(value),
+                "slope_lsb_per_c" => slope = Some(value),
+                _ => {}
+            }
+        }
+
+        match (intercept, slope) {
+            (Some(intercept_lsb), Some(slope_lsb_per_c)) if slope_lsb_per_c != 0.0 => {
+                Ok(TemperatureCalibration { intercept_lsb, slope_lsb_per_c })
+            }
+            _ => Err(Adxl355Error::InvalidParameter(format!(
+                "{} must define intercept_lsb and a non-zero slope_lsb_per_c", path.display()
+            ))),
+        }
+    }
+
+    /// Save as a `key = value` text file readable by [`TemperatureCalibration::load`]
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = format!(
+            "# ADXL355 temperature calibration\nintercept_lsb = {}\nslope_lsb_per_c = {}\n",
+            self.intercept_lsb, self.slope_lsb_per_c
+        );
+        std::fs::write(path, text)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to write {}", path.display()), source: e })
+    }
+}
+
+impl Default for TemperatureCalibration {
+    fn default() -> Self {
+        Self::NOMINAL
+    }
+}
+
 /// Sensor data structure
 #[derive(Debug, Clone, Copy)]
 pub struct SensorData {
@@ -116,7 +499,11 @@ impl SensorData {
     }
 
     pub fn temperature_c(&self) -> f32 {
-        ((self.temperature as f32 - 1885.0) / -9.05) + 25.0
+        TemperatureCalibration::NOMINAL.to_celsius(self.temperature)
+    }
+
+    pub fn temperature_c_calibrated(&self, cal: &TemperatureCalibration) -> f32 {
+        cal.to_celsius(self.temperature)
     }
 }
 
@@ -125,6 +512,57 @@ impl SensorData {
 pub struct FifoBatchResult {
     pub samples: Vec<SensorData>,
     pub overflow_detected: bool,
+    /// Samples at the start of `samples` that were completed from entries
+    /// read by the previous batch (raw path only, otherwise 0)
+    pub carried: usize,
+}
+
+/// Assemble XYZ samples from raw FIFO entries
+///
+/// Stops at the first empty entry. A sample whose Y/Z entries have not been
+/// read yet stays in `partial` and is completed by the next batch, so reads
+/// that race the sensor's FIFO writes lose nothing. Also returns how many
+/// samples were completed from entries left over by the previous batch.
+fn assemble_fifo_samples<'a, I>(entries: I, partial: &mut Vec<[u8; 3]>, temperature: u16) -> (Vec<SensorData>, usize)
+where
+    I: Iterator<Item = &'a [u8]>,
+{
+    let mut samples = Vec::with_capacity(FIFO_DEPTH_ENTRIES / 3);
+    let mut from_previous = !partial.is_empty();
+    let mut carried = 0;
+
+    for entry in entries {
+        let entry = [entry[0], entry[1], entry[2]];
+        if entry[2] & FIFO_EMPTY != 0 {
+            break;
+        }
+
+        if entry[2] & FIFO_X_MARKER != 0 {
+            // New sample starts; an unfinished one before it cannot be completed
+            partial.clear();
+            partial.push(entry);
+            from_previous = false;
+        } else if !partial.is_empty() {
+            partial.push(entry);
+        }
+        // else: Y/Z entry without its X, skip until realigned
+
+        if partial.len() == 3 {
+            samples.push(SensorData {
+                accel_x: parse_20bit(partial[0][0], partial[0][1], partial[0][2]),
+                accel_y: parse_20bit(partial[1][0], partial[1][1], partial[1][2]),
+                accel_z: parse_20bit(partial[2][0], partial[2][1], partial[2][2]),
+                temperature,
+            });
+            partial.clear();
+            if from_previous {
+                carried += 1;
+                from_previous = false;
+            }
+        }
+    }
+
+    (samples, carried)
 }
 
 /// Parse 20-bit two's complement value from 3 bytes
@@ -139,20 +577,61 @@ fn parse_20bit(high: u8, mid: u8, low: u8) -> i32 {
 
 /// ADXL355 sensor interface (SPI)
 pub struct Adxl355 {
-    handle: FT_HANDLE,
+    handle: FT_HANDLE, // Null after a failed reconnect
+    channel_index: u32,
+    config: SpiConfig,
+    // Set when the channel is shared with other sensors (see `SpiBus`)
+    bus: Option<BusLink>,
     range: Range,
+    info: Option<DeviceInfo>,
+    odr: OutputDataRate,
+    sync_mode: SyncMode,
     fifo_enabled: bool,
+    // Raw MPSSE path: stale bytes before register data (measured on first
+    // use) and axis entries of a sample split across two batches
+    raw_skip: Option<usize>,
+    raw_partial: Vec<[u8; 3]>,
+    temp_calibration: TemperatureCalibration,
+    // FIFO holds no temperature, so it is polled periodically and carried forward
+    last_temperature: u16,
+    last_temperature_read: Option<Instant>,
+    temperature_interval: Duration,
+    // Transport hooks, see `transport`
+    tracer: RefCell<Option<Tracer>>,
+    replay: RefCell<Option<Replayer>>,
+    // ACBUS setup, shared with `Gpio` handles (and the other sensors on a bus)
+    gpio: Rc<RefCell<GpioState>>,
 }
 
 impl Adxl355 {
     /// Open SPI channel without sensor init (for diagnostics)
     pub fn new_uninitialized(channel_index: u32) -> Result<Self> {
-        let handle = Self::open_channel(channel_index)?;
-        Ok(Adxl355 {
+        let config = SpiConfig::default();
+        let handle = Self::open_channel(channel_index, &config, 0)?;
+        Ok(Self::from_handle(handle, channel_index, config))
+    }
+
+    fn from_handle(handle: FT_HANDLE, channel_index: u32, config: SpiConfig) -> Self {
+        Adxl355 {
             handle,
+            channel_index,
+            config,
+            bus: None,
             range: Range::G2,
+            info: None,
+            odr: OutputDataRate::Odr1000,
+            sync_mode: SyncMode::Internal,
             fifo_enabled: false,
-        })
+            raw_skip: None,
+            raw_partial: Vec::with_capacity(3),
+            temp_calibration: TemperatureCalibration::NOMINAL,
+            last_temperature: 0,
+            last_temperature_read: None,
+            temperature_interval: DEFAULT_TEMPERATURE_INTERVAL,
+            tracer: RefCell::new(None),
+            replay: RefCell::new(None),
+            gpio: GpioState::new(handle),
+        }
     }
 
     /// Read a raw register value (public, for diagnostics)
@@ -162,86 +641,200 @@ impl Adxl355 {
 
     /// Diagnostic: raw SPI read showing all bytes including dummy
     pub fn read_reg_debug(&mut self, reg: u8) -> Result<(Vec<u8>, Vec<u8>)> {
-        use crate::ffi::*;
         // Send 3 bytes to see what comes back at each position
-        let mut out_buf = [(reg << 1) | 0x01, 0x00, 0x00];
+        let out_buf = [(reg << 1) | 0x01, 0x00, 0x00];
         let mut in_buf = [0u8; 3];
-        let mut transferred: DWORD = 0;
 
         let options = SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE;
 
-        let status = unsafe {
-            SPI_ReadWrite(
-                self.handle,
-                in_buf.as_mut_ptr(),
-                out_buf.as_mut_ptr(),
-                3,
-                &mut transferred,
-                options,
-            )
-        };
-
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        let _selected = self.select()?;
+        self.spi_read_write(&out_buf, &mut in_buf, options)?;
 
         Ok((out_buf.to_vec(), in_buf.to_vec()))
     }
 
-    /// Create a new ADXL355 instance over SPI
+    /// Create a new ADXL355 instance over SPI (1 MHz, mode 0, CS on DBUS3)
     pub fn new(channel_index: u32) -> Result<Self> {
-        let handle = Self::open_channel(channel_index)?;
+        Self::with_config(channel_index, SpiConfig::default())
+    }
 
-        let mut sensor = Adxl355 {
-            handle,
-            range: Range::G2,
-            fifo_enabled: false,
-        };
+    /// Create a new ADXL355 instance with explicit SPI settings
+    pub fn with_config(channel_index: u32, config: SpiConfig) -> Result<Self> {
+        Self::open(channel_index, config, None)
+    }
+
+    /// Like [`Adxl355::with_config`], tracing from the very first transfer
+    ///
+    /// Unlike [`Adxl355::start_trace`] on an open sensor, this also captures
+    /// the bus priming and ID reads done by `init`.
+    pub fn with_trace(channel_index: u32, config: SpiConfig, tracer: Tracer) -> Result<Self> {
+        Self::open(channel_index, config, Some(tracer))
+    }
+
+    fn open(channel_index: u32, config: SpiConfig, tracer: Option<Tracer>) -> Result<Self> {
+        config.validate()?;
+        let handle = Self::open_channel(channel_index, &config, 0)?;
 
+        let mut sensor = Self::from_handle(handle, channel_index, config);
+        *sensor.tracer.get_mut() = tracer;
         sensor.init()?;
 
         Ok(sensor)
     }
 
+    /// Run the driver against a recorded trace instead of hardware
+    ///
+    /// Every transfer, starting with those of `init`, is answered from
+    /// `trace`. The first call that differs from the recording (other
+    /// register, length or options) fails with `Adxl355Error::Replay`
+    /// naming the trace record, which makes captures usable as test input.
+    pub fn replay(trace: Trace, config: SpiConfig) -> Result<Self> {
+        let mut sensor = Self::from_handle(ptr::null_mut(), 0, config);
+        *sensor.replay.get_mut() = Some(Replayer::new(trace));
+        sensor.init()?;
+
+        Ok(sensor)
+    }
+
+    /// Trace records not yet consumed, `None` when not replaying
+    pub fn replay_remaining(&self) -> Option<usize> {
+        self.replay.borrow().as_ref().map(|replay| replay.remaining())
+    }
+
+    /// Record all further transfers; returns the tracer this replaces
+    pub fn start_trace(&mut self, tracer: Tracer) -> Option<Tracer> {
+        self.tracer.replace(Some(tracer))
+    }
+
+    /// Detach the tracer, e.g. to save a ring with [`Tracer::snapshot`]
+    pub fn stop_trace(&mut self) -> Option<Tracer> {
+        self.tracer.get_mut().take()
+    }
+
+    /// Initialize the sensor behind one chip select of a shared bus
+    pub(crate) fn on_bus(link: BusLink, config: SpiConfig) -> Result<Self> {
+        let mut sensor = Self::from_handle(link.handle(), link.channel_index(), config);
+        sensor.gpio = link.gpio_state();
+        sensor.bus = Some(link);
+        sensor.init()?;
+
+        Ok(sensor)
+    }
+
+    /// SPI settings the channel was opened with
+    pub fn spi_config(&self) -> SpiConfig {
+        self.config
+    }
+
+    /// Chip-select line on a shared bus, `None` for a sensor owning its channel
+    pub fn cs_line(&self) -> Option<CsLine> {
+        self.bus.as_ref().map(|link| link.line())
+    }
+
+    /// Route the bus to this sensor for one transaction
+    ///
+    /// A no-op for a sensor that owns its channel. Keep the returned guard
+    /// alive until the transaction's last SPI call has returned.
+    fn select(&self) -> Result<Option<BusSelection<'_>>> {
+        self.bus.as_ref().map(|link| link.select()).transpose()
+    }
+
+    /// Reopen the channel and bring the sensor back to its previous state
+    ///
+    /// Closes the current handle (which may already be dead), opens the
+    /// channel again with the same [`SpiConfig`], re-runs `init`, then
+    /// restores range, ODR, sync mode and FIFO streaming. Not available for
+    /// sensors on a [`crate::SpiBus`], whose channel is shared.
+    pub fn reconnect(&mut self) -> Result<()> {
+        if self.bus.is_some() {
+            return Err(Adxl355Error::InvalidParameter(
+                "Reconnect is not supported for sensors on a shared SpiBus".to_string()
+            ));
+        }
+        if self.replay.get_mut().is_some() {
+            return Err(Adxl355Error::InvalidParameter("Cannot reconnect while replaying a trace".to_string()));
+        }
+
+        let (range, odr, sync_mode, fifo) = (self.range, self.odr, self.sync_mode, self.fifo_enabled);
+
+        if !self.handle.is_null() {
+            self.gpio.borrow_mut().detach();
+            unsafe { SPI_CloseChannel(self.handle) };
+            self.handle = ptr::null_mut();
+        }
+
+        self.handle = Self::open_channel(self.channel_index, &self.config, 0)?;
+        self.gpio.borrow_mut().attach(self.handle)?;
+        self.fifo_enabled = false;
+        self.raw_skip = None;
+        self.raw_partial.clear();
+        self.init()?;
+
+        self.restore_configuration(range, odr, sync_mode, fifo)
+    }
+
+    /// Spare ACBUS pins of this channel as digital I/O
+    ///
+    /// On a shared bus this is the bus's [`Gpio`]; its ACBUS chip selects are off limits.
+    pub fn gpio(&self) -> Gpio {
+        Gpio::new(self.gpio.clone())
+    }
+
+    /// Run a sensor operation with retries and reconnects
+    ///
+    /// Transient USB errors are retried with backoff; if that fails, or the
+    /// adapter has disappeared, the channel is reopened via
+    /// [`Adxl355::reconnect`] until it answers or `policy` gives up. The
+    /// returned [`Outage`] is set when a reconnect was needed.
+    pub fn with_recovery<T, F>(&mut self, policy: &RetryPolicy, op: F) -> Result<(T, Option<Outage>)>
+    where
+        F: FnMut(&mut Self) -> Result<T>,
+    {
+        recovery::recover(self, policy, op, |s| s.reconnect())
+    }
+
+    /// Re-apply range, ODR, sync mode and FIFO state after `init`
+    fn restore_configuration(&mut self, range: Range, odr: OutputDataRate, sync_mode: SyncMode, fifo: bool) -> Result<()> {
+        self.set_range(range)?;
+        self.set_odr(odr)?;
+        if sync_mode != SyncMode::Internal {
+            self.set_sync_mode(sync_mode)?;
+        }
+        if fifo {
+            self.enable_fifo(odr)?;
+        }
+        Ok(())
+    }
+
     /// Prime the SPI bus after channel init.
     /// The FT232H MPSSE returns invalid data for the first few SPI
     /// transactions. A ReadWrite + split Write/Read sequence reliably
     /// brings the bus into a working state.
-    fn prime_spi(handle: FT_HANDLE) {
+    /// Results are ignored: the point is only to clock the bus.
+    fn prime_spi(&self) {
         let opts = SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE;
-        let mut xfer: DWORD = 0;
-        let mut dummy_out = [0x01u8, 0x00, 0x00];
         let mut dummy_in = [0u8; 3];
-        unsafe {
-            SPI_ReadWrite(handle, dummy_in.as_mut_ptr(), dummy_out.as_mut_ptr(),
-                3, &mut xfer, opts);
-        }
+        let _ = self.spi_read_write(&[0x01, 0x00, 0x00], &mut dummy_in, opts);
 
-        let mut cmd = [0x01u8];
-        xfer = 0;
-        unsafe {
-            SPI_Write(handle, cmd.as_mut_ptr(), 1, &mut xfer,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE);
-        }
+        let _ = self.spi_write(&[0x01],
+            SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE);
         let mut rd = [0u8; 2];
-        xfer = 0;
-        unsafe {
-            SPI_Read(handle, rd.as_mut_ptr(), 2, &mut xfer,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE);
-        }
+        let _ = self.spi_read(&mut rd,
+            SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE);
     }
 
     /// Open and configure an SPI channel
-    fn open_channel(channel_index: u32) -> Result<FT_HANDLE> {
+    ///
+    /// `pin` is libMPSSE's initial/final low-byte state, used by [`SpiBus`]
+    /// to drive every chip-select line to its idle level.
+    pub(crate) fn open_channel(channel_index: u32, spi_config: &SpiConfig, pin: DWORD) -> Result<FT_HANDLE> {
         let mut num_channels: DWORD = 0;
         let status = unsafe { SPI_GetNumChannels(&mut num_channels) };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Open, None)?;
 
         if num_channels == 0 {
             return Err(Adxl355Error::NoChannelsFound);
@@ -253,24 +846,14 @@ impl Adxl355 {
 
         let mut handle: FT_HANDLE = ptr::null_mut();
         let status = unsafe { SPI_OpenChannel(channel_index, &mut handle) };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        check_status(status, TransportOp::Open, None)?;
 
-        let mut config = ChannelConfig {
-            ClockRate: 1_000_000, // 1 MHz (start conservative, increase after verification)
-            LatencyTimer: 1,
-            configOptions: SPI_CONFIG_OPTION_MODE0
-                | SPI_CONFIG_OPTION_CS_DBUS3
-                | SPI_CONFIG_OPTION_CS_ACTIVELOW,
-            Pin: 0,
-            currentPinState: 0,
-        };
+        let mut config = spi_config.channel_config(pin);
 
         let status = unsafe { SPI_InitChannel(handle, &mut config) };
-        if status != FT_OK {
+        if let Err(e) = check_status(status, TransportOp::Configure, None) {
             unsafe { SPI_CloseChannel(handle) };
-            return Err(status.into());
+            return Err(e);
         }
 
         Ok(handle)
@@ -278,22 +861,39 @@ impl Adxl355 {
 
     /// Initialize the ADXL355 sensor
     fn init(&mut self) -> Result<()> {
-        Self::prime_spi(self.handle);
+        {
+            let _selected = self.select()?;
+            self.prime_spi();
+        }
 
         let devid_ad = self.read_register(REG_DEVID_AD)?;
         if devid_ad != DEVID_AD_VALUE {
             return Err(Adxl355Error::InvalidDeviceId(devid_ad));
         }
 
-        let partid = self.read_register(REG_PARTID)?;
-        if partid != PARTID_VALUE {
-            return Err(Adxl355Error::InvalidPartId(partid));
+        let devid_mst = self.read_register(REG_DEVID_MST)?;
+        if devid_mst != DEVID_MST_VALUE {
+            return Err(Adxl355Error::InvalidMemsId(devid_mst));
         }
 
+        let partid = self.read_register(REG_PARTID)?;
+        let detected = DeviceVariant::detect(partid)
+            .ok_or(Adxl355Error::InvalidPartId(partid))?;
+        let revid = self.read_register(REG_REVID)?;
+
+        // A variant declared before init (e.g. on reconnect) wins over detection
+        let variant = self.info.map(|info| info.variant).unwrap_or(detected);
+        self.info = Some(DeviceInfo { variant, devid_ad, devid_mst, partid, revid });
+
         // Read-modify-write to preserve upper bits (I2C_HS, INT_POL) — same as set_range()
+        let default_range = variant.default_range();
         let current_range = self.read_register(REG_RANGE)?;
-        self.write_register(REG_RANGE, (current_range & 0xFC) | (Range::G2 as u8))?;
+        self.write_register(REG_RANGE, (current_range & 0xFC) | default_range.register_bits())?;
+        self.range = default_range;
         self.write_register(REG_FILTER, OutputDataRate::Odr1000 as u8)?;
+        self.odr = OutputDataRate::Odr1000;
+        self.write_register(REG_SYNC, SyncMode::Internal.register_value())?;
+        self.sync_mode = SyncMode::Internal;
         self.write_register(REG_POWER_CTL, 0x00)?;
 
         std::thread::sleep(Duration::from_millis(10));
@@ -319,26 +919,13 @@ impl Adxl355 {
     /// Write a single byte to a register
     fn write_register(&mut self, reg: u8, value: u8) -> Result<()> {
         let cmd = (reg << 1) | 0x00; // write command
-        let mut buffer = [cmd, value];
-        let mut transferred: DWORD = 0;
 
         let options = SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE
             | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE;
 
-        let status = unsafe {
-            SPI_Write(
-                self.handle,
-                buffer.as_mut_ptr(),
-                2,
-                &mut transferred,
-                options,
-            )
-        };
-
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        let _selected = self.select()?;
+        self.spi_write(&[cmd, value], options)?;
 
         Ok(())
     }
@@ -346,40 +933,15 @@ impl Adxl355 {
     /// Read a single byte from a register
     fn read_register(&mut self, reg: u8) -> Result<u8> {
         let cmd = (reg << 1) | 0x01; // read command
-        let mut cmd_buf = [cmd];
-        let mut transferred: DWORD = 0;
 
         // Write command byte (CS asserted, stays low)
-        let status = unsafe {
-            SPI_Write(
-                self.handle,
-                cmd_buf.as_mut_ptr(),
-                1,
-                &mut transferred,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
-                    | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE,
-            )
-        };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        let _selected = self.select()?;
+        self.spi_write(&[cmd], SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE)?;
 
         // Read 3 bytes (2 stale pipeline + 1 fresh), then CS deasserted
         let mut data = [0u8; 3];
-        transferred = 0;
-        let status = unsafe {
-            SPI_Read(
-                self.handle,
-                data.as_mut_ptr(),
-                3,
-                &mut transferred,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
-                    | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE,
-            )
-        };
-        if status != FT_OK {
-            return Err(status.into());
-        }
+        self.spi_read(&mut data, SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE)
+            .map_err(|e| e.at_register(reg))?;
 
         Ok(data[2]) // skip 2-byte pipeline delay
     }
@@ -387,44 +949,123 @@ impl Adxl355 {
     /// Read multiple bytes from consecutive registers
     fn read_registers(&mut self, reg: u8, count: usize) -> Result<Vec<u8>> {
         let cmd = (reg << 1) | 0x01; // read command
-        let mut cmd_buf = [cmd];
-        let mut transferred: DWORD = 0;
 
         // Write command byte (CS asserted, stays low)
-        let status = unsafe {
-            SPI_Write(
-                self.handle,
-                cmd_buf.as_mut_ptr(),
-                1,
-                &mut transferred,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
-                    | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE,
-            )
+        let _selected = self.select()?;
+        self.spi_write(&[cmd], SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_ENABLE)?;
+
+        // Read count+2 bytes (2 stale pipeline + count fresh), then CS deasserted
+        let mut data = vec![0u8; count + 2];
+        self.spi_read(&mut data, SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE)
+            .map_err(|e| e.at_register(reg))?;
+
+        // Skip 2-byte pipeline delay
+        Ok(data[2..].to_vec())
+    }
+
+    // ========================================================================
+    // Transport calls
+    //
+    // Every libMPSSE / D2XX transfer goes through `transport`, which answers
+    // from the replay trace when one is loaded and records the call when a
+    // tracer is attached. Both live in RefCells because a bus selection
+    // guard holds `&self` across the transfers of one transaction.
+    // ========================================================================
+
+    /// Issue one transfer; `call` does the FFI work and returns (status, bytes transferred)
+    fn transport<F>(&self, op: TraceOp, options: DWORD, out: &[u8], input: &mut [u8], call: F) -> Result<DWORD>
+    where
+        F: FnOnce(FT_HANDLE, &mut [u8]) -> (FT_STATUS, DWORD),
+    {
+        let started = Instant::now();
+        let result = match self.replay.borrow_mut().as_mut() {
+            Some(replay) => replay.next(op, options, out, input)?,
+            None => call(self.handle, input),
         };
-        if status != FT_OK {
-            return Err(status.into());
+        if let Some(tracer) = self.tracer.borrow_mut().as_mut() {
+            tracer.record(started, op, options, result, out, input);
         }
 
-        // Read count+2 bytes (2 stale pipeline + count fresh), then CS deasserted
-        let total = count + 2;
-        let mut data = vec![0u8; total];
-        transferred = 0;
-        let status = unsafe {
-            SPI_Read(
-                self.handle,
-                data.as_mut_ptr(),
-                total as DWORD,
-                &mut transferred,
-                SPI_TRANSFER_OPTIONS_SIZE_IN_BYTES
-                    | SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE,
-            )
+        let (status, transferred) = result;
+        // The command byte names the register; a plain read continues the previous command
+        let command = out.first().copied();
+        let transport_op = match op {
+            TraceOp::SpiWrite | TraceOp::SpiReadWrite if command.is_some_and(|cmd| cmd & 0x01 == 0) => TransportOp::Write,
+            TraceOp::SpiWrite | TraceOp::SpiReadWrite | TraceOp::SpiRead => TransportOp::Read,
+            TraceOp::MpsseTransfer => TransportOp::Transfer,
+            TraceOp::Purge => TransportOp::Purge,
         };
-        if status != FT_OK {
-            return Err(status.into());
+        let register = match op {
+            TraceOp::SpiWrite | TraceOp::SpiReadWrite => command.map(|cmd| cmd >> 1),
+            _ => None,
+        };
+        check_status(status, transport_op, register)?;
+        Ok(transferred)
+    }
+
+    fn spi_write(&self, data: &[u8], options: DWORD) -> Result<DWORD> {
+        self.transport(TraceOp::SpiWrite, options, data, &mut [], |handle, _| {
+            let mut transferred: DWORD = 0;
+            // libMPSSE takes a mutable pointer but only reads the buffer
+            let status = unsafe {
+                SPI_Write(handle, data.as_ptr() as *mut UCHAR, data.len() as DWORD, &mut transferred, options)
+            };
+            (status, transferred)
+        })
+    }
+
+    fn spi_read(&self, buffer: &mut [u8], options: DWORD) -> Result<DWORD> {
+        self.transport(TraceOp::SpiRead, options, &[], buffer, |handle, buffer| {
+            let mut transferred: DWORD = 0;
+            let status = unsafe {
+                SPI_Read(handle, buffer.as_mut_ptr(), buffer.len() as DWORD, &mut transferred, options)
+            };
+            (status, transferred)
+        })
+    }
+
+    fn spi_read_write(&self, out: &[u8], input: &mut [u8], options: DWORD) -> Result<DWORD> {
+        self.transport(TraceOp::SpiReadWrite, options, out, input, |handle, input| {
+            let mut transferred: DWORD = 0;
+            let status = unsafe {
+                SPI_ReadWrite(handle, input.as_mut_ptr(), out.as_ptr() as *mut UCHAR,
+                    out.len() as DWORD, &mut transferred, options)
+            };
+            (status, transferred)
+        })
+    }
+
+    /// Send a raw MPSSE command buffer and collect its read data
+    fn mpsse_transfer(&self, commands: &CommandBuffer) -> Result<Vec<u8>> {
+        let mut data = vec![0u8; commands.read_len()];
+        let received = self.transport(TraceOp::MpsseTransfer, 0, commands.as_bytes(), &mut data, |handle, data| {
+            match mpsse::transfer(handle, commands) {
+                Ok(received) => {
+                    data.copy_from_slice(&received);
+                    (FT_OK, received.len() as DWORD)
+                }
+                Err(Adxl355Error::Transport { status, .. }) => (status.raw(), 0),
+                Err(Adxl355Error::TransferError { actual, .. }) => (FT_OK, actual),
+                Err(_) => (FT_OTHER_ERROR, 0),
+            }
+        })?;
+
+        if received as usize != data.len() {
+            return Err(Adxl355Error::TransferError {
+                register: None,
+                expected: data.len() as u32,
+                actual: received,
+            });
         }
+        Ok(data)
+    }
 
-        // Skip 2-byte pipeline delay
-        Ok(data[2..].to_vec())
+    /// Drop anything left in the D2XX receive/transmit buffers
+    fn purge(&self) -> Result<()> {
+        self.transport(TraceOp::Purge, 0, &[], &mut [], |handle, _| {
+            (unsafe { FT_Purge(handle, FT_PURGE_RX | FT_PURGE_TX) }, 0)
+        })?;
+        Ok(())
     }
 
     // ========================================================================
@@ -432,11 +1073,17 @@ impl Adxl355 {
     // ========================================================================
 
     pub fn set_range(&mut self, range: Range) -> Result<()> {
+        if !self.variant().ranges().contains(&range) {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "Range {} is not available on the {}", range.label(), self.variant().name()
+            )));
+        }
+
         self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY)?;
         std::thread::sleep(Duration::from_millis(5));
 
         let current = self.read_register(REG_RANGE)?;
-        let new_val = (current & 0xFC) | (range as u8);
+        let new_val = (current & 0xFC) | range.register_bits();
         self.write_register(REG_RANGE, new_val)?;
 
         self.range = range;
@@ -454,6 +1101,7 @@ impl Adxl355 {
         let current = self.read_register(REG_FILTER)?;
         let new_val = (current & 0xF0) | (odr as u8);
         self.write_register(REG_FILTER, new_val)?;
+        self.odr = odr;
 
         self.write_register(REG_POWER_CTL, 0x00)?;
         std::thread::sleep(Duration::from_millis(5));
@@ -465,6 +1113,146 @@ impl Adxl355 {
         self.range
     }
 
+    /// Part variant (ADXL355 unless declared otherwise)
+    pub fn variant(&self) -> DeviceVariant {
+        self.info.map(|info| info.variant).unwrap_or(DeviceVariant::Adxl355)
+    }
+
+    /// Declare the part variant
+    ///
+    /// Needed for the ADXL357, which reports the same PARTID as the ADXL355.
+    /// The current range is re-interpreted for the new part (the register
+    /// code is unchanged), so no bus traffic is involved.
+    pub fn set_variant(&mut self, variant: DeviceVariant) {
+        let info = self.info.get_or_insert(DeviceInfo {
+            variant,
+            devid_ad: DEVID_AD_VALUE,
+            devid_mst: DEVID_MST_VALUE,
+            partid: PARTID_VALUE,
+            revid: 0,
+        });
+        info.variant = variant;
+        self.range = self.range.for_variant(variant);
+    }
+
+    /// Identification registers read during init (None before init)
+    pub fn device_info(&self) -> Option<DeviceInfo> {
+        self.info
+    }
+
+    /// Serial number of the FT232H, `None` if the driver does not report one
+    pub fn serial_number(&self) -> Option<String> {
+        let mut info: FT_DEVICE_LIST_INFO_NODE = unsafe { std::mem::zeroed() };
+        let status = unsafe { SPI_GetChannelInfo(self.channel_index, &mut info) };
+        if status != FT_OK {
+            return None;
+        }
+        let serial = &info.SerialNumber;
+        let len = serial.iter().position(|&b| b == 0).unwrap_or(serial.len());
+        let serial = String::from_utf8_lossy(&serial[..len]).trim().to_string();
+        (!serial.is_empty()).then_some(serial)
+    }
+
+    /// Select the sync/clock source. In the external modes the sensor stops
+    /// producing samples until the DRDY sync pulses (and INT2 clock) arrive.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) -> Result<()> {
+        self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY)?;
+        std::thread::sleep(Duration::from_millis(5));
+
+        self.write_register(REG_SYNC, mode.register_value())?;
+
+        let readback = self.read_register(REG_SYNC)?;
+        if SyncMode::from_register(readback) != Some(mode) {
+            return Err(Adxl355Error::UnexpectedResponse(format!(
+                "SYNC readback mismatch: wrote 0x{:02X}, read 0x{:02X}",
+                mode.register_value(), readback
+            )));
+        }
+
+        self.sync_mode = mode;
+
+        self.write_register(REG_POWER_CTL, 0x00)?;
+        std::thread::sleep(Duration::from_millis(5));
+
+        Ok(())
+    }
+
+    pub fn get_sync_mode(&self) -> SyncMode {
+        self.sync_mode
+    }
+
+    /// Get the current output data rate
+    pub fn get_odr(&self) -> OutputDataRate {
+        self.odr
+    }
+
+    /// Run the datasheet electrostatic self-test
+    ///
+    /// Enables self-test mode (ST1), averages `samples` readings with the
+    /// force off, applies the force (ST2), averages again, then restores
+    /// normal operation. The sensor must be stationary and FIFO mode off.
+    ///
+    /// The test runs at the part's widest range (+/-8 g, or +/-40 g on the
+    /// ADXL357), where 1 g of gravity plus the self-test force cannot
+    /// saturate; the range in use is restored afterwards.
+    pub fn self_test(&mut self, samples: usize) -> Result<SelfTestReport> {
+        if samples == 0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Self-test sample count must be at least 1".to_string()
+            ));
+        }
+        if self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "Disable FIFO mode before running the self-test".to_string()
+            ));
+        }
+
+        // Let the digital filter settle (~10 output periods) after each change
+        let period = Duration::from_secs_f64(1.0 / self.odr.as_hz());
+        let settle = (period * 10).max(Duration::from_millis(20));
+
+        let range = self.range;
+        let test_range = self.variant().ranges()[2];
+        self.set_range(test_range)?;
+
+        let off = self.write_register(REG_SELF_TEST, SELF_TEST_ST1).and_then(|_| {
+            std::thread::sleep(settle);
+            self.average_accel_g(samples, period)
+        });
+
+        let on = off.and_then(|off| {
+            self.write_register(REG_SELF_TEST, SELF_TEST_ST1 | SELF_TEST_ST2)?;
+            std::thread::sleep(settle);
+            self.average_accel_g(samples, period).map(|on| (off, on))
+        });
+
+        // Always leave self-test mode and go back to the range in use, even
+        // if a read failed
+        let reset = self.write_register(REG_SELF_TEST, 0x00);
+        std::thread::sleep(settle);
+        let restore = self.set_range(range);
+
+        let (off_g, on_g) = on?;
+        reset?;
+        restore?;
+        Ok(SelfTestReport::evaluate(test_range, self.variant(), samples, off_g, on_g))
+    }
+
+    /// Average `samples` polled readings, converted to g
+    fn average_accel_g(&mut self, samples: usize, period: Duration) -> Result<[f32; 3]> {
+        let mut sum = [0i64; 3];
+        for _ in 0..samples {
+            let (x, y, z) = self.read_accel()?;
+            sum[0] += x as i64;
+            sum[1] += y as i64;
+            sum[2] += z as i64;
+            std::thread::sleep(period);
+        }
+
+        let scale = self.range.scale_factor() * samples as f32;
+        Ok([sum[0] as f32 / scale, sum[1] as f32 / scale, sum[2] as f32 / scale])
+    }
+
     // ========================================================================
     // Data reading
     // ========================================================================
@@ -483,6 +1271,43 @@ impl Adxl355 {
         Ok((x, y, z))
     }
 
+    /// Set the per-device temperature calibration
+    pub fn set_temperature_calibration(&mut self, cal: TemperatureCalibration) {
+        self.temp_calibration = cal;
+    }
+
+    /// Get the per-device temperature calibration
+    pub fn temperature_calibration(&self) -> TemperatureCalibration {
+        self.temp_calibration
+    }
+
+    /// Read the temperature in Celsius using the device calibration
+    pub fn read_temperature_c(&mut self) -> Result<f32> {
+        let raw = self.read_temperature()?;
+        Ok(self.temp_calibration.to_celsius(raw))
+    }
+
+    /// Set how often FIFO batch reads refresh the temperature registers
+    ///
+    /// Temperature changes slowly, so the default 100 ms keeps the extra
+    /// bus traffic negligible. `Duration::ZERO` reads it on every batch.
+    pub fn set_fifo_temperature_interval(&mut self, interval: Duration) {
+        self.temperature_interval = interval;
+    }
+
+    /// Refresh the carried-forward temperature if the interval has elapsed
+    fn refresh_temperature(&mut self) -> Result<u16> {
+        let due = match self.last_temperature_read {
+            Some(t) => t.elapsed() >= self.temperature_interval,
+            None => true,
+        };
+        if due {
+            self.last_temperature = self.read_temperature()?;
+            self.last_temperature_read = Some(Instant::now());
+        }
+        Ok(self.last_temperature)
+    }
+
     pub fn read_all(&mut self) -> Result<SensorData> {
         let data = self.read_registers(REG_TEMP2, 11)?;
 
@@ -536,6 +1361,52 @@ impl Adxl355 {
         Ok(sample_count)
     }
 
+    /// Polling stream that survives USB errors and unplugging
+    ///
+    /// Like [`Adxl355::stream`], with every read going through
+    /// [`Adxl355::with_recovery`]. The callback sees
+    /// [`StreamEvent::Reconnected`] after a reconnect; the schedule then
+    /// restarts from the current time instead of trying to catch up.
+    pub fn stream_resilient<F>(&mut self, rate_hz: u32, policy: &RetryPolicy, mut callback: F) -> Result<u64>
+    where
+        F: FnMut(StreamEvent<'_, SensorData>) -> StreamControl,
+    {
+        if rate_hz == 0 || rate_hz > 4000 {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "Sample rate must be between 1-4000 Hz, got {}",
+                rate_hz
+            )));
+        }
+
+        let interval = Duration::from_micros(1_000_000 / rate_hz as u64);
+        let mut sample_count = 0u64;
+        let mut next_sample_time = Instant::now();
+
+        loop {
+            let (data, outage) = self.with_recovery(policy, |s| s.read_all())?;
+
+            if let Some(outage) = &outage {
+                next_sample_time = Instant::now();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            }
+
+            sample_count += 1;
+            if callback(StreamEvent::Data(data)) == StreamControl::Break {
+                break;
+            }
+
+            next_sample_time += interval;
+            let now = Instant::now();
+            if next_sample_time > now {
+                std::thread::sleep(next_sample_time - now);
+            }
+        }
+
+        Ok(sample_count)
+    }
+
     pub fn stream_for<F>(&mut self, rate_hz: u32, duration: Duration, mut callback: F) -> Result<u64>
     where
         F: FnMut(SensorData),
@@ -578,11 +1449,14 @@ impl Adxl355 {
         let current_filter = self.read_register(REG_FILTER)?;
         let new_filter = (current_filter & 0xF0) | (odr as u8);
         self.write_register(REG_FILTER, new_filter)?;
+        self.odr = odr;
 
         self.write_register(REG_POWER_CTL, 0x00)?;
         std::thread::sleep(Duration::from_millis(10));
 
         self.fifo_enabled = true;
+        self.last_temperature_read = None;
+        self.raw_partial.clear();
 
         let _ = self.read_fifo_batch();
 
@@ -612,6 +1486,7 @@ impl Adxl355 {
         let bytes_to_read = num_samples * 9;
 
         let fifo_data = self.read_registers(REG_FIFO_DATA, bytes_to_read)?;
+        let temperature = self.refresh_temperature()?;
 
         // Parse with X-axis marker alignment (bit 0 of low byte = X marker)
         let mut samples = Vec::with_capacity(num_samples);
@@ -642,7 +1517,7 @@ impl Adxl355 {
                 accel_x,
                 accel_y,
                 accel_z,
-                temperature: 0,
+                temperature,
             });
 
             i += 9;
@@ -656,13 +1531,14 @@ impl Adxl355 {
         let entries = self.get_fifo_entries()? as usize;
 
         if entries < 3 {
-            return Ok(FifoBatchResult { samples: Vec::new(), overflow_detected: false });
+            return Ok(FifoBatchResult { samples: Vec::new(), overflow_detected: false, carried: 0 });
         }
 
         let num_samples = entries / 3;
         let bytes_to_read = num_samples * 9;
 
         let fifo_data = self.read_registers(REG_FIFO_DATA, bytes_to_read)?;
+        let temperature = self.refresh_temperature()?;
 
         let mut samples = Vec::with_capacity(num_samples);
         let mut i = 0;
@@ -672,7 +1548,7 @@ impl Adxl355 {
         while i + 2 < fifo_data.len() {
             if fifo_data[i + 2] & 0x02 != 0 {
                 overflow_detected = true;
-                return Ok(FifoBatchResult { samples, overflow_detected });
+                return Ok(FifoBatchResult { samples, overflow_detected, carried: 0 });
             }
             if fifo_data[i + 2] & 0x01 != 0 {
                 break;
@@ -695,13 +1571,110 @@ impl Adxl355 {
                 accel_x,
                 accel_y,
                 accel_z,
-                temperature: 0,
+                temperature,
             });
 
             i += 9;
         }
 
-        Ok(FifoBatchResult { samples, overflow_detected })
+        Ok(FifoBatchResult { samples, overflow_detected, carried: 0 })
+    }
+
+    // ========================================================================
+    // Raw MPSSE FIFO path
+    //
+    // One FT_Write carries two SPI transactions (STATUS, then a full-depth
+    // FIFO_DATA burst) and one FT_Read returns both. No FIFO_ENTRIES read is
+    // needed: unfilled entries come back flagged empty and are dropped.
+    // ========================================================================
+
+    fn raw_cs(&self) -> Result<ChipSelect> {
+        let (pin, others) = match &self.bus {
+            None => (self.config.get_cs_pin(), 0),
+            Some(link) => match link.line() {
+                CsLine::Dbus(pin) => (pin, link.other_dbus_lines()),
+                CsLine::Acbus(_) => return Err(Adxl355Error::InvalidParameter(
+                    "Raw MPSSE FIFO reads need a DBUS chip select".to_string()
+                )),
+            },
+        };
+        Ok(ChipSelect::new(pin, self.config.is_cs_active_low()).with_idle_lines(others))
+    }
+
+    /// Queue one register read transaction (CS low, command, data, CS high)
+    fn queue_read(&self, cmds: &mut CommandBuffer, reg: u8, count: usize, skip: usize) {
+        cmds.cs_assert()
+            .write(&[(reg << 1) | 0x01])
+            .read(skip + count)
+            .cs_deassert();
+    }
+
+    /// Find how many stale bytes precede register data on the raw path
+    ///
+    /// Reads DEVID_AD..PARTID with a few extra bytes and looks for the known
+    /// ID sequence, so the offset does not have to be assumed.
+    fn calibrate_raw(&mut self) -> Result<usize> {
+        let mut cmds = CommandBuffer::new(self.raw_cs()?);
+        self.queue_read(&mut cmds, REG_DEVID_AD, 3, 3);
+        cmds.send_immediate();
+
+        let data = {
+            let _selected = self.select()?;
+            self.purge()?;
+            self.mpsse_transfer(&cmds)?
+        };
+
+        let ids = [DEVID_AD_VALUE, DEVID_MST_VALUE, PARTID_VALUE];
+        let skip = (0..=3)
+            .find(|&k| data[k..k + 3] == ids)
+            .ok_or_else(|| Adxl355Error::UnexpectedResponse(format!(
+                "Raw MPSSE ID read did not return AD 1D ED: {:02X?}", data
+            )))?;
+
+        self.raw_skip = Some(skip);
+        Ok(skip)
+    }
+
+    /// Read the whole FIFO in a single USB round-trip
+    ///
+    /// `overflow_detected` reflects the STATUS FIFO_OVR bit, i.e. samples
+    /// were actually dropped by the sensor since the previous call. A full
+    /// FIFO plus a sample carried over from the previous read returns 33
+    /// samples; `carried` tells the two apart.
+    pub fn read_fifo_batch_raw(&mut self) -> Result<FifoBatchResult> {
+        // The queued opcodes clock out on the falling edge and sample on the rising edge
+        if self.config.get_mode() != SpiMode::Mode0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Raw MPSSE FIFO reads require SPI mode 0".to_string()
+            ));
+        }
+
+        let skip = match self.raw_skip {
+            Some(skip) => skip,
+            None => self.calibrate_raw()?,
+        };
+
+        let fifo_bytes = FIFO_DEPTH_ENTRIES * 3;
+        let mut cmds = CommandBuffer::new(self.raw_cs()?);
+        self.queue_read(&mut cmds, REG_STATUS, 1, skip);
+        self.queue_read(&mut cmds, REG_FIFO_DATA, fifo_bytes, skip);
+        cmds.send_immediate();
+
+        let data = {
+            let _selected = self.select()?;
+            self.mpsse_transfer(&cmds)?
+        };
+        let status = data[skip];
+        let fifo = &data[2 * skip + 1..];
+
+        let temperature = self.refresh_temperature()?;
+        let (samples, carried) = assemble_fifo_samples(fifo.chunks_exact(3), &mut self.raw_partial, temperature);
+
+        Ok(FifoBatchResult {
+            samples,
+            overflow_detected: status & STATUS_FIFO_OVR != 0,
+            carried,
+        })
     }
 
     pub fn stream_fifo<F>(&mut self, batch_interval_ms: u64, mut callback: F) -> Result<u64>
@@ -746,6 +1719,87 @@ impl Adxl355 {
         Ok(total_samples)
     }
 
+    /// Stream FIFO data with the read interval chosen automatically
+    ///
+    /// A [`PollScheduler`] aims each read at half of the 32-sample FIFO,
+    /// tracking the measured fill rate and USB latency from the current ODR.
+    /// Overflow detection follows [`Adxl355::read_fifo_batch_checked`].
+    pub fn stream_fifo_adaptive<F>(&mut self, callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        let scheduler = PollScheduler::new(FIFO_DEPTH_ENTRIES / 3, self.odr.as_hz());
+        self.stream_fifo_scheduled(scheduler, callback)
+    }
+
+    /// Stream FIFO data with a caller-configured [`PollScheduler`]
+    pub fn stream_fifo_scheduled<F>(&mut self, mut scheduler: PollScheduler, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(&[SensorData]) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "FIFO not enabled. Call enable_fifo() first.".to_string()
+            ));
+        }
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let result = self.read_fifo_batch_checked()?;
+            scheduler.record(result.samples.len(), result.overflow_detected, read_start.elapsed());
+            let batch = result.samples;
+
+            if !batch.is_empty() && callback(&batch) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
+    /// Adaptive FIFO streaming that survives USB errors and unplugging
+    ///
+    /// Like [`Adxl355::stream_fifo_adaptive`], but each read goes through
+    /// [`Adxl355::with_recovery`]. After a reconnect the callback receives
+    /// [`StreamEvent::Reconnected`] before the next batch.
+    pub fn stream_fifo_resilient<F>(&mut self, policy: &RetryPolicy, mut callback: F) -> Result<PollStats>
+    where
+        F: FnMut(StreamEvent<'_, &[SensorData]>) -> StreamControl,
+    {
+        if !self.fifo_enabled {
+            return Err(Adxl355Error::InvalidParameter(
+                "FIFO not enabled. Call enable_fifo() first.".to_string()
+            ));
+        }
+
+        let mut scheduler = PollScheduler::new(FIFO_DEPTH_ENTRIES / 3, self.odr.as_hz());
+
+        loop {
+            scheduler.wait();
+
+            let read_start = Instant::now();
+            let (result, outage) = self.with_recovery(policy, |s| s.read_fifo_batch_checked())?;
+            let batch = result.samples;
+
+            if let Some(outage) = &outage {
+                scheduler.resync();
+                if callback(StreamEvent::Reconnected(outage)) == StreamControl::Break {
+                    break;
+                }
+            } else {
+                scheduler.record(batch.len(), result.overflow_detected, read_start.elapsed());
+            }
+
+            if !batch.is_empty() && callback(StreamEvent::Data(&batch)) == StreamControl::Break {
+                break;
+            }
+        }
+
+        Ok(*scheduler.stats())
+    }
+
     pub fn collect_samples_fifo(&mut self, odr: OutputDataRate, num_samples: usize) -> Result<Vec<SensorData>> {
         if !self.fifo_enabled {
             self.enable_fifo(odr)?;
@@ -753,7 +1807,7 @@ impl Adxl355 {
 
         let mut samples = Vec::with_capacity(num_samples);
 
-        self.stream_fifo(50, |batch| {
+        self.stream_fifo_adaptive(|batch| {
             samples.extend_from_slice(batch);
 
             if samples.len() >= num_samples {
@@ -771,10 +1825,127 @@ impl Adxl355 {
 
 impl Drop for Adxl355 {
     fn drop(&mut self) {
+        // Channel already closed by a reconnect that did not succeed
+        if self.handle.is_null() {
+            return;
+        }
+
         let _ = self.write_register(REG_POWER_CTL, POWER_CTL_STANDBY);
 
-        unsafe {
-            SPI_CloseChannel(self.handle);
+        // A shared channel is closed when the last device and the `SpiBus` are gone
+        match &self.bus {
+            Some(link) => link.release(),
+            None => {
+                self.gpio.borrow_mut().detach();
+                unsafe { SPI_CloseChannel(self.handle) };
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FIFO entry for `value`, tagged as an X entry when `x` is set
+    fn entry(value: u8, x: bool) -> [u8; 3] {
+        [0x00, value, if x { FIFO_X_MARKER } else { 0x00 }]
+    }
+
+    fn fifo(entries: &[[u8; 3]]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = entries.iter().flatten().copied().collect();
+        bytes.extend_from_slice(&[0x00, 0x00, FIFO_EMPTY]);
+        bytes
+    }
+
+    #[test]
+    fn split_sample_is_completed_and_marked_carried() {
+        let mut partial = Vec::new();
+
+        let first = fifo(&[entry(1, true), entry(2, false), entry(3, false), entry(4, true), entry(5, false)]);
+        let (samples, carried) = assemble_fifo_samples(first.chunks_exact(3), &mut partial, 0);
+        assert_eq!((samples.len(), carried), (1, 0));
+        assert_eq!(partial.len(), 2);
+
+        let second = fifo(&[entry(6, false), entry(7, true), entry(8, false), entry(9, false)]);
+        let (samples, carried) = assemble_fifo_samples(second.chunks_exact(3), &mut partial, 0);
+        assert_eq!((samples.len(), carried), (2, 1));
+        assert_eq!(samples[0].accel_x, parse_20bit(0x00, 4, FIFO_X_MARKER));
+        assert_eq!(samples[0].accel_z, parse_20bit(0x00, 6, 0x00));
+        assert!(partial.is_empty());
+    }
+
+    #[test]
+    fn restarted_sample_is_not_carried() {
+        let mut partial = vec![entry(1, true)];
+        let data = fifo(&[entry(2, true), entry(3, false), entry(4, false)]);
+        let (samples, carried) = assemble_fifo_samples(data.chunks_exact(3), &mut partial, 0);
+        assert_eq!((samples.len(), carried), (1, 0));
+    }
+
+    fn fixture(name: &str) -> Trace {
+        Trace::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)).unwrap()
+    }
+
+    #[test]
+    fn replay_runs_init_and_read_from_a_trace() {
+        let mut sensor = Adxl355::replay(fixture("init_read_accel.trace"), SpiConfig::default()).unwrap();
+        assert_eq!(sensor.device_info().map(|info| info.revid), Some(0x01));
+        assert_eq!(sensor.get_range(), Range::G2);
+
+        assert_eq!(sensor.read_accel().unwrap(), (4096, -256, 256_000));
+        assert_eq!(sensor.replay_remaining(), Some(0));
+
+        // Nothing left to answer from
+        assert!(matches!(sensor.read_temperature(), Err(Adxl355Error::Replay(_))));
+    }
+
+    #[test]
+    fn replay_reports_a_diverging_call() {
+        let mut trace = fixture("init_read_accel.trace");
+        // Recorded against a part that answered REVID with a longer read
+        trace.records[10].input = vec![0x00, 0x00, 0x00, 0x01];
+        assert!(matches!(Adxl355::replay(trace, SpiConfig::default()), Err(Adxl355Error::Replay(_))));
+    }
+
+    #[test]
+    fn sync_modes_round_trip() {
+        let modes = [
+            SyncMode::Internal,
+            SyncMode::ExternalSync,
+            SyncMode::ExternalSyncInterpolated,
+            SyncMode::ExternalClock,
+            SyncMode::ExternalClockAndSync,
+            SyncMode::ExternalClockAndSyncInterpolated,
+        ];
+        for mode in modes {
+            assert_eq!(SyncMode::from_register(mode.register_value()), Some(mode));
+            assert_eq!(SyncMode::from_name(mode.as_str()), Some(mode));
+        }
+        assert_eq!(SyncMode::ExternalClockAndSyncInterpolated.register_value(), 0x06);
+        // EXT_SYNC = 0b11 is reserved
+        assert_eq!(SyncMode::from_register(0x03), None);
+        assert_eq!(SyncMode::from_register(0x07), None);
+    }
+
+    #[test]
+    fn self_test_fails_saturated_axes_only() {
+        // Resting on Z with a 2 g self-test response
+        let (off, on) = ([0.0, 0.0, 1.0], [0.3, 0.3, 3.0]);
+        let report = SelfTestReport::evaluate(Range::G8, DeviceVariant::Adxl355, 50, off, on);
+        assert_eq!(report.axis_saturated, [false; 3]);
+        assert!(report.passed());
+        assert_eq!(report.delta_g[2], 2.0);
+
+        // The same part at +/-2 g: Z clips just above 2 g and cannot pass
+        let clipped = SelfTestReport::evaluate(Range::G2, DeviceVariant::Adxl355, 50, off, [0.3, 0.3, 2.04]);
+        assert_eq!(clipped.axis_saturated, [false, false, true]);
+        assert_eq!(clipped.axis_passed, [true, true, false]);
+        assert!(!clipped.passed());
+
+        // In scale, but X is below the ADXL357 limit
+        let weak = SelfTestReport::evaluate(Range::G40, DeviceVariant::Adxl357, 50, off, [0.05, 0.3, 2.0]);
+        assert_eq!(weak.axis_saturated, [false; 3]);
+        assert_eq!(weak.axis_passed, [false, true, true]);
+    }
+}
@@ -1,10 +1,13 @@
 //! ADXL355 Data Analyzer
 //!
 //! Post-processing analysis tool for sensor data from HDF5 files.
+//! With `--by-events` the window is cut at the recorded events and each
+//! stretch is analysed on its own.
 
 use clap::Parser;
-use ft232_adxl355_spi::{Hdf5Reader, Range, TimestampedSample};
+use ft232_adxl355_spi::{Adxl355Error, Completion, Hdf5Reader, Range, TimestampedSample};
 use ft232_adxl355_spi::analysis::{compute_rms, find_frequency_peaks};
+use ft232_adxl355_spi::events;
 use std::f64::consts::PI;
 use std::fs::File;
 use std::io::{self, Write};
@@ -14,7 +17,7 @@ use std::path::PathBuf;
 #[command(name = "analyzer")]
 #[command(about = "Analyze ADXL355 sensor data from HDF5 file")]
 struct Args {
-    /// Input HDF5 file path
+    /// Input HDF5 file or session manifest (.manifest)
     #[arg(short, long)]
     input: PathBuf,
 
@@ -45,53 +48,72 @@ struct Args {
     /// Output file (default: stdout)
     #[arg(short, long)]
     output: Option<PathBuf>,
+
+    /// Device group of a multi-sensor file, e.g. "dbus4" (default: first device)
+    #[arg(long)]
+    device: Option<String>,
+
+    /// Analyse each stretch between recorded events separately
+    #[arg(long)]
+    by_events: bool,
+
+    /// With --by-events: only cut at events of this kind (e.g. "hit")
+    #[arg(long, requires = "by_events")]
+    event_kind: Option<String>,
 }
 
-fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+/// Analyses selected on the command line
+struct Selection {
+    statistics: bool,
+    fft: bool,
+    vibration: bool,
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    let reader = Hdf5Reader::open(&args.input)?;
+    let reader = Hdf5Reader::open_device(&args.input, args.device.as_deref())?;
     let metadata = reader.metadata();
+    if reader.completion() == Completion::Recovered {
+        eprintln!("Note: file was recovered from an interrupted recording");
+    }
 
-    let range = match metadata.range.as_str() {
-        "4g" => Range::G4,
-        "8g" => Range::G8,
-        _ => Range::G2,
-    };
+    let range = Range::from_label(&metadata.range).unwrap_or(Range::G2);
 
     // Default to all analyses if none specified
     let run_all = args.all || (!args.statistics && !args.fft && !args.vibration);
-    let run_statistics = run_all || args.statistics;
-    let run_fft = run_all || args.fft;
-    let run_vibration = run_all || args.vibration;
+    let selection = Selection {
+        statistics: run_all || args.statistics,
+        fft: run_all || args.fft,
+        vibration: run_all || args.vibration,
+    };
 
     println!("Loading data from {}...", args.input.display());
-    let total_samples = reader.get_total_samples()?;
-
-    if total_samples == 0 {
-        eprintln!("Error: No samples in file");
-        return Ok(());
+    if let Some(manifest) = reader.manifest() {
+        println!("Session of {} segment files", manifest.segments.len());
     }
-
-    let all_samples = reader.read_range(0, total_samples)?;
-    let file_start = all_samples.first().unwrap().timestamp;
-    let file_end = all_samples.last().unwrap().timestamp;
+    let Some((file_start, file_end)) = reader.time_span()? else {
+        return Err(Adxl355Error::Analysis("No samples in file".to_string()).into());
+    };
 
     let start_time = args.start.unwrap_or(file_start);
     let end_time = args.end.unwrap_or(file_end);
 
     if start_time >= end_time {
-        eprintln!("Error: Start time must be before end time");
-        std::process::exit(1);
+        return Err(Adxl355Error::Analysis("Start time must be before end time".to_string()).into());
     }
 
-    let samples: Vec<TimestampedSample> = all_samples.into_iter()
-        .filter(|s| s.timestamp >= start_time && s.timestamp <= end_time)
-        .collect();
+    let samples = reader.read_time_range(start_time, end_time)?;
 
     if samples.is_empty() {
-        eprintln!("Error: No samples in specified time range");
-        return Ok(());
+        return Err(Adxl355Error::Analysis("No samples in specified time range".to_string()).into());
     }
 
     // Compute actual sample rate from timestamps (more accurate than metadata ODR)
@@ -125,43 +147,118 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     writeln!(output, "{}", "=".repeat(80))?;
     writeln!(output)?;
     writeln!(output, "File Information:")?;
-    writeln!(output, "  Sensor: {}", metadata.sensor_type)?;
+    match (&metadata.part, metadata.revision) {
+        (Some(part), Some(rev)) => writeln!(output, "  Sensor: {} (rev {})", part, rev)?,
+        (Some(part), None) => writeln!(output, "  Sensor: {}", part)?,
+        _ => writeln!(output, "  Sensor: {}", metadata.sensor_type)?,
+    }
+    if let Some(device) = reader.device() {
+        writeln!(output, "  Device: {} (of {})", device, metadata.devices.join(", "))?;
+    }
     writeln!(output, "  Range: {}", metadata.range)?;
     writeln!(output, "  Acquisition mode: {}", metadata.acquisition_mode)?;
     writeln!(output, "  Configured ODR: {:.1} Hz", metadata.sample_rate_hz)?;
     writeln!(output, "  Measured sample rate: {:.1} Hz", sample_rate)?;
     writeln!(output, "  Start time: {}", metadata.start_time)?;
+    writeln!(output, "  Schema version: {}", metadata.version)?;
+    if let Some(host) = &metadata.host {
+        writeln!(output, "  Recorded on: {}", host)?;
+    }
+    if let Some(serial) = &metadata.device_serial {
+        writeln!(output, "  FT232H serial: {}", serial)?;
+    }
     writeln!(output)?;
     writeln!(output, "Analysis Range:")?;
     writeln!(output, "  Start: {:.2}s", start_time)?;
     writeln!(output, "  End: {:.2}s", end_time)?;
     writeln!(output, "  Duration: {:.2}s", end_time - start_time)?;
     writeln!(output, "  Samples: {}", samples.len())?;
+    let gaps = reader.discontinuities()?;
+    let gaps: Vec<_> = gaps.iter()
+        .filter(|gap| gap.end_time >= start_time && gap.start_time <= end_time)
+        .collect();
+    if !gaps.is_empty() {
+        let total: f64 = gaps.iter().map(|gap| gap.duration()).sum();
+        writeln!(output, "  Gaps: {} ({:.2}s missing)", gaps.len(), total)?;
+        for gap in &gaps {
+            writeln!(output, "    {:.2}s - {:.2}s: {}", gap.start_time, gap.end_time, gap.cause)?;
+        }
+    }
+    let events = reader.events(start_time..=end_time)?;
+    if !events.is_empty() {
+        writeln!(output, "  Events: {}", events.len())?;
+        for event in &events {
+            match &event.payload {
+                Some(payload) => writeln!(output, "    {:.2}s [{}] {} {}", event.time, event.kind, event.label, payload)?,
+                None => writeln!(output, "    {:.2}s [{}] {}", event.time, event.kind, event.label)?,
+            }
+        }
+    }
+
+    if !args.by_events {
+        run_analyses(&mut output, &selection, &samples, sample_rate, range)?;
+    } else {
+        let boundaries: Vec<_> = events.into_iter()
+            .filter(|event| args.event_kind.iter().all(|kind| &event.kind == kind))
+            .collect();
+        let spans = events::spans(&boundaries, start_time, end_time);
+        for (number, span) in spans.iter().enumerate() {
+            let from = samples.partition_point(|s| s.timestamp < span.start);
+            // The last span keeps the sample at end_time
+            let to = if number + 1 == spans.len() {
+                samples.len()
+            } else {
+                samples.partition_point(|s| s.timestamp < span.end)
+            };
+            writeln!(output, "\n{}", "#".repeat(80))?;
+            match span.event {
+                Some(event) => writeln!(output, "SEGMENT {}: [{}] {}", number + 1, event.kind, event.label)?,
+                None => writeln!(output, "SEGMENT {}: before first event", number + 1)?,
+            }
+            writeln!(output, "  {:.2}s - {:.2}s, {} samples", span.start, span.end, to - from)?;
+            writeln!(output, "{}", "#".repeat(80))?;
+            if from == to {
+                writeln!(output, "\n  No samples in segment")?;
+                continue;
+            }
+            run_analyses(&mut output, &selection, &samples[from..to], sample_rate, range)?;
+        }
+    }
+
+    writeln!(output, "\n{}", "=".repeat(80))?;
+    writeln!(output, "Analysis complete!")?;
 
-    if run_statistics {
+    Ok(())
+}
+
+fn run_analyses(
+    output: &mut dyn Write,
+    selection: &Selection,
+    samples: &[TimestampedSample],
+    sample_rate: f64,
+    range: Range,
+) -> io::Result<()> {
+    if selection.statistics {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "STATISTICAL ANALYSIS")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_statistics_analysis(&mut output, &samples, range)?;
+        run_statistics_analysis(output, samples, range)?;
     }
 
-    if run_fft {
+    if selection.fft {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "FREQUENCY ANALYSIS (FFT)")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_fft_analysis(&mut output, &samples, sample_rate, range)?;
+        run_fft_analysis(output, samples, sample_rate, range)?;
     }
 
-    if run_vibration {
+    if selection.vibration {
         writeln!(output, "\n{}", "=".repeat(80))?;
         writeln!(output, "VIBRATION ANALYSIS")?;
         writeln!(output, "{}", "=".repeat(80))?;
-        run_vibration_analysis(&mut output, &samples, sample_rate, range)?;
+        run_vibration_analysis(output, samples, sample_rate, range)?;
     }
 
-    writeln!(output, "\n{}", "=".repeat(80))?;
-    writeln!(output, "Analysis complete!")?;
-
     Ok(())
 }
 
@@ -1,34 +1,252 @@
 //! ADXL355 Data Collector
 //!
 //! Collects sensor data in polling or FIFO mode and writes to HDF5 file.
+//! An ACBUS input can gate the recording (`--trigger-in`) and an ACBUS
+//! output can mark its start (`--mark-out`). `--rotate` and `--rotate-size`
+//! split a long recording into numbered files with a `.manifest` index.
+//! Lines typed on stdin, or sent to `--event-port`, are stored as events.
+//! `--storage` sets chunking, compression and the timestamp encoding.
+//! `--trigger-level` keeps only the samples around threshold crossings,
+//! each capture in its own file (`<output>_0001.h5`, ...).
+//! `--config collector.toml --profile impact` takes the settings from a
+//! file; options given on the command line override it.
 
 use clap::Parser;
+use ft232_adxl355_spi::gpio::parse_pin;
+use ft232_adxl355_spi::session::segment_path;
 use ft232_adxl355_spi::{
-    Adxl355, Hdf5Writer, OutputDataRate, StreamControl, TimeKeeper, TimestampedSample,
+    Adxl355, Band, Capture, CaptureEvent, CollectionMode, CollectorConfig, CsLine, DeviceInfo, Edge, Event, Gpio, Hdf5Writer,
+    OutputDataRate, DeviceVariant, Outage, PollScheduler, PollStats, Profile, Range, RetryPolicy, RotationInterval, SpiBus,
+    SpiConfig, StorageOptions, StreamControl, StreamEvent, SyncMode, TemperatureCalibration, TimeKeeper, TimestampedSample,
+    Tracer, TriggerProfile, TriggerSource,
 };
-use std::path::PathBuf;
+use std::io::BufRead;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Trigger sampling interval while recording (each sample is a USB round trip)
+const TRIGGER_POLL_INTERVAL: Duration = Duration::from_millis(2);
 
 #[derive(Parser, Debug)]
 #[command(name = "collector")]
 #[command(about = "Collect ADXL355 sensor data to HDF5 file")]
 struct Args {
-    /// Output HDF5 file path
-    #[arg(short, long, default_value = "sensor_data.h5")]
-    output: PathBuf,
+    /// Settings file with named profiles; command-line options override it
+    #[arg(short, long)]
+    config: Option<PathBuf>,
 
-    /// Collection mode: "polling" or "fifo"
-    #[arg(short, long, default_value = "polling")]
-    mode: String,
+    /// Profile of the settings file to use, e.g. "impact" for [profile.impact]
+    #[arg(short, long)]
+    profile: Option<String>,
 
-    /// Target sample rate in Hz (polling: 1-500, fifo: uses ODR presets)
-    #[arg(short, long, default_value = "100")]
-    rate: u32,
+    /// FT232H channel index (default: 0)
+    #[arg(long)]
+    device: Option<u32>,
+
+    /// Output HDF5 file path (default: sensor_data.h5)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Collection mode: "polling" (default) or "fifo"
+    #[arg(short, long, value_parser = mode_arg)]
+    mode: Option<CollectionMode>,
+
+    /// Target sample rate in Hz (polling: 1-500, fifo: uses ODR presets; default: 100)
+    #[arg(short, long)]
+    rate: Option<u32>,
 
     /// Duration in seconds (optional, runs until Ctrl+C if omitted)
     #[arg(short, long)]
     duration: Option<u64>,
+
+    /// Sync source: "internal" (default), "ext-sync", "ext-sync-interp", "ext-clk", "ext-clk-sync"
+    /// or "ext-clk-sync-interp"
+    #[arg(long, value_parser = sync_arg)]
+    sync: Option<SyncMode>,
+
+    /// Run the electrostatic self-test before acquisition (result stored in metadata)
+    #[arg(long)]
+    self_test: bool,
+
+    /// Temperature calibration file (intercept_lsb / slope_lsb_per_c)
+    #[arg(long)]
+    temp_cal: Option<PathBuf>,
+
+    /// Part fitted: "adxl355" or "adxl357"; required, as both report the same PARTID
+    #[arg(long, value_parser = variant_arg)]
+    variant: Option<DeviceVariant>,
+
+    /// Measurement range: "2g", "4g", "8g" (ADXL355) or "10g", "20g", "40g" (ADXL357); default: power-on range
+    #[arg(long, value_parser = range_arg)]
+    range: Option<Range>,
+
+    /// FIFO mode only: read the FIFO with one raw MPSSE transfer per batch
+    #[arg(long)]
+    raw: bool,
+
+    /// Sensors sharing the bus, by chip select: e.g. "dbus3,dbus4,dbus5" or "3,acbus0".
+    /// Several sensors need --mode fifo and are written to one group each
+    #[arg(long, value_delimiter = ',', value_parser = cs_arg)]
+    cs: Option<Vec<CsLine>>,
+
+    /// Retries of a failed transfer before the USB channel is reopened (default: 3)
+    #[arg(long)]
+    retries: Option<u32>,
+
+    /// Seconds to keep trying to reconnect a lost sensor (0 = stop on first disconnect; default: 60).
+    /// Sensors on a shared bus (--cs) are only retried, never reconnected
+    #[arg(long)]
+    reconnect_timeout: Option<u64>,
+
+    /// Record every SPI transfer to this file (see trace-replay); single sensor only
+    #[arg(long)]
+    trace: Option<PathBuf>,
+
+    /// ACBUS pin (e.g. c3) gating the recording: start when it goes active, stop when it drops (one-shot)
+    #[arg(long, value_parser = pin_arg)]
+    trigger_in: Option<u8>,
+
+    /// Treat the trigger line as active low
+    #[arg(long)]
+    trigger_active_low: bool,
+
+    /// ACBUS pin toggled when recording starts. Neither pin may be an ACBUS chip select
+    #[arg(long, value_parser = pin_arg)]
+    mark_out: Option<u8>,
+
+    /// Start a new file "hourly", "daily" or every N s/m/h (e.g. 6h); see the .manifest
+    #[arg(long, value_parser = interval_arg)]
+    rotate: Option<RotationInterval>,
+
+    /// Start a new file once the current one exceeds this many MB
+    #[arg(long)]
+    rotate_size: Option<u64>,
+
+    /// Also take event lines from TCP clients on this localhost port (stdin is always read)
+    #[arg(long)]
+    event_port: Option<u16>,
+
+    /// Sample column layout, e.g. "deflate=6,shuffle,chunk=4096,ticks" (default: deflate=4,chunk=1024)
+    #[arg(long, value_parser = storage_arg)]
+    storage: Option<StorageOptions>,
+
+    /// Only keep samples around crossings of this level in g, one file per capture.
+    /// FIFO mode, single sensor
+    #[arg(long, allow_hyphen_values = true)]
+    trigger_level: Option<f64>,
+
+    /// Signal compared with the trigger level: "x", "y", "z" or "magnitude" (default)
+    #[arg(long, value_parser = source_arg)]
+    trigger_source: Option<TriggerSource>,
+
+    /// Crossing that fires: "rising" (default), "falling" or "both"
+    #[arg(long, value_parser = edge_arg)]
+    trigger_edge: Option<Edge>,
+
+    /// Distance in g the signal must move back before the trigger re-arms (default: 10% of the level)
+    #[arg(long)]
+    trigger_hysteresis: Option<f64>,
+
+    /// Filter each axis before triggering, in Hz: "5-200", "5-" (high-pass) or "-200" (low-pass)
+    #[arg(long, value_parser = band_arg)]
+    trigger_band: Option<Band>,
+
+    /// Seconds of history written before each trigger (default: 1)
+    #[arg(long)]
+    pre_trigger: Option<f64>,
+
+    /// Seconds written after each trigger (default: 4)
+    #[arg(long)]
+    post_trigger: Option<f64>,
+}
+
+fn mode_arg(text: &str) -> std::result::Result<CollectionMode, String> {
+    CollectionMode::from_name(text).ok_or_else(|| format!("'{}' is not polling or fifo", text))
+}
+
+fn sync_arg(text: &str) -> std::result::Result<SyncMode, String> {
+    SyncMode::from_name(text)
+        .ok_or_else(|| format!("'{}' is not internal, ext-sync, ext-sync-interp, ext-clk, ext-clk-sync or ext-clk-sync-interp", text))
+}
+
+fn variant_arg(text: &str) -> std::result::Result<DeviceVariant, String> {
+    DeviceVariant::from_name(text).ok_or_else(|| format!("'{}' is not adxl355 or adxl357", text))
+}
+
+fn range_arg(text: &str) -> std::result::Result<Range, String> {
+    Range::from_label(text).ok_or_else(|| format!("'{}' is not 2g, 4g, 8g, 10g, 20g or 40g", text))
+}
+
+fn cs_arg(text: &str) -> std::result::Result<CsLine, String> {
+    CsLine::parse(text).ok_or_else(|| format!("'{}' is not a chip select (dbus3-dbus7 or acbus0-acbus7)", text))
+}
+
+fn interval_arg(text: &str) -> std::result::Result<RotationInterval, String> {
+    RotationInterval::parse(text).ok_or_else(|| format!("'{}' is not hourly, daily or a time like 900s, 30m, 6h", text))
+}
+
+fn storage_arg(text: &str) -> std::result::Result<StorageOptions, String> {
+    StorageOptions::parse(text).map_err(|e| e.to_string())
+}
+
+fn pin_arg(text: &str) -> std::result::Result<u8, String> {
+    parse_pin(text).ok_or_else(|| format!("'{}' is not an ACBUS pin (0-7, c0-c7 or acbus0-acbus7)", text))
+}
+
+fn source_arg(text: &str) -> std::result::Result<TriggerSource, String> {
+    TriggerSource::from_name(text).ok_or_else(|| format!("'{}' is not x, y, z or magnitude", text))
+}
+
+fn edge_arg(text: &str) -> std::result::Result<Edge, String> {
+    Edge::from_name(text).ok_or_else(|| format!("'{}' is not rising, falling or both", text))
+}
+
+fn band_arg(text: &str) -> std::result::Result<Band, String> {
+    Band::parse(text).ok_or_else(|| format!("'{}' is not a band like 5-200, 5- or -200 (Hz)", text))
+}
+
+impl Args {
+    /// The options given on the command line, as the top settings layer
+    fn overrides(&self) -> Profile {
+        let trigger = TriggerProfile {
+            level_g: self.trigger_level,
+            source: self.trigger_source,
+            edge: self.trigger_edge,
+            hysteresis_g: self.trigger_hysteresis,
+            band: self.trigger_band,
+            pre_secs: self.pre_trigger,
+            post_secs: self.post_trigger,
+        };
+        Profile {
+            device: self.device,
+            output: self.output.clone(),
+            mode: self.mode,
+            rate: self.rate,
+            duration: self.duration,
+            sync: self.sync,
+            self_test: self.self_test.then_some(true),
+            temp_cal: self.temp_cal.clone(),
+            variant: self.variant,
+            range: self.range,
+            raw: self.raw.then_some(true),
+            cs: self.cs.clone(),
+            retries: self.retries,
+            reconnect_timeout: self.reconnect_timeout,
+            trace: self.trace.clone(),
+            trigger_in: self.trigger_in,
+            trigger_active_low: self.trigger_active_low.then_some(true),
+            mark_out: self.mark_out,
+            rotate: self.rotate,
+            rotate_size: self.rotate_size,
+            event_port: self.event_port,
+            storage: self.storage,
+            trigger: (trigger != TriggerProfile::default()).then_some(trigger),
+        }
+    }
 }
 
 /// Map a rate to the nearest ODR preset
@@ -50,45 +268,191 @@ fn rate_to_odr(rate: u32) -> OutputDataRate {
 
 fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
+    let config = match CollectorConfig::resolve(args.config.as_deref(), args.profile.as_deref(), &args.overrides()) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    // Written into every output file, so the run can be repeated with --config
+    let config_toml = config.to_toml()?;
+    let sync_mode = config.sync;
+    let trigger = config.trigger;
 
-    if args.mode != "polling" && args.mode != "fifo" {
-        eprintln!("Error: mode must be 'polling' or 'fifo'");
-        std::process::exit(1);
-    }
+    let temp_cal = match &config.temp_cal {
+        Some(path) => TemperatureCalibration::load(path)?,
+        None => TemperatureCalibration::NOMINAL,
+    };
 
-    let odr = rate_to_odr(args.rate);
+    let odr = rate_to_odr(config.rate);
     let actual_rate = odr.as_hz();
+    if let Some(Err(e)) = trigger.map(|trigger| trigger.validate(actual_rate)) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 
     println!("ADXL355 Data Collector");
     println!("======================");
-    println!("Mode: {}", args.mode);
-    println!("Target rate: {} Hz (actual ODR: {} Hz)", args.rate, actual_rate);
-    println!("Output file: {}", args.output.display());
-    if let Some(duration) = args.duration {
+    if let Some(path) = &args.config {
+        match &args.profile {
+            Some(profile) => println!("Config: {} (profile {})", path.display(), profile),
+            None => println!("Config: {}", path.display()),
+        }
+    }
+    println!("Mode: {}", config.mode.as_str());
+    println!("Target rate: {} Hz (actual ODR: {} Hz)", config.rate, actual_rate);
+    println!("Sync: {}", sync_mode.as_str());
+    println!("Output file: {}", config.output.display());
+    if let Some(duration) = config.duration {
         println!("Duration: {} seconds", duration);
     } else {
         println!("Duration: continuous (Ctrl+C to stop)");
     }
+    if let Some(pin) = config.trigger_in {
+        println!("Trigger: ACBUS{} (active {})", pin, if config.trigger_active_low { "low" } else { "high" });
+    }
+    if let Some(pin) = config.mark_out {
+        println!("Marker: ACBUS{}", pin);
+    }
+    if let Some(port) = config.event_port {
+        println!("Events: stdin and 127.0.0.1:{}", port);
+    }
+    if let Some(trigger) = &trigger {
+        println!("Captures: {} ({}, ...)", trigger, segment_path(&config.output, 1).display());
+    }
     println!();
 
     println!("Initializing sensor...");
-    let mut sensor = Adxl355::new(0)?;
-    sensor.set_odr(odr)?;
-    println!("Sensor initialized!\n");
+    let mut sensors = if let Some(path) = &config.trace {
+        println!("Tracing SPI transfers to {}", path.display());
+        vec![Adxl355::with_trace(config.device, SpiConfig::default(), Tracer::file(path)?)?]
+    } else if config.cs.is_empty() {
+        vec![Adxl355::new(config.device)?]
+    } else {
+        let bus = SpiBus::open(config.device, SpiConfig::default(), &config.cs)?;
+        config.cs.iter()
+            .map(|&line| bus.device(line))
+            .collect::<std::result::Result<Vec<_>, _>>()?
+    };
+    let multi = sensors.len() > 1;
+    // Per-device metadata keys get the CS label appended on a shared bus
+    let labels: Vec<String> = sensors.iter()
+        .map(|sensor| sensor.cs_line().map(|line| line.label()).unwrap_or_default())
+        .collect();
+    let key = |base: &str, index: usize| {
+        if multi { format!("{}_{}", base, labels[index]) } else { base.to_string() }
+    };
 
-    println!("Creating HDF5 file...");
-    let range_str = match sensor.get_range() {
-        ft232_adxl355_spi::Range::G2 => "2g",
-        ft232_adxl355_spi::Range::G4 => "4g",
-        ft232_adxl355_spi::Range::G8 => "8g",
+    for (index, sensor) in sensors.iter_mut().enumerate() {
+        sensor.set_variant(config.variant);
+        if let Some(range) = config.range {
+            sensor.set_range(range)?;
+        }
+        sensor.set_odr(odr)?;
+        sensor.set_temperature_calibration(temp_cal);
+        if multi {
+            println!("Sensor {} initialized! ({:.1} C)", labels[index], sensor.read_temperature_c()?);
+        } else {
+            println!("Sensor initialized! ({:.1} C)", sensor.read_temperature_c()?);
+        }
+    }
+    println!();
+
+    // Self-test runs on the internal clock, before any external sync is applied
+    let mut self_tests = Vec::new();
+    if config.self_test {
+        for (index, sensor) in sensors.iter_mut().enumerate() {
+            if multi {
+                println!("Running self-test on {} (keep the sensor still)...", labels[index]);
+            } else {
+                println!("Running self-test (keep the sensor still)...");
+            }
+            let report = sensor.self_test(50)?;
+            let axes = ["X", "Y", "Z"];
+            for axis in 0..3 {
+                let (min, max) = report.limits_g[axis];
+                println!("  {}: delta {:+.3} g (limits {:.2}..{:.2} g) {}",
+                    axes[axis], report.delta_g[axis], min, max,
+                    if report.axis_saturated[axis] {
+                        "FAIL (saturated)"
+                    } else if report.axis_passed[axis] {
+                        "PASS"
+                    } else {
+                        "FAIL"
+                    });
+            }
+            if report.passed() {
+                println!("Self-test passed\n");
+            } else {
+                eprintln!("Warning: self-test FAILED, continuing with acquisition\n");
+            }
+            self_tests.push((index, report));
+        }
+    }
+
+    if sync_mode != SyncMode::Internal {
+        for sensor in sensors.iter_mut() {
+            sensor.set_sync_mode(sync_mode)?;
+        }
+    }
+
+    let range = sensors[0].get_range();
+    let range_str = range.label();
+    let device_infos: Vec<Option<DeviceInfo>> = sensors.iter().map(Adxl355::device_info).collect();
+    for (index, info) in device_infos.iter().enumerate() {
+        if let Some(info) = info {
+            if multi {
+                println!("Device {}: {} rev {} ({})", labels[index], info.variant.name(), info.revid, range_str);
+            } else {
+                println!("Device: {} rev {} ({})", info.variant.name(), info.revid, range_str);
+            }
+        }
+    }
+    let serial = sensors[0].serial_number();
+    // Run metadata, the same in every file of the run
+    let describe = |writer: &mut Hdf5Writer| -> ft232_adxl355_spi::Result<()> {
+        for (index, info) in device_infos.iter().enumerate() {
+            if let Some(info) = info {
+                if index == 0 {
+                    writer.write_device_info(info)?;
+                }
+                if multi {
+                    writer.write_metadata_f64(&key("revision", index), info.revid as f64)?;
+                }
+            }
+        }
+        if let Some(serial) = &serial {
+            writer.write_device_serial(serial)?;
+        }
+        writer.write_metadata_str("collector_config", &config_toml)?;
+        if let Some(profile) = &args.profile {
+            writer.write_metadata_str("collector_profile", profile)?;
+        }
+        writer.write_metadata_str("sync_mode", sync_mode.as_str())?;
+        if config.mode == CollectionMode::Fifo {
+            writer.write_metadata_str("fifo_transport", if config.raw { "raw-mpsse" } else { "libmpsse" })?;
+        }
+        writer.write_metadata_str("temp_cal_source", &match &config.temp_cal {
+            Some(path) => path.display().to_string(),
+            None => "nominal".to_string(),
+        })?;
+        writer.write_temperature_calibration(&temp_cal)?;
+        for (index, report) in &self_tests {
+            writer.write_metadata_str(&key("self_test", *index), if report.passed() { "pass" } else { "fail" })?;
+            for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+                writer.write_metadata_f64(&key(&format!("self_test_delta_{}_g", name), *index), report.delta_g[axis] as f64)?;
+            }
+        }
+        if let Some(pin) = config.trigger_in {
+            let polarity = if config.trigger_active_low { "low" } else { "high" };
+            writer.write_metadata_str("trigger_in", &format!("ACBUS{} active {}", pin, polarity))?;
+        }
+        if let Some(pin) = config.mark_out {
+            writer.write_metadata_str("mark_out", &format!("ACBUS{}", pin))?;
+        }
+        Ok(())
     };
-    let mut writer = Hdf5Writer::create(
-        &args.output,
-        &args.mode,
-        actual_rate,
-        range_str,
-    )?;
-    println!("HDF5 file created!\n");
 
     // Setup Ctrl+C handler
     let running = Arc::new(AtomicBool::new(true));
@@ -98,29 +462,97 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         r.store(false, Ordering::SeqCst);
     })?;
 
-    let collection_start = std::time::Instant::now();
-    let end_time = args.duration.map(|d| collection_start + std::time::Duration::from_secs(d));
+    let policy = config.retry_policy();
+    if let Some(trigger) = &trigger {
+        let mut captures = Captures {
+            capture: Capture::new(trigger, actual_rate, range)?,
+            output: config.output.clone(),
+            rate: actual_rate,
+            range,
+            storage: config.storage,
+            start_time: String::new(),
+            describe: &describe,
+            writer: None,
+            samples: 0,
+        };
+        let mut control = RunControl::new(&config, &sensors[0].gpio(), running)?;
+        println!("Waiting for the trigger level...");
+        println!("Press Ctrl+C to stop; lines typed go to the next capture as events (\"kind: label\")\n");
+
+        let result = collect_triggered(&mut sensors[0], &mut captures, odr, config.raw, &policy, &mut control);
+        control.finish();
+        // A capture cut short by the end of the run is kept
+        let closed = captures.close();
+        result?;
+        closed?;
+        println!("\nCollection complete!");
+        println!("Captures: {} ({} samples)", captures.capture.count(), captures.samples);
+        println!("Elapsed time: {:.2} seconds", control.elapsed_secs());
+        return Ok(());
+    }
+
+    println!("Creating HDF5 file...");
+    let rotation = config.rotation();
+    let devices: &[String] = if multi { &labels } else { &[] };
+    let mut writer = if rotation.is_enabled() {
+        Hdf5Writer::create_session(&config.output, config.mode.as_str(), actual_rate, range_str, devices, rotation, &config.storage)?
+    } else {
+        Hdf5Writer::create_with(&config.output, config.mode.as_str(), actual_rate, range_str, devices, &config.storage)?
+    };
+    if let Some(manifest) = writer.manifest_path() {
+        println!("Rotating files; session manifest: {}", manifest.display());
+    }
+    describe(&mut writer)?;
+    // If we die mid-run, `recover` restores the unflushed tail from the journal
+    writer.enable_journal()?;
+    // No attributes can be added after this, but readers may now open the file
+    writer.start_swmr()?;
+    println!("HDF5 file created!\n");
+
+    // Sensors on a bus share one channel, so the first one's GPIO covers all
+    let mut control = RunControl::new(&config, &sensors[0].gpio(), running)?;
 
     println!("Starting data collection...");
-    println!("Press Ctrl+C to stop\n");
+    println!("Press Ctrl+C to stop; type a line and Enter to record an event (\"kind: label\")\n");
 
-    let result = if args.mode == "fifo" {
-        collect_fifo(&mut sensor, &mut writer, odr, running.clone(), end_time)
+    let result = if multi {
+        collect_fifo_multi(&mut sensors, &mut writer, odr, config.raw, &policy, &mut control)
+    } else if config.mode == CollectionMode::Fifo {
+        collect_fifo(&mut sensors[0], &mut writer, odr, config.raw, &policy, &mut control)
     } else {
-        collect_polling(&mut sensor, &mut writer, args.rate, running.clone(), end_time)
+        collect_polling(&mut sensors[0], &mut writer, config.rate, &policy, &mut control)
     };
+    control.finish();
 
     match result {
         Ok(()) => {
-            let elapsed = collection_start.elapsed().as_secs_f64();
+            let elapsed = control.elapsed_secs();
             let samples = writer.sample_count();
-            let actual_rate = samples as f64 / elapsed;
 
             println!("\nCollection complete!");
             println!("Total samples: {}", samples);
             println!("Elapsed time: {:.2} seconds", elapsed);
-            println!("Actual sample rate: {:.1} Hz", actual_rate);
-            println!("File: {}", args.output.display());
+            // Zero when --trigger-in never fired
+            if elapsed > 0.0 {
+                println!("Actual sample rate: {:.1} Hz", samples as f64 / elapsed);
+            }
+            if multi {
+                for (index, label) in labels.iter().enumerate() {
+                    println!("  {}: {} samples", label, writer.device_sample_count(index));
+                }
+            }
+            if writer.discontinuity_count() > 0 {
+                println!("Gaps recorded: {}", writer.discontinuity_count());
+            }
+            if writer.event_count() > 0 {
+                println!("Events recorded: {}", writer.event_count());
+            }
+            let manifest = writer.manifest_path();
+            writer.close()?;
+            match manifest {
+                Some(manifest) => println!("Session: {}", manifest.display()),
+                None => println!("File: {}", config.output.display()),
+            }
         }
         Err(e) => {
             eprintln!("\nError during collection: {}", e);
@@ -128,6 +560,7 @@ fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
             if let Err(flush_err) = writer.flush() {
                 eprintln!("Failed to flush: {}", flush_err);
             }
+            eprintln!("The file is not closed; `recover --input {}` repairs it", writer.path().display());
             return Err(e);
         }
     }
@@ -139,25 +572,41 @@ fn collect_polling(
     sensor: &mut Adxl355,
     writer: &mut Hdf5Writer,
     rate: u32,
-    running: Arc<AtomicBool>,
-    end_time: Option<std::time::Instant>,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    if !control.start()? {
+        return Ok(());
+    }
+
     let timer = TimeKeeper::new();
     let mut sample_buffer = Vec::with_capacity(100);
     let mut last_flush = std::time::Instant::now();
     let mut last_progress = std::time::Instant::now();
     let mut progress_count: u64 = 0;
 
-    sensor.stream(rate, |data| {
-        if !running.load(Ordering::SeqCst) {
+    sensor.stream_resilient(rate, policy, |event| {
+        if !control.keep_going() {
+            return StreamControl::Break;
+        }
+        if let Err(e) = control.record_events(writer, &timer) {
+            eprintln!("Write error: {}", e);
             return StreamControl::Break;
         }
 
-        if let Some(end) = end_time {
-            if std::time::Instant::now() >= end {
-                return StreamControl::Break;
+        let data = match event {
+            StreamEvent::Data(data) => data,
+            StreamEvent::Reconnected(outage) => {
+                eprintln!();
+                return match record_outage(writer, &timer, outage) {
+                    Ok(()) => StreamControl::Continue,
+                    Err(e) => {
+                        eprintln!("Write error: {}", e);
+                        StreamControl::Break
+                    }
+                };
             }
-        }
+        };
 
         let sample = TimestampedSample {
             timestamp: timer.elapsed_secs(),
@@ -208,11 +657,17 @@ fn collect_fifo(
     sensor: &mut Adxl355,
     writer: &mut Hdf5Writer,
     odr: OutputDataRate,
-    running: Arc<AtomicBool>,
-    end_time: Option<std::time::Instant>,
+    raw: bool,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    // Enable the FIFO only once triggered, it would overflow while waiting
+    if !control.start()? {
+        return Ok(());
+    }
+
     sensor.enable_fifo(odr)?;
-    println!("FIFO mode enabled (ODR: {} Hz)", odr.as_hz());
+    println!("FIFO mode enabled (ODR: {} Hz{})", odr.as_hz(), if raw { ", raw MPSSE" } else { "" });
 
     let timer = TimeKeeper::new();
     let mut last_flush = std::time::Instant::now();
@@ -220,33 +675,35 @@ fn collect_fifo(
     let sample_rate = odr.as_hz();
     let dt = 1.0 / sample_rate;
     let mut total_samples: u64 = 0;
-    let mut overflow_count: u64 = 0;
     let mut write_buffer: Vec<TimestampedSample> = Vec::with_capacity(256);
-
-    // Wait long enough to accumulate ~10 samples, but stay well under FIFO capacity (32 samples).
-    // Minimum 1ms to avoid busy-spinning.
-    let poll_sleep = std::time::Duration::from_micros(
-        ((10.0 / sample_rate) * 1_000_000.0) as u64
-    ).max(std::time::Duration::from_millis(1));
+    let mut scheduler = PollScheduler::new(32, sample_rate);
 
     loop {
-        if !running.load(Ordering::SeqCst) {
+        if !control.keep_going() {
             break;
         }
-        if let Some(end) = end_time {
-            if std::time::Instant::now() >= end {
-                break;
-            }
-        }
+        control.record_events(writer, &timer)?;
 
-        let result = sensor.read_fifo_batch_checked()?;
+        scheduler.wait();
+        let read_start = std::time::Instant::now();
+        let (result, outage) = sensor.with_recovery(policy, |s| {
+            if raw { s.read_fifo_batch_raw() } else { s.read_fifo_batch_checked() }
+        })?;
+        if let Some(outage) = outage {
+            // The FIFO was re-enabled on reconnect; start the schedule over
+            eprintln!();
+            record_outage(writer, &timer, &outage)?;
+            scheduler.resync();
+        } else {
+            // A read that spanned an outage says nothing about USB latency
+            scheduler.record_with_carry(result.samples.len(), result.carried, result.overflow_detected, read_start.elapsed());
+        }
         if result.overflow_detected {
-            overflow_count += 1;
+            writer.add_event(&Event::new(timer.elapsed_secs(), "overflow", "FIFO overflow, samples lost"))?;
         }
         let batch = result.samples;
 
         if batch.is_empty() {
-            std::thread::sleep(poll_sleep);
             continue;
         }
 
@@ -291,12 +748,478 @@ fn collect_fifo(
     }
 
     eprintln!();
-    if overflow_count > 0 {
-        eprintln!("Warning: {} FIFO overflow(s) detected — some samples were lost", overflow_count);
+    print_poll_stats(scheduler.stats());
+    if scheduler.stats().overflows > 0 {
+        eprintln!("Warning: {} FIFO overflow(s) detected — some samples were lost", scheduler.stats().overflows);
+    }
+
+    sensor.disable_fifo()?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// FIFO collection that keeps only the captures around trigger crossings
+fn collect_triggered(
+    sensor: &mut Adxl355,
+    captures: &mut Captures,
+    odr: OutputDataRate,
+    raw: bool,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    if !control.start()? {
+        return Ok(());
+    }
+
+    sensor.enable_fifo(odr)?;
+    println!("FIFO mode enabled (ODR: {} Hz{}), trigger armed", odr.as_hz(), if raw { ", raw MPSSE" } else { "" });
+
+    let timer = TimeKeeper::new();
+    captures.start_time = chrono::Local::now().to_rfc3339();
+    let sample_rate = odr.as_hz();
+    let dt = 1.0 / sample_rate;
+    let mut scheduler = PollScheduler::new(32, sample_rate);
+
+    loop {
+        if !control.keep_going() {
+            break;
+        }
+        // Lines typed between captures wait for the next one
+        if let Some(writer) = &mut captures.writer {
+            control.record_events(writer, &timer)?;
+        }
+
+        scheduler.wait();
+        let read_start = std::time::Instant::now();
+        let (result, outage) = sensor.with_recovery(policy, |s| {
+            if raw { s.read_fifo_batch_raw() } else { s.read_fifo_batch_checked() }
+        })?;
+        if let Some(outage) = outage {
+            // History from before the gap must not end up in a capture
+            eprintln!();
+            captures.capture.discontinuity();
+            match &mut captures.writer {
+                Some(writer) => record_outage(writer, &timer, &outage)?,
+                None => eprintln!("Warning: sensor reconnected after {:.2} s ({} attempt(s)): {}",
+                    outage.duration().as_secs_f64(), outage.reconnects, outage.cause),
+            }
+            scheduler.resync();
+        } else {
+            scheduler.record_with_carry(result.samples.len(), result.carried, result.overflow_detected, read_start.elapsed());
+        }
+        if result.overflow_detected {
+            if let Some(writer) = &mut captures.writer {
+                writer.add_event(&Event::new(timer.elapsed_secs(), "overflow", "FIFO overflow, samples lost"))?;
+            }
+        }
+        let batch = result.samples;
+
+        if batch.is_empty() {
+            continue;
+        }
+
+        let batch_end_time = timer.elapsed_secs();
+        let batch_size = batch.len();
+        let samples: Vec<TimestampedSample> = batch.iter()
+            .enumerate()
+            .map(|(i, data)| TimestampedSample {
+                timestamp: (batch_end_time - (batch_size - 1 - i) as f64 * dt).max(0.0),
+                data: *data,
+            })
+            .collect();
+
+        if let Err(e) = captures.push(&samples) {
+            eprintln!("Write error: {}", e);
+            break;
+        }
+    }
+
+    print_poll_stats(scheduler.stats());
+    if scheduler.stats().overflows > 0 {
+        eprintln!("Warning: {} FIFO overflow(s) detected — captures may miss samples", scheduler.stats().overflows);
     }
 
     sensor.disable_fifo()?;
+
+    Ok(())
+}
+
+/// Files of a triggered run, `<output>_0001.h5` onwards, one per capture
+///
+/// All share the `start_time` of the run, so their timestamps are on one
+/// time base like the segments of a rotating session.
+struct Captures<'a> {
+    capture: Capture,
+    output: PathBuf,
+    rate: f64,
+    range: Range,
+    storage: StorageOptions,
+    /// RFC 3339 time of t = 0
+    start_time: String,
+    describe: &'a dyn Fn(&mut Hdf5Writer) -> ft232_adxl355_spi::Result<()>,
+    /// File of the running capture
+    writer: Option<Hdf5Writer>,
+    /// Samples in closed captures
+    samples: usize,
+}
+
+impl Captures<'_> {
+    /// Run a batch through the trigger, opening, filling and closing files
+    fn push(&mut self, batch: &[TimestampedSample]) -> ft232_adxl355_spi::Result<()> {
+        for event in self.capture.push(batch) {
+            match event {
+                CaptureEvent::Start { time, value, history } => {
+                    let path = segment_path(&self.output, self.capture.count());
+                    eprintln!("  Trigger at {:.3}s ({:.3} g), capturing to {}", time, value, path.display());
+                    let mut writer = self.create(&path, time, value)?;
+                    writer.append_batch(&history)?;
+                    self.writer = Some(writer);
+                }
+                CaptureEvent::Samples(samples) => {
+                    if let Some(writer) = &mut self.writer {
+                        writer.append_batch(&samples)?;
+                    }
+                }
+                CaptureEvent::End => self.close()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn create(&self, path: &Path, time: f64, value: f64) -> ft232_adxl355_spi::Result<Hdf5Writer> {
+        let mut writer = Hdf5Writer::create_with(path, "fifo", self.rate, self.range.label(), &[], &self.storage)?;
+        writer.set_start_time(&self.start_time)?;
+        (self.describe)(&mut writer)?;
+        writer.write_metadata_str("trigger", &self.capture.config().to_string())?;
+        writer.write_metadata_f64("trigger_time", time)?;
+        writer.write_metadata_f64("trigger_value_g", value)?;
+        writer.enable_journal()?;
+        writer.start_swmr()?;
+        writer.add_event(&Event::new(time, "trigger", &format!("{:.3} g", value)))?;
+        Ok(writer)
+    }
+
+    /// Finish the running capture, if any
+    fn close(&mut self) -> ft232_adxl355_spi::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            let samples = writer.sample_count();
+            let path = writer.path().to_path_buf();
+            writer.close()?;
+            self.samples += samples;
+            eprintln!("  Capture complete: {} samples in {}", samples, path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Read the FIFOs of several sensors on one bus in turn
+///
+/// Each sensor gets its own write buffer and HDF5 group; timestamps are
+/// taken per batch, so the devices share one time base.
+fn collect_fifo_multi(
+    sensors: &mut [Adxl355],
+    writer: &mut Hdf5Writer,
+    odr: OutputDataRate,
+    raw: bool,
+    policy: &RetryPolicy,
+    control: &mut RunControl,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    if !control.start()? {
+        return Ok(());
+    }
+
+    let labels: Vec<String> = sensors.iter()
+        .map(|sensor| sensor.cs_line().map(|line| line.label()).unwrap_or_default())
+        .collect();
+    for sensor in sensors.iter_mut() {
+        sensor.enable_fifo(odr)?;
+    }
+    println!("FIFO mode enabled on {} sensors (ODR: {} Hz{})",
+        sensors.len(), odr.as_hz(), if raw { ", raw MPSSE" } else { "" });
+
+    let timer = TimeKeeper::new();
+    let mut last_flush = std::time::Instant::now();
+    let mut last_progress = std::time::Instant::now();
+    let sample_rate = odr.as_hz();
+    let dt = 1.0 / sample_rate;
+    let mut total_samples: u64 = 0;
+    let mut overflow_counts = vec![0u64; sensors.len()];
+    let mut write_buffers: Vec<Vec<TimestampedSample>> = (0..sensors.len())
+        .map(|_| Vec::with_capacity(256))
+        .collect();
+
+    // A shared bus cannot be reopened from one device, so only retry transfers
+    let policy = RetryPolicy { reconnect_timeout: Some(std::time::Duration::ZERO), ..*policy };
+
+    // One pass reads every sensor; schedule on the fullest FIFO so none overflows
+    let mut scheduler = PollScheduler::new(32, sample_rate);
+
+    'acquire: loop {
+        if !control.keep_going() {
+            break;
+        }
+        control.record_events(writer, &timer)?;
+
+        scheduler.wait();
+        let pass_start = std::time::Instant::now();
+        let mut fullest = 0;
+        let mut any_overflow = false;
+
+        for (index, sensor) in sensors.iter_mut().enumerate() {
+            let (result, _) = sensor.with_recovery(&policy, |s| {
+                if raw { s.read_fifo_batch_raw() } else { s.read_fifo_batch_checked() }
+            })?;
+            if result.overflow_detected {
+                overflow_counts[index] += 1;
+                any_overflow = true;
+                let label = format!("FIFO overflow on {}, samples lost", labels[index]);
+                writer.add_event(&Event::new(timer.elapsed_secs(), "overflow", &label))?;
+            }
+            fullest = fullest.max(result.samples.len() - result.carried);
+            let batch = result.samples;
+            if batch.is_empty() {
+                continue;
+            }
+
+            let batch_end_time = timer.elapsed_secs();
+            let batch_size = batch.len();
+            let buffer = &mut write_buffers[index];
+
+            for (i, data) in batch.iter().enumerate() {
+                let timestamp = (batch_end_time - (batch_size - 1 - i) as f64 * dt).max(0.0);
+                buffer.push(TimestampedSample { timestamp, data: *data });
+            }
+            total_samples += batch_size as u64;
+
+            if buffer.len() >= 200 {
+                if let Err(e) = writer.append_device_batch(index, buffer) {
+                    eprintln!("Write error ({}): {}", labels[index], e);
+                    break 'acquire;
+                }
+                buffer.clear();
+            }
+        }
+        scheduler.record(fullest, any_overflow, pass_start.elapsed());
+
+        if last_flush.elapsed() >= std::time::Duration::from_secs(10) {
+            if let Err(e) = writer.flush() {
+                eprintln!("Flush error: {}", e);
+            }
+            last_flush = std::time::Instant::now();
+        }
+
+        if last_progress.elapsed() >= std::time::Duration::from_secs(2) {
+            let elapsed = timer.elapsed_secs();
+            let rate = total_samples as f64 / elapsed / sensors.len() as f64;
+            eprint!("\r  Collected {} samples in {:.1}s ({:.0} Hz per sensor)    ",
+                total_samples, elapsed, rate);
+            last_progress = std::time::Instant::now();
+        }
+    }
+
+    // Flush remaining buffered samples
+    for (index, buffer) in write_buffers.iter().enumerate() {
+        writer.append_device_batch(index, buffer)?;
+    }
+
+    eprintln!();
+    print_poll_stats(scheduler.stats());
+    for (index, &count) in overflow_counts.iter().enumerate() {
+        if count > 0 {
+            eprintln!("Warning: {} FIFO overflow(s) on {} — some samples were lost", count, labels[index]);
+        }
+    }
+
+    for sensor in sensors.iter_mut() {
+        sensor.disable_fifo()?;
+    }
     writer.flush()?;
 
     Ok(())
 }
+
+/// Store the gap left by a reconnect, and a `reconnect` event where the
+/// samples resume
+fn record_outage(writer: &mut Hdf5Writer, timer: &TimeKeeper, outage: &Outage) -> ft232_adxl355_spi::Result<()> {
+    let end = timer.elapsed_secs();
+    let start = (end - outage.duration().as_secs_f64()).max(0.0);
+    eprintln!("Warning: sensor reconnected after {:.2} s ({} attempt(s)): {}",
+        outage.duration().as_secs_f64(), outage.reconnects, outage.cause);
+    writer.write_discontinuity(start, end, &outage.cause)?;
+    writer.add_event(&Event::new(end, "reconnect", &outage.cause))
+}
+
+/// Forward event lines from stdin and, with `--event-port`, from every
+/// client of a localhost TCP listener, stamped with their arrival time
+fn spawn_event_sources(port: Option<u16>) -> std::io::Result<Receiver<(Instant, String)>> {
+    let (tx, rx) = mpsc::channel();
+    if let Some(port) = port {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let tx = tx.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                std::thread::spawn(move || forward_lines(std::io::BufReader::new(stream), tx));
+            }
+        });
+    }
+    std::thread::spawn(move || forward_lines(std::io::stdin().lock(), tx));
+    Ok(rx)
+}
+
+fn forward_lines(input: impl BufRead, tx: Sender<(Instant, String)>) {
+    for line in input.lines().map_while(Result::ok) {
+        if tx.send((Instant::now(), line)).is_err() {
+            break;
+        }
+    }
+}
+
+fn print_poll_stats(stats: &PollStats) {
+    println!("FIFO reads: {} (mean fill {:.0}%, max {:.0}%), interval {:.1} ms, read {:.2} ms, loop busy {:.1}%",
+        stats.polls, stats.mean_fill * 100.0, stats.max_fill * 100.0,
+        stats.interval.as_secs_f64() * 1000.0, stats.read_latency.as_secs_f64() * 1000.0,
+        stats.cpu_fraction() * 100.0);
+}
+
+/// Start/stop conditions of a run: Ctrl+C, `--duration`, trigger and marker pins
+struct RunControl {
+    running: Arc<AtomicBool>,
+    duration: Option<Duration>,
+    started: Option<Instant>,
+    end_time: Option<Instant>,
+    trigger: Option<Trigger>,
+    mark: Option<(Gpio, u8)>,
+    events: Receiver<(Instant, String)>,
+}
+
+struct Trigger {
+    gpio: Gpio,
+    pin: u8,
+    active_high: bool,
+    last_poll: Instant,
+}
+
+impl Trigger {
+    fn is_active(&self) -> ft232_adxl355_spi::Result<bool> {
+        Ok(self.gpio.read(self.pin)? == self.active_high)
+    }
+
+    /// Transition into the active level
+    fn active_edge(&self) -> Edge {
+        if self.active_high { Edge::Rising } else { Edge::Falling }
+    }
+}
+
+impl RunControl {
+    fn new(config: &CollectorConfig, gpio: &Gpio, running: Arc<AtomicBool>) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        let trigger = match config.trigger_in {
+            Some(pin) => {
+                gpio.set_input(pin)?;
+                Some(Trigger { gpio: gpio.clone(), pin, active_high: !config.trigger_active_low, last_poll: Instant::now() })
+            }
+            None => None,
+        };
+        let mark = match config.mark_out {
+            Some(pin) => {
+                gpio.set_output(pin, false)?;
+                Some((gpio.clone(), pin))
+            }
+            None => None,
+        };
+
+        Ok(RunControl {
+            running,
+            duration: config.duration.map(Duration::from_secs),
+            started: None,
+            end_time: None,
+            trigger,
+            mark,
+            events: spawn_event_sources(config.event_port)?,
+        })
+    }
+
+    /// Block until the trigger is active, then toggle the marker and start the clock
+    ///
+    /// `false` means Ctrl+C was pressed before the trigger came.
+    fn start(&mut self) -> std::result::Result<bool, Box<dyn std::error::Error>> {
+        if let Some(trigger) = &self.trigger {
+            if !trigger.is_active()? {
+                println!("Waiting for trigger on ACBUS{}...", trigger.pin);
+                while !trigger.is_active()? {
+                    if !self.running.load(Ordering::SeqCst) {
+                        return Ok(false);
+                    }
+                    // Short waits so Ctrl+C is noticed; the level check above
+                    // catches an edge that fell between two of them
+                    trigger.gpio.wait_for_edge(trigger.pin, trigger.active_edge(), Some(Duration::from_millis(100)))?;
+                }
+            }
+            println!("Triggered, recording");
+        }
+
+        if let Some((gpio, pin)) = &self.mark {
+            gpio.toggle(*pin)?;
+        }
+
+        let now = Instant::now();
+        self.started = Some(now);
+        self.end_time = self.duration.map(|d| now + d);
+        Ok(true)
+    }
+
+    /// False once Ctrl+C, the duration or a released trigger ends the run
+    ///
+    /// The trigger is one-shot: once released it is not re-armed, the run
+    /// ends and a new one has to be started for the next active period.
+    fn keep_going(&mut self) -> bool {
+        if !self.running.load(Ordering::SeqCst) {
+            return false;
+        }
+        if self.end_time.is_some_and(|end| Instant::now() >= end) {
+            return false;
+        }
+
+        if let Some(trigger) = &mut self.trigger {
+            if trigger.last_poll.elapsed() >= TRIGGER_POLL_INTERVAL {
+                trigger.last_poll = Instant::now();
+                match trigger.is_active() {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        println!("\nTrigger released, stopping collection...");
+                        return false;
+                    }
+                    // A vanished adapter shows up in the next sensor read and is recovered there
+                    Err(e) => eprintln!("Trigger read error: {}", e),
+                }
+            }
+        }
+        true
+    }
+
+    /// Write the event lines received since the last call
+    ///
+    /// Times are taken on arrival; lines sent while waiting for the trigger
+    /// land at the start of the recording.
+    fn record_events(&mut self, writer: &mut Hdf5Writer, timer: &TimeKeeper) -> ft232_adxl355_spi::Result<()> {
+        while let Ok((received, line)) = self.events.try_recv() {
+            let time = (timer.elapsed_secs() - received.elapsed().as_secs_f64()).max(0.0);
+            if let Some(event) = Event::parse(&line, time) {
+                eprintln!("\n  Event at {:.2}s [{}] {}", event.time, event.kind, event.label);
+                writer.add_event(&event)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self) {
+        if let Some((gpio, pin)) = &self.mark {
+            let _ = gpio.write(*pin, false);
+        }
+    }
+
+    fn elapsed_secs(&self) -> f64 {
+        self.started.map_or(0.0, |t| t.elapsed().as_secs_f64())
+    }
+}
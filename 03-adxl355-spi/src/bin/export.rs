@@ -0,0 +1,94 @@
+//! Convert recordings to CSV, WAV, Parquet or MATLAB files
+//!
+//! Usage:
+//!   export --input data.h5 --output data.csv
+//!   export --input data.h5 --output hit.wav --start 12.5 --end 14 --float
+//!   export --input site.manifest --output site.mat
+//!   export --input data.h5 --output data.bin --format parquet
+
+use clap::Parser;
+use ft232_adxl355_spi::export::{export, ExportOptions, Format, WavEncoding};
+use ft232_adxl355_spi::Hdf5Reader;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(name = "export")]
+#[command(about = "Convert ADXL355 SPI recordings to CSV, WAV, Parquet or MAT files", long_about = None)]
+struct Args {
+    /// Input HDF5 file or session manifest (.manifest)
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Output file; the extension picks the format unless --format is given
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// csv, wav, parquet or mat
+    #[arg(long)]
+    format: Option<String>,
+
+    /// Start time in seconds (default: file start)
+    #[arg(long)]
+    start: Option<f64>,
+
+    /// End time in seconds (default: file end)
+    #[arg(long)]
+    end: Option<f64>,
+
+    /// WAV: 32-bit float samples in g instead of 16-bit PCM
+    #[arg(long)]
+    float: bool,
+
+    /// Device group of a multi-sensor file, e.g. "dbus4" (default: first device)
+    #[arg(long)]
+    device: Option<String>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let format = match &args.format {
+        Some(name) => Format::parse(name).ok_or_else(|| format!("Unknown format '{}' (csv, wav, parquet, mat)", name))?,
+        None => Format::from_path(&args.output)
+            .ok_or("Cannot tell the format from the output name, use --format")?,
+    };
+    if !format.is_available() {
+        return Err("Parquet export needs a build with --features parquet".into());
+    }
+
+    let reader = Hdf5Reader::open_device(&args.input, args.device.as_deref())?;
+    let options = ExportOptions {
+        start: args.start,
+        end: args.end,
+        wav_encoding: if args.float { WavEncoding::Float32 } else { WavEncoding::Pcm16 },
+    };
+
+    let samples = export(&reader, &args.output, format, &options)?;
+    if samples == 0 {
+        eprintln!("Warning: no samples in the selected range");
+    }
+    if format == Format::Wav {
+        warn_closed_gaps(&reader, &options)?;
+    }
+    println!("Exported {} samples to {} ({:?})", samples, args.output.display(), format);
+    Ok(())
+}
+
+/// WAV has no time axis: list the gaps that were closed up, after which
+/// the audio runs early by the missing time
+fn warn_closed_gaps(reader: &Hdf5Reader, options: &ExportOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let from = options.start.unwrap_or(f64::NEG_INFINITY);
+    let to = options.end.unwrap_or(f64::INFINITY);
+    let gaps: Vec<_> = reader.discontinuities()?.into_iter()
+        .filter(|gap| gap.end_time >= from && gap.start_time <= to)
+        .collect();
+    if gaps.is_empty() {
+        return Ok(());
+    }
+    let missing: f64 = gaps.iter().map(|gap| gap.end_time - gap.start_time).sum();
+    eprintln!("Warning: {} gap(s), {:.3} s in total, closed up in the WAV file:", gaps.len(), missing);
+    for gap in &gaps {
+        eprintln!("  {:.6} s to {:.6} s ({})", gap.start_time, gap.end_time, gap.cause);
+    }
+    Ok(())
+}
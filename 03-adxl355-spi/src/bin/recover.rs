@@ -0,0 +1,97 @@
+//! Recording repair
+//!
+//! Brings back an HDF5 file whose collector was killed, crashed or lost
+//! power, replaying the `<file>.journal` it left behind.
+//!
+//! Usage:
+//!   recover --input sensor_data.h5
+//!   recover --input sensor_data.h5 --check
+
+use clap::Parser;
+use ft232_adxl355_spi::journal::{journal_path, JournalContents};
+use ft232_adxl355_spi::{recover, Completion, Hdf5Reader};
+use std::path::{Path, PathBuf};
+
+#[derive(Parser, Debug)]
+#[command(name = "recover")]
+#[command(about = "Repair the HDF5 file of an interrupted collector run", long_about = None)]
+struct Args {
+    /// HDF5 file to repair
+    #[arg(short, long)]
+    input: PathBuf,
+
+    /// Print the state of the file and its journal, change nothing
+    #[arg(long)]
+    check: bool,
+}
+
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    if args.check {
+        check(&args.input);
+        return Ok(());
+    }
+
+    let report = recover(&args.input)?;
+    if report.already_complete {
+        println!("{} is complete ({} samples); nothing to recover", args.input.display(), report.sample_count);
+        return Ok(());
+    }
+    if report.rebuilt {
+        println!("Could not open the HDF5 file; kept it as .damaged and rebuilt from the journal");
+        println!("(per-device metadata such as revision and self-test is not in the journal)");
+    }
+    if report.journal_found {
+        println!("Replayed {} samples and {} gaps", report.journal_samples, report.journal_discontinuities);
+    }
+    if report.torn_bytes > 0 {
+        println!("Discarded a partly written journal record ({} bytes)", report.torn_bytes);
+    }
+    if report.lost_samples > 0 {
+        println!("{} samples could not be recovered", report.lost_samples);
+    }
+    println!("{} now holds {} samples", args.input.display(), report.sample_count);
+
+    Ok(())
+}
+
+fn check(path: &Path) {
+    match Hdf5Reader::open(path) {
+        Ok(reader) => {
+            let state = match reader.completion() {
+                Completion::Complete => "closed",
+                Completion::Recovered => "recovered",
+                Completion::Unknown => "not closed",
+            };
+            println!("{}: {}", path.display(), state);
+            let devices = reader.metadata().devices.clone();
+            if devices.is_empty() {
+                println!("  {} samples", reader.get_total_samples().unwrap_or(0));
+            }
+            for device in devices {
+                let samples = Hdf5Reader::open_device(path, Some(&device))
+                    .and_then(|r| r.get_total_samples())
+                    .unwrap_or(0);
+                println!("  {}: {} samples", device, samples);
+            }
+        }
+        Err(e) => println!("{}: cannot open ({})", path.display(), e),
+    }
+
+    let journal = journal_path(path);
+    if !journal.exists() {
+        println!("No journal");
+        return;
+    }
+    match JournalContents::read(&journal) {
+        Ok(contents) => {
+            println!("Journal: {} samples and {} gaps not yet in the file",
+                contents.sample_count(), contents.discontinuities.len());
+            if contents.torn_bytes > 0 {
+                println!("Journal ends in an incomplete record ({} bytes)", contents.torn_bytes);
+            }
+        }
+        Err(e) => println!("Journal: cannot read ({})", e),
+    }
+}
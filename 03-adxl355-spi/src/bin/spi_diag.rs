@@ -1,6 +1,11 @@
 //! SPI diagnostic - test split write+read vs full-duplex ReadWrite
+//!
+//! `spi-diag --sweep` instead steps through SPI clock rates and checks ID
+//! reads and FIFO data integrity at each one.
 
+use ft232_adxl355_spi::{Adxl355, OutputDataRate, SpiConfig};
 use std::ptr;
+use std::time::{Duration, Instant};
 
 #[allow(non_camel_case_types)]
 type DWORD = u32;
@@ -51,6 +56,11 @@ fn open_spi() -> FT_HANDLE {
 }
 
 fn main() {
+    if std::env::args().any(|a| a == "--sweep") {
+        run_sweep();
+        return;
+    }
+
     println!("ADXL355 SPI Transport Diagnostic");
     println!("=================================\n");
 
@@ -117,3 +127,125 @@ fn main() {
 fn expected(addr: u8) -> u8 {
     match addr { 0x00 => 0xAD, 0x01 => 0x1D, 0x02 => 0xED, 0x2C => 0x81, _ => 0x00 }
 }
+
+// ============================================================================
+// Clock sweep
+// ============================================================================
+
+const SWEEP_CLOCKS_HZ: [u32; 8] = [
+    500_000, 1_000_000, 2_000_000, 3_000_000, 4_000_000, 5_000_000, 7_500_000, 10_000_000,
+];
+const ID_READS: usize = 200;
+const FIFO_TEST_TIME: Duration = Duration::from_millis(1000);
+
+struct SweepResult {
+    clock_hz: u32,
+    id_errors: usize,
+    fifo_samples: usize,
+    fifo_rate: f64,
+    bad_samples: usize,
+    error: Option<String>,
+}
+
+impl SweepResult {
+    fn reliable(&self) -> bool {
+        self.error.is_none()
+            && self.id_errors == 0
+            && self.bad_samples == 0
+            // ODR is 1 kHz; allow for timing slop but not for lost batches
+            && self.fifo_rate > 950.0
+    }
+}
+
+fn run_sweep() {
+    println!("ADXL355 SPI Clock Sweep");
+    println!("=======================");
+    println!("Per clock: {} DEVID/PARTID reads, {:.1}s FIFO capture at 1 kHz ODR\n",
+        ID_READS, FIFO_TEST_TIME.as_secs_f64());
+
+    let mut results = Vec::new();
+    for &clock_hz in &SWEEP_CLOCKS_HZ {
+        let result = sweep_one(clock_hz);
+        print_sweep_row(&result);
+        results.push(result);
+    }
+
+    println!();
+    match results.iter().filter(|r| r.reliable()).map(|r| r.clock_hz).max() {
+        Some(best) => println!("Fastest reliable clock: {:.1} MHz (SpiConfig::new().clock_hz({}))",
+            best as f64 / 1e6, best),
+        None => println!("No clock rate passed. Check wiring, CS line and power."),
+    }
+}
+
+fn sweep_one(clock_hz: u32) -> SweepResult {
+    let mut result = SweepResult {
+        clock_hz,
+        id_errors: 0,
+        fifo_samples: 0,
+        fifo_rate: 0.0,
+        bad_samples: 0,
+        error: None,
+    };
+
+    // init() already rejects wrong DEVID/PARTID values
+    let mut sensor = match Adxl355::with_config(0, SpiConfig::new().clock_hz(clock_hz)) {
+        Ok(s) => s,
+        Err(e) => {
+            result.error = Some(format!("init: {}", e));
+            return result;
+        }
+    };
+
+    for _ in 0..ID_READS {
+        let ok = matches!(sensor.read_reg(0x00), Ok(0xAD))
+            && matches!(sensor.read_reg(0x02), Ok(0xED));
+        if !ok {
+            result.id_errors += 1;
+        }
+    }
+
+    if let Err(e) = sensor.enable_fifo(OutputDataRate::Odr1000) {
+        result.error = Some(format!("enable_fifo: {}", e));
+        return result;
+    }
+
+    let range = sensor.get_range();
+    let start = Instant::now();
+    while start.elapsed() < FIFO_TEST_TIME {
+        match sensor.read_fifo_batch() {
+            Ok(batch) => {
+                for sample in &batch {
+                    // A stationary sensor reads ~1 g total; garbage bits land far outside
+                    let (x, y, z) = sample.accel_to_g(range);
+                    let magnitude = (x * x + y * y + z * z).sqrt();
+                    if !(0.5..=1.5).contains(&magnitude) {
+                        result.bad_samples += 1;
+                    }
+                }
+                result.fifo_samples += batch.len();
+            }
+            Err(e) => {
+                result.error = Some(format!("FIFO read: {}", e));
+                break;
+            }
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    result.fifo_rate = result.fifo_samples as f64 / start.elapsed().as_secs_f64();
+
+    let _ = sensor.disable_fifo();
+    result
+}
+
+fn print_sweep_row(r: &SweepResult) {
+    let verdict = if r.reliable() { "OK" } else { "FAIL" };
+    match &r.error {
+        Some(e) => println!("{:>5.1} MHz  {:<4}  {}", r.clock_hz as f64 / 1e6, verdict, e),
+        None => println!(
+            "{:>5.1} MHz  {:<4}  ID errors {:>3}/{}  FIFO {:>5} samples ({:>6.1} Hz)  implausible {}",
+            r.clock_hz as f64 / 1e6, verdict, r.id_errors, ID_READS,
+            r.fifo_samples, r.fifo_rate, r.bad_samples
+        ),
+    }
+}
@@ -0,0 +1,178 @@
+//! Compare HDF5 storage settings on synthetic ADXL355 data
+//!
+//! Writes the same synthetic recording once per configuration, then reads
+//! it back, and prints throughput and file size of each.
+//!
+//! Usage:
+//!   storage-bench
+//!   storage-bench --rate 4000 --seconds 3600 --config deflate=4 --config deflate=1,shuffle,chunk=16384,ticks
+
+use clap::Parser;
+use ft232_adxl355_spi::{Hdf5Reader, Hdf5Writer, SensorData, StorageOptions, TimestampedSample};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Compared when no --config is given; the first is the writer default
+const DEFAULT_CONFIGS: [&str; 10] = [
+    "deflate=4",
+    "none",
+    "deflate=1",
+    "deflate=4,shuffle",
+    "deflate=4,shuffle,chunk=16384",
+    "deflate=4,shuffle,chunk=16384,ticks",
+    "deflate=9,shuffle,chunk=16384,ticks",
+    "lzf,shuffle,chunk=16384,ticks",
+    "blosc-lz4=5,shuffle,chunk=16384,ticks",
+    "blosc-zstd=5,shuffle,chunk=16384,ticks",
+];
+
+/// Samples per append, about one FIFO read at 4 kHz
+const BATCH: usize = 256;
+
+/// Bytes of one sample in memory: f64 timestamp, 3 x i32, u16
+const SAMPLE_BYTES: f64 = 22.0;
+
+#[derive(Parser, Debug)]
+#[command(name = "storage-bench")]
+#[command(about = "Benchmark HDF5 chunking, compression and timestamp settings", long_about = None)]
+struct Args {
+    /// Sample rate of the synthetic data in Hz
+    #[arg(long, default_value_t = 4000.0)]
+    rate: f64,
+
+    /// Length of the synthetic recording in seconds
+    #[arg(long, default_value_t = 300.0)]
+    seconds: f64,
+
+    /// Storage spec to test, repeatable (default: a built-in set)
+    #[arg(long)]
+    config: Vec<String>,
+
+    /// Directory for the test files (default: system temp directory)
+    #[arg(long)]
+    dir: Option<PathBuf>,
+
+    /// Keep the test files
+    #[arg(long)]
+    keep: bool,
+}
+
+/// 1 g on Z with a 0.05 g, 50 Hz vibration, +/-1 mg noise and host-clock
+/// jitter of up to +/-20 us, at range 2g
+struct Synthetic {
+    rate: f64,
+    state: u64,
+}
+
+impl Synthetic {
+    fn new(rate: f64) -> Self {
+        Synthetic { rate, state: 0x2545_F491_4F6C_DD1D }
+    }
+
+    /// Uniform in -1..1 (xorshift64)
+    fn noise(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+
+    fn sample(&mut self, index: usize) -> TimestampedSample {
+        const LSB_PER_G: f64 = 256_000.0;
+        let t = index as f64 / self.rate;
+        let vibration = 0.05 * (2.0 * std::f64::consts::PI * 50.0 * t).sin();
+        let mut accel = |g: f64| ((g + 0.001 * self.noise()) * LSB_PER_G).round() as i32;
+        let data = SensorData {
+            accel_x: accel(vibration),
+            accel_y: accel(0.0),
+            accel_z: accel(1.0 + vibration),
+            temperature: 1885 + (t / 60.0) as u16 % 8,
+        };
+        TimestampedSample { timestamp: t + 20e-6 * self.noise(), data }
+    }
+}
+
+struct Outcome {
+    write_secs: f64,
+    read_secs: f64,
+    bytes: u64,
+}
+
+fn run(path: &Path, storage: &StorageOptions, rate: f64, samples: usize) -> Result<Outcome, Box<dyn std::error::Error>> {
+    let mut source = Synthetic::new(rate);
+    let started = Instant::now();
+    let mut writer = Hdf5Writer::create_with(path, "benchmark", rate, "2g", &[], storage)?;
+    let mut batch = Vec::with_capacity(BATCH);
+    for index in 0..samples {
+        batch.push(source.sample(index));
+        if batch.len() == BATCH {
+            writer.append_batch(&batch)?;
+            batch.clear();
+        }
+    }
+    writer.append_batch(&batch)?;
+    writer.close()?;
+    let write_secs = started.elapsed().as_secs_f64();
+
+    let started = Instant::now();
+    let reader = Hdf5Reader::open(path)?;
+    let mut read = 0;
+    for chunk in reader.iter_chunks(65_536) {
+        read += chunk?.len();
+    }
+    let read_secs = started.elapsed().as_secs_f64();
+    if read != samples {
+        return Err(format!("read back {} of {} samples", read, samples).into());
+    }
+
+    Ok(Outcome { write_secs, read_secs, bytes: std::fs::metadata(path)?.len() })
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    if !(args.rate > 0.0 && args.seconds > 0.0) {
+        return Err("--rate and --seconds must be positive".into());
+    }
+    let samples = (args.rate * args.seconds).round() as usize;
+    let raw_mb = samples as f64 * SAMPLE_BYTES / 1e6;
+    let dir = args.dir.clone().unwrap_or_else(std::env::temp_dir);
+    let specs: Vec<String> = if args.config.is_empty() {
+        DEFAULT_CONFIGS.iter().map(|spec| spec.to_string()).collect()
+    } else {
+        args.config.clone()
+    };
+
+    println!("{} samples ({:.0} s at {} Hz), {:.1} MB in memory\n", samples, args.seconds, args.rate, raw_mb);
+    println!("{:<52} {:>10} {:>10} {:>10} {:>7}", "storage", "write MB/s", "read MB/s", "file MB", "ratio");
+    for (index, spec) in specs.iter().enumerate() {
+        let storage = match StorageOptions::parse(spec) {
+            Ok(storage) => storage,
+            Err(e) => {
+                println!("{:<52} skipped: {}", spec, e);
+                continue;
+            }
+        };
+        let path = dir.join(format!("storage_bench_{}_{:02}.h5", std::process::id(), index));
+        let outcome = run(&path, &storage, args.rate, samples);
+        if !args.keep {
+            let _ = std::fs::remove_file(&path);
+        }
+        let label = storage.to_string();
+        match outcome {
+            Ok(outcome) => println!(
+                "{:<52} {:>10.1} {:>10.1} {:>10.2} {:>7.2}",
+                label,
+                raw_mb / outcome.write_secs,
+                raw_mb / outcome.read_secs,
+                outcome.bytes as f64 / 1e6,
+                raw_mb * 1e6 / outcome.bytes as f64,
+            ),
+            Err(e) => println!("{:<52} failed: {}", label, e),
+        }
+    }
+    println!("\nWrite includes overview and file close; ratio is in-memory size / file size.");
+    if args.keep {
+        println!("Files kept in {}", dir.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,263 @@
+//! Transport trace viewer
+//!
+//! Pretty-prints a capture written by `collector --trace` (or
+//! `Tracer::file`), decoding ADXL355 register commands, compares two
+//! captures call by call, or runs the driver's `init` against one.
+//!
+//! Usage:
+//!   trace-replay capture.trace
+//!   trace-replay good.trace --diff bad.trace --ignore-input
+//!   trace-replay capture.trace --replay
+
+use clap::Parser;
+use ft232_adxl355_spi::{Adxl355, SpiConfig, Trace, TraceOp, TraceRecord};
+use std::path::PathBuf;
+
+// libMPSSE transfer option bits
+const OPT_SIZE_IN_BITS: u32 = 0x01;
+const OPT_CS_ENABLE: u32 = 0x02;
+const OPT_CS_DISABLE: u32 = 0x04;
+
+/// Bytes of a register read that precede fresh data on the split Write+Read path
+const PIPELINE_BYTES: usize = 2;
+
+#[derive(Parser, Debug)]
+#[command(name = "trace-replay")]
+#[command(about = "Show, compare and replay ADXL355 SPI transport traces")]
+struct Args {
+    /// Trace file
+    trace: PathBuf,
+
+    /// Compare against a second trace instead of printing
+    #[arg(long)]
+    diff: Option<PathBuf>,
+
+    /// With --diff: ignore received bytes (sensor data differs between runs)
+    #[arg(long)]
+    ignore_input: bool,
+
+    /// Feed the trace to the driver and check that `init` consumes it as recorded
+    #[arg(long)]
+    replay: bool,
+
+    /// Print at most this many records / differences
+    #[arg(long, default_value = "200")]
+    limit: usize,
+
+    /// Print every byte of long transfers instead of the first 32
+    #[arg(long)]
+    full: bool,
+}
+
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let trace = Trace::load(&args.trace)?;
+
+    if args.replay {
+        return replay(trace);
+    }
+
+    if let Some(other) = &args.diff {
+        let other = Trace::load(other)?;
+        let differences = diff(&trace, &other, &args);
+        if differences > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    print_trace(&trace, &args);
+    Ok(())
+}
+
+fn print_trace(trace: &Trace, args: &Args) {
+    println!("{} record(s) in {}", trace.len(), args.trace.display());
+    if let (Some(first), Some(last)) = (trace.records.first(), trace.records.last()) {
+        let span_ms = (last.time_us + last.duration_us - first.time_us) as f64 / 1000.0;
+        let busy_ms = trace.records.iter().map(|r| r.duration_us).sum::<u64>() as f64 / 1000.0;
+        let failed = trace.records.iter().filter(|r| r.status != 0).count();
+        println!("Span {:.1} ms, {:.1} ms in transfers, {} failed call(s)", span_ms, busy_ms, failed);
+    }
+    println!();
+    println!("{:>6} {:>10} {:>7}  {:<13} {:<8} {:<18} data", "seq", "time ms", "dur us", "op", "options", "status");
+
+    let mut pending_read = false;
+    for record in trace.records.iter().take(args.limit) {
+        println!("{:>6} {:>10.3} {:>7}  {:<13} {:<8} {:<18} {}",
+            record.seq, record.time_us as f64 / 1000.0, record.duration_us, record.op.name(),
+            options_label(record), status_label(record), describe(record, pending_read, args.full));
+
+        // A read command left CS asserted: the next SPI_Read carries its data
+        pending_read = record.op == TraceOp::SpiWrite
+            && record.options & OPT_CS_DISABLE == 0
+            && record.out.first().is_some_and(|cmd| cmd & 0x01 != 0);
+    }
+    if trace.len() > args.limit {
+        println!("... {} more (use --limit)", trace.len() - args.limit);
+    }
+}
+
+/// Compare two traces call by call; returns the number of differences
+fn diff(a: &Trace, b: &Trace, args: &Args) -> usize {
+    let same = |x: &TraceRecord, y: &TraceRecord| {
+        if args.ignore_input {
+            x.op == y.op && x.options == y.options && x.status == y.status
+                && x.out == y.out && x.input.len() == y.input.len()
+        } else {
+            x.same_call(y)
+        }
+    };
+
+    let mut differences = 0;
+    for (x, y) in a.records.iter().zip(&b.records) {
+        if same(x, y) {
+            continue;
+        }
+        differences += 1;
+        if differences <= args.limit {
+            println!("#{} / #{}:", x.seq, y.seq);
+            println!("  - {:<13} {:<8} {:<18} {}", x.op.name(), options_label(x), status_label(x), describe(x, false, args.full));
+            println!("  + {:<13} {:<8} {:<18} {}", y.op.name(), options_label(y), status_label(y), describe(y, false, args.full));
+        }
+    }
+
+    if a.len() != b.len() {
+        differences += a.len().abs_diff(b.len());
+        println!("Length differs: {} vs {} record(s)", a.len(), b.len());
+    }
+
+    if differences == 0 {
+        println!("Traces match ({} record(s))", a.len());
+    } else {
+        println!("{} difference(s)", differences);
+    }
+    differences
+}
+
+fn replay(trace: Trace) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let total = trace.len();
+    let sensor = Adxl355::replay(trace, SpiConfig::default())?;
+    let remaining = sensor.replay_remaining().unwrap_or(0);
+
+    match sensor.device_info() {
+        Some(info) => println!("init replayed: {} rev {}", info.variant.name(), info.revid),
+        None => println!("init replayed"),
+    }
+    println!("{} of {} record(s) consumed, {} left after init", total - remaining, total, remaining);
+    Ok(())
+}
+
+fn options_label(record: &TraceRecord) -> String {
+    if matches!(record.op, TraceOp::MpsseTransfer | TraceOp::Purge) {
+        return "-".to_string();
+    }
+    let mut parts = Vec::new();
+    if record.options & OPT_CS_ENABLE != 0 {
+        parts.push("CS+");
+    }
+    if record.options & OPT_CS_DISABLE != 0 {
+        parts.push("CS-");
+    }
+    if record.options & OPT_SIZE_IN_BITS != 0 {
+        parts.push("bits");
+    }
+    if parts.is_empty() { "-".to_string() } else { parts.join(" ") }
+}
+
+fn status_label(record: &TraceRecord) -> String {
+    let name = match record.status {
+        0 => "OK",
+        1 => "INVALID_HANDLE",
+        2 => "DEVICE_NOT_FOUND",
+        3 => "DEVICE_NOT_OPENED",
+        4 => "IO_ERROR",
+        5 => "INSUFFICIENT_RES",
+        6 => "INVALID_PARAMETER",
+        10 => "FAILED_TO_WRITE",
+        18 => "OTHER_ERROR",
+        _ => "error",
+    };
+    let requested = match record.op {
+        TraceOp::SpiWrite | TraceOp::SpiReadWrite => record.out.len(),
+        _ => record.input.len(),
+    };
+    if record.status == 0 && (record.transferred as usize) < requested {
+        format!("{} {}/{}", name, record.transferred, requested)
+    } else if record.status == 0 {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, record.status)
+    }
+}
+
+/// Human-readable data column, decoding register commands
+fn describe(record: &TraceRecord, after_read_command: bool, full: bool) -> String {
+    match record.op {
+        TraceOp::SpiWrite => match record.out.as_slice() {
+            [cmd] if cmd & 0x01 != 0 => format!("read {}", register_name(cmd >> 1)),
+            [cmd, values @ ..] if cmd & 0x01 == 0 && !values.is_empty() => {
+                format!("write {} = {}", register_name(cmd >> 1), hex(values, full))
+            }
+            bytes => format!("out {}", hex(bytes, full)),
+        },
+        TraceOp::SpiRead if after_read_command && record.input.len() > PIPELINE_BYTES => {
+            let (stale, fresh) = record.input.split_at(PIPELINE_BYTES);
+            format!("stale {} | {}", hex(stale, full), hex(fresh, full))
+        }
+        TraceOp::SpiRead => format!("in {}", hex(&record.input, full)),
+        TraceOp::SpiReadWrite => {
+            let command = match record.out.first() {
+                Some(cmd) if cmd & 0x01 != 0 => format!(" (read {})", register_name(cmd >> 1)),
+                _ => String::new(),
+            };
+            format!("out {} | in {}{}", hex(&record.out, full), hex(&record.input, full), command)
+        }
+        TraceOp::MpsseTransfer => format!("{} opcode byte(s) | in {}",
+            record.out.len(), hex(&record.input, full)),
+        TraceOp::Purge => "rx+tx".to_string(),
+    }
+}
+
+fn register_name(reg: u8) -> String {
+    let name = match reg {
+        0x00 => "DEVID_AD",
+        0x01 => "DEVID_MST",
+        0x02 => "PARTID",
+        0x03 => "REVID",
+        0x04 => "STATUS",
+        0x05 => "FIFO_ENTRIES",
+        0x06 => "TEMP2",
+        0x07 => "TEMP1",
+        0x08 => "XDATA3",
+        0x0B => "YDATA3",
+        0x0E => "ZDATA3",
+        0x11 => "FIFO_DATA",
+        0x1E..=0x23 => "OFFSET",
+        0x24 => "ACT_EN",
+        0x28 => "FILTER",
+        0x29 => "FIFO_SAMPLES",
+        0x2A => "INT_MAP",
+        0x2B => "SYNC",
+        0x2C => "RANGE",
+        0x2D => "POWER_CTL",
+        0x2E => "SELF_TEST",
+        0x2F => "RESET",
+        _ => return format!("0x{:02X}", reg),
+    };
+    name.to_string()
+}
+
+fn hex(bytes: &[u8], full: bool) -> String {
+    if bytes.is_empty() {
+        return "-".to_string();
+    }
+    let shown = if full { bytes.len() } else { bytes.len().min(32) };
+    let mut text = bytes[..shown].iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if shown < bytes.len() {
+        text.push_str(&format!(" ... ({} bytes)", bytes.len()));
+    }
+    text
+}
@@ -0,0 +1,507 @@
+//! Threshold-triggered capture with pre-trigger history
+//!
+//! [`Capture`] watches the sample stream, e.g. the batches of
+//! [`Adxl355::stream_fifo`](crate::Adxl355::stream_fifo), for one axis or
+//! the vector magnitude crossing a level in g. The last
+//! [`TriggerConfig::pre_secs`] of samples are kept in a ring buffer, so when
+//! the trigger fires the capture starts with that history and runs on for
+//! [`TriggerConfig::post_secs`]. After that the trigger re-arms.
+//!
+//! The level has a hysteresis band: after a rising crossing of `level` the
+//! signal must drop below `level - hysteresis` before it can fire again
+//! (above `level + hysteresis` for a falling trigger). An optional
+//! [`Band`] filters each axis first (2nd-order Butterworth high- and/or
+//! low-pass), e.g. to remove gravity and drift before a magnitude trigger.
+//!
+//! The collector's `--trigger-level` mode writes every capture to its own
+//! HDF5 file.
+
+use crate::gpio::Edge;
+use crate::hdf5_format::TimestampedSample;
+use crate::{Adxl355Error, Range, Result};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Signal compared with the level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerSource {
+    /// One axis, signed
+    X,
+    Y,
+    Z,
+    /// sqrt(x² + y² + z²)
+    Magnitude,
+}
+
+impl TriggerSource {
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TriggerSource::X => "x",
+            TriggerSource::Y => "y",
+            TriggerSource::Z => "z",
+            TriggerSource::Magnitude => "magnitude",
+        }
+    }
+
+    /// Parse a name produced by [`TriggerSource::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "x" => Some(TriggerSource::X),
+            "y" => Some(TriggerSource::Y),
+            "z" => Some(TriggerSource::Z),
+            "magnitude" | "mag" => Some(TriggerSource::Magnitude),
+            _ => None,
+        }
+    }
+}
+
+/// Pass band of the trigger signal; `None` leaves that side open
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    /// High-pass corner in Hz
+    pub low_hz: Option<f64>,
+    /// Low-pass corner in Hz
+    pub high_hz: Option<f64>,
+}
+
+impl Band {
+    /// Parse "5-200", "5-" (high-pass only) or "-200" (low-pass only)
+    pub fn parse(text: &str) -> Option<Self> {
+        let (low, high) = text.trim().split_once('-')?;
+        let corner = |text: &str| -> Option<Option<f64>> {
+            match text.trim() {
+                "" => Some(None),
+                text => text.parse().ok().filter(|hz: &f64| *hz > 0.0).map(Some),
+            }
+        };
+        let band = Band { low_hz: corner(low)?, high_hz: corner(high)? };
+        (band.low_hz.is_some() || band.high_hz.is_some()).then_some(band)
+    }
+}
+
+impl fmt::Display for Band {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(low) = self.low_hz {
+            write!(f, "{}", low)?;
+        }
+        write!(f, "-")?;
+        if let Some(high) = self.high_hz {
+            write!(f, "{}", high)?;
+        }
+        Ok(())
+    }
+}
+
+/// When a capture starts and how much it holds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TriggerConfig {
+    pub source: TriggerSource,
+    /// Threshold in g
+    pub level_g: f64,
+    /// `Rising` fires when the signal goes above the level, `Falling` below
+    pub edge: Edge,
+    /// Width of the re-arm band in g
+    pub hysteresis_g: f64,
+    pub band: Option<Band>,
+    /// Seconds of history before the trigger
+    pub pre_secs: f64,
+    /// Seconds recorded after the trigger
+    pub post_secs: f64,
+}
+
+impl TriggerConfig {
+    /// Rising magnitude trigger at `level_g`, 10% hysteresis, 1 s before
+    /// and 4 s after
+    pub fn new(level_g: f64) -> Self {
+        TriggerConfig {
+            source: TriggerSource::Magnitude,
+            level_g,
+            edge: Edge::Rising,
+            hysteresis_g: 0.1 * level_g.abs(),
+            band: None,
+            pre_secs: 1.0,
+            post_secs: 4.0,
+        }
+    }
+
+    /// Check the values against the sample rate
+    pub fn validate(&self, rate: f64) -> Result<()> {
+        let invalid = |message: String| Err(Adxl355Error::InvalidParameter(message));
+        if !self.level_g.is_finite() {
+            return invalid("Trigger level must be a number of g".to_string());
+        }
+        if !(self.hysteresis_g.is_finite() && self.hysteresis_g >= 0.0) {
+            return invalid(format!("Trigger hysteresis {} g must not be negative", self.hysteresis_g));
+        }
+        if !(self.pre_secs >= 0.0 && self.post_secs > 0.0) {
+            return invalid("Pre-trigger time must not be negative and post-trigger time must be positive".to_string());
+        }
+        if let Some(band) = &self.band {
+            let nyquist = rate / 2.0;
+            if band.high_hz.is_some_and(|high| high >= nyquist) || band.low_hz.is_some_and(|low| low >= nyquist) {
+                return invalid(format!("Trigger band {} Hz must stay below {} Hz at {} Hz", band, nyquist, rate));
+            }
+            if let (Some(low), Some(high)) = (band.low_hz, band.high_hz) {
+                if low >= high {
+                    return invalid(format!("Trigger band {} Hz is empty", band));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The description stored in capture files, e.g.
+/// "magnitude rising 2 g, hysteresis 0.2 g, band 5-200 Hz, pre 1 s, post 4 s"
+impl fmt::Display for TriggerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {} g, hysteresis {} g", self.source.as_str(), self.edge.as_str(), self.level_g, self.hysteresis_g)?;
+        if let Some(band) = &self.band {
+            write!(f, ", band {} Hz", band)?;
+        }
+        write!(f, ", pre {} s, post {} s", self.pre_secs, self.post_secs)
+    }
+}
+
+/// What [`Capture::push`] asks the caller to do, in order
+#[derive(Debug, Clone)]
+pub enum CaptureEvent {
+    /// The trigger fired on the sample at `time` with signal `value` (g);
+    /// `history` are the samples before it, oldest first
+    Start { time: f64, value: f64, history: Vec<TimestampedSample> },
+    /// Samples of the running capture, starting with the trigger sample
+    Samples(Vec<TimestampedSample>),
+    /// The post-trigger time is over; the trigger is armed again
+    End,
+}
+
+/// 2nd-order IIR section (direct form I), RBJ cookbook coefficients
+#[derive(Debug, Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Butterworth low-pass (`high_pass` false) or high-pass at `corner_hz`
+    fn butterworth(corner_hz: f64, rate: f64, high_pass: bool) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * corner_hz / rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / std::f64::consts::SQRT_2;
+        let a0 = 1.0 + alpha;
+        let b = if high_pass {
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0]
+        } else {
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0]
+        };
+        Biquad {
+            b: b.map(|b| b / a0),
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    /// Settle on a constant input, so the first samples do not ring
+    fn prime(&mut self, x: f64) {
+        let gain = self.b.iter().sum::<f64>() / (1.0 + self.a[0] + self.a[1]);
+        self.x = [x; 2];
+        self.y = [x * gain; 2];
+    }
+
+    fn step(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1] - self.a[0] * self.y[0] - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Trigger state over a sample stream
+pub struct Capture {
+    config: TriggerConfig,
+    range: Range,
+    /// High- and/or low-pass per axis
+    filters: [Vec<Biquad>; 3],
+    primed: bool,
+    /// Signal beyond the level, with hysteresis; `None` before the first sample
+    above: Option<bool>,
+    history: VecDeque<TimestampedSample>,
+    history_len: usize,
+    /// End of the running capture
+    until: Option<f64>,
+    count: usize,
+}
+
+impl Capture {
+    /// Trigger on samples of `range` arriving at `rate` Hz
+    pub fn new(config: &TriggerConfig, rate: f64, range: Range) -> Result<Self> {
+        config.validate(rate)?;
+        let chain = || {
+            let mut filters = Vec::new();
+            if let Some(band) = &config.band {
+                if let Some(low) = band.low_hz {
+                    filters.push(Biquad::butterworth(low, rate, true));
+                }
+                if let Some(high) = band.high_hz {
+                    filters.push(Biquad::butterworth(high, rate, false));
+                }
+            }
+            filters
+        };
+        let history_len = (config.pre_secs * rate).ceil() as usize;
+        Ok(Capture {
+            config: *config,
+            range,
+            filters: [chain(), chain(), chain()],
+            primed: false,
+            above: None,
+            history: VecDeque::with_capacity(history_len),
+            history_len,
+            until: None,
+            count: 0,
+        })
+    }
+
+    pub fn config(&self) -> &TriggerConfig {
+        &self.config
+    }
+
+    /// A capture is running
+    pub fn is_capturing(&self) -> bool {
+        self.until.is_some()
+    }
+
+    /// Captures started so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Feed the next samples; the returned events say what to write
+    pub fn push(&mut self, samples: &[TimestampedSample]) -> Vec<CaptureEvent> {
+        let mut events = Vec::new();
+        let mut captured = Vec::new();
+        for sample in samples {
+            let value = self.signal(sample);
+            let fired = self.update(value);
+
+            if let Some(until) = self.until {
+                if sample.timestamp <= until {
+                    captured.push(sample.clone());
+                    continue;
+                }
+                if !captured.is_empty() {
+                    events.push(CaptureEvent::Samples(std::mem::take(&mut captured)));
+                }
+                events.push(CaptureEvent::End);
+                self.until = None;
+            }
+
+            if fired {
+                self.count += 1;
+                self.until = Some(sample.timestamp + self.config.post_secs);
+                events.push(CaptureEvent::Start {
+                    time: sample.timestamp,
+                    value,
+                    history: self.history.drain(..).collect(),
+                });
+                captured.push(sample.clone());
+            } else if self.history_len > 0 {
+                if self.history.len() == self.history_len {
+                    self.history.pop_front();
+                }
+                self.history.push_back(sample.clone());
+            }
+        }
+        if !captured.is_empty() {
+            events.push(CaptureEvent::Samples(captured));
+        }
+        events
+    }
+
+    /// Forget history, filter and trigger state after a gap in the samples;
+    /// a running capture carries on
+    pub fn discontinuity(&mut self) {
+        self.history.clear();
+        self.primed = false;
+        self.above = None;
+    }
+
+    /// Band-limited trigger signal of one sample in g
+    fn signal(&mut self, sample: &TimestampedSample) -> f64 {
+        let (x, y, z) = sample.data.accel_to_g(self.range);
+        let mut axes = [x as f64, y as f64, z as f64];
+        for (value, chain) in axes.iter_mut().zip(&mut self.filters) {
+            for filter in chain.iter_mut() {
+                if !self.primed {
+                    filter.prime(*value);
+                }
+                *value = filter.step(*value);
+            }
+        }
+        self.primed = true;
+        match self.config.source {
+            TriggerSource::X => axes[0],
+            TriggerSource::Y => axes[1],
+            TriggerSource::Z => axes[2],
+            TriggerSource::Magnitude => axes.iter().map(|v| v * v).sum::<f64>().sqrt(),
+        }
+    }
+
+    /// Move the hysteresis state on; `true` if that is a trigger edge
+    fn update(&mut self, value: f64) -> bool {
+        let TriggerConfig { level_g: level, hysteresis_g: hysteresis, edge, .. } = self.config;
+        // The band lies on the side the signal returns to after firing
+        let (set, clear) = match edge {
+            Edge::Falling => (level + hysteresis, level),
+            Edge::Rising | Edge::Both => (level, level - hysteresis),
+        };
+        let Some(was) = self.above else {
+            self.above = Some(value >= set);
+            return false;
+        };
+        let now = if was { value > clear } else { value >= set };
+        self.above = Some(now);
+        self.until.is_none() && edge.matches(was, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SensorData;
+
+    const RATE: f64 = 1000.0;
+
+    /// Samples at `RATE` with Z in g given by `z(t)`
+    fn samples(start: usize, count: usize, z: impl Fn(f64) -> f64) -> Vec<TimestampedSample> {
+        let lsb = Range::G2.scale_factor() as f64;
+        (start..start + count)
+            .map(|i| {
+                let t = i as f64 / RATE;
+                TimestampedSample {
+                    timestamp: t,
+                    data: SensorData { accel_x: 0, accel_y: 0, accel_z: (z(t) * lsb).round() as i32, temperature: 0 },
+                }
+            })
+            .collect()
+    }
+
+    fn config(level: f64) -> TriggerConfig {
+        TriggerConfig { source: TriggerSource::Z, pre_secs: 0.1, post_secs: 0.2, ..TriggerConfig::new(level) }
+    }
+
+    #[test]
+    fn captures_history_and_post_window() {
+        let mut capture = Capture::new(&config(0.5), RATE, Range::G2).unwrap();
+        // Step up at t = 0.5 s, back down at 0.6 s
+        let events = capture.push(&samples(0, 1000, |t| if (0.5..0.6).contains(&t) { 1.0 } else { 0.0 }));
+        assert_eq!(capture.count(), 1);
+        let CaptureEvent::Start { time, value, history } = &events[0] else { panic!("{:?}", events[0]) };
+        assert_eq!(*time, 0.5);
+        assert!((value - 1.0).abs() < 1e-5);
+        assert_eq!(history.len(), 100);
+        assert_eq!(history.last().unwrap().timestamp, 0.499);
+        let CaptureEvent::Samples(captured) = &events[1] else { panic!("{:?}", events[1]) };
+        assert_eq!(captured.len(), 201);
+        assert!(matches!(events[2], CaptureEvent::End));
+        assert_eq!(events.len(), 3);
+        assert!(!capture.is_capturing());
+    }
+
+    #[test]
+    fn hysteresis_and_rearm() {
+        let mut capture = Capture::new(&TriggerConfig { hysteresis_g: 0.2, ..config(0.5) }, RATE, Range::G2).unwrap();
+        // Starts above the level: not an edge
+        assert!(capture.push(&samples(0, 10, |_| 1.0)).is_empty());
+        // Dips to 0.4, inside the band, then rises again: still not re-armed
+        assert!(capture.push(&samples(10, 10, |_| 0.4)).is_empty());
+        assert!(capture.push(&samples(20, 10, |_| 1.0)).is_empty());
+        // Below 0.3 re-arms
+        capture.push(&samples(30, 10, |_| 0.0));
+        let events = capture.push(&samples(40, 10, |_| 1.0));
+        assert!(matches!(events[0], CaptureEvent::Start { .. }));
+        assert!(capture.is_capturing());
+        // Crossings during the capture do not start another one
+        capture.push(&samples(50, 50, |t| if t < 0.07 { 0.0 } else { 1.0 }));
+        assert_eq!(capture.count(), 1);
+    }
+
+    #[test]
+    fn falling_edge() {
+        let trigger = TriggerConfig { edge: Edge::Falling, hysteresis_g: 0.1, ..config(-0.5) };
+        let mut capture = Capture::new(&trigger, RATE, Range::G2).unwrap();
+        let events = capture.push(&samples(0, 100, |t| if t < 0.05 { 0.0 } else { -1.0 }));
+        let CaptureEvent::Start { time, .. } = events[0] else { panic!("{:?}", events[0]) };
+        assert_eq!(time, 0.05);
+    }
+
+    #[test]
+    fn band_removes_gravity() {
+        let trigger = TriggerConfig {
+            source: TriggerSource::Magnitude,
+            band: Band::parse("5-"),
+            ..config(0.2)
+        };
+        let mut capture = Capture::new(&trigger, RATE, Range::G2).unwrap();
+        // Steady 1 g is far above 0.2 g but filtered out
+        assert!(capture.push(&samples(0, 2000, |_| 1.0)).is_empty());
+        // A 50 Hz burst of 0.5 g passes
+        let burst = |t: f64| 1.0 + if t >= 2.5 { 0.5 * (2.0 * std::f64::consts::PI * 50.0 * t).sin() } else { 0.0 };
+        let events = capture.push(&samples(2000, 1000, burst));
+        let CaptureEvent::Start { time, .. } = events[0] else { panic!("{:?}", events[0]) };
+        assert!((2.5..2.52).contains(&time), "{}", time);
+    }
+
+    #[test]
+    fn parse_and_validate() {
+        assert_eq!(Band::parse("5-200"), Some(Band { low_hz: Some(5.0), high_hz: Some(200.0) }));
+        assert_eq!(Band::parse("-50"), Some(Band { low_hz: None, high_hz: Some(50.0) }));
+        for text in ["-", "5", "a-b", "0-10"] {
+            assert_eq!(Band::parse(text), None, "{}", text);
+        }
+        assert_eq!(TriggerSource::from_name("mag"), Some(TriggerSource::Magnitude));
+        assert!(config(1.0).validate(RATE).is_ok());
+        assert!(TriggerConfig { band: Band::parse("5-600"), ..config(1.0) }.validate(RATE).is_err());
+        assert!(TriggerConfig { band: Band::parse("50-5"), ..config(1.0) }.validate(RATE).is_err());
+        assert!(TriggerConfig { post_secs: 0.0, ..config(1.0) }.validate(RATE).is_err());
+        assert_eq!(
+            TriggerConfig { band: Band::parse("5-"), ..TriggerConfig::new(2.0) }.to_string(),
+            "magnitude rising 2 g, hysteresis 0.2 g, band 5- Hz, pre 1 s, post 4 s",
+        );
+    }
+
+    #[test]
+    fn fifo_batches_across_a_reconnect() {
+        const ODR: f64 = 4000.0;
+        // ADXL357 at +/-10 g, read in 32-sample FIFO batches
+        let lsb = Range::G10.scale_factor() as f64;
+        let sample = |i: usize, z: f64| TimestampedSample {
+            timestamp: i as f64 / ODR,
+            data: SensorData { accel_x: 0, accel_y: 0, accel_z: (z * lsb).round() as i32, temperature: 0 },
+        };
+        let trigger = TriggerConfig { source: TriggerSource::Z, pre_secs: 0.01, post_secs: 0.01, ..TriggerConfig::new(5.0) };
+        let mut capture = Capture::new(&trigger, ODR, Range::G10).unwrap();
+        let before: Vec<_> = (0..400).map(|i| sample(i, 0.0)).collect();
+        for batch in before.chunks(32) {
+            assert!(capture.push(batch).is_empty());
+        }
+
+        // The sensor reconnects at 0.25 s and sees a 6 g shock 20 samples later
+        capture.discontinuity();
+        let after: Vec<_> = (1000..1200).map(|i| sample(i, if i >= 1020 { 6.0 } else { 0.0 })).collect();
+        let events: Vec<CaptureEvent> = after.chunks(32).flat_map(|batch| capture.push(batch)).collect();
+        let CaptureEvent::Start { time, value, history } = &events[0] else { panic!("{:?}", events[0]) };
+        assert_eq!((*time, *value), (0.255, 6.0));
+        // 40 samples of history were asked for; only those after the gap are kept
+        assert_eq!(history.len(), 20);
+        assert_eq!(history[0].timestamp, 0.25);
+        let captured: usize = events.iter().map(|e| if let CaptureEvent::Samples(s) = e { s.len() } else { 0 }).sum();
+        assert!((40..=41).contains(&captured), "{}", captured);
+        assert!(matches!(events.last(), Some(CaptureEvent::End)));
+        assert_eq!(capture.count(), 1);
+    }
+}
@@ -1,17 +1,145 @@
 //! Error types for ADXL355 sensor interface
+//!
+//! Transport failures ([`Adxl355Error::Transport`]) record which SPI or
+//! MPSSE operation failed, on which register, and the decoded
+//! [`FtStatus`]. Files have their own variants: [`Adxl355Error::Storage`]
+//! for HDF5, [`Adxl355Error::Io`] for calibration files and traces.
 
+use std::fmt;
 use thiserror::Error;
 
-use crate::ffi::{status_to_string, FT_STATUS, FT_OK};
+use crate::ffi::*;
+
+/// Decoded FT_STATUS of a failed call
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FtStatus {
+    InvalidHandle,
+    DeviceNotFound,
+    DeviceNotOpened,
+    IoError,
+    InsufficientResources,
+    InvalidParameter,
+    InvalidBaudRate,
+    DeviceNotOpenedForErase,
+    DeviceNotOpenedForWrite,
+    FailedToWriteDevice,
+    EepromReadFailed,
+    EepromWriteFailed,
+    EepromEraseFailed,
+    EepromNotPresent,
+    EepromNotProgrammed,
+    InvalidArgs,
+    NotSupported,
+    OtherError,
+    /// Code not defined by D2XX
+    Unknown(u32),
+}
+
+impl FtStatus {
+    /// Decode a status code; `None` for FT_OK
+    pub fn from_raw(status: u32) -> Option<Self> {
+        Some(match status {
+            FT_OK => return None,
+            FT_INVALID_HANDLE => FtStatus::InvalidHandle,
+            FT_DEVICE_NOT_FOUND => FtStatus::DeviceNotFound,
+            FT_DEVICE_NOT_OPENED => FtStatus::DeviceNotOpened,
+            FT_IO_ERROR => FtStatus::IoError,
+            FT_INSUFFICIENT_RESOURCES => FtStatus::InsufficientResources,
+            FT_INVALID_PARAMETER => FtStatus::InvalidParameter,
+            FT_INVALID_BAUD_RATE => FtStatus::InvalidBaudRate,
+            FT_DEVICE_NOT_OPENED_FOR_ERASE => FtStatus::DeviceNotOpenedForErase,
+            FT_DEVICE_NOT_OPENED_FOR_WRITE => FtStatus::DeviceNotOpenedForWrite,
+            FT_FAILED_TO_WRITE_DEVICE => FtStatus::FailedToWriteDevice,
+            FT_EEPROM_READ_FAILED => FtStatus::EepromReadFailed,
+            FT_EEPROM_WRITE_FAILED => FtStatus::EepromWriteFailed,
+            FT_EEPROM_ERASE_FAILED => FtStatus::EepromEraseFailed,
+            FT_EEPROM_NOT_PRESENT => FtStatus::EepromNotPresent,
+            FT_EEPROM_NOT_PROGRAMMED => FtStatus::EepromNotProgrammed,
+            FT_INVALID_ARGS => FtStatus::InvalidArgs,
+            FT_NOT_SUPPORTED => FtStatus::NotSupported,
+            FT_OTHER_ERROR => FtStatus::OtherError,
+            other => FtStatus::Unknown(other),
+        })
+    }
+
+    /// The numeric FT_STATUS
+    pub fn raw(&self) -> u32 {
+        match self {
+            FtStatus::InvalidHandle => FT_INVALID_HANDLE,
+            FtStatus::DeviceNotFound => FT_DEVICE_NOT_FOUND,
+            FtStatus::DeviceNotOpened => FT_DEVICE_NOT_OPENED,
+            FtStatus::IoError => FT_IO_ERROR,
+            FtStatus::InsufficientResources => FT_INSUFFICIENT_RESOURCES,
+            FtStatus::InvalidParameter => FT_INVALID_PARAMETER,
+            FtStatus::InvalidBaudRate => FT_INVALID_BAUD_RATE,
+            FtStatus::DeviceNotOpenedForErase => FT_DEVICE_NOT_OPENED_FOR_ERASE,
+            FtStatus::DeviceNotOpenedForWrite => FT_DEVICE_NOT_OPENED_FOR_WRITE,
+            FtStatus::FailedToWriteDevice => FT_FAILED_TO_WRITE_DEVICE,
+            FtStatus::EepromReadFailed => FT_EEPROM_READ_FAILED,
+            FtStatus::EepromWriteFailed => FT_EEPROM_WRITE_FAILED,
+            FtStatus::EepromEraseFailed => FT_EEPROM_ERASE_FAILED,
+            FtStatus::EepromNotPresent => FT_EEPROM_NOT_PRESENT,
+            FtStatus::EepromNotProgrammed => FT_EEPROM_NOT_PROGRAMMED,
+            FtStatus::InvalidArgs => FT_INVALID_ARGS,
+            FtStatus::NotSupported => FT_NOT_SUPPORTED,
+            FtStatus::OtherError => FT_OTHER_ERROR,
+            FtStatus::Unknown(code) => *code,
+        }
+    }
+}
+
+impl fmt::Display for FtStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", status_to_string(self.raw()), self.raw())
+    }
+}
+
+/// What the driver was doing when a transfer failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportOp {
+    /// Enumerating or opening the channel
+    Open,
+    /// Channel setup (clock, latency, chip select)
+    Configure,
+    /// Register read (address phase included)
+    Read,
+    /// Register write
+    Write,
+    /// Raw MPSSE command transfer
+    Transfer,
+    /// Purging the USB buffers
+    Purge,
+    /// ACBUS pin access (also ACBUS chip selects)
+    Gpio,
+}
+
+impl fmt::Display for TransportOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransportOp::Open => "open",
+            TransportOp::Configure => "configure",
+            TransportOp::Read => "read",
+            TransportOp::Write => "write",
+            TransportOp::Transfer => "MPSSE transfer",
+            TransportOp::Purge => "purge",
+            TransportOp::Gpio => "GPIO access",
+        })
+    }
+}
+
+fn at_register(register: &Option<u8>) -> String {
+    register.map(|reg| format!(" at register 0x{:02X}", reg)).unwrap_or_default()
+}
 
 /// Error type for ADXL355 operations
 #[derive(Error, Debug)]
 pub enum Adxl355Error {
-    /// FTDI driver error
-    #[error("FTDI error: {status} ({description})")]
-    FtdiError {
-        status: FT_STATUS,
-        description: String,
+    /// A D2XX / libMPSSE call failed
+    #[error("SPI {op} failed{}: {status}", at_register(.register))]
+    Transport {
+        op: TransportOp,
+        register: Option<u8>,
+        status: FtStatus,
     },
 
     /// No SPI channels found
@@ -22,21 +150,29 @@ pub enum Adxl355Error {
     #[error("Invalid channel index: {0}")]
     InvalidChannel(u32),
 
-    /// Device communication error
-    #[error("Device communication error: {0}")]
-    CommunicationError(String),
-
     /// Invalid DEVID_AD response
     #[error("Invalid device ID: expected DEVID_AD=0xAD, got 0x{0:02X}")]
     InvalidDeviceId(u8),
 
+    /// Invalid DEVID_MST response
+    #[error("Invalid MEMS ID: expected DEVID_MST=0x1D, got 0x{0:02X}")]
+    InvalidMemsId(u8),
+
     /// Invalid PARTID response
     #[error("Invalid part ID: expected PARTID=0xED (ADXL355), got 0x{0:02X}")]
     InvalidPartId(u8),
 
-    /// Data transfer error
-    #[error("Data transfer error: expected {expected} bytes, transferred {actual}")]
-    TransferError { expected: u32, actual: u32 },
+    /// The sensor answered, but not as expected (readback mismatch, no ACK at any address)
+    #[error("Unexpected response from sensor: {0}")]
+    UnexpectedResponse(String),
+
+    /// The call succeeded but moved fewer bytes than requested
+    #[error("Short transfer{}: expected {expected} bytes, transferred {actual}", at_register(.register))]
+    TransferError {
+        register: Option<u8>,
+        expected: u32,
+        actual: u32,
+    },
 
     /// Invalid parameter
     #[error("Invalid parameter: {0}")]
@@ -51,19 +187,147 @@ pub enum Adxl355Error {
     #[allow(dead_code)]
     #[error("Device busy (NVM operation in progress)")]
     DeviceBusy,
+
+    /// Device stayed unreachable for the whole reconnect window
+    #[error("Device lost: {attempts} reconnect attempt(s) over {elapsed_secs:.1} s failed (first error: {cause})")]
+    ReconnectFailed {
+        attempts: u32,
+        elapsed_secs: f64,
+        cause: String,
+    },
+
+    /// Creating, writing or reading an HDF5 file failed
+    #[error("{context}: {source}")]
+    Storage {
+        context: String,
+        source: hdf5::Error,
+    },
+
+    /// File I/O outside HDF5
+    #[error("{context}: {source}")]
+    Io {
+        context: String,
+        source: std::io::Error,
+    },
+
+    /// A replayed trace does not match what the driver sends
+    #[error("Replay: {0}")]
+    Replay(String),
+
+    /// Another device on the shared bus is mid-transaction
+    #[error("SPI bus already in a transaction")]
+    BusBusy,
+
+    /// Recorded data cannot be analysed as requested
+    #[error("Analysis error: {0}")]
+    Analysis(String),
+
+    /// A collector config file or profile is invalid
+    #[error("Configuration error: {0}")]
+    Config(String),
 }
 
-impl From<FT_STATUS> for Adxl355Error {
-    fn from(status: FT_STATUS) -> Self {
-        if status == FT_OK {
-            panic!("Cannot convert FT_OK to error");
+impl Adxl355Error {
+    pub(crate) fn storage(context: impl Into<String>, source: hdf5::Error) -> Self {
+        Adxl355Error::Storage { context: context.into(), source }
+    }
+
+    /// Name the register a transport or short-transfer error belongs to
+    pub(crate) fn at_register(mut self, reg: u8) -> Self {
+        if let Adxl355Error::Transport { register, .. } | Adxl355Error::TransferError { register, .. } = &mut self {
+            *register = Some(reg);
+        }
+        self
+    }
+
+    /// FT_STATUS behind a transport error
+    pub fn status(&self) -> Option<FtStatus> {
+        match self {
+            Adxl355Error::Transport { status, .. } => Some(*status),
+            _ => None,
         }
-        Adxl355Error::FtdiError {
-            status,
-            description: status_to_string(status).to_string(),
+    }
+
+    /// USB hiccup worth retrying on the same handle
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Adxl355Error::Transport { status, .. } => matches!(
+                status,
+                FtStatus::IoError | FtStatus::InsufficientResources | FtStatus::FailedToWriteDevice | FtStatus::OtherError
+            ),
+            Adxl355Error::TransferError { .. } => true,
+            _ => false,
         }
     }
+
+    /// The adapter is gone (unplugged, or its handle is no longer valid)
+    pub fn is_disconnected(&self) -> bool {
+        match self {
+            Adxl355Error::Transport { status, .. } => matches!(
+                status,
+                FtStatus::InvalidHandle | FtStatus::DeviceNotFound | FtStatus::DeviceNotOpened
+            ),
+            Adxl355Error::NoChannelsFound | Adxl355Error::InvalidChannel(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Turn the status of a D2XX / libMPSSE call into a `Result`
+pub(crate) fn check_status(status: FT_STATUS, op: TransportOp, register: Option<u8>) -> Result<()> {
+    match FtStatus::from_raw(status) {
+        None => Ok(()),
+        Some(status) => Err(Adxl355Error::Transport { op, register, status }),
+    }
 }
 
 /// Result type for ADXL355 operations
 pub type Result<T> = std::result::Result<T, Adxl355Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transport_errors_are_classified_by_status() {
+        // (status, transient, disconnected); the operation does not matter
+        let table = [
+            (FtStatus::InvalidHandle, false, true),
+            (FtStatus::DeviceNotFound, false, true),
+            (FtStatus::DeviceNotOpened, false, true),
+            (FtStatus::IoError, true, false),
+            (FtStatus::InsufficientResources, true, false),
+            (FtStatus::InvalidParameter, false, false),
+            (FtStatus::InvalidBaudRate, false, false),
+            (FtStatus::DeviceNotOpenedForErase, false, false),
+            (FtStatus::DeviceNotOpenedForWrite, false, false),
+            (FtStatus::FailedToWriteDevice, true, false),
+            (FtStatus::EepromReadFailed, false, false),
+            (FtStatus::EepromWriteFailed, false, false),
+            (FtStatus::EepromEraseFailed, false, false),
+            (FtStatus::EepromNotPresent, false, false),
+            (FtStatus::EepromNotProgrammed, false, false),
+            (FtStatus::InvalidArgs, false, false),
+            (FtStatus::NotSupported, false, false),
+            (FtStatus::OtherError, true, false),
+            (FtStatus::Unknown(0x42), false, false),
+        ];
+        let ops = [TransportOp::Open, TransportOp::Configure, TransportOp::Read, TransportOp::Write, TransportOp::Transfer, TransportOp::Purge, TransportOp::Gpio];
+        for (status, transient, disconnected) in table {
+            assert_eq!(FtStatus::from_raw(status.raw()), Some(status));
+            for op in ops {
+                let err = Adxl355Error::Transport { op, register: Some(0x08), status };
+                assert_eq!((err.is_transient(), err.is_disconnected()), (transient, disconnected), "{}", err);
+                assert_eq!(err.status(), Some(status));
+            }
+        }
+        assert_eq!(FtStatus::from_raw(FT_OK), None);
+
+        let short = Adxl355Error::TransferError { register: Some(0x08), expected: 2, actual: 1 };
+        assert!(short.is_transient() && !short.is_disconnected());
+        assert!(Adxl355Error::NoChannelsFound.is_disconnected());
+        assert!(Adxl355Error::InvalidChannel(1).is_disconnected());
+        let invalid = Adxl355Error::InvalidParameter("rate".to_string());
+        assert!(!invalid.is_transient() && !invalid.is_disconnected());
+    }
+}
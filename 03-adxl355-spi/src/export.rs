@@ -0,0 +1,636 @@
+//! Export of recordings for tools that do not read HDF5
+//!
+//! [`export`] writes the samples of an [`Hdf5Reader`], or a time window of
+//! them, in physical units:
+//!
+//! | Format  | Layout |
+//! |---------|--------|
+//! | CSV     | `# key: value` metadata lines, a `name (units)` header row, one row per sample |
+//! | WAV     | one channel per axis at the nominal rate; 16-bit PCM with +-32767 at the range's full scale, or 32-bit float in g |
+//! | Parquet | `time` and one double column per channel; metadata and `<column>.units` as key/value metadata |
+//! | MAT v5  | `time` and one column vector per channel, plus `metadata` and `units` structs of strings |
+//!
+//! All formats are written block by block; a MAT file, which stores
+//! one variable per column, takes one pass over the rows per column. WAV
+//! assumes evenly spaced samples: gaps are closed up (the `export` tool
+//! lists them), the temperature channel is left out, and the metadata
+//! goes into the `ICMT` comment.
+//! Parquet needs the `parquet` feature.
+
+use crate::hdf5_format::Hdf5Reader;
+use crate::{Adxl355Error, ChannelInfo, Result, SensorData, TimestampedSample};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Samples read from the file at a time (and rows per Parquet row group)
+const BLOCK: usize = 262_144;
+
+/// Exported `sensor_data` columns and their raw values
+const COLUMNS: [(&str, fn(&SensorData) -> f64); 4] = [
+    ("accel_x", |d| d.accel_x as f64),
+    ("accel_y", |d| d.accel_y as f64),
+    ("accel_z", |d| d.accel_z as f64),
+    ("temperature", |d| d.temperature as f64),
+];
+
+/// Output file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Wav,
+    Parquet,
+    /// MATLAB level 5 MAT-file
+    Mat,
+}
+
+impl Format {
+    /// Parse "csv", "wav", "parquet" or "mat"
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "wav" => Some(Format::Wav),
+            "parquet" => Some(Format::Parquet),
+            "mat" => Some(Format::Mat),
+            _ => None,
+        }
+    }
+
+    /// Format named by the extension of `path`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension().and_then(|ext| ext.to_str()).and_then(Format::parse)
+    }
+
+    /// Whether this build can write the format
+    pub fn is_available(&self) -> bool {
+        *self != Format::Parquet || cfg!(feature = "parquet")
+    }
+}
+
+/// Sample encoding of WAV files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WavEncoding {
+    /// 16-bit PCM, +-32767 at the channel's full scale (clipped beyond)
+    #[default]
+    Pcm16,
+    /// 32-bit IEEE float in the channel's units
+    Float32,
+}
+
+/// What [`export`] writes
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// First timestamp to include (default: start of the recording)
+    pub start: Option<f64>,
+    /// Last timestamp to include (default: end of the recording)
+    pub end: Option<f64>,
+    pub wav_encoding: WavEncoding,
+}
+
+/// Write the samples of `reader` between `options.start` and `options.end`
+/// to `path`; returns the number of samples written
+///
+/// # Example
+/// ```no_run
+/// use ft232_adxl355_spi::export::{export, ExportOptions, Format};
+/// use ft232_adxl355_spi::Hdf5Reader;
+///
+/// let reader = Hdf5Reader::open("run.h5")?;
+/// let options = ExportOptions { start: Some(10.0), end: Some(70.0), ..Default::default() };
+/// export(&reader, "run.csv", Format::Csv, &options)?;
+/// # Ok::<(), ft232_adxl355_spi::Adxl355Error>(())
+/// ```
+pub fn export<P: AsRef<Path>>(reader: &Hdf5Reader, path: P, format: Format, options: &ExportOptions) -> Result<usize> {
+    if !format.is_available() {
+        return Err(Adxl355Error::InvalidParameter(format!("{:?} export needs the parquet feature", format)));
+    }
+    let source = Source::new(reader, options)?;
+    let mut out = Output::create(path.as_ref())?;
+    match format {
+        Format::Csv => write_csv(&source, &mut out)?,
+        Format::Wav => write_wav(&source, options.wav_encoding, &mut out)?,
+        Format::Parquet => return write_parquet(&source, out).map(|_| source.len()),
+        Format::Mat => write_mat(&source, &mut out)?,
+    }
+    out.finish()?;
+    Ok(source.len())
+}
+
+/// A `sensor_data` column with its scaling
+struct Column {
+    name: &'static str,
+    info: ChannelInfo,
+    raw: fn(&SensorData) -> f64,
+}
+
+impl Column {
+    fn value(&self, sample: &TimestampedSample) -> f64 {
+        self.info.to_physical((self.raw)(&sample.data))
+    }
+}
+
+/// Rows `start..end` of the reader's device
+struct Source<'a> {
+    reader: &'a Hdf5Reader,
+    start: usize,
+    end: usize,
+    columns: Vec<Column>,
+}
+
+impl<'a> Source<'a> {
+    fn new(reader: &'a Hdf5Reader, options: &ExportOptions) -> Result<Self> {
+        let start = match options.start {
+            Some(t) => reader.search(t, false)?,
+            None => 0,
+        };
+        let end = match options.end {
+            Some(t) => reader.search(t, true)?,
+            None => reader.get_total_samples()?,
+        };
+        let columns = COLUMNS.iter()
+            .filter_map(|&(name, raw)| Some(Column { name, info: reader.channel_info(name)?, raw }))
+            .collect();
+        Ok(Source { reader, start, end: end.max(start), columns })
+    }
+
+    fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    fn blocks(&self) -> impl Iterator<Item = Result<Vec<TimestampedSample>>> + '_ {
+        (self.start..self.end).step_by(BLOCK)
+            .map(move |from| self.reader.read_range(from, BLOCK.min(self.end - from)))
+    }
+
+    /// File metadata as key/value pairs, absent attributes left out
+    fn header(&self) -> Vec<(&'static str, String)> {
+        let metadata = self.reader.metadata();
+        let mut header = vec![
+            ("sensor_type", metadata.sensor_type.clone()),
+            ("schema_version", metadata.version.clone()),
+            ("start_time", metadata.start_time.clone()),
+            ("sample_rate_hz", metadata.sample_rate_hz.to_string()),
+            ("acquisition_mode", metadata.acquisition_mode.clone()),
+            ("range", metadata.range.clone()),
+        ];
+        let optional = [
+            ("part", metadata.part.clone()),
+            ("device", self.reader.device().map(str::to_string)),
+            ("host", metadata.host.clone()),
+            ("crate_version", metadata.crate_version.clone()),
+            ("device_serial", metadata.device_serial.clone()),
+        ];
+        header.extend(optional.into_iter().filter_map(|(key, value)| Some((key, value?))));
+        header
+    }
+}
+
+/// Buffered output file, named in write errors
+struct Output {
+    out: BufWriter<File>,
+    path: PathBuf,
+}
+
+impl Output {
+    fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to create {}", path.display()), source: e })?;
+        Ok(Output { out: BufWriter::new(file), path: path.to_path_buf() })
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out.write_all(bytes).map_err(|e| self.error(e))
+    }
+
+    fn finish(mut self) -> Result<()> {
+        self.out.flush().map_err(|e| self.error(e))
+    }
+
+    fn error(&self, source: std::io::Error) -> Adxl355Error {
+        Adxl355Error::Io { context: format!("Failed to write {}", self.path.display()), source }
+    }
+}
+
+// ============================================================================
+// CSV
+// ============================================================================
+
+fn write_csv(source: &Source, out: &mut Output) -> Result<()> {
+    let mut text = String::new();
+    for (key, value) in source.header() {
+        text.push_str(&format!("# {}: {}\n", key, value));
+    }
+    let mut names = vec!["time (s)".to_string()];
+    names.extend(source.columns.iter().map(|c| format!("{} ({})", c.name, c.info.units)));
+    text.push_str(&names.join(","));
+    text.push('\n');
+    out.write(text.as_bytes())?;
+
+    for block in source.blocks() {
+        let mut text = String::new();
+        for sample in &block? {
+            text.push_str(&sample.timestamp.to_string());
+            for column in &source.columns {
+                text.push(',');
+                text.push_str(&column.value(sample).to_string());
+            }
+            text.push('\n');
+        }
+        out.write(text.as_bytes())?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// WAV
+// ============================================================================
+
+fn write_wav(source: &Source, encoding: WavEncoding, out: &mut Output) -> Result<()> {
+    let channels: Vec<&Column> = source.columns.iter().filter(|c| c.info.full_scale > 0.0).collect();
+    let rate = source.reader.metadata().sample_rate_hz.round().max(1.0) as u32;
+
+    let mut comment: Vec<String> = source.header().into_iter()
+        .map(|(key, value)| format!("{}: {}", key, value))
+        .collect();
+    for (i, column) in channels.iter().enumerate() {
+        let scale = match encoding {
+            WavEncoding::Pcm16 => format!("32767 = {} {}", column.info.full_scale, column.info.units),
+            WavEncoding::Float32 => column.info.units.clone(),
+        };
+        comment.push(format!("channel {}: {} ({})", i + 1, column.name, scale));
+    }
+    out.write(&wav_header(channels.len() as u16, rate, encoding, source.len() as u64, &comment.join("\n"))?)?;
+
+    for block in source.blocks() {
+        let mut bytes = Vec::new();
+        for sample in &block? {
+            for column in &channels {
+                let value = column.value(sample);
+                match encoding {
+                    WavEncoding::Pcm16 => bytes.extend(pcm16(value, column.info.full_scale).to_le_bytes()),
+                    WavEncoding::Float32 => bytes.extend((value as f32).to_le_bytes()),
+                }
+            }
+        }
+        out.write(&bytes)?;
+    }
+    Ok(())
+}
+
+/// `value` as 16-bit PCM, full scale mapped to 32767
+pub(crate) fn pcm16(value: f64, full_scale: f64) -> i16 {
+    ((value / full_scale).clamp(-1.0, 1.0) * i16::MAX as f64).round() as i16
+}
+
+/// RIFF/WAVE header of `frames` samples per channel, up to the sample data
+pub(crate) fn wav_header(channels: u16, rate: u32, encoding: WavEncoding, frames: u64, comment: &str) -> Result<Vec<u8>> {
+    let (format_tag, bytes_per_sample) = match encoding {
+        WavEncoding::Pcm16 => (1u16, 2u16),
+        WavEncoding::Float32 => (3, 4),
+    };
+    let block_align = channels * bytes_per_sample;
+    let data_len = frames * block_align as u64;
+
+    let mut fmt = Vec::new();
+    fmt.extend(format_tag.to_le_bytes());
+    fmt.extend(channels.to_le_bytes());
+    fmt.extend(rate.to_le_bytes());
+    fmt.extend((rate * block_align as u32).to_le_bytes());
+    fmt.extend(block_align.to_le_bytes());
+    fmt.extend((bytes_per_sample * 8).to_le_bytes());
+
+    let mut chunks = Vec::new();
+    if encoding == WavEncoding::Float32 {
+        // Non-PCM formats carry cbSize and a fact chunk
+        fmt.extend(0u16.to_le_bytes());
+        riff_chunk(&mut chunks, b"fmt ", &fmt);
+        riff_chunk(&mut chunks, b"fact", &(frames.min(u32::MAX as u64) as u32).to_le_bytes());
+    } else {
+        riff_chunk(&mut chunks, b"fmt ", &fmt);
+    }
+    let mut info = b"INFO".to_vec();
+    let mut text = comment.as_bytes().to_vec();
+    text.push(0);
+    riff_chunk(&mut info, b"ICMT", &text);
+    riff_chunk(&mut chunks, b"LIST", &info);
+
+    let riff_len = 4 + chunks.len() as u64 + 8 + data_len;
+    if riff_len > u32::MAX as u64 {
+        return Err(Adxl355Error::InvalidParameter(
+            "Export range exceeds the 4 GB limit of WAV files, choose a shorter --start/--end".to_string()));
+    }
+    let mut header = b"RIFF".to_vec();
+    header.extend((riff_len as u32).to_le_bytes());
+    header.extend(b"WAVE");
+    header.extend(chunks);
+    header.extend(b"data");
+    header.extend((data_len as u32).to_le_bytes());
+    Ok(header)
+}
+
+/// Append a chunk, padded to an even length
+fn riff_chunk(out: &mut Vec<u8>, id: &[u8; 4], body: &[u8]) {
+    out.extend(id);
+    out.extend((body.len() as u32).to_le_bytes());
+    out.extend(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+// ============================================================================
+// PARQUET
+// ============================================================================
+
+#[cfg(feature = "parquet")]
+fn write_parquet(source: &Source, out: Output) -> Result<()> {
+    use parquet::basic::Compression;
+    use parquet::data_type::DoubleType;
+    use parquet::file::metadata::KeyValue;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    let Output { out, path } = out;
+    let error = |e: parquet::errors::ParquetError| {
+        Adxl355Error::Io { context: format!("Failed to write {}", path.display()), source: e.into() }
+    };
+
+    let fields: Vec<String> = std::iter::once("time")
+        .chain(source.columns.iter().map(|c| c.name))
+        .map(|name| format!("required double {};", name))
+        .collect();
+    let schema = parse_message_type(&format!("message recording {{ {} }}", fields.join(" "))).map_err(error)?;
+
+    let mut metadata: Vec<KeyValue> = source.header().into_iter()
+        .map(|(key, value)| KeyValue::new(key.to_string(), value))
+        .collect();
+    metadata.push(KeyValue::new("time.units".to_string(), "s".to_string()));
+    for column in &source.columns {
+        metadata.push(KeyValue::new(format!("{}.units", column.name), column.info.units.clone()));
+    }
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(metadata))
+        .build();
+
+    let mut writer = SerializedFileWriter::new(out, Arc::new(schema), Arc::new(properties)).map_err(error)?;
+
+    for block in source.blocks() {
+        let block = block?;
+        let mut values = vec![block.iter().map(|s| s.timestamp).collect::<Vec<f64>>()];
+        values.extend(source.columns.iter().map(|c| block.iter().map(|s| c.value(s)).collect()));
+
+        let mut row_group = writer.next_row_group().map_err(error)?;
+        for column_values in &values {
+            let Some(mut column) = row_group.next_column().map_err(error)? else {
+                break;
+            };
+            column.typed::<DoubleType>().write_batch(column_values, None, None).map_err(error)?;
+            column.close().map_err(error)?;
+        }
+        row_group.close().map_err(error)?;
+    }
+    writer.close().map_err(error)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_parquet(_source: &Source, _out: Output) -> Result<()> {
+    unreachable!("checked by Format::is_available")
+}
+
+// ============================================================================
+// MAT v5
+// ============================================================================
+
+const MI_INT8: u32 = 1;
+const MI_UINT16: u32 = 4;
+const MI_INT32: u32 = 5;
+const MI_UINT32: u32 = 6;
+const MI_DOUBLE: u32 = 9;
+const MI_MATRIX: u32 = 14;
+
+const MX_STRUCT_CLASS: u32 = 2;
+const MX_CHAR_CLASS: u32 = 4;
+const MX_DOUBLE_CLASS: u32 = 6;
+
+fn write_mat(source: &Source, out: &mut Output) -> Result<()> {
+    let metadata = source.reader.metadata();
+    out.write(&mat_file_header(&format!(
+        "MATLAB 5.0 MAT-file, {} recording of {}, written by {}",
+        metadata.sensor_type, metadata.start_time, crate::schema::CRATE_VERSION
+    )))?;
+    mat_column(source, out, "time", |sample| sample.timestamp)?;
+    for column in &source.columns {
+        mat_column(source, out, column.name, |sample| column.value(sample))?;
+    }
+
+    let header: Vec<(&str, String)> = source.header();
+    out.write(&mat_struct("metadata", &header)?)?;
+    let mut units = vec![("time", "s".to_string())];
+    units.extend(source.columns.iter().map(|c| (c.name, c.info.units.clone())));
+    out.write(&mat_struct("units", &units)?)
+}
+
+/// Column vector of `value` over all rows
+///
+/// MAT variables are stored one after the other, so every column is its
+/// own pass over the rows instead of holding the whole range in memory.
+fn mat_column(source: &Source, out: &mut Output, name: &str, value: impl Fn(&TimestampedSample) -> f64) -> Result<()> {
+    let rows = source.len();
+    out.write(&mat_matrix_head(name, MX_DOUBLE_CLASS, rows, 1, 8 + rows * 8)?)?;
+    out.write(&mat_tag(MI_DOUBLE, rows * 8))?;
+    for block in source.blocks() {
+        let bytes: Vec<u8> = block?.iter().flat_map(|sample| value(sample).to_le_bytes()).collect();
+        out.write(&bytes)?;
+    }
+    Ok(())
+}
+
+/// 128-byte file header: description, no subsystem data, version 0x0100,
+/// little-endian
+fn mat_file_header(text: &str) -> Vec<u8> {
+    let mut header: Vec<u8> = text.bytes().take(116).collect();
+    header.resize(116, b' ');
+    header.extend([0u8; 8]);
+    header.extend(0x0100u16.to_le_bytes());
+    header.extend(b"IM");
+    header
+}
+
+fn mat_tag(data_type: u32, len: usize) -> [u8; 8] {
+    let mut tag = [0u8; 8];
+    tag[..4].copy_from_slice(&data_type.to_le_bytes());
+    tag[4..].copy_from_slice(&(len as u32).to_le_bytes());
+    tag
+}
+
+/// Append a data element, padded to 8 bytes
+fn mat_element(out: &mut Vec<u8>, data_type: u32, body: &[u8]) {
+    out.extend(mat_tag(data_type, body.len()));
+    out.extend(body);
+    out.resize(out.len() + (8 - body.len() % 8) % 8, 0);
+}
+
+/// Start of a miMATRIX element whose flags, dimensions and name are
+/// followed by `data_len` bytes of data elements
+fn mat_matrix_head(name: &str, class: u32, rows: usize, cols: usize, data_len: usize) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    mat_element(&mut body, MI_UINT32, &[class.to_le_bytes(), [0; 4]].concat());
+    mat_element(&mut body, MI_INT32, &[(rows as i32).to_le_bytes(), (cols as i32).to_le_bytes()].concat());
+    mat_element(&mut body, MI_INT8, name.as_bytes());
+
+    let len = body.len() + data_len;
+    if len > u32::MAX as usize {
+        return Err(Adxl355Error::InvalidParameter(format!(
+            "{} exceeds the 4 GB variable limit of MAT v5 files, choose a shorter --start/--end", name)));
+    }
+    let mut head = mat_tag(MI_MATRIX, len).to_vec();
+    head.extend(body);
+    Ok(head)
+}
+
+/// 1-by-n char array
+fn mat_char(name: &str, text: &str) -> Result<Vec<u8>> {
+    let units: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+    let mut data = Vec::new();
+    mat_element(&mut data, MI_UINT16, &units);
+    let mut matrix = mat_matrix_head(name, MX_CHAR_CLASS, 1, units.len() / 2, data.len())?;
+    matrix.extend(data);
+    Ok(matrix)
+}
+
+/// 1-by-1 struct with a char field per entry (names cut to 31 characters)
+fn mat_struct(name: &str, fields: &[(&str, String)]) -> Result<Vec<u8>> {
+    const NAME_LEN: usize = 32;
+    let mut data = Vec::new();
+    // Field name length, as a small data element
+    data.extend(((4u32 << 16) | MI_INT32).to_le_bytes());
+    data.extend((NAME_LEN as i32).to_le_bytes());
+    let mut names = Vec::new();
+    for (field, _) in fields {
+        let mut bytes: Vec<u8> = field.bytes().take(NAME_LEN - 1).collect();
+        bytes.resize(NAME_LEN, 0);
+        names.extend(bytes);
+    }
+    mat_element(&mut data, MI_INT8, &names);
+    for (_, value) in fields {
+        data.extend(mat_char("", value)?);
+    }
+    let mut matrix = mat_matrix_head(name, MX_STRUCT_CLASS, 1, 1, data.len())?;
+    matrix.extend(data);
+    Ok(matrix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u32_at(bytes: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+    }
+
+    /// Name and row count of each variable of a MAT file
+    fn mat_variables(bytes: &[u8]) -> Vec<(String, u32)> {
+        let mut variables = Vec::new();
+        let mut at = 128;
+        while at < bytes.len() {
+            let body = &bytes[at + 8..at + 8 + u32_at(bytes, at + 4) as usize];
+            // Array flags (16 bytes), then dimensions, then the name
+            let name_len = u32_at(body, 36) as usize;
+            variables.push((String::from_utf8(body[40..40 + name_len].to_vec()).unwrap(), u32_at(body, 24)));
+            at += 8 + body.len();
+        }
+        variables
+    }
+
+    #[test]
+    fn format_from_name_and_extension() {
+        assert_eq!(Format::parse("WAV"), Some(Format::Wav));
+        assert_eq!(Format::from_path(Path::new("run.mat")), Some(Format::Mat));
+        assert_eq!(Format::from_path(Path::new("run.parquet")), Some(Format::Parquet));
+        assert_eq!(Format::from_path(Path::new("run.h5")), None);
+    }
+
+    #[test]
+    fn pcm16_maps_full_scale_and_clips() {
+        assert_eq!(pcm16(2.0, 2.0), 32767);
+        assert_eq!(pcm16(-1.0, 2.0), -16384);
+        assert_eq!(pcm16(0.0, 2.0), 0);
+        assert_eq!(pcm16(-5.0, 2.0), -32767);
+    }
+
+    #[test]
+    fn wav_header_sizes() {
+        let header = wav_header(3, 1000, WavEncoding::Pcm16, 10, "odd").unwrap();
+        assert_eq!(&header[..4], b"RIFF");
+        assert_eq!(&header[8..12], b"WAVE");
+        // fmt: PCM, 3 channels, 1000 Hz, 6 bytes per frame, 16 bits
+        assert_eq!(&header[12..16], b"fmt ");
+        assert_eq!(u32_at(&header, 16), 16);
+        assert_eq!(u32_at(&header, 24), 1000);
+        assert_eq!(u32_at(&header, 28), 6000);
+        // The data chunk is last and the RIFF size covers the samples
+        let data_at = header.len() - 8;
+        assert_eq!(&header[data_at..data_at + 4], b"data");
+        assert_eq!(u32_at(&header, data_at + 4), 60);
+        assert_eq!(u32_at(&header, 4) as usize, header.len() - 8 + 60);
+
+        let float = wav_header(3, 1000, WavEncoding::Float32, 10, "").unwrap();
+        assert_eq!(u32_at(&float, 16), 18);
+        assert_eq!(&float[38..42], b"fact");
+        assert_eq!(u32_at(&float, float.len() - 4), 120);
+    }
+
+    #[test]
+    fn mat_elements_are_8_byte_aligned() {
+        assert_eq!(mat_file_header("MATLAB 5.0 MAT-file").len(), 128);
+
+        let text = mat_char("units", "deg").unwrap();
+        assert_eq!(u32_at(&text, 0), MI_MATRIX);
+        assert_eq!(u32_at(&text, 4) as usize, text.len() - 8);
+        assert_eq!(text.len() % 8, 0);
+
+        let fields = [("start_time", "2024-01-01T00:00:00Z".to_string()), ("range", "2g".to_string())];
+        let matrix = mat_struct("metadata", &fields).unwrap();
+        assert_eq!(u32_at(&matrix, 4) as usize, matrix.len() - 8);
+        assert_eq!(matrix.len() % 8, 0);
+    }
+
+    #[test]
+    fn mat_export_of_one_device_group() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("adxl355-export-mat-{}.h5", std::process::id()));
+        let output = input.with_extension("mat");
+        let devices = vec!["dbus3".to_string(), "dbus4".to_string()];
+        let storage = crate::StorageOptions::default();
+        let mut writer = crate::Hdf5Writer::create_with(&input, "fifo", 1000.0, "2g", &devices, &storage).unwrap();
+        let samples = |n: i32| -> Vec<TimestampedSample> {
+            (0..n)
+                .map(|i| TimestampedSample {
+                    timestamp: i as f64 / 1000.0,
+                    data: SensorData { accel_x: i, accel_y: 0, accel_z: 256_000, temperature: 1885 },
+                })
+                .collect()
+        };
+        writer.append_device_batch(0, &samples(30)).unwrap();
+        writer.append_device_batch(1, &samples(12)).unwrap();
+        writer.close().unwrap();
+
+        let reader = Hdf5Reader::open_device(&input, Some("dbus4")).unwrap();
+        let written = export(&reader, &output, Format::Mat, &ExportOptions::default()).unwrap();
+        drop(reader);
+        let bytes = std::fs::read(&output).unwrap();
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+
+        assert_eq!(written, 12);
+        let variables = mat_variables(&bytes);
+        assert_eq!(variables.len(), 7);
+        assert_eq!(variables[0], ("time".to_string(), 12));
+        assert_eq!(variables[4], ("temperature".to_string(), 12));
+        assert_eq!(variables[5].0, "metadata");
+    }
+}
@@ -48,7 +48,14 @@ pub const SPI_TRANSFER_OPTIONS_CHIPSELECT_DISABLE: DWORD = 0x00000004;
 
 // SPI Config Options
 pub const SPI_CONFIG_OPTION_MODE0: DWORD = 0x00000000; // CPOL=0, CPHA=0
+pub const SPI_CONFIG_OPTION_MODE1: DWORD = 0x00000001; // CPOL=0, CPHA=1
+pub const SPI_CONFIG_OPTION_MODE2: DWORD = 0x00000002; // CPOL=1, CPHA=0
+pub const SPI_CONFIG_OPTION_MODE3: DWORD = 0x00000003; // CPOL=1, CPHA=1
 pub const SPI_CONFIG_OPTION_CS_DBUS3: DWORD = 0x00000000;
+pub const SPI_CONFIG_OPTION_CS_DBUS4: DWORD = 0x00000004;
+pub const SPI_CONFIG_OPTION_CS_DBUS5: DWORD = 0x00000008;
+pub const SPI_CONFIG_OPTION_CS_DBUS6: DWORD = 0x0000000C;
+pub const SPI_CONFIG_OPTION_CS_DBUS7: DWORD = 0x00000010;
 pub const SPI_CONFIG_OPTION_CS_ACTIVELOW: DWORD = 0x00000020;
 
 // FT_DEVICE_LIST_INFO_NODE structure
@@ -83,6 +90,7 @@ extern "C" {
     pub fn Init_libMPSSE();
     pub fn Cleanup_libMPSSE();
     pub fn SPI_GetNumChannels(numChannels: *mut DWORD) -> FT_STATUS;
+    pub fn SPI_GetChannelInfo(index: DWORD, chanInfo: *mut FT_DEVICE_LIST_INFO_NODE) -> FT_STATUS;
     pub fn SPI_OpenChannel(index: DWORD, handle: *mut FT_HANDLE) -> FT_STATUS;
     pub fn SPI_InitChannel(handle: FT_HANDLE, config: *mut ChannelConfig) -> FT_STATUS;
     pub fn SPI_CloseChannel(handle: FT_HANDLE) -> FT_STATUS;
@@ -111,6 +119,38 @@ extern "C" {
         sizeTransferred: LPDWORD,
         options: DWORD,
     ) -> FT_STATUS;
+
+    pub fn SPI_ChangeCS(handle: FT_HANDLE, configOptions: DWORD) -> FT_STATUS;
+
+    // ACBUS0-7 (high byte) general purpose I/O
+    pub fn FT_WriteGPIO(handle: FT_HANDLE, dir: UCHAR, value: UCHAR) -> FT_STATUS;
+    pub fn FT_ReadGPIO(handle: FT_HANDLE, value: *mut UCHAR) -> FT_STATUS;
+}
+
+// FT_Purge mask bits
+pub const FT_PURGE_RX: DWORD = 1;
+pub const FT_PURGE_TX: DWORD = 2;
+
+// D2XX calls used by the raw MPSSE path (same FT_HANDLE as libMPSSE)
+#[link(name = "ftd2xx")]
+extern "C" {
+    pub fn FT_Write(
+        handle: FT_HANDLE,
+        buffer: *mut c_void,
+        bytesToWrite: DWORD,
+        bytesWritten: LPDWORD,
+    ) -> FT_STATUS;
+
+    pub fn FT_Read(
+        handle: FT_HANDLE,
+        buffer: *mut c_void,
+        bytesToRead: DWORD,
+        bytesReturned: LPDWORD,
+    ) -> FT_STATUS;
+
+    pub fn FT_GetQueueStatus(handle: FT_HANDLE, rxBytes: LPDWORD) -> FT_STATUS;
+
+    pub fn FT_Purge(handle: FT_HANDLE, mask: DWORD) -> FT_STATUS;
 }
 
 pub fn status_to_string(status: FT_STATUS) -> &'static str {
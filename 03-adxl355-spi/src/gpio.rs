@@ -0,0 +1,271 @@
+//! Spare FT232H pins as digital I/O
+//!
+//! SPI occupies ADBUS0-2 plus the chip select on ADBUS3-7. The eight ACBUS
+//! pins are free, apart from chip selects a [`crate::SpiBus`] puts there, and
+//! libMPSSE reaches them through `FT_WriteGPIO` / `FT_ReadGPIO`.
+//! [`Gpio`] wraps those calls for one open channel. It is a cheap handle
+//! that can be cloned and used between sensor reads (e.g. inside a
+//! streaming callback). Directions and output levels survive a reconnect;
+//! once the sensor is dropped every call fails with FT_INVALID_HANDLE.
+//!
+//! ```no_run
+//! use ft232_adxl355_spi::{Adxl355, Edge};
+//! use std::time::Duration;
+//!
+//! let sensor = Adxl355::new(0)?;
+//! let gpio = sensor.gpio();
+//! gpio.set_output(4, false)?;                 // ACBUS4: marker for a camera
+//! gpio.set_input(3)?;                         // ACBUS3: start button
+//! if gpio.wait_for_edge(3, Edge::Rising, Some(Duration::from_secs(10)))? {
+//!     gpio.toggle(4)?;
+//! }
+//! # Ok::<(), ft232_adxl355_spi::Adxl355Error>(())
+//! ```
+
+use crate::error::{check_status, Adxl355Error, FtStatus, Result, TransportOp};
+use crate::ffi::*;
+use std::cell::RefCell;
+use std::ptr;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// Number of ACBUS lines
+pub const GPIO_PINS: u8 = 8;
+
+/// How often `wait_for_edge` samples the pin
+const EDGE_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Error for pin access after the channel was closed
+const DETACHED: Adxl355Error = Adxl355Error::Transport {
+    op: TransportOp::Gpio,
+    register: None,
+    status: FtStatus::InvalidHandle,
+};
+
+/// Direction and output levels of ACBUS, shared by the driver and its [`Gpio`] handles
+pub(crate) struct GpioState {
+    handle: FT_HANDLE,
+    dir: u8,
+    value: u8,
+    /// ACBUS chip selects of a shared SPI bus, not available as GPIO
+    reserved: u8,
+}
+
+impl GpioState {
+    pub(crate) fn new(handle: FT_HANDLE) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(GpioState {
+            handle,
+            dir: 0,
+            value: 0,
+            reserved: 0,
+        }))
+    }
+
+    /// Point at a freshly opened channel and re-apply the pin setup
+    pub(crate) fn attach(&mut self, handle: FT_HANDLE) -> Result<()> {
+        self.handle = handle;
+        if self.dir != 0 {
+            self.write(self.dir, self.value)?;
+        }
+        Ok(())
+    }
+
+    /// Forget the handle; the channel is being closed
+    pub(crate) fn detach(&mut self) {
+        self.handle = ptr::null_mut();
+    }
+
+    pub(crate) fn write(&mut self, dir: u8, value: u8) -> Result<()> {
+        if self.handle.is_null() {
+            return Err(DETACHED);
+        }
+        let status = unsafe { FT_WriteGPIO(self.handle, dir, value) };
+        check_status(status, TransportOp::Gpio, None)?;
+        self.dir = dir;
+        self.value = value;
+        Ok(())
+    }
+
+    fn read(&self) -> Result<u8> {
+        if self.handle.is_null() {
+            return Err(DETACHED);
+        }
+        let mut value: UCHAR = 0;
+        let status = unsafe { FT_ReadGPIO(self.handle, &mut value) };
+        check_status(status, TransportOp::Gpio, None)?;
+        Ok(value)
+    }
+
+    /// Keep pins used by the driver itself away from [`Gpio`]
+    pub(crate) fn reserve(&mut self, mask: u8) {
+        self.reserved |= mask;
+    }
+
+    /// Current (direction, value) bytes
+    pub(crate) fn levels(&self) -> (u8, u8) {
+        (self.dir, self.value)
+    }
+}
+
+/// Signal transition to wait for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Rising,
+    Falling,
+    Both,
+}
+
+impl Edge {
+    /// Short name used on the command line and in HDF5 metadata
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Edge::Rising => "rising",
+            Edge::Falling => "falling",
+            Edge::Both => "both",
+        }
+    }
+
+    /// Parse a name produced by [`Edge::as_str`]
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rising" => Some(Edge::Rising),
+            "falling" => Some(Edge::Falling),
+            "both" => Some(Edge::Both),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn matches(&self, from: bool, to: bool) -> bool {
+        match self {
+            Edge::Rising => !from && to,
+            Edge::Falling => from && !to,
+            Edge::Both => from != to,
+        }
+    }
+}
+
+/// Parse a pin name: "acbus3", "c3" or just "3"
+pub fn parse_pin(text: &str) -> Option<u8> {
+    let text = text.trim().to_ascii_lowercase();
+    let number = text.strip_prefix("acbus")
+        .or_else(|| text.strip_prefix('c'))
+        .unwrap_or(&text);
+    number.parse().ok().filter(|pin| *pin < GPIO_PINS)
+}
+
+/// ACBUS0-7 of an open channel
+#[derive(Clone)]
+pub struct Gpio {
+    state: Rc<RefCell<GpioState>>,
+}
+
+impl Gpio {
+    pub(crate) fn new(state: Rc<RefCell<GpioState>>) -> Self {
+        Gpio { state }
+    }
+
+    fn mask(&self, pin: u8) -> Result<u8> {
+        if pin >= GPIO_PINS {
+            return Err(Adxl355Error::InvalidParameter(format!("GPIO pin must be 0-7 (ACBUS), got {}", pin)));
+        }
+        if self.state.borrow().reserved & (1 << pin) != 0 {
+            return Err(Adxl355Error::InvalidParameter(format!("ACBUS{} is a chip select of the SPI bus", pin)));
+        }
+        Ok(1 << pin)
+    }
+
+    /// Make `pin` an output at the given level
+    pub fn set_output(&self, pin: u8, high: bool) -> Result<()> {
+        let mask = self.mask(pin)?;
+        let mut state = self.state.borrow_mut();
+        let value = if high { state.value | mask } else { state.value & !mask };
+        let dir = state.dir | mask;
+        state.write(dir, value)
+    }
+
+    /// Make `pin` an input (the power-on state)
+    pub fn set_input(&self, pin: u8) -> Result<()> {
+        let mask = self.mask(pin)?;
+        let mut state = self.state.borrow_mut();
+        let (dir, value) = (state.dir & !mask, state.value & !mask);
+        state.write(dir, value)
+    }
+
+    /// Drive an output pin
+    pub fn write(&self, pin: u8, high: bool) -> Result<()> {
+        let mask = self.mask(pin)?;
+        if self.state.borrow().dir & mask == 0 {
+            return Err(Adxl355Error::InvalidParameter(format!("ACBUS{} is not an output", pin)));
+        }
+        self.set_output(pin, high)
+    }
+
+    /// Invert an output pin; returns the new level
+    pub fn toggle(&self, pin: u8) -> Result<bool> {
+        let high = self.state.borrow().value & self.mask(pin)? == 0;
+        self.write(pin, high)?;
+        Ok(high)
+    }
+
+    /// Level of one pin (outputs read back what is driven)
+    pub fn read(&self, pin: u8) -> Result<bool> {
+        let mask = self.mask(pin)?;
+        Ok(self.read_all()? & mask != 0)
+    }
+
+    /// Levels of all eight ACBUS pins, bit n = ACBUSn
+    pub fn read_all(&self) -> Result<u8> {
+        self.state.borrow().read()
+    }
+
+    /// Poll `pin` until `edge` occurs; `false` if `timeout` ran out first
+    ///
+    /// Sampled every millisecond, so pulses shorter than the USB round-trip
+    /// (~1 ms) can be missed.
+    pub fn wait_for_edge(&self, pin: u8, edge: Edge, timeout: Option<Duration>) -> Result<bool> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut detector = self.edge_detector(pin, edge)?;
+        loop {
+            if detector.poll()? {
+                return Ok(true);
+            }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                return Ok(false);
+            }
+            std::thread::sleep(EDGE_POLL_INTERVAL);
+        }
+    }
+
+    /// Non-blocking edge detection, for polling from a read loop
+    pub fn edge_detector(&self, pin: u8, edge: Edge) -> Result<EdgeDetector> {
+        let last = self.read(pin)?;
+        Ok(EdgeDetector { gpio: self.clone(), pin, edge, last })
+    }
+}
+
+/// Remembers a pin's level between polls and reports matching transitions
+pub struct EdgeDetector {
+    gpio: Gpio,
+    pin: u8,
+    edge: Edge,
+    last: bool,
+}
+
+impl EdgeDetector {
+    /// Sample the pin; `true` if it made the wanted transition since the last poll
+    pub fn poll(&mut self) -> Result<bool> {
+        let level = self.gpio.read(self.pin)?;
+        let fired = self.edge.matches(self.last, level);
+        self.last = level;
+        Ok(fired)
+    }
+
+    /// Level seen by the last poll
+    pub fn level(&self) -> bool {
+        self.last
+    }
+
+    pub fn pin(&self) -> u8 {
+        self.pin
+    }
+}
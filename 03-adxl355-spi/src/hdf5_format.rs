@@ -1,9 +1,43 @@
 //! HDF5 file format for ADXL355 sensor data storage
+//!
+//! A writer switched to SWMR mode with [`Hdf5Writer::start_swmr`] can be
+//! read concurrently: [`Hdf5Reader::open_swmr`] binds to one device group
+//! and [`Hdf5Reader::follow`] returns whatever has been appended since,
+//! moving on to new segment files when opened on a rotating session.
+//! [`Hdf5Writer::close`] leaves a `completion` attribute behind; see
+//! [`crate::journal`] for files that never got one.
+//! Recordings rotated by [`Hdf5Writer::create_session`] are read back through
+//! their manifest (see [`crate::session`]). The layout itself, and how it
+//! changed between versions, is described in [`crate::schema`].
+//! [`Hdf5Reader::read_overview`] serves plots from the per-device bins of
+//! [`crate::overview`] instead of the full sample columns. Annotations
+//! live in the event track of [`crate::events`]. Chunking, compression and
+//! the timestamp encoding of the sample columns are set with
+//! [`crate::storage::StorageOptions`].
 
-use crate::{Adxl355Error, Result, SensorData};
-use hdf5::{Dataset, File, Group};
-use std::path::Path;
-use std::time::Instant;
+use crate::events::{self, Event, EventTrack};
+use crate::journal::{journal_path, Journal, JournalHeader};
+use crate::overview::{self, Level, OverviewBin, OverviewWriter, Rebinner};
+use crate::schema::{self, ChannelInfo, CRATE_VERSION, SCHEMA_VERSION};
+use crate::session::{is_manifest, Manifest, Rotation, Session};
+use crate::storage::{self, StorageOptions, TimestampFormat};
+use crate::{Adxl355Error, DeviceInfo, Range, Result, SensorData, TemperatureCalibration};
+use hdf5::types::{FixedUnicode, TypeDescriptor, VarLenUnicode};
+use hdf5::{Dataset, File, Group, Location};
+use std::fs::OpenOptions;
+use std::ops::RangeBounds;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// SWMR readers see new samples after at most this long
+const SWMR_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The file is synced to disk and the journal emptied at most this often
+/// while SWMR flushes run
+const JOURNAL_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rows read at a time when binning raw samples
+const READ_BLOCK: usize = 65_536;
 
 /// Sample with timestamp
 #[derive(Debug, Clone)]
@@ -21,6 +55,71 @@ pub struct Metadata {
     pub sensor_type: String,
     pub range: String,
     pub version: String,
+    /// Exact part ("ADXL355", "ADXL357"), absent in older files
+    pub part: Option<String>,
+    /// Silicon revision (REVID), absent in older files
+    pub revision: Option<u8>,
+    /// Device groups under `sensor_data`, empty for single-sensor files
+    pub devices: Vec<String>,
+    /// Machine the recording was made on (schema 2)
+    pub host: Option<String>,
+    /// Library that wrote the file (schema 2)
+    pub crate_version: Option<String>,
+    /// FT232H serial number (schema 2, if the driver reported one)
+    pub device_serial: Option<String>,
+}
+
+/// How a file was finished, read from its `completion` metadata attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Completion {
+    /// Finished by [`Hdf5Writer::close`]
+    Complete,
+    /// Repaired by [`crate::journal::recover`]
+    Recovered,
+    /// Attribute missing: still recording, interrupted, or written before
+    /// completion markers were introduced
+    Unknown,
+}
+
+impl Completion {
+    fn marker(self) -> &'static str {
+        match self {
+            Completion::Complete => "complete",
+            Completion::Recovered => "recovered",
+            Completion::Unknown => "unknown",
+        }
+    }
+
+    fn from_marker(marker: &str) -> Self {
+        match marker {
+            "complete" => Completion::Complete,
+            "recovered" => Completion::Recovered,
+            _ => Completion::Unknown,
+        }
+    }
+}
+
+/// Gap in the recording, e.g. while the sensor was being reconnected
+#[derive(Debug, Clone)]
+pub struct Discontinuity {
+    /// Seconds since collection start, same clock as the sample timestamps
+    pub start_time: f64,
+    pub end_time: f64,
+    pub cause: String,
+}
+
+impl Discontinuity {
+    pub fn duration(&self) -> f64 {
+        self.end_time - self.start_time
+    }
+}
+
+/// Datasets of the `discontinuities` group, created on first use
+struct DiscontinuityHandles {
+    start_time: Dataset,
+    end_time: Dataset,
+    cause: Dataset,
+    count: usize,
 }
 
 /// Handles for HDF5 datasets
@@ -30,82 +129,629 @@ struct DatasetHandles {
     accel_y: Dataset,
     accel_z: Dataset,
     temperature: Dataset,
+    time: TimestampFormat,
+    /// Rows per chunk; the time search steps through `timestamps` in these units
+    chunk_size: usize,
+}
+
+impl DatasetHandles {
+    fn open(group: &Group) -> Result<Self> {
+        let open = |name: &str| group.dataset(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to open {}", name), e));
+        let timestamps = open("timestamps")?;
+        Ok(DatasetHandles {
+            time: TimestampFormat::of(&timestamps),
+            chunk_size: timestamps.chunk().and_then(|chunk| chunk.first().copied()).unwrap_or(storage::DEFAULT_CHUNK_SIZE),
+            timestamps,
+            accel_x: open("accel_x")?,
+            accel_y: open("accel_y")?,
+            accel_z: open("accel_z")?,
+            temperature: open("temperature")?,
+        })
+    }
+
+    /// Length of the shortest column
+    fn len(&self) -> usize {
+        self.all().iter().map(|d| d.size()).min().unwrap_or(0)
+    }
+
+    /// Timestamps of rows `start..end` in seconds
+    fn read_timestamps(&self, start: usize, end: usize) -> Result<Vec<f64>> {
+        match self.time {
+            TimestampFormat::Seconds => {
+                let seconds: Vec<f64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Adxl355Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(seconds)
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = self.timestamps.read_slice_1d(start..end)
+                    .map_err(|e| Adxl355Error::storage("Failed to read timestamps", e))?
+                    .to_vec();
+                Ok(ticks.into_iter().map(|t| storage::from_ticks(t, rate)).collect())
+            }
+        }
+    }
+
+    /// Rows `start..end` as samples
+    fn read(&self, start: usize, end: usize) -> Result<Vec<TimestampedSample>> {
+        let timestamps = self.read_timestamps(start, end)?;
+        let accel_x: Vec<i32> = self.accel_x.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_x", e))?
+            .to_vec();
+        let accel_y: Vec<i32> = self.accel_y.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_y", e))?
+            .to_vec();
+        let accel_z: Vec<i32> = self.accel_z.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read accel_z", e))?
+            .to_vec();
+        let temperature: Vec<u16> = self.temperature.read_slice_1d(start..end)
+            .map_err(|e| Adxl355Error::storage("Failed to read temperature", e))?
+            .to_vec();
+
+        let samples = timestamps.into_iter()
+            .zip(accel_x)
+            .zip(accel_y)
+            .zip(accel_z)
+            .zip(temperature)
+            .map(|((((ts, ax), ay), az), temp)| {
+                TimestampedSample {
+                    timestamp: ts,
+                    data: SensorData {
+                        accel_x: ax,
+                        accel_y: ay,
+                        accel_z: az,
+                        temperature: temp,
+                    },
+                }
+            })
+            .collect();
+
+        Ok(samples)
+    }
+
+    fn get(&self, name: &str) -> Option<&Dataset> {
+        match name {
+            "timestamps" => Some(&self.timestamps),
+            "accel_x" => Some(&self.accel_x),
+            "accel_y" => Some(&self.accel_y),
+            "accel_z" => Some(&self.accel_z),
+            "temperature" => Some(&self.temperature),
+            _ => None,
+        }
+    }
+
+    /// Tag each column with its [`ChannelInfo`] attributes; `timestamps`
+    /// gets the scaling of its own encoding
+    fn describe(&self, columns: &[(&str, ChannelInfo)]) -> Result<()> {
+        for (name, info) in columns {
+            match self.get(name) {
+                Some(dataset) if *name == "timestamps" => self.time.channel().write(dataset)?,
+                Some(dataset) => info.write(dataset)?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn all(&self) -> [&Dataset; 5] {
+        [&self.timestamps, &self.accel_x, &self.accel_y, &self.accel_z, &self.temperature]
+    }
 }
 
 /// HDF5 writer for sensor data collection
 pub struct Hdf5Writer {
     file: File,
-    datasets: DatasetHandles,
+    /// One entry per device group (a single entry for single-sensor files)
+    datasets: Vec<DatasetHandles>,
     start_time: Instant,
-    sample_count: usize,
+    discontinuities: Option<DiscontinuityHandles>,
+    events: Option<EventTrack>,
+    sample_counts: Vec<usize>,
+    swmr: bool,
+    last_flush: Instant,
+    last_checkpoint: Instant,
+    path: PathBuf,
+    metadata: Metadata,
+    journal: Option<Journal>,
+    session: Option<Session>,
+    /// Attributes from `write_metadata_*`, copied into each new segment
+    extra_metadata: Vec<(String, ExtraAttr)>,
+    temperature_calibration: Option<TemperatureCalibration>,
+    /// Per device group; `None` where a repaired file has no overview
+    overviews: Vec<Option<OverviewWriter>>,
+    /// Layout of the sample columns, kept for new segments
+    storage: StorageOptions,
+}
+
+enum ExtraAttr {
+    Str(String),
+    F64(f64),
 }
 
 impl Hdf5Writer {
     /// Create a new HDF5 file for data collection
     pub fn create<P: AsRef<Path>>(path: P, mode: &str, rate: f64, range: &str) -> Result<Self> {
-        let file = File::create(path)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create HDF5 file: {}", e)))?;
+        Self::create_with(path, mode, rate, range, &[], &StorageOptions::default())
+    }
+
+    /// Create a file holding one group per sensor of a shared bus
+    ///
+    /// Device `i` is written to `sensor_data/<devices[i]>` with
+    /// [`Hdf5Writer::append_device_batch`]; the names are also listed in the
+    /// `devices` metadata attribute.
+    pub fn create_multi<P: AsRef<Path>>(path: P, mode: &str, rate: f64, range: &str, devices: &[String]) -> Result<Self> {
+        if devices.is_empty() {
+            return Err(Adxl355Error::InvalidParameter("No device groups given".to_string()));
+        }
+        Self::create_with(path, mode, rate, range, devices, &StorageOptions::default())
+    }
+
+    /// Like [`create`](Self::create), or [`create_multi`](Self::create_multi)
+    /// when `devices` is not empty, with the sample columns laid out per
+    /// `storage`
+    pub fn create_with<P: AsRef<Path>>(
+        path: P, mode: &str, rate: f64, range: &str, devices: &[String], storage: &StorageOptions,
+    ) -> Result<Self> {
+        storage.validate()?;
+        let file = File::with_options()
+            .with_fapl(|p| p.libver_latest())
+            .create(path.as_ref())
+            .map_err(|e| Adxl355Error::storage("Failed to create HDF5 file", e))?;
 
         // Create metadata group
         let metadata_group = file.create_group("metadata")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create metadata group: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to create metadata group", e))?;
 
         // Write metadata attributes
         let start_time = chrono::Local::now().to_rfc3339();
         let write_str_attr = |group: &Group, name: &str, value: &str| -> Result<()> {
-            let vlu: hdf5::types::VarLenUnicode = value.parse().unwrap();
+            let vlu = attr_text(name, value)?;
             group.new_attr::<hdf5::types::VarLenUnicode>()
                 .create(name)
                 .and_then(|attr| attr.write_scalar(&vlu))
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write {}: {}", name, e)))
+                .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))
         };
 
         write_str_attr(&metadata_group, "start_time", &start_time)?;
         write_str_attr(&metadata_group, "acquisition_mode", mode)?;
         write_str_attr(&metadata_group, "sensor_type", "adxl355")?;
         write_str_attr(&metadata_group, "range", range)?;
-        write_str_attr(&metadata_group, "version", "1.0")?;
+        write_str_attr(&metadata_group, "version", SCHEMA_VERSION)?;
+        write_str_attr(&metadata_group, "crate_version", CRATE_VERSION)?;
+        let host = schema::host_name();
+        if let Some(host) = &host {
+            write_str_attr(&metadata_group, "host", host)?;
+        }
+        if !devices.is_empty() {
+            write_str_attr(&metadata_group, "devices", &devices.join(","))?;
+        }
 
         metadata_group.new_attr::<f64>()
             .create("sample_rate_hz")
             .and_then(|attr| attr.write_scalar(&rate))
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write sample_rate_hz: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to write sample_rate_hz", e))?;
 
         // Create sensor_data group
         let data_group = file.create_group("sensor_data")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create sensor_data group: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to create sensor_data group", e))?;
 
-        let chunk_size = 1024;
+        let datasets = if devices.is_empty() {
+            vec![Self::create_datasets(&data_group, storage)?]
+        } else {
+            devices.iter()
+                .map(|name| {
+                    let group = data_group.create_group(name)
+                        .map_err(|e| Adxl355Error::storage(format!("Failed to create group {}", name), e))?;
+                    Self::create_datasets(&group, storage)
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+        let scale_range = Range::from_label(range).unwrap_or(Range::G2);
+        let columns = schema::channels(scale_range, &TemperatureCalibration::NOMINAL);
+        for handles in &datasets {
+            handles.describe(&columns)?;
+        }
 
-        let timestamps = Self::create_dataset::<f64>(&data_group, "timestamps", chunk_size)?;
-        let accel_x = Self::create_dataset::<i32>(&data_group, "accel_x", chunk_size)?;
-        let accel_y = Self::create_dataset::<i32>(&data_group, "accel_y", chunk_size)?;
-        let accel_z = Self::create_dataset::<i32>(&data_group, "accel_z", chunk_size)?;
-        let temperature = Self::create_dataset::<u16>(&data_group, "temperature", chunk_size)?;
+        let overview_group = file.create_group("overview")
+            .map_err(|e| Adxl355Error::storage("Failed to create overview group", e))?;
+        let overviews = if devices.is_empty() {
+            vec![Some(OverviewWriter::create(&overview_group)?)]
+        } else {
+            devices.iter()
+                .map(|name| {
+                    let group = overview_group.create_group(name)
+                        .map_err(|e| Adxl355Error::storage(format!("Failed to create overview group {}", name), e))?;
+                    Ok(Some(OverviewWriter::create(&group)?))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
 
-        let datasets = DatasetHandles {
-            timestamps,
-            accel_x,
-            accel_y,
-            accel_z,
-            temperature,
+        Ok(Self {
+            file,
+            sample_counts: vec![0; datasets.len()],
+            datasets,
+            start_time: Instant::now(),
+            discontinuities: None,
+            events: None,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.as_ref().to_path_buf(),
+            metadata: Metadata {
+                start_time,
+                sample_rate_hz: rate,
+                acquisition_mode: mode.to_string(),
+                sensor_type: "adxl355".to_string(),
+                range: range.to_string(),
+                version: SCHEMA_VERSION.to_string(),
+                part: None,
+                revision: None,
+                devices: devices.to_vec(),
+                host,
+                crate_version: Some(CRATE_VERSION.to_string()),
+                device_serial: None,
+            },
+            journal: None,
+            session: None,
+            extra_metadata: Vec::new(),
+            temperature_calibration: None,
+            overviews,
+            storage: *storage,
+        })
+    }
+
+    /// Create a recording that rotates to a new file per `rotation`
+    ///
+    /// `devices` as for [`create_multi`](Self::create_multi), or empty for a
+    /// single sensor. Segments are `data_0001.h5`, `data_0002.h5`, ... next
+    /// to `data.manifest`; sample and gap counts span the whole session.
+    /// Every segment is laid out per `storage`.
+    pub fn create_session<P: AsRef<Path>>(
+        output: P, mode: &str, rate: f64, range: &str, devices: &[String], rotation: Rotation, storage: &StorageOptions,
+    ) -> Result<Self> {
+        let mut session = Session::new(output.as_ref(), rotation, devices.len().max(1));
+        let mut writer = Self::create_with(session.begin_segment(), mode, rate, range, devices, storage)?;
+        session.set_start_time(&writer.metadata.start_time);
+        session.save_manifest()?;
+        writer.session = Some(session);
+        Ok(writer)
+    }
+
+    /// Manifest of a session writer
+    pub fn manifest_path(&self) -> Option<PathBuf> {
+        self.session.as_ref().map(Session::manifest_path)
+    }
+
+    /// File currently being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Close this segment and continue the session in a new file
+    fn rotate(&mut self) -> Result<()> {
+        let Some(mut session) = self.session.take() else {
+            return Ok(());
         };
+        for (before, count) in session.samples_before.iter_mut().zip(&self.sample_counts) {
+            *before += count;
+        }
+        session.gaps_before += self.segment_discontinuities();
+        session.events_before += self.segment_events();
+
+        let metadata = &self.metadata;
+        let mut next = Self::create_with(
+            session.begin_segment(), &metadata.acquisition_mode, metadata.sample_rate_hz, &metadata.range, &metadata.devices,
+            &self.storage,
+        )?;
+        next.set_start_time(&metadata.start_time)?;
+        next.start_time = self.start_time;
+        for (name, value) in &self.extra_metadata {
+            match value {
+                ExtraAttr::Str(value) => next.write_metadata_str(name, value)?,
+                ExtraAttr::F64(value) => next.write_metadata_f64(name, *value)?,
+            }
+        }
+        if let Some(cal) = &self.temperature_calibration {
+            next.write_temperature_calibration(cal)?;
+        }
+        if self.journal.is_some() {
+            next.enable_journal()?;
+        }
+        if self.swmr {
+            next.start_swmr()?;
+        }
+        next.session = Some(session);
+
+        std::mem::replace(self, next).close()?;
+        if let Some(session) = &self.session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Replace the `start_time` taken at creation (RFC 3339), e.g. for data
+    /// recorded earlier by another instrument
+    pub fn set_start_time(&mut self, start_time: &str) -> Result<()> {
+        let group = self.metadata_group("start_time")?;
+        let value = attr_text("start_time", start_time)?;
+        set_attr(&group, "start_time", &value)?;
+        self.metadata.start_time = start_time.to_string();
+        Ok(())
+    }
+
+    /// Open the file of an interrupted recording so that
+    /// [`crate::journal::recover`] can trim and extend it
+    pub(crate) fn reopen(path: &Path) -> Result<Self> {
+        let file = swmr::open_for_repair(path)
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for repair", e))?;
+        let metadata = Hdf5Reader::read_metadata(&file)?;
+        let data_group = file.group("sensor_data")
+            .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+
+        let datasets = if metadata.devices.is_empty() {
+            vec![DatasetHandles::open(&data_group)?]
+        } else {
+            metadata.devices.iter()
+                .map(|name| {
+                    let group = data_group.group(name)
+                        .map_err(|e| Adxl355Error::storage(format!("Failed to open group {}", name), e))?;
+                    DatasetHandles::open(&group)
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let discontinuities = match file.group("discontinuities") {
+            Ok(group) => {
+                let open = |name: &str| group.dataset(name)
+                    .map_err(|e| Adxl355Error::storage(format!("Failed to open discontinuity {}", name), e));
+                let (start_time, end_time, cause) = (open("start_time")?, open("end_time")?, open("cause")?);
+                let count = start_time.size().min(end_time.size()).min(cause.size());
+                Some(DiscontinuityHandles { start_time, end_time, cause, count })
+            }
+            Err(_) => None,
+        };
+        let events = match file.group("events") {
+            Ok(group) => Some(EventTrack::open(&group)?),
+            Err(_) => None,
+        };
+
+        let sample_counts: Vec<usize> = datasets.iter().map(DatasetHandles::len).collect();
+        let overviews = Self::resume_overviews(&file, &metadata.devices, &datasets, &sample_counts)?;
 
         Ok(Self {
             file,
+            sample_counts,
             datasets,
             start_time: Instant::now(),
-            sample_count: 0,
+            discontinuities,
+            events,
+            swmr: false,
+            last_flush: Instant::now(),
+            last_checkpoint: Instant::now(),
+            path: path.to_path_buf(),
+            metadata,
+            journal: None,
+            session: None,
+            extra_metadata: Vec::new(),
+            temperature_calibration: None,
+            overviews,
+            // Only read for new segments, which a repaired file never gets
+            storage: StorageOptions::default(),
+        })
+    }
+
+    /// Continue the overview of every device group whose file has one
+    fn resume_overviews(
+        file: &File, devices: &[String], datasets: &[DatasetHandles], sample_counts: &[usize],
+    ) -> Result<Vec<Option<OverviewWriter>>> {
+        datasets.iter().zip(sample_counts).enumerate()
+            .map(|(index, (handles, &samples))| {
+                match overview_group(file, devices.get(index).map(String::as_str)) {
+                    Some(group) => Ok(Some(OverviewWriter::resume(&group, samples, |start, end| handles.read(start, end))?)),
+                    None => Ok(None),
+                }
+            })
+            .collect()
+    }
+
+    /// Fresh file laid out as described by a journal header, with the
+    /// default [`StorageOptions`]
+    pub(crate) fn rebuild(path: &Path, header: &JournalHeader) -> Result<Self> {
+        let mut writer = Self::create_with(
+            path, &header.acquisition_mode, header.sample_rate_hz, &header.range, &header.devices, &StorageOptions::default(),
+        )?;
+        writer.set_start_time(&header.start_time)?;
+        Ok(writer)
+    }
+
+    /// Shrink device group `i` to `samples[i]` rows and the discontinuities
+    /// to `gaps` entries
+    pub(crate) fn truncate(&mut self, samples: &[usize], gaps: usize) -> Result<()> {
+        for ((datasets, count), &target) in self.datasets.iter().zip(&mut self.sample_counts).zip(samples) {
+            for dataset in datasets.all() {
+                dataset.resize((target,))
+                    .map_err(|e| Adxl355Error::storage("Failed to truncate dataset", e))?;
+            }
+            *count = target;
+        }
+        self.overviews = Self::resume_overviews(&self.file, &self.metadata.devices, &self.datasets, &self.sample_counts)?;
+
+        if let Some(handles) = &mut self.discontinuities {
+            for dataset in [&handles.start_time, &handles.end_time, &handles.cause] {
+                dataset.resize((gaps,))
+                    .map_err(|e| Adxl355Error::storage("Failed to truncate discontinuities", e))?;
+            }
+            handles.count = gaps;
+        }
+        Ok(())
+    }
+
+    /// Keep unflushed batches and gaps in `<file>.journal` as well, for
+    /// [`crate::journal::recover`]
+    pub fn enable_journal(&mut self) -> Result<()> {
+        if self.journal.is_some() {
+            return Ok(());
+        }
+        self.flush()?;
+        let header = JournalHeader {
+            sample_bases: self.sample_counts.iter().map(|&n| n as u64).collect(),
+            gap_base: self.segment_discontinuities() as u64,
+            start_time: self.metadata.start_time.clone(),
+            acquisition_mode: self.metadata.acquisition_mode.clone(),
+            range: self.metadata.range.clone(),
+            sample_rate_hz: self.metadata.sample_rate_hz,
+            devices: self.metadata.devices.clone(),
+        };
+        self.journal = Some(Journal::create(journal_path(&self.path), header)?);
+        Ok(())
+    }
+
+    /// Finish the recording: flush, close, set `completion = "complete"`
+    ///
+    /// The total `sample_count` is stored next to the marker and the journal
+    /// is deleted. A writer that is merely dropped leaves no marker. For a
+    /// session, the last segment is closed and the manifest brought up to date.
+    pub fn close(self) -> Result<()> {
+        self.finish(Completion::Complete)
+    }
+
+    pub(crate) fn finish(mut self, completion: Completion) -> Result<()> {
+        for overview in self.overviews.iter_mut().flatten() {
+            overview.finish()?;
+        }
+        self.flush()?;
+        let sample_count: usize = self.sample_counts.iter().sum();
+        let Hdf5Writer { file, path, journal, session, .. } = self;
+        // The dataset handles still held by `self` die with the file
+        file.close()
+            .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+
+        // No new attributes under SWMR; add them on a plain read-write handle
+        let file = File::open_rw(&path)
+            .map_err(|e| Adxl355Error::storage("Failed to reopen HDF5 file", e))?;
+        let group = file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+        set_attr(&group, "sample_count", &(sample_count as u64))?;
+        let marker: hdf5::types::VarLenUnicode = completion.marker().parse().unwrap();
+        set_attr(&group, "completion", &marker)?;
+        file.close()
+            .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+
+        if let Some(journal) = journal {
+            journal.remove()?;
+        }
+        if let Some(session) = session {
+            session.save_manifest()?;
+        }
+        Ok(())
+    }
+
+    /// Switch to SWMR mode so other processes can read while we append
+    ///
+    /// The file layout is frozen from here on: call this after the last
+    /// `write_metadata_*`/`write_device_info`.
+    pub fn start_swmr(&mut self) -> Result<()> {
+        if self.swmr {
+            return Ok(());
+        }
+        self.create_discontinuities()?;
+        self.create_events()?;
+        swmr::start_write(&self.file)
+            .map_err(|e| Adxl355Error::storage("Failed to start SWMR write", e))?;
+        self.swmr = true;
+        Ok(())
+    }
+
+    pub fn is_swmr(&self) -> bool {
+        self.swmr
+    }
+
+    /// Open the `metadata` group for a new attribute
+    fn metadata_group(&self, name: &str) -> Result<Group> {
+        if self.swmr {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "Metadata '{}' written after start_swmr()", name
+            )));
+        }
+        self.file.group("metadata")
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))
+    }
+
+    fn create_datasets(group: &Group, storage: &StorageOptions) -> Result<DatasetHandles> {
+        let timestamps = match storage.timestamps {
+            TimestampFormat::Seconds => storage.create_dataset::<f64>(group, "timestamps")?,
+            TimestampFormat::Ticks(_) => storage.create_dataset::<i64>(group, "timestamps")?,
+        };
+
+        Ok(DatasetHandles {
+            timestamps,
+            accel_x: storage.create_dataset::<i32>(group, "accel_x")?,
+            accel_y: storage.create_dataset::<i32>(group, "accel_y")?,
+            accel_z: storage.create_dataset::<i32>(group, "accel_z")?,
+            temperature: storage.create_dataset::<u16>(group, "temperature")?,
+            time: storage.timestamps,
+            chunk_size: storage.chunk_size,
         })
     }
 
-    fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
+    pub(crate) fn create_dataset<T: hdf5::H5Type>(group: &Group, name: &str, chunk_size: usize) -> Result<Dataset> {
         group.new_dataset::<T>()
             .shape((0..,))
             .chunk((chunk_size,))
             .deflate(4)
             .create(name)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to create dataset {}: {}", name, e)))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create dataset {}", name), e))
+    }
+
+    /// Write an extra string attribute into the `metadata` group
+    pub fn write_metadata_str(&mut self, name: &str, value: &str) -> Result<()> {
+        let group = self.metadata_group(name)?;
+        let vlu = attr_text(name, value)?;
+        group.new_attr::<hdf5::types::VarLenUnicode>()
+            .create(name)
+            .and_then(|attr| attr.write_scalar(&vlu))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::Str(value.to_string())));
+        Ok(())
+    }
+
+    /// Write an extra numeric attribute into the `metadata` group
+    pub fn write_metadata_f64(&mut self, name: &str, value: f64) -> Result<()> {
+        let group = self.metadata_group(name)?;
+        group.new_attr::<f64>()
+            .create(name)
+            .and_then(|attr| attr.write_scalar(&value))
+            .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))?;
+        self.extra_metadata.push((name.to_string(), ExtraAttr::F64(value)));
+        Ok(())
+    }
+
+    /// Record the exact part and silicon revision
+    pub fn write_device_info(&mut self, info: &DeviceInfo) -> Result<()> {
+        self.write_metadata_str("part", info.variant.name())?;
+        self.write_metadata_f64("revision", info.revid as f64)
+    }
+
+    /// Record the serial number of the FT232H the sensors hang off
+    pub fn write_device_serial(&mut self, serial: &str) -> Result<()> {
+        self.write_metadata_str("device_serial", serial)?;
+        self.metadata.device_serial = Some(serial.to_string());
+        Ok(())
+    }
+
+    /// Store the temperature calibration shared by all sensors, both as
+    /// `temp_cal_*` metadata and as the scaling of every `temperature` column
+    pub fn write_temperature_calibration(&mut self, cal: &TemperatureCalibration) -> Result<()> {
+        let group = self.metadata_group("temp_cal_intercept_lsb")?;
+        set_attr(&group, "temp_cal_intercept_lsb", &(cal.intercept_lsb as f64))?;
+        set_attr(&group, "temp_cal_slope_lsb_per_c", &(cal.slope_lsb_per_c as f64))?;
+        let info = schema::temperature_channel(cal);
+        for handles in &self.datasets {
+            info.write(&handles.temperature)?;
+        }
+        self.temperature_calibration = Some(*cal);
+        Ok(())
     }
 
     /// Append a single sample
@@ -113,49 +759,202 @@ impl Hdf5Writer {
         self.append_batch(&[sample])
     }
 
-    /// Append a batch of samples
+    /// Append a batch of samples (to the first device of a multi-device file)
     pub fn append_batch(&mut self, samples: &[TimestampedSample]) -> Result<()> {
+        self.append_device_batch(0, samples)
+    }
+
+    /// Append a batch of samples to the group of device `index`
+    pub fn append_device_batch(&mut self, index: usize, samples: &[TimestampedSample]) -> Result<()> {
         if samples.is_empty() {
             return Ok(());
         }
+        let datasets = self.datasets.get(index).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("No device group {}", index))
+        })?;
+        if let Some(journal) = &mut self.journal {
+            journal.append_samples(index, samples)?;
+        }
 
-        let new_size = self.sample_count + samples.len();
+        let new_size = self.sample_counts[index] + samples.len();
 
-        let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
         let accel_x: Vec<i32> = samples.iter().map(|s| s.data.accel_x).collect();
         let accel_y: Vec<i32> = samples.iter().map(|s| s.data.accel_y).collect();
         let accel_z: Vec<i32> = samples.iter().map(|s| s.data.accel_z).collect();
         let temperature: Vec<u16> = samples.iter().map(|s| s.data.temperature).collect();
 
-        self.append_to_dataset(&self.datasets.timestamps, new_size, &timestamps)?;
-        self.append_to_dataset(&self.datasets.accel_x, new_size, &accel_x)?;
-        self.append_to_dataset(&self.datasets.accel_y, new_size, &accel_y)?;
-        self.append_to_dataset(&self.datasets.accel_z, new_size, &accel_z)?;
-        self.append_to_dataset(&self.datasets.temperature, new_size, &temperature)?;
+        match datasets.time {
+            TimestampFormat::Seconds => {
+                let timestamps: Vec<f64> = samples.iter().map(|s| s.timestamp).collect();
+                Self::append_to_dataset(&datasets.timestamps, new_size, &timestamps)?;
+            }
+            TimestampFormat::Ticks(rate) => {
+                let ticks: Vec<i64> = samples.iter().map(|s| storage::to_ticks(s.timestamp, rate)).collect();
+                Self::append_to_dataset(&datasets.timestamps, new_size, &ticks)?;
+            }
+        }
+        Self::append_to_dataset(&datasets.accel_x, new_size, &accel_x)?;
+        Self::append_to_dataset(&datasets.accel_y, new_size, &accel_y)?;
+        Self::append_to_dataset(&datasets.accel_z, new_size, &accel_z)?;
+        Self::append_to_dataset(&datasets.temperature, new_size, &temperature)?;
+
+        self.sample_counts[index] = new_size;
+        if let Some(Some(overview)) = self.overviews.get_mut(index) {
+            overview.push(samples);
+        }
+        if self.swmr && self.last_flush.elapsed() >= SWMR_FLUSH_INTERVAL {
+            self.flush_file()?;
+            if self.last_checkpoint.elapsed() >= JOURNAL_CHECKPOINT_INTERVAL {
+                self.checkpoint()?;
+            }
+        }
 
-        self.sample_count = new_size;
+        let rotate = match &mut self.session {
+            Some(session) => {
+                session.observe(index, samples);
+                session.due(&self.path)
+            }
+            None => false,
+        };
+        if rotate {
+            self.rotate()?;
+        }
         Ok(())
     }
 
-    fn append_to_dataset<T: hdf5::H5Type>(&self, dataset: &Dataset, new_size: usize, data: &[T]) -> Result<()> {
+    fn append_to_dataset<T: hdf5::H5Type>(dataset: &Dataset, new_size: usize, data: &[T]) -> Result<()> {
         dataset.resize((new_size,))
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to resize dataset: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to resize dataset", e))?;
 
         let start = new_size - data.len();
         dataset.write_slice(data, start..)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to write to dataset: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to write to dataset", e))?;
 
         Ok(())
     }
 
+    /// Record a gap in the data (times in seconds, same clock as the samples)
+    pub fn write_discontinuity(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        self.create_discontinuities()?;
+        if let Some(journal) = &mut self.journal {
+            journal.append_gap(start_time, end_time, cause)?;
+        }
+        let handles = self.discontinuities.as_ref().unwrap();
+        let new_size = handles.count + 1;
+        let cause = fixed_text::<TEXT_LEN>(cause)?;
+
+        Self::append_to_dataset(&handles.start_time, new_size, &[start_time])?;
+        Self::append_to_dataset(&handles.end_time, new_size, &[end_time])?;
+        Self::append_to_dataset(&handles.cause, new_size, &[cause])?;
+
+        self.discontinuities.as_mut().unwrap().count = new_size;
+        Ok(())
+    }
+
+    fn create_discontinuities(&mut self) -> Result<()> {
+        if self.discontinuities.is_some() {
+            return Ok(());
+        }
+        let group = self.file.create_group("discontinuities")
+            .map_err(|e| Adxl355Error::storage("Failed to create discontinuities group", e))?;
+        self.discontinuities = Some(DiscontinuityHandles {
+            start_time: Self::create_dataset::<f64>(&group, "start_time", 16)?,
+            end_time: Self::create_dataset::<f64>(&group, "end_time", 16)?,
+            cause: Self::create_dataset::<FixedUnicode<TEXT_LEN>>(&group, "cause", 16)?,
+            count: 0,
+        });
+        Ok(())
+    }
+
+    /// Add an event to the `events` group (see [`crate::events`])
+    ///
+    /// `event.time` is on the sample clock. In a session the event goes to
+    /// the segment being written.
+    pub fn add_event(&mut self, event: &Event) -> Result<()> {
+        self.create_events()?;
+        self.events.as_mut().unwrap().append(event)
+    }
+
+    fn create_events(&mut self) -> Result<()> {
+        if self.events.is_some() {
+            return Ok(());
+        }
+        let group = self.file.create_group("events")
+            .map_err(|e| Adxl355Error::storage("Failed to create events group", e))?;
+        self.events = Some(EventTrack::create(&group)?);
+        Ok(())
+    }
+
+    /// Events added, earlier segments of a session included
+    pub fn event_count(&self) -> usize {
+        self.segment_events() + self.session.as_ref().map_or(0, |s| s.events_before)
+    }
+
+    fn segment_events(&self) -> usize {
+        self.events.as_ref().map_or(0, EventTrack::count)
+    }
+
+    /// Gaps recorded, earlier segments of a session included
+    pub fn discontinuity_count(&self) -> usize {
+        self.segment_discontinuities() + self.session.as_ref().map_or(0, |s| s.gaps_before)
+    }
+
+    fn segment_discontinuities(&self) -> usize {
+        self.discontinuities.as_ref().map_or(0, |d| d.count)
+    }
+
+    /// Write buffered data to the file and, with a journal, sync the file
+    /// to disk and empty the journal
     pub fn flush(&mut self) -> Result<()> {
+        self.flush_file()?;
+        self.checkpoint()
+    }
+
+    /// Hand buffered data to HDF5, which makes it visible to SWMR readers
+    fn flush_file(&mut self) -> Result<()> {
+        for overview in self.overviews.iter_mut().flatten() {
+            overview.flush()?;
+        }
         self.file.flush()
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to flush HDF5 file: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to flush HDF5 file", e))?;
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// Sync the file to disk, then empty the journal: it may only drop
+    /// what has reached the disk
+    fn checkpoint(&mut self) -> Result<()> {
+        self.last_checkpoint = Instant::now();
+        let gaps = self.segment_discontinuities();
+        let Some(journal) = &mut self.journal else {
+            return Ok(());
+        };
+        // FlushFileBuffers on Windows needs a handle with write access
+        OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .and_then(|file| file.sync_all())
+            .map_err(|source| Adxl355Error::Io {
+                context: format!("Failed to sync {}", self.path.display()),
+                source,
+            })?;
+        journal.restart(&self.sample_counts, gaps)?;
         Ok(())
     }
 
+    /// Samples written, summed over all devices
     pub fn sample_count(&self) -> usize {
-        self.sample_count
+        (0..self.device_count()).map(|index| self.device_sample_count(index)).sum()
+    }
+
+    /// Device groups in the file (1 for a single-sensor file)
+    pub fn device_count(&self) -> usize {
+        self.datasets.len()
+    }
+
+    pub fn device_sample_count(&self, index: usize) -> usize {
+        let before = self.session.as_ref().and_then(|s| s.samples_before.get(index)).copied().unwrap_or(0);
+        self.sample_counts.get(index).copied().unwrap_or(0) + before
     }
 
     pub fn elapsed_secs(&self) -> f64 {
@@ -163,49 +962,248 @@ impl Hdf5Writer {
     }
 }
 
-/// HDF5 reader for accessing collected sensor data
-pub struct Hdf5Reader {
-    #[allow(dead_code)]
+/// One file of a recording, bound to a device group
+struct Segment {
     file: File,
     datasets: DatasetHandles,
+    overview: Option<Group>,
+}
+
+impl Segment {
+    fn open(file: File, device: Option<&str>) -> Result<Self> {
+        let mut data_group = file.group("sensor_data")
+            .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+        if let Some(name) = device {
+            data_group = data_group.group(name)
+                .map_err(|e| Adxl355Error::storage(format!("Failed to open group {}", name), e))?;
+        }
+        let datasets = DatasetHandles::open(&data_group)?;
+        let overview = overview_group(&file, device);
+        Ok(Segment { file, datasets, overview })
+    }
+
+    /// Add rows `start..end` to `out`: whole bins of level `factor` where
+    /// the overview has them, raw rows for the rest
+    fn summarize(&self, start: usize, end: usize, factor: Option<usize>, out: &mut Rebinner) -> Result<()> {
+        let level = match (factor, &self.overview) {
+            (Some(factor), Some(group)) => Level::open(group, factor).ok(),
+            _ => None,
+        };
+        let Some(level) = level else {
+            return self.summarize_raw(start, end, out);
+        };
+        let first = start.div_ceil(level.factor);
+        let last = (end / level.factor).min(level.len());
+        if first >= last {
+            return self.summarize_raw(start, end, out);
+        }
+        self.summarize_raw(start, first * level.factor, out)?;
+        for bin in level.read(first, last)? {
+            out.push(bin);
+        }
+        self.summarize_raw(last * level.factor, end, out)
+    }
+
+    fn summarize_raw(&self, start: usize, end: usize, out: &mut Rebinner) -> Result<()> {
+        for from in (start..end).step_by(READ_BLOCK) {
+            let to = (from + READ_BLOCK).min(end);
+            for sample in self.datasets.read(from, to)? {
+                out.push(OverviewBin::from_sample(&sample));
+            }
+        }
+        Ok(())
+    }
+
+    fn completion(&self) -> Completion {
+        self.file.group("metadata")
+            .and_then(|group| group.attr("completion"))
+            .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+            .map(|marker| Completion::from_marker(marker.as_str()))
+            .unwrap_or(Completion::Unknown)
+    }
+
+    fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let group = match self.file.group("discontinuities") {
+            Ok(group) => group,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let read_f64 = |name: &str| -> Result<Vec<f64>> {
+            group.dataset(name)
+                .and_then(|d| d.read_raw::<f64>())
+                .map_err(|e| Adxl355Error::storage(format!("Failed to read discontinuity {}", name), e))
+        };
+        let start_time = read_f64("start_time")?;
+        let end_time = read_f64("end_time")?;
+        let cause = group.dataset("cause")
+            .and_then(|d| read_text(&d))
+            .map_err(|e| Adxl355Error::storage("Failed to read discontinuity cause", e))?;
+
+        Ok(start_time.into_iter()
+            .zip(end_time)
+            .zip(cause)
+            .map(|((start_time, end_time), cause)| Discontinuity {
+                start_time,
+                end_time,
+                cause,
+            })
+            .collect())
+    }
+
+    fn events(&self) -> Result<Vec<Event>> {
+        match self.file.group("events") {
+            Ok(group) => events::read(&group),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    fn timestamp(&self, index: usize) -> Result<f64> {
+        let value = self.datasets.read_timestamps(index, index + 1)?;
+        value.first().copied()
+            .ok_or_else(|| Adxl355Error::InvalidParameter(format!("No timestamp at index {}", index)))
+    }
+
+    /// First row at or after `t` (strictly after with `after`); the row
+    /// count if the file ends earlier
+    ///
+    /// Bisects over the first timestamp of each chunk, then reads the one
+    /// chunk that holds the boundary.
+    fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let before = |ts: f64| if after { ts <= t } else { ts < t };
+        let len = self.datasets.len();
+        if len == 0 || before(self.timestamp(len - 1)?) {
+            return Ok(len);
+        }
+
+        let chunk_size = self.datasets.chunk_size;
+        let (mut low, mut high) = (0, len.div_ceil(chunk_size));
+        while low < high {
+            let mid = (low + high) / 2;
+            if before(self.timestamp(mid * chunk_size)?) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        if low == 0 {
+            return Ok(0);
+        }
+
+        let start = (low - 1) * chunk_size;
+        let end = (low * chunk_size).min(len);
+        let chunk = self.datasets.read_timestamps(start, end)?;
+        Ok(start + chunk.partition_point(|&ts| before(ts)))
+    }
+}
+
+/// HDF5 reader for accessing collected sensor data
+///
+/// A reader on a session manifest chains the device's group across all
+/// segment files; indices and timestamps run on through the whole session.
+pub struct Hdf5Reader {
+    segments: Vec<Segment>,
     metadata: Metadata,
+    device: Option<String>,
+    manifest: Option<Manifest>,
+    /// Manifest re-read on every refresh, for a session opened with `open_swmr`
+    live_manifest: Option<PathBuf>,
 }
 
 impl Hdf5Reader {
+    /// Open a file or session manifest; for multi-device recordings this
+    /// reads the first device
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_device(path, None)
+    }
+
+    /// Open one device group of a multi-device file (`None` = first device)
+    ///
+    /// `path` may also be a session manifest.
+    pub fn open_device<P: AsRef<Path>>(path: P, device: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            return Self::open_session(path, device);
+        }
         let file = File::open(path)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open HDF5 file: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file", e))?;
+        Self::from_file(file, device)
+    }
 
+    /// Open a device group across all segments of a session manifest
+    pub fn open_session<P: AsRef<Path>>(manifest_path: P, device: Option<&str>) -> Result<Self> {
+        Self::from_manifest(manifest_path.as_ref(), device, |path| File::open(path))
+    }
+
+    /// Like [`open_device`](Self::open_device), for a file a SWMR writer is
+    /// still appending to
+    ///
+    /// On a session manifest, every refresh re-reads the manifest and opens
+    /// the segments the writer has rotated into since.
+    pub fn open_swmr<P: AsRef<Path>>(path: P, device: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        if is_manifest(path) {
+            let mut reader = Self::from_manifest(path, device, swmr::open_read)?;
+            reader.live_manifest = Some(path.to_path_buf());
+            return Ok(reader);
+        }
+        let file = swmr::open_read(path)
+            .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for SWMR read", e))?;
+        Self::from_file(file, device)
+    }
+
+    fn from_file(file: File, device: Option<&str>) -> Result<Self> {
         let metadata = Self::read_metadata(&file)?;
+        let device = Self::select_device(&metadata, device)?;
+        let segment = Segment::open(file, device.as_deref())?;
+        Ok(Self { segments: vec![segment], metadata, device, manifest: None, live_manifest: None })
+    }
 
-        let data_group = file.group("sensor_data")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open sensor_data group: {}", e)))?;
-
-        let datasets = DatasetHandles {
-            timestamps: data_group.dataset("timestamps")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open timestamps: {}", e)))?,
-            accel_x: data_group.dataset("accel_x")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_x: {}", e)))?,
-            accel_y: data_group.dataset("accel_y")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_y: {}", e)))?,
-            accel_z: data_group.dataset("accel_z")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open accel_z: {}", e)))?,
-            temperature: data_group.dataset("temperature")
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open temperature: {}", e)))?,
-        };
+    fn from_manifest(manifest_path: &Path, device: Option<&str>, open: fn(&Path) -> hdf5::Result<File>) -> Result<Self> {
+        let manifest = Manifest::load(manifest_path)?;
+        let files = manifest.segment_paths(manifest_path).iter()
+            .map(|path| Self::open_segment_file(path, open))
+            .collect::<Result<Vec<_>>>()?;
+        let metadata = Self::read_metadata(&files[0])?;
+        let device = Self::select_device(&metadata, device)?;
+        let segments = files.into_iter()
+            .map(|file| Segment::open(file, device.as_deref()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { segments, metadata, device, manifest: Some(manifest), live_manifest: None })
+    }
+
+    fn open_segment_file(path: &Path, open: fn(&Path) -> hdf5::Result<File>) -> Result<File> {
+        open(path).map_err(|e| Adxl355Error::storage(format!("Failed to open segment {}", path.display()), e))
+    }
 
-        Ok(Self { file, datasets, metadata })
+    /// Check a requested device against the file, defaulting to the first
+    fn select_device(metadata: &Metadata, device: Option<&str>) -> Result<Option<String>> {
+        match device {
+            Some(name) if !metadata.devices.iter().any(|d| d == name) => {
+                Err(Adxl355Error::InvalidParameter(format!(
+                    "File has no device '{}' (devices: {})",
+                    name,
+                    if metadata.devices.is_empty() { "none".to_string() } else { metadata.devices.join(", ") }
+                )))
+            }
+            Some(name) => Ok(Some(name.to_string())),
+            None => Ok(metadata.devices.first().cloned()),
+        }
+    }
+
+    /// Device group this reader is bound to, `None` for single-sensor files
+    pub fn device(&self) -> Option<&str> {
+        self.device.as_deref()
     }
 
     fn read_metadata(file: &File) -> Result<Metadata> {
         let group = file.group("metadata")
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to open metadata: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to open metadata", e))?;
 
         let read_str = |name: &str| -> Result<String> {
             group.attr(name)
                 .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
                 .map(|s| s.to_string())
-                .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read {}: {}", name, e)))
+                .map_err(|e| Adxl355Error::storage(format!("Failed to read {}", name), e))
         };
 
         let start_time = read_str("start_time")?;
@@ -216,7 +1214,16 @@ impl Hdf5Reader {
 
         let sample_rate_hz = group.attr("sample_rate_hz")
             .and_then(|attr| attr.read_scalar::<f64>())
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read sample_rate_hz: {}", e)))?;
+            .map_err(|e| Adxl355Error::storage("Failed to read sample_rate_hz", e))?;
+
+        let part = read_str("part").ok();
+        let revision = group.attr("revision")
+            .and_then(|attr| attr.read_scalar::<f64>())
+            .ok()
+            .map(|v| v as u8);
+        let devices = read_str("devices")
+            .map(|list| list.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
 
         Ok(Metadata {
             start_time,
@@ -225,6 +1232,12 @@ impl Hdf5Reader {
             sensor_type,
             range,
             version,
+            part,
+            revision,
+            devices,
+            host: read_str("host").ok(),
+            crate_version: read_str("crate_version").ok(),
+            device_serial: read_str("device_serial").ok(),
         })
     }
 
@@ -232,8 +1245,97 @@ impl Hdf5Reader {
         &self.metadata
     }
 
+    /// Session manifest, if the reader was opened on one
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Whether the file was closed, recovered, or neither; for a session,
+    /// the least complete of its segments
+    pub fn completion(&self) -> Completion {
+        self.segments.iter().map(Segment::completion).fold(Completion::Complete, |all, c| match (all, c) {
+            (Completion::Unknown, _) | (_, Completion::Unknown) => Completion::Unknown,
+            (Completion::Recovered, _) | (_, Completion::Recovered) => Completion::Recovered,
+            _ => Completion::Complete,
+        })
+    }
+
+    /// Temperature calibration recorded with the file, or the datasheet
+    /// nominal for files written without one
+    pub fn temperature_calibration(&self) -> TemperatureCalibration {
+        match self.segments[0].file.group("metadata") {
+            Ok(group) => schema::stored_calibration(&group),
+            Err(_) => TemperatureCalibration::NOMINAL,
+        }
+    }
+
+    /// Units and scaling of column `name` of the reader's device
+    ///
+    /// Read from the dataset attributes of schema 2 files, derived from
+    /// `range` and the temperature calibration for version 1 files.
+    pub fn channel_info(&self, name: &str) -> Option<ChannelInfo> {
+        let dataset = self.segments[0].datasets.get(name)?;
+        ChannelInfo::read(dataset).or_else(|| {
+            let range = Range::from_label(&self.metadata.range).unwrap_or(Range::G2);
+            schema::channels(range, &self.temperature_calibration())
+                .into_iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, info)| info)
+        })
+    }
+
+    /// Gaps recorded during collection (empty for files without any)
+    pub fn discontinuities(&self) -> Result<Vec<Discontinuity>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.discontinuities()?);
+        }
+        Ok(all)
+    }
+
+    /// Events with a time in `range`, in time order (empty for files
+    /// without an event track)
+    ///
+    /// Events belong to the recording, not a device group, so every reader
+    /// of a multi-device file sees the same ones.
+    pub fn events<R: RangeBounds<f64>>(&self, range: R) -> Result<Vec<Event>> {
+        let mut all = Vec::new();
+        for segment in &self.segments {
+            all.extend(segment.events()?.into_iter().filter(|event| range.contains(&event.time)));
+        }
+        all.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(all)
+    }
+
+    /// Number of samples present in every column of the device group
     pub fn get_total_samples(&self) -> Result<usize> {
-        Ok(self.datasets.timestamps.size())
+        Ok(self.segments.iter().map(|s| s.datasets.len()).sum())
+    }
+
+    /// Re-read the extents of the device's datasets; returns the new total
+    ///
+    /// A session opened with [`open_swmr`](Self::open_swmr) first picks up
+    /// the segments listed in its manifest since the last refresh.
+    pub fn refresh(&mut self) -> Result<usize> {
+        if let Some(manifest_path) = &self.live_manifest {
+            let manifest = Manifest::load(manifest_path)?;
+            for path in manifest.segment_paths(manifest_path).iter().skip(self.segments.len()) {
+                let file = Self::open_segment_file(path, swmr::open_read)?;
+                self.segments.push(Segment::open(file, self.device.as_deref())?);
+            }
+            self.manifest = Some(manifest);
+        }
+        for dataset in self.segments.iter().flat_map(|s| s.datasets.all()) {
+            swmr::refresh(dataset)
+                .map_err(|e| Adxl355Error::storage("Failed to refresh dataset", e))?;
+        }
+        self.get_total_samples()
+    }
+
+    /// Samples from index `from` up to the end of a growing file
+    pub fn follow(&mut self, from: usize) -> Result<Vec<TimestampedSample>> {
+        let total = self.refresh()?;
+        self.read_range(from, total.saturating_sub(from))
     }
 
     pub fn read_range(&self, start: usize, count: usize) -> Result<Vec<TimestampedSample>> {
@@ -245,39 +1347,19 @@ impl Hdf5Reader {
         let actual_count = count.min(total - start);
         let end = start + actual_count;
 
-        let timestamps: Vec<f64> = self.datasets.timestamps.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read timestamps: {}", e)))?
-            .to_vec();
-        let accel_x: Vec<i32> = self.datasets.accel_x.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_x: {}", e)))?
-            .to_vec();
-        let accel_y: Vec<i32> = self.datasets.accel_y.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_y: {}", e)))?
-            .to_vec();
-        let accel_z: Vec<i32> = self.datasets.accel_z.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read accel_z: {}", e)))?
-            .to_vec();
-        let temperature: Vec<u16> = self.datasets.temperature.read_slice_1d(start..end)
-            .map_err(|e| Adxl355Error::CommunicationError(format!("Failed to read temperature: {}", e)))?
-            .to_vec();
-
-        let samples = timestamps.into_iter()
-            .zip(accel_x)
-            .zip(accel_y)
-            .zip(accel_z)
-            .zip(temperature)
-            .map(|((((ts, ax), ay), az), temp)| {
-                TimestampedSample {
-                    timestamp: ts,
-                    data: SensorData {
-                        accel_x: ax,
-                        accel_y: ay,
-                        accel_z: az,
-                        temperature: temp,
-                    },
-                }
-            })
-            .collect();
+        let mut samples = Vec::with_capacity(actual_count);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                samples.extend(segment.datasets.read(from - offset, to - offset)?);
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
 
         Ok(samples)
     }
@@ -290,4 +1372,360 @@ impl Hdf5Reader {
         let start = total.saturating_sub(count);
         self.read_range(start, count)
     }
+
+    /// Timestamps of the first and last sample of the reader's device, `None` if empty
+    pub fn time_span(&self) -> Result<Option<(f64, f64)>> {
+        let mut filled = self.segments.iter().filter(|s| s.datasets.len() > 0);
+        let Some(first) = filled.next() else {
+            return Ok(None);
+        };
+        let last = filled.next_back().unwrap_or(first);
+        Ok(Some((first.timestamp(0)?, last.timestamp(last.datasets.len() - 1)?)))
+    }
+
+    /// Index of the first sample at or after `t` (the sample count if
+    /// there is none)
+    pub fn index_at_time(&self, t: f64) -> Result<usize> {
+        self.search(t, false)
+    }
+
+    pub(crate) fn search(&self, t: f64, after: bool) -> Result<usize> {
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let index = segment.search(t, after)?;
+            if index < len {
+                return Ok(offset + index);
+            }
+            offset += len;
+        }
+        Ok(offset)
+    }
+
+    /// Samples from `t0` to `t1` inclusive, read without touching the rest
+    /// of the file
+    pub fn read_time_range(&self, t0: f64, t1: f64) -> Result<Vec<TimestampedSample>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        self.read_range(start, end.saturating_sub(start))
+    }
+
+    /// The reader's device from `t0` to `t1` as at most about `max_bins`
+    /// min/max/mean bins
+    ///
+    /// Whole bins come from the coarsest overview level fine enough for
+    /// `max_bins`, so the cost hardly grows with the window. Windows with
+    /// fewer than `max_bins` samples give one bin per sample; recordings
+    /// without an overview are binned from the samples.
+    pub fn read_overview(&self, t0: f64, t1: f64, max_bins: usize) -> Result<Vec<OverviewBin>> {
+        let start = self.search(t0, false)?;
+        let end = self.search(t1, true)?;
+        if end <= start {
+            return Ok(Vec::new());
+        }
+        let per_bin = (end - start).div_ceil(max_bins.max(1));
+        let factor = overview::FACTORS.iter().rev().copied().find(|&f| f <= per_bin);
+
+        let mut out = Rebinner::new(per_bin);
+        let mut offset = 0;
+        for segment in &self.segments {
+            let len = segment.datasets.len();
+            let (from, to) = (start.max(offset), end.min(offset + len));
+            if from < to {
+                segment.summarize(from - offset, to - offset, factor, &mut out)?;
+            }
+            offset += len;
+            if offset >= end {
+                break;
+            }
+        }
+        Ok(out.finish())
+    }
+
+    /// Walk the recording in blocks of at most `size` samples
+    ///
+    /// Memory use is bounded by one block. Samples a SWMR writer appends
+    /// after the call are not visited.
+    ///
+    /// # Example
+    /// ```no_run
+    /// use ft232_adxl355_spi::Hdf5Reader;
+    ///
+    /// let reader = Hdf5Reader::open("long_run.h5")?;
+    /// let mut count = 0;
+    /// for chunk in reader.iter_chunks(100_000) {
+    ///     count += chunk?.len();
+    /// }
+    /// # Ok::<(), ft232_adxl355_spi::Adxl355Error>(())
+    /// ```
+    pub fn iter_chunks(&self, size: usize) -> SampleChunks<'_> {
+        SampleChunks {
+            reader: self,
+            position: 0,
+            end: self.get_total_samples().unwrap_or(0),
+            size: size.max(1),
+        }
+    }
+}
+
+/// Blocks of samples, see [`Hdf5Reader::iter_chunks`]
+pub struct SampleChunks<'a> {
+    reader: &'a Hdf5Reader,
+    position: usize,
+    end: usize,
+    size: usize,
+}
+
+impl Iterator for SampleChunks<'_> {
+    type Item = Result<Vec<TimestampedSample>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.end {
+            return None;
+        }
+        let count = self.size.min(self.end - self.position);
+        let chunk = self.reader.read_range(self.position, count);
+        // A failed block ends the iteration
+        self.position = if chunk.is_ok() { self.position + count } else { self.end };
+        Some(chunk)
+    }
+}
+
+/// Overview group of `device` (`None` in single-sensor files), absent in
+/// files written before schema 2.1
+fn overview_group(file: &File, device: Option<&str>) -> Option<Group> {
+    let group = file.group("overview").ok()?;
+    match device {
+        Some(name) => group.group(name).ok(),
+        None => Some(group),
+    }
+}
+
+/// Set a scalar attribute, creating it if needed
+pub(crate) fn set_attr<T: hdf5::H5Type>(location: &Location, name: &str, value: &T) -> Result<()> {
+    let attr = match location.attr(name) {
+        Ok(attr) => attr,
+        Err(_) => location.new_attr::<T>().create(name)
+            .map_err(|e| Adxl355Error::storage(format!("Failed to create {}", name), e))?,
+    };
+    attr.write_scalar(value)
+        .map_err(|e| Adxl355Error::storage(format!("Failed to write {}", name), e))
+}
+
+/// Convert text for a string attribute, refusing NUL characters
+pub(crate) fn attr_text(name: &str, value: &str) -> Result<VarLenUnicode> {
+    value.parse()
+        .map_err(|_| Adxl355Error::InvalidParameter(format!("{} contains a NUL character", name)))
+}
+
+/// Byte capacity of the short fixed-length text columns (causes, event kinds and labels)
+pub(crate) const TEXT_LEN: usize = 256;
+/// Byte capacity of the event payload column
+pub(crate) const PAYLOAD_LEN: usize = 1024;
+
+/// Convert free text for a fixed-length string column
+///
+/// Variable-length strings cannot be appended once SWMR is on, so text that
+/// grows with the recording is stored fixed-length. NUL bytes are dropped
+/// and text longer than `N` bytes is cut at a character boundary.
+pub(crate) fn fixed_text<const N: usize>(value: &str) -> Result<FixedUnicode<N>> {
+    let mut text: String = value.chars().filter(|&c| c != '\0').collect();
+    if text.len() > N {
+        let mut end = N;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    text.parse()
+        .map_err(|e| Adxl355Error::InvalidParameter(format!("Text cannot be stored: {}", e)))
+}
+
+/// Read a text column written either fixed-length or, by older versions, variable-length
+pub(crate) fn read_text(dataset: &Dataset) -> hdf5::Result<Vec<String>> {
+    let descriptor = dataset.dtype()?.to_descriptor()?;
+    Ok(match descriptor {
+        TypeDescriptor::VarLenUnicode | TypeDescriptor::VarLenAscii => dataset
+            .read_raw::<VarLenUnicode>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        TypeDescriptor::FixedUnicode(n) | TypeDescriptor::FixedAscii(n) if n <= TEXT_LEN => dataset
+            .read_raw::<FixedUnicode<TEXT_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        _ => dataset
+            .read_raw::<FixedUnicode<PAYLOAD_LEN>>()?
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+    })
+}
+
+/// Thin wrappers over the SWMR entry points of the C library
+mod swmr {
+    use hdf5::{Dataset, File};
+    use hdf5_sys::{h5, h5d, h5f, h5p};
+    use std::ffi::{c_void, CString};
+    use std::path::Path;
+
+    fn check(status: i32, call: &str) -> hdf5::Result<()> {
+        if status < 0 {
+            return Err(format!("{} failed", call).into());
+        }
+        Ok(())
+    }
+
+    pub fn start_write(file: &File) -> hdf5::Result<()> {
+        check(unsafe { h5f::H5Fstart_swmr_write(file.id()) }, "H5Fstart_swmr_write")
+    }
+
+    pub fn refresh(dataset: &Dataset) -> hdf5::Result<()> {
+        check(unsafe { h5d::H5Drefresh(dataset.id()) }, "H5Drefresh")
+    }
+
+    fn c_path(path: &Path) -> hdf5::Result<CString> {
+        CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| hdf5::Error::from("file name contains a NUL byte"))
+    }
+
+    fn open(path: &Path, flags: u32, fapl: hdf5_sys::h5i::hid_t) -> hdf5::Result<File> {
+        let name = c_path(path)?;
+        let id = unsafe { h5f::H5Fopen(name.as_ptr(), flags, fapl) };
+        if id < 0 {
+            return Err(format!("H5Fopen failed for {}", path.display()).into());
+        }
+        hdf5::from_id(id)
+    }
+
+    pub fn open_read(path: &Path) -> hdf5::Result<File> {
+        open(path, h5f::H5F_ACC_RDONLY | h5f::H5F_ACC_SWMR_READ, h5p::H5P_DEFAULT)
+    }
+
+    /// Open read-write, clearing the superblock "in use" flags left by a
+    /// writer that crashed in SWMR mode (what `h5clear -s` does)
+    pub fn open_for_repair(path: &Path) -> hdf5::Result<File> {
+        let key = CString::new("clear_status_flags").unwrap();
+        let mut clear: h5::hbool_t = 1;
+        unsafe {
+            check(h5::H5open(), "H5open")?;
+            let fapl = h5p::H5Pcreate(*h5p::H5P_CLS_FILE_ACCESS);
+            if fapl < 0 {
+                return Err("H5Pcreate failed".into());
+            }
+            let result = check(
+                h5p::H5Pset(fapl, key.as_ptr(), &mut clear as *mut h5::hbool_t as *mut c_void),
+                "H5Pset(clear_status_flags)",
+            )
+            .and_then(|_| open(path, h5f::H5F_ACC_RDWR, fapl));
+            h5p::H5Pclose(fapl);
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(i: i32, rate: f64) -> TimestampedSample {
+        TimestampedSample {
+            timestamp: i as f64 / rate,
+            data: SensorData { accel_x: i, accel_y: 0, accel_z: 256_000, temperature: 1885 },
+        }
+    }
+
+    #[test]
+    fn time_lookup_per_device_group() {
+        let path = std::env::temp_dir().join(format!("adxl355-time-range-{}.h5", std::process::id()));
+        let devices = vec!["cs0".to_string(), "cs1".to_string()];
+        let storage = StorageOptions { chunk_size: 8, ..StorageOptions::default() };
+        let mut writer = Hdf5Writer::create_with(&path, "fifo", 1000.0, "2g", &devices, &storage).unwrap();
+        // cs1 runs at half the rate and starts later
+        writer.append_device_batch(0, &(0..100).map(|i| sample(i, 1000.0)).collect::<Vec<_>>()).unwrap();
+        writer.append_device_batch(1, &(10..50).map(|i| sample(i, 500.0)).collect::<Vec<_>>()).unwrap();
+        writer.close().unwrap();
+
+        let cs0 = Hdf5Reader::open_device(&path, Some("cs0")).unwrap();
+        let cs1 = Hdf5Reader::open_device(&path, Some("cs1")).unwrap();
+        let spans = (cs0.time_span().unwrap(), cs1.time_span().unwrap());
+        let starts = (cs0.index_at_time(0.03).unwrap(), cs1.index_at_time(0.03).unwrap());
+        let window0 = cs0.read_time_range(0.04, 0.044).unwrap();
+        let window1 = cs1.read_time_range(0.04, 0.044).unwrap();
+        let before1 = cs1.read_time_range(0.0, 0.019).unwrap();
+        drop((cs0, cs1));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(spans, (Some((0.0, 0.099)), Some((0.02, 0.098))));
+        assert_eq!(starts, (30, 5));
+        let x = |window: &[TimestampedSample]| -> Vec<i32> { window.iter().map(|s| s.data.accel_x).collect() };
+        assert_eq!(x(&window0), [40, 41, 42, 43, 44]);
+        assert_eq!(x(&window1), [20, 21, 22]);
+        assert!(before1.is_empty());
+    }
+
+    #[test]
+    fn swmr_flushes_keep_the_journal_until_a_checkpoint() {
+        use crate::journal::{journal_path, JournalContents};
+
+        let path = std::env::temp_dir().join(format!("adxl355-flush-journal-{}.h5", std::process::id()));
+        let journaled = || JournalContents::read(journal_path(&path)).unwrap();
+        let devices = vec!["cs0".to_string(), "cs1".to_string()];
+        let mut writer = Hdf5Writer::create_with(&path, "fifo", 1000.0, "2g", &devices, &StorageOptions::default()).unwrap();
+        writer.enable_journal().unwrap();
+        writer.start_swmr().unwrap();
+        writer.append_device_batch(0, &(0..500).map(|i| sample(i, 1000.0)).collect::<Vec<_>>()).unwrap();
+        // Past the SWMR flush interval: the file is flushed, the journal kept
+        std::thread::sleep(SWMR_FLUSH_INTERVAL);
+        writer.append_device_batch(1, &(0..300).map(|i| sample(i, 1000.0)).collect::<Vec<_>>()).unwrap();
+        assert_eq!(journaled().sample_count(), 800);
+
+        writer.flush().unwrap();
+        let contents = journaled();
+        assert_eq!(contents.sample_count(), 0);
+        assert_eq!(contents.header.sample_bases, [500, 300]);
+        writer.append_device_batch(1, &(300..500).map(|i| sample(i, 1000.0)).collect::<Vec<_>>()).unwrap();
+        writer.close().unwrap();
+        assert!(!journal_path(&path).exists());
+        let total = Hdf5Reader::open_device(&path, Some("cs1")).unwrap().get_total_samples().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(total, 500);
+    }
+
+    #[test]
+    fn following_a_session_continues_into_new_segments() {
+        let dir = std::env::temp_dir().join(format!("adxl355-follow-session-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let devices = vec!["cs0".to_string(), "cs1".to_string()];
+        let mut writer = Hdf5Writer::create_session(
+            dir.join("run.h5"), "fifo", 1000.0, "2g", &devices, Rotation::default(), &StorageOptions::default(),
+        ).unwrap();
+        writer.start_swmr().unwrap();
+        let samples: Vec<TimestampedSample> = (0..150).map(|i| sample(i, 1000.0)).collect();
+        writer.append_device_batch(0, &samples[..100]).unwrap();
+        writer.append_device_batch(1, &samples[..80]).unwrap();
+        writer.rotate().unwrap();
+        writer.append_device_batch(1, &samples[80..]).unwrap();
+        let manifest = writer.manifest_path().unwrap();
+        writer.close().unwrap();
+
+        // Start following while the manifest still lists the first segment only
+        let full = Manifest::load(&manifest).unwrap();
+        let mut first = full.clone();
+        first.segments.truncate(1);
+        first.save(&manifest).unwrap();
+        let mut reader = Hdf5Reader::open_swmr(&manifest, Some("cs1")).unwrap();
+        let before = reader.follow(0).unwrap();
+        full.save(&manifest).unwrap();
+        let after = reader.follow(before.len()).unwrap();
+        let listed = reader.manifest().map(|m| m.segments.len());
+        drop(reader);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before.len(), 80);
+        let x: Vec<i32> = after.iter().map(|s| s.data.accel_x).collect();
+        assert_eq!(x, (80..150).collect::<Vec<_>>());
+        assert_eq!(listed, Some(2));
+    }
 }
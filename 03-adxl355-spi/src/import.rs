@@ -0,0 +1,537 @@
+//! Import of data recorded with other instruments
+//!
+//! [`import_csv`] and [`import_wav`] turn a CSV table or a WAV file into a
+//! normal recording, so reference accelerometers and other DAQs can be
+//! compared with FT232H captures in the analyzer. Input values times the
+//! declared scale give g; they are stored as raw counts of the chosen
+//! range, as an ADXL355 would have reported them, and clipped at its
+//! 20-bit limit. A mapped temperature column is in degC; without one the
+//! temperature reads 25 degC.
+//!
+//! The file gets `acquisition_mode = "import"` and three more `metadata`
+//! attributes:
+//!
+//! ```text
+//!     source_sensor      str   instrument named on import
+//!     source_file        str   path of the CSV or WAV file
+//!     source_scale       f64   input units -> g
+//! ```
+//!
+//! CSV: lines starting with `#` are skipped (so [`crate::export`] output
+//! imports again), the delimiter is `,`, `;` or tab, and a first row that
+//! is not all numbers names the columns. Times from a time column are in
+//! seconds, must increase from row to row and are shifted to start at 0;
+//! steps of more than two nominal periods are stored as discontinuities.
+//! WAV: 8/16/24/32-bit PCM or 32/64-bit float, integer samples read as
+//! -1..1 of full scale.
+
+use crate::hdf5_format::Hdf5Writer;
+use crate::schema::{self, ChannelInfo};
+use crate::{Adxl355Error, Range, Result, SensorData, TemperatureCalibration, TimestampedSample};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Samples written per batch
+const BLOCK: usize = 65_536;
+
+/// Rows read ahead to find the rate of a CSV with a time column
+const RATE_ROWS: usize = 1024;
+
+/// Channels a source column can be mapped to, in default order
+pub const CHANNELS: [&str; 4] = ["accel_x", "accel_y", "accel_z", "temperature"];
+
+/// Acceleration channels filled by position when nothing is mapped
+const DEFAULT_CHANNELS: usize = 3;
+
+/// Largest raw count of the 20-bit output
+const RAW_LIMIT: f64 = 524_287.0;
+
+/// Where sample times come from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timing {
+    /// CSV column holding seconds (name or 1-based number)
+    Column(String),
+    /// Evenly spaced samples at this rate
+    Rate(f64),
+}
+
+/// How values are read and stored
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Instrument the data comes from, stored as `source_sensor`
+    pub sensor: String,
+    /// Input units -> g (1 for data in g, 1/9.80665 for m/s^2; for WAV, g at full scale)
+    pub scale: f64,
+    /// Range whose LSB/g the values are stored with
+    pub range: Range,
+    /// (channel, source) pairs: CSV columns by name or 1-based number, WAV
+    /// channels by number. Empty: columns named like [`CHANNELS`], else the
+    /// data columns in order as accel_x/y/z
+    pub mapping: Vec<(String, String)>,
+    /// RFC 3339 time of t = 0 (default: time of the import)
+    pub start_time: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportReport {
+    pub samples: usize,
+    /// Values beyond the range, stored at its limit
+    pub clipped: usize,
+    /// Gaps found in the time column
+    pub gaps: usize,
+    /// Rate given or estimated from the time column
+    pub sample_rate_hz: f64,
+}
+
+/// Build the recording `output` from the CSV file `input`
+pub fn import_csv<P: AsRef<Path>, Q: AsRef<Path>>(
+    input: P, output: Q, timing: &Timing, options: &ImportOptions,
+) -> Result<ImportReport> {
+    let input = input.as_ref();
+    let file = File::open(input).map_err(read_error(input))?;
+    let mut lines = BufReader::new(file).lines().enumerate()
+        .map(|(index, line)| (index + 1, line))
+        .filter(|(_, line)| !matches!(line, Ok(text) if text.trim().is_empty() || text.starts_with('#')));
+
+    let Some((first_number, first)) = lines.next() else {
+        return Err(Adxl355Error::InvalidParameter(format!("{} holds no data", input.display())));
+    };
+    let first = first.map_err(read_error(input))?;
+    let delimiter = detect_delimiter(&first);
+    let first_row = parse_row(&first, delimiter).ok();
+    let names: Vec<String> = match first_row {
+        Some(ref row) => (1..=row.len()).map(|i| i.to_string()).collect(),
+        None => split(&first, delimiter).map(str::to_string).collect(),
+    };
+
+    let time = match timing {
+        Timing::Column(spec) => Some(find_column(&names, spec).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("No time column '{}' in {}", spec, input.display()))
+        })?),
+        Timing::Rate(_) => None,
+    };
+    let converter = Converter::new(&names, time, options)?;
+
+    let width = names.len();
+    let mut rows = first_row.map(|row| Ok((first_number, row))).into_iter().chain(lines.map(move |(number, line)| {
+        let line = line.map_err(read_error(input))?;
+        let row = parse_row(&line, delimiter).map_err(|field| Adxl355Error::InvalidParameter(format!(
+            "{} line {}: '{}' is not a number", input.display(), number, field
+        )))?;
+        if row.len() != width {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "{} line {}: {} fields, expected {}", input.display(), number, row.len(), width
+            )));
+        }
+        Ok((number, row))
+    }));
+
+    match (timing, time) {
+        (Timing::Rate(rate), _) => {
+            let rate = *rate;
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err(Adxl355Error::InvalidParameter(format!("Sample rate {} is not usable", rate)));
+            }
+            let rows = rows.enumerate().map(move |(i, row)| row.map(|(number, row)| (number, i as f64 / rate, row)));
+            write_recording(output.as_ref(), input, rate, true, options, converter, rows)
+        }
+        (Timing::Column(_), Some(time)) => {
+            let ahead: Vec<(usize, Vec<f64>)> = rows.by_ref().take(RATE_ROWS).collect::<Result<_>>()?;
+            let rate = estimate_rate(ahead.iter().map(|(_, row)| row[time])).ok_or_else(|| {
+                Adxl355Error::InvalidParameter(format!("Cannot tell the sample rate from the time column of {}", input.display()))
+            })?;
+            let t0 = ahead[0].1[time];
+            let rows = ahead.into_iter().map(Ok).chain(rows)
+                .map(move |row| row.map(|(number, row)| (number, row[time] - t0, row)));
+            write_recording(output.as_ref(), input, rate, false, options, converter, rows)
+        }
+        (Timing::Column(_), None) => unreachable!("time column resolved above"),
+    }
+}
+
+/// Build the recording `output` from the WAV file `input`
+pub fn import_wav<P: AsRef<Path>, Q: AsRef<Path>>(input: P, output: Q, options: &ImportOptions) -> Result<ImportReport> {
+    let input = input.as_ref();
+    let file = File::open(input).map_err(read_error(input))?;
+    let mut reader = BufReader::new(file);
+    let wav = WavFormat::read(&mut reader).map_err(read_error(input))?;
+
+    let names: Vec<String> = (1..=wav.channels).map(|i| i.to_string()).collect();
+    let converter = Converter::new(&names, None, options)?;
+    let rate = wav.rate as f64;
+    let frame_len = wav.frame_len();
+    let frames = wav.data_len / frame_len as u64;
+    let rows = (0..frames).map(move |i| {
+        let mut frame = vec![0u8; frame_len];
+        reader.read_exact(&mut frame).map_err(read_error(input))?;
+        Ok((i as usize + 1, i as f64 / rate, wav.decode(&frame)))
+    });
+    write_recording(output.as_ref(), input, rate, true, options, converter, rows)
+}
+
+fn read_error(path: &Path) -> impl Fn(io::Error) -> Adxl355Error + '_ {
+    move |source| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source }
+}
+
+/// `rows` hold the line (CSV) or frame (WAV) number, the time and the values
+fn write_recording(
+    output: &Path,
+    input: &Path,
+    rate: f64,
+    evenly_spaced: bool,
+    options: &ImportOptions,
+    mut converter: Converter,
+    rows: impl Iterator<Item = Result<(usize, f64, Vec<f64>)>>,
+) -> Result<ImportReport> {
+    let mut writer = Hdf5Writer::create(output, "import", rate, options.range.label())?;
+    if let Some(start_time) = &options.start_time {
+        writer.set_start_time(start_time)?;
+    }
+    writer.write_metadata_str("source_sensor", &options.sensor)?;
+    writer.write_metadata_str("source_file", &input.display().to_string())?;
+    writer.write_metadata_f64("source_scale", options.scale)?;
+
+    let mut batch = Vec::with_capacity(BLOCK);
+    let mut samples = 0;
+    let mut previous: Option<f64> = None;
+    for row in rows {
+        let (number, timestamp, values) = row?;
+        if previous.is_some_and(|t| timestamp <= t) {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "{} line {}: time does not increase", input.display(), number
+            )));
+        }
+        if let Some(previous) = previous.filter(|&t| !evenly_spaced && timestamp - t > 2.0 / rate) {
+            writer.write_discontinuity(previous, timestamp, "gap in imported data")?;
+        }
+        previous = Some(timestamp);
+        batch.push(converter.sample(timestamp, &values));
+        if batch.len() == BLOCK {
+            writer.append_batch(&batch)?;
+            samples += batch.len();
+            batch.clear();
+        }
+    }
+    writer.append_batch(&batch)?;
+    samples += batch.len();
+    let gaps = writer.discontinuity_count();
+    writer.close()?;
+
+    Ok(ImportReport { samples, clipped: converter.clipped, gaps, sample_rate_hz: rate })
+}
+
+/// Source column of each channel and the scaling to raw counts
+struct Converter {
+    columns: [Option<usize>; CHANNELS.len()],
+    /// LSB per input unit
+    lsb: f64,
+    temperature: ChannelInfo,
+    clipped: usize,
+}
+
+impl Converter {
+    fn new(names: &[String], time: Option<usize>, options: &ImportOptions) -> Result<Self> {
+        if !(options.scale.is_finite() && options.scale != 0.0) {
+            return Err(Adxl355Error::InvalidParameter(format!("Scale factor {} is not usable", options.scale)));
+        }
+        Ok(Converter {
+            columns: map_columns(names, time, &options.mapping)?,
+            lsb: options.scale * options.range.scale_factor() as f64,
+            temperature: schema::temperature_channel(&TemperatureCalibration::NOMINAL),
+            clipped: 0,
+        })
+    }
+
+    fn sample(&mut self, timestamp: f64, row: &[f64]) -> TimestampedSample {
+        let value = |channel: usize| self.columns[channel].and_then(|c| row.get(c).copied());
+        let (x, y, z, celsius) = (value(0), value(1), value(2), value(3));
+        let data = SensorData {
+            accel_x: self.accel(x),
+            accel_y: self.accel(y),
+            accel_z: self.accel(z),
+            temperature: self.temperature(celsius),
+        };
+        TimestampedSample { timestamp, data }
+    }
+
+    fn accel(&mut self, value: Option<f64>) -> i32 {
+        let raw = (value.unwrap_or(0.0) * self.lsb).round();
+        if !(-RAW_LIMIT - 1.0..=RAW_LIMIT).contains(&raw) {
+            self.clipped += 1;
+        }
+        raw.clamp(-RAW_LIMIT - 1.0, RAW_LIMIT) as i32
+    }
+
+    fn temperature(&self, celsius: Option<f64>) -> u16 {
+        let raw = match celsius {
+            Some(celsius) => (celsius - self.temperature.offset) / self.temperature.scale_factor,
+            None => TemperatureCalibration::NOMINAL.intercept_lsb as f64,
+        };
+        raw.round().clamp(0.0, u16::MAX as f64) as u16
+    }
+}
+
+/// Source column index per entry of [`CHANNELS`]
+fn map_columns(names: &[String], time: Option<usize>, mapping: &[(String, String)]) -> Result<[Option<usize>; CHANNELS.len()]> {
+    let mut columns = [None; CHANNELS.len()];
+    if mapping.is_empty() {
+        for (column, channel) in columns.iter_mut().zip(CHANNELS) {
+            *column = names.iter().position(|name| column_name_matches(name, channel));
+        }
+        if columns.iter().all(Option::is_none) {
+            let data = (0..names.len()).filter(|&i| Some(i) != time);
+            for (column, index) in columns[..DEFAULT_CHANNELS].iter_mut().zip(data) {
+                *column = Some(index);
+            }
+        }
+        return Ok(columns);
+    }
+    for (channel, spec) in mapping {
+        let slot = CHANNELS.iter().position(|c| c == channel).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("Unknown channel '{}' (one of {})", channel, CHANNELS.join(", ")))
+        })?;
+        columns[slot] = Some(find_column(names, spec).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("No column '{}' to map to {}", spec, channel))
+        })?);
+    }
+    Ok(columns)
+}
+
+/// Column named `spec` (also as "spec (units)"), or numbered `spec` from 1
+fn find_column(names: &[String], spec: &str) -> Option<usize> {
+    names.iter().position(|name| column_name_matches(name, spec))
+        .or_else(|| spec.parse::<usize>().ok().filter(|&n| (1..=names.len()).contains(&n)).map(|n| n - 1))
+}
+
+fn column_name_matches(name: &str, spec: &str) -> bool {
+    name == spec || name.strip_prefix(spec).is_some_and(|rest| rest.starts_with(" ("))
+}
+
+/// The most frequent of `,`, `;` and tab (`,` on a tie)
+fn detect_delimiter(line: &str) -> char {
+    [',', ';', '\t'].into_iter().rev().max_by_key(|&d| line.matches(d).count()).unwrap()
+}
+
+fn split(line: &str, delimiter: char) -> impl Iterator<Item = &str> {
+    line.split(delimiter).map(|field| field.trim().trim_matches('"'))
+}
+
+/// Numbers of a data row; the first field that is not one on error
+fn parse_row(line: &str, delimiter: char) -> std::result::Result<Vec<f64>, String> {
+    split(line, delimiter)
+        .map(|field| field.parse::<f64>().map_err(|_| field.to_string()))
+        .collect()
+}
+
+/// Rate from the first and last of some consecutive timestamps
+fn estimate_rate(times: impl Iterator<Item = f64>) -> Option<f64> {
+    let (mut first, mut last, mut count) = (None, 0.0, 0usize);
+    for t in times {
+        first.get_or_insert(t);
+        last = t;
+        count += 1;
+    }
+    let span = last - first?;
+    (count >= 2 && span > 0.0).then(|| (count - 1) as f64 / span)
+}
+
+/// Sample layout from a WAV `fmt ` chunk
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WavFormat {
+    channels: u16,
+    rate: u32,
+    bits: u16,
+    float: bool,
+    /// Bytes in the `data` chunk
+    data_len: u64,
+}
+
+impl WavFormat {
+    /// Read the header up to the sample data
+    fn read<R: Read>(input: &mut R) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+        let mut riff = [0u8; 12];
+        input.read_exact(&mut riff)?;
+        if &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+            return Err(invalid("not a RIFF/WAVE file"));
+        }
+
+        let mut format = None;
+        loop {
+            let mut header = [0u8; 8];
+            input.read_exact(&mut header)?;
+            let len = u32::from_le_bytes(header[4..].try_into().unwrap()) as u64;
+            match &header[..4] {
+                b"fmt " => {
+                    let mut body = vec![0u8; len as usize];
+                    input.read_exact(&mut body)?;
+                    if len % 2 == 1 {
+                        input.read_exact(&mut [0u8; 1])?;
+                    }
+                    format = Some(Self::parse_fmt(&body).ok_or_else(|| invalid("unsupported WAV sample format"))?);
+                }
+                b"data" => {
+                    let mut format = format.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                    format.data_len = len;
+                    return Ok(format);
+                }
+                _ => {
+                    io::copy(&mut input.by_ref().take(len + len % 2), &mut io::sink())?;
+                }
+            }
+        }
+    }
+
+    fn parse_fmt(body: &[u8]) -> Option<Self> {
+        let u16_at = |at: usize| body.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+        let mut tag = u16_at(0)?;
+        if tag == 0xFFFE {
+            // WAVE_FORMAT_EXTENSIBLE: the sub-format GUID starts with the tag
+            tag = u16_at(24)?;
+        }
+        let format = WavFormat {
+            channels: u16_at(2)?,
+            rate: u32::from_le_bytes(body.get(4..8)?.try_into().ok()?),
+            bits: u16_at(14)?,
+            float: tag == 3,
+            data_len: 0,
+        };
+        let supported = match tag {
+            1 => matches!(format.bits, 8 | 16 | 24 | 32),
+            3 => matches!(format.bits, 32 | 64),
+            _ => false,
+        };
+        (supported && format.channels > 0 && format.rate > 0).then_some(format)
+    }
+
+    fn frame_len(&self) -> usize {
+        self.channels as usize * (self.bits / 8) as usize
+    }
+
+    /// Samples of one frame, integers scaled to -1..1
+    fn decode(&self, frame: &[u8]) -> Vec<f64> {
+        frame.chunks_exact((self.bits / 8) as usize)
+            .map(|b| match (self.float, self.bits) {
+                (true, 32) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+                (true, _) => f64::from_le_bytes(b.try_into().unwrap()),
+                (false, 8) => (b[0] as f64 - 128.0) / 128.0,
+                (false, 16) => i16::from_le_bytes([b[0], b[1]]) as f64 / 32_768.0,
+                (false, 24) => (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f64 / 8_388_608.0,
+                (false, _) => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / 2_147_483_648.0,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{pcm16, wav_header, WavEncoding};
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn options(mapping: &[(&str, &str)]) -> ImportOptions {
+        ImportOptions {
+            sensor: "reference".to_string(),
+            scale: 1.0,
+            range: Range::G2,
+            mapping: mapping.iter().map(|(c, s)| (c.to_string(), s.to_string())).collect(),
+            start_time: None,
+        }
+    }
+
+    #[test]
+    fn csv_rows_and_delimiters() {
+        assert_eq!(detect_delimiter("t;ax;ay"), ';');
+        assert_eq!(detect_delimiter("t\tax"), '\t');
+        assert_eq!(detect_delimiter("single"), ',');
+        assert_eq!(parse_row(" 1.5, \"-2\" ,3e-3", ','), Ok(vec![1.5, -2.0, 0.003]));
+        assert_eq!(parse_row("time (s),accel_x (g)", ','), Err("time (s)".to_string()));
+        assert!((estimate_rate([0.0, 0.001, 0.002, 0.003].into_iter()).unwrap() - 1000.0).abs() < 1e-9);
+        assert_eq!(estimate_rate([1.0].into_iter()), None);
+    }
+
+    #[test]
+    fn columns_by_name_number_and_position() {
+        let exported = names(&["time (s)", "accel_x (g)", "accel_y (g)", "accel_z (g)", "temperature (degC)"]);
+        assert_eq!(map_columns(&exported, Some(0), &[]).unwrap(), [Some(1), Some(2), Some(3), Some(4)]);
+
+        let other = names(&["t", "ch1", "ch2", "ch3", "ch4"]);
+        assert_eq!(map_columns(&other, Some(0), &[]).unwrap(), [Some(1), Some(2), Some(3), None]);
+        let mapping = options(&[("accel_z", "ch1"), ("accel_x", "5")]).mapping;
+        assert_eq!(map_columns(&other, Some(0), &mapping).unwrap(), [Some(4), None, Some(1), None]);
+
+        let unknown = options(&[("accel_w", "ch1")]).mapping;
+        assert!(map_columns(&other, None, &unknown).is_err());
+        let missing = options(&[("accel_x", "ch9")]).mapping;
+        assert!(map_columns(&other, None, &missing).is_err());
+    }
+
+    #[test]
+    fn values_scaled_to_raw_and_clipped() {
+        let mut converter = Converter::new(&names(&["x", "y", "z"]), None, &options(&[])).unwrap();
+        let sample = converter.sample(0.5, &[1.0, -0.5, 3.0]);
+        assert_eq!(sample.data.accel_x, 256_000);
+        assert_eq!(sample.data.accel_y, -128_000);
+        assert_eq!(sample.data.accel_z, 524_287);
+        assert_eq!(converter.clipped, 1);
+        // No temperature column: 25 degC
+        assert_eq!(sample.data.temperature, 1885);
+    }
+
+    #[test]
+    fn wav_written_by_export_reads_back() {
+        let mut bytes = wav_header(2, 500, WavEncoding::Pcm16, 2, "comment").unwrap();
+        for value in [1.0, -0.5, 0.25, 2.0] {
+            bytes.extend(pcm16(value, 2.0).to_le_bytes());
+        }
+        let mut input = &bytes[..];
+        let format = WavFormat::read(&mut input).unwrap();
+        assert_eq!((format.channels, format.rate, format.bits, format.float), (2, 500, 16, false));
+        assert_eq!(format.data_len, 8);
+        let frame = &input[..format.frame_len()];
+        let values = format.decode(frame);
+        assert!((values[0] * 2.0 - 1.0).abs() < 1e-4);
+        assert!((values[1] * 2.0 + 0.5).abs() < 1e-4);
+
+        let float = wav_header(1, 100, WavEncoding::Float32, 1, "").unwrap();
+        let format = WavFormat::read(&mut &float[..]).unwrap();
+        assert!(format.float);
+        assert_eq!(format.decode(&0.75f32.to_le_bytes()), vec![0.75]);
+    }
+
+    #[test]
+    fn wav_24_bit_sign_extends() {
+        let format = WavFormat { channels: 1, rate: 1, bits: 24, float: false, data_len: 3 };
+        assert_eq!(format.decode(&[0x00, 0x00, 0xC0]), vec![-0.5]);
+    }
+
+    #[test]
+    fn import_writes_a_single_sensor_file_and_rejects_repeated_times() {
+        use crate::hdf5_format::Hdf5Reader;
+
+        let dir = std::env::temp_dir().join(format!("adxl-spi-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (csv, output) = (dir.join("reference.tsv"), dir.join("reference.h5"));
+        std::fs::write(&csv, "0.5\t0\t1\n0.5\t0\t1\n0.75\t-1\t1\n").unwrap();
+        let options = ImportOptions { range: Range::G8, ..options(&[]) };
+        let report = import_csv(&csv, &output, &Timing::Rate(2000.0), &options).unwrap();
+        assert_eq!((report.samples, report.gaps), (3, 0));
+
+        let reader = Hdf5Reader::open_device(&output, None).unwrap();
+        assert_eq!(reader.device(), None);
+        let samples = reader.read_range(0, 3).unwrap();
+        assert_eq!(samples[2].timestamp, 0.001);
+        assert_eq!((samples[2].data.accel_x, samples[2].data.accel_y), (48_000, -64_000));
+        drop(reader);
+
+        // The same numbers read as a time column repeat 0.5 s on line 2
+        let error = import_csv(&csv, &output, &Timing::Column("1".to_string()), &options).unwrap_err();
+        assert!(error.to_string().contains("line 2: time does not increase"), "{}", error);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
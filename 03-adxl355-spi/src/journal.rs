@@ -0,0 +1,514 @@
+//! Append-only journal that makes recordings survive a crash
+//!
+//! [`Hdf5Writer::enable_journal`] makes the writer log every batch and
+//! discontinuity to `<file>.journal` before touching the HDF5 file, and
+//! empty the log whenever the file is synced to disk ([`Hdf5Writer::flush`],
+//! and every few seconds of SWMR writing). What is left after a crash
+//! or power cut is therefore exactly what the file may be missing.
+//! [`recover`] cuts each device group back to its last flushed length and
+//! replays the log; an HDF5 file that no longer opens is replaced by one
+//! built from the log alone (metadata added after creation, such as
+//! revisions and self-test results, cannot be restored that way).
+//!
+//! Format, little endian: magic `ADXSJRN1`; header with the flushed sample
+//! count of every device group, the flushed gap count, start time, mode,
+//! range, rate and device names; then records `tag u8, length u32,
+//! payload, FNV-1a u32`. Sample records start with the device index.
+//! Parsing ends at the first record that is cut off or fails its checksum.
+
+use crate::hdf5_format::Completion;
+use crate::{Adxl355Error, Discontinuity, Hdf5Reader, Hdf5Writer, Result, SensorData, TimestampedSample};
+use std::fs::{File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"ADXSJRN1";
+const TAG_SAMPLES: u8 = 1;
+const TAG_GAP: u8 = 2;
+/// f64 timestamp, three i32 axes, u16 temperature
+const SAMPLE_BYTES: usize = 8 + 3 * 4 + 2;
+
+/// Longest stretch of records that may sit in the page cache unsynced
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Flushed state of the HDF5 file the journal continues from
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalHeader {
+    /// Flushed samples per device group, in file order
+    pub sample_bases: Vec<u64>,
+    /// Flushed discontinuities
+    pub gap_base: u64,
+    pub start_time: String,
+    pub acquisition_mode: String,
+    pub range: String,
+    pub sample_rate_hz: f64,
+    /// Device group names, empty for a single-sensor file
+    pub devices: Vec<String>,
+}
+
+/// Path of the journal kept for `path`
+pub fn journal_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut name = path.as_ref().as_os_str().to_owned();
+    name.push(".journal");
+    PathBuf::from(name)
+}
+
+fn io_error(context: String) -> impl FnOnce(std::io::Error) -> Adxl355Error {
+    move |source| Adxl355Error::Io { context, source }
+}
+
+/// The journal an [`Hdf5Writer`] is appending to
+pub(crate) struct Journal {
+    out: File,
+    path: PathBuf,
+    header: JournalHeader,
+    last_sync: Instant,
+}
+
+impl Journal {
+    pub(crate) fn create(path: PathBuf, header: JournalHeader) -> Result<Self> {
+        let out = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(io_error(format!("Cannot create journal {}", path.display())))?;
+
+        let mut journal = Journal { out, path, header, last_sync: Instant::now() };
+        journal.write_header()?;
+        Ok(journal)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let header = &self.header;
+        let mut buf = MAGIC.to_vec();
+        buf.extend_from_slice(&(header.sample_bases.len() as u16).to_le_bytes());
+        for base in &header.sample_bases {
+            buf.extend_from_slice(&base.to_le_bytes());
+        }
+        buf.extend_from_slice(&header.gap_base.to_le_bytes());
+        put_str(&mut buf, &header.start_time);
+        put_str(&mut buf, &header.acquisition_mode);
+        put_str(&mut buf, &header.range);
+        buf.extend_from_slice(&header.sample_rate_hz.to_le_bytes());
+        put_str(&mut buf, &header.devices.join(","));
+
+        self.out.write_all(&buf)
+            .and_then(|_| self.out.sync_data())
+            .map_err(io_error(format!("Failed to write journal {}", self.path.display())))?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    fn append(&mut self, tag: u8, payload: &[u8]) -> Result<()> {
+        // Records go out in one write, so only the final one can be torn
+        let mut record = Vec::with_capacity(payload.len() + 9);
+        record.push(tag);
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        record.extend_from_slice(&fnv1a(payload).to_le_bytes());
+
+        self.out.write_all(&record)
+            .map_err(io_error(format!("Failed to append to journal {}", self.path.display())))?;
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.out.sync_data()
+                .map_err(io_error(format!("Failed to sync journal {}", self.path.display())))?;
+            self.last_sync = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn append_samples(&mut self, index: usize, samples: &[TimestampedSample]) -> Result<()> {
+        let mut payload = Vec::with_capacity(6 + samples.len() * SAMPLE_BYTES);
+        payload.extend_from_slice(&(index as u16).to_le_bytes());
+        payload.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+        for sample in samples {
+            let d = &sample.data;
+            payload.extend_from_slice(&sample.timestamp.to_le_bytes());
+            for axis in [d.accel_x, d.accel_y, d.accel_z] {
+                payload.extend_from_slice(&axis.to_le_bytes());
+            }
+            payload.extend_from_slice(&d.temperature.to_le_bytes());
+        }
+        self.append(TAG_SAMPLES, &payload)
+    }
+
+    pub(crate) fn append_gap(&mut self, start_time: f64, end_time: f64, cause: &str) -> Result<()> {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&start_time.to_le_bytes());
+        payload.extend_from_slice(&end_time.to_le_bytes());
+        put_str(&mut payload, cause);
+        self.append(TAG_GAP, &payload)
+    }
+
+    /// Truncate once the HDF5 file is synced and record the new flushed counts
+    pub(crate) fn restart(&mut self, sample_counts: &[usize], gap_base: usize) -> Result<()> {
+        self.header.sample_bases = sample_counts.iter().map(|&n| n as u64).collect();
+        self.header.gap_base = gap_base as u64;
+        self.out.set_len(0)
+            .and_then(|_| self.out.seek(SeekFrom::Start(0)))
+            .map_err(io_error(format!("Failed to reset journal {}", self.path.display())))?;
+        self.write_header()
+    }
+
+    pub(crate) fn remove(self) -> Result<()> {
+        let Journal { out, path, .. } = self;
+        drop(out);
+        std::fs::remove_file(&path).map_err(io_error(format!("Failed to delete journal {}", path.display())))
+    }
+}
+
+fn put_str(buf: &mut Vec<u8>, text: &str) {
+    let mut end = text.len().min(u16::MAX as usize);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let bytes = &text.as_bytes()[..end];
+    buf.extend_from_slice(&(bytes.len() as u16).to_le_bytes());
+    buf.extend_from_slice(bytes);
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811C_9DC5u32, |hash, &b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Sequential little-endian field reader
+struct Cursor<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.buf.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+}
+
+/// Contents of a journal found next to an HDF5 file
+#[derive(Debug, Clone)]
+pub struct JournalContents {
+    pub header: JournalHeader,
+    /// Unflushed samples, one list per device group
+    pub samples: Vec<Vec<TimestampedSample>>,
+    pub discontinuities: Vec<Discontinuity>,
+    /// Length of the broken record the journal ends with, 0 if none
+    pub torn_bytes: usize,
+}
+
+impl JournalContents {
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(io_error(format!("Cannot read journal {}", path.display())))?;
+        Self::parse(&bytes).ok_or_else(|| {
+            Adxl355Error::InvalidParameter(format!("{} has no valid journal header", path.display()))
+        })
+    }
+
+    /// Samples over all device groups
+    pub fn sample_count(&self) -> usize {
+        self.samples.iter().map(Vec::len).sum()
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let mut cursor = Cursor { buf: bytes, pos: 0 };
+        if cursor.take(MAGIC.len())? != MAGIC {
+            return None;
+        }
+        let groups = cursor.u16()? as usize;
+        let sample_bases = (0..groups).map(|_| cursor.u64()).collect::<Option<Vec<_>>>()?;
+        let gap_base = cursor.u64()?;
+        let start_time = cursor.string()?;
+        let acquisition_mode = cursor.string()?;
+        let range = cursor.string()?;
+        let sample_rate_hz = cursor.f64()?;
+        let devices: Vec<String> = match cursor.string()? {
+            list if list.is_empty() => Vec::new(),
+            list => list.split(',').map(str::to_string).collect(),
+        };
+        if groups != devices.len().max(1) {
+            return None;
+        }
+        let header = JournalHeader { sample_bases, gap_base, start_time, acquisition_mode, range, sample_rate_hz, devices };
+
+        let mut contents = JournalContents {
+            header,
+            samples: vec![Vec::new(); groups],
+            discontinuities: Vec::new(),
+            torn_bytes: 0,
+        };
+        while cursor.remaining() > 0 {
+            let start = cursor.pos;
+            if contents.parse_record(&mut cursor).is_none() {
+                contents.torn_bytes = bytes.len() - start;
+                break;
+            }
+        }
+        Some(contents)
+    }
+
+    fn parse_record(&mut self, cursor: &mut Cursor) -> Option<()> {
+        let tag = cursor.take(1)?[0];
+        let len = cursor.u32()? as usize;
+        let payload = cursor.take(len)?;
+        if cursor.u32()? != fnv1a(payload) {
+            return None;
+        }
+
+        let mut fields = Cursor { buf: payload, pos: 0 };
+        match tag {
+            TAG_SAMPLES => {
+                let index = fields.u16()? as usize;
+                let count = fields.u32()? as usize;
+                if index >= self.samples.len() || fields.remaining() != count * SAMPLE_BYTES {
+                    return None;
+                }
+                for _ in 0..count {
+                    let timestamp = fields.f64()?;
+                    let data = SensorData {
+                        accel_x: fields.i32()?,
+                        accel_y: fields.i32()?,
+                        accel_z: fields.i32()?,
+                        temperature: fields.u16()?,
+                    };
+                    self.samples[index].push(TimestampedSample { timestamp, data });
+                }
+            }
+            TAG_GAP => self.discontinuities.push(Discontinuity {
+                start_time: fields.f64()?,
+                end_time: fields.f64()?,
+                cause: fields.string()?,
+            }),
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+/// Summary of a [`recover`] run
+#[derive(Debug, Clone, Default)]
+pub struct RecoveryReport {
+    /// Closed cleanly and no journal present; the file was not modified
+    pub already_complete: bool,
+    /// A journal was present and replayed
+    pub journal_found: bool,
+    /// The HDF5 file did not open; it is kept as `<file>.damaged` and a
+    /// new file was written from the journal
+    pub rebuilt: bool,
+    /// Samples replayed, over all device groups
+    pub journal_samples: usize,
+    pub journal_discontinuities: usize,
+    /// Previously flushed samples that did not survive
+    pub lost_samples: usize,
+    /// Partial record discarded at the end of the journal
+    pub torn_bytes: usize,
+    /// Samples in the file after recovery, over all device groups
+    pub sample_count: usize,
+}
+
+/// Repair the file of a recording that did not end with [`Hdf5Writer::close`]
+///
+/// Leaves cleanly closed files without a journal alone. Otherwise every
+/// device group is trimmed to its flushed length (or to its shortest
+/// column when there is no journal), the journal is replayed, and the file
+/// is marked [`Completion::Recovered`].
+pub fn recover<P: AsRef<Path>>(path: P) -> Result<RecoveryReport> {
+    let path = path.as_ref();
+    let journal_file = journal_path(path);
+    let journal = if journal_file.exists() { Some(JournalContents::read(&journal_file)?) } else { None };
+
+    let mut report = RecoveryReport::default();
+    if journal.is_none() {
+        if let Ok(reader) = Hdf5Reader::open(path) {
+            if reader.completion() == Completion::Complete {
+                report.already_complete = true;
+                report.sample_count = reader.get_total_samples()?;
+                return Ok(report);
+            }
+        }
+    }
+
+    let mut writer = match (Hdf5Writer::reopen(path), &journal) {
+        (Ok(writer), _) => writer,
+        (Err(e), None) => return Err(e),
+        (Err(_), Some(contents)) => {
+            let mut damaged = path.as_os_str().to_owned();
+            damaged.push(".damaged");
+            std::fs::rename(path, &damaged)
+                .map_err(io_error(format!("Cannot move damaged file {}", path.display())))?;
+            report.rebuilt = true;
+            Hdf5Writer::rebuild(path, &contents.header)?
+        }
+    };
+
+    match &journal {
+        Some(contents) => {
+            let header = &contents.header;
+            if header.sample_bases.len() != writer.device_count() {
+                return Err(Adxl355Error::InvalidParameter(format!(
+                    "Journal has {} device groups, file has {}",
+                    header.sample_bases.len(),
+                    writer.device_count()
+                )));
+            }
+            let samples: Vec<usize> = header.sample_bases.iter().enumerate()
+                .map(|(index, &base)| writer.device_sample_count(index).min(base as usize))
+                .collect();
+            let gaps = writer.discontinuity_count().min(header.gap_base as usize);
+            report.lost_samples += header.sample_bases.iter().sum::<u64>() as usize - samples.iter().sum::<usize>();
+            writer.truncate(&samples, gaps)?;
+
+            for (index, batch) in contents.samples.iter().enumerate() {
+                writer.append_device_batch(index, batch)?;
+            }
+            for gap in &contents.discontinuities {
+                writer.write_discontinuity(gap.start_time, gap.end_time, &gap.cause)?;
+            }
+            report.journal_found = true;
+            report.journal_samples = contents.sample_count();
+            report.journal_discontinuities = contents.discontinuities.len();
+            report.torn_bytes = contents.torn_bytes;
+        }
+        None => {
+            let samples: Vec<usize> = (0..writer.device_count()).map(|i| writer.device_sample_count(i)).collect();
+            writer.truncate(&samples, writer.discontinuity_count())?;
+        }
+    }
+
+    report.sample_count = writer.sample_count();
+    writer.finish(Completion::Recovered)?;
+    if journal.is_some() {
+        std::fs::remove_file(&journal_file)
+            .map_err(io_error(format!("Failed to delete journal {}", journal_file.display())))?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> JournalHeader {
+        JournalHeader {
+            sample_bases: vec![4096, 4095],
+            gap_base: 0,
+            start_time: "2026-10-18T09:00:00+02:00".to_string(),
+            acquisition_mode: "fifo".to_string(),
+            range: "±2g".to_string(),
+            sample_rate_hz: 1000.0,
+            devices: vec!["dbus3".to_string(), "dbus4".to_string()],
+        }
+    }
+
+    fn sample(i: i32) -> TimestampedSample {
+        TimestampedSample {
+            timestamp: i as f64 * 0.001,
+            data: SensorData { accel_x: i, accel_y: -i, accel_z: 256_000, temperature: 1885 },
+        }
+    }
+
+    fn journal_bytes(name: &str) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("adxl355-{}-{}.journal", name, std::process::id()));
+        let mut journal = Journal::create(path.clone(), header()).unwrap();
+        journal.append_samples(0, &[sample(1), sample(2)]).unwrap();
+        journal.append_samples(1, &[sample(-524_288)]).unwrap();
+        journal.append_gap(3.0, 3.5, "SPI timeout").unwrap();
+        journal.append_samples(1, &[sample(4)]).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        journal.remove().unwrap();
+        assert!(!path.exists());
+        bytes
+    }
+
+    #[test]
+    fn journal_round_trip() {
+        let contents = JournalContents::parse(&journal_bytes("round-trip")).unwrap();
+
+        assert_eq!(contents.header, header());
+        assert_eq!(contents.samples[0].len(), 2);
+        assert_eq!(contents.samples[1].len(), 2);
+        assert_eq!(contents.samples[1][0].data.accel_x, -524_288);
+        assert_eq!(contents.samples[1][1].timestamp, 0.004);
+        assert_eq!(contents.sample_count(), 4);
+        assert_eq!(contents.discontinuities[0].cause, "SPI timeout");
+        assert_eq!(contents.torn_bytes, 0);
+    }
+
+    #[test]
+    fn torn_or_corrupt_records_end_the_journal() {
+        let bytes = journal_bytes("torn");
+
+        let cut = &bytes[..bytes.len() - 3];
+        let contents = JournalContents::parse(cut).unwrap();
+        assert_eq!(contents.sample_count(), 3);
+        assert_eq!(contents.discontinuities.len(), 1);
+        assert!(contents.torn_bytes > 0);
+
+        // A bad checksum drops that record and everything after it
+        let mut flipped = cut.to_vec();
+        let gap_checksum = flipped.len() - contents.torn_bytes - 1;
+        flipped[gap_checksum] ^= 0xFF;
+        let contents = JournalContents::parse(&flipped).unwrap();
+        assert_eq!(contents.sample_count(), 3);
+        assert!(contents.discontinuities.is_empty());
+
+        assert!(JournalContents::parse(&bytes[..12]).is_none());
+    }
+
+    #[test]
+    fn rebuild_counts_flushed_samples_of_every_device_once() {
+        let path = std::env::temp_dir().join(format!("adxl355-rebuild-{}.h5", std::process::id()));
+        std::fs::write(&path, b"truncated by a power cut").unwrap();
+        let mut journal = Journal::create(journal_path(&path), header()).unwrap();
+        journal.append_samples(0, &[sample(1), sample(2)]).unwrap();
+        journal.append_samples(1, &[sample(3)]).unwrap();
+        drop(journal);
+
+        let report = recover(&path).unwrap();
+        let mut damaged = path.as_os_str().to_owned();
+        damaged.push(".damaged");
+        std::fs::remove_file(&damaged).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(report.rebuilt);
+        assert_eq!(report.lost_samples, 4096 + 4095);
+        assert_eq!((report.journal_samples, report.sample_count), (3, 3));
+        assert!(!journal_path(&path).exists());
+    }
+}
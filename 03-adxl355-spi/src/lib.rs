@@ -5,15 +5,42 @@
 
 pub mod error;
 mod ffi;
+mod mpsse;
 pub mod adxl355;
+pub mod spi;
 pub mod hdf5_format;
+pub mod journal;
+pub mod events;
+pub mod export;
+pub mod import;
+pub mod overview;
+pub mod session;
+pub mod storage;
+pub mod capture;
+pub mod config;
+pub mod schema;
 pub mod common;
+pub mod recovery;
+pub mod gpio;
+pub mod trace;
 #[cfg(feature = "analysis")]
 pub mod analysis;
 
 pub use error::{Adxl355Error, Result};
-pub use adxl355::{Adxl355, SensorData, StreamControl, Range, OutputDataRate, FifoBatchResult};
-pub use hdf5_format::{Hdf5Reader, Hdf5Writer, Metadata, TimestampedSample};
-pub use common::{TimeKeeper, create_bar};
+pub use adxl355::{Adxl355, SensorData, StreamControl, Range, OutputDataRate, FifoBatchResult, SyncMode, SelfTestReport, TemperatureCalibration, DeviceVariant, DeviceInfo};
+pub use spi::{CsLine, SpiBus, SpiConfig, SpiMode};
+pub use hdf5_format::{Completion, Discontinuity, Hdf5Reader, Hdf5Writer, Metadata, SampleChunks, TimestampedSample};
+pub use overview::OverviewBin;
+pub use events::{Event, EventSpan};
+pub use journal::{recover, RecoveryReport};
+pub use session::{Manifest, Rotation, RotationInterval};
+pub use storage::{Compression, StorageOptions, TimestampFormat};
+pub use capture::{Band, Capture, CaptureEvent, TriggerConfig, TriggerSource};
+pub use config::{CollectionMode, CollectorConfig, ConfigFile, Profile, TriggerProfile};
+pub use schema::{migrate, ChannelInfo, SCHEMA_VERSION};
+pub use common::{PollScheduler, PollStats, TimeKeeper, create_bar};
+pub use recovery::{Outage, RetryPolicy, StreamEvent};
+pub use gpio::{Edge, EdgeDetector, Gpio};
+pub use trace::{Trace, TraceOp, TraceRecord, Tracer};
 #[cfg(feature = "analysis")]
 pub use analysis::{compute_rms, find_frequency_peaks, FrequencyPeak};
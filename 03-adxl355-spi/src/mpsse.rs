@@ -0,0 +1,181 @@
+//! Raw MPSSE command buffers for batched SPI transfers
+//!
+//! libMPSSE turns every `SPI_Write`/`SPI_Read` call into its own USB
+//! round-trip. For FIFO streaming that adds up to four round-trips per
+//! batch. This module builds the MPSSE opcodes directly so several SPI
+//! transactions (CS edges, command bytes, reads) go out in one `FT_Write`
+//! and come back in one `FT_Read`.
+//!
+//! Opcodes used (FTDI AN_108):
+//!   0x80  set ADBUS[7:0] value and direction
+//!   0x11  clock bytes out, MSB first, on the falling edge (SPI mode 0)
+//!   0x20  clock bytes in, MSB first, on the rising edge (SPI mode 0)
+//!   0x87  send immediate (flush the read buffer back to the host)
+
+use crate::error::{check_status, Adxl355Error, Result, TransportOp};
+use crate::ffi::*;
+use std::time::{Duration, Instant};
+
+const OP_SET_LOW_BYTE: u8 = 0x80;
+const OP_BYTES_OUT_NEG_EDGE: u8 = 0x11;
+const OP_BYTES_IN_POS_EDGE: u8 = 0x20;
+const OP_SEND_IMMEDIATE: u8 = 0x87;
+
+// ADBUS pin assignment in SPI mode
+const PIN_SCK: u8 = 0x01;
+const PIN_MOSI: u8 = 0x02;
+
+/// Largest length a single clock-in/clock-out opcode accepts
+const MAX_CLOCK_BYTES: usize = 65536;
+
+/// How long `transfer` waits for the read data before giving up
+const READ_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Chip-select pin on the low byte (ADBUS3..ADBUS7)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChipSelect {
+    mask: u8,
+    active_low: bool,
+    /// CS lines of other devices on a shared bus, held deasserted
+    others: u8,
+}
+
+impl ChipSelect {
+    /// `dbus` is the ADBUS pin number (3-7)
+    pub fn new(dbus: u8, active_low: bool) -> Self {
+        debug_assert!((3..=7).contains(&dbus));
+        ChipSelect { mask: 1 << dbus, active_low, others: 0 }
+    }
+
+    /// Also drive the given low-byte pins, at the same polarity, to their idle level
+    pub fn with_idle_lines(mut self, others: u8) -> Self {
+        self.others = others & !self.mask;
+        self
+    }
+
+    /// Low-byte value with CS in the given state and SCK idle low
+    fn low_byte(&self, asserted: bool) -> u8 {
+        let idle = if self.active_low { self.others } else { 0x00 };
+        if asserted == self.active_low {
+            idle
+        } else {
+            idle | self.mask
+        }
+    }
+
+    /// SCK, MOSI and CS are outputs, everything else (incl. MISO) input
+    fn direction(&self) -> u8 {
+        PIN_SCK | PIN_MOSI | self.mask | self.others
+    }
+}
+
+/// Builder for a batch of MPSSE commands
+#[derive(Debug, Clone)]
+pub struct CommandBuffer {
+    cs: ChipSelect,
+    bytes: Vec<u8>,
+    read_len: usize,
+}
+
+impl CommandBuffer {
+    pub fn new(cs: ChipSelect) -> Self {
+        CommandBuffer {
+            cs,
+            bytes: Vec::with_capacity(64),
+            read_len: 0,
+        }
+    }
+
+    pub fn cs_assert(&mut self) -> &mut Self {
+        self.bytes.extend_from_slice(&[OP_SET_LOW_BYTE, self.cs.low_byte(true), self.cs.direction()]);
+        self
+    }
+
+    pub fn cs_deassert(&mut self) -> &mut Self {
+        self.bytes.extend_from_slice(&[OP_SET_LOW_BYTE, self.cs.low_byte(false), self.cs.direction()]);
+        self
+    }
+
+    /// Clock `data` out on MOSI
+    pub fn write(&mut self, data: &[u8]) -> &mut Self {
+        for chunk in data.chunks(MAX_CLOCK_BYTES) {
+            let len = (chunk.len() - 1) as u16;
+            self.bytes.extend_from_slice(&[OP_BYTES_OUT_NEG_EDGE, len as u8, (len >> 8) as u8]);
+            self.bytes.extend_from_slice(chunk);
+        }
+        self
+    }
+
+    /// Clock `count` bytes in from MISO
+    pub fn read(&mut self, count: usize) -> &mut Self {
+        let mut remaining = count;
+        while remaining > 0 {
+            let n = remaining.min(MAX_CLOCK_BYTES);
+            let len = (n - 1) as u16;
+            self.bytes.extend_from_slice(&[OP_BYTES_IN_POS_EDGE, len as u8, (len >> 8) as u8]);
+            remaining -= n;
+        }
+        self.read_len += count;
+        self
+    }
+
+    pub fn send_immediate(&mut self) -> &mut Self {
+        self.bytes.push(OP_SEND_IMMEDIATE);
+        self
+    }
+
+    /// Bytes the device will return for this buffer
+    pub fn read_len(&self) -> usize {
+        self.read_len
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+/// Send a command buffer and collect its read data in one round-trip
+pub fn transfer(handle: FT_HANDLE, commands: &CommandBuffer) -> Result<Vec<u8>> {
+    let out = commands.as_bytes();
+    let mut written: DWORD = 0;
+    let status = unsafe {
+        FT_Write(handle, out.as_ptr() as *mut _, out.len() as DWORD, &mut written)
+    };
+    check_status(status, TransportOp::Transfer, None)?;
+    if written as usize != out.len() {
+        return Err(Adxl355Error::TransferError {
+            register: None,
+            expected: out.len() as u32,
+            actual: written,
+        });
+    }
+
+    let expected = commands.read_len();
+    let mut data = vec![0u8; expected];
+    let mut received = 0usize;
+    let deadline = Instant::now() + READ_TIMEOUT;
+
+    while received < expected {
+        let mut n: DWORD = 0;
+        let status = unsafe {
+            FT_Read(
+                handle,
+                data[received..].as_mut_ptr() as *mut _,
+                (expected - received) as DWORD,
+                &mut n,
+            )
+        };
+        check_status(status, TransportOp::Transfer, None)?;
+        received += n as usize;
+
+        if received < expected && Instant::now() >= deadline {
+            return Err(Adxl355Error::TransferError {
+                register: None,
+                expected: expected as u32,
+                actual: received as u32,
+            });
+        }
+    }
+
+    Ok(data)
+}
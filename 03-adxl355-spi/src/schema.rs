@@ -0,0 +1,268 @@
+//! Versioned layout of the HDF5 recordings
+//!
+//! Version 2 (`metadata/version = "2.2"`):
+//!
+//! ```text
+//! /metadata                    attributes
+//!     version            str   "2.2"
+//!     start_time         str   RFC 3339 wall-clock time of t = 0
+//!     sample_rate_hz     f64   output data rate
+//!     acquisition_mode   str   "polling", "fifo" or "import"
+//!     sensor_type        str   "adxl355"
+//!     range              str   "2g", "4g", "8g" (ADXL357: "10g", "20g", "40g")
+//!     devices            str   chip-select lines, comma-separated (--cs only)
+//!     host               str   recording machine, if known
+//!     crate_version      str   e.g. "ft232_adxl355_spi 0.1.0"
+//!     device_serial      str   FT232H serial number, if known
+//!     sample_count, completion, part, revision, temp_cal_*, ...
+//!     source_sensor, source_file, source_scale (imports, see crate::import)
+//! /sensor_data                 one row per sample; with `devices`, one
+//!                              subgroup per line holding these datasets
+//!     timestamps         f64   s since start_time (i64 ticks with
+//!                              StorageOptions::timestamps, see crate::storage)
+//!     accel_x/y/z        i32   20-bit raw, LSB/g depends on range
+//!     temperature        u16   raw TEMP2:TEMP1
+//! /discontinuities             start_time, end_time (f64 s), cause (str, 256 bytes)
+//! /overview                    binned min/max/mean, grouped like
+//!                              sensor_data (see crate::overview)
+//! /events                      time (f64 s), kind, label, payload (str),
+//!                              see crate::events
+//! ```
+//!
+//! Every `sensor_data` dataset is tagged with the [`ChannelInfo`]
+//! attributes `units`, `scale_factor`, `offset`, `full_scale` and `axis`;
+//! the physical value is `raw * scale_factor + offset`. Axis names follow
+//! the package marking of the ADXL355 (right-handed, +Z away from the
+//! board). The temperature attributes use the calibration the collector
+//! applied, the datasheet nominal if it had none.
+//!
+//! Version 2.1 introduced `overview`. It is optional: 2.0 files and
+//! migrated 1.0 files are binned from `sensor_data` when read, and
+//! [`migrate`] leaves every 2.x file as it is. Version 2.2 added
+//! `events`, equally optional: files without it have no events. Text
+//! columns that grow during a recording are fixed-length so they can be
+//! appended under SWMR; older files stored them variable-length and read
+//! the same.
+//!
+//! Version 1.0 files carry neither the dataset attributes nor `host`,
+//! `crate_version` or `device_serial`. For them
+//! [`Hdf5Reader::channel_info`](crate::Hdf5Reader::channel_info) computes
+//! the scaling from `range` and `temp_cal_*`, and [`migrate`] stores it.
+
+use crate::hdf5_format::set_attr;
+use crate::{Adxl355Error, Range, Result, TemperatureCalibration};
+use hdf5::{Dataset, File, Group};
+use std::path::Path;
+
+/// Schema version of newly written files
+pub const SCHEMA_VERSION: &str = "2.2";
+
+/// Value of the `crate_version` attribute
+pub(crate) const CRATE_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// Units and scaling of one `sensor_data` column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelInfo {
+    /// "s", "g" or "degC"
+    pub units: String,
+    /// `units` per LSB
+    pub scale_factor: f64,
+    /// Added after scaling (non-zero for temperature only)
+    pub offset: f64,
+    /// Measurement limit in `units`, 0 where there is none
+    pub full_scale: f64,
+    /// "+X", "+Y", "+Z", or empty
+    pub axis: String,
+}
+
+impl ChannelInfo {
+    /// Convert a raw column value to `units`
+    pub fn to_physical(&self, raw: f64) -> f64 {
+        raw * self.scale_factor + self.offset
+    }
+
+    pub(crate) fn write(&self, dataset: &Dataset) -> Result<()> {
+        let units: hdf5::types::VarLenUnicode = self.units.parse().unwrap();
+        let axis: hdf5::types::VarLenUnicode = self.axis.parse().unwrap();
+        set_attr(dataset, "units", &units)?;
+        set_attr(dataset, "scale_factor", &self.scale_factor)?;
+        set_attr(dataset, "offset", &self.offset)?;
+        set_attr(dataset, "full_scale", &self.full_scale)?;
+        set_attr(dataset, "axis", &axis)
+    }
+
+    /// Attributes of a version 2 dataset, `None` if they are missing
+    pub(crate) fn read(dataset: &Dataset) -> Option<Self> {
+        let text = |name: &str| -> Option<String> {
+            dataset.attr(name).ok()?
+                .read_scalar::<hdf5::types::VarLenUnicode>().ok()
+                .map(|s| s.to_string())
+        };
+        let number = |name: &str| -> Option<f64> {
+            dataset.attr(name).ok()?.read_scalar::<f64>().ok()
+        };
+        Some(ChannelInfo {
+            units: text("units")?,
+            scale_factor: number("scale_factor")?,
+            offset: number("offset").unwrap_or(0.0),
+            full_scale: number("full_scale").unwrap_or(0.0),
+            axis: text("axis").unwrap_or_default(),
+        })
+    }
+}
+
+/// Column descriptions for `range` and temperature calibration `cal`
+pub fn channels(range: Range, cal: &TemperatureCalibration) -> Vec<(&'static str, ChannelInfo)> {
+    let accel = |axis: &str| ChannelInfo {
+        units: "g".to_string(),
+        scale_factor: 1.0 / range.scale_factor() as f64,
+        offset: 0.0,
+        full_scale: range.full_scale_g() as f64,
+        axis: axis.to_string(),
+    };
+    vec![
+        ("timestamps", ChannelInfo {
+            units: "s".to_string(),
+            scale_factor: 1.0,
+            offset: 0.0,
+            full_scale: 0.0,
+            axis: String::new(),
+        }),
+        ("accel_x", accel("+X")),
+        ("accel_y", accel("+Y")),
+        ("accel_z", accel("+Z")),
+        ("temperature", temperature_channel(cal)),
+    ]
+}
+
+/// T = 25 C + (raw - intercept) / slope, as a scale and offset
+pub(crate) fn temperature_channel(cal: &TemperatureCalibration) -> ChannelInfo {
+    let slope = cal.slope_lsb_per_c as f64;
+    ChannelInfo {
+        units: "degC".to_string(),
+        scale_factor: 1.0 / slope,
+        offset: 25.0 - cal.intercept_lsb as f64 / slope,
+        full_scale: 0.0,
+        axis: String::new(),
+    }
+}
+
+/// Major number of a `version` attribute ("1.0" -> 1, unparsable -> 1)
+pub fn major_version(version: &str) -> u32 {
+    version.split('.').next().and_then(|v| v.trim().parse().ok()).unwrap_or(1)
+}
+
+/// Host name for the `host` attribute
+pub(crate) fn host_name() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Upgrade a version 1 file in place to [`SCHEMA_VERSION`]
+///
+/// Every device group gets the column attributes derived from the file's
+/// `range` and `temp_cal_*` metadata; the previous version is kept as
+/// `migrated_from`. Returns `false`, leaving the file alone, if it is
+/// already current.
+pub fn migrate<P: AsRef<Path>>(path: P) -> Result<bool> {
+    let file = File::open_rw(path.as_ref())
+        .map_err(|e| Adxl355Error::storage("Failed to open HDF5 file for migration", e))?;
+    let metadata = file.group("metadata")
+        .map_err(|e| Adxl355Error::storage("Failed to open metadata group", e))?;
+    let read_str = |name: &str| -> Result<String> {
+        metadata.attr(name)
+            .and_then(|attr| attr.read_scalar::<hdf5::types::VarLenUnicode>())
+            .map(|s| s.to_string())
+            .map_err(|e| Adxl355Error::storage(format!("Failed to read {}", name), e))
+    };
+    let version = read_str("version")?;
+    if major_version(&version) >= major_version(SCHEMA_VERSION) {
+        return Ok(false);
+    }
+    let range = Range::from_label(&read_str("range")?).unwrap_or(Range::G2);
+    let columns = channels(range, &stored_calibration(&metadata));
+
+    let data_group = file.group("sensor_data")
+        .map_err(|e| Adxl355Error::storage("Failed to open sensor_data group", e))?;
+    let groups = match read_str("devices") {
+        Ok(list) => list.split(',')
+            .map(|name| data_group.group(name)
+                .map_err(|e| Adxl355Error::storage(format!("Failed to open group {}", name), e)))
+            .collect::<Result<Vec<_>>>()?,
+        Err(_) => vec![data_group],
+    };
+    for group in &groups {
+        for (name, info) in &columns {
+            let dataset = group.dataset(name)
+                .map_err(|e| Adxl355Error::storage(format!("Failed to open {}", name), e))?;
+            info.write(&dataset)?;
+        }
+    }
+
+    let text = |value: &str| -> hdf5::types::VarLenUnicode { value.parse().unwrap() };
+    set_attr(&metadata, "migrated_from", &text(&version))?;
+    set_attr(&metadata, "migrated_by", &text(CRATE_VERSION))?;
+    set_attr(&metadata, "version", &text(SCHEMA_VERSION))?;
+    file.close()
+        .map_err(|e| Adxl355Error::storage("Failed to close HDF5 file", e))?;
+    Ok(true)
+}
+
+/// Calibration in the `temp_cal_*` attributes, or the datasheet nominal
+pub(crate) fn stored_calibration(metadata: &Group) -> TemperatureCalibration {
+    let read = |name: &str| -> Option<f32> {
+        metadata.attr(name).ok()?.read_scalar::<f64>().ok().map(|v| v as f32)
+    };
+    match (read("temp_cal_intercept_lsb"), read("temp_cal_slope_lsb_per_c")) {
+        (Some(intercept_lsb), Some(slope_lsb_per_c)) if slope_lsb_per_c != 0.0 => {
+            TemperatureCalibration { intercept_lsb, slope_lsb_per_c }
+        }
+        _ => TemperatureCalibration::NOMINAL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, range: Range) -> ChannelInfo {
+        channels(range, &TemperatureCalibration::NOMINAL)
+            .into_iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, info)| info)
+            .unwrap()
+    }
+
+    #[test]
+    fn acceleration_follows_range() {
+        let g2 = column("accel_x", Range::G2);
+        assert_eq!(g2.units, "g");
+        assert_eq!(g2.axis, "+X");
+        assert!((g2.to_physical(256_000.0) - 1.0).abs() < 1e-12);
+        assert_eq!(g2.full_scale, Range::G2.full_scale_g() as f64);
+
+        let g8 = column("accel_z", Range::G8);
+        assert!((g8.to_physical(64_000.0) - 1.0).abs() < 1e-12);
+        assert!(g8.full_scale > 8.0 && g8.full_scale < 8.2);
+    }
+
+    #[test]
+    fn temperature_matches_calibration() {
+        let cal = TemperatureCalibration { intercept_lsb: 1900.0, slope_lsb_per_c: -9.0 };
+        let info = temperature_channel(&cal);
+        assert_eq!(info.units, "degC");
+        assert!((info.to_physical(1900.0) - 25.0).abs() < 1e-9);
+        assert!((info.to_physical(1810.0) - 35.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn version_parsing() {
+        assert_eq!(major_version("1.0"), 1);
+        assert_eq!(major_version(SCHEMA_VERSION), 2);
+        assert_eq!(major_version(""), 1);
+    }
+}
@@ -0,0 +1,375 @@
+//! Rotating a recording over numbered HDF5 files
+//!
+//! [`Hdf5Writer::create_session`] starts with `data_0001.h5` and moves on to
+//! `data_0002.h5`, ... when a [`Rotation`] limit is reached. Each segment has
+//! the layout of a normal file, device groups included, carries the session
+//! `start_time` and continues its timestamps. `data.manifest` records every
+//! segment's time range and per-device sample counts; pass it to
+//! [`Hdf5Reader::open`](crate::Hdf5Reader::open) or
+//! [`Hdf5Reader::open_device`](crate::Hdf5Reader::open_device) to read the
+//! whole session.
+//!
+//! [`Hdf5Writer::create_session`]: crate::Hdf5Writer::create_session
+
+use crate::{Adxl355Error, Result, TimestampedSample};
+use chrono::Timelike;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the size of the current segment is checked
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// When to start the next segment by time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RotationInterval {
+    /// At the top of every hour (local time)
+    Hourly,
+    /// At local midnight
+    Daily,
+    /// A fixed length after the segment was started
+    Every(Duration),
+}
+
+impl RotationInterval {
+    /// "hourly", "daily", or a length such as "900", "900s", "15m", "6h"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        match text.as_str() {
+            "hourly" => return Some(RotationInterval::Hourly),
+            "daily" => return Some(RotationInterval::Daily),
+            _ => {}
+        }
+        let (number, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+            Some((i, _)) => text.split_at(i),
+            None => (text.as_str(), "s"),
+        };
+        let scale = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3600,
+            _ => return None,
+        };
+        match number.parse::<u64>() {
+            Ok(n) if n > 0 => Some(RotationInterval::Every(Duration::from_secs(n * scale))),
+            _ => None,
+        }
+    }
+
+    /// Time from now until a segment started now should end
+    fn remaining(&self) -> Duration {
+        let into_day = chrono::Local::now().num_seconds_from_midnight() as u64;
+        match self {
+            RotationInterval::Hourly => Duration::from_secs(3600 - into_day % 3600),
+            RotationInterval::Daily => Duration::from_secs(86_400u64.saturating_sub(into_day).max(1)),
+            RotationInterval::Every(length) => *length,
+        }
+    }
+}
+
+/// Same form as [`RotationInterval::parse`] takes, e.g. "daily" or "15m"
+impl fmt::Display for RotationInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotationInterval::Hourly => write!(f, "hourly"),
+            RotationInterval::Daily => write!(f, "daily"),
+            RotationInterval::Every(length) => match length.as_secs() {
+                secs if secs % 3600 == 0 => write!(f, "{}h", secs / 3600),
+                secs if secs % 60 == 0 => write!(f, "{}m", secs / 60),
+                secs => write!(f, "{}s", secs),
+            },
+        }
+    }
+}
+
+/// Limits that end a segment; whichever is reached first counts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rotation {
+    pub interval: Option<RotationInterval>,
+    /// Segment file size in bytes
+    pub max_bytes: Option<u64>,
+}
+
+impl Rotation {
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some() || self.max_bytes.is_some()
+    }
+}
+
+/// One file of a session, as listed in the manifest
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentInfo {
+    /// File name, relative to the manifest
+    pub file: String,
+    /// Wall-clock time the segment was started (RFC 3339)
+    pub start_time: String,
+    /// Earliest and latest sample timestamp over all devices (seconds
+    /// since session start), NaN while the segment has no samples
+    pub first_timestamp: f64,
+    pub last_timestamp: f64,
+    /// Samples per device group, in `devices` order
+    pub samples: Vec<u64>,
+}
+
+impl SegmentInfo {
+    /// Samples summed over all devices
+    pub fn total_samples(&self) -> u64 {
+        self.samples.iter().sum()
+    }
+}
+
+/// Segment list of a session, stored as `<name>.manifest`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    /// Start of the session, the `start_time` of every segment
+    pub start_time: String,
+    pub segments: Vec<SegmentInfo>,
+}
+
+impl Manifest {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to read {}", path.display()), source: e })?;
+        let malformed = |line: &str| Adxl355Error::InvalidParameter(format!("Malformed line in {}: {}", path.display(), line));
+
+        let mut manifest = Manifest::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| malformed(line))?;
+            match key.trim() {
+                "start_time" => manifest.start_time = value.trim().to_string(),
+                "segment" => {
+                    let fields: Vec<&str> = value.split('|').map(str::trim).collect();
+                    let [file, start_time, first, last, samples] = fields[..] else {
+                        return Err(malformed(line));
+                    };
+                    manifest.segments.push(SegmentInfo {
+                        file: file.to_string(),
+                        start_time: start_time.to_string(),
+                        first_timestamp: first.parse().map_err(|_| malformed(line))?,
+                        last_timestamp: last.parse().map_err(|_| malformed(line))?,
+                        samples: samples.split(',')
+                            .map(|n| n.trim().parse().map_err(|_| malformed(line)))
+                            .collect::<Result<_>>()?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        if manifest.segments.is_empty() {
+            return Err(Adxl355Error::InvalidParameter(format!("{} lists no segments", path.display())));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut text = format!("# ADXL355 recording session\nstart_time = {}\n", self.start_time);
+        text.push_str("# segment = file | started | first timestamp | last timestamp | samples per device\n");
+        for s in &self.segments {
+            let samples: Vec<String> = s.samples.iter().map(u64::to_string).collect();
+            text.push_str(&format!(
+                "segment = {} | {} | {:.6} | {:.6} | {}\n",
+                s.file, s.start_time, s.first_timestamp, s.last_timestamp, samples.join(",")
+            ));
+        }
+        // Replace atomically: a reader may load the manifest mid-recording
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| Adxl355Error::Io { context: format!("Failed to write {}", path.display()), source: e })
+    }
+
+    /// Segment files, resolved against the directory of the manifest
+    pub fn segment_paths<P: AsRef<Path>>(&self, manifest_path: P) -> Vec<PathBuf> {
+        let dir = manifest_path.as_ref().parent().unwrap_or(Path::new(""));
+        self.segments.iter().map(|s| dir.join(&s.file)).collect()
+    }
+}
+
+/// `data.h5` -> `data.manifest`
+pub fn manifest_path<P: AsRef<Path>>(output: P) -> PathBuf {
+    output.as_ref().with_extension("manifest")
+}
+
+/// `data.h5`, 3 -> `data_0003.h5`
+pub fn segment_path<P: AsRef<Path>>(output: P, index: usize) -> PathBuf {
+    let output = output.as_ref();
+    let stem = output.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let ext = output.extension().map(|e| e.to_string_lossy()).unwrap_or("h5".into());
+    output.with_file_name(format!("{}_{:04}.{}", stem, index, ext))
+}
+
+/// Whether `path` names a session manifest rather than an HDF5 file
+pub fn is_manifest<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().extension().is_some_and(|ext| ext == "manifest")
+}
+
+/// Rotation state of a writer created with `Hdf5Writer::create_session`
+pub(crate) struct Session {
+    output: PathBuf,
+    rotation: Rotation,
+    manifest: Manifest,
+    deadline: Option<Instant>,
+    next_size_check: Instant,
+    devices: usize,
+    /// Samples per device, discontinuities and events in the finished segments
+    pub(crate) samples_before: Vec<usize>,
+    pub(crate) gaps_before: usize,
+    pub(crate) events_before: usize,
+}
+
+impl Session {
+    pub(crate) fn new(output: &Path, rotation: Rotation, devices: usize) -> Self {
+        Session {
+            output: output.to_path_buf(),
+            rotation,
+            manifest: Manifest::default(),
+            deadline: None,
+            next_size_check: Instant::now(),
+            devices,
+            samples_before: vec![0; devices],
+            gaps_before: 0,
+            events_before: 0,
+        }
+    }
+
+    pub(crate) fn set_start_time(&mut self, start_time: &str) {
+        self.manifest.start_time = start_time.to_string();
+    }
+
+    /// Add the next segment to the manifest and return its path
+    pub(crate) fn begin_segment(&mut self) -> PathBuf {
+        let path = segment_path(&self.output, self.manifest.segments.len() + 1);
+        self.manifest.segments.push(SegmentInfo {
+            file: path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+            start_time: chrono::Local::now().to_rfc3339(),
+            first_timestamp: f64::NAN,
+            last_timestamp: f64::NAN,
+            samples: vec![0; self.devices],
+        });
+        self.deadline = self.rotation.interval.map(|interval| Instant::now() + interval.remaining());
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        path
+    }
+
+    /// Account for samples of device `index` appended to the current segment
+    pub(crate) fn observe(&mut self, index: usize, samples: &[TimestampedSample]) {
+        let (Some(segment), Some(first), Some(last)) = (self.manifest.segments.last_mut(), samples.first(), samples.last()) else {
+            return;
+        };
+        // f64::min/max skip the NaN of an empty segment
+        segment.first_timestamp = segment.first_timestamp.min(first.timestamp);
+        segment.last_timestamp = segment.last_timestamp.max(last.timestamp);
+        if let Some(count) = segment.samples.get_mut(index) {
+            *count += samples.len() as u64;
+        }
+    }
+
+    /// Whether the segment at `path` has reached a rotation limit
+    pub(crate) fn due(&mut self, path: &Path) -> bool {
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+        let Some(max_bytes) = self.rotation.max_bytes else {
+            return false;
+        };
+        if Instant::now() < self.next_size_check {
+            return false;
+        }
+        self.next_size_check = Instant::now() + SIZE_CHECK_INTERVAL;
+        std::fs::metadata(path).is_ok_and(|m| m.len() >= max_bytes)
+    }
+
+    pub(crate) fn save_manifest(&self) -> Result<()> {
+        self.manifest.save(manifest_path(&self.output))
+    }
+
+    pub(crate) fn manifest_path(&self) -> PathBuf {
+        manifest_path(&self.output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interval_parsing() {
+        assert_eq!(RotationInterval::parse("hourly"), Some(RotationInterval::Hourly));
+        assert_eq!(RotationInterval::parse("Daily"), Some(RotationInterval::Daily));
+        assert_eq!(RotationInterval::parse("900"), Some(RotationInterval::Every(Duration::from_secs(900))));
+        assert_eq!(RotationInterval::parse("15m"), Some(RotationInterval::Every(Duration::from_secs(900))));
+        assert_eq!(RotationInterval::parse("6h"), Some(RotationInterval::Every(Duration::from_secs(21_600))));
+        assert_eq!(RotationInterval::parse("0"), None);
+        assert_eq!(RotationInterval::parse("10d"), None);
+        assert_eq!(RotationInterval::parse("weekly"), None);
+        for text in ["hourly", "daily", "45s", "15m", "6h"] {
+            assert_eq!(RotationInterval::parse(text).unwrap().to_string(), text);
+        }
+
+        assert!(RotationInterval::Hourly.remaining() <= Duration::from_secs(3600));
+        assert!(RotationInterval::Daily.remaining() <= Duration::from_secs(86_400));
+    }
+
+    #[test]
+    fn segment_names() {
+        assert_eq!(segment_path("site/bus.h5", 7), PathBuf::from("site/bus_0007.h5"));
+        assert_eq!(manifest_path("site/bus.h5"), PathBuf::from("site/bus.manifest"));
+        assert!(is_manifest("bus.manifest"));
+        assert!(!is_manifest("bus.h5"));
+    }
+
+    #[test]
+    fn manifest_round_trip() {
+        let manifest = Manifest {
+            start_time: "2026-10-18T00:00:00+00:00".to_string(),
+            segments: vec![
+                SegmentInfo {
+                    file: "bus_0001.h5".to_string(),
+                    start_time: "2026-10-18T00:00:00+00:00".to_string(),
+                    first_timestamp: 0.001,
+                    last_timestamp: 86_399.5,
+                    samples: vec![345_600, 345_598],
+                },
+                SegmentInfo {
+                    file: "bus_0002.h5".to_string(),
+                    start_time: "2026-10-19T00:00:00+00:00".to_string(),
+                    first_timestamp: f64::NAN,
+                    last_timestamp: f64::NAN,
+                    samples: vec![0, 0],
+                },
+            ],
+        };
+        let path = std::env::temp_dir().join(format!("adxl-session-{}.manifest", std::process::id()));
+        manifest.save(&path).unwrap();
+        let loaded = Manifest::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.segments[0], manifest.segments[0]);
+        assert_eq!(loaded.segments[0].total_samples(), 691_198);
+        assert_eq!(loaded.segments[1].samples, vec![0, 0]);
+        assert!(loaded.segments[1].last_timestamp.is_nan());
+    }
+
+    #[test]
+    fn observe_tracks_each_device() {
+        let sample = |timestamp| TimestampedSample {
+            timestamp,
+            data: crate::SensorData { accel_x: 0, accel_y: 0, accel_z: 0, temperature: 0 },
+        };
+        let mut session = Session::new(Path::new("bus.h5"), Rotation::default(), 2);
+        session.begin_segment();
+        session.observe(1, &[sample(0.5), sample(0.6)]);
+        session.observe(0, &[sample(0.4)]);
+
+        let segment = &session.manifest.segments[0];
+        assert_eq!(segment.samples, vec![1, 2]);
+        assert_eq!((segment.first_timestamp, segment.last_timestamp), (0.4, 0.6));
+    }
+}
@@ -0,0 +1,460 @@
+//! SPI channel configuration and shared-bus support for the FT232H MPSSE engine
+
+use crate::adxl355::Adxl355;
+use crate::error::{check_status, Adxl355Error, Result, TransportOp};
+use crate::ffi::*;
+use crate::gpio::{Gpio, GpioState};
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+/// Fastest SCLK the ADXL355 accepts
+pub const MAX_CLOCK_HZ: u32 = 10_000_000;
+
+/// SPI clock polarity/phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpiMode {
+    /// CPOL=0, CPHA=0 (ADXL355 default)
+    Mode0,
+    /// CPOL=0, CPHA=1
+    Mode1,
+    /// CPOL=1, CPHA=0
+    Mode2,
+    /// CPOL=1, CPHA=1
+    Mode3,
+}
+
+impl SpiMode {
+    fn config_bits(&self) -> DWORD {
+        match self {
+            SpiMode::Mode0 => SPI_CONFIG_OPTION_MODE0,
+            SpiMode::Mode1 => SPI_CONFIG_OPTION_MODE1,
+            SpiMode::Mode2 => SPI_CONFIG_OPTION_MODE2,
+            SpiMode::Mode3 => SPI_CONFIG_OPTION_MODE3,
+        }
+    }
+}
+
+/// SPI channel settings
+///
+/// ```no_run
+/// use ft232_adxl355_spi::{Adxl355, SpiConfig};
+///
+/// let config = SpiConfig::new()
+///     .clock_hz(5_000_000)
+///     .cs_pin(4);
+/// let sensor = Adxl355::with_config(0, config)?;
+/// # Ok::<(), ft232_adxl355_spi::Adxl355Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiConfig {
+    clock_hz: u32,
+    latency_ms: u8,
+    mode: SpiMode,
+    cs_pin: u8,
+    cs_active_low: bool,
+}
+
+impl SpiConfig {
+    /// 1 MHz, 1 ms latency, mode 0, CS on DBUS3 active low
+    pub fn new() -> Self {
+        SpiConfig {
+            clock_hz: 1_000_000,
+            latency_ms: 1,
+            mode: SpiMode::Mode0,
+            cs_pin: 3,
+            cs_active_low: true,
+        }
+    }
+
+    /// SCLK frequency in Hz (max 10 MHz)
+    pub fn clock_hz(mut self, hz: u32) -> Self {
+        self.clock_hz = hz;
+        self
+    }
+
+    /// USB latency timer in ms (1-255)
+    pub fn latency_timer(mut self, ms: u8) -> Self {
+        self.latency_ms = ms;
+        self
+    }
+
+    pub fn mode(mut self, mode: SpiMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Chip-select line, DBUS3-DBUS7
+    pub fn cs_pin(mut self, dbus: u8) -> Self {
+        self.cs_pin = dbus;
+        self
+    }
+
+    pub fn cs_active_low(mut self, active_low: bool) -> Self {
+        self.cs_active_low = active_low;
+        self
+    }
+
+    pub fn get_clock_hz(&self) -> u32 {
+        self.clock_hz
+    }
+
+    pub fn get_latency_timer(&self) -> u8 {
+        self.latency_ms
+    }
+
+    pub fn get_mode(&self) -> SpiMode {
+        self.mode
+    }
+
+    pub fn get_cs_pin(&self) -> u8 {
+        self.cs_pin
+    }
+
+    pub fn is_cs_active_low(&self) -> bool {
+        self.cs_active_low
+    }
+
+    /// Check the settings against FT232H and ADXL355 limits
+    pub fn validate(&self) -> Result<()> {
+        if self.clock_hz == 0 || self.clock_hz > MAX_CLOCK_HZ {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "SPI clock must be 1-{} Hz, got {}", MAX_CLOCK_HZ, self.clock_hz
+            )));
+        }
+        if self.latency_ms == 0 {
+            return Err(Adxl355Error::InvalidParameter(
+                "Latency timer must be 1-255 ms".to_string()
+            ));
+        }
+        if !(3..=7).contains(&self.cs_pin) {
+            return Err(Adxl355Error::InvalidParameter(format!(
+                "CS pin must be DBUS3-DBUS7, got DBUS{}", self.cs_pin
+            )));
+        }
+        Ok(())
+    }
+
+    /// libMPSSE `configOptions` word
+    pub(crate) fn config_options(&self) -> DWORD {
+        // CS_DBUSx selection lives in bits [4:2], DBUS3 = 0
+        let cs_bits = ((self.cs_pin - 3) as DWORD) << 2;
+        let polarity = if self.cs_active_low { SPI_CONFIG_OPTION_CS_ACTIVELOW } else { 0 };
+        self.mode.config_bits() | cs_bits | polarity
+    }
+
+    /// libMPSSE channel configuration
+    pub(crate) fn channel_config(&self, pin: DWORD) -> ChannelConfig {
+        ChannelConfig {
+            ClockRate: self.clock_hz,
+            LatencyTimer: self.latency_ms,
+            configOptions: self.config_options(),
+            Pin: pin,
+            currentPinState: 0,
+        }
+    }
+}
+
+impl Default for SpiConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============================================================================
+// Shared bus
+// ============================================================================
+
+/// Chip-select line of one device on a shared bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsLine {
+    /// ADBUS3-7, switched by the MPSSE engine with each transfer
+    Dbus(u8),
+    /// ACBUS0-7, driven as GPIO around each transfer (always active low)
+    Acbus(u8),
+}
+
+impl CsLine {
+    /// Name used on the command line and for HDF5 groups ("dbus3", "acbus0")
+    pub fn label(&self) -> String {
+        match self {
+            CsLine::Dbus(pin) => format!("dbus{}", pin),
+            CsLine::Acbus(pin) => format!("acbus{}", pin),
+        }
+    }
+
+    /// Parse a label produced by [`CsLine::label`]; a bare number means DBUS
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        let (pin, line): (&str, fn(u8) -> CsLine) = if let Some(pin) = text.strip_prefix("acbus") {
+            (pin, CsLine::Acbus)
+        } else if let Some(pin) = text.strip_prefix("dbus") {
+            (pin, CsLine::Dbus)
+        } else {
+            (text.as_str(), CsLine::Dbus)
+        };
+        let line = line(pin.parse().ok()?);
+        line.is_valid().then_some(line)
+    }
+
+    fn is_valid(&self) -> bool {
+        match self {
+            CsLine::Dbus(pin) => (3..=7).contains(pin),
+            CsLine::Acbus(pin) => *pin <= 7,
+        }
+    }
+
+    /// Bit of this line within its byte
+    fn mask(&self) -> u8 {
+        match self {
+            CsLine::Dbus(pin) | CsLine::Acbus(pin) => 1 << pin,
+        }
+    }
+}
+
+/// Bus state shared by every device on one FT232H channel
+pub(crate) struct BusState {
+    handle: FT_HANDLE,
+    channel_index: u32,
+    config: SpiConfig,
+    lines: Vec<CsLine>,
+    claimed: Vec<CsLine>,
+    /// DBUS pin libMPSSE currently toggles as CS
+    mpsse_cs: u8,
+    /// Unused DBUS pin libMPSSE toggles while an ACBUS device is addressed
+    park_pin: Option<u8>,
+    /// ACBUS levels, shared with the bus's `Gpio` handles
+    gpio: Rc<RefCell<GpioState>>,
+}
+
+impl BusState {
+    /// Mask of every DBUS chip-select line on the bus
+    fn dbus_mask(&self) -> u8 {
+        self.lines.iter()
+            .filter(|l| matches!(l, CsLine::Dbus(_)))
+            .fold(0, |m, l| m | l.mask())
+    }
+
+    /// Point libMPSSE's CS at `pin` unless it already is
+    fn route_mpsse_cs(&mut self, pin: u8) -> Result<()> {
+        if self.mpsse_cs == pin {
+            return Ok(());
+        }
+        let options = self.config.cs_pin(pin).config_options();
+        let status = unsafe { SPI_ChangeCS(self.handle, options) };
+        check_status(status, TransportOp::Configure, None)?;
+        self.mpsse_cs = pin;
+        Ok(())
+    }
+
+    /// Drive the ACBUS chip selects in `mask` low (asserted) or high
+    fn set_acbus_cs(&self, mask: u8, asserted: bool) -> Result<()> {
+        let mut gpio = self.gpio.borrow_mut();
+        let (dir, value) = gpio.levels();
+        let value = if asserted { value & !mask } else { value | mask };
+        gpio.write(dir, value)
+    }
+}
+
+impl Drop for BusState {
+    fn drop(&mut self) {
+        self.gpio.borrow_mut().detach();
+        unsafe {
+            SPI_CloseChannel(self.handle);
+        }
+    }
+}
+
+/// A device's link to its bus
+pub(crate) struct BusLink {
+    state: Rc<RefCell<BusState>>,
+    line: CsLine,
+}
+
+/// Bus access held for the duration of one transaction
+///
+/// Routes CS to the device on creation; for ACBUS lines the GPIO is asserted
+/// here and released on drop.
+pub(crate) struct BusSelection<'a> {
+    state: RefMut<'a, BusState>,
+    gpio_line: Option<u8>,
+}
+
+impl BusLink {
+    pub(crate) fn select(&self) -> Result<BusSelection<'_>> {
+        let mut state = self.state.try_borrow_mut().map_err(|_| Adxl355Error::BusBusy)?;
+
+        let gpio_line = match self.line {
+            CsLine::Dbus(pin) => {
+                state.route_mpsse_cs(pin)?;
+                None
+            }
+            CsLine::Acbus(_) => {
+                let park = state.park_pin.ok_or_else(|| Adxl355Error::InvalidParameter(
+                    "No free DBUS pin to park the MPSSE chip select".to_string()
+                ))?;
+                state.route_mpsse_cs(park)?;
+                state.set_acbus_cs(self.line.mask(), true)?;
+                Some(self.line.mask())
+            }
+        };
+
+        Ok(BusSelection { state, gpio_line })
+    }
+
+    pub(crate) fn line(&self) -> CsLine {
+        self.line
+    }
+
+    pub(crate) fn handle(&self) -> FT_HANDLE {
+        self.state.borrow().handle
+    }
+
+    pub(crate) fn gpio_state(&self) -> Rc<RefCell<GpioState>> {
+        self.state.borrow().gpio.clone()
+    }
+
+    pub(crate) fn channel_index(&self) -> u32 {
+        self.state.borrow().channel_index
+    }
+
+    /// DBUS chip-select lines of the other devices (held idle by raw transfers)
+    pub(crate) fn other_dbus_lines(&self) -> u8 {
+        let state = self.state.borrow();
+        state.dbus_mask() & !match self.line {
+            CsLine::Dbus(_) => self.line.mask(),
+            CsLine::Acbus(_) => 0,
+        }
+    }
+
+    pub(crate) fn release(&self) {
+        self.state.borrow_mut().claimed.retain(|l| *l != self.line);
+    }
+}
+
+impl Drop for BusSelection<'_> {
+    fn drop(&mut self) {
+        if let Some(mask) = self.gpio_line {
+            let _ = self.state.set_acbus_cs(mask, false);
+        }
+    }
+}
+
+/// One FT232H SPI channel shared by several sensors, each on its own CS line
+///
+/// ```no_run
+/// use ft232_adxl355_spi::{CsLine, SpiBus, SpiConfig};
+///
+/// let lines = [CsLine::Dbus(3), CsLine::Dbus(4), CsLine::Dbus(5)];
+/// let bus = SpiBus::open(0, SpiConfig::new().clock_hz(5_000_000), &lines)?;
+/// let mut sensors = lines.iter()
+///     .map(|&line| bus.device(line))
+///     .collect::<Result<Vec<_>, _>>()?;
+/// # Ok::<(), ft232_adxl355_spi::Adxl355Error>(())
+/// ```
+pub struct SpiBus {
+    state: Rc<RefCell<BusState>>,
+}
+
+impl SpiBus {
+    /// Open a channel and drive every listed CS line to its idle level
+    ///
+    /// The `cs_pin` of `config` is ignored; DBUS polarity follows
+    /// `cs_active_low`. ACBUS lines are always active low.
+    pub fn open(channel_index: u32, config: SpiConfig, lines: &[CsLine]) -> Result<Self> {
+        config.validate()?;
+
+        if lines.is_empty() {
+            return Err(Adxl355Error::InvalidParameter("No chip-select lines given".to_string()));
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if !line.is_valid() {
+                return Err(Adxl355Error::InvalidParameter(format!("Invalid CS line {:?}", line)));
+            }
+            if lines[..i].contains(line) {
+                return Err(Adxl355Error::InvalidParameter(format!("CS line {} listed twice", line.label())));
+            }
+        }
+
+        let dbus_mask = lines.iter()
+            .filter(|l| matches!(l, CsLine::Dbus(_)))
+            .fold(0u8, |m, l| m | l.mask());
+        let acbus_mask = lines.iter()
+            .filter(|l| matches!(l, CsLine::Acbus(_)))
+            .fold(0u8, |m, l| m | l.mask());
+
+        // ACBUS devices need libMPSSE's own CS pointed at an unconnected DBUS pin
+        let park_pin = if acbus_mask != 0 {
+            Some((3..=7).find(|p| dbus_mask & (1 << p) == 0).ok_or_else(|| {
+                Adxl355Error::InvalidParameter(
+                    "ACBUS chip selects need one DBUS3-7 pin left unused".to_string()
+                )
+            })?)
+        } else {
+            None
+        };
+
+        let first_cs = lines.iter()
+            .find_map(|l| match l { CsLine::Dbus(p) => Some(*p), _ => None })
+            .or(park_pin)
+            .unwrap_or(3);
+        let config = config.cs_pin(first_cs);
+
+        // Pin word: [7:0] initial direction, [15:8] initial value, then the same for close
+        let idle = if config.is_cs_active_low() { dbus_mask } else { 0 };
+        let low_byte = (dbus_mask as DWORD) | ((idle as DWORD) << 8);
+        let pin = low_byte | (low_byte << 16);
+
+        let handle = Adxl355::open_channel(channel_index, &config, pin)?;
+
+        let state = BusState {
+            handle,
+            channel_index,
+            config,
+            lines: lines.to_vec(),
+            claimed: Vec::new(),
+            mpsse_cs: first_cs,
+            park_pin,
+            gpio: GpioState::new(handle),
+        };
+        if acbus_mask != 0 {
+            state.set_acbus_cs(acbus_mask, false)?;
+            state.gpio.borrow_mut().reserve(acbus_mask);
+        }
+
+        Ok(SpiBus { state: Rc::new(RefCell::new(state)) })
+    }
+
+    /// Initialize the sensor on `line` and return it
+    ///
+    /// Each line can be claimed once; dropping the sensor frees it again.
+    pub fn device(&self, line: CsLine) -> Result<Adxl355> {
+        {
+            let mut state = self.state.borrow_mut();
+            if !state.lines.contains(&line) {
+                return Err(Adxl355Error::InvalidParameter(format!(
+                    "CS line {} was not declared when the bus was opened", line.label()
+                )));
+            }
+            if state.claimed.contains(&line) {
+                return Err(Adxl355Error::InvalidParameter(format!(
+                    "CS line {} is already in use", line.label()
+                )));
+            }
+            state.claimed.push(line);
+        }
+
+        let link = BusLink { state: self.state.clone(), line };
+        let config = self.state.borrow().config;
+        Adxl355::on_bus(link, config)
+    }
+
+    /// Chip-select lines declared for this bus
+    pub fn lines(&self) -> Vec<CsLine> {
+        self.state.borrow().lines.clone()
+    }
+
+    /// ACBUS pins not used as chip selects, as digital I/O
+    pub fn gpio(&self) -> Gpio {
+        Gpio::new(self.state.borrow().gpio.clone())
+    }
+}
@@ -0,0 +1,413 @@
+//! Capture and replay of SPI transport calls
+//!
+//! A [`Tracer`] attached to an [`crate::Adxl355`] records every libMPSSE
+//! transfer (`SPI_Write`, `SPI_Read`, `SPI_ReadWrite`) and every raw MPSSE
+//! round-trip the driver makes: bytes sent, bytes received, transfer
+//! options, FT_STATUS and timing. Records go to a bounded in-memory ring or
+//! straight to a text file.
+//!
+//! The file format is one record per line, `#` starts a comment:
+//!
+//! ```text
+//! # adxl355-spi trace v1
+//! # seq time_us dur_us op options status transferred out in
+//! 0 12 310 SPI_ReadWrite 0x06 0 3 010000 FFFFFF
+//! 1 335 180 SPI_Write 0x02 0 1 01 -
+//! ```
+//!
+//! Byte fields are hex, `-` when empty. A saved [`Trace`] can be fed back
+//! through [`crate::Adxl355::replay`], which answers each driver call from
+//! the capture instead of the hardware and fails on the first call that
+//! differs from what was recorded.
+//!
+//! Chip-select switching of a shared [`crate::SpiBus`] is not captured.
+
+use crate::error::{Adxl355Error, Result};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const TRACE_HEADER: &str = "# adxl355-spi trace v1";
+const TRACE_COLUMNS: &str = "# seq time_us dur_us op options status transferred out in";
+
+/// Transport call recorded in a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceOp {
+    /// libMPSSE `SPI_Write` (MOSI only)
+    SpiWrite,
+    /// libMPSSE `SPI_Read` (MISO only)
+    SpiRead,
+    /// libMPSSE `SPI_ReadWrite` (full duplex)
+    SpiReadWrite,
+    /// Raw MPSSE command buffer: one `FT_Write` plus the `FT_Read`s for its data
+    MpsseTransfer,
+    /// `FT_Purge` of both D2XX buffers
+    Purge,
+}
+
+impl TraceOp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceOp::SpiWrite => "SPI_Write",
+            TraceOp::SpiRead => "SPI_Read",
+            TraceOp::SpiReadWrite => "SPI_ReadWrite",
+            TraceOp::MpsseTransfer => "MPSSE",
+            TraceOp::Purge => "FT_Purge",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SPI_Write" => Some(TraceOp::SpiWrite),
+            "SPI_Read" => Some(TraceOp::SpiRead),
+            "SPI_ReadWrite" => Some(TraceOp::SpiReadWrite),
+            "MPSSE" => Some(TraceOp::MpsseTransfer),
+            "FT_Purge" => Some(TraceOp::Purge),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for TraceOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One transport call
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceRecord {
+    pub seq: u64,
+    /// Start of the call, microseconds since the tracer was created
+    pub time_us: u64,
+    /// How long the call took
+    pub duration_us: u64,
+    pub op: TraceOp,
+    /// libMPSSE transfer options (0 for raw MPSSE and purge)
+    pub options: u32,
+    /// FT_STATUS returned
+    pub status: u32,
+    /// Bytes the driver reported as transferred
+    pub transferred: u32,
+    /// Bytes sent to the FT232H (MPSSE opcodes for raw transfers)
+    pub out: Vec<u8>,
+    /// Bytes received; sized to the request even when fewer arrived
+    pub input: Vec<u8>,
+}
+
+impl TraceRecord {
+    /// Format as one line of the trace file
+    pub fn to_line(&self) -> String {
+        format!("{} {} {} {} 0x{:02X} {} {} {} {}",
+            self.seq, self.time_us, self.duration_us, self.op, self.options,
+            self.status, self.transferred, hex(&self.out), hex(&self.input))
+    }
+
+    /// Parse one line of a trace file
+    pub fn parse(line: &str) -> Result<Self> {
+        let invalid = |what: &str| Adxl355Error::InvalidParameter(format!("Trace line '{}': bad {}", line, what));
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() != 9 {
+            return Err(invalid("field count"));
+        }
+
+        let number = |index: usize, what: &str| fields[index].parse::<u64>().map_err(|_| invalid(what));
+        let options = fields[4].strip_prefix("0x")
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| invalid("options"))?;
+
+        Ok(TraceRecord {
+            seq: number(0, "sequence number")?,
+            time_us: number(1, "timestamp")?,
+            duration_us: number(2, "duration")?,
+            op: TraceOp::from_name(fields[3]).ok_or_else(|| invalid("operation"))?,
+            options,
+            status: number(5, "status")? as u32,
+            transferred: number(6, "transfer count")? as u32,
+            out: unhex(fields[7]).ok_or_else(|| invalid("out bytes"))?,
+            input: unhex(fields[8]).ok_or_else(|| invalid("in bytes"))?,
+        })
+    }
+
+    /// Same call with the same result, ignoring sequence number and timing
+    pub fn same_call(&self, other: &TraceRecord) -> bool {
+        self.op == other.op
+            && self.options == other.options
+            && self.status == other.status
+            && self.transferred == other.transferred
+            && self.out == other.out
+            && self.input == other.input
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "-".to_string();
+    }
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if text == "-" {
+        return Some(Vec::new());
+    }
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [_, _] => u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A sequence of recorded transport calls
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub records: Vec<TraceRecord>,
+}
+
+impl Trace {
+    /// Load a trace file written by [`Tracer::file`] or [`Trace::save`]
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path.as_ref()).map_err(|e| Adxl355Error::Io {
+            context: format!("Cannot open trace {}", path.as_ref().display()),
+            source: e,
+        })?;
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| Adxl355Error::Io { context: "Failed to read trace".to_string(), source: e })?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            records.push(TraceRecord::parse(line)?);
+        }
+
+        Ok(Trace { records })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let write = || -> std::io::Result<()> {
+            let mut out = BufWriter::new(File::create(path.as_ref())?);
+            writeln!(out, "{}", TRACE_HEADER)?;
+            writeln!(out, "{}", TRACE_COLUMNS)?;
+            for record in &self.records {
+                writeln!(out, "{}", record.to_line())?;
+            }
+            out.flush()
+        };
+        write().map_err(|e| Adxl355Error::Io {
+            context: format!("Failed to write trace {}", path.as_ref().display()),
+            source: e,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+enum Sink {
+    Ring { records: VecDeque<TraceRecord>, capacity: usize },
+    File(BufWriter<File>),
+}
+
+/// Recorder for transport calls, attached with [`crate::Adxl355::start_trace`]
+pub struct Tracer {
+    sink: Sink,
+    start: Instant,
+    next_seq: u64,
+}
+
+impl Tracer {
+    /// Keep the last `capacity` calls in memory
+    pub fn ring(capacity: usize) -> Self {
+        Tracer {
+            sink: Sink::Ring { records: VecDeque::with_capacity(capacity.min(4096)), capacity: capacity.max(1) },
+            start: Instant::now(),
+            next_seq: 0,
+        }
+    }
+
+    /// Append every call to a trace file as it happens
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let create = || -> std::io::Result<BufWriter<File>> {
+            let mut out = BufWriter::new(File::create(path.as_ref())?);
+            writeln!(out, "{}", TRACE_HEADER)?;
+            writeln!(out, "{}", TRACE_COLUMNS)?;
+            Ok(out)
+        };
+        let out = create().map_err(|e| Adxl355Error::Io {
+            context: format!("Cannot create trace {}", path.as_ref().display()),
+            source: e,
+        })?;
+
+        Ok(Tracer { sink: Sink::File(out), start: Instant::now(), next_seq: 0 })
+    }
+
+    /// Calls recorded so far (all of them, including ones dropped from the ring)
+    pub fn count(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Contents of the ring; empty for a file tracer
+    pub fn snapshot(&self) -> Trace {
+        match &self.sink {
+            Sink::Ring { records, .. } => Trace { records: records.iter().cloned().collect() },
+            Sink::File(_) => Trace::default(),
+        }
+    }
+
+    /// Write buffered records of a file tracer to disk
+    pub fn flush(&mut self) -> Result<()> {
+        if let Sink::File(out) = &mut self.sink {
+            out.flush().map_err(|e| Adxl355Error::Io { context: "Failed to flush trace".to_string(), source: e })?;
+        }
+        Ok(())
+    }
+
+    /// Store one call; `result` is (FT_STATUS, bytes transferred)
+    pub(crate) fn record(&mut self, started: Instant, op: TraceOp, options: u32, result: (u32, u32),
+                         out: &[u8], input: &[u8]) {
+        let (status, transferred) = result;
+        let record = TraceRecord {
+            seq: self.next_seq,
+            time_us: started.saturating_duration_since(self.start).as_micros() as u64,
+            duration_us: started.elapsed().as_micros() as u64,
+            op,
+            options,
+            status,
+            transferred,
+            out: out.to_vec(),
+            input: input.to_vec(),
+        };
+        self.next_seq += 1;
+
+        match &mut self.sink {
+            Sink::Ring { records, capacity } => {
+                if records.len() == *capacity {
+                    records.pop_front();
+                }
+                records.push_back(record);
+            }
+            // A failing trace file must not take the acquisition down with it
+            Sink::File(out) => {
+                let _ = writeln!(out, "{}", record.to_line());
+            }
+        }
+    }
+}
+
+/// Answers driver calls from a recorded [`Trace`]
+pub(crate) struct Replayer {
+    records: VecDeque<TraceRecord>,
+}
+
+impl Replayer {
+    pub(crate) fn new(trace: Trace) -> Self {
+        Replayer { records: trace.records.into() }
+    }
+
+    /// Calls in the trace not yet consumed
+    pub(crate) fn remaining(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Match the next recorded call and fill `input` from it
+    ///
+    /// Returns the recorded status and transfer count. Read data is not
+    /// compared, only its length: the driver cannot know it in advance.
+    pub(crate) fn next(&mut self, op: TraceOp, options: u32, out: &[u8], input: &mut [u8]) -> Result<(u32, u32)> {
+        let record = self.records.pop_front().ok_or_else(|| Adxl355Error::Replay(format!(
+            "trace exhausted at {} {}", op, hex(out)
+        )))?;
+
+        if record.op != op || record.options != options || record.out != out || record.input.len() != input.len() {
+            return Err(Adxl355Error::Replay(format!(
+                "diverged at #{}: recorded {} 0x{:02X} out={} ({} in), driver sent {} 0x{:02X} out={} ({} in)",
+                record.seq, record.op, record.options, hex(&record.out), record.input.len(),
+                op, options, hex(out), input.len()
+            )));
+        }
+
+        input.copy_from_slice(&record.input);
+        Ok((record.status, record.transferred))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(seq: u64, op: TraceOp, out: &[u8], input: &[u8]) -> TraceRecord {
+        TraceRecord {
+            seq,
+            time_us: seq * 100,
+            duration_us: 42,
+            op,
+            options: 0x06,
+            status: 0,
+            transferred: out.len().max(input.len()) as u32,
+            out: out.to_vec(),
+            input: input.to_vec(),
+        }
+    }
+
+    #[test]
+    fn line_round_trip() {
+        let original = record(7, TraceOp::SpiReadWrite, &[0x01, 0x00, 0x00], &[0xFF, 0xAD, 0x1D]);
+        assert_eq!(TraceRecord::parse(&original.to_line()).unwrap(), original);
+
+        let empty = record(8, TraceOp::Purge, &[], &[]);
+        assert!(empty.to_line().ends_with(" - -"));
+        assert_eq!(TraceRecord::parse(&empty.to_line()).unwrap(), empty);
+
+        assert!(TraceRecord::parse("1 2 3 SPI_Nope 0x00 0 0 - -").is_err());
+        assert!(TraceRecord::parse("1 2 3 SPI_Read 0x00 0 0 ABC -").is_err());
+    }
+
+    #[test]
+    fn replay_feeds_recorded_input() {
+        let trace = Trace {
+            records: vec![
+                record(0, TraceOp::SpiWrite, &[0x01], &[]),
+                record(1, TraceOp::SpiRead, &[], &[0x00, 0x00, 0xAD]),
+            ],
+        };
+        let mut replay = Replayer::new(trace);
+
+        assert_eq!(replay.next(TraceOp::SpiWrite, 0x06, &[0x01], &mut []).unwrap(), (0, 1));
+        let mut input = [0u8; 3];
+        replay.next(TraceOp::SpiRead, 0x06, &[], &mut input).unwrap();
+        assert_eq!(input, [0x00, 0x00, 0xAD]);
+        assert_eq!(replay.remaining(), 0);
+        assert!(replay.next(TraceOp::SpiRead, 0x06, &[], &mut input).is_err());
+    }
+
+    #[test]
+    fn replay_rejects_a_different_call() {
+        let trace = Trace { records: vec![record(0, TraceOp::SpiWrite, &[0x01], &[])] };
+        let mut replay = Replayer::new(trace);
+        assert!(replay.next(TraceOp::SpiWrite, 0x06, &[0x03], &mut []).is_err());
+    }
+
+    #[test]
+    fn ring_keeps_the_latest_calls() {
+        let mut tracer = Tracer::ring(2);
+        for byte in 0..5u8 {
+            tracer.record(Instant::now(), TraceOp::SpiWrite, 0, (0, 1), &[byte], &[]);
+        }
+        let trace = tracer.snapshot();
+        assert_eq!(tracer.count(), 5);
+        assert_eq!(trace.records.iter().map(|r| r.out[0]).collect::<Vec<_>>(), vec![3, 4]);
+        assert_eq!(trace.records[0].seq, 3);
+    }
+}